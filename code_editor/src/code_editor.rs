@@ -1,6 +1,8 @@
 use {
     crate::{
+        completion::{CompletionItem, Completions},
         decoration::{Decoration, DecorationType},
+        find_bar::{FindBar, FindBarAction},
         layout::{BlockElement, WrappedElement},
         selection::Affinity,
         session::{SelectionMode, Session},
@@ -18,6 +20,7 @@ use {
 live_design! {
     import makepad_draw::shader::std::*;
     import makepad_widgets::theme_desktop_dark::*;
+    import crate::find_bar::*;
 
     TokenColors = {{TokenColors}} {
         unknown: #C0C0C0,
@@ -116,6 +119,7 @@ live_design! {
         height: Fill,
         margin: 0,
         scroll_bars: <ScrollBars> {}
+        find_bar: <FindBar> {}
         draw_bg: {
            // draw_depth: 0.0,
             color: #2a
@@ -148,6 +152,27 @@ live_design! {
         draw_selection: {
            // draw_depth: 3.0,
         }
+        draw_search_match: {
+            color: #fc04
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(0., 0., self.rect_size.x, self.rect_size.y, 2.0);
+                return sdf.fill(self.color);
+            }
+        }
+        draw_completion_bg: {
+            draw_depth: 2.0,
+            color: #3a
+        }
+        draw_completion_selection: {
+            draw_depth: 2.5,
+            color: #08f8
+        }
+        draw_completion_text: {
+            draw_depth: 3.0,
+            text_style: <THEME_FONT_CODE> {}
+            color: #D4D4D4
+        }
 
         draw_cursor: {
           //  draw_depth: 4.0,
@@ -228,9 +253,16 @@ pub struct CodeEditor {
     #[live] draw_indent_guide: DrawIndentGuide,
     #[live] draw_decoration: DrawDecoration,
     #[live] draw_selection: DrawSelection,
+    #[live] draw_search_match: DrawColor,
+    #[live] draw_completion_bg: DrawColor,
+    #[live] draw_completion_selection: DrawColor,
+    #[live] draw_completion_text: DrawText,
     #[live] draw_cursor: DrawColor,
     #[live] draw_cursor_bg: DrawColor,
     #[live] draw_bg: DrawColor,
+    #[live] find_bar: FindBar,
+    #[rust] find_open: bool,
+    #[rust] completions: Option<Completions>,
     #[rust(KeepCursorInView::Off)] keep_cursor_in_view: KeepCursorInView,
     #[rust] last_cursor_screen_pos: Option<DVec2>,
 
@@ -327,6 +359,13 @@ impl CodeEditor {
     
     pub fn find_widgets(&mut self, _path: &[LiveId], _cached: WidgetCache, _results: &mut WidgetSet){
     }
+    
+    pub fn area(&self)->Area{
+        self.scroll_bars.area()
+    }
+    
+    pub fn widget_tree_children(&self, _cx: &Cx, _children: &mut Vec<WidgetTreeNode>){
+    }
         
     pub fn draw_walk_editor(&mut self, cx: &mut Cx2d, session: &mut Session, walk:Walk) {
         // This needs to be called first to ensure the session is up to date.
@@ -472,22 +511,33 @@ impl CodeEditor {
         self.draw_bg.draw_abs(cx, cx.turtle().unscrolled_rect());
 
         self.draw_gutter(cx, session);
+        self.draw_decoration_layer(cx, session, &session.search_matches());
         self.draw_selection_layer(cx, session);
         self.draw_text_layer(cx, session);
         self.draw_indent_guide_layer(cx, session);
-        self.draw_decoration_layer(cx, session);
+        self.draw_decoration_layer(cx, session, &session.document().decorations());
         self.draw_selection_layer(cx, session);
 
         // Get the last added selection.
         // Get the normalized cursor position. To go from normalized to screen position, multiply by
         // the cell size, then shift by the viewport origin.
 
+        if self.completions.is_some() {
+            self.draw_completions(cx, session);
+        }
+
         cx.turtle_mut().set_used(
             session.layout().width() * self.cell_size.x,
             session.layout().height() * self.cell_size.y + (self.viewport_rect.size.y),
         );
 
         self.scroll_bars.end(cx);
+        if self.find_open {
+            let rect = self.scroll_bars.area().rect(cx);
+            let mut walk = self.find_bar.walk(cx);
+            walk.abs_pos = Some(rect.pos + dvec2(rect.size.x - walk.width.fixed_or_zero() - 16.0, 0.0));
+            self.find_bar.draw_walk_all(cx, &mut Scope::empty(), walk);
+        }
         if session.update_folds() {
             self.scroll_bars.area().redraw(cx);
         } else if self.keep_cursor_in_view.is_locked() {
@@ -510,6 +560,235 @@ impl CodeEditor {
         self.redraw(cx);
     }
 
+    /// Selects the text between `start` and `end` and scrolls it into view.
+    pub fn set_selection_and_scroll(
+        &mut self,
+        cx: &mut Cx,
+        start: Position,
+        end: Position,
+        session: &mut Session,
+    ) {
+        session.set_selection(start, Affinity::Before, SelectionMode::Simple);
+        session.move_to(end, Affinity::Before);
+        self.keep_cursor_in_view = KeepCursorInView::JumpToPosition;
+        self.redraw(cx);
+    }
+
+    /// Opens the find bar, prefilled with the selected text if it fits on a single line.
+    pub fn open_find_bar(&mut self, cx: &mut Cx, session: &Session) {
+        let selection = session.selections()[session.last_added_selection_index().unwrap()];
+        let text = if !selection.is_empty() && selection.start().line_index == selection.end().line_index {
+            let text = session.document().as_text();
+            let line = &text.as_lines()[selection.start().line_index];
+            Some(line[selection.start().byte_index..selection.end().byte_index].to_string())
+        } else {
+            None
+        };
+        self.find_open = true;
+        self.find_bar.open(cx, text.as_deref());
+        self.search(cx, session);
+    }
+
+    pub fn close_find_bar(&mut self, cx: &mut Cx, session: &Session) {
+        self.find_open = false;
+        session.clear_search();
+        self.set_key_focus(cx);
+        self.redraw(cx);
+    }
+
+    fn search(&mut self, cx: &mut Cx, session: &Session) {
+        match session.set_search(self.find_bar.query()) {
+            Ok(_) => {
+                if session.select_nearest_match() {
+                    self.keep_cursor_in_view = KeepCursorInView::JumpToPosition;
+                }
+                self.update_find_status(cx, session);
+            }
+            Err(error) => {
+                session.clear_search();
+                self.find_bar.set_status(cx, &error.message);
+            }
+        }
+        self.redraw(cx);
+    }
+
+    fn update_find_status(&mut self, cx: &mut Cx, session: &Session) {
+        let count = session.search_matches().len();
+        let mut status = match session.selected_match_index() {
+            _ if count == 0 && self.find_bar.query().text.is_empty() => String::new(),
+            Some(index) => format!("{} of {}", index + 1, count),
+            None => format!("{} found", count),
+        };
+        if session.search_is_incomplete() {
+            status.push_str(", some lines too complex to search");
+        }
+        self.find_bar.set_status(cx, &status);
+    }
+
+    /// Shows a popup below the cursor with the items that match the word in front of it.
+    pub fn show_completions(&mut self, cx: &mut Cx, session: &Session, items: Vec<CompletionItem>) {
+        let mut completions = Completions::new(items);
+        completions.filter(&session.word_before_cursor());
+        self.completions = if completions.is_empty() {
+            None
+        } else {
+            Some(completions)
+        };
+        self.redraw(cx);
+    }
+
+    pub fn hide_completions(&mut self, cx: &mut Cx) {
+        if self.completions.take().is_some() {
+            self.redraw(cx);
+        }
+    }
+
+    fn draw_completions(&mut self, cx: &mut Cx2d, session: &Session) {
+        const MAX_VISIBLE_ITEM_COUNT: usize = 10;
+
+        // start new draw calls, so the popup isn't batched in below the text drawn before it
+        self.draw_completion_bg.new_draw_call(cx);
+        self.draw_completion_selection.new_draw_call(cx);
+        self.draw_completion_text.new_draw_call(cx);
+
+        let completions = self.completions.as_ref().unwrap();
+        let selection = session.selections()[session.last_added_selection_index().unwrap()];
+        let (x, y) = session
+            .layout()
+            .logical_to_normalized_position(selection.cursor.position, selection.cursor.affinity);
+        let origin = DVec2 { x, y: y + 1.0 } * self.cell_size + self.viewport_rect.pos;
+        // scroll the list so the selected item stays visible
+        let first = completions
+            .selected_index()
+            .saturating_sub(MAX_VISIBLE_ITEM_COUNT - 1);
+        let items: Vec<_> = completions
+            .visible_items()
+            .skip(first)
+            .take(MAX_VISIBLE_ITEM_COUNT)
+            .collect();
+        let column_count = items
+            .iter()
+            .map(|item| item.label.column_count() + 2 + item.detail.column_count())
+            .max()
+            .unwrap_or(0)
+            .min(80);
+        self.draw_completion_bg.draw_abs(
+            cx,
+            Rect {
+                pos: origin,
+                size: DVec2 {
+                    x: column_count as f64 + 2.0,
+                    y: items.len() as f64,
+                } * self.cell_size,
+            },
+        );
+        for (index, item) in items.iter().enumerate() {
+            let pos = origin + DVec2 { x: 1.0, y: index as f64 } * self.cell_size;
+            if first + index == completions.selected_index() {
+                self.draw_completion_selection.draw_abs(
+                    cx,
+                    Rect {
+                        pos: dvec2(origin.x, pos.y),
+                        size: DVec2 {
+                            x: column_count as f64 + 2.0,
+                            y: 1.0,
+                        } * self.cell_size,
+                    },
+                );
+            }
+            self.draw_completion_text.draw_abs(cx, pos, &item.label);
+            let detail_pos = pos
+                + DVec2 {
+                    x: (item.label.column_count() + 2) as f64,
+                    y: 0.0,
+                } * self.cell_size;
+            self.draw_completion_text.draw_abs(cx, detail_pos, &item.detail);
+        }
+    }
+
+    // Up and down pick an item of the completion popup, return or tab accept it and escape
+    // closes the popup. Returns whether the event was used by the popup.
+    fn handle_completion_key(
+        &mut self,
+        cx: &mut Cx,
+        event: &Event,
+        session: &mut Session,
+        actions: &mut Vec<CodeEditorAction>,
+    ) -> bool {
+        let Event::KeyDown(KeyEvent { key_code, .. }) = event else {
+            return false;
+        };
+        if !cx.has_key_focus(self.scroll_bars.area()) {
+            return false;
+        }
+        let completions = self.completions.as_mut().unwrap();
+        match key_code {
+            KeyCode::ArrowDown => completions.select_next(),
+            KeyCode::ArrowUp => completions.select_prev(),
+            KeyCode::ReturnKey | KeyCode::Tab => {
+                if let Some(item) = completions.selected_item() {
+                    session.complete_word(&item.insert_text);
+                    actions.push(CodeEditorAction::TextDidChange);
+                }
+                self.completions = None;
+                self.keep_cursor_in_view = KeepCursorInView::Once;
+            }
+            KeyCode::Escape => self.completions = None,
+            _ => return false,
+        }
+        self.redraw(cx);
+        true
+    }
+
+    fn handle_find_bar_event(
+        &mut self,
+        cx: &mut Cx,
+        event: &Event,
+        session: &mut Session,
+        actions: &mut Vec<CodeEditorAction>,
+    ) {
+        for action in self.find_bar.handle_find_bar_event(cx, event) {
+            match action {
+                FindBarAction::Search(_) => {
+                    self.search(cx, session);
+                    continue;
+                }
+                FindBarAction::Next => {
+                    session.select_next_match();
+                }
+                FindBarAction::Prev => {
+                    session.select_prev_match();
+                }
+                FindBarAction::SelectAll => {
+                    if session.select_all_matches() {
+                        // keep the selections and type into them
+                        self.find_open = false;
+                        self.set_key_focus(cx);
+                    }
+                }
+                FindBarAction::Replace(replace) => {
+                    let had_selected_match = session.selected_match_index().is_some();
+                    session.replace_match(&replace);
+                    if had_selected_match {
+                        actions.push(CodeEditorAction::TextDidChange);
+                    }
+                }
+                FindBarAction::ReplaceAll(replace) => {
+                    if session.replace_all_matches(&replace) > 0 {
+                        actions.push(CodeEditorAction::TextDidChange);
+                    }
+                }
+                FindBarAction::Close => {
+                    self.close_find_bar(cx, session);
+                    continue;
+                }
+            }
+            self.keep_cursor_in_view = KeepCursorInView::JumpToPosition;
+            self.update_find_status(cx, session);
+            self.redraw(cx);
+        }
+    }
+
     pub fn reset_font_size(&mut self) {
         self.draw_gutter.text_style.font_size = 9.0;
         self.draw_text.text_style.font_size = 9.0;
@@ -556,6 +835,17 @@ impl CodeEditor {
 
         session.handle_changes();
 
+        // the find bar is drawn over the editor, so it gets to handle events first
+        if self.find_open {
+            self.handle_find_bar_event(cx, event, session, &mut actions);
+        }
+
+        if self.completions.is_some()
+            && self.handle_completion_key(cx, event, session, &mut actions)
+        {
+            return actions;
+        }
+
         if self.scroll_bars.handle_event(cx, event).len()>0{
             self.redraw(cx);
         };
@@ -637,6 +927,62 @@ impl CodeEditor {
                     self.redraw(cx);
                 }
             }
+            Hit::KeyDown(KeyEvent {
+                key_code: KeyCode::KeyF,
+                modifiers: KeyModifiers { control, logo, .. },
+                ..
+            }) => {
+                if control || logo {
+                    self.open_find_bar(cx, session);
+                }
+            }
+            Hit::KeyDown(KeyEvent {
+                key_code: KeyCode::Space,
+                modifiers: KeyModifiers { control: true, .. },
+                ..
+            }) => {
+                actions.push(CodeEditorAction::RequestCompletion);
+            }
+            Hit::KeyDown(KeyEvent {
+                key_code: KeyCode::KeyI,
+                modifiers: KeyModifiers { control, logo, .. },
+                ..
+            }) => {
+                if control || logo {
+                    actions.push(CodeEditorAction::RequestHover);
+                }
+            }
+            Hit::KeyDown(KeyEvent {
+                key_code: KeyCode::F12,
+                ..
+            }) => {
+                actions.push(CodeEditorAction::RequestDefinition);
+            }
+            Hit::KeyDown(KeyEvent {
+                key_code: KeyCode::Period,
+                modifiers: KeyModifiers { control, logo, .. },
+                ..
+            }) => {
+                if control || logo {
+                    actions.push(CodeEditorAction::QuickFix);
+                }
+            }
+            Hit::KeyDown(KeyEvent {
+                key_code: KeyCode::KeyG,
+                modifiers: KeyModifiers { shift, control, logo, .. },
+                ..
+            }) => {
+                if (control || logo) && self.find_open {
+                    if shift {
+                        session.select_prev_match();
+                    } else {
+                        session.select_next_match();
+                    }
+                    self.update_find_status(cx, session);
+                    self.keep_cursor_in_view = KeepCursorInView::JumpToPosition;
+                    self.redraw(cx);
+                }
+            }
             Hit::KeyDown(KeyEvent {
                 key_code: KeyCode::KeyA,
                 modifiers: KeyModifiers {control, logo, ..},
@@ -945,6 +1291,14 @@ impl CodeEditor {
         if keyboard_moved_cursor {
             self.keep_cursor_in_view = KeepCursorInView::Once;
             self.reset_cursor_blinker(cx);
+            // narrow the completions down to the word being typed, or drop them once it is gone
+            if let Some(completions) = &mut self.completions {
+                let word = session.word_before_cursor();
+                completions.filter(&word);
+                if word.is_empty() || completions.is_empty() {
+                    self.completions = None;
+                }
+            }
         }
         if let KeepCursorInView::Always(abs, next) = &mut self.keep_cursor_in_view {
             if next.is_event(event).is_some() {
@@ -991,6 +1345,9 @@ impl CodeEditor {
                 BlockElement::Widget(widget) => {
                     origin_y += widget.height;
                 }
+                BlockElement::Text(_) => {
+                    origin_y += element.height();
+                }
             }
         }
     }
@@ -1041,24 +1398,7 @@ impl CodeEditor {
                                     };
                                     let (text_0, text_1) = text.split_at(token.len);
                                     text = text_1;
-                                    self.draw_text.color = match token.kind {
-                                        TokenKind::Unknown => self.token_colors.unknown,
-                                        TokenKind::BranchKeyword => {
-                                            self.token_colors.branch_keyword
-                                        }
-                                        TokenKind::Comment => self.token_colors.comment,
-                                        TokenKind::Constant => self.token_colors.constant,
-                                        TokenKind::Delimiter => self.token_colors.delimiter,
-                                        TokenKind::Identifier => self.token_colors.identifier,
-                                        TokenKind::LoopKeyword => self.token_colors.loop_keyword,
-                                        TokenKind::Number => self.token_colors.number,
-                                        TokenKind::OtherKeyword => self.token_colors.other_keyword,
-                                        TokenKind::Punctuator => self.token_colors.punctuator,
-                                        TokenKind::String => self.token_colors.string,
-                                        TokenKind::Function => self.token_colors.function,
-                                        TokenKind::Typename => self.token_colors.typename,
-                                        TokenKind::Whitespace => self.token_colors.whitespace,
-                                    };
+                                    self.draw_text.color = self.token_colors.color(token.kind);
                                     self.draw_text.outline = 0.0;
                                     if let TokenKind::Delimiter = token.kind {
                                         if highlighted_delimiter_positions.contains(&Position {
@@ -1113,6 +1453,23 @@ impl CodeEditor {
                 BlockElement::Widget(widget) => {
                     origin_y += widget.height;
                 }
+                BlockElement::Text(text) => {
+                    self.draw_text.font_scale = 1.0;
+                    self.draw_text.outline = 0.0;
+                    self.draw_text.color = self.token_colors.comment;
+                    for (index, text_line) in text.lines().enumerate() {
+                        self.draw_text.draw_abs(
+                            cx,
+                            DVec2 {
+                                x: 0.0,
+                                y: origin_y + index as f64,
+                            } * self.cell_size
+                                + self.viewport_rect.pos,
+                            text_line,
+                        );
+                    }
+                    origin_y += element.height();
+                }
             }
         }
     }
@@ -1151,13 +1508,20 @@ impl CodeEditor {
                 BlockElement::Widget(widget) => {
                     origin_y += widget.height;
                 }
+                BlockElement::Text(_) => {
+                    origin_y += element.height();
+                }
             }
         }
     }
 
-    fn draw_decoration_layer(&mut self, cx: &mut Cx2d<'_>, session: &Session) {
+    fn draw_decoration_layer(
+        &mut self,
+        cx: &mut Cx2d<'_>,
+        session: &Session,
+        decorations: &[Decoration],
+    ) {
         let mut active_decoration = None;
-        let decorations = session.document().decorations();
         let mut decorations = decorations.iter();
         while decorations.as_slice().first().map_or(false, |decoration| {
             decoration.end().line_index < self.line_start
@@ -1410,6 +1774,9 @@ impl CodeEditor {
                 BlockElement::Widget(widget) => {
                     origin_y += widget.height;
                 }
+                BlockElement::Text(_) => {
+                    origin_y += block.height();
+                }
             }
         }
         panic!()
@@ -1419,6 +1786,10 @@ impl CodeEditor {
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, DefaultNone)]
 pub enum CodeEditorAction {
     TextDidChange,
+    RequestCompletion,
+    RequestHover,
+    RequestDefinition,
+    QuickFix,
     None
 }
 
@@ -1534,6 +1905,9 @@ impl<'a> DrawDecorationLayer<'a> {
                 BlockElement::Widget(widget) => {
                     origin_y += widget.height;
                 }
+                BlockElement::Text(_) => {
+                    origin_y += block.height();
+                }
             }
         }
     }
@@ -1588,26 +1962,28 @@ impl<'a> DrawDecorationLayer<'a> {
     ) {
         let start_x = mem::take(&mut self.active_decoration.as_mut().unwrap().start_x);
         let (x, y) = line.grid_to_normalized_position(row_index, column_index);
+        let rect = Rect {
+            pos: DVec2 {
+                x: start_x,
+                y: origin_y + y,
+            } * self.code_editor.cell_size
+                + self.code_editor.viewport_rect.pos,
+            size: DVec2 {
+                x: x - start_x,
+                y: line.scale(),
+            } * self.code_editor.cell_size,
+        };
         self.code_editor.draw_decoration.color =
             match self.active_decoration.as_mut().unwrap().decoration.ty {
                 DecorationType::Warning => self.code_editor.token_colors.warning_decoration,
                 DecorationType::Error => self.code_editor.token_colors.error_decoration,
+                // search matches are a box behind the text rather than a squiggle
+                DecorationType::SearchMatch => {
+                    self.code_editor.draw_search_match.draw_abs(cx, rect);
+                    return;
+                }
             };
-
-        self.code_editor.draw_decoration.draw_abs(
-            cx,
-            Rect {
-                pos: DVec2 {
-                    x: start_x,
-                    y: origin_y + y,
-                } * self.code_editor.cell_size
-                    + self.code_editor.viewport_rect.pos,
-                size: DVec2 {
-                    x: x - start_x,
-                    y: line.scale(),
-                } * self.code_editor.cell_size,
-            },
-        );
+        self.code_editor.draw_decoration.draw_abs(cx, rect);
     }
 }
 
@@ -1729,6 +2105,9 @@ impl<'a> DrawSelectionLayer<'a> {
                 BlockElement::Widget(widget) => {
                     origin_y += widget.height;
                 }
+                BlockElement::Text(_) => {
+                    origin_y += block.height();
+                }
             }
         }
         if self.active_selection.is_some() {
@@ -1867,7 +2246,7 @@ struct ActiveSelection {
 }
 
 #[derive(Live, LiveHook, LiveRegister)]
-struct TokenColors {
+pub struct TokenColors {
     #[live]
     unknown: Vec4,
     #[live]
@@ -1904,6 +2283,27 @@ struct TokenColors {
     warning_decoration: Vec4,
 }
 
+impl TokenColors {
+    pub fn color(&self, kind: TokenKind) -> Vec4 {
+        match kind {
+            TokenKind::Unknown => self.unknown,
+            TokenKind::BranchKeyword => self.branch_keyword,
+            TokenKind::Comment => self.comment,
+            TokenKind::Constant => self.constant,
+            TokenKind::Delimiter => self.delimiter,
+            TokenKind::Identifier => self.identifier,
+            TokenKind::LoopKeyword => self.loop_keyword,
+            TokenKind::Number => self.number,
+            TokenKind::OtherKeyword => self.other_keyword,
+            TokenKind::Punctuator => self.punctuator,
+            TokenKind::String => self.string,
+            TokenKind::Function => self.function,
+            TokenKind::Typename => self.typename,
+            TokenKind::Whitespace => self.whitespace,
+        }
+    }
+}
+
 #[derive(Live, LiveHook, LiveRegister)]
 #[repr(C)]
 pub struct DrawIndentGuide {
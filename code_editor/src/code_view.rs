@@ -0,0 +1,91 @@
+use {
+    crate::{
+        code_editor::TokenColors,
+        text::Text,
+        tokenizer::Tokenizer,
+        Token,
+    },
+    makepad_widgets::*,
+};
+
+live_design! {
+    import makepad_widgets::theme_desktop_dark::*;
+
+    CodeView = {{CodeView}} {
+        width: Fill,
+        height: Fit,
+        flow: RightWrap,
+        padding: 8,
+        draw_bg: {
+            color: #2a
+        }
+        draw_text: {
+            text_style: <THEME_FONT_CODE> {}
+        }
+    }
+}
+
+// A read only piece of code, colored by the code editor tokenizer. Use it for code
+// blocks in a Markdown widget with `code_block = <CodeView> {}`.
+#[derive(Live, Widget)]
+pub struct CodeView {
+    #[walk] walk: Walk,
+    #[layout] layout: Layout,
+    #[redraw] #[live] draw_bg: DrawColor,
+    #[live] draw_text: DrawText,
+    #[live] token_colors: TokenColors,
+    #[live] text: String,
+    // the file extension of the language to color the code as, like "toml"
+    #[live] language: String,
+    #[rust] tokens: Vec<Vec<Token>>,
+}
+
+impl LiveHook for CodeView {
+    fn after_apply_from(&mut self, _cx: &mut Cx, _apply: &mut Apply) {
+        self.tokenize();
+    }
+}
+
+impl CodeView {
+    fn tokenize(&mut self) {
+        let text = Text::from(self.text.as_str());
+        let line_count = text.as_lines().len();
+        self.tokens = vec![Vec::new(); line_count];
+        Tokenizer::for_extension(&self.language, line_count).update(&text, &mut self.tokens);
+    }
+}
+
+impl Widget for CodeView {
+    fn handle_event(&mut self, _cx: &mut Cx, _event: &Event, _scope: &mut Scope) {
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, _scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.draw_bg.begin(cx, walk, self.layout);
+        for (line, tokens) in self.text.split('\n').zip(&self.tokens) {
+            if line.is_empty() {
+                // an empty line still has to take up a line
+                self.draw_text.draw_walk_word(cx, " ");
+            }
+            let mut start = 0;
+            for token in tokens {
+                self.draw_text.color = self.token_colors.color(token.kind);
+                self.draw_text.draw_walk_word(cx, &line[start..start + token.len]);
+                start += token.len;
+            }
+            cx.turtle_new_line();
+        }
+        self.draw_bg.end(cx);
+        DrawStep::done()
+    }
+
+    fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    fn set_text(&mut self, v: &str) {
+        if self.text != v {
+            self.text = v.to_string();
+            self.tokenize();
+        }
+    }
+}
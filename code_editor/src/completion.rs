@@ -0,0 +1,116 @@
+/// An entry of the completion popup.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub detail: String,
+    pub insert_text: String,
+}
+
+/// The items offered for the word in front of the cursor, narrowed down as that word is typed.
+#[derive(Clone, Debug, Default)]
+pub struct Completions {
+    items: Vec<CompletionItem>,
+    visible: Vec<usize>,
+    selected: usize,
+}
+
+impl Completions {
+    pub fn new(items: Vec<CompletionItem>) -> Self {
+        Self {
+            visible: (0..items.len()).collect(),
+            items,
+            selected: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visible.is_empty()
+    }
+
+    pub fn visible_items(&self) -> impl Iterator<Item = &CompletionItem> + '_ {
+        self.visible.iter().map(|&index| &self.items[index])
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_item(&self) -> Option<&CompletionItem> {
+        self.visible.get(self.selected).map(|&index| &self.items[index])
+    }
+
+    /// Keeps the items whose label starts with `word`, ignoring case.
+    pub fn filter(&mut self, word: &str) {
+        let word = word.to_lowercase();
+        self.visible = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.label.to_lowercase().starts_with(&word))
+            .map(|(index, _)| index)
+            .collect();
+        self.selected = self.selected.min(self.visible.len().saturating_sub(1));
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.visible.is_empty() {
+            self.selected = (self.selected + 1) % self.visible.len();
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        if !self.visible.is_empty() {
+            self.selected = (self.selected + self.visible.len() - 1) % self.visible.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completions(labels: &[&str]) -> Completions {
+        Completions::new(labels.iter().map(|label| CompletionItem {
+            label: label.to_string(),
+            detail: String::new(),
+            insert_text: label.to_string(),
+        }).collect())
+    }
+
+    fn visible_labels(completions: &Completions) -> Vec<&str> {
+        completions.visible_items().map(|item| item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn filters_by_prefix_ignoring_case() {
+        let mut completions = completions(&["apple", "Apricot", "banana", "grape"]);
+        completions.filter("AP");
+        assert_eq!(visible_labels(&completions), vec!["apple", "Apricot"]);
+        completions.filter("ban");
+        assert_eq!(visible_labels(&completions), vec!["banana"]);
+        completions.filter("x");
+        assert!(completions.is_empty());
+        assert_eq!(completions.selected_item(), None);
+        completions.filter("");
+        assert_eq!(visible_labels(&completions).len(), 4);
+    }
+
+    #[test]
+    fn selection_wraps_and_stays_in_the_filtered_items() {
+        let mut completions = completions(&["apple", "Apricot", "banana"]);
+        completions.select_prev();
+        assert_eq!(completions.selected_index(), 2);
+        completions.select_next();
+        assert_eq!(completions.selected_index(), 0);
+        completions.select_prev();
+        completions.filter("ap");
+        // the selection was past the end of the filtered items
+        assert_eq!(completions.selected_item().map(|item| item.label.as_str()), Some("Apricot"));
+        completions.select_next();
+        assert_eq!(completions.selected_item().map(|item| item.label.as_str()), Some("apple"));
+        completions.filter("x");
+        completions.select_prev();
+        completions.select_next();
+        assert_eq!(completions.selected_index(), 0);
+    }
+}
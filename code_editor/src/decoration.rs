@@ -7,6 +7,7 @@ use {
 pub enum DecorationType {
     Error,
     Warning,
+    SearchMatch,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -94,6 +95,10 @@ impl DecorationSet {
         self.decorations.clear();
     }
 
+    pub fn retain(&mut self, f: impl FnMut(&Decoration) -> bool) {
+        self.decorations.retain(f);
+    }
+
     pub fn apply_edit(&mut self, edit: &Edit) {
         for decoration in &mut self.decorations {
             *decoration = decoration.apply_edit(edit);
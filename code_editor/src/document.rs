@@ -29,6 +29,11 @@ pub struct Document(Rc<DocumentInner>);
 
 impl Document {
     pub fn new(text: Text, decorations: DecorationSet) -> Self {
+        Self::new_for_extension(text, decorations, "rs")
+    }
+
+    /// Creates a document that is tokenized in the language for the file extension.
+    pub fn new_for_extension(text: Text, decorations: DecorationSet, extension: &str) -> Self {
         let line_count = text.as_lines().len();
         let tokens: Vec<_> = (0..line_count)
             .map(|line| tokenize(&text.as_lines()[line]).collect::<Vec<_>>())
@@ -41,7 +46,7 @@ impl Document {
                 inline_inlays: (0..line_count).map(|_| Vec::new()).collect(),
                 block_inlays: Vec::new(),
             }),
-            tokenizer: RefCell::new(Tokenizer::new(line_count)),
+            tokenizer: RefCell::new(Tokenizer::for_extension(extension, line_count)),
             decorations: RefCell::new(decorations),
             edit_senders: RefCell::new(HashMap::new()),
         }));
@@ -182,6 +187,16 @@ impl Document {
         self.0.decorations.borrow_mut().clear()
     }
 
+    /// Replaces the blocks drawn between the lines of the document. Each block is drawn above
+    /// its line, and they must be sorted by line.
+    pub fn set_block_inlays(&mut self, block_inlays: Vec<(usize, BlockInlay)>) {
+        self.0.layout.borrow_mut().block_inlays = block_inlays;
+        // an edit without changes makes each session lay out its lines again
+        for edit_sender in self.0.edit_senders.borrow().values() {
+            edit_sender.send((None, Vec::new())).unwrap();
+        }
+    }
+
     pub fn add_session(
         &mut self,
         session_id: SessionId,
@@ -374,6 +389,7 @@ impl Document {
         for edit in edits {
             self.apply_change_to_tokens(&edit.change);
             self.apply_change_to_inline_inlays(&edit.change, edit.drift);
+            self.apply_change_to_block_inlays(&edit.change);
             self.0.tokenizer.borrow_mut().apply_change(&edit.change);
         }
         self.update_indent_state();
@@ -604,6 +620,30 @@ impl Document {
         }
     }
 
+    fn apply_change_to_block_inlays(&self, change: &Change) {
+        let mut layout = self.0.layout.borrow_mut();
+        let block_inlays = &mut layout.block_inlays;
+        match *change {
+            Change::Insert(point, ref text) => {
+                let line_count = text.length().line_count;
+                for (line, _) in block_inlays.iter_mut() {
+                    if *line > point.line_index {
+                        *line += line_count;
+                    }
+                }
+            }
+            Change::Delete(start, length) => {
+                let end_line = start.line_index + length.line_count;
+                block_inlays.retain(|&(line, _)| line <= start.line_index || line > end_line);
+                for (line, _) in block_inlays.iter_mut() {
+                    if *line > end_line {
+                        *line -= length.line_count;
+                    }
+                }
+            }
+        }
+    }
+
     fn update_indent_state(&self) {
         let mut layout = self.0.layout.borrow_mut();
         let indent_state = &mut layout.indent_state;
@@ -697,3 +737,40 @@ fn tokenize(text: &str) -> impl Iterator<Item = Token> + '_ {
         },
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_inlay_lines(document: &Document) -> Vec<usize> {
+        document.0.layout.borrow().block_inlays.iter().map(|(line, _)| *line).collect()
+    }
+
+    fn position(line_index: usize, byte_index: usize) -> Position {
+        Position {line_index, byte_index}
+    }
+
+    #[test]
+    fn block_inlays_follow_inserts_and_deletes() {
+        let mut document = Document::new("a\nb\nc\nd\ne".into(), DecorationSet::new());
+        // a hover below line 1
+        document.set_block_inlays(vec![(2, BlockInlay::Text("hover".to_string()))]);
+        // inserting within a line before it, or on the line below it, doesn't move it
+        document.apply_change_to_block_inlays(&Change::Insert(position(0, 1), "x".into()));
+        document.apply_change_to_block_inlays(&Change::Insert(position(2, 0), "x\ny".into()));
+        assert_eq!(block_inlay_lines(&document), vec![2]);
+        // new lines above it push it down
+        document.apply_change_to_block_inlays(&Change::Insert(position(1, 1), "x\ny\n".into()));
+        assert_eq!(block_inlay_lines(&document), vec![4]);
+        // deleted lines above it pull it up
+        document.apply_change_to_block_inlays(&Change::Delete(position(0, 1), Length {line_count: 2, byte_count: 0}));
+        assert_eq!(block_inlay_lines(&document), vec![2]);
+        // a delete within a line, or one that starts on its line, keeps it
+        document.apply_change_to_block_inlays(&Change::Delete(position(1, 0), Length {line_count: 0, byte_count: 1}));
+        document.apply_change_to_block_inlays(&Change::Delete(position(2, 0), Length {line_count: 1, byte_count: 0}));
+        assert_eq!(block_inlay_lines(&document), vec![2]);
+        // joining the two lines it sits between removes it
+        document.apply_change_to_block_inlays(&Change::Delete(position(1, 1), Length {line_count: 1, byte_count: 0}));
+        assert!(block_inlay_lines(&document).is_empty());
+    }
+}
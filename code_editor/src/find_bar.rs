@@ -0,0 +1,171 @@
+use {
+    crate::search::SearchQuery,
+    makepad_widgets::*,
+};
+
+live_design! {
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    FindBar = {{FindBar}} {
+        width: 460,
+        height: Fit,
+        flow: Down,
+        padding: 6,
+        spacing: 4,
+        optimize: DrawList,
+        show_bg: true,
+        draw_bg: {
+            color: #3a
+        }
+
+        find_row = <View> {
+            width: Fill,
+            height: Fit,
+            spacing: 4,
+            align: {y: 0.5}
+            find_input = <TextInput> {width: Fill, empty_message: "Find"}
+            case_sensitive = <CheckBox> {text: "Aa"}
+            whole_word = <CheckBox> {text: "W"}
+            regex = <CheckBox> {text: ".*"}
+            count = <Label> {width: 64, text: ""}
+            prev = <Button> {text: "<"}
+            next = <Button> {text: ">"}
+            close = <Button> {text: "x"}
+        }
+        replace_row = <View> {
+            width: Fill,
+            height: Fit,
+            spacing: 4,
+            align: {y: 0.5}
+            replace_input = <TextInput> {width: Fill, empty_message: "Replace"}
+            replace = <Button> {text: "Replace"}
+            replace_all = <Button> {text: "All"}
+            select_all = <Button> {text: "Select All"}
+        }
+    }
+}
+
+// The find and replace bar the code editor shows over its top right corner. It only turns
+// input into actions, the editor applies them to its session.
+#[derive(Live, LiveHook, Widget)]
+pub struct FindBar {
+    #[deref]
+    view: View,
+    #[rust]
+    query: SearchQuery,
+    // the find input can only take key focus once it has been drawn
+    #[rust]
+    focus_on_draw: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FindBarAction {
+    Search(SearchQuery),
+    Next,
+    Prev,
+    SelectAll,
+    Replace(String),
+    ReplaceAll(String),
+    Close,
+}
+
+impl Widget for FindBar {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk_all(cx, scope, walk);
+        if self.focus_on_draw {
+            self.focus_on_draw = false;
+            let find_input = self.view.text_input(id!(find_input));
+            find_input.set_key_focus(cx);
+            find_input.select_all();
+        }
+        DrawStep::done()
+    }
+}
+
+impl FindBar {
+    pub fn query(&self) -> &SearchQuery {
+        &self.query
+    }
+
+    /// Shows `text` in the find input and focuses it with all of its text selected.
+    pub fn open(&mut self, cx: &mut Cx, text: Option<&str>) {
+        if let Some(text) = text {
+            self.query.text = text.to_string();
+            self.view.text_input(id!(find_input)).set_text(text);
+        }
+        self.focus_on_draw = true;
+        self.view.redraw(cx);
+    }
+
+    pub fn set_status(&mut self, cx: &mut Cx, status: &str) {
+        self.view.label(id!(count)).set_text_and_redraw(cx, status);
+    }
+
+    pub fn handle_find_bar_event(&mut self, cx: &mut Cx, event: &Event) -> Vec<FindBarAction> {
+        let actions = cx.capture_actions(|cx| {
+            self.view.handle_event(cx, event, &mut Scope::empty())
+        });
+        let mut find_bar_actions = Vec::new();
+
+        let find_input = self.view.text_input(id!(find_input));
+        let mut query_changed = false;
+        if let Some(text) = find_input.changed(&actions) {
+            self.query.text = text;
+            query_changed = true;
+        }
+        if let Some(value) = self.view.check_box(id!(case_sensitive)).changed(&actions) {
+            self.query.case_sensitive = value;
+            query_changed = true;
+        }
+        if let Some(value) = self.view.check_box(id!(whole_word)).changed(&actions) {
+            self.query.whole_word = value;
+            query_changed = true;
+        }
+        if let Some(value) = self.view.check_box(id!(regex)).changed(&actions) {
+            self.query.regex = value;
+            query_changed = true;
+        }
+        if query_changed {
+            find_bar_actions.push(FindBarAction::Search(self.query.clone()));
+        }
+
+        // the text input also reports a return when it loses focus, so check for the key itself
+        if find_input.returned(&actions).is_some() {
+            if let Event::KeyDown(KeyEvent { key_code: KeyCode::ReturnKey, modifiers, .. }) = event {
+                find_bar_actions.push(if modifiers.shift {
+                    FindBarAction::Prev
+                } else {
+                    FindBarAction::Next
+                });
+            }
+        }
+        if self.view.button(id!(next)).clicked(&actions) {
+            find_bar_actions.push(FindBarAction::Next);
+        }
+        if self.view.button(id!(prev)).clicked(&actions) {
+            find_bar_actions.push(FindBarAction::Prev);
+        }
+        if self.view.button(id!(select_all)).clicked(&actions) {
+            find_bar_actions.push(FindBarAction::SelectAll);
+        }
+        let replace_input = self.view.text_input(id!(replace_input));
+        if self.view.button(id!(replace)).clicked(&actions) {
+            find_bar_actions.push(FindBarAction::Replace(replace_input.text()));
+        }
+        if self.view.button(id!(replace_all)).clicked(&actions) {
+            find_bar_actions.push(FindBarAction::ReplaceAll(replace_input.text()));
+        }
+        if self.view.button(id!(close)).clicked(&actions)
+            || find_input.escaped(&actions)
+            || replace_input.escaped(&actions)
+        {
+            find_bar_actions.push(FindBarAction::Close);
+        }
+        find_bar_actions
+    }
+}
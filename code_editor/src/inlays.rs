@@ -9,4 +9,6 @@ pub enum InlineInlay {
 #[derive(Clone, Debug, PartialEq)]
 pub enum BlockInlay {
     Widget(BlockWidget),
+    // a block of lines of text, such as a hover, drawn between two lines of the document
+    Text(String),
 }
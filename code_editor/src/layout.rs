@@ -469,6 +469,7 @@ impl<'a> Iterator for BlockElements<'a> {
             let (_, block_inlay) = self.block_inlays.next().unwrap();
             return Some(match *block_inlay {
                 BlockInlay::Widget(widget) => BlockElement::Widget(widget),
+                BlockInlay::Text(ref text) => BlockElement::Text(text),
             });
         }
         let line = self.lines.next()?;
@@ -484,4 +485,15 @@ impl<'a> Iterator for BlockElements<'a> {
 pub enum BlockElement<'a> {
     Line { is_inlay: bool, line: Line<'a> },
     Widget(BlockWidget),
+    Text(&'a str),
+}
+
+impl<'a> BlockElement<'a> {
+    pub fn height(&self) -> f64 {
+        match *self {
+            BlockElement::Line { line, .. } => line.height(),
+            BlockElement::Widget(widget) => widget.height,
+            BlockElement::Text(text) => text.lines().count().max(1) as f64,
+        }
+    }
 }
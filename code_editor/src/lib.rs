@@ -1,14 +1,19 @@
 pub use makepad_widgets;
+pub use makepad_regex as regex;
 use makepad_widgets::*;
 
 pub mod char;
 pub mod code_editor;
+pub mod code_view;
+pub mod completion;
 pub mod decoration;
 pub mod document;
+pub mod find_bar;
 pub mod history;
 pub mod inlays;
 pub mod iter;
 pub mod layout;
+pub mod search;
 pub mod selection;
 pub mod session;
 pub mod settings;
@@ -20,10 +25,12 @@ pub mod widgets;
 pub mod wrap;
 
 pub use self::{
-    code_editor::CodeEditor, document::Document, history::History, layout::Line,
+    code_editor::CodeEditor, code_view::CodeView, document::Document, history::History, layout::Line,
     selection::Selection, session::Session, settings::Settings, token::Token, tokenizer::Tokenizer,
 };
 
 pub fn live_design(cx: &mut Cx) {
+    crate::find_bar::live_design(cx);
     crate::code_editor::live_design(cx);
+    crate::code_view::live_design(cx);
 }
@@ -0,0 +1,86 @@
+use crate::{
+    regex::{Regex, RegexError, RegexMatch},
+    text::Position,
+};
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SearchQuery {
+    pub text: String,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub regex: bool,
+}
+
+impl SearchQuery {
+    pub fn compile(&self) -> Result<Search, RegexError> {
+        let regex = if self.regex {
+            Regex::new(&self.text)?
+        } else {
+            Regex::literal(&self.text)
+        };
+        Ok(Search {
+            regex: regex
+                .ignore_case(!self.case_sensitive)
+                .whole_word(self.whole_word),
+            expand_groups: self.regex,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Search {
+    regex: Regex,
+    expand_groups: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchMatch {
+    pub start: Position,
+    pub end: Position,
+    regex_match: RegexMatch,
+}
+
+/// The matches found in a line before the search gave up on it, because the pattern was too
+/// complex to search the line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchAborted {
+    pub matches: Vec<SearchMatch>,
+}
+
+impl Search {
+    // matches never span lines
+    pub fn find_in_line(
+        &self,
+        line_index: usize,
+        line: &str,
+    ) -> Result<Vec<SearchMatch>, SearchAborted> {
+        let to_search_match = |regex_match: RegexMatch| SearchMatch {
+            start: Position {
+                line_index,
+                byte_index: regex_match.start(),
+            },
+            end: Position {
+                line_index,
+                byte_index: regex_match.end(),
+            },
+            regex_match,
+        };
+        match self.regex.find_all(line) {
+            Ok(regex_matches) => Ok(regex_matches.into_iter().map(to_search_match).collect()),
+            Err(aborted) => Err(SearchAborted {
+                matches: aborted.matches.into_iter().map(to_search_match).collect(),
+            }),
+        }
+    }
+
+    // the text a match is replaced with, regex searches can refer to groups with $1
+    pub fn replacement(&self, lines: &[String], search_match: &SearchMatch, replace: &str) -> String {
+        if self.expand_groups {
+            search_match
+                .regex_match
+                .expand(&lines[search_match.start.line_index], replace)
+        } else {
+            replace.to_string()
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use {
     crate::{
         char::CharExt,
+        decoration::{Decoration, DecorationSet, DecorationType},
         document::Document,
         history::EditKind,
         layout::{BlockElement, Layout, WrappedElement},
+        regex::RegexError,
+        search::{Search, SearchMatch, SearchQuery},
         selection::{Affinity, Cursor, SelectionSet},
         str::StrExt,
         text::{Change, Drift, Edit, Length, Position, Text},
@@ -30,6 +33,7 @@ pub struct Session {
     selection_state: RefCell<SelectionState>,
     wrap_column: Cell<Option<usize>>,
     fold_state: RefCell<FoldState>,
+    search_state: RefCell<SearchState>,
     edit_receiver: Receiver<(Option<SelectionSet>, Vec<Edit>)>,
 }
 
@@ -63,6 +67,11 @@ impl Session {
                 folded_lines: HashSet::new(),
                 unfolding_lines: HashSet::new(),
             }),
+            search_state: RefCell::new(SearchState {
+                search: None,
+                matches: DecorationSet::new(),
+                aborted_lines: Vec::new(),
+            }),
             edit_receiver,
         };
         for line in 0..line_count {
@@ -113,6 +122,12 @@ impl Session {
         })
     }
 
+    pub fn search_matches(&self) -> Ref<'_, [Decoration]> {
+        Ref::map(self.search_state.borrow(), |search_state| {
+            search_state.matches.as_decorations()
+        })
+    }
+
     pub fn set_wrap_column(&self, wrap_column: Option<usize>) {
         if self.wrap_column.get() == wrap_column {
             return;
@@ -779,12 +794,398 @@ impl Session {
             .redo(self.id, &self.selection_state.borrow().selections)
     }
 
+    /// Searches the document and keeps all matches highlighted while it is edited. Returns the
+    /// number of matches, an empty query clears the search.
+    pub fn set_search(&self, query: &SearchQuery) -> Result<usize, RegexError> {
+        let search = if query.text.is_empty() {
+            None
+        } else {
+            Some(query.compile()?)
+        };
+        self.search_state.borrow_mut().search = search;
+        self.update_search_matches();
+        Ok(self.search_state.borrow().matches.as_decorations().len())
+    }
+
+    pub fn clear_search(&self) {
+        let mut search_state = self.search_state.borrow_mut();
+        search_state.search = None;
+        search_state.matches.clear();
+        search_state.aborted_lines.clear();
+    }
+
+    /// Whether the search gave up on some lines because the pattern was too complex to search
+    /// them, in which case not every match was found.
+    pub fn search_is_incomplete(&self) -> bool {
+        !self.search_state.borrow().aborted_lines.is_empty()
+    }
+
+    /// The index of the match that is selected, if any.
+    pub fn selected_match_index(&self) -> Option<usize> {
+        let selection = self.last_added_selection();
+        self.search_matches().iter().position(|decoration| {
+            decoration.start() == selection.start() && decoration.end() == selection.end()
+        })
+    }
+
+    /// Selects the first match after the selection, wrapping around at the end of the document.
+    pub fn select_next_match(&self) -> bool {
+        let position = self.last_added_selection().end();
+        let decoration = {
+            let matches = self.search_matches();
+            matches
+                .iter()
+                .find(|decoration| decoration.start() >= position)
+                .or(matches.first())
+                .copied()
+        };
+        decoration.map(|decoration| self.select_match(decoration)).is_some()
+    }
+
+    /// Selects the last match before the selection, wrapping around at the start of the document.
+    pub fn select_prev_match(&self) -> bool {
+        let position = self.last_added_selection().start();
+        let decoration = {
+            let matches = self.search_matches();
+            matches
+                .iter()
+                .rev()
+                .find(|decoration| decoration.start() < position)
+                .or(matches.last())
+                .copied()
+        };
+        decoration.map(|decoration| self.select_match(decoration)).is_some()
+    }
+
+    /// Selects the first match starting at the selection, so the selected match stays selected
+    /// while typing a query.
+    pub fn select_nearest_match(&self) -> bool {
+        let position = self.last_added_selection().start();
+        let decoration = {
+            let matches = self.search_matches();
+            matches
+                .iter()
+                .find(|decoration| decoration.start() >= position)
+                .or(matches.first())
+                .copied()
+        };
+        decoration.map(|decoration| self.select_match(decoration)).is_some()
+    }
+
+    /// Turns every match into a selection.
+    pub fn select_all_matches(&self) -> bool {
+        let selected_match_index = self.selected_match_index();
+        let matches = self.search_matches();
+        let Some((first, rest)) = matches.split_first() else {
+            return false;
+        };
+        let mut selections = SelectionSet::new();
+        selections.set_selection(match_selection(*first));
+        for decoration in rest {
+            selections.add_selection(match_selection(*decoration));
+        }
+        drop(matches);
+        let mut selection_state = self.selection_state.borrow_mut();
+        selection_state.mode = SelectionMode::Simple;
+        selection_state.selections = selections;
+        selection_state.last_added_selection_index = Some(selected_match_index.unwrap_or(0));
+        selection_state.injected_char_stack.clear();
+        drop(selection_state);
+        self.update_highlighted_delimiter_positions();
+        self.document().force_new_group();
+        true
+    }
+
+    /// Replaces the selected match and selects the next one. If no match is selected, it only
+    /// selects the next one.
+    pub fn replace_match(&self, replace: &str) -> bool {
+        let Some(search) = self.search_state.borrow().search.clone() else {
+            return false;
+        };
+        let selection = self.last_added_selection();
+        let search_match = search
+            .find_in_line(
+                selection.start().line_index,
+                &self.document.as_text().as_lines()[selection.start().line_index],
+            )
+            .unwrap_or_else(|aborted| aborted.matches)
+            .into_iter()
+            .find(|search_match| {
+                search_match.start == selection.start() && search_match.end == selection.end()
+            });
+        if let Some(search_match) = search_match {
+            let replacement =
+                search.replacement(self.document.as_text().as_lines(), &search_match, replace);
+            let mut selections = SelectionSet::new();
+            selections.set_selection(selection);
+            self.replace_selections(&selections, vec![replacement]);
+        }
+        self.select_next_match()
+    }
+
+    /// Replaces all matches in a single undo group and returns how many were replaced.
+    pub fn replace_all_matches(&self, replace: &str) -> usize {
+        let Some(search) = self.search_state.borrow().search.clone() else {
+            return 0;
+        };
+        let (selections, replacements) = {
+            let text = self.document.as_text();
+            let lines = text.as_lines();
+            let search_matches: Vec<SearchMatch> = lines
+                .iter()
+                .enumerate()
+                .flat_map(|(line_index, line)| {
+                    search
+                        .find_in_line(line_index, line)
+                        .unwrap_or_else(|aborted| aborted.matches)
+                })
+                .collect();
+            let mut selections = SelectionSet::new();
+            let mut replacements = Vec::new();
+            for (index, search_match) in search_matches.iter().enumerate() {
+                let selection = Selection {
+                    anchor: search_match.start,
+                    cursor: Cursor {
+                        position: search_match.end,
+                        affinity: Affinity::Before,
+                        preferred_column_index: None,
+                    },
+                };
+                if index == 0 {
+                    selections.set_selection(selection);
+                } else {
+                    selections.add_selection(selection);
+                }
+                replacements.push(search.replacement(lines, search_match, replace));
+            }
+            (selections, replacements)
+        };
+        let count = replacements.len();
+        if count > 0 {
+            self.replace_selections(&selections, replacements);
+        }
+        count
+    }
+
+    /// Replaces ranges in a single undo group, as when applying fixes a compiler suggested.
+    /// Ranges that are out of bounds, overlap an earlier range or no longer hold their original
+    /// text are skipped. Returns how many ranges were replaced.
+    pub fn replace_ranges(&self, replacements: &[RangeReplacement]) -> usize {
+        let mut ranges: Vec<_> = replacements.iter().collect();
+        ranges.sort_by_key(|range| (range.start, range.end));
+        let (selections, replacements) = {
+            let text = self.document.as_text();
+            let lines = text.as_lines();
+            let is_valid = |position: Position| {
+                lines
+                    .get(position.line_index)
+                    .is_some_and(|line| line.is_char_boundary(position.byte_index))
+            };
+            let mut selections = SelectionSet::new();
+            let mut replacements = Vec::new();
+            let mut prev_selection: Option<Selection> = None;
+            for range in ranges {
+                if range.start > range.end || !is_valid(range.start) || !is_valid(range.end) {
+                    continue;
+                }
+                // the text was edited since the range was found
+                if text.slice(range.start, range.end - range.start).to_string() != range.original {
+                    continue;
+                }
+                let selection = Selection {
+                    anchor: range.start,
+                    cursor: Cursor {
+                        position: range.end,
+                        affinity: Affinity::Before,
+                        preferred_column_index: None,
+                    },
+                };
+                match prev_selection {
+                    Some(prev_selection) if prev_selection.overlaps_with(selection) => continue,
+                    Some(_) => {
+                        selections.add_selection(selection);
+                    }
+                    None => selections.set_selection(selection),
+                }
+                replacements.push(range.text.clone());
+                prev_selection = Some(selection);
+            }
+            (selections, replacements)
+        };
+        let count = replacements.len();
+        if count > 0 {
+            self.document.force_new_group();
+            self.replace_selections(&selections, replacements);
+            self.document.force_new_group();
+        }
+        count
+    }
+
+    /// The part of the word in front of the last added cursor, which completions are matched to.
+    pub fn word_before_cursor(&self) -> String {
+        let position = self.last_added_selection().cursor.position;
+        let text = self.document.as_text();
+        let line = &text.as_lines()[position.line_index];
+        line[word_start(line, position.byte_index)..position.byte_index].to_string()
+    }
+
+    /// Replaces the word in front of each cursor with `text`, as when accepting a completion.
+    pub fn complete_word(&self, text: &str) {
+        let selections = {
+            let document_text = self.document.as_text();
+            let lines = document_text.as_lines();
+            let mut selections = SelectionSet::new();
+            for (index, selection) in self.selections().iter().enumerate() {
+                let position = selection.cursor.position;
+                let selection = Selection {
+                    anchor: Position {
+                        line_index: position.line_index,
+                        byte_index: word_start(&lines[position.line_index], position.byte_index),
+                    },
+                    cursor: Cursor {
+                        position,
+                        affinity: Affinity::Before,
+                        preferred_column_index: None,
+                    },
+                };
+                if index == 0 {
+                    selections.set_selection(selection);
+                } else {
+                    selections.add_selection(selection);
+                }
+            }
+            selections
+        };
+        let count = selections.as_selections().len();
+        self.replace_selections(&selections, vec![text.to_string(); count]);
+    }
+
     pub fn handle_changes(&mut self) {
         while let Ok((selections, edits)) = self.edit_receiver.try_recv() {
             self.update_after_edit(selections, &edits);
         }
     }
 
+    fn last_added_selection(&self) -> Selection {
+        let selection_state = self.selection_state.borrow();
+        selection_state.selections[selection_state.last_added_selection_index.unwrap_or(0)]
+    }
+
+    fn select_match(&self, decoration: Decoration) {
+        let mut selection_state = self.selection_state.borrow_mut();
+        selection_state.mode = SelectionMode::Simple;
+        selection_state
+            .selections
+            .set_selection(match_selection(decoration));
+        selection_state.last_added_selection_index = Some(0);
+        selection_state.injected_char_stack.clear();
+        drop(selection_state);
+        self.update_highlighted_delimiter_positions();
+        self.document().force_new_group();
+    }
+
+    fn replace_selections(&self, selections: &SelectionSet, replacements: Vec<String>) {
+        let mut replacements = replacements.into_iter();
+        self.document.edit_selections(
+            self.id,
+            EditKind::Other,
+            selections,
+            &self.settings,
+            |mut editor, position, length| {
+                editor.apply_edit(Edit {
+                    change: Change::Delete(position, length),
+                    drift: Drift::Before,
+                });
+                editor.apply_edit(Edit {
+                    change: Change::Insert(position, replacements.next().unwrap().into()),
+                    drift: Drift::Before,
+                });
+            },
+        );
+        // apply the edits to this session right away, so the next match is found in the new text
+        while let Ok((selections, edits)) = self.edit_receiver.try_recv() {
+            self.update_after_edit(selections, &edits);
+        }
+    }
+
+    fn update_search_matches(&self) {
+        let mut search_state = self.search_state.borrow_mut();
+        search_state.matches.clear();
+        search_state.aborted_lines.clear();
+        drop(search_state);
+        let line_count = self.document.as_text().as_lines().len();
+        self.search_lines(0..line_count);
+    }
+
+    // only the edited lines are searched again, the matches on the other lines move with the edits
+    fn update_search_matches_after_edit(&self, edits: &[Edit]) {
+        let mut search_state = self.search_state.borrow_mut();
+        if search_state.search.is_none() {
+            return;
+        }
+        let SearchState {
+            matches,
+            aborted_lines,
+            ..
+        } = &mut *search_state;
+        let mut edited_lines = Vec::new();
+        for edit in edits {
+            matches.apply_edit(edit);
+            for line in aborted_lines.iter_mut().chain(edited_lines.iter_mut()) {
+                *line = line_after_edit(*line, edit);
+            }
+            match edit.change {
+                Change::Insert(point, ref text) => edited_lines
+                    .extend(point.line_index..=point.line_index + text.length().line_count),
+                Change::Delete(start, _) => edited_lines.push(start.line_index),
+            }
+        }
+        edited_lines.sort();
+        edited_lines.dedup();
+        aborted_lines.dedup();
+        aborted_lines.retain(|line| edited_lines.binary_search(line).is_err());
+        matches.retain(|decoration| {
+            edited_lines
+                .binary_search(&decoration.start().line_index)
+                .is_err()
+        });
+        drop(search_state);
+        self.search_lines(edited_lines);
+    }
+
+    fn search_lines(&self, lines: impl IntoIterator<Item = usize>) {
+        let mut search_state = self.search_state.borrow_mut();
+        let SearchState {
+            search,
+            matches,
+            aborted_lines,
+        } = &mut *search_state;
+        let Some(search) = search else {
+            return;
+        };
+        let text = self.document.as_text();
+        for line_index in lines {
+            let search_matches = match search.find_in_line(line_index, &text.as_lines()[line_index])
+            {
+                Ok(search_matches) => search_matches,
+                Err(aborted) => {
+                    if let Err(index) = aborted_lines.binary_search(&line_index) {
+                        aborted_lines.insert(index, line_index);
+                    }
+                    aborted.matches
+                }
+            };
+            for search_match in search_matches {
+                matches.add_decoration(Decoration::new(
+                    0,
+                    search_match.start,
+                    search_match.end,
+                    DecorationType::SearchMatch,
+                ));
+            }
+        }
+    }
+
     fn modify_selections(
         &self,
         reset_anchor: bool,
@@ -814,6 +1215,10 @@ impl Session {
     }
 
     fn update_after_edit(&self, selections: Option<SelectionSet>, edits: &[Edit]) {
+        if edits.is_empty() {
+            // the block inlays of the document changed, so every line may have moved
+            self.layout.borrow_mut().y.clear();
+        }
         for edit in edits {
             match edit.change {
                 Change::Insert(point, ref text) => {
@@ -886,6 +1291,7 @@ impl Session {
         }
         drop(selection_state);
         self.update_highlighted_delimiter_positions();
+        self.update_search_matches_after_edit(edits);
     }
 
     fn update_y(&self) {
@@ -913,6 +1319,9 @@ impl Session {
                 BlockElement::Widget(widget) => {
                     y += widget.height;
                 }
+                BlockElement::Text(_) => {
+                    y += block.height();
+                }
             }
         }
         ys.push(y);
@@ -988,6 +1397,16 @@ impl Drop for Session {
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct SessionId(usize);
 
+/// A replacement of the text between `start` and `end` with `text`, which only happens if the
+/// range still holds `original`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RangeReplacement {
+    pub start: Position,
+    pub end: Position,
+    pub original: String,
+    pub text: String,
+}
+
 #[derive(Debug)]
 pub struct SessionLayout {
     pub y: Vec<f64>,
@@ -1014,6 +1433,13 @@ struct SelectionState {
     highlighted_delimiter_positions: HashSet<Position>,
 }
 
+#[derive(Debug)]
+struct SearchState {
+    search: Option<Search>,
+    matches: DecorationSet,
+    aborted_lines: Vec<usize>,
+}
+
 #[derive(Debug)]
 struct FoldState {
     folding_lines: HashSet<usize>,
@@ -1021,6 +1447,39 @@ struct FoldState {
     unfolding_lines: HashSet<usize>,
 }
 
+// the index a line has after an edit, lines that are deleted end up on the line the deletion starts
+fn line_after_edit(line: usize, edit: &Edit) -> usize {
+    match edit.change {
+        Change::Insert(point, ref text) if line > point.line_index => {
+            line + text.length().line_count
+        }
+        Change::Delete(start, length) if line > start.line_index => {
+            line.saturating_sub(length.line_count).max(start.line_index)
+        }
+        _ => line,
+    }
+}
+
+fn word_start(line: &str, byte_index: usize) -> usize {
+    line[..byte_index]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, char)| char.is_alphanumeric() || char == '_')
+        .last()
+        .map_or(byte_index, |(index, _)| index)
+}
+
+fn match_selection(decoration: Decoration) -> Selection {
+    Selection {
+        anchor: decoration.start(),
+        cursor: Cursor {
+            position: decoration.end(),
+            affinity: Affinity::Before,
+            preferred_column_index: None,
+        },
+    }
+}
+
 pub fn reindent(string: &str, f: impl FnOnce(usize) -> usize) -> (usize, usize, String) {
     let indentation = string.indent().unwrap_or("");
     let indentation_column_count = indentation.column_count();
@@ -1297,3 +1756,174 @@ fn find_closing_delimiter(
         position.byte_index = 0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_session(text: &str) -> Session {
+        Session::new(Document::new(text.into(), DecorationSet::new()))
+    }
+
+    fn search(session: &Session, text: &str, regex: bool) -> usize {
+        session
+            .set_search(&SearchQuery {
+                text: text.to_string(),
+                regex,
+                ..SearchQuery::default()
+            })
+            .unwrap()
+    }
+
+    fn matches(session: &Session) -> Vec<(usize, usize, usize)> {
+        session
+            .search_matches()
+            .iter()
+            .map(|decoration| {
+                (
+                    decoration.start().line_index,
+                    decoration.start().byte_index,
+                    decoration.end().byte_index,
+                )
+            })
+            .collect()
+    }
+
+    fn position(line_index: usize, byte_index: usize) -> Position {
+        Position {
+            line_index,
+            byte_index,
+        }
+    }
+
+    #[test]
+    fn selects_matches_in_order() {
+        let session = new_session("foo bar\nbar foo foo");
+        assert_eq!(search(&session, "foo", false), 3);
+        assert_eq!(matches(&session), vec![(0, 0, 3), (1, 4, 7), (1, 8, 11)]);
+        session.set_selection(position(0, 5), Affinity::Before, SelectionMode::Simple);
+        assert!(session.select_next_match());
+        assert_eq!(session.selected_match_index(), Some(1));
+        assert!(session.select_next_match());
+        assert!(session.select_next_match());
+        assert_eq!(session.selected_match_index(), Some(0));
+        assert!(session.select_prev_match());
+        assert_eq!(session.selected_match_index(), Some(2));
+        assert_eq!(search(&session, "", false), 0);
+        assert!(!session.select_next_match());
+    }
+
+    #[test]
+    fn replaces_the_selected_match() {
+        let mut session = new_session("foo bar foo");
+        search(&session, "foo", false);
+        session.set_selection(position(0, 4), Affinity::Before, SelectionMode::Simple);
+        // nothing is replaced until a match is selected
+        assert!(session.replace_match("x"));
+        assert_eq!(session.document().as_text().to_string(), "foo bar foo");
+        assert_eq!(session.selected_match_index(), Some(1));
+        assert!(session.replace_match("x"));
+        session.handle_changes();
+        assert_eq!(session.document().as_text().to_string(), "foo bar x");
+        assert_eq!(session.selected_match_index(), Some(0));
+    }
+
+    #[test]
+    fn replaces_all_matches_in_one_undo_group() {
+        let mut session = new_session("a=1\nb=2 c=3");
+        search(&session, "(\\w)=(\\d)", true);
+        assert_eq!(session.replace_all_matches("$2=$1"), 3);
+        session.handle_changes();
+        assert_eq!(session.document().as_text().to_string(), "1=a\n2=b 3=c");
+        assert!(session.search_matches().is_empty());
+        assert!(session.undo());
+        session.handle_changes();
+        assert_eq!(session.document().as_text().to_string(), "a=1\nb=2 c=3");
+        assert_eq!(session.search_matches().len(), 3);
+    }
+
+    #[test]
+    fn updates_matches_after_edits() {
+        let mut session = new_session("foo\nbar\nfoo bar");
+        search(&session, "foo", false);
+        // matches on the lines after an edit move with it
+        session.set_selection(position(0, 0), Affinity::Before, SelectionMode::Simple);
+        session.insert("foo\n\n".into());
+        session.handle_changes();
+        assert_eq!(matches(&session), vec![(0, 0, 3), (2, 0, 3), (4, 0, 3)]);
+        // a match that is broken up goes away, one that is completed shows up
+        session.set_selection(position(3, 0), Affinity::Before, SelectionMode::Simple);
+        session.insert("fo".into());
+        session.handle_changes();
+        session.set_selection(position(4, 1), Affinity::Before, SelectionMode::Simple);
+        session.insert(" ".into());
+        session.handle_changes();
+        assert_eq!(matches(&session), vec![(0, 0, 3), (2, 0, 3)]);
+        session.set_selection(position(3, 2), Affinity::Before, SelectionMode::Simple);
+        session.insert("o".into());
+        session.handle_changes();
+        assert_eq!(matches(&session), vec![(0, 0, 3), (2, 0, 3), (3, 0, 3)]);
+        // deleting lines moves the matches after them up
+        session.set_selection(position(0, 0), Affinity::Before, SelectionMode::Simple);
+        session.move_down(false);
+        session.move_down(false);
+        session.delete();
+        session.handle_changes();
+        assert_eq!(
+            session.document().as_text().to_string(),
+            "foo\nfoobar\nf oo bar"
+        );
+        assert_eq!(matches(&session), vec![(0, 0, 3), (1, 0, 3)]);
+    }
+
+    #[test]
+    fn replaces_ranges_in_one_undo_group() {
+        let mut session = new_session("let a = 1;\nlet b = 2;");
+        let replacement = |start: Position, end: Position, original: &str, text: &str| {
+            RangeReplacement {
+                start,
+                end,
+                original: original.to_string(),
+                text: text.to_string(),
+            }
+        };
+        let replacements = [
+            replacement(position(1, 4), position(1, 5), "b", "_b"),
+            replacement(position(0, 4), position(0, 5), "a", "_a"),
+            // overlaps the one before it
+            replacement(position(0, 4), position(0, 9), "a = 1", "x"),
+            // out of range
+            replacement(position(2, 0), position(2, 1), "", "x"),
+            replacement(position(1, 8), position(1, 20), "2;", "x"),
+            // no longer holds the text it was found in
+            replacement(position(1, 8), position(1, 9), "3", "4"),
+        ];
+        assert_eq!(session.replace_ranges(&replacements), 2);
+        session.handle_changes();
+        assert_eq!(
+            session.document().as_text().to_string(),
+            "let _a = 1;\nlet _b = 2;"
+        );
+        // applying them again finds different text in the ranges
+        assert_eq!(session.replace_ranges(&replacements), 0);
+        assert!(session.undo());
+        session.handle_changes();
+        assert_eq!(
+            session.document().as_text().to_string(),
+            "let a = 1;\nlet b = 2;"
+        );
+        assert!(!session.undo());
+    }
+
+    #[test]
+    fn marks_the_search_incomplete_when_it_gives_up() {
+        let mut session = new_session(&format!("ab\n{}", "ab".repeat(50_000)));
+        search(&session, "(ab)+", true);
+        assert!(session.search_is_incomplete());
+        assert_eq!(matches(&session).first(), Some(&(0, 0, 2)));
+        session.set_selection(position(1, 0), Affinity::Before, SelectionMode::Line);
+        session.delete();
+        session.handle_changes();
+        assert!(!session.search_is_incomplete());
+    }
+}
@@ -1,22 +1,88 @@
-use crate::{
-    text::{Change, Text},
-    token::TokenKind,
-    Token,
+use {
+    crate::{
+        text::{Change, Text},
+        token::TokenKind,
+        Token,
+    },
+    std::fmt::Debug,
 };
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+mod json;
+mod live;
+mod markdown;
+mod toml;
+
+pub use self::{
+    json::JsonLanguage,
+    live::{LiveLanguage, LiveState},
+    markdown::MarkdownLanguage,
+    toml::TomlLanguage,
+};
+
+/// A language the code editor can color. Lines are tokenized one at a time, the state carries
+/// things like block comments and multiline strings over to the next line, so that after an
+/// edit only the lines whose start state changed are tokenized again.
+pub trait Language: Debug + 'static {
+    type State: Copy + Debug + Default + Eq;
+
+    /// Returns the state after the token at the cursor and its kind. The cursor has to be
+    /// advanced by at least one char.
+    fn next_token(&self, state: Self::State, cursor: &mut Cursor) -> (Self::State, TokenKind);
+}
+
+#[derive(Debug)]
 pub struct Tokenizer {
-    state: Vec<Option<(State, State)>>,
+    lines: Box<dyn LineTokenizer>,
 }
 
 impl Tokenizer {
     pub fn new(line_count: usize) -> Self {
+        Self::with_language(RustLanguage, line_count)
+    }
+
+    pub fn with_language(language: impl Language, line_count: usize) -> Self {
         Self {
-            state: (0..line_count).map(|_| None).collect(),
+            lines: Box::new(LanguageTokenizer {
+                language,
+                state: (0..line_count).map(|_| None).collect(),
+            }),
+        }
+    }
+
+    /// Picks the language by file extension. Anything unknown is tokenized as Rust, with the
+    /// `live_design!` blocks in it tokenized as live.
+    pub fn for_extension(extension: &str, line_count: usize) -> Self {
+        match extension {
+            "json" => Self::with_language(JsonLanguage, line_count),
+            "live" => Self::with_language(LiveLanguage, line_count),
+            "md" | "markdown" => Self::with_language(MarkdownLanguage, line_count),
+            "toml" => Self::with_language(TomlLanguage, line_count),
+            _ => Self::new(line_count),
         }
     }
 
     pub fn apply_change(&mut self, change: &Change) {
+        self.lines.apply_change(change);
+    }
+
+    pub fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]) {
+        self.lines.update(text, tokens);
+    }
+}
+
+trait LineTokenizer: Debug {
+    fn apply_change(&mut self, change: &Change);
+    fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]);
+}
+
+#[derive(Debug)]
+struct LanguageTokenizer<L: Language> {
+    language: L,
+    state: Vec<Option<(L::State, L::State)>>,
+}
+
+impl<L: Language> LineTokenizer for LanguageTokenizer<L> {
+    fn apply_change(&mut self, change: &Change) {
         match *change {
             Change::Insert(point, ref text) => {
                 self.state[point.line_index] = None;
@@ -38,8 +104,8 @@ impl Tokenizer {
         }
     }
 
-    pub fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]) {
-        let mut state = State::default();
+    fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]) {
+        let mut state = L::State::default();
         for line in 0..text.as_lines().len() {
             match self.state[line] {
                 Some((start_state, end_state)) if state == start_state => {
@@ -49,13 +115,16 @@ impl Tokenizer {
                     let start_state = state;
                     let mut new_tokens = Vec::new();
                     let mut cursor = Cursor::new(&text.as_lines()[line]);
-                    loop {
-                        let (next_state, token) = state.next(&mut cursor);
+                    while cursor.index < cursor.string.len() {
+                        let start = cursor.index;
+                        let (next_state, kind) = self.language.next_token(state, &mut cursor);
                         state = next_state;
-                        match token {
-                            Some(token) => new_tokens.push(token),
-                            None => break,
-                        }
+                        let end = cursor.index;
+                        assert!(start < end);
+                        new_tokens.push(Token {
+                            len: end - start,
+                            kind,
+                        });
                     }
                     self.state[line] = Some((start_state, state));
                     tokens[line] = new_tokens;
@@ -65,6 +134,75 @@ impl Tokenizer {
     }
 }
 
+/// Rust, with the DSL inside `live_design!` blocks tokenized by `LiveLanguage`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct RustLanguage;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RustState {
+    Rust(State),
+    // after `live_design`, up to the brace that opens the block
+    LiveDesignStart,
+    // inside a `live_design!` block, with the number of braces that are still open
+    LiveDesign(usize, LiveState),
+}
+
+impl Default for RustState {
+    fn default() -> RustState {
+        RustState::Rust(State::default())
+    }
+}
+
+impl Language for RustLanguage {
+    type State = RustState;
+
+    fn next_token(&self, state: RustState, cursor: &mut Cursor) -> (RustState, TokenKind) {
+        match state {
+            RustState::Rust(state) => {
+                let start = cursor.index;
+                let (state, kind) = state.next(cursor);
+                if &cursor.string[start..cursor.index] == "live_design" && cursor.peek(0) == '!' {
+                    return (RustState::LiveDesignStart, kind);
+                }
+                (RustState::Rust(state), kind)
+            }
+            RustState::LiveDesignStart => match cursor.peek(0) {
+                '!' => {
+                    cursor.skip(1);
+                    (RustState::LiveDesignStart, TokenKind::Punctuator)
+                }
+                '{' => {
+                    cursor.skip(1);
+                    (
+                        RustState::LiveDesign(1, LiveState::default()),
+                        TokenKind::Delimiter,
+                    )
+                }
+                char if char.is_whitespace() => {
+                    while cursor.skip_if(|char| char.is_whitespace()) {}
+                    (RustState::LiveDesignStart, TokenKind::Whitespace)
+                }
+                _ => self.next_token(RustState::default(), cursor),
+            },
+            RustState::LiveDesign(depth, state) => {
+                let start = cursor.index;
+                let (state, kind) = LiveLanguage.next_token(state, cursor);
+                if kind != TokenKind::Delimiter {
+                    return (RustState::LiveDesign(depth, state), kind);
+                }
+                // `{{RustType}}` opens and closes two braces, which leaves the depth as it was
+                let delimiter = &cursor.string[start..cursor.index];
+                let depth = (depth + delimiter.matches('{').count())
+                    .saturating_sub(delimiter.matches('}').count());
+                if depth == 0 {
+                    return (RustState::default(), kind);
+                }
+                (RustState::LiveDesign(depth, state), kind)
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum State {
     Initial(InitialState),
@@ -73,33 +211,20 @@ pub enum State {
     RawDoubleQuotedStringTail(RawDoubleQuotedStringTailState),
 }
 
-impl Default for State {
-    fn default() -> State {
-        State::Initial(InitialState)
-    }
-}
-
 impl State {
-    pub fn next(self, cursor: &mut Cursor) -> (State, Option<Token>) {
-        if cursor.peek(0) == '\0' {
-            return (self, None);
-        }
-        let start = cursor.index;
-        let (next_state, kind) = match self {
+    fn next(self, cursor: &mut Cursor) -> (State, TokenKind) {
+        match self {
             State::Initial(state) => state.next(cursor),
             State::BlockCommentTail(state) => state.next(cursor),
             State::DoubleQuotedStringTail(state) => state.next(cursor),
             State::RawDoubleQuotedStringTail(state) => state.next(cursor),
-        };
-        let end = cursor.index;
-        assert!(start < end);
-        (
-            next_state,
-            Some(Token {
-                len: end - start,
-                kind,
-            }),
-        )
+        }
+    }
+}
+
+impl Default for State {
+    fn default() -> State {
+        State::Initial(InitialState)
     }
 }
 
@@ -481,6 +606,15 @@ impl<'a> Cursor<'a> {
         Cursor { string, index: 0 }
     }
 
+    fn rest(&self) -> &'a str {
+        &self.string[self.index..]
+    }
+
+    // only whitespace comes before the cursor on its line
+    fn is_at_line_start(&self) -> bool {
+        self.string[..self.index].chars().all(|char| char.is_whitespace())
+    }
+
     fn peek(&self, index: usize) -> char {
         self.string[self.index..].chars().nth(index).unwrap_or('\0')
     }
@@ -560,3 +694,193 @@ impl CharExt for char {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, TokenKind::*};
+
+    // the tokens of each line with their text, leaving out whitespace
+    fn tokenize(extension: &str, text: &str) -> Vec<Vec<(std::string::String, TokenKind)>> {
+        let text = Text::from(text);
+        let lines = text.as_lines();
+        let mut tokens = vec![Vec::new(); lines.len()];
+        Tokenizer::for_extension(extension, lines.len()).update(&text, &mut tokens);
+        lines
+            .iter()
+            .zip(tokens)
+            .map(|(line, tokens)| {
+                let mut start = 0;
+                let mut line_tokens = Vec::new();
+                for token in tokens {
+                    if token.kind != Whitespace {
+                        line_tokens.push((line[start..start + token.len].to_string(), token.kind));
+                    }
+                    start += token.len;
+                }
+                line_tokens
+            })
+            .collect()
+    }
+
+    fn line(tokens: &[(&str, TokenKind)]) -> Vec<(std::string::String, TokenKind)> {
+        tokens
+            .iter()
+            .map(|(text, kind)| (text.to_string(), *kind))
+            .collect()
+    }
+
+    #[test]
+    fn tokenizes_toml() {
+        let tokens = tokenize(
+            "toml",
+            "[package]\nname = \"app\" # the name\nv.x = [1, true]\ndoc = \"\"\"\nline\n\"\"\"",
+        );
+        assert_eq!(tokens[0], line(&[("[package]", Typename)]));
+        assert_eq!(
+            tokens[1],
+            line(&[
+                ("name", Identifier),
+                ("=", Punctuator),
+                ("\"app\"", String),
+                ("# the name", Comment)
+            ])
+        );
+        assert_eq!(
+            tokens[2],
+            line(&[
+                ("v", Identifier),
+                (".", Punctuator),
+                ("x", Identifier),
+                ("=", Punctuator),
+                ("[", Delimiter),
+                ("1", Number),
+                (",", Punctuator),
+                ("true", Constant),
+                ("]", Delimiter)
+            ])
+        );
+        // the multiline string carries over to the next lines
+        assert_eq!(tokens[3][2], ("\"\"\"".to_string(), String));
+        assert_eq!(tokens[4], line(&[("line", String)]));
+        assert_eq!(tokens[5], line(&[("\"\"\"", String)]));
+    }
+
+    #[test]
+    fn tokenizes_json() {
+        let tokens = tokenize("json", "{\"a\": [-1.5e3, \"b\\\"\", null],\n\"c\": x}");
+        assert_eq!(
+            tokens[0],
+            line(&[
+                ("{", Delimiter),
+                ("\"a\"", Identifier),
+                (":", Punctuator),
+                ("[", Delimiter),
+                ("-1.5e3", Number),
+                (",", Punctuator),
+                ("\"b\\\"\"", String),
+                (",", Punctuator),
+                ("null", Constant),
+                ("]", Delimiter),
+                (",", Punctuator)
+            ])
+        );
+        assert_eq!(
+            tokens[1],
+            line(&[
+                ("\"c\"", Identifier),
+                (":", Punctuator),
+                ("x", Unknown),
+                ("}", Delimiter)
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenizes_markdown() {
+        let tokens = tokenize(
+            "md",
+            "# Title\n- a `b` **c** [d](e)\n```rust\n# not a heading\n```\n<!-- a\nb -->",
+        );
+        assert_eq!(tokens[0], line(&[("# Title", Typename)]));
+        assert_eq!(
+            tokens[1],
+            line(&[
+                ("-", Punctuator),
+                ("a", Identifier),
+                ("`b`", String),
+                ("**c**", Constant),
+                ("[", Delimiter),
+                ("d", Identifier),
+                ("]", Delimiter),
+                ("(e)", String)
+            ])
+        );
+        // fenced code and html comments carry over to the next lines
+        assert_eq!(tokens[2], line(&[("```rust", Punctuator)]));
+        assert_eq!(tokens[3], line(&[("# not a heading", String)]));
+        assert_eq!(tokens[4], line(&[("```", Punctuator)]));
+        assert_eq!(tokens[5], line(&[("<!-- a", Comment)]));
+        assert_eq!(tokens[6], line(&[("b -->", Comment)]));
+    }
+
+    #[test]
+    fn tokenizes_live() {
+        let tokens = tokenize(
+            "live",
+            "App = {{App}} {\n    color: #f00, /* a\n    */ width: 1.5\n    <View> {}\n}",
+        );
+        assert_eq!(
+            tokens[0],
+            line(&[
+                ("App", Typename),
+                ("=", Punctuator),
+                ("{{", Delimiter),
+                ("App", Typename),
+                ("}}", Delimiter),
+                ("{", Delimiter)
+            ])
+        );
+        assert_eq!(
+            tokens[1],
+            line(&[
+                ("color", Identifier),
+                (":", Punctuator),
+                ("#f00", Number),
+                (",", Punctuator),
+                ("/* a", Comment)
+            ])
+        );
+        // the block comment carries over to the next line
+        assert_eq!(
+            tokens[2],
+            line(&[
+                ("    */", Comment),
+                ("width", Identifier),
+                (":", Punctuator),
+                ("1.5", Number)
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenizes_live_design_blocks_in_rust() {
+        let tokens = tokenize(
+            "rs",
+            "live_design! {\n    a = {{A}} { b: {c: #fff}}\n}\nfn f() {}",
+        );
+        assert_eq!(
+            tokens[0],
+            line(&[
+                ("live_design", Identifier),
+                ("!", Punctuator),
+                ("{", Delimiter)
+            ])
+        );
+        // `#fff` is a color in live, not an attribute
+        assert_eq!(tokens[1][11], ("#fff".to_string(), Number));
+        assert_eq!(tokens[2], line(&[("}", Delimiter)]));
+        // after the block it is Rust again
+        assert_eq!(tokens[3][0], ("fn".to_string(), OtherKeyword));
+        assert_eq!(tokens[3][1], ("f".to_string(), Function));
+    }
+}
@@ -0,0 +1,83 @@
+use {
+    super::{Cursor, Language},
+    crate::token::TokenKind,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct JsonLanguage;
+
+impl Language for JsonLanguage {
+    // no json token spans more than one line
+    type State = ();
+
+    fn next_token(&self, _state: (), cursor: &mut Cursor) -> ((), TokenKind) {
+        let kind = match cursor.peek(0) {
+            '"' => string(cursor),
+            '{' | '}' | '[' | ']' => {
+                cursor.skip(1);
+                TokenKind::Delimiter
+            }
+            ',' | ':' => {
+                cursor.skip(1);
+                TokenKind::Punctuator
+            }
+            '-' | '0'..='9' => number(cursor),
+            char if char.is_alphabetic() => {
+                let start = cursor.index;
+                while cursor.skip_if(|char| char.is_alphanumeric()) {}
+                match &cursor.string[start..cursor.index] {
+                    "true" | "false" | "null" => TokenKind::Constant,
+                    _ => TokenKind::Unknown,
+                }
+            }
+            char if char.is_whitespace() => {
+                while cursor.skip_if(|char| char.is_whitespace()) {}
+                TokenKind::Whitespace
+            }
+            _ => {
+                cursor.skip(1);
+                TokenKind::Unknown
+            }
+        };
+        ((), kind)
+    }
+}
+
+// object keys are colored as identifiers, all other strings as strings
+fn string(cursor: &mut Cursor) -> TokenKind {
+    debug_assert!(cursor.peek(0) == '"');
+    cursor.skip(1);
+    loop {
+        match (cursor.peek(0), cursor.peek(1)) {
+            ('"', _) => {
+                cursor.skip(1);
+                break;
+            }
+            ('\0', _) => return TokenKind::String,
+            ('\\', '"') | ('\\', '\\') => cursor.skip(2),
+            _ => cursor.skip(1),
+        }
+    }
+    if cursor.rest().trim_start().starts_with(':') {
+        TokenKind::Identifier
+    } else {
+        TokenKind::String
+    }
+}
+
+fn number(cursor: &mut Cursor) -> TokenKind {
+    cursor.skip_if(|char| char == '-');
+    if !cursor.skip_digits(10) {
+        return TokenKind::Unknown;
+    }
+    if cursor.peek(0) == '.' {
+        cursor.skip(1);
+        if !cursor.skip_digits(10) {
+            return TokenKind::Unknown;
+        }
+    }
+    if (cursor.peek(0) == 'e' || cursor.peek(0) == 'E') && !cursor.skip_exponent() {
+        return TokenKind::Unknown;
+    }
+    TokenKind::Number
+}
@@ -0,0 +1,154 @@
+use {
+    super::{CharExt, Cursor, Language},
+    crate::token::TokenKind,
+};
+
+/// The DSL inside `live_design!`: properties, `<Component>` and `{{RustType}}` references,
+/// `#rgb` colors and the shader language.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct LiveLanguage;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum LiveState {
+    #[default]
+    Initial,
+    BlockComment(usize),
+}
+
+impl Language for LiveLanguage {
+    type State = LiveState;
+
+    fn next_token(&self, state: LiveState, cursor: &mut Cursor) -> (LiveState, TokenKind) {
+        if let LiveState::BlockComment(depth) = state {
+            return block_comment_tail(cursor, depth);
+        }
+        let kind = match (cursor.peek(0), cursor.peek(1)) {
+            ('/', '/') => {
+                while cursor.skip_if(|char| char != '\0') {}
+                TokenKind::Comment
+            }
+            ('/', '*') => {
+                cursor.skip(2);
+                return block_comment_tail(cursor, 0);
+            }
+            ('"', _) => {
+                cursor.skip(1);
+                loop {
+                    match (cursor.peek(0), cursor.peek(1)) {
+                        ('"', _) => {
+                            cursor.skip(1);
+                            break;
+                        }
+                        ('\0', _) => break,
+                        ('\\', '"') | ('\\', '\\') => cursor.skip(2),
+                        _ => cursor.skip(1),
+                    }
+                }
+                TokenKind::String
+            }
+            ('#', char) if char.is_ascii_hexdigit() || char == 'x' => {
+                cursor.skip(1);
+                while cursor.skip_if(|char| char.is_ascii_alphanumeric()) {}
+                TokenKind::Number
+            }
+            ('{', '{') | ('}', '}') => {
+                cursor.skip(2);
+                TokenKind::Delimiter
+            }
+            ('(', _) | (')', _) | ('[', _) | (']', _) | ('{', _) | ('}', _) => {
+                cursor.skip(1);
+                TokenKind::Delimiter
+            }
+            ('!', '=')
+            | ('&', '&')
+            | ('*', '=')
+            | ('+', '=')
+            | ('-', '=')
+            | ('-', '>')
+            | ('/', '=')
+            | (':', ':')
+            | ('<', '=')
+            | ('=', '=')
+            | ('>', '=')
+            | ('|', '|') => {
+                cursor.skip(2);
+                TokenKind::Punctuator
+            }
+            ('.', char) if char.is_ascii_digit() => number(cursor),
+            (char, _) if char.is_ascii_digit() => number(cursor),
+            (char, _) if char.is_identifier_start() => identifier_or_keyword(cursor),
+            (char, _) if char.is_whitespace() => {
+                while cursor.skip_if(|char| char.is_whitespace()) {}
+                TokenKind::Whitespace
+            }
+            (char, _) if char.is_ascii_punctuation() => {
+                cursor.skip(1);
+                TokenKind::Punctuator
+            }
+            _ => {
+                cursor.skip(1);
+                TokenKind::Unknown
+            }
+        };
+        (LiveState::Initial, kind)
+    }
+}
+
+fn block_comment_tail(cursor: &mut Cursor, mut depth: usize) -> (LiveState, TokenKind) {
+    loop {
+        match (cursor.peek(0), cursor.peek(1)) {
+            ('/', '*') => {
+                cursor.skip(2);
+                depth += 1;
+            }
+            ('*', '/') => {
+                cursor.skip(2);
+                if depth == 0 {
+                    break (LiveState::Initial, TokenKind::Comment);
+                }
+                depth -= 1;
+            }
+            ('\0', _) => break (LiveState::BlockComment(depth), TokenKind::Comment),
+            _ => cursor.skip(1),
+        }
+    }
+}
+
+fn number(cursor: &mut Cursor) -> TokenKind {
+    cursor.skip_digits(10);
+    if cursor.peek(0) == '.' && cursor.peek(1) != '.' {
+        cursor.skip(1);
+        cursor.skip_digits(10);
+    }
+    if (cursor.peek(0) == 'e' || cursor.peek(0) == 'E') && !cursor.skip_exponent() {
+        return TokenKind::Unknown;
+    }
+    TokenKind::Number
+}
+
+fn identifier_or_keyword(cursor: &mut Cursor) -> TokenKind {
+    let start = cursor.index;
+    cursor.skip(1);
+    while cursor.skip_if(|char| char.is_identifier_continue()) {}
+    let string = &cursor.string[start..cursor.index];
+    match string {
+        "else" | "if" | "match" | "return" => TokenKind::BranchKeyword,
+        "break" | "continue" | "for" | "loop" | "while" => TokenKind::LoopKeyword,
+        "const" | "false" | "fn" | "import" | "in" | "instance" | "let" | "self" | "texture"
+        | "true" | "uniform" | "use" | "var" | "varying" | "bool" | "float" | "int" | "vec2"
+        | "vec3" | "vec4" | "mat2" | "mat3" | "mat4" | "f32" | "f64" => TokenKind::OtherKeyword,
+        _ => {
+            let mut chars = string.chars();
+            if chars.next().unwrap().is_uppercase() {
+                match chars.next() {
+                    Some(char) if char.is_uppercase() => TokenKind::Constant,
+                    _ => TokenKind::Typename,
+                }
+            } else if cursor.peek(0) == '(' {
+                TokenKind::Function
+            } else {
+                TokenKind::Identifier
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use {
+    super::{Cursor, Language},
+    crate::token::TokenKind,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct MarkdownLanguage;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum MarkdownState {
+    #[default]
+    Initial,
+    // inside a fenced code block, with the fence char and how many of them opened it
+    FencedCode(char, usize),
+    HtmlComment,
+}
+
+impl Language for MarkdownLanguage {
+    type State = MarkdownState;
+
+    fn next_token(&self, state: MarkdownState, cursor: &mut Cursor) -> (MarkdownState, TokenKind) {
+        match state {
+            MarkdownState::Initial => initial(cursor),
+            MarkdownState::FencedCode(fence, len) => {
+                if cursor.is_at_line_start() && fence_len(cursor, fence) >= len {
+                    while cursor.skip_if(|char| char != '\0') {}
+                    return (MarkdownState::Initial, TokenKind::Punctuator);
+                }
+                while cursor.skip_if(|char| char != '\0') {}
+                (state, TokenKind::String)
+            }
+            MarkdownState::HtmlComment => html_comment_tail(cursor),
+        }
+    }
+}
+
+fn initial(cursor: &mut Cursor) -> (MarkdownState, TokenKind) {
+    let char = cursor.peek(0);
+    if char.is_whitespace() {
+        while cursor.skip_if(|char| char.is_whitespace()) {}
+        return (MarkdownState::Initial, TokenKind::Whitespace);
+    }
+    if cursor.is_at_line_start() {
+        if let Some(state_and_kind) = block_start(cursor) {
+            return state_and_kind;
+        }
+    }
+    let kind = match (char, cursor.peek(1)) {
+        ('<', '!') if cursor.rest().starts_with("<!--") => {
+            cursor.skip(4);
+            return html_comment_tail(cursor);
+        }
+        ('`', _) => inline_code(cursor),
+        ('*', _) => emphasis(cursor),
+        ('!', '[') | ('[', _) | (']', _) => {
+            cursor.skip(1);
+            TokenKind::Delimiter
+        }
+        // the target of a [link](target)
+        ('(', _) if cursor.string[..cursor.index].ends_with(']') => {
+            while cursor.skip_if(|char| char != ')' && char != '\0') {}
+            cursor.skip_if(|char| char == ')');
+            TokenKind::String
+        }
+        ('\\', _) => {
+            cursor.skip(2);
+            TokenKind::Identifier
+        }
+        _ => {
+            cursor.skip(1);
+            while cursor.skip_if(|char| !char.is_whitespace() && !"`*[]!(<\\\0".contains(char)) {}
+            TokenKind::Identifier
+        }
+    };
+    (MarkdownState::Initial, kind)
+}
+
+// headings, fences, quotes, list markers and rules, which only count at the start of a line
+fn block_start(cursor: &mut Cursor) -> Option<(MarkdownState, TokenKind)> {
+    let rest = cursor.rest();
+    for fence in ['`', '~'] {
+        let len = fence_len(cursor, fence);
+        if len >= 3 {
+            while cursor.skip_if(|char| char != '\0') {}
+            return Some((MarkdownState::FencedCode(fence, len), TokenKind::Punctuator));
+        }
+    }
+    if rest.starts_with('#') {
+        let level = rest.chars().take_while(|char| *char == '#').count();
+        if level <= 6 && rest[level..].chars().next().map_or(true, |char| char == ' ') {
+            while cursor.skip_if(|char| char != '\0') {}
+            return Some((MarkdownState::Initial, TokenKind::Typename));
+        }
+    }
+    let marks: String = rest.chars().filter(|char| !char.is_whitespace()).collect();
+    if marks.len() >= 3 && ['-', '*', '_'].iter().any(|mark| marks.chars().all(|char| char == *mark)) {
+        while cursor.skip_if(|char| char != '\0') {}
+        return Some((MarkdownState::Initial, TokenKind::Punctuator));
+    }
+    if rest.starts_with('>') {
+        cursor.skip(1);
+        return Some((MarkdownState::Initial, TokenKind::Comment));
+    }
+    if rest.starts_with("- ") || rest.starts_with("* ") || rest.starts_with("+ ") {
+        cursor.skip(1);
+        return Some((MarkdownState::Initial, TokenKind::Punctuator));
+    }
+    let digits = rest.chars().take_while(|char| char.is_ascii_digit()).count();
+    if digits > 0 && (rest[digits..].starts_with(". ") || rest[digits..].starts_with(") ")) {
+        cursor.skip(digits + 1);
+        return Some((MarkdownState::Initial, TokenKind::Punctuator));
+    }
+    None
+}
+
+fn fence_len(cursor: &Cursor, fence: char) -> usize {
+    cursor
+        .rest()
+        .trim_start()
+        .chars()
+        .take_while(|char| *char == fence)
+        .count()
+}
+
+fn html_comment_tail(cursor: &mut Cursor) -> (MarkdownState, TokenKind) {
+    match cursor.rest().find("-->") {
+        Some(index) => {
+            cursor.skip(cursor.rest()[..index + 3].chars().count());
+            (MarkdownState::Initial, TokenKind::Comment)
+        }
+        None => {
+            while cursor.skip_if(|char| char != '\0') {}
+            (MarkdownState::HtmlComment, TokenKind::Comment)
+        }
+    }
+}
+
+// `code` spans end at a run of as many backticks as they started with
+fn inline_code(cursor: &mut Cursor) -> TokenKind {
+    let len = cursor.rest().chars().take_while(|char| *char == '`').count();
+    let fence = &cursor.rest()[..len];
+    match cursor.rest()[len..].find(fence) {
+        Some(index) => {
+            cursor.skip(len + cursor.rest()[len..len + index].chars().count() + len);
+            TokenKind::String
+        }
+        None => {
+            cursor.skip(len);
+            TokenKind::Punctuator
+        }
+    }
+}
+
+// *emphasis* and **strong** spans that close on the same line
+fn emphasis(cursor: &mut Cursor) -> TokenKind {
+    let len = cursor.rest().chars().take_while(|char| *char == '*').count();
+    let marks = &cursor.rest()[..len];
+    match cursor.rest()[len..].find(marks) {
+        Some(index) if index > 0 => {
+            cursor.skip(len + cursor.rest()[len..len + index].chars().count() + len);
+            TokenKind::Constant
+        }
+        _ => {
+            cursor.skip(len);
+            TokenKind::Punctuator
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use {
+    super::{Cursor, Language},
+    crate::token::TokenKind,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TomlLanguage;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum TomlState {
+    #[default]
+    Initial,
+    // inside a """ or ''' string, with its quote char
+    MultilineString(char),
+}
+
+impl Language for TomlLanguage {
+    type State = TomlState;
+
+    fn next_token(&self, state: TomlState, cursor: &mut Cursor) -> (TomlState, TokenKind) {
+        if let TomlState::MultilineString(quote) = state {
+            return multiline_string_tail(cursor, quote);
+        }
+        let kind = match (cursor.peek(0), cursor.peek(1), cursor.peek(2)) {
+            ('#', _, _) => {
+                while cursor.skip_if(|char| char != '\0') {}
+                TokenKind::Comment
+            }
+            ('"', '"', '"') | ('\'', '\'', '\'') => {
+                let quote = cursor.peek(0);
+                cursor.skip(3);
+                return multiline_string_tail(cursor, quote);
+            }
+            ('"', _, _) | ('\'', _, _) => string(cursor),
+            ('[', _, _) if cursor.is_at_line_start() => {
+                // a [table] or [[array.of.tables]] header
+                while cursor.skip_if(|char| char != ']' && char != '\0') {}
+                while cursor.skip_if(|char| char == ']') {}
+                TokenKind::Typename
+            }
+            ('[', _, _) | (']', _, _) | ('{', _, _) | ('}', _, _) => {
+                cursor.skip(1);
+                TokenKind::Delimiter
+            }
+            ('=', _, _) | (',', _, _) | ('.', _, _) => {
+                cursor.skip(1);
+                TokenKind::Punctuator
+            }
+            (char, _, _) if is_bare_key(char) || char == '+' => bare_key_or_value(cursor),
+            (char, _, _) if char.is_whitespace() => {
+                while cursor.skip_if(|char| char.is_whitespace()) {}
+                TokenKind::Whitespace
+            }
+            _ => {
+                cursor.skip(1);
+                TokenKind::Unknown
+            }
+        };
+        (TomlState::Initial, kind)
+    }
+}
+
+fn is_bare_key(char: char) -> bool {
+    char.is_ascii_alphanumeric() || char == '_' || char == '-'
+}
+
+fn is_key(cursor: &Cursor) -> bool {
+    let rest = cursor.rest().trim_start();
+    rest.starts_with('=') || rest.starts_with('.')
+}
+
+fn string(cursor: &mut Cursor) -> TokenKind {
+    let quote = cursor.peek(0);
+    cursor.skip(1);
+    loop {
+        match (cursor.peek(0), cursor.peek(1)) {
+            (char, _) if char == quote => {
+                cursor.skip(1);
+                break;
+            }
+            ('\0', _) => return TokenKind::String,
+            // literal strings have no escapes
+            ('\\', '"') | ('\\', '\\') if quote == '"' => cursor.skip(2),
+            _ => cursor.skip(1),
+        }
+    }
+    if is_key(cursor) {
+        TokenKind::Identifier
+    } else {
+        TokenKind::String
+    }
+}
+
+fn multiline_string_tail(cursor: &mut Cursor, quote: char) -> (TomlState, TokenKind) {
+    loop {
+        match (cursor.peek(0), cursor.peek(1), cursor.peek(2)) {
+            (a, b, c) if a == quote && b == quote && c == quote => {
+                cursor.skip(3);
+                // up to two quotes can directly precede the closing ones
+                cursor.skip_if(|char| char == quote);
+                cursor.skip_if(|char| char == quote);
+                break (TomlState::Initial, TokenKind::String);
+            }
+            ('\0', _, _) => break (TomlState::MultilineString(quote), TokenKind::String),
+            ('\\', _, _) if quote == '"' => cursor.skip(2),
+            _ => cursor.skip(1),
+        }
+    }
+}
+
+// keys, numbers, dates and booleans all start out as bare words
+fn bare_key_or_value(cursor: &mut Cursor) -> TokenKind {
+    let start = cursor.index;
+    let first = cursor.peek(0);
+    if first.is_ascii_digit() || first == '+' || first == '-' {
+        // floats, dates and times also contain '.', ':' and '+'
+        while cursor.skip_if(|char| is_bare_key(char) || matches!(char, '.' | ':' | '+')) {}
+    } else {
+        while cursor.skip_if(is_bare_key) {}
+    }
+    if is_key(cursor) {
+        return TokenKind::Identifier;
+    }
+    match &cursor.string[start..cursor.index] {
+        "true" | "false" => TokenKind::Constant,
+        "inf" | "nan" | "+inf" | "-inf" | "+nan" | "-nan" => TokenKind::Number,
+        _ if first.is_ascii_digit() || first == '+' || first == '-' => TokenKind::Number,
+        _ => TokenKind::Unknown,
+    }
+}
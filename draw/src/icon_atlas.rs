@@ -13,10 +13,11 @@ pub use {
         turtle::{Walk, Layout},
         draw_list_2d::{ManyInstances, DrawList2d, RedrawingApi},
         geometry::GeometryQuad2D,
-        makepad_vector::trapezoidator::Trapezoidator,
+        makepad_vector::trapezoidator::{Trapezoidator, FillRule},
         makepad_vector::geometry::{AffineTransformation, Transform, Vector, Point},
         makepad_vector::internal_iter::*,
         makepad_vector::path::{PathIterator, PathCommand},
+        svg::{SvgDocument, parse_svg_path},
     }
 };
 
@@ -37,10 +38,16 @@ pub struct CxIconEntry {
 
 struct CxIconPathCommands {
     bounds: Rect,
+    parts: Vec<CxIconPathPart>
+}
+
+/// Every part is trapezoidated separately, as svg shapes can each have their own fill rule
+struct CxIconPathPart {
+    fill_rule: FillRule,
     path: Vec<PathCommand>
 }
 
-impl<'a> InternalIterator for &CxIconPathCommands {
+impl<'a> InternalIterator for &CxIconPathPart {
     type Item = PathCommand;
     fn for_each<F>(self, f: &mut F) -> bool
     where
@@ -123,47 +130,65 @@ impl CxIconAtlas {
     pub fn parse_and_cache_path(&mut self, path_hash: CxIconPathHash, path: &[u8]) -> Option<(CxIconPathHash, Rect)> {
         match parse_svg_path(path) {
             Ok(path) => {
-                let mut min = dvec2(f64::INFINITY, f64::INFINITY);
-                let mut max = dvec2(-f64::INFINITY, -f64::INFINITY);
-                fn bound(p: &Point, min: &mut DVec2, max: &mut DVec2) {
-                    if p.x < min.x {min.x = p.x}
-                    if p.y < min.y {min.y = p.y}
-                    if p.x > max.x {max.x = p.x}
-                    if p.y > max.y {max.y = p.y}
-                }
-                for cmd in &path {
-                    match cmd {
-                        PathCommand::MoveTo(p) => {bound(p, &mut min, &mut max)},
-                        PathCommand::LineTo(p) => {bound(p, &mut min, &mut max)},
-                        PathCommand::ArcTo(e, r, _, _, _) => {
-                            // TODO: this is pretty rough
-                            bound(&Point{x: e.x + r.x, y: e.y + r.y}, &mut min, &mut max);
-                            bound(&Point{x: e.x - r.x, y: e.y - r.y}, &mut min, &mut max);
-                        },
-                        PathCommand::QuadraticTo(p1, p) => {
-                            bound(p1, &mut min, &mut max);
-                            bound(p, &mut min, &mut max);
-                        },
-                        PathCommand::CubicTo(p1, p2, p) => {
-                            bound(p1, &mut min, &mut max);
-                            bound(p2, &mut min, &mut max);
-                            bound(p, &mut min, &mut max);
-                        },
-                        PathCommand::Close => ()
-                    }
-                }
-                let bounds = Rect {pos: min, size: max - min};
-                self.paths.insert(path_hash, CxIconPathCommands {
-                    bounds,
-                    path
-                });
-                return Some((path_hash, bounds));
+                Some(self.cache_path_parts(path_hash, vec![CxIconPathPart {fill_rule: FillRule::NonZero, path}]))
             }
             Err(e) => {
                 log!("Error in SVG Path {}", e);
-                return None
+                None
+            }
+        }
+    }
+    
+    pub fn parse_and_cache_svg(&mut self, path_hash: CxIconPathHash, data: &str) -> Result<(CxIconPathHash, Rect), String> {
+        let doc = SvgDocument::parse(data) ?;
+        // icons are drawn in a single color, so we only need the outlines of the fills and strokes
+        let tolerance = doc.view_box.size.x.max(doc.view_box.size.y).max(1.0) / 1000.0;
+        let mut parts = Vec::new();
+        for (fill_rule, path) in doc.outlines(tolerance) {
+            parts.push(CxIconPathPart {fill_rule, path});
+        }
+        if parts.is_empty() {
+            return Err("No shapes found".to_string())
+        }
+        Ok(self.cache_path_parts(path_hash, parts))
+    }
+    
+    fn cache_path_parts(&mut self, path_hash: CxIconPathHash, parts: Vec<CxIconPathPart>) -> (CxIconPathHash, Rect) {
+        let mut min = dvec2(f64::INFINITY, f64::INFINITY);
+        let mut max = dvec2(-f64::INFINITY, -f64::INFINITY);
+        fn bound(p: &Point, min: &mut DVec2, max: &mut DVec2) {
+            if p.x < min.x {min.x = p.x}
+            if p.y < min.y {min.y = p.y}
+            if p.x > max.x {max.x = p.x}
+            if p.y > max.y {max.y = p.y}
+        }
+        for cmd in parts.iter().flat_map( | part | part.path.iter()) {
+            match cmd {
+                PathCommand::MoveTo(p) => {bound(p, &mut min, &mut max)},
+                PathCommand::LineTo(p) => {bound(p, &mut min, &mut max)},
+                PathCommand::ArcTo(e, r, _, _, _) => {
+                    // TODO: this is pretty rough
+                    bound(&Point{x: e.x + r.x, y: e.y + r.y}, &mut min, &mut max);
+                    bound(&Point{x: e.x - r.x, y: e.y - r.y}, &mut min, &mut max);
+                },
+                PathCommand::QuadraticTo(p1, p) => {
+                    bound(p1, &mut min, &mut max);
+                    bound(p, &mut min, &mut max);
+                },
+                PathCommand::CubicTo(p1, p2, p) => {
+                    bound(p1, &mut min, &mut max);
+                    bound(p2, &mut min, &mut max);
+                    bound(p, &mut min, &mut max);
+                },
+                PathCommand::Close => ()
             }
         }
+        let bounds = Rect {pos: min, size: max - min};
+        self.paths.insert(path_hash, CxIconPathCommands {
+            bounds,
+            parts
+        });
+        (path_hash, bounds)
     }
     
     pub fn get_icon_bounds(&mut self, cx: &Cx, path_str: &Rc<String>, svg_dep: &Rc<String>) -> Option<(CxIconPathHash, Rect)> {
@@ -177,32 +202,16 @@ impl CxIconAtlas {
             }
             let path_hash = CxIconPathHash(LiveId(self.svg_deps.len() as u64));
             self.svg_deps.insert(svg_dep.as_str().to_string(), path_hash);
-            // lets parse the path range out of the svg file
             match cx.get_dependency(svg_dep.as_str()) {
                 Ok(data)=>{
-                    fn find_path_str(data:&[u8])->Option<&[u8]>{
-                        let pat = "path d=\"".as_bytes();
-                        'outer:for i in 0..data.len(){
-                            for j in 0..pat.len(){
-                                if data[i+j] != pat[j]{
-                                    continue 'outer;
-                                }
-                            }
-                            for k in i+pat.len()..data.len(){
-                                if data[k] == '\"' as u8{
-                                    return Some(&data[i+pat.len()..k])
-                                }
-                            }
+                    let data = String::from_utf8_lossy(&data);
+                    match self.parse_and_cache_svg(path_hash, &data) {
+                        Ok(result) => return Some(result),
+                        Err(err) => {
+                            println!("Error in SVG file {}: {}", svg_dep, err);
                             return None
                         }
-                        None
-                    }
-                    if let Some(data) = find_path_str(&data){
-                        return self.parse_and_cache_path(path_hash, data)
                     }
-                    println!("No SVG path tag found in svg file {}",path_str);
-                    return None
-                    
                 }
                 Err(_err)=>{
                     println!("Error in SVG file {}: {}",path_str, _err);
@@ -294,11 +303,12 @@ impl CxIconAtlas {
 impl DrawTrapezoidVector {
     // atlas drawing function used by CxAfterDraw
     fn draw_vector(&mut self, entry: &CxIconEntry, path: &CxIconPathCommands, many: &mut ManyInstances) {
-        let trapezoids = {
-            let mut trapezoids = Vec::new();
+        let mut trapezoids = Vec::new();
+        for part in &path.parts {
             //log_str(&format!("Serializing char {} {} {} {}", glyphtc.tx1 , cx.fonts_atlas.texture_size.x ,todo.subpixel_x_fract ,atlas_page.dpi_factor));
+            self.trapezoidator.set_fill_rule(part.fill_rule);
             let trapezoidate = self.trapezoidator.trapezoidate(
-                path.map({
+                part.map({
                     //log!("{:?} {:?}", entry.args, entry.pos);
                     move | cmd | {
                         let cmd = cmd.transform(
@@ -316,8 +326,7 @@ impl DrawTrapezoidVector {
                     trapezoidate
                 );
             }
-            trapezoids
-        };
+        }
         
         for trapezoid in trapezoids {
             self.a_xs = Vec2 {x: trapezoid.xs[0], y: trapezoid.xs[1]};
@@ -423,321 +432,3 @@ impl<'a> Cx2d<'a> {
     
     
 }
-
-fn parse_svg_path(path: &[u8]) -> Result<Vec<PathCommand>, String> {
-    #[derive(Debug)]
-    enum Cmd {
-        Unknown,
-        Move(bool),
-        Hor(bool),
-        Vert(bool),
-        Line(bool),
-        Arc(bool),
-        Cubic(bool),
-        CubicSmooth(bool),
-        Quadratic(bool),
-        Close
-    }
-    impl Default for Cmd {fn default() -> Self {Self::Unknown}}
-    
-    #[derive(Default)]
-    struct ParseState {
-        cmd: Cmd,
-        expect_nums: usize,
-        chain: bool,
-        nums: [f64; 7],
-        num_count: usize,
-        last_pt: Point,
-        last_ctl_pt: Option<Point>,
-        out: Vec<PathCommand>,
-        num_state: Option<NumState>
-    }
-    
-    #[derive(Debug)]
-    struct NumState {
-        num: f64,
-        mul: f64,
-        has_dot: bool,
-    }
-    
-    impl NumState {
-        fn new_pos(v: f64) -> Self {Self {num: v, mul: 1.0, has_dot: false}}
-        fn new_min() -> Self {Self {num: 0.0, mul: -1.0, has_dot: false}}
-        fn finalize(self) -> f64 {self.num * self.mul}
-        fn add_digit(&mut self, digit: f64) {
-            self.num *= 10.0;
-            self.num += digit;
-            if self.has_dot {
-                self.mul *= 0.1;
-            }
-        }
-    }
-    
-    impl ParseState {
-        fn next_cmd(&mut self, cmd: Cmd) -> Result<(), String> {
-            self.finalize_cmd() ?;
-            self.chain = false;
-            self.expect_nums = match cmd {
-                Cmd::Unknown => panic!(),
-                Cmd::Move(_) => 2,
-                Cmd::Hor(_) => 1,
-                Cmd::Vert(_) => 1,
-                Cmd::Line(_) => 2,
-                Cmd::Cubic(_) => 6,
-                Cmd::CubicSmooth(_) => 4,
-                Cmd::Arc(_) => 7,
-                Cmd::Quadratic(_) => 4,
-                Cmd::Close => 0
-            };
-            self.cmd = cmd;
-            Ok(())
-        }
-        
-        fn add_min(&mut self) -> Result<(), String> {
-            if self.num_state.is_some() {
-                self.finalize_num();
-            }
-            if self.expect_nums == self.num_count {
-                self.finalize_cmd() ?;
-            }
-            if self.expect_nums == 0 {
-                return Err(format!("Unexpected minus"));
-            }
-            self.num_state = Some(NumState::new_min());
-            Ok(())
-        }
-        
-        fn add_digit(&mut self, digit: f64) -> Result<(), String> {
-            if let Some(num_state) = &mut self.num_state {
-                num_state.add_digit(digit);
-            }
-            else {
-                if self.expect_nums == self.num_count {
-                    self.finalize_cmd() ?;
-                }
-                if self.expect_nums == 0 {
-                    return Err(format!("Unexpected digit"));
-                }
-                self.num_state = Some(NumState::new_pos(digit))
-            }
-            Ok(())
-        }
-        
-        fn add_dot(&mut self) -> Result<(), String> {
-            if let Some(num_state) = &mut self.num_state {
-                if num_state.has_dot {
-                    self.finalize_num();
-                    self.add_digit(0.0) ?;
-                    self.add_dot() ?;
-                    return Ok(());
-                }
-                num_state.has_dot = true;
-            }
-            else {
-                self.add_digit(0.0) ?;
-                self.add_dot() ?;
-            }
-            Ok(())
-        }
-        
-        fn finalize_num(&mut self) {
-            if let Some(num_state) = self.num_state.take() {
-                self.nums[self.num_count] = num_state.finalize();
-                self.num_count += 1;
-            }
-        }
-        
-        fn whitespace(&mut self) -> Result<(), String> {
-            self.finalize_num();
-            if self.expect_nums == self.num_count {
-                self.finalize_cmd() ?;
-            }
-            Ok(())
-        }
-        
-        fn finalize_cmd(&mut self) -> Result<(), String> {
-            self.finalize_num();
-            if self.chain && self.num_count == 0 {
-                return Ok(())
-            }
-            if self.expect_nums != self.num_count {
-                return Err(format!("SVG Path command {:?} expected {} points, got {}", self.cmd, self.expect_nums, self.num_count));
-            }
-            match self.cmd {
-                Cmd::Unknown => (),
-                Cmd::Move(abs) => {
-                    self.last_ctl_pt = None;
-                    if abs {
-                        self.last_pt = Point {x: self.nums[0], y: self.nums[1]};
-                    }
-                    else {
-                        self.last_pt += Vector {x: self.nums[0], y: self.nums[1]};
-                    }
-                    self.out.push(PathCommand::MoveTo(self.last_pt));
-                },
-                Cmd::Hor(abs) => {
-                    self.last_ctl_pt = None;
-                    if abs {
-                        self.last_pt = Point {x: self.nums[0], y: self.last_pt.y};
-                    }
-                    else {
-                        self.last_pt += Vector {x: self.nums[0], y: 0.0};
-                    }
-                    self.out.push(PathCommand::LineTo(self.last_pt));
-                }
-                Cmd::Vert(abs) => {
-                    self.last_ctl_pt = None;
-                    if abs {
-                        self.last_pt = Point {x: self.last_pt.x, y: self.nums[0]};
-                    }
-                    else {
-                        self.last_pt += Vector {x: 0.0, y: self.nums[0]};
-                    }
-                    self.out.push(PathCommand::LineTo(self.last_pt));
-                }
-                Cmd::Line(abs) => {
-                    self.last_ctl_pt = None;
-                    if abs {
-                        self.last_pt = Point {x: self.nums[0], y: self.nums[1]};
-                    }
-                    else {
-                        self.last_pt += Vector {x: self.nums[0], y: self.nums[1]};
-                    }
-                    self.out.push(PathCommand::LineTo(self.last_pt));
-                },
-                Cmd::Cubic(abs) => {
-                    if abs {
-                        self.last_pt = Point {x: self.nums[4], y: self.nums[5]};
-                        self.out.push(PathCommand::CubicTo(
-                            Point {x: self.nums[0], y: self.nums[1]},
-                            Point {x: self.nums[2], y: self.nums[3]},
-                            self.last_pt,
-                        ));
-                        self.last_ctl_pt = Some(Point {x: self.nums[2], y: self.nums[3]});
-                    } else {
-                        self.out.push(PathCommand::CubicTo(
-                            self.last_pt + Vector {x: self.nums[0], y: self.nums[1]},
-                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]},
-                            self.last_pt + Vector {x: self.nums[4], y: self.nums[5]},
-                        ));
-                        self.last_ctl_pt = Some(
-                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]});
-                        self.last_pt += Vector {x: self.nums[4], y: self.nums[5]};
-                    }
-                },
-                Cmd::CubicSmooth(abs) => {
-                    // Calculate absolute x1, y1 for control point 1
-                    let a_cp1 = if self.last_ctl_pt.is_none() {
-                        self.last_pt
-                    } else {
-                        self.last_pt + (self.last_pt - self.last_ctl_pt.unwrap())
-                    };
-
-                    // Transform s command into c command
-                    let mut tmp = [0.0; 4];
-                    tmp.copy_from_slice(&self.nums[0..4]);
-                    self.nums[2..6].copy_from_slice(&tmp);
-
-                    if abs {
-                        self.nums[0..2].copy_from_slice(&[a_cp1.x, a_cp1.y]);
-                        self.last_pt = Point {x: self.nums[4], y: self.nums[5]};
-                        self.out.push(PathCommand::CubicTo(
-                            Point {x: self.nums[0], y: self.nums[1]},
-                            Point {x: self.nums[2], y: self.nums[3]},
-                            self.last_pt,
-                        ));
-                        self.last_ctl_pt = Some(Point {x: self.nums[2], y: self.nums[3]});
-                    } else {
-                        let r_cp1 = a_cp1 - self.last_pt;
-                        self.nums[0..2].copy_from_slice(&[r_cp1.x, r_cp1.y]);
-                        self.out.push(PathCommand::CubicTo(
-                            self.last_pt + Vector {x: self.nums[0], y: self.nums[1]},
-                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]},
-                            self.last_pt + Vector {x: self.nums[4], y: self.nums[5]},
-                        ));
-                        self.last_ctl_pt = Some(
-                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]});
-                        self.last_pt += Vector {x: self.nums[4], y: self.nums[5]};
-                    }
-                },
-                Cmd::Arc(abs) => {
-                    if abs {
-                        self.last_pt = Point {x: self.nums[5], y: self.nums[6]};
-                        self.out.push(PathCommand::ArcTo(
-                            self.last_pt,
-                            Point {x: self.nums[0], y: self.nums[1]},
-                            self.nums[2],
-                            self.nums[3] != 0.0,
-                            self.nums[4] != 0.0,
-                        ));
-                    }
-                    else {
-                        self.out.push(PathCommand::ArcTo(
-                            self.last_pt + Vector {x: self.nums[5], y: self.nums[6]},
-                            Point {x: self.nums[0], y: self.nums[1]},
-                            self.nums[2],
-                            self.nums[3] != 0.0,
-                            self.nums[4] != 0.0,
-                        ));
-                        self.last_pt += Vector {x: self.nums[5], y: self.nums[6]};
-                    }
-                },
-                Cmd::Quadratic(abs) => {
-                    if abs {
-                        self.last_pt = Point {x: self.nums[2], y: self.nums[3]};
-                        self.out.push(PathCommand::QuadraticTo(
-                            Point {x: self.nums[0], y: self.nums[1]},
-                            self.last_pt
-                        ));
-                    }
-                    else {
-                        self.out.push(PathCommand::QuadraticTo(
-                            self.last_pt + Vector {x: self.nums[0], y: self.nums[1]},
-                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]},
-                        ));
-                        self.last_pt += Vector {x: self.nums[2], y: self.nums[3]};
-                    }
-                }
-                Cmd::Close => {
-                    self.out.push(PathCommand::Close);
-                }
-            }
-            self.num_count = 0;
-            self.chain = true;
-            Ok(())
-        }
-    }
-    
-    let mut state = ParseState::default();
-    for i in 0..path.len() {
-        match path[i] {
-            b'M' => state.next_cmd(Cmd::Move(true)) ?,
-            b'm' => state.next_cmd(Cmd::Move(false)) ?,
-            b'Q' => state.next_cmd(Cmd::Quadratic(true)) ?,
-            b'q' => state.next_cmd(Cmd::Quadratic(false)) ?,
-            b'C' => state.next_cmd(Cmd::Cubic(true)) ?,
-            b'c' => state.next_cmd(Cmd::Cubic(false)) ?,
-            b'S' => state.next_cmd(Cmd::CubicSmooth(true)) ?,
-            b's' => state.next_cmd(Cmd::CubicSmooth(false)) ?,
-            b'H' => state.next_cmd(Cmd::Hor(true)) ?,
-            b'h' => state.next_cmd(Cmd::Hor(false)) ?,
-            b'V' => state.next_cmd(Cmd::Vert(true)) ?,
-            b'v' => state.next_cmd(Cmd::Vert(false)) ?,
-            b'L' => state.next_cmd(Cmd::Line(true)) ?,
-            b'l' => state.next_cmd(Cmd::Line(false)) ?,
-            b'A' => state.next_cmd(Cmd::Arc(true)) ?,
-            b'a' => state.next_cmd(Cmd::Arc(false)) ?,
-            b'Z' | b'z' => state.next_cmd(Cmd::Close) ?,
-            b'-' => state.add_min() ?,
-            b'0'..=b'9' => state.add_digit((path[i] - b'0') as f64) ?,
-            b'.' => state.add_dot() ?,
-            b',' | b' ' | b'\r' | b'\n' | b'\t' => state.whitespace() ?,
-            x => {
-                return Err(format!("Unexpected character {} - {}", x, x as char))
-            }
-        }
-    }
-    state.finalize_cmd() ?;
-    
-    Ok(state.out)
-}
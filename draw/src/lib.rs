@@ -2,6 +2,7 @@
 pub use makepad_platform;
 pub use makepad_platform::*;
 pub use makepad_vector;
+pub use makepad_html;
 pub mod match_event;
 pub mod overlay;
 pub mod cx_2d;
@@ -12,6 +13,7 @@ pub mod font_atlas;
 pub mod geometry;
 pub mod nav;
 pub mod icon_atlas;
+pub mod svg;
 mod owned_font_face;
  
 pub use crate::{
@@ -53,6 +55,7 @@ pub use crate::{
         draw_line::DrawLine,
         draw_text::DrawText,
         draw_color::DrawColor,
+        draw_svg::DrawSvg,
     },
     geometry::{
         GeometryGen,
@@ -69,4 +72,5 @@ pub fn live_design(cx: &mut Cx) {
     crate::geometry::geometry_gen::live_design(cx);
     crate::shader::std::live_design(cx);
     crate::shader::draw_trapezoid::live_design(cx);
+    crate::shader::draw_svg::live_design(cx);
 }
\ No newline at end of file
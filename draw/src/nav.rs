@@ -21,7 +21,9 @@ pub struct CxNavTreeRc(pub Rc<RefCell<CxNavTree >>);
 
 #[derive(Debug, Default, Clone)]
 pub struct CxNavList {
-    pub nav_list: Vec<NavItem>
+    pub nav_list: Vec<NavItem>,
+    // a focus scope keeps tab navigation inside this draw list, like a popup menu or modal
+    pub is_scope: bool,
 }
 
 impl std::ops::Index<DrawListId> for CxNavTree {
@@ -37,10 +39,11 @@ impl std::ops::IndexMut<DrawListId> for CxNavTree {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum NavOrder {
-    Default,
+    // explicit tab indices come first, in ascending order
     Top(u64),
+    Default,
     Middle(u64),
     Bottom(u64),
 }
@@ -61,11 +64,80 @@ pub enum NavItem {
     EndScroll(Area)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum NavRole {
     TextInput,
     DropDown,
     Slider,
+    Button,
+    CheckBox,
+}
+
+impl CxNavTree {
+    // the stops below root in tab order, see Cx2d::nav_stops. is_valid filters out the stops
+    // of widgets that weren't drawn this frame
+    pub fn stops(&self, root: DrawListId, is_valid: impl Fn(&Area) -> bool) -> (Vec<(NavStop, Vec<Area>)>, bool) {
+        fn find_scope(nav_tree: &CxNavTree, draw_list_id: DrawListId, scope: &mut Option<DrawListId>) {
+            if draw_list_id.index() >= nav_tree.nav_lists.len() {
+                return
+            }
+            if nav_tree[draw_list_id].is_scope {
+                *scope = Some(draw_list_id);
+            }
+            for nav_item in &nav_tree[draw_list_id].nav_list {
+                if let NavItem::Child(draw_list_id) = nav_item {
+                    find_scope(nav_tree, *draw_list_id, scope);
+                }
+            }
+        }
+        
+        fn collect_stops(nav_tree: &CxNavTree, draw_list_id: DrawListId, is_valid: &dyn Fn(&Area) -> bool, scroll_stack: &mut Vec<Area>, stops: &mut Vec<(NavStop, Vec<Area>)>) {
+            if draw_list_id.index() >= nav_tree.nav_lists.len() {
+                return
+            }
+            for nav_item in &nav_tree[draw_list_id].nav_list {
+                match nav_item {
+                    NavItem::Child(draw_list_id) => {
+                        collect_stops(nav_tree, *draw_list_id, is_valid, scroll_stack, stops);
+                    }
+                    NavItem::Stop(stop) => if is_valid(&stop.area) {
+                        let mut stack = scroll_stack.clone();
+                        stack.push(stop.area);
+                        stops.push((stop.clone(), stack));
+                    }
+                    NavItem::BeginScroll(area) => {
+                        scroll_stack.push(*area);
+                    }
+                    NavItem::EndScroll(_) => {
+                        scroll_stack.pop();
+                    }
+                }
+            }
+        }
+        
+        let mut scope = None;
+        find_scope(self, root, &mut scope);
+        let mut stops = Vec::new();
+        collect_stops(self, scope.unwrap_or(root), &is_valid, &mut Vec::new(), &mut stops);
+        // stable, so stops with the same order stay in widget tree order
+        stops.sort_by_key(|(stop, _)| stop.order);
+        (stops, scope.is_some())
+    }
+}
+
+impl NavOrder {
+    // maps a tab_index live property onto a nav order, negative indices are not a nav stop
+    pub fn from_tab_index(tab_index: i64) -> Option<NavOrder> {
+        if tab_index < 0 {
+            None
+        }
+        else if tab_index == 0 {
+            Some(NavOrder::Default)
+        }
+        else {
+            Some(NavOrder::Top(tab_index as u64))
+        }
+    }
 }
 
 impl<'a> Cx2d<'a> {
@@ -114,12 +186,24 @@ impl<'a> Cx2d<'a> {
         }
     }
     
+    // returns all valid nav stops in tab order with the scroll areas they are nested in.
+    // if a focus scope is drawn, only the stops of the last scope are returned and the bool is true
+    pub fn nav_stops(cx: &mut Cx, root: DrawListId) -> (Vec<(NavStop, Vec<Area>)>, bool) {
+        if !cx.has_global::<CxNavTreeRc>() {
+            return (Vec::new(), false)
+        }
+        let nav_tree_rc = cx.get_global::<CxNavTreeRc>().clone();
+        let nav_tree = &*nav_tree_rc.0.borrow();
+        nav_tree.stops(root, | area | area.is_valid(cx))
+    }
+    
     pub fn nav_list_clear(&mut self, draw_list_id: DrawListId) {
         let mut nav_tree = self.nav_tree_rc.0.borrow_mut();
         if draw_list_id.index() >= nav_tree.nav_lists.len() {
             nav_tree.nav_lists.resize(draw_list_id.index() + 1, Default::default());
         }
         nav_tree[draw_list_id].nav_list.clear();
+        nav_tree[draw_list_id].is_scope = false;
     }
     
     pub fn nav_list_item_push(&mut self, draw_list_id: DrawListId, item: NavItem){
@@ -137,6 +221,25 @@ impl<'a> Cx2d<'a> {
         }));
     }
     
+    pub fn add_nav_stop_with_tab_index(&mut self, area: Area, role: NavRole, margin: Margin, tab_index: i64) {
+        if let Some(order) = NavOrder::from_tab_index(tab_index) {
+            let draw_list_id = *self.draw_list_stack.last().unwrap();
+            self.nav_list_item_push(draw_list_id, NavItem::Stop(NavStop {
+                role,
+                area,
+                order,
+                margin
+            }));
+        }
+    }
+    
+    // makes the current draw list a focus scope, tab navigation stays inside it while it is drawn
+    pub fn add_nav_scope(&mut self) {
+        let mut nav_tree = self.nav_tree_rc.0.borrow_mut();
+        let draw_list_id = *self.draw_list_stack.last().unwrap();
+        nav_tree[draw_list_id].is_scope = true;
+    }
+    
     pub fn add_begin_scroll(&mut self)->NavScrollIndex{
         let mut nav_tree = self.nav_tree_rc.0.borrow_mut();
         let draw_list_id = *self.draw_list_stack.last().unwrap();
@@ -153,4 +256,125 @@ impl<'a> Cx2d<'a> {
     }
 }
 
-pub struct NavScrollIndex(usize);
\ No newline at end of file
+pub struct NavScrollIndex(usize);
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::makepad_platform::{DrawList, RectArea},
+    };
+
+    struct Tree {
+        nav_tree: CxNavTree,
+        lists: Vec<DrawListId>,
+        // the pool reuses the ids of dropped draw lists
+        _draw_lists: Vec<DrawList>,
+    }
+
+    impl Tree {
+        fn new(count: usize) -> Self {
+            let mut cx = Cx::new(Box::new( | _, _ | {}));
+            let draw_lists: Vec<_> = (0..count).map( | _ | cx.draw_lists.alloc()).collect();
+            let lists = draw_lists.iter().map( | list | list.id()).collect();
+            let nav_tree = CxNavTree {nav_lists: vec![CxNavList::default(); count]};
+            Self {nav_tree, lists, _draw_lists: draw_lists}
+        }
+
+        fn area(&self, id: usize) -> Area {
+            Area::Rect(RectArea {draw_list_id: self.lists[0], rect_id: id, redraw_id: 0})
+        }
+
+        fn push(&mut self, list: usize, item: NavItem) {
+            let id = self.lists[list];
+            self.nav_tree[id].nav_list.push(item);
+        }
+
+        fn stop(&mut self, list: usize, id: usize, role: NavRole, order: NavOrder) {
+            let area = self.area(id);
+            self.push(list, NavItem::Stop(NavStop {role, order, margin: Margin::default(), area}));
+        }
+
+        // the rect ids of the stops in tab order, skipping the ones with an id of 99 or more
+        fn stops(&self) -> (Vec<(usize, Vec<usize>)>, bool) {
+            let rect_id = | area: &Area | match area {Area::Rect(rect) => rect.rect_id, _ => usize::MAX};
+            let (stops, in_scope) = self.nav_tree.stops(self.lists[0], | area | rect_id(area) < 99);
+            let stops = stops.iter().map( | (stop, stack) | (rect_id(&stop.area), stack.iter().map(rect_id).collect())).collect();
+            (stops, in_scope)
+        }
+    }
+
+    #[test]
+    fn maps_tab_indices_to_orders() {
+        assert_eq!(NavOrder::from_tab_index(-1), None);
+        assert_eq!(NavOrder::from_tab_index(0), Some(NavOrder::Default));
+        assert_eq!(NavOrder::from_tab_index(3), Some(NavOrder::Top(3)));
+        // explicit indices go before the default order, lower ones first
+        assert!(NavOrder::Top(1) < NavOrder::Top(2));
+        assert!(NavOrder::Top(u64::MAX) < NavOrder::Default);
+        assert!(NavOrder::Default < NavOrder::Middle(0));
+        assert!(NavOrder::Middle(5) < NavOrder::Bottom(0));
+    }
+
+    #[test]
+    fn orders_stops_by_tab_index_then_tree_order() {
+        let mut tree = Tree::new(2);
+        let list1 = tree.lists[1];
+        tree.stop(0, 1, NavRole::Button, NavOrder::Default);
+        tree.push(0, NavItem::Child(list1));
+        tree.stop(0, 2, NavRole::TextInput, NavOrder::Top(2));
+        let scroll = tree.area(50);
+        tree.push(0, NavItem::BeginScroll(scroll));
+        tree.stop(0, 3, NavRole::Button, NavOrder::Default);
+        tree.stop(0, 99, NavRole::Button, NavOrder::Top(1));
+        tree.stop(0, 4, NavRole::CheckBox, NavOrder::Default);
+        tree.push(0, NavItem::EndScroll(scroll));
+        tree.stop(0, 5, NavRole::Slider, NavOrder::Top(1));
+        tree.stop(1, 6, NavRole::DropDown, NavOrder::Default);
+
+        let (stops, in_scope) = tree.stops();
+        assert!(!in_scope);
+        assert_eq!(stops, vec![
+            (5, vec![5]),
+            (2, vec![2]),
+            (1, vec![1]),
+            (6, vec![6]),
+            (3, vec![50, 3]),
+            (4, vec![50, 4]),
+        ]);
+    }
+
+    #[test]
+    fn keeps_stops_inside_the_last_scope() {
+        let mut tree = Tree::new(4);
+        let (list1, list2, list3) = (tree.lists[1], tree.lists[2], tree.lists[3]);
+        tree.stop(0, 1, NavRole::Button, NavOrder::Default);
+        tree.push(0, NavItem::Child(list1));
+        tree.push(0, NavItem::Child(list3));
+        tree.push(1, NavItem::Child(list2));
+        tree.stop(1, 2, NavRole::Button, NavOrder::Default);
+        tree.stop(2, 3, NavRole::Button, NavOrder::Default);
+        tree.stop(3, 4, NavRole::Button, NavOrder::Default);
+        tree.nav_tree[list1].is_scope = true;
+        // a nested child of the scope is part of it, a sibling is not
+        let (stops, in_scope) = tree.stops();
+        assert!(in_scope);
+        assert_eq!(stops.iter().map( | (id, _) | *id).collect::<Vec<_>>(), vec![3, 2]);
+
+        // the scope drawn last wins, like a popup over a modal
+        tree.nav_tree[list3].is_scope = true;
+        let (stops, _) = tree.stops();
+        assert_eq!(stops.iter().map( | (id, _) | *id).collect::<Vec<_>>(), vec![4]);
+    }
+
+    #[test]
+    fn ignores_lists_that_were_never_drawn() {
+        let mut tree = Tree::new(3);
+        let list2 = tree.lists[2];
+        tree.nav_tree.nav_lists.truncate(2);
+        tree.stop(0, 1, NavRole::Button, NavOrder::Default);
+        tree.push(0, NavItem::Child(list2));
+        let (stops, in_scope) = tree.stops();
+        assert_eq!(stops, vec![(1, vec![1])]);
+        assert!(!in_scope);
+    }
+}
@@ -0,0 +1,342 @@
+use {
+    std::{
+        rc::Rc,
+        collections::HashMap,
+    },
+    crate::{
+        makepad_platform::*,
+        geometry::GeometryQuad2D,
+        cx_2d::Cx2d,
+        turtle::{Walk, Size},
+        svg::{SvgDocument, SvgTessellation, SvgTransform, SvgLayerPaint, SvgGradientKind, SvgGradientStop},
+    },
+};
+
+live_design!{
+    DrawSvg = {{DrawSvg}} {
+        varying v_p0: vec2;
+        varying v_p1: vec2;
+        varying v_p2: vec2;
+        varying v_p3: vec2;
+        varying v_pixel: vec2;
+        varying v_local: vec2;
+
+        fn intersect_line_segment_with_vertical_line(p0: vec2, p1: vec2, x: float) -> vec2 {
+            return vec2(
+                x,
+                mix(p0.y, p1.y, (x - p0.x) / (p1.x - p0.x))
+            );
+        }
+
+        fn intersect_line_segment_with_horizontal_line(p0: vec2, p1: vec2, y: float) -> vec2 {
+            return vec2(
+                mix(p0.x, p1.x, (y - p0.y) / (p1.y - p0.y)),
+                y
+            );
+        }
+
+        fn compute_clamped_right_trapezoid_area(p0: vec2, p1: vec2, p_min: vec2, p_max: vec2) -> float {
+            let x0 = clamp(p0.x, p_min.x, p_max.x);
+            let x1 = clamp(p1.x, p_min.x, p_max.x);
+            if (p0.x < p_min.x && p_min.x < p1.x) {
+                p0 = intersect_line_segment_with_vertical_line(p0, p1, p_min.x);
+            }
+            if (p0.x < p_max.x && p_max.x < p1.x) {
+                p1 = intersect_line_segment_with_vertical_line(p0, p1, p_max.x);
+            }
+            if (p0.y < p_min.y && p_min.y < p1.y) {
+                p0 = intersect_line_segment_with_horizontal_line(p0, p1, p_min.y);
+            }
+            if (p1.y < p_min.y && p_min.y < p0.y) {
+                p1 = intersect_line_segment_with_horizontal_line(p1, p0, p_min.y);
+            }
+            if (p0.y < p_max.y && p_max.y < p1.y) {
+                p1 = intersect_line_segment_with_horizontal_line(p0, p1, p_max.y);
+            }
+            if (p1.y < p_max.y && p_max.y < p0.y) {
+                p0 = intersect_line_segment_with_horizontal_line(p1, p0, p_max.y);
+            }
+            p0 = clamp(p0, p_min, p_max);
+            p1 = clamp(p1, p_min, p_max);
+            let h0 = p_max.y - p0.y;
+            let h1 = p_max.y - p1.y;
+            let a0 = (p0.x - x0) * h0;
+            let a1 = (p1.x - p0.x) * (h0 + h1) * 0.5;
+            let a2 = (x1 - p1.x) * h1;
+            return a0 + a1 + a2;
+        }
+
+        fn compute_clamped_trapezoid_area(self, p_min: vec2, p_max: vec2) -> float {
+            let a0 = compute_clamped_right_trapezoid_area(self.v_p0, self.v_p1, p_min, p_max);
+            let a1 = compute_clamped_right_trapezoid_area(self.v_p2, self.v_p3, p_min, p_max);
+            return a0 - a1;
+        }
+
+        fn stop_color(self, t: float) -> vec4 {
+            let o = self.stop_offsets;
+            if t <= o.y {
+                return mix(self.stop_color0, self.stop_color1, clamp((t - o.x) / max(o.y - o.x, 0.00001), 0.0, 1.0));
+            }
+            if t <= o.z {
+                return mix(self.stop_color1, self.stop_color2, clamp((t - o.y) / max(o.z - o.y, 0.00001), 0.0, 1.0));
+            }
+            return mix(self.stop_color2, self.stop_color3, clamp((t - o.z) / max(o.w - o.z, 0.00001), 0.0, 1.0));
+        }
+
+        fn get_color(self) -> vec4 {
+            if self.paint_kind < 0.5 {
+                return self.color;
+            }
+            // the position in the coordinate space of the gradient
+            let g = vec2(
+                dot(self.paint_matrix.xz, self.v_local),
+                dot(self.paint_matrix.yw, self.v_local)
+            ) + self.paint_offset;
+            if self.paint_kind < 1.5 {
+                let d = self.paint_geom.zw - self.paint_geom.xy;
+                let t = dot(g - self.paint_geom.xy, d) / max(dot(d, d), 0.00001);
+                return self.stop_color(clamp(t, 0.0, 1.0));
+            }
+            let t = length(g - self.paint_geom.xy) / max(self.paint_geom.z, 0.00001);
+            return self.stop_color(clamp(t, 0.0, 1.0));
+        }
+
+        fn pixel(self) -> vec4 {
+            let p_min = self.v_pixel.xy - 0.5;
+            let p_max = self.v_pixel.xy + 0.5;
+            let t_area = abs(self.compute_clamped_trapezoid_area(p_min, p_max));
+            let color = self.get_color();
+            return vec4(color.rgb * color.a, color.a) * t_area;
+        }
+
+        fn vertex(self) -> vec4 {
+            // trapezoids are in device pixels relative to the top left of the rect
+            let origin = self.rect_pos * self.dpi_factor;
+            let pos_min = vec2(self.a_xs.x, min(self.a_ys.x, self.a_ys.y)) + origin;
+            let pos_max = vec2(self.a_xs.y, max(self.a_ys.z, self.a_ys.w)) + origin;
+            let pos = clamp(
+                mix(pos_min - 1.0, pos_max + 1.0, self.geom_pos),
+                self.draw_clip.xy * self.dpi_factor,
+                self.draw_clip.zw * self.dpi_factor
+            );
+
+            // set the varyings
+            self.v_p0 = vec2(self.a_xs.x, self.a_ys.x) + origin;
+            self.v_p1 = vec2(self.a_xs.y, self.a_ys.y) + origin;
+            self.v_p2 = vec2(self.a_xs.x, self.a_ys.z) + origin;
+            self.v_p3 = vec2(self.a_xs.y, self.a_ys.w) + origin;
+            self.v_pixel = pos;
+            self.v_local = pos - origin;
+
+            return self.camera_projection * (self.camera_view * (self.view_transform * vec4(
+                pos.x / self.dpi_factor,
+                pos.y / self.dpi_factor,
+                self.draw_depth + self.draw_zbias,
+                1.
+            )));
+        }
+    }
+}
+
+/// The parsed documents, shared between all DrawSvg's that use the same file
+#[derive(Default)]
+pub struct CxSvgDocuments(HashMap<Rc<String>, Option<Rc<SvgDocument>>>);
+
+/// Draws an svg file with all its shapes, strokes and gradients. The document is
+/// tessellated into trapezoids on the CPU and antialiased analytically in the shader.
+#[derive(Live, LiveRegister)]
+#[repr(C)]
+pub struct DrawSvg {
+    /// The allowed curve flattening error in device pixels
+    #[live(0.25)] pub linearize: f64,
+    #[live] pub svg_file: LiveDependency,
+    #[live(1.0)] pub scale: f64,
+
+    #[rust] document: Option<Rc<SvgDocument>>,
+    #[rust] tessellation: Option<(DVec2, Rc<SvgTessellation>)>,
+
+    #[live] pub geometry: GeometryQuad2D,
+    #[deref] pub draw_vars: DrawVars,
+    #[calc] pub rect_pos: Vec2,
+    #[calc] pub rect_size: Vec2,
+    #[calc] pub draw_clip: Vec4,
+    #[live(1.0)] pub draw_depth: f32,
+
+    #[calc] pub a_xs: Vec2,
+    #[calc] pub a_ys: Vec4,
+    #[calc] pub color: Vec4,
+    #[calc] pub paint_kind: f32,
+    #[calc] pub paint_matrix: Vec4,
+    #[calc] pub paint_offset: Vec2,
+    #[calc] pub paint_geom: Vec4,
+    #[calc] pub stop_offsets: Vec4,
+    #[calc] pub stop_color0: Vec4,
+    #[calc] pub stop_color1: Vec4,
+    #[calc] pub stop_color2: Vec4,
+    #[calc] pub stop_color3: Vec4,
+}
+
+impl LiveHook for DrawSvg{
+    fn before_apply(&mut self, cx: &mut Cx, apply: &mut Apply, index: usize, nodes: &[LiveNode]){
+        self.draw_vars.before_apply_init_shader(cx, apply, index, nodes, &self.geometry);
+    }
+    fn after_apply(&mut self, cx: &mut Cx, apply: &mut Apply, index: usize, nodes: &[LiveNode]) {
+        self.draw_vars.after_apply_update_self(cx, apply, index, nodes, &self.geometry);
+        // the file might have changed, we look it up again on the next draw
+        self.document = None;
+        self.tessellation = None;
+    }
+}
+
+impl DrawSvg {
+    /// Returns the parsed document, or None if the file isn't loaded (yet) or can't be parsed
+    pub fn document(&mut self, cx: &mut Cx) -> Option<Rc<SvgDocument>> {
+        if let Some(document) = &self.document {
+            return Some(document.clone())
+        }
+        let path = self.svg_file.as_ref();
+        if path.len() == 0 {
+            return None
+        }
+        if !cx.has_global::<CxSvgDocuments>() {
+            cx.set_global(CxSvgDocuments::default());
+        }
+        if let Some(document) = cx.get_global::<CxSvgDocuments>().0.get(path) {
+            self.document = document.clone();
+            return self.document.clone()
+        }
+        // a dependency that is still loading isn't cached, we just try again next draw
+        let data = cx.get_dependency(path.as_str()).ok() ?;
+        let document = match SvgDocument::parse(&String::from_utf8_lossy(&data)) {
+            Ok(document) => Some(Rc::new(document)),
+            Err(err) => {
+                error!("Error in SVG file {}: {}", path, err);
+                None
+            }
+        };
+        cx.get_global::<CxSvgDocuments>().0.insert(path.clone(), document.clone());
+        self.document = document;
+        self.document.clone()
+    }
+
+    /// The size the document is drawn at when the walk is Fit
+    pub fn intrinsic_size(&mut self, cx: &mut Cx) -> Option<DVec2> {
+        self.document(cx).map( | document | document.size * self.scale)
+    }
+
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, mut walk: Walk) -> Rect {
+        let size = match self.intrinsic_size(cx) {
+            Some(size) if size.x > 0.0 && size.y > 0.0 => size,
+            _ => return cx.walk_turtle(walk)
+        };
+        let width_is_fit = walk.width.is_fit();
+        let height_is_fit = walk.height.is_fit();
+        let peek_rect = cx.peek_walk_turtle(walk);
+        // fit keeps the aspect ratio of the document
+        if width_is_fit {
+            let scale = if height_is_fit {1.0} else {peek_rect.size.y / size.y};
+            walk.width = Size::Fixed(size.x * scale);
+        }
+        if height_is_fit {
+            let scale = if width_is_fit {1.0} else {peek_rect.size.x / size.x};
+            walk.height = Size::Fixed(size.y * scale);
+        }
+        let rect = cx.walk_turtle(walk);
+        self.draw_abs(cx, rect);
+        rect
+    }
+
+    /// Draws the document into `rect`, scaled to fit and centered like `xMidYMid meet`
+    pub fn draw_abs(&mut self, cx: &mut Cx2d, rect: Rect) {
+        if rect.is_nan() || rect.size.x <= 0.0 || rect.size.y <= 0.0 {
+            return
+        }
+        let document = match self.document(cx) {
+            Some(document) => document,
+            None => return
+        };
+        let view_box = document.view_box;
+        if view_box.size.x <= 0.0 || view_box.size.y <= 0.0 {
+            return
+        }
+        let dpi_factor = cx.current_dpi_factor();
+        let scale = (rect.size.x / view_box.size.x).min(rect.size.y / view_box.size.y);
+        let size = view_box.size * scale;
+        let pos = rect.pos + (rect.size - size) * 0.5;
+
+        // the tessellation only depends on the scale, the position is added in the shader
+        let device_scale = dvec2(scale * dpi_factor, scale * dpi_factor);
+        let tessellation = match &self.tessellation {
+            Some((cached_scale, tessellation)) if *cached_scale == device_scale => tessellation.clone(),
+            _ => {
+                let transform = SvgTransform::scaling(device_scale.x, device_scale.y)
+                    .then_after(&SvgTransform::translation(-view_box.pos.x, -view_box.pos.y));
+                let tessellation = Rc::new(document.tessellate(&transform, self.linearize.max(0.01)));
+                self.tessellation = Some((device_scale, tessellation.clone()));
+                tessellation
+            }
+        };
+
+        self.rect_pos = pos.into();
+        self.rect_size = size.into();
+
+        if !self.draw_vars.can_instance() {
+            return
+        }
+        let mut many = match cx.begin_many_aligned_instances(&self.draw_vars) {
+            Some(many) => many,
+            None => return
+        };
+        for layer in &tessellation.layers {
+            self.set_layer_paint(&layer.paint);
+            for trapezoid in &layer.trapezoids {
+                self.a_xs = Vec2 {x: trapezoid.xs[0], y: trapezoid.xs[1]};
+                self.a_ys = Vec4 {x: trapezoid.ys[0], y: trapezoid.ys[1], z: trapezoid.ys[2], w: trapezoid.ys[3]};
+                many.instances.extend_from_slice(self.draw_vars.as_slice());
+            }
+        }
+        let new_area = cx.end_many_instances(many);
+        self.draw_vars.area = cx.update_area_refs(self.draw_vars.area, new_area);
+    }
+
+    fn set_layer_paint(&mut self, paint: &SvgLayerPaint) {
+        match paint {
+            SvgLayerPaint::Color(color) => {
+                self.paint_kind = 0.0;
+                self.color = *color;
+            }
+            SvgLayerPaint::Gradient {kind, to_gradient, stops} => {
+                self.paint_matrix = vec4(to_gradient.a as f32, to_gradient.b as f32, to_gradient.c as f32, to_gradient.d as f32);
+                self.paint_offset = vec2(to_gradient.e as f32, to_gradient.f as f32);
+                match *kind {
+                    SvgGradientKind::Linear {x1, y1, x2, y2} => {
+                        self.paint_kind = 1.0;
+                        self.paint_geom = vec4(x1 as f32, y1 as f32, x2 as f32, y2 as f32);
+                    }
+                    SvgGradientKind::Radial {cx, cy, r} => {
+                        self.paint_kind = 2.0;
+                        self.paint_geom = vec4(cx as f32, cy as f32, r as f32, 0.0);
+                    }
+                }
+                let stops = four_stops(stops);
+                self.stop_offsets = vec4(stops[0].offset as f32, stops[1].offset as f32, stops[2].offset as f32, stops[3].offset as f32);
+                self.stop_color0 = stops[0].color;
+                self.stop_color1 = stops[1].color;
+                self.stop_color2 = stops[2].color;
+                self.stop_color3 = stops[3].color;
+            }
+        }
+    }
+}
+
+/// The shader interpolates between 4 stops, longer gradients are resampled
+fn four_stops(stops: &[SvgGradientStop]) -> [SvgGradientStop; 4] {
+    let last = stops[stops.len() - 1];
+    match stops.len() {
+        1 => [last; 4],
+        2 => [stops[0], last, SvgGradientStop {offset: 1.0, ..last}, SvgGradientStop {offset: 1.0, ..last}],
+        3 => [stops[0], stops[1], last, SvgGradientStop {offset: 1.0, ..last}],
+        4 => [stops[0], stops[1], stops[2], last],
+        n => [stops[0], stops[n / 3], stops[2 * n / 3], last],
+    }
+}
@@ -607,6 +607,11 @@ impl DrawText {
     }
     
     pub fn draw_walk_word(&mut self, cx: &mut Cx2d, text: &str) {
+        self.draw_walk_word_with(cx, text, |_,_|{})
+    }
+    
+    // same as draw_walk_word, calls f with the rect of every word so callers can decorate or hit test them
+    pub fn draw_walk_word_with<F>(&mut self, cx: &mut Cx2d, text: &str, mut f: F) where F: FnMut(&mut Cx2d, Rect) {
         
         // this walks the turtle per word
         if text.len() == 0 {
@@ -642,6 +647,7 @@ impl DrawText {
             });
             // make sure our iterator uses the xpos from the turtle
             self.draw_inner(cx, walk_rect.pos, &text[word.start..word.end], fonts_atlas);
+            f(cx, walk_rect);
         }
         if self.many_instances.is_some() {
             self.end_many_instances(cx)
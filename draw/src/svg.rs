@@ -0,0 +1,1719 @@
+use {
+    std::{
+        rc::Rc,
+        collections::HashMap,
+        f64::consts::PI,
+    },
+    crate::{
+        makepad_platform::*,
+        makepad_html::{parse_html, HtmlNode},
+        makepad_vector::{
+            geometry::{Point, Vector, Transform, Transformation, Trapezoid},
+            internal_iter::ExtendFromInternalIterator,
+            path::{PathCommand, PathIterator, LinePathCommand},
+            trapezoidator::{Trapezoidator, FillRule},
+        },
+    }
+};
+
+/// A 2d affine transform in the SVG `matrix(a b c d e f)` notation, it maps
+/// `(x, y)` to `(a * x + c * y + e, b * x + d * y + f)`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgTransform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Default for SvgTransform {
+    fn default() -> Self {Self::identity()}
+}
+
+impl SvgTransform {
+    pub fn identity() -> Self {
+        Self {a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0}
+    }
+
+    pub fn translation(x: f64, y: f64) -> Self {
+        Self {a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: x, f: y}
+    }
+
+    pub fn scaling(x: f64, y: f64) -> Self {
+        Self {a: x, b: 0.0, c: 0.0, d: y, e: 0.0, f: 0.0}
+    }
+
+    pub fn rotation(degrees: f64) -> Self {
+        let (s, c) = (degrees * PI / 180.0).sin_cos();
+        Self {a: c, b: s, c: -s, d: c, e: 0.0, f: 0.0}
+    }
+
+    /// The transform that first applies `other` and then `self`
+    pub fn then_after(&self, other: &SvgTransform) -> SvgTransform {
+        SvgTransform {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    pub fn invert(&self) -> Option<SvgTransform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < 1e-12 {
+            return None
+        }
+        Some(SvgTransform {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+
+    /// The average scale of the transform, used for stroke widths and curve tolerances
+    pub fn scale_factor(&self) -> f64 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    pub fn apply(&self, p: DVec2) -> DVec2 {
+        dvec2(self.a * p.x + self.c * p.y + self.e, self.b * p.x + self.d * p.y + self.f)
+    }
+}
+
+impl Transformation for SvgTransform {
+    fn transform_point(&self, p: Point) -> Point {
+        Point::new(self.a * p.x + self.c * p.y + self.e, self.b * p.x + self.d * p.y + self.f)
+    }
+
+    fn transform_vector(&self, v: Vector) -> Vector {
+        Vector::new(self.a * v.x + self.c * v.y, self.b * v.x + self.d * v.y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SvgGradientKind {
+    Linear {x1: f64, y1: f64, x2: f64, y2: f64},
+    Radial {cx: f64, cy: f64, r: f64},
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgGradientStop {
+    pub offset: f64,
+    pub color: Vec4,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgGradient {
+    pub kind: SvgGradientKind,
+    /// With `gradientUnits="objectBoundingBox"` (the default) the gradient coordinates are
+    /// fractions of the bounding box of the shape it is used on
+    pub object_bounding_box: bool,
+    pub transform: SvgTransform,
+    pub stops: Vec<SvgGradientStop>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SvgPaint {
+    Color(Vec4),
+    Gradient(Rc<SvgGradient>),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SvgLineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SvgLineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgStrokeStyle {
+    pub width: f64,
+    pub line_cap: SvgLineCap,
+    pub line_join: SvgLineJoin,
+    pub miter_limit: f64,
+}
+
+impl Default for SvgStrokeStyle {
+    fn default() -> Self {
+        Self {width: 1.0, line_cap: SvgLineCap::Butt, line_join: SvgLineJoin::Miter, miter_limit: 4.0}
+    }
+}
+
+/// One filled and/or stroked path, with all styles and transforms of its groups resolved
+#[derive(Clone, Debug)]
+pub struct SvgShape {
+    pub path: Vec<PathCommand>,
+    pub transform: SvgTransform,
+    pub fill: Option<SvgPaint>,
+    pub fill_opacity: f64,
+    pub fill_rule: FillRule,
+    pub stroke: Option<SvgPaint>,
+    pub stroke_opacity: f64,
+    pub stroke_style: SvgStrokeStyle,
+}
+
+/// A parsed SVG document, reduced to a flat list of shapes in paint order
+#[derive(Clone, Debug, Default)]
+pub struct SvgDocument {
+    /// The area of the user coordinate space that is visible
+    pub view_box: Rect,
+    /// The intrinsic size from the width and height attributes, or the view box size
+    pub size: DVec2,
+    pub shapes: Vec<SvgShape>,
+}
+
+/// A polyline in device space, the output of flattening a path
+#[derive(Clone, Debug, Default)]
+pub struct SvgPolyline {
+    pub points: Vec<Point>,
+    pub closed: bool,
+}
+
+/// The paint of one layer of a tessellated document. Gradient coordinates are
+/// reached from the space the trapezoids are in with `to_gradient`.
+#[derive(Clone, Debug)]
+pub enum SvgLayerPaint {
+    Color(Vec4),
+    Gradient {
+        kind: SvgGradientKind,
+        to_gradient: SvgTransform,
+        stops: Vec<SvgGradientStop>,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SvgLayer {
+    pub paint: SvgLayerPaint,
+    pub trapezoids: Vec<Trapezoid>,
+}
+
+/// A document turned into trapezoids for one transform, layers are in paint order
+#[derive(Clone, Debug, Default)]
+pub struct SvgTessellation {
+    pub layers: Vec<SvgLayer>,
+}
+
+impl SvgDocument {
+    pub fn parse(data: &str) -> Result<SvgDocument, String> {
+        let doc = parse_html(data, &mut None);
+        let root = SvgElement::from_html_nodes(&doc.decoded, &doc.nodes);
+        let svg = root.find_first(live_id!(svg)).ok_or_else( || "No svg element found".to_string()) ?;
+
+        let view_box = svg.attr("viewBox").map(parse_numbers).and_then( | v | {
+            if v.len() == 4 && v[2] > 0.0 && v[3] > 0.0 {
+                Some(Rect {pos: dvec2(v[0], v[1]), size: dvec2(v[2], v[3])})
+            }
+            else {
+                None
+            }
+        });
+        let width = svg.attr("width").and_then(parse_absolute_length);
+        let height = svg.attr("height").and_then(parse_absolute_length);
+
+        let mut gradients = HashMap::new();
+        root.collect_gradients(&mut gradients);
+        let gradients = resolve_gradients(gradients);
+
+        let mut parser = SvgParser {
+            gradients,
+            viewport: view_box.map( | v | v.size).unwrap_or(dvec2(width.unwrap_or(100.0), height.unwrap_or(100.0))),
+            in_svg: false,
+            shapes: Vec::new(),
+        };
+        parser.walk(svg, &SvgStyle::default(), &SvgTransform::identity());
+        let shapes = parser.shapes;
+
+        let view_box = match (view_box, width, height) {
+            (Some(view_box), _, _) => view_box,
+            (None, Some(w), Some(h)) => Rect {pos: dvec2(0.0, 0.0), size: dvec2(w, h)},
+            _ => {
+                let mut doc = SvgDocument {view_box: Rect::default(), size: DVec2::default(), shapes};
+                doc.view_box = doc.bounds();
+                doc.size = doc.view_box.size;
+                return Ok(doc)
+            }
+        };
+        let size = match (width, height) {
+            (Some(w), Some(h)) => dvec2(w, h),
+            (Some(w), None) => dvec2(w, w * view_box.size.y / view_box.size.x),
+            (None, Some(h)) => dvec2(h * view_box.size.x / view_box.size.y, h),
+            (None, None) => view_box.size,
+        };
+        Ok(SvgDocument {view_box, size, shapes})
+    }
+
+    /// The bounds of all shape outlines in user space, strokes are not included
+    pub fn bounds(&self) -> Rect {
+        let mut min = dvec2(f64::INFINITY, f64::INFINITY);
+        let mut max = dvec2(-f64::INFINITY, -f64::INFINITY);
+        for shape in &self.shapes {
+            for polyline in flatten_path(&shape.path, &shape.transform, 0.1) {
+                for p in polyline.points {
+                    min = dvec2(min.x.min(p.x), min.y.min(p.y));
+                    max = dvec2(max.x.max(p.x), max.y.max(p.y));
+                }
+            }
+        }
+        if min.x > max.x {
+            return Rect::default()
+        }
+        Rect {pos: min, size: max - min}
+    }
+
+    /// The outlines of all fills and strokes in user space, each with the fill rule it
+    /// needs. This is what a single color icon is drawn from.
+    pub fn outlines(&self, tolerance: f64) -> Vec<(FillRule, Vec<PathCommand>)> {
+        let mut outlines = Vec::new();
+        for shape in &self.shapes {
+            let polylines = flatten_path(&shape.path, &shape.transform, tolerance);
+            if shape.fill.is_some() {
+                outlines.push((shape.fill_rule, polylines_to_path(polylines.iter())));
+            }
+            if shape.stroke.is_some() {
+                let mut style = shape.stroke_style;
+                style.width *= shape.transform.scale_factor();
+                outlines.push((FillRule::NonZero, polylines_to_path(stroke_polylines(&polylines, &style, tolerance).iter())));
+            }
+        }
+        outlines
+    }
+
+    /// Turns the document into trapezoids, `transform` maps user space to the space the
+    /// trapezoids are in and `tolerance` is the allowed curve flattening error in that space
+    pub fn tessellate(&self, transform: &SvgTransform, tolerance: f64) -> SvgTessellation {
+        let mut trapezoidator = Trapezoidator::default();
+        let mut layers = Vec::new();
+        for shape in &self.shapes {
+            let transform = transform.then_after(&shape.transform);
+            let polylines = flatten_path(&shape.path, &transform, tolerance);
+            if polylines.is_empty() {
+                continue;
+            }
+            let local_bounds = || {
+                let mut min = dvec2(f64::INFINITY, f64::INFINITY);
+                let mut max = dvec2(-f64::INFINITY, -f64::INFINITY);
+                for polyline in flatten_path(&shape.path, &SvgTransform::identity(), tolerance / transform.scale_factor().max(1e-9)) {
+                    for p in polyline.points {
+                        min = dvec2(min.x.min(p.x), min.y.min(p.y));
+                        max = dvec2(max.x.max(p.x), max.y.max(p.y));
+                    }
+                }
+                Rect {pos: min, size: max - min}
+            };
+            if let Some(fill) = &shape.fill {
+                trapezoidator.set_fill_rule(shape.fill_rule);
+                let trapezoids = trapezoidate(&mut trapezoidator, polylines.iter());
+                if !trapezoids.is_empty() {
+                    if let Some(paint) = layer_paint(fill, shape.fill_opacity, &transform, local_bounds) {
+                        layers.push(SvgLayer {paint, trapezoids});
+                    }
+                }
+            }
+            if let Some(stroke) = &shape.stroke {
+                let mut style = shape.stroke_style;
+                style.width *= transform.scale_factor();
+                let outline = stroke_polylines(&polylines, &style, tolerance);
+                trapezoidator.set_fill_rule(FillRule::NonZero);
+                let trapezoids = trapezoidate(&mut trapezoidator, outline.iter());
+                if !trapezoids.is_empty() {
+                    if let Some(paint) = layer_paint(stroke, shape.stroke_opacity, &transform, local_bounds) {
+                        layers.push(SvgLayer {paint, trapezoids});
+                    }
+                }
+            }
+        }
+        SvgTessellation {layers}
+    }
+}
+
+fn layer_paint(paint: &SvgPaint, opacity: f64, transform: &SvgTransform, local_bounds: impl Fn() -> Rect) -> Option<SvgLayerPaint> {
+    match paint {
+        SvgPaint::Color(color) => {
+            Some(SvgLayerPaint::Color(vec4(color.x, color.y, color.z, color.w * opacity as f32)))
+        }
+        SvgPaint::Gradient(gradient) => {
+            let stops: Vec<SvgGradientStop> = gradient.stops.iter().map( | stop | SvgGradientStop {
+                offset: stop.offset,
+                color: vec4(stop.color.x, stop.color.y, stop.color.z, stop.color.w * opacity as f32)
+            }).collect();
+            if stops.is_empty() {
+                return None
+            }
+            if stops.len() == 1 {
+                return Some(SvgLayerPaint::Color(stops[0].color))
+            }
+            let mut from_gradient = transform.then_after(&gradient.transform);
+            if gradient.object_bounding_box {
+                let bounds = local_bounds();
+                if bounds.size.x <= 0.0 || bounds.size.y <= 0.0 {
+                    return None
+                }
+                let bbox = SvgTransform::translation(bounds.pos.x, bounds.pos.y)
+                    .then_after(&SvgTransform::scaling(bounds.size.x, bounds.size.y));
+                from_gradient = transform.then_after(&bbox).then_after(&gradient.transform);
+            }
+            let to_gradient = from_gradient.invert() ?;
+            Some(SvgLayerPaint::Gradient {kind: gradient.kind, to_gradient, stops})
+        }
+    }
+}
+
+fn trapezoidate<'a>(trapezoidator: &mut Trapezoidator, polylines: impl Iterator<Item = &'a SvgPolyline>) -> Vec<Trapezoid> {
+    let mut commands = Vec::new();
+    for (p0, p1) in split_crossings(polylines) {
+        commands.push(LinePathCommand::MoveTo(p0));
+        commands.push(LinePathCommand::LineTo(p1));
+    }
+    let mut trapezoids = Vec::new();
+    if let Some(trapezoidate) = trapezoidator.trapezoidate(commands.into_iter()) {
+        trapezoids.extend_from_internal_iter(trapezoidate);
+    }
+    trapezoids
+}
+
+fn polylines_to_path<'a>(polylines: impl Iterator<Item = &'a SvgPolyline>) -> Vec<PathCommand> {
+    let mut path = Vec::new();
+    for (p0, p1) in split_crossings(polylines) {
+        path.push(PathCommand::MoveTo(p0));
+        path.push(PathCommand::LineTo(p1));
+    }
+    path
+}
+
+/// Returns the edges of closed polylines, split where they cross each other. The
+/// trapezoidator expects edges that only meet at their end points, which overlapping
+/// stroke pieces and self intersecting paths don't give us.
+fn split_crossings<'a>(polylines: impl Iterator<Item = &'a SvgPolyline>) -> Vec<(Point, Point)> {
+    let mut segments = Vec::new();
+    for polyline in polylines {
+        let n = polyline.points.len();
+        if n < 3 {
+            continue;
+        }
+        for i in 0..n {
+            let (p0, p1) = (polyline.points[i], polyline.points[(i + 1) % n]);
+            if p0 != p1 {
+                segments.push((p0, p1));
+            }
+        }
+    }
+    // sweep over the segments sorted by their left end, only testing those that overlap in x
+    let min_x = | s: &(Point, Point) | s.0.x.min(s.1.x);
+    let max_x = | s: &(Point, Point) | s.0.x.max(s.1.x);
+    let mut order: Vec<usize> = (0..segments.len()).collect();
+    order.sort_by( | a, b | min_x(&segments[*a]).total_cmp(&min_x(&segments[*b])));
+    let mut splits: Vec<Vec<(f64, Point)>> = vec![Vec::new(); segments.len()];
+    let mut active: Vec<usize> = Vec::new();
+    for &i in &order {
+        let (p, p1) = segments[i];
+        active.retain( | j | max_x(&segments[*j]) >= min_x(&segments[i]));
+        for &j in &active {
+            let (q, q1) = segments[j];
+            if p.y.max(p1.y) < q.y.min(q1.y) || q.y.max(q1.y) < p.y.min(p1.y) {
+                continue;
+            }
+            let r = p1 - p;
+            let s = q1 - q;
+            let d = r.cross(s);
+            if d.abs() < 1e-12 {
+                continue;
+            }
+            let qp = q - p;
+            let t = qp.cross(s) / d;
+            let u = qp.cross(r) / d;
+            const EPS: f64 = 1e-9;
+            if t > EPS && t < 1.0 - EPS && u > EPS && u < 1.0 - EPS {
+                let x = p + r * t;
+                splits[i].push((t, x));
+                splits[j].push((u, x));
+            }
+        }
+        active.push(i);
+    }
+    let mut out = Vec::with_capacity(segments.len());
+    for (i, (p0, p1)) in segments.into_iter().enumerate() {
+        let split = &mut splits[i];
+        if split.is_empty() {
+            out.push((p0, p1));
+            continue;
+        }
+        split.sort_by( | a, b | a.0.total_cmp(&b.0));
+        let mut last = p0;
+        for (_, x) in split.iter() {
+            if *x != last {
+                out.push((last, *x));
+                last = *x;
+            }
+        }
+        if last != p1 {
+            out.push((last, p1));
+        }
+    }
+    out
+}
+
+/// Flattens a path into polylines, transformed into the target space. The tolerance is in
+/// the target space, so curves are subdivided for the size they end up at.
+pub fn flatten_path(path: &[PathCommand], transform: &SvgTransform, tolerance: f64) -> Vec<SvgPolyline> {
+    use crate::makepad_vector::internal_iter::InternalIterator;
+    let mut polylines = Vec::new();
+    if !matches!(path.first(), Some(PathCommand::MoveTo(_))) {
+        return polylines
+    }
+    let epsilon = tolerance / transform.scale_factor().max(1e-9);
+    let mut current = SvgPolyline::default();
+    path.iter().cloned().linearize(epsilon).for_each(&mut | command | {
+        match command {
+            LinePathCommand::MoveTo(p) => {
+                if current.points.len() > 1 {
+                    polylines.push(std::mem::take(&mut current));
+                }
+                current.points.clear();
+                current.closed = false;
+                current.points.push(p.transform(transform));
+            }
+            LinePathCommand::LineTo(p) => {
+                let p = p.transform(transform);
+                if current.points.last() != Some(&p) {
+                    current.points.push(p);
+                }
+            }
+            LinePathCommand::Close => {
+                if current.points.len() > 1 && current.points.first() == current.points.last() {
+                    current.points.pop();
+                }
+                if current.points.len() > 1 {
+                    current.closed = true;
+                    let start = current.points[0];
+                    polylines.push(std::mem::take(&mut current));
+                    // drawing can continue from the start of the closed subpath
+                    current.points.push(start);
+                }
+            }
+        }
+        true
+    });
+    if current.points.len() > 1 {
+        polylines.push(current);
+    }
+    polylines.retain( | polyline | polyline.points.iter().all( | p | p.x.is_finite() && p.y.is_finite()));
+    polylines
+}
+
+/// Turns polylines into closed outlines covering their stroke. The outlines all have the
+/// same orientation and overlap, so they have to be filled with the nonzero fill rule.
+pub fn stroke_polylines(polylines: &[SvgPolyline], style: &SvgStrokeStyle, tolerance: f64) -> Vec<SvgPolyline> {
+    let mut out = Vec::new();
+    let hw = style.width * 0.5;
+    if hw <= 0.0 {
+        return out
+    }
+    let mut push = | mut points: Vec<Point> | {
+        let mut area = 0.0;
+        for i in 0..points.len() {
+            let p0 = points[i];
+            let p1 = points[(i + 1) % points.len()];
+            area += p0.x * p1.y - p1.x * p0.y;
+        }
+        if area.abs() < 1e-12 {
+            return
+        }
+        if area < 0.0 {
+            points.reverse();
+        }
+        out.push(SvgPolyline {points, closed: true});
+    };
+    let circle = | c: Point | -> Vec<Point> {
+        let steps = circle_steps(hw, tolerance);
+        (0..steps).map( | i | {
+            let a = i as f64 * 2.0 * PI / steps as f64;
+            Point::new(c.x + hw * a.cos(), c.y + hw * a.sin())
+        }).collect()
+    };
+    for polyline in polylines {
+        let points = &polyline.points;
+        let n = points.len();
+        if n < 2 {
+            continue;
+        }
+        let segment_count = if polyline.closed {n} else {n - 1};
+        let dir = | i: usize | -> Vector {
+            (points[(i + 1) % n] - points[i]).normalize().unwrap_or(Vector::zero())
+        };
+        for i in 0..segment_count {
+            let p0 = points[i];
+            let p1 = points[(i + 1) % n];
+            let d = dir(i);
+            let nv = Vector::new(-d.y, d.x) * hw;
+            push(vec![p0 + nv, p1 + nv, p1 - nv, p0 - nv]);
+        }
+        // the joins between segments
+        let joins = if polyline.closed {0..n} else {1..n - 1};
+        for i in joins {
+            let d0 = dir((i + n - 1) % n);
+            let d1 = dir(i);
+            let v = points[i];
+            let cross = d0.cross(d1);
+            if cross.abs() < 1e-9 && d0.dot(d1) > 0.0 {
+                continue;
+            }
+            match style.line_join {
+                SvgLineJoin::Round => push(circle(v)),
+                SvgLineJoin::Miter | SvgLineJoin::Bevel => {
+                    // the outer side of the turn is opposite to the side it turns to
+                    let s = if cross > 0.0 {-hw} else {hw};
+                    let n0 = Vector::new(-d0.y, d0.x);
+                    let n1 = Vector::new(-d1.y, d1.x);
+                    let m = n0 + n1;
+                    let m_len2 = m.dot(m);
+                    let miter_ratio = if m_len2 > 1e-12 {2.0 / m_len2.sqrt()} else {f64::INFINITY};
+                    if style.line_join == SvgLineJoin::Miter && miter_ratio <= style.miter_limit {
+                        let tip = v + m * (s * 2.0 / m_len2);
+                        push(vec![v, v + n0 * s, tip, v + n1 * s]);
+                    }
+                    else {
+                        push(vec![v, v + n0 * s, v + n1 * s]);
+                    }
+                }
+            }
+        }
+        if !polyline.closed {
+            for (p, d) in [(points[0], dir(0) * -1.0), (points[n - 1], dir(n - 2))] {
+                match style.line_cap {
+                    SvgLineCap::Butt => (),
+                    SvgLineCap::Round => push(circle(p)),
+                    SvgLineCap::Square => {
+                        let nv = Vector::new(-d.y, d.x) * hw;
+                        let e = d * hw;
+                        push(vec![p + nv, p + nv + e, p - nv + e, p - nv]);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+fn circle_steps(radius: f64, tolerance: f64) -> usize {
+    if radius <= tolerance {
+        return 8
+    }
+    let steps = (PI / (1.0 - tolerance / radius).acos()).ceil();
+    (steps as usize).clamp(8, 128)
+}
+
+/// Parses the `d` attribute of an SVG path element
+pub fn parse_svg_path(path: &[u8]) -> Result<Vec<PathCommand>, String> {
+    #[derive(Debug)]
+    enum Cmd {
+        Unknown,
+        Move(bool),
+        Hor(bool),
+        Vert(bool),
+        Line(bool),
+        Arc(bool),
+        Cubic(bool),
+        CubicSmooth(bool),
+        Quadratic(bool),
+        Close
+    }
+    impl Default for Cmd {fn default() -> Self {Self::Unknown}}
+
+    #[derive(Default)]
+    struct ParseState {
+        cmd: Cmd,
+        expect_nums: usize,
+        chain: bool,
+        nums: [f64; 7],
+        num_count: usize,
+        last_pt: Point,
+        last_ctl_pt: Option<Point>,
+        out: Vec<PathCommand>,
+        num_state: Option<NumState>
+    }
+
+    #[derive(Debug)]
+    struct NumState {
+        num: f64,
+        mul: f64,
+        has_dot: bool,
+    }
+
+    impl NumState {
+        fn new_pos(v: f64) -> Self {Self {num: v, mul: 1.0, has_dot: false}}
+        fn new_min() -> Self {Self {num: 0.0, mul: -1.0, has_dot: false}}
+        fn finalize(self) -> f64 {self.num * self.mul}
+        fn add_digit(&mut self, digit: f64) {
+            self.num *= 10.0;
+            self.num += digit;
+            if self.has_dot {
+                self.mul *= 0.1;
+            }
+        }
+    }
+
+    impl ParseState {
+        fn next_cmd(&mut self, cmd: Cmd) -> Result<(), String> {
+            self.finalize_cmd() ?;
+            self.chain = false;
+            self.expect_nums = match cmd {
+                Cmd::Unknown => panic!(),
+                Cmd::Move(_) => 2,
+                Cmd::Hor(_) => 1,
+                Cmd::Vert(_) => 1,
+                Cmd::Line(_) => 2,
+                Cmd::Cubic(_) => 6,
+                Cmd::CubicSmooth(_) => 4,
+                Cmd::Arc(_) => 7,
+                Cmd::Quadratic(_) => 4,
+                Cmd::Close => 0
+            };
+            self.cmd = cmd;
+            Ok(())
+        }
+
+        fn add_min(&mut self) -> Result<(), String> {
+            if self.num_state.is_some() {
+                self.finalize_num();
+            }
+            if self.expect_nums == self.num_count {
+                self.finalize_cmd() ?;
+            }
+            if self.expect_nums == 0 {
+                return Err(format!("Unexpected minus"));
+            }
+            self.num_state = Some(NumState::new_min());
+            Ok(())
+        }
+
+        fn add_digit(&mut self, digit: f64) -> Result<(), String> {
+            if let Some(num_state) = &mut self.num_state {
+                num_state.add_digit(digit);
+            }
+            else {
+                if self.expect_nums == self.num_count {
+                    self.finalize_cmd() ?;
+                }
+                if self.expect_nums == 0 {
+                    return Err(format!("Unexpected digit"));
+                }
+                self.num_state = Some(NumState::new_pos(digit))
+            }
+            Ok(())
+        }
+
+        fn add_dot(&mut self) -> Result<(), String> {
+            if let Some(num_state) = &mut self.num_state {
+                if num_state.has_dot {
+                    self.finalize_num();
+                    self.add_digit(0.0) ?;
+                    self.add_dot() ?;
+                    return Ok(());
+                }
+                num_state.has_dot = true;
+            }
+            else {
+                self.add_digit(0.0) ?;
+                self.add_dot() ?;
+            }
+            Ok(())
+        }
+
+        fn finalize_num(&mut self) {
+            if let Some(num_state) = self.num_state.take() {
+                self.nums[self.num_count] = num_state.finalize();
+                self.num_count += 1;
+            }
+        }
+
+        fn whitespace(&mut self) -> Result<(), String> {
+            self.finalize_num();
+            if self.expect_nums == self.num_count {
+                self.finalize_cmd() ?;
+            }
+            Ok(())
+        }
+
+        fn finalize_cmd(&mut self) -> Result<(), String> {
+            self.finalize_num();
+            if self.chain && self.num_count == 0 {
+                return Ok(())
+            }
+            if self.expect_nums != self.num_count {
+                return Err(format!("SVG Path command {:?} expected {} points, got {}", self.cmd, self.expect_nums, self.num_count));
+            }
+            match self.cmd {
+                Cmd::Unknown => (),
+                Cmd::Move(abs) => {
+                    self.last_ctl_pt = None;
+                    if abs {
+                        self.last_pt = Point {x: self.nums[0], y: self.nums[1]};
+                    }
+                    else {
+                        self.last_pt += Vector {x: self.nums[0], y: self.nums[1]};
+                    }
+                    self.out.push(PathCommand::MoveTo(self.last_pt));
+                    // coordinate pairs after a moveto are implicit linetos
+                    self.cmd = Cmd::Line(abs);
+                },
+                Cmd::Hor(abs) => {
+                    self.last_ctl_pt = None;
+                    if abs {
+                        self.last_pt = Point {x: self.nums[0], y: self.last_pt.y};
+                    }
+                    else {
+                        self.last_pt += Vector {x: self.nums[0], y: 0.0};
+                    }
+                    self.out.push(PathCommand::LineTo(self.last_pt));
+                }
+                Cmd::Vert(abs) => {
+                    self.last_ctl_pt = None;
+                    if abs {
+                        self.last_pt = Point {x: self.last_pt.x, y: self.nums[0]};
+                    }
+                    else {
+                        self.last_pt += Vector {x: 0.0, y: self.nums[0]};
+                    }
+                    self.out.push(PathCommand::LineTo(self.last_pt));
+                }
+                Cmd::Line(abs) => {
+                    self.last_ctl_pt = None;
+                    if abs {
+                        self.last_pt = Point {x: self.nums[0], y: self.nums[1]};
+                    }
+                    else {
+                        self.last_pt += Vector {x: self.nums[0], y: self.nums[1]};
+                    }
+                    self.out.push(PathCommand::LineTo(self.last_pt));
+                },
+                Cmd::Cubic(abs) => {
+                    if abs {
+                        self.last_pt = Point {x: self.nums[4], y: self.nums[5]};
+                        self.out.push(PathCommand::CubicTo(
+                            Point {x: self.nums[0], y: self.nums[1]},
+                            Point {x: self.nums[2], y: self.nums[3]},
+                            self.last_pt,
+                        ));
+                        self.last_ctl_pt = Some(Point {x: self.nums[2], y: self.nums[3]});
+                    } else {
+                        self.out.push(PathCommand::CubicTo(
+                            self.last_pt + Vector {x: self.nums[0], y: self.nums[1]},
+                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]},
+                            self.last_pt + Vector {x: self.nums[4], y: self.nums[5]},
+                        ));
+                        self.last_ctl_pt = Some(
+                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]});
+                        self.last_pt += Vector {x: self.nums[4], y: self.nums[5]};
+                    }
+                },
+                Cmd::CubicSmooth(abs) => {
+                    // Calculate absolute x1, y1 for control point 1
+                    let a_cp1 = if self.last_ctl_pt.is_none() {
+                        self.last_pt
+                    } else {
+                        self.last_pt + (self.last_pt - self.last_ctl_pt.unwrap())
+                    };
+
+                    // Transform s command into c command
+                    let mut tmp = [0.0; 4];
+                    tmp.copy_from_slice(&self.nums[0..4]);
+                    self.nums[2..6].copy_from_slice(&tmp);
+
+                    if abs {
+                        self.nums[0..2].copy_from_slice(&[a_cp1.x, a_cp1.y]);
+                        self.last_pt = Point {x: self.nums[4], y: self.nums[5]};
+                        self.out.push(PathCommand::CubicTo(
+                            Point {x: self.nums[0], y: self.nums[1]},
+                            Point {x: self.nums[2], y: self.nums[3]},
+                            self.last_pt,
+                        ));
+                        self.last_ctl_pt = Some(Point {x: self.nums[2], y: self.nums[3]});
+                    } else {
+                        let r_cp1 = a_cp1 - self.last_pt;
+                        self.nums[0..2].copy_from_slice(&[r_cp1.x, r_cp1.y]);
+                        self.out.push(PathCommand::CubicTo(
+                            self.last_pt + Vector {x: self.nums[0], y: self.nums[1]},
+                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]},
+                            self.last_pt + Vector {x: self.nums[4], y: self.nums[5]},
+                        ));
+                        self.last_ctl_pt = Some(
+                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]});
+                        self.last_pt += Vector {x: self.nums[4], y: self.nums[5]};
+                    }
+                },
+                Cmd::Arc(abs) => {
+                    self.last_ctl_pt = None;
+                    if abs {
+                        self.last_pt = Point {x: self.nums[5], y: self.nums[6]};
+                        self.out.push(PathCommand::ArcTo(
+                            self.last_pt,
+                            Point {x: self.nums[0], y: self.nums[1]},
+                            self.nums[2],
+                            self.nums[3] != 0.0,
+                            self.nums[4] != 0.0,
+                        ));
+                    }
+                    else {
+                        self.out.push(PathCommand::ArcTo(
+                            self.last_pt + Vector {x: self.nums[5], y: self.nums[6]},
+                            Point {x: self.nums[0], y: self.nums[1]},
+                            self.nums[2],
+                            self.nums[3] != 0.0,
+                            self.nums[4] != 0.0,
+                        ));
+                        self.last_pt += Vector {x: self.nums[5], y: self.nums[6]};
+                    }
+                },
+                Cmd::Quadratic(abs) => {
+                    self.last_ctl_pt = None;
+                    if abs {
+                        self.last_pt = Point {x: self.nums[2], y: self.nums[3]};
+                        self.out.push(PathCommand::QuadraticTo(
+                            Point {x: self.nums[0], y: self.nums[1]},
+                            self.last_pt
+                        ));
+                    }
+                    else {
+                        self.out.push(PathCommand::QuadraticTo(
+                            self.last_pt + Vector {x: self.nums[0], y: self.nums[1]},
+                            self.last_pt + Vector {x: self.nums[2], y: self.nums[3]},
+                        ));
+                        self.last_pt += Vector {x: self.nums[2], y: self.nums[3]};
+                    }
+                }
+                Cmd::Close => {
+                    self.last_ctl_pt = None;
+                    // a new subpath starts where the closed one started
+                    if let Some(start) = self.out.iter().rev().find_map( | cmd | if let PathCommand::MoveTo(p) = cmd {Some(*p)} else {None}) {
+                        self.last_pt = start;
+                    }
+                    self.out.push(PathCommand::Close);
+                }
+            }
+            self.num_count = 0;
+            self.chain = true;
+            Ok(())
+        }
+    }
+
+    let mut state = ParseState::default();
+    for i in 0..path.len() {
+        match path[i] {
+            b'M' => state.next_cmd(Cmd::Move(true)) ?,
+            b'm' => state.next_cmd(Cmd::Move(false)) ?,
+            b'Q' => state.next_cmd(Cmd::Quadratic(true)) ?,
+            b'q' => state.next_cmd(Cmd::Quadratic(false)) ?,
+            b'C' => state.next_cmd(Cmd::Cubic(true)) ?,
+            b'c' => state.next_cmd(Cmd::Cubic(false)) ?,
+            b'S' => state.next_cmd(Cmd::CubicSmooth(true)) ?,
+            b's' => state.next_cmd(Cmd::CubicSmooth(false)) ?,
+            b'H' => state.next_cmd(Cmd::Hor(true)) ?,
+            b'h' => state.next_cmd(Cmd::Hor(false)) ?,
+            b'V' => state.next_cmd(Cmd::Vert(true)) ?,
+            b'v' => state.next_cmd(Cmd::Vert(false)) ?,
+            b'L' => state.next_cmd(Cmd::Line(true)) ?,
+            b'l' => state.next_cmd(Cmd::Line(false)) ?,
+            b'A' => state.next_cmd(Cmd::Arc(true)) ?,
+            b'a' => state.next_cmd(Cmd::Arc(false)) ?,
+            b'Z' | b'z' => state.next_cmd(Cmd::Close) ?,
+            b'-' => state.add_min() ?,
+            b'0'..=b'9' => state.add_digit((path[i] - b'0') as f64) ?,
+            b'.' => state.add_dot() ?,
+            b',' | b' ' | b'\r' | b'\n' | b'\t' => state.whitespace() ?,
+            x => {
+                return Err(format!("Unexpected character {} - {}", x, x as char))
+            }
+        }
+    }
+    state.finalize_cmd() ?;
+
+    Ok(state.out)
+}
+
+/// The element tree of the document, made from the flat node list of the html parser
+struct SvgElement<'a> {
+    tag: LiveId,
+    attrs: Vec<(LiveId, &'a str)>,
+    children: Vec<SvgElement<'a>>,
+}
+
+impl<'a> SvgElement<'a> {
+    fn from_html_nodes(decoded: &'a str, nodes: &[HtmlNode]) -> SvgElement<'a> {
+        let mut stack = vec![SvgElement {tag: LiveId(0), attrs: Vec::new(), children: Vec::new()}];
+        for node in nodes {
+            match node {
+                HtmlNode::OpenTag {lc, ..} => {
+                    stack.push(SvgElement {tag: *lc, attrs: Vec::new(), children: Vec::new()});
+                }
+                HtmlNode::Attribute {lc, start, end, ..} => {
+                    if let Some(element) = stack.last_mut() {
+                        element.attrs.push((*lc, &decoded[*start..*end]));
+                    }
+                }
+                HtmlNode::CloseTag {lc, ..} => {
+                    // close up to the matching open tag, unmatched close tags are ignored
+                    if let Some(index) = stack.iter().rposition( | element | element.tag == *lc) {
+                        while stack.len() > index.max(1) {
+                            let element = stack.pop().unwrap();
+                            stack.last_mut().unwrap().children.push(element);
+                        }
+                    }
+                }
+                HtmlNode::Text {..} => ()
+            }
+        }
+        while stack.len() > 1 {
+            let element = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(element);
+        }
+        stack.pop().unwrap()
+    }
+
+    fn attr(&self, name: &str) -> Option<&'a str> {
+        let id = LiveId::from_str_lc(name);
+        self.attrs.iter().find( | (attr, _) | *attr == id).map( | (_, value) | *value)
+    }
+
+    fn find_first(&self, tag: LiveId) -> Option<&SvgElement<'a>> {
+        if self.tag == tag {
+            return Some(self)
+        }
+        self.children.iter().find_map( | child | child.find_first(tag))
+    }
+
+    fn collect_gradients(&self, gradients: &mut HashMap<String, RawGradient>) {
+        let linear = self.tag == live_id!(lineargradient);
+        if linear || self.tag == live_id!(radialgradient) {
+            if let Some(id) = self.attr("id") {
+                gradients.insert(id.to_string(), RawGradient::from_element(self, linear));
+            }
+            return
+        }
+        for child in &self.children {
+            child.collect_gradients(gradients);
+        }
+    }
+}
+
+/// A gradient element before the stops and attributes it references with `href` are resolved
+#[derive(Clone)]
+struct RawGradient {
+    linear: bool,
+    href: Option<String>,
+    attrs: Vec<(LiveId, String)>,
+    stops: Vec<SvgGradientStop>,
+}
+
+impl RawGradient {
+    fn from_element(element: &SvgElement, linear: bool) -> Self {
+        let href = element.attr("href").or_else( || element.attr("xlink:href"))
+            .and_then( | href | href.trim().strip_prefix('#')).map( | href | href.to_string());
+        let mut stops = Vec::new();
+        for stop in element.children.iter().filter( | child | child.tag == live_id!(stop)) {
+            let mut color = parse_color(stop.attr("stop-color").unwrap_or("black"));
+            let mut opacity = stop.attr("stop-opacity").and_then(parse_fraction).unwrap_or(1.0);
+            let mut offset = stop.attr("offset").and_then(parse_fraction).unwrap_or(0.0);
+            if let Some(style) = stop.attr("style") {
+                for (key, value) in parse_style(style) {
+                    match key {
+                        "stop-color" => color = parse_color(value),
+                        "stop-opacity" => opacity = parse_fraction(value).unwrap_or(1.0),
+                        "offset" => offset = parse_fraction(value).unwrap_or(offset),
+                        _ => ()
+                    }
+                }
+            }
+            let color = match color {
+                Some(PaintSpec::Color(c)) => c,
+                _ => vec4(0.0, 0.0, 0.0, 1.0)
+            };
+            // offsets can't go backwards, later stops are moved up to the earlier ones
+            let offset = offset.clamp(0.0, 1.0).max(stops.last().map( | s: &SvgGradientStop | s.offset).unwrap_or(0.0));
+            stops.push(SvgGradientStop {offset, color: vec4(color.x, color.y, color.z, color.w * opacity as f32)});
+        }
+        RawGradient {
+            linear,
+            href,
+            attrs: element.attrs.iter().map( | (id, value) | (*id, value.to_string())).collect(),
+            stops,
+        }
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        let id = LiveId::from_str_lc(name);
+        self.attrs.iter().find( | (attr, _) | *attr == id).map( | (_, value) | value.as_str())
+    }
+}
+
+fn resolve_gradients(raw: HashMap<String, RawGradient>) -> HashMap<String, Rc<SvgGradient>> {
+    let mut gradients = HashMap::new();
+    for (id, gradient) in &raw {
+        // follow the href chain for stops and attributes that are not set here
+        let mut chain = vec![gradient];
+        while let Some(next) = chain.last().unwrap().href.as_ref().and_then( | href | raw.get(href)) {
+            if chain.len() > 16 || chain.iter().any( | g | std::ptr::eq(*g, next)) {
+                break;
+            }
+            chain.push(next);
+        }
+        let attr = | name: &str | chain.iter().find_map( | g | g.attr(name));
+        let stops = chain.iter().find( | g | !g.stops.is_empty()).map( | g | g.stops.clone()).unwrap_or_default();
+        let object_bounding_box = attr("gradientUnits") != Some("userSpaceOnUse");
+        let coord = | name: &str, default: f64 | {
+            attr(name).and_then( | v | parse_length(v, if object_bounding_box {1.0} else {100.0})).unwrap_or(default)
+        };
+        let kind = if gradient.linear {
+            SvgGradientKind::Linear {
+                x1: coord("x1", 0.0),
+                y1: coord("y1", 0.0),
+                x2: coord("x2", if object_bounding_box {1.0} else {100.0}),
+                y2: coord("y2", 0.0),
+            }
+        }
+        else {
+            let half = if object_bounding_box {0.5} else {50.0};
+            SvgGradientKind::Radial {
+                cx: coord("cx", half),
+                cy: coord("cy", half),
+                r: coord("r", half),
+            }
+        };
+        let transform = attr("gradientTransform").map(parse_transform).unwrap_or_default();
+        gradients.insert(id.clone(), Rc::new(SvgGradient {kind, object_bounding_box, transform, stops}));
+    }
+    gradients
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PaintSpec {
+    Color(Vec4),
+    Url(String),
+    CurrentColor,
+}
+
+#[derive(Clone)]
+struct SvgStyle {
+    fill: Option<PaintSpec>,
+    fill_opacity: f64,
+    fill_rule: FillRule,
+    stroke: Option<PaintSpec>,
+    stroke_opacity: f64,
+    stroke_style: SvgStrokeStyle,
+    opacity: f64,
+    color: Vec4,
+    visible: bool,
+}
+
+impl Default for SvgStyle {
+    fn default() -> Self {
+        Self {
+            fill: Some(PaintSpec::Color(vec4(0.0, 0.0, 0.0, 1.0))),
+            fill_opacity: 1.0,
+            fill_rule: FillRule::NonZero,
+            stroke: None,
+            stroke_opacity: 1.0,
+            stroke_style: SvgStrokeStyle::default(),
+            opacity: 1.0,
+            color: vec4(0.0, 0.0, 0.0, 1.0),
+            visible: true,
+        }
+    }
+}
+
+impl SvgStyle {
+    /// The style of an element, inherited from its parent and set by its presentation
+    /// attributes, which the `style` attribute overrides
+    fn for_element(&self, element: &SvgElement) -> SvgStyle {
+        let mut style = self.clone();
+        // display and opacity are not inherited, but applying them to the
+        // children of a group gives the same result for everything we draw
+        for (id, value) in &element.attrs {
+            style.apply(*id, value);
+        }
+        if let Some(css) = element.attr("style") {
+            for (key, value) in parse_style(css) {
+                style.apply(LiveId::from_str_lc(key), value);
+            }
+        }
+        style
+    }
+
+    fn apply(&mut self, id: LiveId, value: &str) {
+        let value = value.trim();
+        if value == "inherit" {
+            return
+        }
+        if id == LiveId::from_str_lc("fill") {
+            self.fill = parse_color(value);
+        }
+        else if id == LiveId::from_str_lc("stroke") {
+            self.stroke = parse_color(value);
+        }
+        else if id == LiveId::from_str_lc("fill-opacity") {
+            self.fill_opacity = parse_fraction(value).unwrap_or(1.0);
+        }
+        else if id == LiveId::from_str_lc("stroke-opacity") {
+            self.stroke_opacity = parse_fraction(value).unwrap_or(1.0);
+        }
+        else if id == LiveId::from_str_lc("opacity") {
+            self.opacity *= parse_fraction(value).unwrap_or(1.0);
+        }
+        else if id == LiveId::from_str_lc("fill-rule") {
+            self.fill_rule = if value == "evenodd" {FillRule::EvenOdd} else {FillRule::NonZero};
+        }
+        else if id == LiveId::from_str_lc("stroke-width") {
+            if let Some(width) = parse_length(value, 100.0) {
+                self.stroke_style.width = width;
+            }
+        }
+        else if id == LiveId::from_str_lc("stroke-linecap") {
+            self.stroke_style.line_cap = match value {
+                "round" => SvgLineCap::Round,
+                "square" => SvgLineCap::Square,
+                _ => SvgLineCap::Butt,
+            };
+        }
+        else if id == LiveId::from_str_lc("stroke-linejoin") {
+            self.stroke_style.line_join = match value {
+                "round" => SvgLineJoin::Round,
+                "bevel" => SvgLineJoin::Bevel,
+                _ => SvgLineJoin::Miter,
+            };
+        }
+        else if id == LiveId::from_str_lc("stroke-miterlimit") {
+            if let Some(limit) = parse_number(value) {
+                self.stroke_style.miter_limit = limit.max(1.0);
+            }
+        }
+        else if id == LiveId::from_str_lc("color") {
+            if let Some(PaintSpec::Color(color)) = parse_color(value) {
+                self.color = color;
+            }
+        }
+        else if id == LiveId::from_str_lc("display") {
+            if value == "none" {
+                self.visible = false;
+            }
+        }
+        else if id == LiveId::from_str_lc("visibility") {
+            self.visible = value != "hidden" && value != "collapse";
+        }
+    }
+}
+
+struct SvgParser {
+    gradients: HashMap<String, Rc<SvgGradient>>,
+    viewport: DVec2,
+    in_svg: bool,
+    shapes: Vec<SvgShape>,
+}
+
+impl SvgParser {
+    fn walk(&mut self, element: &SvgElement, parent_style: &SvgStyle, parent_transform: &SvgTransform) {
+        let style = parent_style.for_element(element);
+        if !style.visible && element.children.is_empty() {
+            return
+        }
+        let mut transform = *parent_transform;
+        if let Some(t) = element.attr("transform") {
+            transform = transform.then_after(&parse_transform(t));
+        }
+        let tag = element.tag;
+        if tag == live_id!(svg) || tag == live_id!(g) || tag == live_id!(a) || tag == live_id!(switch) {
+            if tag == live_id!(svg) && self.in_svg {
+                // nested svg elements are placed at their x and y
+                let x = element.attr("x").and_then( | v | parse_length(v, self.viewport.x)).unwrap_or(0.0);
+                let y = element.attr("y").and_then( | v | parse_length(v, self.viewport.y)).unwrap_or(0.0);
+                transform = transform.then_after(&SvgTransform::translation(x, y));
+            }
+            let in_svg = std::mem::replace(&mut self.in_svg, true);
+            for child in &element.children {
+                self.walk(child, &style, &transform);
+            }
+            self.in_svg = in_svg;
+            return
+        }
+        if !style.visible {
+            return
+        }
+        let path = match self.shape_path(element) {
+            Some(path) if !path.is_empty() => path,
+            _ => return
+        };
+        let resolve = | paint: &Option<PaintSpec> | -> Option<SvgPaint> {
+            match paint.as_ref() ? {
+                PaintSpec::Color(color) => Some(SvgPaint::Color(*color)),
+                PaintSpec::CurrentColor => Some(SvgPaint::Color(style.color)),
+                PaintSpec::Url(id) => self.gradients.get(id).map( | g | SvgPaint::Gradient(g.clone())),
+            }
+        };
+        // lines and polylines are never filled
+        let fill = if tag == live_id!(line) {None} else {resolve(&style.fill)};
+        let stroke = resolve(&style.stroke);
+        if fill.is_none() && stroke.is_none() {
+            return
+        }
+        self.shapes.push(SvgShape {
+            path,
+            transform,
+            fill,
+            fill_opacity: style.fill_opacity * style.opacity,
+            fill_rule: style.fill_rule,
+            stroke,
+            stroke_opacity: style.stroke_opacity * style.opacity,
+            stroke_style: style.stroke_style,
+        });
+    }
+
+    fn shape_path(&self, element: &SvgElement) -> Option<Vec<PathCommand>> {
+        let len_x = | name: &str | element.attr(name).and_then( | v | parse_length(v, self.viewport.x));
+        let len_y = | name: &str | element.attr(name).and_then( | v | parse_length(v, self.viewport.y));
+        let len_r = | name: &str | element.attr(name).and_then( | v | parse_length(v, self.viewport.length() / 2f64.sqrt()));
+        let p = | x: f64, y: f64 | Point::new(x, y);
+        let tag = element.tag;
+        if tag == live_id!(path) {
+            let d = element.attr("d") ?;
+            return match parse_svg_path(d.as_bytes()) {
+                Ok(path) => Some(path),
+                Err(e) => {
+                    log!("Error in SVG path {}", e);
+                    None
+                }
+            }
+        }
+        if tag == live_id!(rect) {
+            let (x, y) = (len_x("x").unwrap_or(0.0), len_y("y").unwrap_or(0.0));
+            let (w, h) = (len_x("width") ?, len_y("height") ?);
+            if w <= 0.0 || h <= 0.0 {
+                return None
+            }
+            let (rx, ry) = match (len_x("rx"), len_y("ry")) {
+                (Some(rx), Some(ry)) => (rx, ry),
+                (Some(r), None) | (None, Some(r)) => (r, r),
+                (None, None) => (0.0, 0.0)
+            };
+            let (rx, ry) = (rx.clamp(0.0, w / 2.0), ry.clamp(0.0, h / 2.0));
+            if rx == 0.0 || ry == 0.0 {
+                return Some(vec![
+                    PathCommand::MoveTo(p(x, y)),
+                    PathCommand::LineTo(p(x + w, y)),
+                    PathCommand::LineTo(p(x + w, y + h)),
+                    PathCommand::LineTo(p(x, y + h)),
+                    PathCommand::Close,
+                ])
+            }
+            let r = p(rx, ry);
+            return Some(vec![
+                PathCommand::MoveTo(p(x + rx, y)),
+                PathCommand::LineTo(p(x + w - rx, y)),
+                PathCommand::ArcTo(p(x + w, y + ry), r, 0.0, false, true),
+                PathCommand::LineTo(p(x + w, y + h - ry)),
+                PathCommand::ArcTo(p(x + w - rx, y + h), r, 0.0, false, true),
+                PathCommand::LineTo(p(x + rx, y + h)),
+                PathCommand::ArcTo(p(x, y + h - ry), r, 0.0, false, true),
+                PathCommand::LineTo(p(x, y + ry)),
+                PathCommand::ArcTo(p(x + rx, y), r, 0.0, false, true),
+                PathCommand::Close,
+            ])
+        }
+        if tag == live_id!(circle) || tag == live_id!(ellipse) {
+            let (cx, cy) = (len_x("cx").unwrap_or(0.0), len_y("cy").unwrap_or(0.0));
+            let (rx, ry) = if tag == live_id!(circle) {
+                let r = len_r("r") ?;
+                (r, r)
+            }
+            else {
+                (len_x("rx") ?, len_y("ry") ?)
+            };
+            if rx <= 0.0 || ry <= 0.0 {
+                return None
+            }
+            let r = p(rx, ry);
+            return Some(vec![
+                PathCommand::MoveTo(p(cx + rx, cy)),
+                PathCommand::ArcTo(p(cx - rx, cy), r, 0.0, false, true),
+                PathCommand::ArcTo(p(cx + rx, cy), r, 0.0, false, true),
+                PathCommand::Close,
+            ])
+        }
+        if tag == live_id!(line) {
+            return Some(vec![
+                PathCommand::MoveTo(p(len_x("x1").unwrap_or(0.0), len_y("y1").unwrap_or(0.0))),
+                PathCommand::LineTo(p(len_x("x2").unwrap_or(0.0), len_y("y2").unwrap_or(0.0))),
+            ])
+        }
+        if tag == live_id!(polyline) || tag == live_id!(polygon) {
+            let nums = parse_numbers(element.attr("points") ?);
+            let mut path = Vec::new();
+            for (i, pt) in nums.chunks_exact(2).enumerate() {
+                path.push(if i == 0 {PathCommand::MoveTo(p(pt[0], pt[1]))} else {PathCommand::LineTo(p(pt[0], pt[1]))});
+            }
+            if tag == live_id!(polygon) && !path.is_empty() {
+                path.push(PathCommand::Close);
+            }
+            return Some(path)
+        }
+        // everything else (defs, clipPath, text, ...) is not drawn
+        None
+    }
+}
+
+/// Parses a list of numbers separated by whitespace and/or commas, like a viewBox or a list of points
+pub fn parse_numbers(s: &str) -> Vec<f64> {
+    let mut out = Vec::new();
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c == b'-' || c == b'+' || c == b'.' || c.is_ascii_digit() {
+            let start = i;
+            let mut seen_dot = c == b'.';
+            let mut seen_exp = false;
+            i += 1;
+            while i < bytes.len() {
+                let c = bytes[i];
+                if c.is_ascii_digit() {
+                }
+                else if c == b'.' && !seen_dot && !seen_exp {
+                    seen_dot = true;
+                }
+                else if (c == b'e' || c == b'E') && !seen_exp {
+                    seen_exp = true;
+                    if i + 1 < bytes.len() && (bytes[i + 1] == b'-' || bytes[i + 1] == b'+') {
+                        i += 1;
+                    }
+                }
+                else {
+                    break;
+                }
+                i += 1;
+            }
+            if let Ok(v) = s[start..i].parse::<f64>() {
+                out.push(v);
+            }
+        }
+        else {
+            i += 1;
+        }
+    }
+    out
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let end = s.find( | c: char | !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e' || c == 'E')).unwrap_or(s.len());
+    // a unit like the e in em would be eaten as exponent, strip it again
+    let num = s[..end].trim_end_matches(['e', 'E']);
+    num.parse().ok()
+}
+
+/// A length with an optional unit, percentages are relative to `reference`
+fn parse_length(s: &str, reference: f64) -> Option<f64> {
+    let s = s.trim();
+    let v = parse_number(s) ?;
+    if s.ends_with('%') {
+        return Some(v * reference / 100.0)
+    }
+    Some(v * unit_scale(s))
+}
+
+/// A length for the size of the document, percentages have no meaning there
+fn parse_absolute_length(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if s.ends_with('%') {
+        return None
+    }
+    parse_number(s).map( | v | v * unit_scale(s)).filter( | v | *v > 0.0)
+}
+
+fn unit_scale(s: &str) -> f64 {
+    if s.ends_with("pt") {96.0 / 72.0}
+    else if s.ends_with("pc") {16.0}
+    else if s.ends_with("mm") {96.0 / 25.4}
+    else if s.ends_with("cm") {96.0 / 2.54}
+    else if s.ends_with("in") {96.0}
+    else if s.ends_with("em") {16.0}
+    else {1.0}
+}
+
+/// A number or percentage that ends up between 0 and 1, like an opacity or a gradient offset
+fn parse_fraction(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let v = parse_number(s) ?;
+    Some(if s.ends_with('%') {v / 100.0} else {v}.clamp(0.0, 1.0))
+}
+
+fn parse_style(css: &str) -> impl Iterator<Item = (&str, &str)> {
+    css.split(';').filter_map( | decl | {
+        let (key, value) = decl.split_once(':') ?;
+        Some((key.trim(), value.trim()))
+    })
+}
+
+/// Parses a transform list like `translate(10 20) rotate(45)`
+pub fn parse_transform(s: &str) -> SvgTransform {
+    let mut transform = SvgTransform::identity();
+    let mut rest = s;
+    while let Some(open) = rest.find('(') {
+        let name = rest[..open].trim_matches( | c: char | c.is_whitespace() || c == ',');
+        let close = match rest[open..].find(')') {
+            Some(close) => open + close,
+            None => break
+        };
+        let v = parse_numbers(&rest[open + 1..close]);
+        let arg = | i: usize | v.get(i).copied().unwrap_or(0.0);
+        let t = match (name, v.len()) {
+            ("matrix", 6) => SvgTransform {a: v[0], b: v[1], c: v[2], d: v[3], e: v[4], f: v[5]},
+            ("translate", 1..=2) => SvgTransform::translation(arg(0), arg(1)),
+            ("scale", 1) => SvgTransform::scaling(v[0], v[0]),
+            ("scale", 2) => SvgTransform::scaling(v[0], v[1]),
+            ("rotate", 1) => SvgTransform::rotation(v[0]),
+            ("rotate", 3) => SvgTransform::translation(v[1], v[2])
+                .then_after(&SvgTransform::rotation(v[0]))
+                .then_after(&SvgTransform::translation(-v[1], -v[2])),
+            ("skewX", 1) => SvgTransform {c: (v[0] * PI / 180.0).tan(), ..SvgTransform::identity()},
+            ("skewY", 1) => SvgTransform {b: (v[0] * PI / 180.0).tan(), ..SvgTransform::identity()},
+            _ => SvgTransform::identity()
+        };
+        transform = transform.then_after(&t);
+        rest = &rest[close + 1..];
+    }
+    transform
+}
+
+fn parse_color(s: &str) -> Option<PaintSpec> {
+    let s = s.trim();
+    if s == "none" || s.is_empty() {
+        return None
+    }
+    if s == "currentColor" {
+        return Some(PaintSpec::CurrentColor)
+    }
+    if let Some(url) = s.strip_prefix("url(") {
+        // a fallback color after the url is ignored
+        let id = url.split(')').next().unwrap_or("").trim().trim_matches(['\'', '"']);
+        return Some(PaintSpec::Url(id.trim_start_matches('#').to_string()))
+    }
+    if let Some(hex) = s.strip_prefix('#') {
+        let digit = | i: usize | u8::from_str_radix(&hex[i..i + 1], 16).ok().map( | d | d as f32 / 15.0);
+        let byte = | i: usize | u8::from_str_radix(&hex[i..i + 2], 16).ok().map( | d | d as f32 / 255.0);
+        if !hex.is_ascii() {
+            return None
+        }
+        return match hex.len() {
+            3 => Some(PaintSpec::Color(vec4(digit(0) ?, digit(1) ?, digit(2) ?, 1.0))),
+            4 => Some(PaintSpec::Color(vec4(digit(0) ?, digit(1) ?, digit(2) ?, digit(3) ?))),
+            6 => Some(PaintSpec::Color(vec4(byte(0) ?, byte(2) ?, byte(4) ?, 1.0))),
+            8 => Some(PaintSpec::Color(vec4(byte(0) ?, byte(2) ?, byte(4) ?, byte(6) ?))),
+            _ => None
+        }
+    }
+    if let Some(args) = s.strip_prefix("rgba(").or_else( || s.strip_prefix("rgb(")) {
+        let args = args.trim_end_matches(')');
+        let parts: Vec<&str> = args.split( | c: char | c == ',' || c == '/' || c.is_whitespace()).filter( | p | !p.is_empty()).collect();
+        if parts.len() < 3 {
+            return None
+        }
+        let channel = | p: &str | -> Option<f32> {
+            let v = parse_number(p) ?;
+            Some(if p.ends_with('%') {v / 100.0} else {v / 255.0}.clamp(0.0, 1.0) as f32)
+        };
+        let alpha = parts.get(3).and_then( | p | parse_fraction(p)).unwrap_or(1.0) as f32;
+        return Some(PaintSpec::Color(vec4(channel(parts[0]) ?, channel(parts[1]) ?, channel(parts[2]) ?, alpha)))
+    }
+    let rgb = match s.to_ascii_lowercase().as_str() {
+        "black" => 0x000000,
+        "white" => 0xffffff,
+        "red" => 0xff0000,
+        "lime" => 0x00ff00,
+        "green" => 0x008000,
+        "blue" => 0x0000ff,
+        "yellow" => 0xffff00,
+        "cyan" | "aqua" => 0x00ffff,
+        "magenta" | "fuchsia" => 0xff00ff,
+        "gray" | "grey" => 0x808080,
+        "silver" => 0xc0c0c0,
+        "maroon" => 0x800000,
+        "olive" => 0x808000,
+        "purple" => 0x800080,
+        "teal" => 0x008080,
+        "navy" => 0x000080,
+        "orange" => 0xffa500,
+        "transparent" => return Some(PaintSpec::Color(vec4(0.0, 0.0, 0.0, 0.0))),
+        _ => return None
+    };
+    Some(PaintSpec::Color(vec4(
+        ((rgb >> 16) & 0xff) as f32 / 255.0,
+        ((rgb >> 8) & 0xff) as f32 / 255.0,
+        (rgb & 0xff) as f32 / 255.0,
+        1.0
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(x: f64, y: f64) -> Point {
+        Point::new(x, y)
+    }
+
+    fn color(paint: &Option<SvgPaint>) -> Option<Vec4> {
+        match paint {
+            Some(SvgPaint::Color(color)) => Some(*color),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn parses_path_commands() {
+        use PathCommand::*;
+        assert_eq!(parse_svg_path(b"M10 20 l5 0 h5 v-10 H0 V0 z m1 1").unwrap(), vec![
+            MoveTo(p(10.0, 20.0)),
+            LineTo(p(15.0, 20.0)),
+            LineTo(p(20.0, 20.0)),
+            LineTo(p(20.0, 10.0)),
+            LineTo(p(0.0, 10.0)),
+            LineTo(p(0.0, 0.0)),
+            Close,
+            // relative to the start of the closed subpath
+            MoveTo(p(11.0, 21.0)),
+        ]);
+        // pairs after a moveto are linetos, numbers can run into each other
+        assert_eq!(parse_svg_path(b"M1.5.5-2-3,4\n5").unwrap(), vec![
+            MoveTo(p(1.5, 0.5)),
+            LineTo(p(-2.0, -3.0)),
+            LineTo(p(4.0, 5.0)),
+        ]);
+        // the smooth cubic reflects the last control point
+        assert_eq!(parse_svg_path(b"M0 0C1 1 2 2 3 3S5 5 6 6s1 0 2 0").unwrap(), vec![
+            MoveTo(p(0.0, 0.0)),
+            CubicTo(p(1.0, 1.0), p(2.0, 2.0), p(3.0, 3.0)),
+            CubicTo(p(4.0, 4.0), p(5.0, 5.0), p(6.0, 6.0)),
+            CubicTo(p(7.0, 7.0), p(7.0, 6.0), p(8.0, 6.0)),
+        ]);
+        assert_eq!(parse_svg_path(b"m1 1 q1 0 2 2 Q0 0 1 1 a5 5 30 1 0 10 0").unwrap(), vec![
+            MoveTo(p(1.0, 1.0)),
+            QuadraticTo(p(2.0, 1.0), p(3.0, 3.0)),
+            QuadraticTo(p(0.0, 0.0), p(1.0, 1.0)),
+            ArcTo(p(11.0, 1.0), p(5.0, 5.0), 30.0, true, false),
+        ]);
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        assert!(parse_svg_path(b"M10").unwrap_err().contains("expected 2 points, got 1"));
+        assert!(parse_svg_path(b"M0 0 L1 2 3").is_err());
+        assert!(parse_svg_path(b"M0 0 X").unwrap_err().contains("Unexpected character"));
+        assert!(parse_svg_path(b"M0 0 Z 5").unwrap_err().contains("Unexpected digit"));
+        assert!(parse_svg_path(b"M0 0 Z -5").unwrap_err().contains("Unexpected minus"));
+
+        assert_eq!(SvgDocument::parse("<html><g/></html>").unwrap_err(), "No svg element found");
+        // a broken path is skipped, the rest of the document still parses
+        let doc = SvgDocument::parse(r#"<svg viewBox="0 0 10 10"><path d="M0 0 L"/><rect width="4" height="4"/></svg>"#).unwrap();
+        assert_eq!(doc.shapes.len(), 1);
+    }
+
+    #[test]
+    fn composes_transforms() {
+        let t = parse_transform("translate(10 20) scale(2)");
+        assert_eq!(t.apply(dvec2(1.0, 1.0)), dvec2(12.0, 22.0));
+        let t = parse_transform("scale(2), translate(10,20)");
+        assert_eq!(t.apply(dvec2(1.0, 1.0)), dvec2(22.0, 42.0));
+        let t = parse_transform("rotate(90 10 0)");
+        let r = t.apply(dvec2(20.0, 0.0));
+        assert!((r.x - 10.0).abs() < 1e-9 && (r.y - 10.0).abs() < 1e-9);
+        assert_eq!(parse_transform("matrix(1 0 0 1 3 4) bogus(1)"), SvgTransform::translation(3.0, 4.0));
+        let t = parse_transform("skewX(45) scale(2 3) translate(5)");
+        let back = t.invert().unwrap().apply(t.apply(dvec2(3.0, -7.0)));
+        assert!((back.x - 3.0).abs() < 1e-9 && (back.y + 7.0).abs() < 1e-9);
+        assert!(SvgTransform::scaling(0.0, 1.0).invert().is_none());
+
+        let doc = SvgDocument::parse(r#"<svg width="20" height="10" viewBox="0 0 40 20">
+            <g transform="translate(10,0)">
+                <g transform="scale(2)">
+                    <rect x="1" y="1" width="2" height="2" transform="translate(0 1)"/>
+                </g>
+            </g>
+        </svg>"#).unwrap();
+        assert_eq!(doc.size, dvec2(20.0, 10.0));
+        assert_eq!(doc.view_box, Rect {pos: dvec2(0.0, 0.0), size: dvec2(40.0, 20.0)});
+        assert_eq!(doc.shapes[0].transform.apply(dvec2(1.0, 1.0)), dvec2(12.0, 4.0));
+        assert_eq!(doc.bounds(), Rect {pos: dvec2(12.0, 4.0), size: dvec2(4.0, 4.0)});
+    }
+
+    #[test]
+    fn inherits_styles() {
+        let doc = SvgDocument::parse(r#"<svg viewBox="0 0 100 100">
+            <g fill="red" stroke="blue" stroke-width="3" opacity="0.5" color="lime">
+                <rect width="10" height="10" style="fill: #00f; stroke-linecap: round" fill-opacity="0.5"/>
+                <circle cx="5" cy="5" r="5" stroke="none" fill-rule="evenodd"/>
+                <g display="none"><rect width="10" height="10"/></g>
+                <line x2="10" y2="10" stroke="currentColor"/>
+                <g fill="none" stroke="inherit"><polygon points="0 0 10 0 10 10"/></g>
+            </g>
+            <rect width="1" height="1"/>
+        </svg>"#).unwrap();
+        let shapes = &doc.shapes;
+        assert_eq!(shapes.len(), 5);
+
+        assert_eq!(color(&shapes[0].fill), Some(vec4(0.0, 0.0, 1.0, 1.0)));
+        assert_eq!(color(&shapes[0].stroke), Some(vec4(0.0, 0.0, 1.0, 1.0)));
+        assert_eq!(shapes[0].fill_opacity, 0.25);
+        assert_eq!(shapes[0].stroke_opacity, 0.5);
+        assert_eq!(shapes[0].stroke_style.width, 3.0);
+        assert_eq!(shapes[0].stroke_style.line_cap, SvgLineCap::Round);
+
+        assert_eq!(color(&shapes[1].fill), Some(vec4(1.0, 0.0, 0.0, 1.0)));
+        assert!(shapes[1].stroke.is_none());
+        assert_eq!(shapes[1].fill_rule, FillRule::EvenOdd);
+
+        // lines are never filled, currentColor comes from the inherited color
+        assert!(shapes[2].fill.is_none());
+        assert_eq!(color(&shapes[2].stroke), Some(vec4(0.0, 1.0, 0.0, 1.0)));
+
+        assert!(shapes[3].fill.is_none());
+        assert_eq!(color(&shapes[3].stroke), Some(vec4(0.0, 0.0, 1.0, 1.0)));
+        assert_eq!(shapes[3].path.last(), Some(&PathCommand::Close));
+
+        // outside the group the defaults apply again
+        assert_eq!(color(&shapes[4].fill), Some(vec4(0.0, 0.0, 0.0, 1.0)));
+        assert!(shapes[4].stroke.is_none());
+        assert_eq!(shapes[4].fill_opacity, 1.0);
+    }
+
+    #[test]
+    fn resolves_gradient_references() {
+        let doc = SvgDocument::parse(r##"<svg viewBox="0 0 10 10">
+            <defs>
+                <linearGradient id="base"><stop offset="0" stop-color="#000"/><stop offset="50%" stop-color="#fff" stop-opacity="0.5"/></linearGradient>
+                <linearGradient id="derived" href="#base" x2="0" y2="1"/>
+            </defs>
+            <rect width="10" height="10" fill="url(#derived)"/>
+            <rect width="10" height="10" fill="url(#missing)"/>
+        </svg>"##).unwrap();
+        assert_eq!(doc.shapes.len(), 1);
+        let Some(SvgPaint::Gradient(gradient)) = &doc.shapes[0].fill else {panic!("expected a gradient")};
+        assert_eq!(gradient.kind, SvgGradientKind::Linear {x1: 0.0, y1: 0.0, x2: 0.0, y2: 1.0});
+        assert!(gradient.object_bounding_box);
+        assert_eq!(gradient.stops.len(), 2);
+        assert_eq!(gradient.stops[1].offset, 0.5);
+        assert_eq!(gradient.stops[1].color, vec4(1.0, 1.0, 1.0, 0.5));
+    }
+}
@@ -419,6 +419,23 @@ impl<'a> Cx2d<'a> {
         rect
     }
     
+    // moves a RightWrap turtle to the start of the next line, does nothing on an empty line
+    pub fn turtle_new_line(&mut self) {
+        let turtle = self.turtles.last_mut().unwrap();
+        let left = turtle.origin.x + turtle.layout.padding.left;
+        if turtle.pos.x > left {
+            turtle.pos.x = left;
+            turtle.pos.y = turtle.height_used + turtle.origin.y + turtle.layout.line_spacing;
+        }
+    }
+    
+    // moves to the start of a line at least spacing below everything walked so far
+    pub fn turtle_new_line_with_spacing(&mut self, spacing: f64) {
+        let turtle = self.turtles.last_mut().unwrap();
+        turtle.pos.x = turtle.origin.x + turtle.layout.padding.left;
+        turtle.pos.y = turtle.pos.y.max(turtle.height_used + turtle.origin.y + spacing);
+    }
+    
     pub fn walk_turtle(&mut self, walk: Walk) -> Rect {
         self.walk_turtle_move(walk, self.align_list.len())
     }
@@ -6,12 +6,32 @@ use std::collections::BinaryHeap;
 use std::mem;
 use std::ops::Range;
 
+/// The rule that decides which regions of a set of contours are inside.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FillRule {
+    /// A region is inside if its winding number is not zero.
+    #[default]
+    NonZero,
+    /// A region is inside if its winding number is odd.
+    EvenOdd,
+}
+
+impl FillRule {
+    fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
 /// Converts a sequence of line path commands to a sequence of trapezoids. The line path commands
 /// should define a set of closed contours.
 #[derive(Clone, Debug, Default)]
 pub struct Trapezoidator {
     event_queue: BinaryHeap<Event>,
     active_segments: Vec<ActiveSegment>,
+    fill_rule: FillRule,
 }
 
 impl Trapezoidator {
@@ -20,6 +40,11 @@ impl Trapezoidator {
         Trapezoidator::default()
     }
 
+    /// Sets the fill rule used by subsequent calls to `trapezoidate`.
+    pub fn set_fill_rule(&mut self, fill_rule: FillRule) {
+        self.fill_rule = fill_rule;
+    }
+
     /// Returns an iterator over trapezoids corresponding to the given iterator over line path
     /// commands.
     pub fn trapezoidate<P: LinePathIterator>(&mut self, path: P)->Option<Trapezoidate>{
@@ -212,6 +237,7 @@ impl Trapezoidator {
         incident_segment_range: &mut Range<usize>,
         right_segments: &[PendingSegment],
     ) {
+        let fill_rule = self.fill_rule;
         let mut lower_region = if incident_segment_range.end == 0 {
             Region {
                 is_inside: false,
@@ -226,7 +252,7 @@ impl Trapezoidator {
                 let upper_region = {
                     let winding = lower_region.winding + right_segment.winding;
                     Region {
-                        is_inside: winding != 0,
+                        is_inside: fill_rule.is_inside(winding),
                         winding,
                     }
                 };
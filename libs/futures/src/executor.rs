@@ -31,6 +31,7 @@ impl Executor {
 #[derive(Clone, Debug)]
 pub struct Spawner {
     task_sender: Sender<Arc<Task>>,
+    wake_hook: Option<fn()>,
 }
 
 impl Spawner {
@@ -39,10 +40,14 @@ impl Spawner {
             inner: Mutex::new(TaskInner {
                 future: Some(Box::pin(future)),
                 task_sender: self.task_sender.clone(),
+                wake_hook: self.wake_hook,
             }),
         })).is_err() {
             return Err(SpawnError::shutdown());
         }
+        if let Some(wake_hook) = self.wake_hook {
+            wake_hook();
+        }
         Ok(())
     }
 }
@@ -87,23 +92,109 @@ impl Task {
 
 impl Wake for Task {
     fn wake(self: Arc<Task>) {
-        self.inner
-            .lock()
-            .unwrap()
-            .task_sender
-            .send(self.clone())
-            .unwrap();
+        let wake_hook = {
+            let inner = self.inner.lock().unwrap();
+            // the executor may already be gone, in which case there is nothing left to run us
+            let _ = inner.task_sender.send(self.clone());
+            inner.wake_hook
+        };
+        if let Some(wake_hook) = wake_hook {
+            wake_hook();
+        }
     }
 }
 
 struct TaskInner {
     future: Option<Pin<Box<dyn Future<Output = ()> + 'static>>>,
     task_sender: Sender<Arc<Task>>,
+    wake_hook: Option<fn()>,
 }
 
 pub fn new_executor_and_spawner() -> (Executor, Spawner) {
+    new_executor_and_spawner_with_wake_hook(None)
+}
+
+/// Like `new_executor_and_spawner`, but calls `wake_hook` whenever a task is woken.
+/// Event loops use this to get scheduled again when a task is woken from another thread.
+pub fn new_executor_and_spawner_with_wake_hook(wake_hook: Option<fn()>) -> (Executor, Spawner) {
     use std::sync::mpsc;
 
     let (task_sender, task_receiver) = mpsc::channel();
-    (Executor { task_receiver }, Spawner { task_sender })
+    (Executor { task_receiver }, Spawner { task_sender, wake_hook })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::channel::oneshot,
+        std::{
+            cell::Cell,
+            rc::Rc,
+            sync::atomic::{AtomicUsize, Ordering},
+            task::{Poll, Waker},
+            thread,
+        },
+    };
+
+    #[test]
+    fn wake_hook_fires_on_wake() {
+        static WAKES: AtomicUsize = AtomicUsize::new(0);
+        fn wake_hook() {
+            WAKES.fetch_add(1, Ordering::SeqCst);
+        }
+        let (executor, spawner) = new_executor_and_spawner_with_wake_hook(Some(wake_hook));
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let polls = Rc::new(Cell::new(0));
+        spawner.spawn({
+            let waker = waker.clone();
+            let polls = polls.clone();
+            std::future::poll_fn(move | cx | {
+                polls.set(polls.get() + 1);
+                if polls.get() == 1 {
+                    *waker.lock().unwrap() = Some(cx.waker().clone());
+                    return Poll::Pending
+                }
+                Poll::Ready(())
+            })
+        }).unwrap();
+        assert_eq!(WAKES.load(Ordering::SeqCst), 1);
+
+        executor.run_until_stalled();
+        assert_eq!(polls.get(), 1);
+        // a pending task is not polled again until it is woken
+        executor.run_until_stalled();
+        assert_eq!(polls.get(), 1);
+
+        waker.lock().unwrap().take().unwrap().wake();
+        assert_eq!(WAKES.load(Ordering::SeqCst), 2);
+        executor.run_until_stalled();
+        assert_eq!(polls.get(), 2);
+    }
+
+    #[test]
+    fn completes_when_woken_from_another_thread() {
+        let (executor, spawner) = new_executor_and_spawner();
+        let (sender, receiver) = oneshot::channel();
+        let result = Rc::new(Cell::new(None));
+        spawner.spawn({
+            let result = result.clone();
+            async move {
+                result.set(receiver.await.ok());
+            }
+        }).unwrap();
+        executor.run_until_stalled();
+        assert_eq!(result.get(), None);
+
+        thread::spawn(move || sender.send(42).unwrap()).join().unwrap();
+        executor.run_until_stalled();
+        assert_eq!(result.get(), Some(42));
+    }
+
+    #[test]
+    fn spawn_fails_after_shutdown() {
+        let (executor, spawner) = new_executor_and_spawner();
+        drop(executor);
+        assert_eq!(spawner.spawn(async {}), Err(SpawnError::shutdown()));
+    }
 }
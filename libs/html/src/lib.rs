@@ -209,10 +209,11 @@ pub struct HtmlDoc{
          CommentStartDash2,
          CommentEndDash,
          CommentEnd,
-         CommentBody
+         CommentBody,
+         Declaration
      }
              
-     fn process_entity(c:char, body:&str, in_entity:&mut Option<usize>, i:usize, decoded:&mut String, last_was_ws:&mut bool, errors:&mut Option<Vec<HtmlError>>){
+     fn process_entity(c:char, body:&str, in_entity:&mut Option<usize>, i:usize, decoded:&mut String, last_was_ws:&mut bool, collapse_ws:bool, errors:&mut Option<Vec<HtmlError>>){
          if c=='&'{
              if in_entity.is_some(){
                  if let Some(errors) = errors{errors.push(HtmlError{message:"Unexpected & inside entity".into(), position:i})};
@@ -221,19 +222,21 @@ pub struct HtmlDoc{
          }
          else if let Some(start) = in_entity{
              if c == ';'{
-                 match match_entity(&body[*start..i-1]){
+                 match match_entity(&body[*start..i]){
                      Err(e)=>{
                          if let Some(errors) = errors{errors.push(HtmlError{message:e, position:i})};
-                         decoded.push_str(&body[*start..i-1]);
+                         decoded.push_str(&body[*start-1..=i]);
                      }
                      Ok(entity)=>{
                          decoded.push(std::char::from_u32(entity).unwrap());
                      }
                  }
+                 *in_entity = None;
+                 *last_was_ws = false;
              }
          }
          else{
-             if c.is_whitespace(){
+             if c.is_whitespace() && collapse_ws{
                  if !*last_was_ws{
                      decoded.push(c);
                  }
@@ -251,11 +254,13 @@ pub struct HtmlDoc{
      let mut decoded = String::new();
      let mut in_entity = None;
      let mut last_was_ws = false;
+     // whitespace is kept as is inside <pre>
+     let mut pre_depth = 0;
      for (i, c) in body.char_indices(){
          state = match state{
              State::Text(start)=>{ 
                  if c == '<'{
-                     if start != i{
+                     if start != decoded.len(){
                          if let Some(start) = in_entity{
                               if let Some(errors) = errors{errors.push(HtmlError{message:"Unterminated entity".into(), position:start})};
                          }
@@ -264,7 +269,7 @@ pub struct HtmlDoc{
                      State::ElementName(i+1)
                  }
                  else{
-                     process_entity(c, &body, &mut in_entity, i, &mut decoded, &mut last_was_ws, errors);
+                     process_entity(c, &body, &mut in_entity, i, &mut decoded, &mut last_was_ws, pre_depth == 0, errors);
                      State::Text(start)
                  }
              }
@@ -275,22 +280,29 @@ pub struct HtmlDoc{
                  else if c == '!' && i == start{
                      State::CommentStartDash1
                  }
+                 else if c == '?' && i == start{
+                     // <?xml ... ?> processing instructions are skipped
+                     State::Declaration
+                 }
                  else if c.is_whitespace(){
                      if start == i{
                           if let Some(errors) = errors{errors.push(HtmlError{message:"Found whitespace at beginning of tag".into(), position:i})};
-                         State::Text(i+1)
+                         State::Text(decoded.len())
                      }
                      else{
                         nodes.push(HtmlNode::OpenTag{lc:LiveId::from_str_lc(&body[start..i]),nc:LiveId::from_str(&body[start..i])});
+                        if body[start..i].eq_ignore_ascii_case("pre"){pre_depth += 1}
                         State::ElementAttrs
                     }
                 }
                  else if c == '/'{
                      nodes.push(HtmlNode::OpenTag{lc:LiveId::from_str_lc(&body[start..i]),nc:LiveId::from_str(&body[start..i])});
+                     if body[start..i].eq_ignore_ascii_case("pre"){pre_depth += 1}
                      State::ElementSelfClose
                  }
                  else if c == '>'{
                      nodes.push(HtmlNode::OpenTag{lc:LiveId::from_str_lc(&body[start..i]),nc:LiveId::from_str(&body[start..i])});
+                     if body[start..i].eq_ignore_ascii_case("pre"){pre_depth += 1}
                      State::Text(decoded.len())
                  }
                  else{
@@ -300,10 +312,12 @@ pub struct HtmlDoc{
              State::ElementClose(start)=>{
                  if c == '>'{
                      nodes.push(HtmlNode::CloseTag{lc:LiveId::from_str_lc(&body[start..i]),nc:LiveId::from_str(&body[start..i])});
+                     if body[start..i].eq_ignore_ascii_case("pre") && pre_depth > 0{pre_depth -= 1}
                      State::Text(decoded.len())
                  }
                  else if c.is_whitespace(){
                      nodes.push(HtmlNode::CloseTag{lc:LiveId::from_str_lc(&body[start..i]),nc:LiveId::from_str(&body[start..i])});
+                     if body[start..i].eq_ignore_ascii_case("pre") && pre_depth > 0{pre_depth -= 1}
                      State::ElementCloseScanSpaces
                  }
                  else{
@@ -312,11 +326,11 @@ pub struct HtmlDoc{
              }
              State::ElementCloseScanSpaces=>{
                  if c == '>'{
-                     State::Text(i+1)
+                     State::Text(decoded.len())
                  }
                  else if !c.is_whitespace(){
                       if let Some(errors) = errors{errors.push(HtmlError{message:"Unexpected character after whitespace whilst looking for closing tag >".into(), position:i})};
-                     State::Text(i+1)
+                     State::Text(decoded.len())
                  }
                  else{
                      State::ElementCloseScanSpaces
@@ -328,6 +342,7 @@ pub struct HtmlDoc{
                  }
                  // look backwards to the OpenTag
                  let begin = nodes.iter().rev().find_map(|v| if let HtmlNode::OpenTag{lc,nc} = v{Some((lc,nc))}else{None}).unwrap();
+                 if *begin.0 == LiveId::from_str_lc("pre") && pre_depth > 0{pre_depth -= 1}
                  nodes.push(HtmlNode::CloseTag{lc:*begin.0,nc:*begin.1});
                  State::Text(decoded.len())
              }
@@ -337,7 +352,7 @@ pub struct HtmlDoc{
                      State::ElementSelfClose
                  }
                  else if c == '>'{
-                     State::Text(i+1)
+                     State::Text(decoded.len())
                  }
                  else if !c.is_whitespace(){
                      State::AttribName(i)
@@ -372,7 +387,7 @@ pub struct HtmlDoc{
                  }
                  else if c == '>'{
                      nodes.push(HtmlNode::Attribute{lc,nc,start:0,end:0});
-                     State::Text(i+1)
+                     State::Text(decoded.len())
                  }
                  else if c == '='{
                      State::AttribValueStart(lc,nc)
@@ -395,8 +410,9 @@ pub struct HtmlDoc{
                      State::AttribValueSq(lc,nc, decoded.len())
                  }
                  else if !c.is_whitespace(){
-                     decoded.push(c);
-                     State::AttribValueBare(lc,nc, decoded.len()-1)
+                     let start = decoded.len();
+                     process_entity(c, &body, &mut in_entity, i, &mut decoded, &mut last_was_ws, pre_depth == 0, errors);
+                     State::AttribValueBare(lc,nc, start)
                  }
                  else{
                      State::AttribValueStart(lc,nc)
@@ -411,7 +427,7 @@ pub struct HtmlDoc{
                      State::ElementAttrs
                  }
                  else{
-                     process_entity(c, &body, &mut in_entity, i, &mut decoded, &mut last_was_ws, errors);
+                     process_entity(c, &body, &mut in_entity, i, &mut decoded, &mut last_was_ws, pre_depth == 0, errors);
                      State::AttribValueSq(lc,nc, start)
                  }
              }
@@ -424,33 +440,41 @@ pub struct HtmlDoc{
                      State::ElementAttrs
                  }
                  else{
-                     process_entity(c, &body, &mut in_entity, i, &mut decoded, &mut last_was_ws, errors);
+                     process_entity(c, &body, &mut in_entity, i, &mut decoded, &mut last_was_ws, pre_depth == 0, errors);
                      State::AttribValueDq(lc,nc, start)
                  }
              }
              State::AttribValueBare(lc,nc, start)=>{
-                 if c == '/'{
-                     nodes.push(HtmlNode::Attribute{lc,nc, start, end:decoded.len()});
-                     State::ElementSelfClose
-                 }
-                 else if c == '>'{
-                     nodes.push(HtmlNode::Attribute{lc,nc, start, end:decoded.len()});
-                     State::Text(i+1)
-                 }
-                 else if c.is_whitespace(){
+                 if c == '/' || c == '>' || c.is_whitespace(){
+                     if let Some(start) = in_entity.take(){
+                          if let Some(errors) = errors{errors.push(HtmlError{message:"Unterminated entity".into(), position:start})};
+                     }
                      nodes.push(HtmlNode::Attribute{lc,nc, start, end:decoded.len()});
-                     State::ElementAttrs
+                     if c == '/'{State::ElementSelfClose}
+                     else if c == '>'{State::Text(decoded.len())}
+                     else{State::ElementAttrs}
                  }
                  else{
-                     decoded.push(c);
+                     process_entity(c, &body, &mut in_entity, i, &mut decoded, &mut last_was_ws, pre_depth == 0, errors);
                      State::AttribValueBare(lc,nc, start)
                  }
              }
              State::CommentStartDash1=>{
                  if c != '-'{
-                      if let Some(errors) = errors{errors.push(HtmlError{message:"Unexpected character looking for - after <!".into(), position:i})};
+                     // <!DOCTYPE ...> and friends are skipped
+                     if c == '>'{State::Text(decoded.len())} else {State::Declaration}
+                 }
+                 else{
+                     State::CommentStartDash2
+                 }
+             }
+             State::Declaration=>{
+                 if c == '>'{
+                     State::Text(decoded.len())
+                 }
+                 else{
+                     State::Declaration
                  }
-                 State::CommentStartDash2
              }
              State::CommentStartDash2=>{
                  if c != '-'{
@@ -476,7 +500,7 @@ pub struct HtmlDoc{
              },
              State::CommentEnd=>{
                  if c == '>'{
-                     State::Text(i+1)
+                     State::Text(decoded.len())
                  }
                  else{
                      State::CommentBody
@@ -488,7 +512,7 @@ pub struct HtmlDoc{
           if let Some(errors) = errors{errors.push(HtmlError{message:"Unterminated entity".into(), position:start})};
      }
      if let State::Text(start) = state{
-         if start != body.len(){
+         if start != decoded.len(){
              nodes.push(HtmlNode::Text{start, end:decoded.len()});
          }
      }
@@ -2054,4 +2078,78 @@ pub struct HtmlDoc{
              }
          }
      })
- }
\ No newline at end of file
+ }
+
+#[cfg(test)]
+mod test{
+    use super::*;
+
+    fn tag_name(id:LiveId)->&'static str{
+        ["p", "a", "b", "pre", "img", "div"].into_iter().find(|name| LiveId::from_str(name) == id).unwrap_or("?")
+    }
+
+    fn dump(doc:&HtmlDoc)->String{
+        let mut out = String::new();
+        for node in &doc.nodes{
+            match node{
+                HtmlNode::OpenTag{lc, ..} => out.push_str(&format!("<{}>", tag_name(*lc))),
+                HtmlNode::CloseTag{lc, ..} => out.push_str(&format!("</{}>", tag_name(*lc))),
+                HtmlNode::Attribute{start, end, ..} => out.push_str(&format!("[{}]", &doc.decoded[*start..*end])),
+                HtmlNode::Text{start, end} => out.push_str(&doc.decoded[*start..*end]),
+            }
+        }
+        out
+    }
+
+    fn parse(body:&str)->(HtmlDoc, Vec<HtmlError>){
+        let mut errors = Some(Vec::new());
+        let doc = parse_html(body, &mut errors);
+        (doc, errors.unwrap())
+    }
+
+    #[test]
+    fn entities(){
+        let (doc, errors) = parse("a &lt;b&gt; &copy; &#65;&#x42;");
+        assert_eq!(dump(&doc), "a <b> © AB");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn unknown_entity_is_kept_as_written(){
+        let (doc, errors) = parse("x &bogus; y");
+        assert_eq!(dump(&doc), "x &bogus; y");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "unknown html entity");
+        assert_eq!(errors[0].position, 8);
+    }
+
+    #[test]
+    fn entities_in_attributes(){
+        let (doc, errors) = parse("<a href=\"?a=1&amp;b=2\" title='&quot;q&quot;'>x</a>");
+        assert_eq!(dump(&doc), "<a>[?a=1&b=2][\"q\"]x</a>");
+        assert!(errors.is_empty());
+        let a = doc.walk();
+        assert_eq!(a.find_attr_lc(live_id!(href)), Some("?a=1&b=2"));
+        assert_eq!(a.find_attr_lc(live_id!(title)), Some("\"q\""));
+    }
+
+    #[test]
+    fn text_offsets_follow_the_decoded_attributes(){
+        let (doc, _) = parse("<a href=x&amp;y>link</a> tail");
+        assert_eq!(doc.decoded, "x&ylink tail");
+        let texts:Vec<(usize, usize)> = doc.nodes.iter().filter_map(|node| if let HtmlNode::Text{start, end} = node{Some((*start, *end))}else{None}).collect();
+        assert_eq!(texts, vec![(3, 7), (7, 12)]);
+    }
+
+    #[test]
+    fn whitespace_collapses_outside_pre(){
+        let (doc, _) = parse("<p>a  \n  b</p><pre>c  \n  d</pre><p>e   f</p>");
+        assert_eq!(dump(&doc), "<p>a b</p><pre>c  \n  d</pre><p>e f</p>");
+    }
+
+    #[test]
+    fn nested_pre_keeps_whitespace_until_the_outer_close(){
+        let (doc, _) = parse("<pre><pre>a  b</pre>c  d</pre>e  f");
+        assert_eq!(dump(&doc), "<pre><pre>a  b</pre>c  d</pre>e f");
+    }
+}
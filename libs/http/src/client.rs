@@ -0,0 +1,405 @@
+// a minimal blocking HTTP/1.1 client. It only knows about bytes on a stream,
+// the platform layer supplies the (tls) transport and the threading.
+
+use std::io::prelude::*;
+
+pub const MAX_REDIRECTS: usize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpUrl {
+    pub is_tls: bool,
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl HttpUrl {
+    pub fn parse(url: &str) -> Option<HttpUrl> {
+        let (is_tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        }
+        else if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        }
+        else if let Some(rest) = url.strip_prefix("wss://") {
+            (true, rest)
+        }
+        else if let Some(rest) = url.strip_prefix("ws://") {
+            (false, rest)
+        }
+        else {
+            return None
+        };
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(pos) => (&rest[0..pos], &rest[pos..]),
+            None => (rest, "/")
+        };
+        // drop any userinfo, we don't do basic auth from the url
+        let authority = match authority.rfind('@') {
+            Some(pos) => &authority[pos + 1..],
+            None => authority
+        };
+        let default_port = if is_tls {443} else {80};
+        let (host, port) = if authority.starts_with('[') { // ipv6 literal
+            let end = authority.find(']') ?;
+            let host = &authority[1..end];
+            match authority[end + 1..].strip_prefix(':') {
+                Some(port) => (host, port.parse().ok() ?),
+                None => (host, default_port)
+            }
+        }
+        else {
+            match authority.rfind(':') {
+                Some(pos) => (&authority[0..pos], authority[pos + 1..].parse().ok() ?),
+                None => (authority, default_port)
+            }
+        };
+        if host.is_empty() {
+            return None
+        }
+        // strip the fragment, its never sent to the server
+        let path = match path.find('#') {
+            Some(pos) => &path[0..pos],
+            None => path
+        };
+        let path = if path.starts_with('?') {format!("/{}", path)} else {path.to_string()};
+        Some(HttpUrl {
+            is_tls,
+            host: host.to_string(),
+            port,
+            path
+        })
+    }
+
+    pub fn is_default_port(&self) -> bool {
+        self.port == if self.is_tls {443} else {80}
+    }
+
+    pub fn host_header(&self) -> String {
+        let host = if self.host.contains(':') {format!("[{}]", self.host)} else {self.host.clone()};
+        if self.is_default_port() {
+            host
+        }
+        else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+
+    pub fn to_url_string(&self) -> String {
+        format!("{}://{}{}", if self.is_tls {"https"} else {"http"}, self.host_header(), self.path)
+    }
+
+    /// Whether both urls have the same scheme, host and port. Credentials are only sent along
+    /// with a redirect to the same origin.
+    pub fn is_same_origin(&self, other: &HttpUrl) -> bool {
+        self.is_tls == other.is_tls && self.host.eq_ignore_ascii_case(&other.host) && self.port == other.port
+    }
+
+    /// Resolves a `Location` header value against this url
+    pub fn join(&self, location: &str) -> Option<HttpUrl> {
+        if location.starts_with("http://") || location.starts_with("https://") {
+            return HttpUrl::parse(location)
+        }
+        if let Some(rest) = location.strip_prefix("//") {
+            return HttpUrl::parse(&format!("{}://{}", if self.is_tls {"https"} else {"http"}, rest))
+        }
+        let path = if location.starts_with('/') {
+            location.to_string()
+        }
+        else {
+            let base = match self.path.find('?') {
+                Some(pos) => &self.path[0..pos],
+                None => &self.path
+            };
+            match base.rfind('/') {
+                Some(pos) => format!("{}{}", &base[0..pos + 1], location),
+                None => format!("/{}", location)
+            }
+        };
+        Some(HttpUrl {
+            is_tls: self.is_tls,
+            host: self.host.clone(),
+            port: self.port,
+            path
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HttpResponseHead {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpResponseHead {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status_code, 301 | 302 | 303 | 307 | 308) && self.header("Location").is_some()
+    }
+
+    pub fn is_chunked(&self) -> bool {
+        if let Some(v) = self.header("Transfer-Encoding") {
+            return v.to_ascii_lowercase().contains("chunked")
+        }
+        false
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("Content-Length").and_then(|v| v.trim().parse().ok())
+    }
+}
+
+/// Drops the headers that carry credentials, as curl does when a redirect leaves the origin
+/// they were meant for.
+pub fn remove_credential_headers(headers: &mut Vec<(String, String)>) {
+    headers.retain( | (name, _) | !name.eq_ignore_ascii_case("authorization") && !name.eq_ignore_ascii_case("cookie"));
+}
+
+pub fn build_request_head(method: &str, url: &HttpUrl, headers: &[(String, String)], body_len: Option<usize>) -> String {
+    let mut head = format!("{} {} HTTP/1.1\r\n", method, url.path);
+    let has_header = | name: &str | headers.iter().any( | (k, _) | k.eq_ignore_ascii_case(name));
+    if !has_header("Host") {
+        head.push_str(&format!("Host: {}\r\n", url.host_header()));
+    }
+    if !has_header("User-Agent") {
+        head.push_str("User-Agent: makepad\r\n");
+    }
+    if !has_header("Accept") {
+        head.push_str("Accept: */*\r\n");
+    }
+    if !has_header("Connection") {
+        head.push_str("Connection: close\r\n");
+    }
+    if let Some(body_len) = body_len {
+        if !has_header("Content-Length") {
+            head.push_str(&format!("Content-Length: {}\r\n", body_len));
+        }
+    }
+    for (key, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    head.push_str("\r\n");
+    head
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, String> {
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line).map_err( | e | format!("Read error: {}", e)) ?;
+    if line.is_empty() {
+        return Err("Connection closed".to_string())
+    }
+    if line.len() > 65536 {
+        return Err("Header line too long".to_string())
+    }
+    while line.last() == Some(&b'\n') || line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line).map_err( | _ | "Header is not UTF8".to_string())
+}
+
+pub fn read_response_head<R: BufRead>(reader: &mut R) -> Result<HttpResponseHead, String> {
+    let status_line = read_line(reader) ?;
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().unwrap_or("");
+    if !version.starts_with("HTTP/") {
+        return Err(format!("Invalid status line: {}", status_line))
+    }
+    let status_code = parts.next().and_then( | v | v.parse().ok()).ok_or_else( || format!("Invalid status line: {}", status_line)) ?;
+
+    let mut headers: Vec<(String, String)> = Vec::new();
+    loop {
+        let line = read_line(reader) ?;
+        if line.is_empty() {
+            break;
+        }
+        // obsolete line folding, append to the previous header
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if headers.len() > 4096 {
+            return Err("Too many headers".to_string())
+        }
+        if let Some(pos) = line.find(':') {
+            headers.push((line[0..pos].trim().to_string(), line[pos + 1..].trim().to_string()));
+        }
+    }
+    Ok(HttpResponseHead {
+        status_code,
+        headers
+    })
+}
+
+fn read_exact_with_progress<R: Read, F: FnMut(u64)>(reader: &mut R, body: &mut Vec<u8>, len: u64, progress: &mut F) -> Result<(), String> {
+    let mut left = len;
+    let mut buf = [0u8; 16384];
+    while left > 0 {
+        let want = (buf.len() as u64).min(left) as usize;
+        let n = reader.read(&mut buf[0..want]).map_err( | e | format!("Read error: {}", e)) ?;
+        if n == 0 {
+            return Err("Connection closed before end of body".to_string())
+        }
+        body.extend_from_slice(&buf[0..n]);
+        left -= n as u64;
+        progress(body.len() as u64);
+    }
+    Ok(())
+}
+
+/// Reads the body following `head`. Calls `progress(loaded, total)` as data arrives,
+/// total is 0 when the server didn't tell us a length.
+pub fn read_response_body<R: BufRead, F: FnMut(u64, u64)>(
+    reader: &mut R,
+    head: &HttpResponseHead,
+    is_head_request: bool,
+    mut progress: F
+) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    if is_head_request || head.status_code == 204 || head.status_code == 304 || (head.status_code >= 100 && head.status_code < 200) {
+        return Ok(body)
+    }
+    if head.is_chunked() {
+        loop {
+            let line = read_line(reader) ?;
+            let size = line.split(';').next().unwrap_or("").trim();
+            let size = u64::from_str_radix(size, 16).map_err( | _ | format!("Invalid chunk size: {}", line)) ?;
+            if size == 0 {
+                // skip trailers
+                while !read_line(reader) ?.is_empty() {}
+                break;
+            }
+            read_exact_with_progress(reader, &mut body, size, &mut | loaded | progress(loaded, 0)) ?;
+            let crlf = read_line(reader) ?;
+            if !crlf.is_empty() {
+                return Err("Invalid chunk terminator".to_string())
+            }
+        }
+    }
+    else if let Some(len) = head.content_length() {
+        read_exact_with_progress(reader, &mut body, len, &mut | loaded | progress(loaded, len)) ?;
+    }
+    else { // read until the connection closes
+        let mut buf = [0u8; 16384];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    body.extend_from_slice(&buf[0..n]);
+                    progress(body.len() as u64, 0);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(format!("Read error: {}", e))
+            }
+        }
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    #[test]
+    fn parse_urls() {
+        let url = HttpUrl::parse("https://example.com:8443/a/b?c=d#frag").unwrap();
+        assert_eq!(url, HttpUrl {is_tls: true, host: "example.com".into(), port: 8443, path: "/a/b?c=d".into()});
+        let url = HttpUrl::parse("http://[::1]/").unwrap();
+        assert_eq!(url.host, "::1");
+        assert_eq!(url.host_header(), "[::1]");
+        let url = HttpUrl::parse("http://localhost?x=1").unwrap();
+        assert_eq!(url.path, "/?x=1");
+        assert!(HttpUrl::parse("ftp://example.com").is_none());
+    }
+
+    #[test]
+    fn join_redirects() {
+        let url = HttpUrl::parse("http://example.com/a/b?q").unwrap();
+        assert_eq!(url.join("c").unwrap().path, "/a/c");
+        assert_eq!(url.join("/d").unwrap().path, "/d");
+        assert_eq!(url.join("https://other.org/x").unwrap().to_url_string(), "https://other.org/x");
+        assert_eq!(url.join("//cdn.org/y").unwrap().to_url_string(), "http://cdn.org/y");
+    }
+
+    #[test]
+    fn compare_origins() {
+        let url = HttpUrl::parse("http://example.com/a").unwrap();
+        assert!(url.is_same_origin(&url.join("/b").unwrap()));
+        assert!(url.is_same_origin(&HttpUrl::parse("http://EXAMPLE.com:80/c").unwrap()));
+        assert!(!url.is_same_origin(&HttpUrl::parse("https://example.com/a").unwrap()));
+        assert!(!url.is_same_origin(&HttpUrl::parse("http://example.com:8080/a").unwrap()));
+        assert!(!url.is_same_origin(&HttpUrl::parse("http://other.org/a").unwrap()));
+
+        let mut headers = vec![
+            ("Authorization".to_string(), "Bearer a".to_string()),
+            ("cookie".to_string(), "b=c".to_string()),
+            ("Accept".to_string(), "*/*".to_string()),
+        ];
+        remove_credential_headers(&mut headers);
+        assert_eq!(headers, vec![("Accept".to_string(), "*/*".to_string())]);
+    }
+
+    #[test]
+    fn read_chunked_response() {
+        let data = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nX-Thing: a:b\r\n\r\n5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n";
+        let mut reader = BufReader::new(&data[..]);
+        let head = read_response_head(&mut reader).unwrap();
+        assert_eq!(head.status_code, 200);
+        assert_eq!(head.header("x-thing"), Some("a:b"));
+        let body = read_response_body(&mut reader, &head, false, | _, _ | {}).unwrap();
+        assert_eq!(body, b"hello, world");
+    }
+
+    #[test]
+    fn read_content_length_response() {
+        let data = b"HTTP/1.1 404 Not Found\r\nContent-Length: 3\r\n\r\nabcdef";
+        let mut reader = BufReader::new(&data[..]);
+        let head = read_response_head(&mut reader).unwrap();
+        assert_eq!(head.status_code, 404);
+        let mut last = (0, 0);
+        let body = read_response_body(&mut reader, &head, false, | l, t | last = (l, t)).unwrap();
+        assert_eq!(body, b"abc");
+        assert_eq!(last, (3, 3));
+    }
+
+    #[test]
+    fn request_against_http_server() {
+        use crate::server::*;
+        use std::net::TcpStream;
+        use std::sync::mpsc;
+
+        let (tx_request, rx_request) = mpsc::channel::<HttpServerRequest>();
+        // let the os pick a free port, so parallel runs don't collide
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let listen_address = listener.local_addr().unwrap();
+        start_http_server_with_listener(listener, HttpServer {
+            listen_address,
+            post_max_size: 0,
+            request: tx_request
+        });
+        std::thread::spawn(move || {
+            while let Ok(request) = rx_request.recv() {
+                if let HttpServerRequest::Get {headers, response_sender} = request {
+                    let _ = response_sender.send(HttpServerResponse {
+                        header: "HTTP/1.1 200 OK\r\n\r\n".to_string(),
+                        body: headers.path.into_bytes()
+                    });
+                }
+            }
+        });
+
+        let url = HttpUrl::parse(&format!("http://{}/test.txt", listen_address)).unwrap();
+        // the server sends no content-length, so we read until close
+        let mut stream = TcpStream::connect((url.host.as_str(), url.port)).unwrap();
+        stream.write_all(build_request_head("GET", &url, &[], None).as_bytes()).unwrap();
+        let mut reader = BufReader::new(stream);
+        let head = read_response_head(&mut reader).unwrap();
+        assert_eq!(read_response_body(&mut reader, &head, false, | _, _ | {}).unwrap(), b"/test.txt");
+    }
+}
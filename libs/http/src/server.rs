@@ -50,7 +50,15 @@ pub fn start_http_server(
 ) -> Option<std::thread::JoinHandle<() >> {
     
     let listener = if let Ok(listener) = TcpListener::bind(http_server.listen_address) {listener} else {println!("Cannot bind http server port"); return None};
-    
+    Some(start_http_server_with_listener(listener, http_server))
+}
+
+/// Serves on a listener that is already bound, which lets the caller bind to port 0 and
+/// read back the port it got.
+pub fn start_http_server_with_listener(
+    listener: TcpListener,
+    http_server: HttpServer,
+) -> std::thread::JoinHandle<() > {
     let listen_thread = {
         std::thread::spawn(move || {
             let mut connection_counter = 0u64;
@@ -86,7 +94,7 @@ pub fn start_http_server(
             }
         })
     };
-    Some(listen_thread)
+    listen_thread
 }
 
 fn handle_post(http_server: HttpServer, mut tcp_stream: TcpStream, headers: HttpServerHeaders) {
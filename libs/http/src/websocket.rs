@@ -1,6 +1,7 @@
 
 use crate::digest::{Sha1, base64_encode};
 use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug, PartialEq)]
 enum State {
@@ -60,7 +61,10 @@ pub const PONG_MESSAGE:[u8;2] = [128 | 10,0];
 
 pub enum MessageFormat {
     Binary,
-    Text
+    Text,
+    Ping,
+    Pong,
+    Close
 }
 
 pub struct MessageHeader {
@@ -77,6 +81,9 @@ impl MessageHeader {
         match format {
             MessageFormat::Binary => data[0] = 128 | 2,
             MessageFormat::Text => data[0] = 128 | 1,
+            MessageFormat::Close => data[0] = 128 | 8,
+            MessageFormat::Ping => data[0] = 128 | 9,
+            MessageFormat::Pong => data[0] = 128 | 10,
         }
 
         if masked {
@@ -123,22 +130,31 @@ impl MessageHeader {
 
     pub fn mask(&mut self)->Option<&[u8]> {
         if self.masked {
-            match self.len {
-                6 => Some(&self.data[2..6]),
-                10 => Some(&self.data[6..10]),
-                14 => Some(&self.data[10..14]),
-                _ => None
-            }
+            // the mask key is always the last 4 bytes of the header
+            Some(&self.data[self.len - 4..self.len])
         } else {
             None
         }
     }
 
-    // TODO Improve this using a proper random number generator
     fn random_byte() -> u8 {
-        let num = SystemTime::now().duration_since(UNIX_EPOCH).expect("duration_since failed").subsec_nanos();
-        num as u8
+        random_u64() as u8
+    }
+}
+
+static RANDOM_STATE: AtomicU64 = AtomicU64::new(0);
+
+// xorshift seeded from the clock, good enough for frame masks and handshake keys
+fn random_u64() -> u64 {
+    let mut x = RANDOM_STATE.load(Ordering::Relaxed);
+    if x == 0 {
+        x = SystemTime::now().duration_since(UNIX_EPOCH).expect("duration_since failed").as_nanos() as u64 | 1;
     }
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    RANDOM_STATE.store(x, Ordering::Relaxed);
+    x
 }
 
 impl WebSocket {
@@ -160,17 +176,27 @@ impl WebSocket {
         }
     }
     
-    pub fn create_upgrade_response(key: &str) -> String {
+    pub fn create_accept_key(key: &str) -> String {
         let to_hash = format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key);
         let mut sha1 = Sha1::new();
         sha1.update(to_hash.as_bytes());
         let out_bytes = sha1.finalise();
-        let base64 = base64_encode(&out_bytes);
-        let response_ack = format!(
+        base64_encode(&out_bytes)
+    }
+    
+    pub fn create_upgrade_response(key: &str) -> String {
+        format!(
             "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
-            base64
-        );
-        response_ack
+            Self::create_accept_key(key)
+        )
+    }
+    
+    /// The random Sec-WebSocket-Key a client sends in its upgrade request
+    pub fn create_client_key() -> String {
+        let mut key = [0u8; 16];
+        key[0..8].copy_from_slice(&random_u64().to_le_bytes());
+        key[8..16].copy_from_slice(&random_u64().to_le_bytes());
+        base64_encode(&key)
     }
 
     pub fn build_message(mut header: MessageHeader, data: &[u8])->Vec<u8>{
@@ -357,3 +383,32 @@ impl Default for WebSocket {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_rfc6455() {
+        assert_eq!(WebSocket::create_accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(WebSocket::create_client_key().len(), 24);
+    }
+
+    #[test]
+    fn masked_frames_roundtrip() {
+        let payload = vec![7u8; 300];
+        let frame = WebSocket::build_message(MessageHeader::from_len(payload.len(), MessageFormat::Binary, true), &payload);
+        let ping = WebSocket::build_message(MessageHeader::from_len(2, MessageFormat::Ping, true), b"hi");
+        let mut web_socket = WebSocket::new();
+        let mut got = Vec::new();
+        // feed it byte by byte to exercise the partial header states
+        for byte in frame.iter().chain(ping.iter()) {
+            web_socket.parse(&[*byte], | result | match result {
+                Ok(WebSocketMessage::Binary(data)) => got.push(data.to_vec()),
+                Ok(WebSocketMessage::Ping(data)) => got.push(data.to_vec()),
+                _ => panic!()
+            });
+        }
+        assert_eq!(got, vec![payload, b"hi".to_vec()]);
+    }
+}
@@ -1127,7 +1127,7 @@ pub fn decode(src: &[u8]) -> Result<ImageBuffer, String> {
                     let mut tsp = start;
                     let le = from_be16(&src[tsp..tsp + 2]) == 0x4949; // figure out endianness
                     tsp += 4; // skip 0x2A
-                    tsp += (if le {from_le32(&src[tsp..tsp + 4])} else {from_be32(&src[tsp..tsp + 4])} -8) as usize; // go to IFD0
+                    tsp = start + (if le {from_le32(&src[tsp..tsp + 4])} else {from_be32(&src[tsp..tsp + 4])}) as usize; // go to IFD0, relative to the TIFF header
                     let entries = if le {from_le16(&src[tsp..tsp + 2])} else {from_be16(&src[tsp..tsp + 2])}; // number of entries
                     tsp += 2;
                     for _i in 0..entries {
@@ -1235,6 +1235,500 @@ pub fn decode(src: &[u8]) -> Result<ImageBuffer, String> {
     Err("Invalid JPEG 11".to_string())
 }
 
-pub fn encode(_image: &ImageBuffer) -> Result<Vec<u8>, String> {
-    Err("not implemented yet".to_string())
+// natural order index of the n-th coefficient in zigzag order
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5,
+    12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+// the example tables from Annex K of the JPEG spec, in natural order
+const LUMINANCE_QUANT: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55,
+    14, 13, 16, 24, 40, 57, 69, 56, 14, 17, 22, 29, 51, 87, 80, 62,
+    18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113, 92,
+    49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+const CHROMINANCE_QUANT: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99,
+    24, 26, 56, 99, 99, 99, 99, 99, 47, 66, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+const DC_LUMINANCE_BITS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+const DC_LUMINANCE_VALUES: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+const DC_CHROMINANCE_BITS: [u8; 16] = [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0];
+const DC_CHROMINANCE_VALUES: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+const AC_LUMINANCE_BITS: [u8; 16] = [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7D];
+const AC_LUMINANCE_VALUES: [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07,
+    0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xA1, 0x08, 0x23, 0x42, 0xB1, 0xC1, 0x15, 0x52, 0xD1, 0xF0,
+    0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0A, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x25, 0x26, 0x27, 0x28,
+    0x29, 0x2A, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+    0x4A, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6A, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+    0x8A, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7,
+    0xA8, 0xA9, 0xAA, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xB9, 0xBA, 0xC2, 0xC3, 0xC4, 0xC5,
+    0xC6, 0xC7, 0xC8, 0xC9, 0xCA, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xD9, 0xDA, 0xE1, 0xE2,
+    0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xEA, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8,
+    0xF9, 0xFA,
+];
+const AC_CHROMINANCE_BITS: [u8; 16] = [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77];
+const AC_CHROMINANCE_VALUES: [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71,
+    0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xA1, 0xB1, 0xC1, 0x09, 0x23, 0x33, 0x52, 0xF0,
+    0x15, 0x62, 0x72, 0xD1, 0x0A, 0x16, 0x24, 0x34, 0xE1, 0x25, 0xF1, 0x17, 0x18, 0x19, 0x1A, 0x26,
+    0x27, 0x28, 0x29, 0x2A, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+    0x49, 0x4A, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+    0x69, 0x6A, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+    0x88, 0x89, 0x8A, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0xA2, 0xA3, 0xA4, 0xA5,
+    0xA6, 0xA7, 0xA8, 0xA9, 0xAA, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xB9, 0xBA, 0xC2, 0xC3,
+    0xC4, 0xC5, 0xC6, 0xC7, 0xC8, 0xC9, 0xCA, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xD9, 0xDA,
+    0xE2, 0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xEA, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8,
+    0xF9, 0xFA,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JpegSubsampling {
+    // full resolution chroma
+    Yuv444,
+    // chroma at half resolution in both directions
+    Yuv420,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct JpegEncodeOptions {
+    // 1 to 100, with the same meaning as in libjpeg
+    pub quality: u8,
+    pub subsampling: JpegSubsampling,
+    // written as the EXIF orientation tag (1 to 8), use exif_orientation to carry it over from a source file
+    pub orientation: Option<u16>,
+}
+
+impl Default for JpegEncodeOptions {
+    fn default() -> Self {
+        JpegEncodeOptions {
+            quality: 85,
+            subsampling: JpegSubsampling::Yuv420,
+            orientation: None,
+        }
+    }
+}
+
+/// Reads the EXIF orientation tag of a JPEG file, if there is one
+pub fn exif_orientation(src: &[u8]) -> Option<u16> {
+    if src.len() < 4 || from_be16(&src[0..2]) != 0xFFD8 {
+        return None;
+    }
+    let mut sp = 2;
+    while sp + 4 <= src.len() {
+        let marker = from_be16(&src[sp..sp + 2]);
+        let length = from_be16(&src[sp + 2..sp + 4]) as usize;
+        if marker == 0xFFDA || marker == 0xFFD9 || sp + 2 + length > src.len() {
+            return None;
+        }
+        // APP1 starting with "Exif\0\0"
+        if marker == 0xFFE1 && length >= 8 && &src[sp + 4..sp + 10] == b"Exif\0\0" {
+            let tiff = &src[sp + 10..sp + 2 + length];
+            if tiff.len() < 8 {
+                return None;
+            }
+            let le = from_be16(&tiff[0..2]) == 0x4949;
+            let rd16 = | p: usize | if le {from_le16(&tiff[p..p + 2])} else {from_be16(&tiff[p..p + 2])};
+            let rd32 = | p: usize | if le {from_le32(&tiff[p..p + 4])} else {from_be32(&tiff[p..p + 4])};
+            let ifd = rd32(4) as usize;
+            if ifd + 2 > tiff.len() {
+                return None;
+            }
+            let entries = rd16(ifd) as usize;
+            for i in 0..entries {
+                let ep = ifd + 2 + i * 12;
+                if ep + 12 > tiff.len() {
+                    return None;
+                }
+                if rd16(ep) == 0x0112 {
+                    return Some(rd16(ep + 8));
+                }
+            }
+            return None;
+        }
+        sp += length + 2;
+    }
+    None
+}
+
+struct HuffmanCodes {
+    code: [u16; 256],
+    size: [u8; 256],
+}
+
+impl HuffmanCodes {
+    // generates the canonical codes as in Annex C of the spec
+    fn new(bits: &[u8; 16], values: &[u8]) -> HuffmanCodes {
+        let mut codes = HuffmanCodes {code: [0; 256], size: [0; 256]};
+        let mut code: u16 = 0;
+        let mut k = 0;
+        for length in 1..=16 {
+            for _ in 0..bits[length - 1] {
+                codes.code[values[k] as usize] = code;
+                codes.size[values[k] as usize] = length as u8;
+                code += 1;
+                k += 1;
+            }
+            code <<= 1;
+        }
+        codes
+    }
+}
+
+struct BitWriter {
+    data: Vec<u8>,
+    acc: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn put(&mut self, bits: u32, size: u32) {
+        self.acc = (self.acc << size) | (bits & ((1 << size) - 1));
+        self.count += size;
+        while self.count >= 8 {
+            let byte = (self.acc >> (self.count - 8)) as u8;
+            self.data.push(byte);
+            if byte == 0xFF {
+                self.data.push(0x00);
+            }
+            self.count -= 8;
+        }
+        self.acc &= (1 << self.count) - 1;
+    }
+
+    fn flush(&mut self) {
+        if self.count > 0 {
+            // pad with one bits
+            let pad = 8 - self.count;
+            self.put((1 << pad) - 1, pad);
+        }
+    }
+}
+
+fn scale_quant(table: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 {5000 / quality} else {200 - quality * 2};
+    let mut result = [0u16; 64];
+    for (result, value) in result.iter_mut().zip(table.iter()) {
+        *result = ((*value as u32 * scale + 50) / 100).clamp(1, 255) as u16;
+    }
+    result
+}
+
+fn fdct(block: &mut [f32; 64]) {
+    let mut cos = [[0f32; 8]; 8];
+    for (u, row) in cos.iter_mut().enumerate() {
+        let cu = if u == 0 {std::f32::consts::FRAC_1_SQRT_2} else {1.0};
+        for (x, c) in row.iter_mut().enumerate() {
+            *c = 0.5 * cu * (((2 * x + 1) as f32 * u as f32 * std::f32::consts::PI) / 16.0).cos();
+        }
+    }
+    let mut tmp = [0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            tmp[y * 8 + u] = (0..8).map( | x | block[y * 8 + x] * cos[u][x]).sum();
+        }
+    }
+    for u in 0..8 {
+        for v in 0..8 {
+            block[v * 8 + u] = (0..8).map( | y | tmp[y * 8 + u] * cos[v][y]).sum();
+        }
+    }
+}
+
+struct BlockEncoder {
+    writer: BitWriter,
+    dc_codes: [HuffmanCodes; 2],
+    ac_codes: [HuffmanCodes; 2],
+    quant: [[u16; 64]; 2],
+}
+
+fn magnitude(v: i32) -> (u32, u32) {
+    let size = 32 - v.unsigned_abs().leading_zeros();
+    let bits = if v < 0 {(v - 1) as u32} else {v as u32};
+    (size, bits)
+}
+
+impl BlockEncoder {
+    fn encode_block(&mut self, block: &mut [f32; 64], table: usize, last_dc: &mut i32) {
+        fdct(block);
+        let mut coeffs = [0i32; 64];
+        for k in 0..64 {
+            let q = self.quant[table][ZIGZAG[k]] as f32;
+            coeffs[k] = (block[ZIGZAG[k]] / q).round() as i32;
+        }
+        let dc_codes = &self.dc_codes[table];
+        let ac_codes = &self.ac_codes[table];
+        let diff = coeffs[0] - *last_dc;
+        *last_dc = coeffs[0];
+        let (size, bits) = magnitude(diff);
+        self.writer.put(dc_codes.code[size as usize] as u32, dc_codes.size[size as usize] as u32);
+        if size > 0 {
+            self.writer.put(bits, size);
+        }
+        let mut run = 0;
+        for &coeff in &coeffs[1..] {
+            if coeff == 0 {
+                run += 1;
+                continue;
+            }
+            while run >= 16 {
+                // ZRL
+                self.writer.put(ac_codes.code[0xF0] as u32, ac_codes.size[0xF0] as u32);
+                run -= 16;
+            }
+            let (size, bits) = magnitude(coeff);
+            let symbol = (run << 4) | size as usize;
+            self.writer.put(ac_codes.code[symbol] as u32, ac_codes.size[symbol] as u32);
+            self.writer.put(bits, size);
+            run = 0;
+        }
+        if run > 0 {
+            // EOB
+            self.writer.put(ac_codes.code[0] as u32, ac_codes.size[0] as u32);
+        }
+    }
+}
+
+fn write_segment(dst: &mut Vec<u8>, marker: u16, data: &[u8]) {
+    dst.extend_from_slice(&marker.to_be_bytes());
+    dst.extend_from_slice(&((data.len() + 2) as u16).to_be_bytes());
+    dst.extend_from_slice(data);
+}
+
+fn write_huffman_table(data: &mut Vec<u8>, class_id: u8, bits: &[u8; 16], values: &[u8]) {
+    data.push(class_id);
+    data.extend_from_slice(bits);
+    data.extend_from_slice(values);
+}
+
+/// Encodes as baseline JPEG with the default options (quality 85, 4:2:0)
+pub fn encode(image: &ImageBuffer) -> Result<Vec<u8>, String> {
+    encode_with_options(image, &JpegEncodeOptions::default())
+}
+
+/// Encodes as baseline JPEG, alpha is dropped
+pub fn encode_with_options(image: &ImageBuffer, options: &JpegEncodeOptions) -> Result<Vec<u8>, String> {
+    let (width, height) = (image.width, image.height);
+    if width == 0 || height == 0 || width > 65535 || height > 65535 || image.data.len() < width * height {
+        return Err("invalid image size".to_string());
+    }
+    let mut dst: Vec<u8> = vec![0xFF, 0xD8];
+
+    // JFIF 1.01, no density, no thumbnail
+    write_segment(&mut dst, 0xFFE0, &[0x4A, 0x46, 0x49, 0x46, 0x00, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00]);
+
+    if let Some(orientation) = options.orientation {
+        // big endian TIFF header with a single IFD0 entry: orientation, SHORT, count 1
+        let mut exif: Vec<u8> = b"Exif\0\0MM\0\x2A\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]); // value padding, no next IFD
+        write_segment(&mut dst, 0xFFE1, &exif);
+    }
+
+    let quant = [
+        scale_quant(&LUMINANCE_QUANT, options.quality),
+        scale_quant(&CHROMINANCE_QUANT, options.quality),
+    ];
+    let mut dqt = Vec::with_capacity(130);
+    for (id, table) in quant.iter().enumerate() {
+        dqt.push(id as u8);
+        for k in 0..64 {
+            dqt.push(table[ZIGZAG[k]] as u8);
+        }
+    }
+    write_segment(&mut dst, 0xFFDB, &dqt);
+
+    let luma_sampling = if options.subsampling == JpegSubsampling::Yuv420 {0x22} else {0x11};
+    let mut sof = vec![8];
+    sof.extend_from_slice(&(height as u16).to_be_bytes());
+    sof.extend_from_slice(&(width as u16).to_be_bytes());
+    sof.extend_from_slice(&[3, 1, luma_sampling, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    write_segment(&mut dst, 0xFFC0, &sof);
+
+    let mut dht = Vec::new();
+    write_huffman_table(&mut dht, 0x00, &DC_LUMINANCE_BITS, &DC_LUMINANCE_VALUES);
+    write_huffman_table(&mut dht, 0x10, &AC_LUMINANCE_BITS, &AC_LUMINANCE_VALUES);
+    write_huffman_table(&mut dht, 0x01, &DC_CHROMINANCE_BITS, &DC_CHROMINANCE_VALUES);
+    write_huffman_table(&mut dht, 0x11, &AC_CHROMINANCE_BITS, &AC_CHROMINANCE_VALUES);
+    write_segment(&mut dst, 0xFFC4, &dht);
+
+    write_segment(&mut dst, 0xFFDA, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+
+    // level shifted YCbCr planes, edges replicated out to whole macroblocks
+    let mb_size = if options.subsampling == JpegSubsampling::Yuv420 {16} else {8};
+    let pw = width.div_ceil(mb_size) * mb_size;
+    let ph = height.div_ceil(mb_size) * mb_size;
+    let mut planes = [vec![0f32; pw * ph], vec![0f32; pw * ph], vec![0f32; pw * ph]];
+    for y in 0..ph {
+        for x in 0..pw {
+            let c = image.data[y.min(height - 1) * width + x.min(width - 1)];
+            let r = ((c >> 16) & 255) as f32;
+            let g = ((c >> 8) & 255) as f32;
+            let b = (c & 255) as f32;
+            planes[0][y * pw + x] = 0.299 * r + 0.587 * g + 0.114 * b - 128.0;
+            planes[1][y * pw + x] = -0.168736 * r - 0.331264 * g + 0.5 * b;
+            planes[2][y * pw + x] = 0.5 * r - 0.418688 * g - 0.081312 * b;
+        }
+    }
+
+    let mut encoder = BlockEncoder {
+        writer: BitWriter {data: Vec::new(), acc: 0, count: 0},
+        dc_codes: [
+            HuffmanCodes::new(&DC_LUMINANCE_BITS, &DC_LUMINANCE_VALUES),
+            HuffmanCodes::new(&DC_CHROMINANCE_BITS, &DC_CHROMINANCE_VALUES),
+        ],
+        ac_codes: [
+            HuffmanCodes::new(&AC_LUMINANCE_BITS, &AC_LUMINANCE_VALUES),
+            HuffmanCodes::new(&AC_CHROMINANCE_BITS, &AC_CHROMINANCE_VALUES),
+        ],
+        quant,
+    };
+    let mut last_dc = [0i32; 3];
+    let mut block = [0f32; 64];
+    for my in (0..ph).step_by(mb_size) {
+        for mx in (0..pw).step_by(mb_size) {
+            for by in (0..mb_size).step_by(8) {
+                for bx in (0..mb_size).step_by(8) {
+                    for y in 0..8 {
+                        for x in 0..8 {
+                            block[y * 8 + x] = planes[0][(my + by + y) * pw + mx + bx + x];
+                        }
+                    }
+                    encoder.encode_block(&mut block, 0, &mut last_dc[0]);
+                }
+            }
+            for plane in 1..3 {
+                if mb_size == 16 {
+                    for y in 0..8 {
+                        for x in 0..8 {
+                            let p = (my + y * 2) * pw + mx + x * 2;
+                            let src = &planes[plane];
+                            block[y * 8 + x] = (src[p] + src[p + 1] + src[p + pw] + src[p + pw + 1]) * 0.25;
+                        }
+                    }
+                }
+                else {
+                    for y in 0..8 {
+                        for x in 0..8 {
+                            block[y * 8 + x] = planes[plane][(my + y) * pw + mx + x];
+                        }
+                    }
+                }
+                encoder.encode_block(&mut block, 1, &mut last_dc[plane]);
+            }
+        }
+    }
+    encoder.writer.flush();
+    dst.extend_from_slice(&encoder.writer.data);
+    dst.extend_from_slice(&[0xFF, 0xD9]);
+    Ok(dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_image(width: usize, height: usize) -> ImageBuffer {
+        let mut image = ImageBuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let r = (x * 255 / width.max(2)) as u32;
+                let g = (y * 255 / height.max(2)) as u32;
+                let b = if ((x / 8) + (y / 8)) % 2 == 0 {200} else {60};
+                image.data[y * width + x] = 0xFF000000 | (r << 16) | (g << 8) | b;
+            }
+        }
+        image
+    }
+
+    fn psnr(a: &[u32], b: &[u8]) -> f64 {
+        let mut sum = 0.0;
+        for (i, c) in a.iter().enumerate() {
+            for (k, shift) in [16, 8, 0].iter().enumerate() {
+                let d = ((c >> shift) & 255) as f64 - b[i * 3 + k] as f64;
+                sum += d * d;
+            }
+        }
+        let mse = sum / (a.len() * 3) as f64;
+        10.0 * (255.0 * 255.0 / mse.max(1e-10)).log10()
+    }
+
+    fn to_rgb(image: &ImageBuffer) -> Vec<u8> {
+        image.data.iter().flat_map( | c | [(c >> 16) as u8, (c >> 8) as u8, *c as u8]).collect()
+    }
+
+    fn round_trip(image: &ImageBuffer, options: &JpegEncodeOptions) -> Vec<u8> {
+        let data = encode_with_options(image, options).unwrap();
+        assert_eq!(&data[0..2], &[0xFF, 0xD8]);
+        assert_eq!(&data[data.len() - 2..], &[0xFF, 0xD9]);
+        data
+    }
+
+    #[test]
+    fn decode_with_own_decoder() {
+        for subsampling in [JpegSubsampling::Yuv420, JpegSubsampling::Yuv444] {
+            let image = test_image(64, 48);
+            let data = round_trip(&image, &JpegEncodeOptions {quality: 90, subsampling, orientation: None});
+            let decoded = decode(&data).unwrap();
+            assert_eq!((decoded.width, decoded.height), (64, 48));
+            assert!(psnr(&image.data, &to_rgb(&decoded)) > 30.0);
+        }
+    }
+
+    #[test]
+    fn decode_with_zune_jpeg() {
+        for subsampling in [JpegSubsampling::Yuv420, JpegSubsampling::Yuv444] {
+            for (width, height) in [(1, 1), (17, 9), (33, 70), (64, 64)] {
+                let image = test_image(width, height);
+                let data = round_trip(&image, &JpegEncodeOptions {quality: 90, subsampling, orientation: None});
+                let mut decoder = makepad_zune_jpeg::JpegDecoder::new(&data[..]);
+                let pixels = decoder.decode().unwrap();
+                let info = decoder.info().unwrap();
+                assert_eq!((info.width as usize, info.height as usize), (width, height));
+                // zune-jpeg interpolates the chroma planes, which smears the hard chroma edges of the checkerboard
+                let min_psnr = if subsampling == JpegSubsampling::Yuv420 {24.0} else {38.0};
+                assert!(psnr(&image.data, &pixels) > min_psnr, "{}x{} {:?}", width, height, subsampling);
+            }
+        }
+    }
+
+    #[test]
+    fn quality_changes_size() {
+        let image = test_image(64, 64);
+        let low = encode_with_options(&image, &JpegEncodeOptions {quality: 10, ..Default::default()}).unwrap();
+        let high = encode_with_options(&image, &JpegEncodeOptions {quality: 95, ..Default::default()}).unwrap();
+        assert!(low.len() < high.len());
+        let mut decoder = makepad_zune_jpeg::JpegDecoder::new(&low[..]);
+        assert!(decoder.decode().is_ok());
+    }
+
+    #[test]
+    fn orientation_passthrough() {
+        let image = test_image(16, 16);
+        let data = encode(&image).unwrap();
+        assert_eq!(exif_orientation(&data), None);
+        let data = round_trip(&image, &JpegEncodeOptions {orientation: Some(6), ..Default::default()});
+        assert_eq!(exif_orientation(&data), Some(6));
+        assert!(decode(&data).is_ok());
+        let mut decoder = makepad_zune_jpeg::JpegDecoder::new(&data[..]);
+        assert!(decoder.decode().is_ok());
+        // carried over from an existing file
+        let again = round_trip(&image, &JpegEncodeOptions {orientation: exif_orientation(&data), ..Default::default()});
+        assert_eq!(exif_orientation(&again), Some(6));
+    }
+
+    #[test]
+    fn invalid_size() {
+        assert!(encode(&ImageBuffer::new(0, 4)).is_err());
+    }
 }
@@ -5,6 +5,6 @@
 mod image;
 pub use image::*;
 //pub mod bmp;
-//pub mod png;
+pub mod png;
 pub mod jpeg;
 
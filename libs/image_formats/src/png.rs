@@ -3,6 +3,8 @@
 
 use std::cmp::Ordering;
 
+use makepad_miniz::compress_to_vec_zlib;
+
 use crate::ImageBuffer;
 
 // Inflate algorithm
@@ -822,8 +824,8 @@ pub fn decode(src: &[u8]) -> Result<ImageBuffer, String> {
                     TYPE_RGBA8 => {stride = width * 4; bpp = 4;},
                     TYPE_L16 => {stride = width * 2; bpp = 2;},
                     TYPE_RGB16 => {stride = width * 6; bpp = 6;},
-                    TYPE_LA16 => {stride = width * 2; bpp = 4;},
-                    TYPE_RGBA16 => {stride = width * 4; bpp = 8;},
+                    TYPE_LA16 => {stride = width * 4; bpp = 4;},
+                    TYPE_RGBA16 => {stride = width * 8; bpp = 8;},
                     _ => {return Err("Invalid PNG".to_string());}
                 }
                 sp += chunk_length;
@@ -1039,6 +1041,304 @@ pub fn decode(src: &[u8]) -> Result<ImageBuffer, String> {
     }
 }
 
-pub fn encode(_image: &ImageBuffer) -> Result<Vec<u8>, String> {
-    Err("not implemented".to_string())
-}
\ No newline at end of file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PngColorType {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+}
+
+impl PngColorType {
+    fn channels(&self) -> usize {
+        match self {
+            PngColorType::Gray => 1,
+            PngColorType::GrayAlpha => 2,
+            PngColorType::Rgb => 3,
+            PngColorType::Rgba => 4,
+        }
+    }
+
+    fn code(&self) -> u16 {
+        match self {
+            PngColorType::Gray => 0,
+            PngColorType::GrayAlpha => 4,
+            PngColorType::Rgb => 2,
+            PngColorType::Rgba => 6,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PngBitDepth {
+    Eight,
+    Sixteen,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PngEncodeOptions {
+    pub color_type: PngColorType,
+    pub bit_depth: PngBitDepth,
+    // deflate level, 0 stores the data uncompressed, 10 is the slowest and smallest
+    pub compression: u8,
+}
+
+impl Default for PngEncodeOptions {
+    fn default() -> Self {
+        PngEncodeOptions {
+            color_type: PngColorType::Rgba,
+            bit_depth: PngBitDepth::Eight,
+            compression: 6,
+        }
+    }
+}
+
+fn crc32(chunk: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in chunk {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if (crc & 1) != 0 {(crc >> 1) ^ 0xEDB88320} else {crc >> 1};
+        }
+    }
+    crc ^ 0xFFFFFFFF
+}
+
+fn write_chunk(dst: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    dst.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = dst.len();
+    dst.extend_from_slice(chunk_type);
+    dst.extend_from_slice(data);
+    let crc = crc32(&dst[start..]);
+    dst.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let d = a as i32 + b as i32 - c as i32;
+    let pa = (d - a as i32).abs();
+    let pb = (d - b as i32).abs();
+    let pc = (d - c as i32).abs();
+    if (pa <= pb) && (pa <= pc) {a} else if pb <= pc {b} else {c}
+}
+
+// the inverse of unfilter, picks the filter per scanline with the usual minimum sum of absolute differences heuristic
+fn filter(src: &[u8], height: usize, stride: usize, bpp: usize, adaptive: bool) -> Vec<u8> {
+    let mut dst: Vec<u8> = Vec::with_capacity((stride + 1) * height);
+    let mut candidate: Vec<u8> = vec![0; stride];
+    let mut best: Vec<u8> = vec![0; stride];
+    let zero_line: Vec<u8> = vec![0; stride];
+    for y in 0..height {
+        let line = &src[y * stride..(y + 1) * stride];
+        let prev = if y >= 1 {&src[(y - 1) * stride..y * stride]} else {&zero_line[..]};
+        if !adaptive {
+            dst.push(0);
+            dst.extend_from_slice(line);
+            continue;
+        }
+        let mut best_type = 0;
+        let mut best_sum = usize::MAX;
+        for ftype in 0..5u8 {
+            let mut sum = 0;
+            for x in 0..stride {
+                let a = if x >= bpp {line[x - bpp]} else {0};
+                let b = prev[x];
+                let c = if x >= bpp {prev[x - bpp]} else {0};
+                let p = match ftype {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) >> 1) as u8,
+                    _ => paeth(a, b, c),
+                };
+                let v = line[x].wrapping_sub(p);
+                candidate[x] = v;
+                sum += (v as i8).unsigned_abs() as usize;
+            }
+            if sum < best_sum {
+                best_sum = sum;
+                best_type = ftype;
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+        dst.push(best_type);
+        dst.extend_from_slice(&best);
+    }
+    dst
+}
+
+fn encode_raw(width: usize, height: usize, itype: u16, bpp: usize, raw_data: &[u8], compression: u8) -> Vec<u8> {
+    let stride = raw_data.len() / height;
+    // filtering only pays off when we actually compress
+    let filtered_data = filter(raw_data, height, stride, bpp, compression > 0);
+    let mut dst: Vec<u8> = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    ihdr.extend_from_slice(&itype.to_be_bytes());
+    ihdr.extend_from_slice(&[0, 0, 0]); // compression, filter, interlace
+    write_chunk(&mut dst, b"IHDR", &ihdr);
+    write_chunk(&mut dst, b"IDAT", &compress_to_vec_zlib(&filtered_data, compression.min(10)));
+    write_chunk(&mut dst, b"IEND", &[]);
+    dst
+}
+
+/// Encodes as 8 bit RGBA with the default compression level
+pub fn encode(image: &ImageBuffer) -> Result<Vec<u8>, String> {
+    encode_with_options(image, &PngEncodeOptions::default())
+}
+
+/// Encodes with the given color type, bit depth and compression level. Color is converted to
+/// grayscale using the Rec. 601 luma weights, 8 bit components are widened when writing 16 bit.
+pub fn encode_with_options(image: &ImageBuffer, options: &PngEncodeOptions) -> Result<Vec<u8>, String> {
+    if image.width == 0 || image.height == 0 || image.data.len() < image.width * image.height {
+        return Err("invalid image size".to_string());
+    }
+    let channels = options.color_type.channels();
+    let sample_bytes = if options.bit_depth == PngBitDepth::Sixteen {2} else {1};
+    let mut raw_data: Vec<u8> = Vec::with_capacity(image.width * image.height * channels * sample_bytes);
+    let mut samples = [0u8; 4];
+    for c in &image.data[0..image.width * image.height] {
+        let r = (c >> 16) & 255;
+        let g = (c >> 8) & 255;
+        let b = c & 255;
+        let a = (c >> 24) as u8;
+        let l = ((r * 77 + g * 150 + b * 29 + 128) >> 8) as u8;
+        let samples = match options.color_type {
+            PngColorType::Gray => {samples[0] = l; &samples[0..1]},
+            PngColorType::GrayAlpha => {samples[0] = l; samples[1] = a; &samples[0..2]},
+            PngColorType::Rgb => {samples[0] = r as u8; samples[1] = g as u8; samples[2] = b as u8; &samples[0..3]},
+            PngColorType::Rgba => {samples[0] = r as u8; samples[1] = g as u8; samples[2] = b as u8; samples[3] = a; &samples[0..4]},
+        };
+        for sample in samples {
+            raw_data.push(*sample);
+            if sample_bytes == 2 {
+                // v * 257 maps 0..255 exactly onto 0..65535
+                raw_data.push(*sample);
+            }
+        }
+    }
+    let depth: u16 = if options.bit_depth == PngBitDepth::Sixteen {16} else {8};
+    Ok(encode_raw(image.width, image.height, (depth << 8) | options.color_type.code(), channels * sample_bytes, &raw_data, options.compression))
+}
+
+/// Encodes 16 bit samples as they are, interleaved per pixel in the channel order of the color type
+pub fn encode_u16(width: usize, height: usize, color_type: PngColorType, samples: &[u16], compression: u8) -> Result<Vec<u8>, String> {
+    let channels = color_type.channels();
+    if width == 0 || height == 0 || samples.len() < width * height * channels {
+        return Err("invalid image size".to_string());
+    }
+    let mut raw_data: Vec<u8> = Vec::with_capacity(width * height * channels * 2);
+    for sample in &samples[0..width * height * channels] {
+        raw_data.extend_from_slice(&sample.to_be_bytes());
+    }
+    Ok(encode_raw(width, height, 0x1000 | color_type.code(), channels * 2, &raw_data, compression))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_image(width: usize, height: usize) -> ImageBuffer {
+        let mut image = ImageBuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let r = (x * 255 / width) as u32;
+                let g = (y * 255 / height) as u32;
+                let b = ((x * 7 + y * 13) & 255) as u32;
+                let a = (255 - ((x + y) & 127)) as u32;
+                image.data[y * width + x] = (a << 24) | (r << 16) | (g << 8) | b;
+            }
+        }
+        image
+    }
+
+    fn gray_image(width: usize, height: usize) -> ImageBuffer {
+        let mut image = test_image(width, height);
+        for c in &mut image.data {
+            let l = (*c >> 8) & 255;
+            *c = (*c & 0xff000000) | (l << 16) | (l << 8) | l;
+        }
+        image
+    }
+
+    fn round_trip(image: &ImageBuffer, options: PngEncodeOptions) -> ImageBuffer {
+        let data = encode_with_options(image, &options).unwrap();
+        assert_eq!(test(&data), Some((image.width, image.height)));
+        decode(&data).unwrap()
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b"IEND"), 0xAE426082);
+    }
+
+    #[test]
+    fn rgba8_all_levels() {
+        let image = test_image(67, 45);
+        for compression in 0..=10 {
+            let decoded = round_trip(&image, PngEncodeOptions {compression, ..Default::default()});
+            assert_eq!(decoded.data, image.data, "compression level {}", compression);
+        }
+    }
+
+    #[test]
+    fn rgb8() {
+        let image = test_image(31, 17);
+        let decoded = round_trip(&image, PngEncodeOptions {color_type: PngColorType::Rgb, ..Default::default()});
+        let expected: Vec<u32> = image.data.iter().map( | c | c | 0xff000000).collect();
+        assert_eq!(decoded.data, expected);
+    }
+
+    #[test]
+    fn gray8() {
+        let image = gray_image(33, 21);
+        let decoded = round_trip(&image, PngEncodeOptions {color_type: PngColorType::Gray, ..Default::default()});
+        let expected: Vec<u32> = image.data.iter().map( | c | c | 0xff000000).collect();
+        assert_eq!(decoded.data, expected);
+        let decoded = round_trip(&image, PngEncodeOptions {color_type: PngColorType::GrayAlpha, ..Default::default()});
+        assert_eq!(decoded.data, image.data);
+    }
+
+    #[test]
+    fn sixteen_bit() {
+        let image = test_image(19, 23);
+        let gray = gray_image(19, 23);
+        let opaque = | image: &ImageBuffer | image.data.iter().map( | c | c | 0xff000000).collect::<Vec<u32>>();
+        let sixteen = | color_type | PngEncodeOptions {color_type, bit_depth: PngBitDepth::Sixteen, ..Default::default()};
+        assert_eq!(round_trip(&image, sixteen(PngColorType::Rgba)).data, image.data);
+        assert_eq!(round_trip(&image, sixteen(PngColorType::Rgb)).data, opaque(&image));
+        assert_eq!(round_trip(&gray, sixteen(PngColorType::Gray)).data, opaque(&gray));
+        assert_eq!(round_trip(&gray, sixteen(PngColorType::GrayAlpha)).data, gray.data);
+    }
+
+    #[test]
+    fn u16_samples() {
+        let (width, height) = (13, 11);
+        let samples: Vec<u16> = (0..width * height * 3).map( | i | (i * 997 % 65536) as u16).collect();
+        let data = encode_u16(width, height, PngColorType::Rgb, &samples, 6).unwrap();
+        let decoded = decode(&data).unwrap();
+        for (i, c) in decoded.data.iter().enumerate() {
+            let r = (samples[i * 3] >> 8) as u32;
+            let g = (samples[i * 3 + 1] >> 8) as u32;
+            let b = (samples[i * 3 + 2] >> 8) as u32;
+            assert_eq!(*c, 0xff000000 | (r << 16) | (g << 8) | b);
+        }
+    }
+
+    #[test]
+    fn compression_levels() {
+        let image = test_image(128, 128);
+        let stored = encode_with_options(&image, &PngEncodeOptions {compression: 0, ..Default::default()}).unwrap();
+        let fast = encode_with_options(&image, &PngEncodeOptions {compression: 1, ..Default::default()}).unwrap();
+        let best = encode_with_options(&image, &PngEncodeOptions {compression: 9, ..Default::default()}).unwrap();
+        assert!(stored.len() > 128 * 128 * 4);
+        assert!(fast.len() < stored.len());
+        assert!(best.len() <= fast.len());
+    }
+
+    #[test]
+    fn invalid_size() {
+        assert!(encode(&ImageBuffer::new(0, 10)).is_err());
+        assert!(encode_u16(4, 4, PngColorType::Rgba, &[0; 10], 6).is_err());
+    }
+}
@@ -0,0 +1,1226 @@
+use {
+    std::collections::HashMap,
+    makepad_html::match_entity,
+};
+
+// Markdown parsed into a flat vector of nodes, like HtmlDoc. It covers the CommonMark block
+// structure (headings, paragraphs, quotes, lists, code blocks, thematic breaks) plus GFM tables
+// and strikethrough, and the usual inlines: emphasis, code spans, links, images and autolinks.
+// All strings point into `decoded`, which holds the unescaped text.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListKind{
+    Unordered,
+    Ordered(u64)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableAlign{
+    None,
+    Left,
+    Center,
+    Right
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkdownNode{
+    BeginHead{level:usize},
+    EndHead,
+    BeginParagraph,
+    EndParagraph,
+    BeginQuote,
+    EndQuote,
+    BeginList{kind:ListKind, tight:bool},
+    EndList,
+    BeginListItem,
+    EndListItem,
+    BeginTable{columns:usize},
+    EndTable,
+    BeginTableRow{header:bool},
+    EndTableRow,
+    BeginTableCell{align:TableAlign},
+    EndTableCell,
+    CodeBlock{lang_start:usize, lang_end:usize, start:usize, end:usize},
+    Separator,
+    BeginBold,
+    EndBold,
+    BeginItalic,
+    EndItalic,
+    BeginStrikethrough,
+    EndStrikethrough,
+    BeginLink{href_start:usize, href_end:usize},
+    EndLink,
+    Image{src_start:usize, src_end:usize, alt_start:usize, alt_end:usize},
+    InlineCode{start:usize, end:usize},
+    Text{start:usize, end:usize},
+    SoftBreak,
+    HardBreak,
+}
+
+// where a top level block starts, so appended text can be parsed from the last block onwards
+#[derive(Clone, Copy)]
+struct BlockStart{
+    source: usize,
+    node: usize,
+    decoded: usize,
+}
+
+#[derive(Default)]
+pub struct MarkdownDoc{
+    pub decoded: String,
+    pub nodes: Vec<MarkdownNode>,
+    source: String,
+    blocks: Vec<BlockStart>,
+    link_refs: HashMap<String, String>,
+}
+
+impl MarkdownDoc{
+    pub fn source(&self)->&str{
+        &self.source
+    }
+
+    pub fn str(&self, start:usize, end:usize)->&str{
+        &self.decoded[start..end]
+    }
+
+    // Replaces the document text. When the new text extends the old one (streamed text)
+    // only the last top level block is parsed again. Returns false if nothing changed.
+    pub fn update(&mut self, body:&str)->bool{
+        if body == self.source{
+            return false
+        }
+        let link_refs = collect_link_refs(body);
+        if body.starts_with(self.source.as_str()) && link_refs == self.link_refs{
+            if let Some(last) = self.blocks.pop(){
+                self.nodes.truncate(last.node);
+                self.decoded.truncate(last.decoded);
+                self.source = body.to_string();
+                self.parse_from(last.source);
+                return true
+            }
+        }
+        *self = parse_markdown(body);
+        true
+    }
+
+    fn parse_from(&mut self, offset:usize){
+        let mut lines = Vec::new();
+        let mut line_starts = Vec::new();
+        let mut start = offset;
+        for line in self.source[offset..].split('\n'){
+            line_starts.push(start);
+            start += line.len() + 1;
+            lines.push(expand_tabs(line.strip_suffix('\r').unwrap_or(line)));
+        }
+        let mut parser = Parser{
+            decoded: std::mem::take(&mut self.decoded),
+            nodes: std::mem::take(&mut self.nodes),
+            link_refs: &self.link_refs,
+            block_starts: Vec::new(),
+        };
+        parser.parse_blocks(&lines, true);
+        for (line, node, decoded) in parser.block_starts{
+            self.blocks.push(BlockStart{
+                source: line_starts[line],
+                node,
+                decoded
+            });
+        }
+        self.decoded = parser.decoded;
+        self.nodes = parser.nodes;
+    }
+}
+
+pub fn parse_markdown(body:&str)->MarkdownDoc{
+    let mut doc = MarkdownDoc{
+        source: body.to_string(),
+        link_refs: collect_link_refs(body),
+        ..Default::default()
+    };
+    doc.parse_from(0);
+    doc
+}
+
+struct Parser<'a>{
+    decoded: String,
+    nodes: Vec<MarkdownNode>,
+    link_refs: &'a HashMap<String, String>,
+    block_starts: Vec<(usize, usize, usize)>,
+}
+
+enum ListMarker{
+    Bullet(char),
+    Ordered(u64, char)
+}
+
+impl ListMarker{
+    fn same_list(&self, other:&ListMarker)->bool{
+        match (self, other){
+            (ListMarker::Bullet(a), ListMarker::Bullet(b)) => a == b,
+            (ListMarker::Ordered(_, a), ListMarker::Ordered(_, b)) => a == b,
+            _ => false
+        }
+    }
+}
+
+struct ListItemStart{
+    marker: ListMarker,
+    // column where the item content starts
+    content: usize,
+    empty: bool,
+}
+
+impl<'a> Parser<'a>{
+    fn push_str(&mut self, s:&str)->(usize, usize){
+        let start = self.decoded.len();
+        self.decoded.push_str(s);
+        (start, self.decoded.len())
+    }
+
+    fn push_text(&mut self, s:&str){
+        if s.is_empty(){
+            return
+        }
+        let len = self.decoded.len();
+        if let Some(MarkdownNode::Text{end, ..}) = self.nodes.last_mut(){
+            if *end == len{
+                self.decoded.push_str(s);
+                *end = self.decoded.len();
+                return
+            }
+        }
+        let (start, end) = self.push_str(s);
+        self.nodes.push(MarkdownNode::Text{start, end});
+    }
+
+    fn parse_blocks(&mut self, lines:&[String], top_level:bool){
+        let mut i = 0;
+        while i < lines.len(){
+            let line = &lines[i];
+            if is_blank(line){
+                i += 1;
+                continue;
+            }
+            if top_level{
+                self.block_starts.push((i, self.nodes.len(), self.decoded.len()));
+            }
+            // indented code
+            if indent(line) >= 4{
+                let mut end = i;
+                let mut j = i;
+                while j < lines.len() && (is_blank(&lines[j]) || indent(&lines[j]) >= 4){
+                    if !is_blank(&lines[j]){
+                        end = j + 1;
+                    }
+                    j += 1;
+                }
+                let code:Vec<&str> = lines[i..end].iter().map(|l| strip_indent(l, 4)).collect();
+                self.code_block("", &code);
+                i = end;
+                continue;
+            }
+            if let Some((ch, len, fence_indent, info)) = fence_start(line){
+                let mut j = i + 1;
+                let mut code = Vec::new();
+                while j < lines.len() && !is_fence_end(&lines[j], ch, len){
+                    code.push(strip_indent(&lines[j], fence_indent));
+                    j += 1;
+                }
+                let lang = unescape(info.split_whitespace().next().unwrap_or(""));
+                self.code_block(&lang, &code);
+                i = j + 1;
+                continue;
+            }
+            if let Some((level, content)) = atx_heading(line){
+                self.nodes.push(MarkdownNode::BeginHead{level});
+                self.parse_inline(content);
+                self.nodes.push(MarkdownNode::EndHead);
+                i += 1;
+                continue;
+            }
+            if is_thematic_break(line){
+                self.nodes.push(MarkdownNode::Separator);
+                i += 1;
+                continue;
+            }
+            if quote_start(line).is_some(){
+                let mut inner = Vec::new();
+                let mut j = i;
+                while j < lines.len(){
+                    if let Some(rest) = quote_start(&lines[j]){
+                        inner.push(rest.to_string());
+                    }
+                    // lazy continuation of a paragraph inside the quote
+                    else if !is_blank(&lines[j]) && inner.last().is_some_and(|l:&String| !is_blank(l)) && !interrupts_paragraph(&lines[j]){
+                        inner.push(lines[j].clone());
+                    }
+                    else{
+                        break
+                    }
+                    j += 1;
+                }
+                self.nodes.push(MarkdownNode::BeginQuote);
+                self.parse_blocks(&inner, false);
+                self.nodes.push(MarkdownNode::EndQuote);
+                i = j;
+                continue;
+            }
+            if let Some(item) = list_item_start(line){
+                i = self.parse_list(lines, i, item);
+                continue;
+            }
+            if i + 1 < lines.len() && line.contains('|'){
+                if let Some(aligns) = table_delimiter(&lines[i + 1]){
+                    if split_table_row(line).len() == aligns.len(){
+                        i = self.parse_table(lines, i, &aligns);
+                        continue;
+                    }
+                }
+            }
+            // paragraph
+            let mut para = vec![line.trim_start()];
+            let mut heading = None;
+            let mut j = i + 1;
+            while j < lines.len(){
+                let l = &lines[j];
+                if is_blank(l){
+                    break
+                }
+                if indent(l) < 4{
+                    if let Some(level) = setext_underline(l){
+                        heading = Some(level);
+                        j += 1;
+                        break
+                    }
+                    if interrupts_paragraph(l){
+                        break
+                    }
+                }
+                para.push(l.trim_start());
+                j += 1;
+            }
+            i = j;
+            while para.first().is_some_and(|l| parse_link_ref(l).is_some()){
+                para.remove(0);
+            }
+            if para.is_empty(){
+                continue;
+            }
+            let text = para.join("\n");
+            let text = text.trim_end();
+            if let Some(level) = heading{
+                self.nodes.push(MarkdownNode::BeginHead{level});
+                self.parse_inline(text);
+                self.nodes.push(MarkdownNode::EndHead);
+            }
+            else{
+                self.nodes.push(MarkdownNode::BeginParagraph);
+                self.parse_inline(text);
+                self.nodes.push(MarkdownNode::EndParagraph);
+            }
+        }
+    }
+
+    fn code_block(&mut self, lang:&str, code:&[&str]){
+        let (lang_start, lang_end) = self.push_str(lang);
+        let start = self.decoded.len();
+        for (i, line) in code.iter().enumerate(){
+            if i > 0{
+                self.decoded.push('\n');
+            }
+            self.decoded.push_str(line);
+        }
+        let end = self.decoded.len();
+        self.nodes.push(MarkdownNode::CodeBlock{lang_start, lang_end, start, end});
+    }
+
+    fn parse_list(&mut self, lines:&[String], mut i:usize, first:ListItemStart)->usize{
+        let kind = match first.marker{
+            ListMarker::Bullet(_) => ListKind::Unordered,
+            ListMarker::Ordered(start, _) => ListKind::Ordered(start)
+        };
+        let mut items = Vec::new();
+        let mut tight = true;
+        let mut item_start = first;
+        loop{
+            let line = &lines[i];
+            let content = item_start.content;
+            let mut item = vec![if item_start.empty{String::new()} else {skip_columns(line, content).to_string()}];
+            i += 1;
+            while i < lines.len(){
+                let l = &lines[i];
+                if is_blank(l){
+                    item.push(String::new());
+                }
+                else if indent(l) >= content{
+                    item.push(strip_indent(l, content).to_string());
+                }
+                // lazy continuation of the last paragraph
+                else if !is_blank(item.last().unwrap()) && !interrupts_paragraph(l) && list_item_start(l).is_none(){
+                    item.push(l.trim_start().to_string());
+                }
+                else{
+                    break
+                }
+                i += 1;
+            }
+            let mut trailing = 0;
+            while item.len() > 1 && is_blank(item.last().unwrap()){
+                item.pop();
+                trailing += 1;
+            }
+            if item.iter().any(|l| is_blank(l)) && item.iter().filter(|l| !is_blank(l)).count() > 1{
+                tight = false;
+            }
+            items.push(item);
+            if i < lines.len() && !is_thematic_break(&lines[i]){
+                if let Some(next) = list_item_start(&lines[i]){
+                    if next.marker.same_list(&item_start.marker){
+                        if trailing > 0{
+                            tight = false;
+                        }
+                        item_start = next;
+                        continue;
+                    }
+                }
+            }
+            break
+        }
+        self.nodes.push(MarkdownNode::BeginList{kind, tight});
+        for item in items{
+            self.nodes.push(MarkdownNode::BeginListItem);
+            self.parse_blocks(&item, false);
+            self.nodes.push(MarkdownNode::EndListItem);
+        }
+        self.nodes.push(MarkdownNode::EndList);
+        i
+    }
+
+    fn parse_table(&mut self, lines:&[String], i:usize, aligns:&[TableAlign])->usize{
+        self.nodes.push(MarkdownNode::BeginTable{columns: aligns.len()});
+        self.table_row(&lines[i], aligns, true);
+        let mut j = i + 2;
+        while j < lines.len(){
+            let l = &lines[j];
+            if is_blank(l) || indent(l) < 4 && (atx_heading(l).is_some() || fence_start(l).is_some() || quote_start(l).is_some() || is_thematic_break(l)){
+                break
+            }
+            self.table_row(l, aligns, false);
+            j += 1;
+        }
+        self.nodes.push(MarkdownNode::EndTable);
+        j
+    }
+
+    fn table_row(&mut self, line:&str, aligns:&[TableAlign], header:bool){
+        let cells = split_table_row(line);
+        self.nodes.push(MarkdownNode::BeginTableRow{header});
+        for (i, align) in aligns.iter().enumerate(){
+            self.nodes.push(MarkdownNode::BeginTableCell{align: *align});
+            if let Some(cell) = cells.get(i){
+                self.parse_inline(cell);
+            }
+            self.nodes.push(MarkdownNode::EndTableCell);
+        }
+        self.nodes.push(MarkdownNode::EndTableRow);
+    }
+
+    fn parse_inline(&mut self, text:&str){
+        let items = parse_inlines(text, self.link_refs);
+        self.emit_inlines(&items);
+    }
+
+    fn emit_inlines(&mut self, items:&[Inline]){
+        for item in items{
+            match item{
+                Inline::Text(s) => self.push_text(s),
+                Inline::Delim{ch, count, ..} => self.push_text(&ch.to_string().repeat(*count)),
+                Inline::Bracket{image, ..} => self.push_text(if *image{"!["} else {"["}),
+                Inline::Code(s) => {
+                    let (start, end) = self.push_str(s);
+                    self.nodes.push(MarkdownNode::InlineCode{start, end});
+                }
+                Inline::SoftBreak => self.nodes.push(MarkdownNode::SoftBreak),
+                Inline::HardBreak => self.nodes.push(MarkdownNode::HardBreak),
+                Inline::Style{style, children} => {
+                    self.nodes.push(match style{
+                        Style::Bold => MarkdownNode::BeginBold,
+                        Style::Italic => MarkdownNode::BeginItalic,
+                        Style::Strikethrough => MarkdownNode::BeginStrikethrough,
+                    });
+                    self.emit_inlines(children);
+                    self.nodes.push(match style{
+                        Style::Bold => MarkdownNode::EndBold,
+                        Style::Italic => MarkdownNode::EndItalic,
+                        Style::Strikethrough => MarkdownNode::EndStrikethrough,
+                    });
+                }
+                Inline::Link{href, children} => {
+                    let (href_start, href_end) = self.push_str(href);
+                    self.nodes.push(MarkdownNode::BeginLink{href_start, href_end});
+                    self.emit_inlines(children);
+                    self.nodes.push(MarkdownNode::EndLink);
+                }
+                Inline::Image{src, children} => {
+                    let (src_start, src_end) = self.push_str(src);
+                    let mut alt = String::new();
+                    plain_text(children, &mut alt);
+                    let (alt_start, alt_end) = self.push_str(&alt);
+                    self.nodes.push(MarkdownNode::Image{src_start, src_end, alt_start, alt_end});
+                }
+            }
+        }
+    }
+}
+
+// Block level helpers
+
+fn expand_tabs(line:&str)->String{
+    // tabs in the indentation become spaces up to the next 4 column tab stop
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next(){
+        match c{
+            ' ' => out.push(' '),
+            '\t' => {
+                let n = 4 - out.len() % 4;
+                out.extend(std::iter::repeat_n(' ', n));
+            }
+            _ => break
+        }
+        rest = &rest[1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_blank(line:&str)->bool{
+    line.trim().is_empty()
+}
+
+fn indent(line:&str)->usize{
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn strip_indent(line:&str, n:usize)->&str{
+    &line[indent(line).min(n)..]
+}
+
+fn skip_columns(line:&str, n:usize)->&str{
+    line.get(n..).unwrap_or("")
+}
+
+fn atx_heading(line:&str)->Option<(usize, &str)>{
+    if indent(line) >= 4{
+        return None
+    }
+    let line = line.trim();
+    let level = line.len() - line.trim_start_matches('#').len();
+    if level == 0 || level > 6{
+        return None
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' '){
+        return None
+    }
+    let rest = rest.trim();
+    // an optional closing sequence of #'s preceded by a space
+    let closed = rest.trim_end_matches('#');
+    let content = if closed.is_empty() || closed.ends_with(' '){closed.trim_end()} else {rest};
+    Some((level, content))
+}
+
+fn is_thematic_break(line:&str)->bool{
+    if indent(line) >= 4{
+        return false
+    }
+    let mut ch = None;
+    let mut count = 0;
+    for c in line.chars(){
+        match c{
+            ' ' | '\t' => (),
+            '-' | '*' | '_' if ch.is_none() || ch == Some(c) => {
+                ch = Some(c);
+                count += 1;
+            }
+            _ => return false
+        }
+    }
+    count >= 3
+}
+
+fn setext_underline(line:&str)->Option<usize>{
+    let line = line.trim();
+    if !line.is_empty() && line.chars().all(|c| c == '='){
+        Some(1)
+    }
+    else if !line.is_empty() && line.chars().all(|c| c == '-'){
+        Some(2)
+    }
+    else{
+        None
+    }
+}
+
+fn fence_start(line:&str)->Option<(char, usize, usize, &str)>{
+    let ind = indent(line);
+    if ind >= 4{
+        return None
+    }
+    let rest = &line[ind..];
+    let ch = rest.chars().next()?;
+    if ch != '`' && ch != '~'{
+        return None
+    }
+    let len = rest.len() - rest.trim_start_matches(ch).len();
+    if len < 3{
+        return None
+    }
+    let info = rest[len..].trim();
+    if ch == '`' && info.contains('`'){
+        return None
+    }
+    Some((ch, len, ind, info))
+}
+
+fn is_fence_end(line:&str, ch:char, len:usize)->bool{
+    if indent(line) >= 4{
+        return false
+    }
+    let rest = line.trim_start();
+    let run = rest.len() - rest.trim_start_matches(ch).len();
+    run >= len && rest[run..].trim().is_empty()
+}
+
+fn quote_start(line:&str)->Option<&str>{
+    if indent(line) >= 4{
+        return None
+    }
+    let rest = line.trim_start().strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+fn list_item_start(line:&str)->Option<ListItemStart>{
+    let ind = indent(line);
+    if ind >= 4{
+        return None
+    }
+    let rest = &line[ind..];
+    let (marker, marker_len) = match rest.chars().next()?{
+        c @ ('-' | '+' | '*') => (ListMarker::Bullet(c), 1),
+        _ => {
+            let digits = rest.len() - rest.trim_start_matches(|c:char| c.is_ascii_digit()).len();
+            if digits == 0 || digits > 9{
+                return None
+            }
+            let delim = rest[digits..].chars().next()?;
+            if delim != '.' && delim != ')'{
+                return None
+            }
+            (ListMarker::Ordered(rest[..digits].parse().ok()?, delim), digits + 1)
+        }
+    };
+    let after = &rest[marker_len..];
+    if !after.is_empty() && !after.starts_with(' '){
+        return None
+    }
+    let empty = after.trim().is_empty();
+    let spaces = indent(after);
+    // content indented 5 or more spaces after the marker is indented code, it starts after 1 space
+    let content = ind + marker_len + if empty || spaces > 4{1} else {spaces};
+    Some(ListItemStart{marker, content, empty})
+}
+
+fn interrupts_paragraph(line:&str)->bool{
+    if indent(line) >= 4{
+        return false
+    }
+    if atx_heading(line).is_some() || is_thematic_break(line) || fence_start(line).is_some() || quote_start(line).is_some(){
+        return true
+    }
+    matches!(
+        list_item_start(line),
+        Some(ListItemStart{empty: false, marker: ListMarker::Bullet(_) | ListMarker::Ordered(1, _), ..})
+    )
+}
+
+fn table_delimiter(line:&str)->Option<Vec<TableAlign>>{
+    if indent(line) >= 4 || !line.contains('-'){
+        return None
+    }
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    let mut aligns = Vec::new();
+    for cell in line.split('|'){
+        let cell = cell.trim();
+        let left = cell.starts_with(':');
+        let right = cell.ends_with(':');
+        let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+        if dashes.is_empty() || !dashes.chars().all(|c| c == '-'){
+            return None
+        }
+        aligns.push(match (left, right){
+            (true, true) => TableAlign::Center,
+            (true, false) => TableAlign::Left,
+            (false, true) => TableAlign::Right,
+            (false, false) => TableAlign::None
+        });
+    }
+    Some(aligns)
+}
+
+fn split_table_row(line:&str)->Vec<String>{
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut in_code = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next(){
+        match c{
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '`' => {
+                in_code = !in_code;
+                cell.push(c);
+            }
+            '|' if !in_code => {
+                cells.push(cell.trim().to_string());
+                cell.clear();
+            }
+            _ => cell.push(c)
+        }
+    }
+    if !cell.trim().is_empty(){
+        cells.push(cell.trim().to_string());
+    }
+    cells
+}
+
+fn normalize_label(label:&str)->String{
+    label.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// parses a single line link reference definition: [label]: destination "optional title"
+fn parse_link_ref(line:&str)->Option<(String, String)>{
+    if indent(line) >= 4{
+        return None
+    }
+    let rest = line.trim_start().strip_prefix('[')?;
+    let close = rest.find(']')?;
+    let label = &rest[..close];
+    if label.trim().is_empty() || label.contains('['){
+        return None
+    }
+    let rest = rest[close + 1..].strip_prefix(':')?.trim();
+    let dest = if let Some(rest) = rest.strip_prefix('<'){
+        &rest[..rest.find('>')?]
+    }
+    else{
+        rest.split_whitespace().next()?
+    };
+    Some((normalize_label(label), unescape(dest)))
+}
+
+fn collect_link_refs(body:&str)->HashMap<String, String>{
+    let mut refs = HashMap::new();
+    for line in body.lines(){
+        if let Some((label, dest)) = parse_link_ref(line){
+            refs.entry(label).or_insert(dest);
+        }
+    }
+    refs
+}
+
+// Inline level
+
+enum Style{
+    Bold,
+    Italic,
+    Strikethrough
+}
+
+enum Inline{
+    Text(String),
+    Code(String),
+    SoftBreak,
+    HardBreak,
+    Delim{ch:char, count:usize, orig:usize, open:bool, close:bool},
+    Bracket{image:bool, active:bool},
+    Style{style:Style, children:Vec<Inline>},
+    Link{href:String, children:Vec<Inline>},
+    Image{src:String, children:Vec<Inline>},
+}
+
+fn plain_text(items:&[Inline], out:&mut String){
+    for item in items{
+        match item{
+            Inline::Text(s) | Inline::Code(s) => out.push_str(s),
+            Inline::SoftBreak | Inline::HardBreak => out.push(' '),
+            Inline::Delim{ch, count, ..} => out.push_str(&ch.to_string().repeat(*count)),
+            Inline::Bracket{image, ..} => out.push_str(if *image{"!["} else {"["}),
+            Inline::Style{children, ..} | Inline::Link{children, ..} | Inline::Image{children, ..} => plain_text(children, out),
+        }
+    }
+}
+
+fn is_punctuation(c:char)->bool{
+    c.is_ascii_punctuation() || !c.is_ascii() && !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+fn decode_entity(text:&str)->Option<(char, usize)>{
+    // text starts with '&'
+    let end = text[1..].find(';')? + 1;
+    if !(2..=32).contains(&end) || !text[1..end].chars().all(|c| c.is_ascii_alphanumeric() || c == '#'){
+        return None
+    }
+    let code = match_entity(&text[1..end]).ok()?;
+    Some((char::from_u32(code).unwrap_or('\u{FFFD}'), end + 1))
+}
+
+fn unescape(text:&str)->String{
+    let mut out = String::new();
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next(){
+        if c == '\\'{
+            if let Some(n) = text[i + 1..].chars().next().filter(|n| n.is_ascii_punctuation()){
+                out.push(n);
+                i += 2;
+                continue;
+            }
+        }
+        else if c == '&'{
+            if let Some((c, len)) = decode_entity(&text[i..]){
+                out.push(c);
+                i += len;
+                continue;
+            }
+        }
+        out.push(c);
+        i += c.len_utf8();
+    }
+    out
+}
+
+// parses the (destination "title") part of an inline link, returning the destination and length
+fn parse_link_target(text:&str)->Option<(String, usize)>{
+    let b = text.as_bytes();
+    let skip_ws = |mut i:usize|{
+        while i < b.len() && (b[i] as char).is_ascii_whitespace(){
+            i += 1;
+        }
+        i
+    };
+    let mut i = skip_ws(1);
+    let dest = if b.get(i) == Some(&b'<'){
+        let end = i + 1 + text[i + 1..].find(['>', '\n'])?;
+        if b[end] != b'>'{
+            return None
+        }
+        let dest = unescape(&text[i + 1..end]);
+        i = end + 1;
+        dest
+    }
+    else{
+        let start = i;
+        let mut depth = 0;
+        while i < b.len(){
+            match b[i]{
+                b'\\' if i + 1 < b.len() => i += 1,
+                b'(' => depth += 1,
+                b')' if depth == 0 => break,
+                b')' => depth -= 1,
+                c if c <= b' ' => break,
+                _ => ()
+            }
+            i += 1;
+        }
+        unescape(&text[start..i])
+    };
+    let before_title = i;
+    i = skip_ws(i);
+    if i > before_title{
+        let close = match b.get(i){
+            Some(b'"') => Some(b'"'),
+            Some(b'\'') => Some(b'\''),
+            Some(b'(') => Some(b')'),
+            _ => None
+        };
+        if let Some(close) = close{
+            i += 1;
+            while i < b.len() && b[i] != close{
+                if b[i] == b'\\'{
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = skip_ws(i + 1);
+        }
+    }
+    if b.get(i) == Some(&b')'){
+        Some((dest, i + 1))
+    }
+    else{
+        None
+    }
+}
+
+fn autolink(text:&str)->Option<(String, String, usize)>{
+    // text starts with '<'
+    let end = text.find('>')?;
+    let inner = &text[1..end];
+    if inner.is_empty() || inner.contains(|c:char| c.is_whitespace() || c == '<'){
+        return None
+    }
+    if let Some(colon) = inner.find(':'){
+        let scheme = &inner[..colon];
+        if scheme.len() >= 2 && scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '.' || c == '-'){
+            return Some((inner.to_string(), inner.to_string(), end + 1))
+        }
+    }
+    if let Some(at) = inner.find('@'){
+        if at > 0 && inner[at + 1..].contains('.'){
+            return Some((format!("mailto:{}", inner), inner.to_string(), end + 1))
+        }
+    }
+    None
+}
+
+fn bare_autolink(text:&str)->Option<(String, usize)>{
+    let prefix = ["https://", "http://", "www."].into_iter().find(|p| text.starts_with(p))?;
+    let mut end = text.find(|c:char| c.is_whitespace() || c == '<').unwrap_or(text.len());
+    // trailing punctuation and unbalanced closing parens are not part of the link
+    loop{
+        let link = &text[..end];
+        match link.chars().last(){
+            Some('?' | '!' | '.' | ',' | ':' | '*' | '_' | '~' | '\'' | '"' | ';') => end -= 1,
+            Some(')') if link.matches(')').count() > link.matches('(').count() => end -= 1,
+            _ => break
+        }
+    }
+    if end <= prefix.len(){
+        return None
+    }
+    let link = &text[..end];
+    let href = if prefix == "www."{format!("http://{}", link)} else {link.to_string()};
+    Some((href, end))
+}
+
+fn parse_inlines(text:&str, link_refs:&HashMap<String, String>)->Vec<Inline>{
+    let mut items = Vec::new();
+    // open brackets, item index and the start of their text
+    let mut brackets:Vec<(usize, usize)> = Vec::new();
+    let mut buf = String::new();
+
+    fn flush(buf:&mut String, items:&mut Vec<Inline>){
+        if !buf.is_empty(){
+            items.push(Inline::Text(std::mem::take(buf)));
+        }
+    }
+
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next(){
+        let prev = text[..i].chars().last();
+        match c{
+            '\\' => {
+                match text[i + 1..].chars().next(){
+                    Some('\n') => {
+                        flush(&mut buf, &mut items);
+                        items.push(Inline::HardBreak);
+                        i += 2;
+                    }
+                    Some(n) if n.is_ascii_punctuation() => {
+                        buf.push(n);
+                        i += 2;
+                    }
+                    _ => {
+                        buf.push('\\');
+                        i += 1;
+                    }
+                }
+            }
+            '`' => {
+                let run = text[i..].len() - text[i..].trim_start_matches('`').len();
+                let after = i + run;
+                let mut search = after;
+                let mut found = None;
+                while let Some(pos) = text[search..].find('`'){
+                    let start = search + pos;
+                    let len = text[start..].len() - text[start..].trim_start_matches('`').len();
+                    if len == run{
+                        found = Some(start);
+                        break
+                    }
+                    search = start + len;
+                }
+                if let Some(close) = found{
+                    let code = text[after..close].replace('\n', " ");
+                    let code = if code.len() > 1 && code.starts_with(' ') && code.ends_with(' ') && !code.trim().is_empty(){
+                        code[1..code.len() - 1].to_string()
+                    }
+                    else{
+                        code
+                    };
+                    flush(&mut buf, &mut items);
+                    items.push(Inline::Code(code));
+                    i = close + run;
+                }
+                else{
+                    buf.push_str(&text[i..after]);
+                    i = after;
+                }
+            }
+            '*' | '_' | '~' => {
+                let run = text[i..].len() - text[i..].trim_start_matches(c).len();
+                if c == '~' && run > 2{
+                    buf.push_str(&text[i..i + run]);
+                    i += run;
+                    continue;
+                }
+                let prev = prev.unwrap_or(' ');
+                let next = text[i + run..].chars().next().unwrap_or(' ');
+                let left = !next.is_whitespace() && (!is_punctuation(next) || prev.is_whitespace() || is_punctuation(prev));
+                let right = !prev.is_whitespace() && (!is_punctuation(prev) || next.is_whitespace() || is_punctuation(next));
+                let (open, close) = if c == '_'{
+                    (left && (!right || is_punctuation(prev)), right && (!left || is_punctuation(next)))
+                }
+                else{
+                    (left, right)
+                };
+                flush(&mut buf, &mut items);
+                items.push(Inline::Delim{ch: c, count: run, orig: run, open, close});
+                i += run;
+            }
+            '!' if text[i + 1..].starts_with('[') => {
+                flush(&mut buf, &mut items);
+                brackets.push((items.len(), i + 2));
+                items.push(Inline::Bracket{image: true, active: true});
+                i += 2;
+            }
+            '[' => {
+                flush(&mut buf, &mut items);
+                brackets.push((items.len(), i + 1));
+                items.push(Inline::Bracket{image: false, active: true});
+                i += 1;
+            }
+            ']' => {
+                let Some((opener, label_start)) = brackets.pop() else {
+                    buf.push(']');
+                    i += 1;
+                    continue;
+                };
+                flush(&mut buf, &mut items);
+                let (image, active) = match items[opener]{
+                    Inline::Bracket{image, active} => (image, active),
+                    _ => unreachable!()
+                };
+                let rest = &text[i + 1..];
+                let target = if !active{
+                    None
+                }
+                else if rest.starts_with('('){
+                    parse_link_target(rest)
+                }
+                else{
+                    // reference links: [text][label], [label][] and [label]
+                    let label = &text[label_start..i];
+                    let (label, len) = match rest.strip_prefix('[').and_then(|r| r.find(']').map(|end| (&r[..end], end + 2))){
+                        Some(("", len)) => (label, len),
+                        Some((label, len)) => (label, len),
+                        None => (label, 0)
+                    };
+                    link_refs.get(&normalize_label(label)).map(|dest| (dest.clone(), len))
+                };
+                if let Some((dest, len)) = target{
+                    let mut children:Vec<Inline> = items.drain(opener + 1..).collect();
+                    items.pop();
+                    process_emphasis(&mut children);
+                    if image{
+                        items.push(Inline::Image{src: dest, children});
+                    }
+                    else{
+                        items.push(Inline::Link{href: dest, children});
+                        // links can't contain other links
+                        for (index, _) in &brackets{
+                            if let Inline::Bracket{image: false, active} = &mut items[*index]{
+                                *active = false;
+                            }
+                        }
+                    }
+                    i += 1 + len;
+                }
+                else{
+                    items[opener] = Inline::Text(if image{"![".into()} else {"[".into()});
+                    buf.push(']');
+                    i += 1;
+                }
+            }
+            '<' => {
+                if let Some((href, label, len)) = autolink(&text[i..]){
+                    flush(&mut buf, &mut items);
+                    items.push(Inline::Link{href, children: vec![Inline::Text(label)]});
+                    i += len;
+                }
+                else{
+                    buf.push('<');
+                    i += 1;
+                }
+            }
+            '&' => {
+                if let Some((c, len)) = decode_entity(&text[i..]){
+                    buf.push(c);
+                    i += len;
+                }
+                else{
+                    buf.push('&');
+                    i += 1;
+                }
+            }
+            '\n' => {
+                let hard = buf.ends_with("  ");
+                let trimmed = buf.trim_end_matches(' ').len();
+                buf.truncate(trimmed);
+                flush(&mut buf, &mut items);
+                items.push(if hard{Inline::HardBreak} else {Inline::SoftBreak});
+                i += 1;
+                i += text[i..].len() - text[i..].trim_start_matches(' ').len();
+            }
+            'h' | 'w' if prev.is_none_or(|p| p.is_whitespace() || p == '(') => {
+                if let Some((href, len)) = bare_autolink(&text[i..]){
+                    flush(&mut buf, &mut items);
+                    items.push(Inline::Link{href, children: vec![Inline::Text(text[i..i + len].to_string())]});
+                    i += len;
+                }
+                else{
+                    buf.push(c);
+                    i += 1;
+                }
+            }
+            _ => {
+                buf.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    flush(&mut buf, &mut items);
+    process_emphasis(&mut items);
+    items
+}
+
+// the CommonMark delimiter run algorithm, turns matching delimiter runs into styled spans
+fn process_emphasis(items:&mut Vec<Inline>){
+    let mut c = 0;
+    while c < items.len(){
+        let (cch, ccount, corig, copen) = match items[c]{
+            Inline::Delim{ch, count, orig, open, close: true} if count > 0 => (ch, count, orig, open),
+            _ => {
+                c += 1;
+                continue;
+            }
+        };
+        let mut opener = None;
+        for o in (0..c).rev(){
+            if let Inline::Delim{ch, count, orig, open: true, close} = items[o]{
+                if ch != cch || count == 0{
+                    continue
+                }
+                if cch == '~'{
+                    if count != ccount{
+                        continue
+                    }
+                }
+                else if (close || copen) && (orig + corig) % 3 == 0 && !(orig % 3 == 0 && corig % 3 == 0){
+                    continue
+                }
+                opener = Some((o, count));
+                break
+            }
+        }
+        let Some((o, ocount)) = opener else {
+            if let Inline::Delim{close, ..} = &mut items[c]{
+                *close = false;
+            }
+            c += 1;
+            continue;
+        };
+        let n = if cch == '~'{ccount} else if ocount >= 2 && ccount >= 2{2} else {1};
+        let style = if cch == '~'{Style::Strikethrough} else if n == 2{Style::Bold} else {Style::Italic};
+        let children = items.drain(o + 1..c).collect();
+        items.insert(o + 1, Inline::Style{style, children});
+        let mut closer = o + 2;
+        for index in [o, closer]{
+            if let Inline::Delim{count, ..} = &mut items[index]{
+                *count -= n;
+            }
+        }
+        if let Inline::Delim{count: 0, ..} = items[closer]{
+            items.remove(closer);
+        }
+        if let Inline::Delim{count: 0, ..} = items[o]{
+            items.remove(o);
+            closer -= 1;
+        }
+        c = closer;
+    }
+}
+
+#[cfg(test)]
+mod test{
+    use super::*;
+
+    fn dump(doc:&MarkdownDoc)->String{
+        let mut out = String::new();
+        for node in &doc.nodes{
+            match node{
+                MarkdownNode::Text{start, end} => out.push_str(doc.str(*start, *end)),
+                MarkdownNode::InlineCode{start, end} => out.push_str(&format!("`{}`", doc.str(*start, *end))),
+                MarkdownNode::BeginLink{href_start, href_end} => out.push_str(&format!("<a {}>", doc.str(*href_start, *href_end))),
+                MarkdownNode::Image{src_start, src_end, alt_start, alt_end} => out.push_str(&format!("<img {} {}>", doc.str(*src_start, *src_end), doc.str(*alt_start, *alt_end))),
+                MarkdownNode::CodeBlock{lang_start, lang_end, start, end} => out.push_str(&format!("<code {}>{}", doc.str(*lang_start, *lang_end), doc.str(*start, *end))),
+                node => out.push_str(&format!("<{:?}>", node)),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn emphasis(){
+        let doc = parse_markdown("a **b *c*** ~~d~~ _e_f");
+        assert_eq!(dump(&doc), "<BeginParagraph>a <BeginBold>b <BeginItalic>c<EndItalic><EndBold> <BeginStrikethrough>d<EndStrikethrough> _e_f<EndParagraph>");
+    }
+
+    #[test]
+    fn links_and_code(){
+        let doc = parse_markdown("[a `b`](http://x.y \"t\") ![img](p.png) <http://z> [r]\n\n[r]: /ref");
+        assert_eq!(dump(&doc), "<BeginParagraph><a http://x.y>a `b`<EndLink> <img p.png img> <a http://z>http://z<EndLink> <a /ref>r<EndLink><EndParagraph>");
+    }
+
+    #[test]
+    fn blocks(){
+        let doc = parse_markdown("# Head\n\n> quote\nlazy\n\n- a\n- b\n\n  c\n\n```rust\nfn x(){}\n```\n\n| a | b |\n|:--|--:|\n| 1 | 2 |");
+        assert_eq!(dump(&doc), concat!(
+            "<BeginHead { level: 1 }>Head<EndHead>",
+            "<BeginQuote><BeginParagraph>quote<SoftBreak>lazy<EndParagraph><EndQuote>",
+            "<BeginList { kind: Unordered, tight: false }><BeginListItem><BeginParagraph>a<EndParagraph><EndListItem>",
+            "<BeginListItem><BeginParagraph>b<EndParagraph><BeginParagraph>c<EndParagraph><EndListItem><EndList>",
+            "<code rust>fn x(){}",
+            "<BeginTable { columns: 2 }><BeginTableRow { header: true }><BeginTableCell { align: Left }>a<EndTableCell><BeginTableCell { align: Right }>b<EndTableCell><EndTableRow>",
+            "<BeginTableRow { header: false }><BeginTableCell { align: Left }>1<EndTableCell><BeginTableCell { align: Right }>2<EndTableCell><EndTableRow><EndTable>",
+        ));
+    }
+
+    #[test]
+    fn incremental_matches_full_parse(){
+        let text = "# Title\n\nSome *streamed* text\nwith a [link](x).\n\n1. one\n2. two\n\n```\ncode\n```\n";
+        let mut doc = MarkdownDoc::default();
+        for end in 0..=text.len(){
+            if text.is_char_boundary(end){
+                doc.update(&text[..end]);
+                assert_eq!(dump(&doc), dump(&parse_markdown(&text[..end])));
+            }
+        }
+    }
+}
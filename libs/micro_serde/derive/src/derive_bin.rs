@@ -1,196 +1,141 @@
 use proc_macro::{TokenStream};
 use makepad_micro_proc_macro::*;
+use crate::parse::*;
 
+// binary data has no keys, so only #[skip] changes the encoding
 pub fn derive_ser_bin_impl(input: TokenStream) -> TokenStream {
-    let mut parser = TokenParser::new(input);
+    let item = match parse_item(input, "SerBin") {
+        Ok(item) => item,
+        Err(err) => return err
+    };
     let mut tb = TokenBuilder::new();
-    
-    parser.eat_attributes();
-    parser.eat_ident("pub");
-    if parser.eat_ident("struct"){
-        if let Some(name) = parser.eat_any_ident(){
 
-            let generic = parser.eat_generic();
-            let types = parser.eat_all_types();
-            let where_clause = parser.eat_where_clause(Some("SerBin"));
+    item.impl_header(&mut tb, "SerBin");
+    tb.add("{ fn ser_bin ( & self , s : & mut Vec < u8 > ) {");
 
-            tb.add("impl").stream(generic.clone());
-            tb.add("SerBin for").ident(&name).stream(generic).stream(where_clause);
-            tb.add("{ fn ser_bin ( & self , s : & mut Vec < u8 > ) {");
-
-            if let Some(types) = types{
-                for i in 0..types.len(){
-                     tb.add("self .").unsuf_usize(i).add(". ser_bin ( s ) ;");
-                }
+    match &item.data {
+        Data::Struct(Fields::Tuple(types)) => {
+            for i in 0..types.len(){
+                tb.add("self .").unsuf_usize(i).add(". ser_bin ( s ) ;");
             }
-            else if let Some(fields) = parser.eat_all_struct_fields(){ 
-                for field in fields{
-                    tb.add("self .").ident(&field.name).add(". ser_bin ( s ) ;");
-                }
-            }
-            else{
-                return parser.unexpected()
+        }
+        Data::Struct(Fields::Named(fields)) => {
+            for field in fields.iter().filter( | f | !f.skip){
+                tb.add("self .").ident(&field.name).add(". ser_bin ( s ) ;");
             }
-            tb.add("} } ;"); 
-            return tb.end();
         }
-    }
-    else if parser.eat_ident("enum"){
-        if let Some(name) = parser.eat_any_ident(){
-            let generic = parser.eat_generic();
-            let where_clause = parser.eat_where_clause(Some("SerBin"));
-            
-            tb.add("impl").stream(generic.clone());
-            tb.add("SerBin for").ident(&name).stream(generic).stream(where_clause);
-            tb.add("{ fn ser_bin ( & self , s : & mut Vec < u8 > ) {");
+        Data::Struct(Fields::Unit) => return error("unit structs are not supported"),
+        Data::Enum(_, variants) => {
             tb.add("match self {");
-            
-            if !parser.open_brace(){
-                return parser.unexpected()
-            }
-            let mut index = 0;
-            while !parser.eat_eot(){
-                parser.eat_attributes();
-                // parse ident
-                if let Some(variant) = parser.eat_any_ident(){
-                    if let Some(types) = parser.eat_all_types(){
-                        tb.add("Self ::").ident(&variant).add("(");
+            for (index, variant) in variants.iter().enumerate(){
+                tb.add("Self ::").ident(&variant.name);
+                match &variant.fields{
+                    Fields::Unit => {
+                        tb.add("=> {").suf_u16(index as u16).add(". ser_bin ( s ) ; }");
+                    }
+                    Fields::Tuple(types) => {
+                        tb.add("(");
                         for i in 0..types.len(){
                             tb.ident(&format!("n{}", i)).add(",");
                         }
-                        tb.add(") => {").suf_u16(index).add(". ser_bin ( s ) ;");
+                        tb.add(") => {").suf_u16(index as u16).add(". ser_bin ( s ) ;");
                         for i in 0..types.len(){
                             tb.ident(&format!("n{}", i)).add(". ser_bin ( s ) ;");
                         }
                         tb.add("}");
                     }
-                    else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for field in fields.iter(){
-                            tb.ident(&field.name).add(",");
+                    Fields::Named(fields) => {
+                        tb.add("{");
+                        for (i, field) in fields.iter().enumerate(){
+                            tb.ident(&field.name).add(":");
+                            if field.skip{
+                                tb.add("_ ,");
+                            }
+                            else{
+                                tb.ident(&format!("n{}", i)).add(",");
+                            }
                         }
-                        tb.add("} => {").suf_u16(index).add(". ser_bin ( s ) ;");
-                        for field in fields{
-                            tb.ident(&field.name).add(". ser_bin ( s ) ;");
+                        tb.add("} => {").suf_u16(index as u16).add(". ser_bin ( s ) ;");
+                        for (i, field) in fields.iter().enumerate(){
+                            if !field.skip{
+                                tb.ident(&format!("n{}", i)).add(". ser_bin ( s ) ;");
+                            }
                         }
                         tb.add("}");
                     }
-                    else if parser.is_punct_alone(',') || parser.is_eot(){ // bare variant
-                        tb.add("Self ::").ident(&variant).add("=> {");
-                        tb.suf_u16(index).add(". ser_bin ( s ) ; }");
-                    }
-                    else{
-                        return parser.unexpected();
-                    }
-                    index += 1;
-                    parser.eat_punct_alone(',');
-                }
-                else{
-                    return parser.unexpected()
                 }
             }
-            tb.add("} } } ;");
-            return tb.end();
+            tb.add("}");
+        }
+    }
+    tb.add("} } ;");
+    tb.end()
+}
+
+fn de_bin_fields(tb: &mut TokenBuilder, fields: &[Field]){
+    tb.add("{");
+    for field in fields{
+        tb.ident(&field.name).add(":");
+        if field.skip{
+            field.default_value(tb);
         }
+        else{
+            tb.add("DeBin :: de_bin ( o , d ) ?");
+        }
+        tb.add(",");
     }
-    parser.unexpected()
-} 
+    tb.add("}");
+}
 
 pub fn derive_de_bin_impl(input: TokenStream) -> TokenStream {
-    let mut parser = TokenParser::new(input);
+    let item = match parse_item(input, "DeBin") {
+        Ok(item) => item,
+        Err(err) => return err
+    };
     let mut tb = TokenBuilder::new();
-    
-    parser.eat_attributes();
-    parser.eat_ident("pub");
-    if parser.eat_ident("struct"){
-        if let Some(name) = parser.eat_any_ident(){
-            let generic = parser.eat_generic();
-            let types = parser.eat_all_types();
-            let where_clause = parser.eat_where_clause(Some("DeBin"));
 
-            tb.add("impl").stream(generic.clone());
-            tb.add("DeBin for").ident(&name).stream(generic).stream(where_clause);
-            tb.add("{ fn de_bin ( o : & mut usize , d : & [ u8 ] )");
-            tb.add("-> std :: result :: Result < Self , DeBinErr > { ");
-            tb.add("std :: result :: Result :: Ok ( Self");
+    item.impl_header(&mut tb, "DeBin");
+    tb.add("{ fn de_bin ( o : & mut usize , d : & [ u8 ] )");
+    tb.add("-> std :: result :: Result < Self , DeBinErr > {");
 
-            if let Some(types) = types{
-                tb.add("(");
-                for _ in 0..types.len(){
-                     tb.add("DeBin :: de_bin ( o , d ) ? ,");
-                }
-                tb.add(")");
-            }
-            else if let Some(fields) = parser.eat_all_struct_fields(){ 
-                tb.add("{");
-                for field in fields{
-                    tb.ident(&field.name).add(": DeBin :: de_bin ( o , d ) ? ,");
-                }
-                tb.add("}");
+    match &item.data {
+        Data::Struct(Fields::Tuple(types)) => {
+            tb.add("std :: result :: Result :: Ok ( Self (");
+            for _ in 0..types.len(){
+                tb.add("DeBin :: de_bin ( o , d ) ? ,");
             }
-            else{
-                return parser.unexpected()
-            }
-            tb.add(") } } ;"); 
-            return tb.end();
+            tb.add(") )");
         }
-    }
-    else if parser.eat_ident("enum"){
-        if let Some(name) = parser.eat_any_ident(){
-            let generic = parser.eat_generic();
-            let where_clause = parser.eat_where_clause(Some("DeBin"));
-            
-            tb.add("impl").stream(generic.clone());
-            tb.add("DeBin for").ident(&name).stream(generic).stream(where_clause);
-            tb.add("{ fn de_bin ( o : & mut usize , d : & [ u8 ] )");
-            tb.add("-> std :: result :: Result < Self , DeBinErr > {");
+        Data::Struct(Fields::Named(fields)) => {
+            tb.add("std :: result :: Result :: Ok ( Self");
+            de_bin_fields(&mut tb, fields);
+            tb.add(")");
+        }
+        Data::Struct(Fields::Unit) => return error("unit structs are not supported"),
+        Data::Enum(_, variants) => {
             tb.add("let id : u16 = DeBin :: de_bin ( o , d ) ? ;");
             tb.add("match id {");
-            
-            if !parser.open_brace(){
-                return parser.unexpected()
-            }
-            let mut index = 0;
-            while !parser.eat_eot(){
-                // parse ident
-                parser.eat_attributes();
-                if let Some(variant) = parser.eat_any_ident(){
-                    tb.suf_u16(index as u16).add("=> {");
-                    tb.add("std :: result :: Result :: Ok ( Self ::");
-                    if let Some(types) = parser.eat_all_types(){
-                        tb.ident(&variant).add("(");
+            for (index, variant) in variants.iter().enumerate(){
+                tb.suf_u16(index as u16).add("=> {");
+                tb.add("std :: result :: Result :: Ok ( Self ::").ident(&variant.name);
+                match &variant.fields{
+                    Fields::Unit => (),
+                    Fields::Tuple(types) => {
+                        tb.add("(");
                         for _ in 0..types.len(){
                             tb.add("DeBin :: de_bin ( o , d ) ? ,");
                         }
                         tb.add(")");
                     }
-                    else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.ident(&variant).add("{");
-                        for field in fields.iter(){
-                            tb.ident(&field.name).add(": DeBin :: de_bin ( o , d ) ? ,");
-                        }
-                        tb.add("}");
-                    }
-                    else if parser.is_punct_alone(',') || parser.is_eot(){ // bare variant
-                        tb.ident(&variant);
-                    }
-                    else{
-                        return parser.unexpected();
-                    }
-                    
-                    tb.add(") }");
-                    index += 1;
-                    parser.eat_punct_alone(',');
+                    Fields::Named(fields) => de_bin_fields(&mut tb, fields),
                 }
-                else{
-                    return parser.unexpected()
-                }
-            } 
+                tb.add(") }");
+            }
             tb.add("_ => std :: result :: Result :: Err ( DeBinErr { o : * o , l :");
-            tb.unsuf_usize(1).add(", s : d . len ( ) , msg : ").string(&name).add(". to_string ( ) } )");
-            tb.add("} } } ;");
-            return tb.end();
+            tb.unsuf_usize(1).add(", s : d . len ( ) , msg : ").string(&item.name).add(". to_string ( ) } )");
+            tb.add("}");
         }
     }
-    parser.unexpected()
+    tb.add("} } ;");
+    tb.end()
 }
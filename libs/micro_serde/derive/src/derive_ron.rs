@@ -1,301 +1,403 @@
+use crate::parse::*;
 use makepad_micro_proc_macro::*;
 use proc_macro::TokenStream;
 
-pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
-    let mut parser = TokenParser::new(input);
-    let mut tb = TokenBuilder::new();
-
-    parser.eat_attributes();
-    parser.eat_ident("pub");
-    if parser.eat_ident("struct") {
-        if let Some(name) = parser.eat_any_ident() {
-            let generic = parser.eat_generic();
-            let types = parser.eat_all_types();
-            let where_clause = parser.eat_where_clause(Some("SerRon"));
+// a reference to a named field, either through self or through the n{} binding of a variant
+fn field_ref(tb: &mut TokenBuilder, index: usize, field: &Field, in_variant: bool) {
+    if in_variant {
+        tb.ident(&format!("n{}", index));
+    } else {
+        tb.add("( & self .").ident(&field.name).add(")");
+    }
+}
 
-            tb.add("impl").stream(generic.clone());
-            tb.add("SerRon for")
-                .ident(&name)
-                .stream(generic)
-                .stream(where_clause);
+fn variant_pattern(tb: &mut TokenBuilder, variant: &Variant) {
+    tb.add("Self ::").ident(&variant.name);
+    match &variant.fields {
+        Fields::Unit => (),
+        Fields::Tuple(types) => {
+            tb.add("(");
+            for i in 0..types.len() {
+                tb.ident(&format!("n{}", i)).add(",");
+            }
+            tb.add(")");
+        }
+        Fields::Named(fields) => {
             tb.add("{");
-            tb.add("fn ser_ron(&self, d: usize, s: &mut SerRonState){");
-
-            if let Some(types) = types {
-                tb.add("s.out.push(").chr('(').add(");");
-                for i in 0..types.len() {
-                    tb.add("self.").unsuf_usize(i).add(".ser_ron(d, s);");
-                    if i != types.len() - 1 {
-                        tb.add("s.out.push_str(").string(", ").add(") ;");
-                    }
-                }
-                tb.add("s.out.push(").chr(')').add(");");
-            } else if let Some(fields) = parser.eat_all_struct_fields() {
-                tb.add("s.st_pre( ) ;");
-
-                // named struct
-                for field in fields {
-                    if field.ty.into_iter().next().unwrap().to_string() == "Option" {
-                        tb.add("if let Some ( t ) = ")
-                            .add("& self .")
-                            .ident(&field.name)
-                            .add("{");
-                        tb.add("s.field ( d + 1 ,").string(&field.name).add(") ;");
-                        tb.add("t.ser_ron ( d + 1 , s ) ; s . conl ( ) ; } ;");
-                    } else {
-                        tb.add("s . field ( d + 1 ,")
-                            .string(&field.name)
-                            .add(" ) ;");
-                        tb.add("self .")
-                            .ident(&field.name)
-                            .add(". ser_ron ( d + 1 , s ) ; s . conl ( ) ;");
-                    }
+            for (i, field) in fields.iter().enumerate() {
+                tb.ident(&field.name).add(":");
+                if field.skip {
+                    tb.add("_ ,");
+                } else {
+                    tb.ident(&format!("n{}", i)).add(",");
                 }
-                tb.add("s . st_post ( d ) ;");
-            } else {
-                return parser.unexpected();
             }
-            tb.add("} } ;");
-            return tb.end();
+            tb.add("}");
         }
-    } else if parser.eat_ident("enum") {
-        if let Some(name) = parser.eat_any_ident() {
-            let generic = parser.eat_generic();
-            let where_clause = parser.eat_where_clause(Some("SerRon"));
+    }
+}
 
-            tb.add("impl").stream(generic.clone());
-            tb.add("SerRon for")
-                .ident(&name)
-                .stream(generic)
-                .stream(where_clause);
-            tb.add("{ fn ser_ron ( & self , d : usize , s : & mut  SerRonState ) {");
-            tb.add("match self {");
+fn ser_tag(tb: &mut TokenBuilder, tag: &str, variant: &Variant) {
+    tb.add("s . field ( d + 1 ,").string(tag).add(") ;");
+    tb.add("s . out . push_str (").string(&variant.key()).add(") ; s . conl ( ) ;");
+}
 
-            if !parser.open_brace() {
-                return parser.unexpected();
-            }
+fn ser_fields(tb: &mut TokenBuilder, fields: &[Field], in_variant: bool) {
+    for (i, field) in fields.iter().enumerate() {
+        if field.skip {
+            continue;
+        }
+        if field.flatten {
+            tb.add("s . flatten ( d ,");
+            field_ref(tb, i, field, in_variant);
+            tb.add(") ;");
+        } else if field.is_option() {
+            tb.add("if let Some ( t ) =");
+            field_ref(tb, i, field, in_variant);
+            tb.add("{ s . field ( d + 1 ,").string(&field.ron_key()).add(") ;");
+            tb.add("t . ser_ron ( d + 1 , s ) ; s . conl ( ) ; }");
+        } else {
+            tb.add("s . field ( d + 1 ,").string(&field.ron_key()).add(") ;");
+            field_ref(tb, i, field, in_variant);
+            tb.add(". ser_ron ( d + 1 , s ) ; s . conl ( ) ;");
+        }
+    }
+}
 
-            while !parser.eat_eot() {
-                // parse ident
-                parser.eat_attributes();
-                if let Some(variant) = parser.eat_any_ident() {
-                    if let Some(types) = parser.eat_all_types() {
-                        tb.add("Self ::").ident(&variant).add("(");
-                        for i in 0..types.len() {
-                            tb.ident(&format!("n{}", i)).add(",");
-                        }
-                        tb.add(") => {");
-                        tb.add("s . out . push_str (").string(&variant).add(") ;");
-                        tb.add("s . out . push (").chr('(').add(") ;");
+fn ser_tuple(tb: &mut TokenBuilder, len: usize, in_variant: bool) {
+    tb.add("s . out . push (").chr('(').add(") ;");
+    for i in 0..len {
+        if in_variant {
+            tb.ident(&format!("n{}", i));
+        } else {
+            tb.add("self .").unsuf_usize(i);
+        }
+        tb.add(". ser_ron ( d , s ) ;");
+        if i != len - 1 {
+            tb.add("s . out . push_str (").string(", ").add(") ;");
+        }
+    }
+    tb.add("s . out . push (").chr(')').add(") ;");
+}
+
+// the variant data on its own, as used by adjacently tagged and untagged enums
+fn ser_content(tb: &mut TokenBuilder, variant: &Variant) {
+    match &variant.fields {
+        Fields::Unit => {
+            tb.add("s . out . push_str (").string("()").add(") ;");
+        }
+        Fields::Tuple(types) if types.len() == 1 => {
+            tb.add("n0 . ser_ron ( d , s ) ;");
+        }
+        Fields::Tuple(types) => ser_tuple(tb, types.len(), true),
+        Fields::Named(fields) => {
+            tb.add("s . st_pre ( ) ;");
+            ser_fields(tb, fields, true);
+            tb.add("s . st_post ( d ) ;");
+        }
+    }
+}
+
+pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
+    let item = match parse_item(input, "SerRon") {
+        Ok(item) => item,
+        Err(err) => return err,
+    };
+    let mut tb = TokenBuilder::new();
+
+    item.impl_header(&mut tb, "SerRon");
+    tb.add("{ fn ser_ron ( & self , d : usize , s : & mut SerRonState ) {");
 
-                        for i in 0..types.len() {
-                            tb.ident(&format!("n{}", i)).add(". ser_ron ( d , s ) ;");
-                            if i != types.len() - 1 {
-                                tb.add("s . out . push_str (").string(", ").add(") ;");
+    match &item.data {
+        Data::Struct(Fields::Tuple(types)) => ser_tuple(&mut tb, types.len(), false),
+        Data::Struct(Fields::Named(fields)) => {
+            tb.add("s . st_pre ( ) ;");
+            ser_fields(&mut tb, fields, false);
+            tb.add("s . st_post ( d ) ;");
+        }
+        Data::Struct(Fields::Unit) => return error("unit structs are not supported"),
+        Data::Enum(tagging, variants) => {
+            tb.add("match self {");
+            for variant in variants {
+                variant_pattern(&mut tb, variant);
+                tb.add("=> {");
+                match tagging {
+                    Tagging::External => {
+                        tb.add("s . out . push_str (").string(&variant.key()).add(") ;");
+                        match &variant.fields {
+                            Fields::Unit => (),
+                            Fields::Tuple(types) => ser_tuple(&mut tb, types.len(), true),
+                            Fields::Named(fields) => {
+                                tb.add("s . st_pre ( ) ;");
+                                ser_fields(&mut tb, fields, true);
+                                tb.add("s . st_post ( d ) ;");
                             }
                         }
-                        tb.add("s . out . push (").chr(')').add(") ;");
-                        tb.add("}");
-                    } else if let Some(fields) = parser.eat_all_struct_fields() {
-                        // named variant
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for field in fields.iter() {
-                            tb.ident(&field.name).add(",");
-                        }
-                        tb.add("} => {");
-
-                        tb.add("s . out . push_str (").string(&variant).add(") ;");
+                    }
+                    Tagging::Internal(tag) => {
                         tb.add("s . st_pre ( ) ;");
-
-                        for field in fields {
-                            if field.ty.into_iter().next().unwrap().to_string() == "Option" {
-                                tb.add("if ").ident(&field.name).add(". is_some ( ) {");
-                                tb.add("s . field ( d + 1 ,").string(&field.name).add(") ;");
-                                tb.ident(&field.name)
-                                    .add(" . ser_ron ( d + 1 , s ) ; s . conl ( ) ; } ;");
-                            } else {
-                                tb.add("s . field ( d + 1 ,")
-                                    .string(&field.name)
-                                    .add(" ) ;");
-                                tb.ident(&field.name)
-                                    .add(". ser_ron ( d + 1 , s ) ; s . conl ( ) ;");
+                        ser_tag(&mut tb, tag, variant);
+                        match &variant.fields {
+                            Fields::Unit => (),
+                            Fields::Tuple(_) => {
+                                tb.add("s . flatten ( d , n0 ) ;");
                             }
+                            Fields::Named(fields) => ser_fields(&mut tb, fields, true),
                         }
-                        tb.add("s . st_post ( d ) ; }");
-                    } else if parser.is_punct_alone(',') || parser.is_eot() {
-                        // bare variant
-                        tb.add("Self ::").ident(&variant).add("=> {");
-                        tb.add("s . out . push_str (").string(&variant).add(") ; }");
-                    } else {
-                        return parser.unexpected();
+                        tb.add("s . st_post ( d ) ;");
                     }
-                    parser.eat_punct_alone(',');
-                } else {
-                    return parser.unexpected();
+                    Tagging::Adjacent(tag, content) => {
+                        tb.add("s . st_pre ( ) ;");
+                        ser_tag(&mut tb, tag, variant);
+                        if !matches!(variant.fields, Fields::Unit) {
+                            tb.add("s . field ( d + 1 ,").string(content).add(") ;");
+                            tb.add("{ let d = d + 1 ;");
+                            ser_content(&mut tb, variant);
+                            tb.add("} s . conl ( ) ;");
+                        }
+                        tb.add("s . st_post ( d ) ;");
+                    }
+                    Tagging::Untagged => ser_content(&mut tb, variant),
                 }
+                tb.add("}");
             }
             tb.add("}");
-            tb.add("} } ;");
-            return tb.end();
         }
     }
-    parser.unexpected()
+    tb.add("} } ;");
+    tb.end()
 }
 
-pub fn derive_de_ron_impl(input: TokenStream) -> TokenStream {
-    let mut parser = TokenParser::new(input);
-    let mut tb = TokenBuilder::new();
-    parser.eat_attributes();
-    parser.eat_ident("pub");
-    if parser.eat_ident("struct") {
-        if let Some(name) = parser.eat_any_ident() {
-            let generic = parser.eat_generic();
-            let types = parser.eat_all_types();
-            let where_clause = parser.eat_where_clause(Some("DeRon"));
+// whether the generated de_ron needs to remember if unknown keys may be skipped
+fn needs_skip_unknown(item: &Item) -> bool {
+    matches!(item.data, Data::Enum(Tagging::Adjacent(..), _))
+        || item
+            .all_fields()
+            .iter()
+            .any(|fields| matches!(fields, Fields::Named(_)) && !fields.has_flatten())
+}
 
-            tb.add("impl").stream(generic.clone());
-            tb.add("DeRon for")
-                .ident(&name)
-                .stream(generic)
-                .stream(where_clause);
-            tb.add("{ fn de_ron ( s : &  mut DeRonState , i : & mut std :: str :: Chars )");
-            tb.add("-> std :: result :: Result < Self , DeRonErr > { ");
+fn de_ctor(tb: &mut TokenBuilder, variant: Option<&Variant>) {
+    tb.add("Self");
+    if let Some(variant) = variant {
+        tb.add("::").ident(&variant.name);
+    }
+}
 
-            if let Some(types) = types {
-                tb.add("s . paren_open ( i ) ? ;");
-                tb.add("let r = Self");
-                tb.add("(");
-                for _ in 0..types.len() {
-                    tb.add("{ let r = DeRon :: de_ron ( s , i ) ? ; s . eat_comma_paren ( i ) ? ; r } ,");
-                }
-                tb.add(") ;");
-                tb.add("s . paren_close ( i ) ? ;");
-                tb.add("std :: result :: Result :: Ok ( r ) ");
-            } else if let Some(fields) = parser.eat_all_struct_fields() {
-                tb.add("s . paren_open ( i ) ? ;");
-                for field in &fields {
-                    tb.add("let mut")
-                        .ident(&format!("_{}", field.name))
-                        .add("= None ;");
-                }
-                tb.add("while let Some ( _ ) = s . next_ident ( ) {");
-                tb.add("match s . identbuf . as_ref ( ) {");
-                for field in &fields {
-                    tb.string(&field.name).add("=> { s . next_colon ( i ) ? ;");
-                    tb.ident(&format!("_{}", field.name))
-                        .add("= Some ( DeRon :: de_ron ( s , i ) ? ) ; } ,");
-                }
-                tb.add(
-                    "_ => return std :: result :: Result :: Err ( s . err_exp ( & s . identbuf ) )",
-                );
-                tb.add("} ; s . eat_comma_paren ( i ) ? ;");
-                tb.add("} ; s . paren_close ( i ) ? ;");
+// parses a struct into the named fields, evaluates to the constructed value.
+// skip_keys are consumed and ignored, such as the tag of an internally tagged enum
+fn de_struct(tb: &mut TokenBuilder, fields: &[Field], variant: Option<&Variant>, skip_keys: &[&str]) {
+    let has_flatten = fields.iter().any(|f| f.flatten);
+    tb.add("{");
+    if has_flatten {
+        tb.add("let start = ( s . clone ( ) , i . clone ( ) ) ;");
+    }
+    tb.add("s . paren_open ( i ) ? ;");
+    for field in fields.iter().filter(|f| !f.skip && !f.flatten) {
+        tb.add("let mut")
+            .ident(&format!("_{}", field.name))
+            .add("= None ;");
+    }
+    tb.add("while let Some ( _ ) = s . next_ident ( ) {");
+    tb.add("match s . identbuf . as_ref ( ) {");
+    for field in fields.iter().filter(|f| !f.skip && !f.flatten) {
+        tb.string(&field.ron_key()).add("=> { s . next_colon ( i ) ? ;");
+        tb.ident(&format!("_{}", field.name))
+            .add("= Some ( DeRon :: de_ron ( s , i ) ? ) ; } ,");
+    }
+    let mut skipped: Vec<String> = skip_keys.iter().map(|k| k.to_string()).collect();
+    skipped.extend(fields.iter().filter(|f| f.skip).map(|f| f.ron_key()));
+    for (i, key) in skipped.iter().enumerate() {
+        if i != 0 {
+            tb.add("|");
+        }
+        tb.string(key);
+    }
+    if !skipped.is_empty() {
+        tb.add("=> { s . next_colon ( i ) ? ; s . skip_value ( i ) ? ; } ,");
+    }
+    if has_flatten {
+        tb.add("_ => { s . next_colon ( i ) ? ; s . skip_value ( i ) ? ; }");
+    } else {
+        tb.add("_ => if skip_unknown { s . next_colon ( i ) ? ; s . skip_value ( i ) ? ; }");
+        tb.add("else { return std :: result :: Result :: Err ( s . err_exp ( & s . identbuf ) ) }");
+    }
+    tb.add("} ; s . eat_comma_paren ( i ) ? ;");
+    tb.add("} ; s . paren_close ( i ) ? ;");
 
-                tb.add("std :: result :: Result :: Ok ( Self {");
-                for field in fields {
-                    tb.ident(&field.name).add(":");
-                    if field.ty.into_iter().next().unwrap().to_string() == "Option" {
-                        tb.add("if let Some ( t ) =")
-                            .ident(&format!("_{}", field.name));
-                        tb.add("{ t } else { None } ,");
-                    } else {
-                        tb.add("if let Some ( t ) =")
-                            .ident(&format!("_{}", field.name));
-                        tb.add("{ t } else { return Err ( s . err_nf (");
-                        tb.string(&field.name).add(") ) } ,");
-                    }
-                }
-                tb.add("} )");
+    de_ctor(tb, variant);
+    tb.add("{");
+    for field in fields {
+        tb.ident(&field.name).add(":");
+        if field.skip {
+            field.default_value(tb);
+        } else if field.flatten {
+            tb.add("{ let ( mut s , mut i ) = start . clone ( ) ; s . skip_unknown = true ;");
+            tb.add("DeRon :: de_ron ( & mut s , & mut i ) ? }");
+        } else {
+            if field.default.is_some() {
+                tb.add("if let Some ( t ) =")
+                    .ident(&format!("_{}", field.name))
+                    .add("{ t } else {");
+                field.default_value(tb);
+                tb.add("}");
+            } else if field.is_option() {
+                tb.ident(&format!("_{}", field.name))
+                    .add(". unwrap_or ( None )");
             } else {
-                return parser.unexpected();
+                tb.ident(&format!("_{}", field.name))
+                    .add(". ok_or_else ( | | s . err_nf (")
+                    .string(&field.ron_key())
+                    .add(") ) ?");
             }
-            tb.add("} } ;");
-            return tb.end();
         }
-    } else if parser.eat_ident("enum") {
-        if let Some(name) = parser.eat_any_ident() {
-            let generic = parser.eat_generic();
-            let where_clause = parser.eat_where_clause(Some("DeRon"));
+        tb.add(",");
+    }
+    tb.add("} }");
+}
+
+fn de_tuple(tb: &mut TokenBuilder, len: usize, variant: Option<&Variant>) {
+    tb.add("{ s . paren_open ( i ) ? ;");
+    tb.add("let r =");
+    de_ctor(tb, variant);
+    tb.add("(");
+    for _ in 0..len {
+        tb.add("{ let r = DeRon :: de_ron ( s , i ) ? ; s . eat_comma_paren ( i ) ? ; r } ,");
+    }
+    tb.add(") ;");
+    tb.add("s . paren_close ( i ) ? ; r }");
+}
+
+// the counterpart of ser_content
+fn de_content(tb: &mut TokenBuilder, variant: &Variant) {
+    match &variant.fields {
+        Fields::Unit => {
+            tb.add("{ s . paren_open ( i ) ? ; s . paren_close ( i ) ? ;");
+            de_ctor(tb, Some(variant));
+            tb.add("}");
+        }
+        Fields::Tuple(types) if types.len() == 1 => {
+            de_ctor(tb, Some(variant));
+            tb.add("( DeRon :: de_ron ( s , i ) ? )");
+        }
+        Fields::Tuple(types) => de_tuple(tb, types.len(), Some(variant)),
+        Fields::Named(fields) => de_struct(tb, fields, Some(variant), &[]),
+    }
+}
 
-            tb.add("impl").stream(generic.clone());
-            tb.add("DeRon for")
-                .ident(&name)
-                .stream(generic)
-                .stream(where_clause);
-            tb.add("{ fn de_ron ( s : & mut  DeRonState , i : & mut std :: str :: Chars )");
-            tb.add("-> std :: result :: Result < Self , DeRonErr > { ");
+pub fn derive_de_ron_impl(input: TokenStream) -> TokenStream {
+    let item = match parse_item(input, "DeRon") {
+        Ok(item) => item,
+        Err(err) => return err,
+    };
+    let mut tb = TokenBuilder::new();
+
+    item.impl_header(&mut tb, "DeRon");
+    tb.add("{ fn de_ron ( s : & mut DeRonState , i : & mut std :: str :: Chars )");
+    tb.add("-> std :: result :: Result < Self , DeRonErr > {");
+    if needs_skip_unknown(&item) {
+        tb.add("let skip_unknown = std :: mem :: replace ( & mut s . skip_unknown , false ) ;");
+    } else {
+        tb.add("s . skip_unknown = false ;");
+    }
+
+    match &item.data {
+        Data::Struct(Fields::Tuple(types)) => {
+            tb.add("std :: result :: Result :: Ok (");
+            de_tuple(&mut tb, types.len(), None);
+            tb.add(")");
+        }
+        Data::Struct(Fields::Named(fields)) => {
+            tb.add("std :: result :: Result :: Ok (");
+            de_struct(&mut tb, fields, None, &[]);
+            tb.add(")");
+        }
+        Data::Struct(Fields::Unit) => return error("unit structs are not supported"),
+        Data::Enum(Tagging::External, variants) => {
             tb.add("s . ident ( i ) ? ;");
             tb.add("std :: result :: Result :: Ok ( match s . identbuf . as_ref ( ) {");
-
-            if !parser.open_brace() {
-                return parser.unexpected();
+            for variant in variants {
+                tb.string(&variant.key()).add("=>");
+                match &variant.fields {
+                    Fields::Unit => de_ctor(&mut tb, Some(variant)),
+                    Fields::Tuple(types) => de_tuple(&mut tb, types.len(), Some(variant)),
+                    Fields::Named(fields) => de_struct(&mut tb, fields, Some(variant), &[]),
+                }
+                tb.add(",");
             }
-            while !parser.eat_eot() {
-                // parse ident
-                parser.eat_attributes();
-                if let Some(variant) = parser.eat_any_ident() {
-                    tb.string(&variant).add("=> {");
-                    if let Some(types) = parser.eat_all_types() {
-                        tb.add("s . paren_open ( i ) ? ;");
-                        tb.add("let r = Self ::").ident(&variant).add("(");
-                        for _ in 0..types.len() {
-                            tb.add("{ let r = DeRon :: de_ron ( s , i ) ? ; s . eat_comma_paren ( i ) ? ; r } ,");
-                        }
-                        tb.add(") ;");
-                        tb.add("s . paren_close ( i ) ? ; r");
-                    } else if let Some(fields) = parser.eat_all_struct_fields() {
-                        // named variant
-                        tb.add("s . paren_open ( i ) ? ;");
-                        for field in &fields {
-                            tb.add("let mut")
-                                .ident(&format!("_{}", field.name))
-                                .add("= None ;");
-                        }
-                        tb.add("while let Some ( _ ) = s . next_ident ( ) {");
-                        tb.add("match s . identbuf . as_ref ( ) {");
-                        for field in &fields {
-                            tb.string(&field.name).add("=> { s . next_colon ( i ) ? ;");
-                            tb.ident(&format!("_{}", field.name))
-                                .add("= Some ( DeRon :: de_ron ( s , i ) ? ) ; } ,");
-                        }
-                        tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . strbuf ) )");
-                        tb.add("} ; s . eat_comma_paren ( i ) ? ;");
-                        tb.add("} ; s . paren_close ( i ) ? ;");
-
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for field in fields {
-                            tb.ident(&field.name).add(":");
-                            if field.ty.into_iter().next().unwrap().to_string() == "Option" {
-                                tb.add("if let Some ( t ) =")
-                                    .ident(&format!("_{}", field.name));
-                                tb.add("{ t } else { None } ,");
-                            } else {
-                                tb.add("if let Some ( t ) =")
-                                    .ident(&format!("_{}", field.name));
-                                tb.add("{ t } else { return Err ( s . err_nf (");
-                                tb.string(&field.name).add(") ) } ,");
-                            }
-                        }
+            tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( & s . identbuf ) )");
+            tb.add("} )");
+        }
+        Data::Enum(Tagging::Internal(tag), variants) => {
+            tb.add("let tag = s . peek_tag ( i ,").string(tag).add(") ? ;");
+            tb.add("std :: result :: Result :: Ok ( match tag . as_ref ( ) {");
+            for variant in variants {
+                tb.string(&variant.key()).add("=>");
+                match &variant.fields {
+                    Fields::Unit => {
+                        tb.add("{ s . skip_value ( i ) ? ;");
+                        de_ctor(&mut tb, Some(variant));
                         tb.add("}");
-                    } else if parser.is_punct_alone(',') || parser.is_eot() {
-                        // bare variant
-                        tb.add("Self ::").ident(&variant);
-                    } else {
-                        return parser.unexpected();
                     }
-
-                    tb.add("}");
-                    parser.eat_punct_alone(',');
-                } else {
-                    return parser.unexpected();
+                    Fields::Tuple(_) => {
+                        tb.add("{ s . skip_unknown = true ;");
+                        de_ctor(&mut tb, Some(variant));
+                        tb.add("( DeRon :: de_ron ( s , i ) ? ) }");
+                    }
+                    Fields::Named(fields) => de_struct(&mut tb, fields, Some(variant), &[tag]),
                 }
+                tb.add(",");
+            }
+            tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( & tag ) )");
+            tb.add("} )");
+        }
+        Data::Enum(Tagging::Adjacent(tag, content), variants) => {
+            tb.add("let tag = s . peek_tag ( i ,").string(tag).add(") ? ;");
+            tb.add("s . paren_open ( i ) ? ;");
+            tb.add("let mut r = None ;");
+            tb.add("while let Some ( _ ) = s . next_ident ( ) {");
+            tb.add("if s . identbuf ==").string(content).add("{ s . next_colon ( i ) ? ;");
+            tb.add("r = Some ( match tag . as_ref ( ) {");
+            for variant in variants {
+                tb.string(&variant.key()).add("=>");
+                de_content(&mut tb, variant);
+                tb.add(",");
+            }
+            tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( & tag ) )");
+            tb.add("} ) ; }");
+            tb.add("else if s . identbuf ==").string(tag).add("|| skip_unknown {");
+            tb.add("s . next_colon ( i ) ? ; s . skip_value ( i ) ? ; }");
+            tb.add("else { return std :: result :: Result :: Err ( s . err_exp ( & s . identbuf ) ) }");
+            tb.add("s . eat_comma_paren ( i ) ? ;");
+            tb.add("} s . paren_close ( i ) ? ;");
+            tb.add("if let Some ( r ) = r { return std :: result :: Result :: Ok ( r ) }");
+            tb.add("std :: result :: Result :: Ok ( match tag . as_ref ( ) {");
+            for variant in variants {
+                if let Fields::Unit = variant.fields {
+                    tb.string(&variant.key()).add("=>");
+                    de_ctor(&mut tb, Some(variant));
+                    tb.add(",");
+                }
+            }
+            tb.add("_ => return std :: result :: Result :: Err ( s . err_nf (")
+                .string(content)
+                .add(") )");
+            tb.add("} )");
+        }
+        Data::Enum(Tagging::Untagged, variants) => {
+            for variant in variants {
+                tb.add("{ let mut ts = s . clone ( ) ; let mut ti = i . clone ( ) ;");
+                tb.add("let r = ( | s : & mut DeRonState , i : & mut std :: str :: Chars |");
+                tb.add("-> std :: result :: Result < Self , DeRonErr > { std :: result :: Result :: Ok (");
+                de_content(&mut tb, variant);
+                tb.add(") } ) ( & mut ts , & mut ti ) ;");
+                tb.add("if let std :: result :: Result :: Ok ( r ) = r { * s = ts ; * i = ti ; return std :: result :: Result :: Ok ( r ) } }");
             }
-            tb.add(
-                "_ => return std :: result :: Result :: Err ( s . err_enum ( & s . identbuf ) )",
-            );
-            tb.add("} ) } }");
-            return tb.end();
+            tb.add("std :: result :: Result :: Err ( s . err_msg (")
+                .string(&format!("No variant of {} matches", item.name))
+                .add(") )");
         }
     }
-    parser.unexpected()
+    tb.add("} } ;");
+    tb.end()
 }
@@ -0,0 +1,286 @@
+use proc_macro::{TokenStream, TokenTree};
+use makepad_micro_proc_macro::*;
+
+// the parsed shape of a type we derive for, including its serde attributes
+//
+// field attributes:
+//   #[rename("name")]   use another key for the field (json/ron)
+//   #[default]          use Default::default() when the key is missing
+//   #[default(expr)]    use expr when the key is missing
+//   #[skip]             never serialize the field, deserialize it as its default
+//   #[flatten]          inline the fields of a struct valued field into the parent
+// variant attributes:
+//   #[rename("name")]   use another name for the variant (json/ron)
+// enum attributes:
+//   #[tag("t")]                  internally tagged {"t":"Variant", ..fields}
+//   #[tag("t")] #[content("c")]  adjacently tagged {"t":"Variant", "c":content}
+//   #[untagged]                  only the content, the first variant that parses wins
+
+pub struct Field {
+    pub name: String,
+    pub ty: TokenStream,
+    pub rename: Option<String>,
+    pub default: Option<TokenStream>,
+    pub skip: bool,
+    pub flatten: bool,
+}
+
+pub enum Fields {
+    Unit,
+    Tuple(Vec<TokenStream>),
+    Named(Vec<Field>),
+}
+
+pub struct Variant {
+    pub name: String,
+    pub rename: Option<String>,
+    pub fields: Fields,
+}
+
+pub enum Tagging {
+    External,
+    Internal(String),
+    Adjacent(String, String),
+    Untagged,
+}
+
+pub enum Data {
+    Struct(Fields),
+    Enum(Tagging, Vec<Variant>),
+}
+
+pub struct Item {
+    pub name: String,
+    pub generic: Option<TokenStream>,
+    pub where_clause: Option<TokenStream>,
+    pub data: Data,
+}
+
+impl Field {
+    pub fn is_option(&self) -> bool {
+        self.ty.clone().into_iter().next().map(|t| t.to_string() == "Option").unwrap_or(false)
+    }
+
+    pub fn ron_key(&self) -> String {
+        self.rename.clone().unwrap_or_else(|| self.name.clone())
+    }
+
+    // json keys drop a leading _ so fields can be named after keywords
+    pub fn json_key(&self) -> String {
+        if let Some(rename) = &self.rename {
+            return rename.clone()
+        }
+        if let Some(v) = self.name.strip_prefix('_') {v.to_string()} else {self.name.clone()}
+    }
+
+    // the value a field gets when it is skipped or its key is missing
+    pub fn default_value(&self, tb: &mut TokenBuilder) {
+        if let Some(default) = &self.default {
+            tb.add("{").stream(Some(default.clone())).add("}");
+        }
+        else {
+            tb.add("std :: default :: Default :: default ( )");
+        }
+    }
+}
+
+impl Fields {
+    pub fn named(&self) -> &[Field] {
+        if let Fields::Named(fields) = self {fields} else {&[]}
+    }
+
+    pub fn has_flatten(&self) -> bool {
+        self.named().iter().any( | f | f.flatten)
+    }
+}
+
+impl Variant {
+    pub fn key(&self) -> String {
+        self.rename.clone().unwrap_or_else(|| self.name.clone())
+    }
+}
+
+impl Item {
+    pub fn impl_header(&self, tb: &mut TokenBuilder, trait_name: &str) {
+        tb.add("impl").stream(self.generic.clone());
+        tb.ident(trait_name).add("for").ident(&self.name).stream(self.generic.clone()).stream(self.where_clause.clone());
+    }
+
+    pub fn all_fields(&self) -> Vec<&Fields> {
+        match &self.data {
+            Data::Struct(fields) => vec![fields],
+            Data::Enum(_, variants) => variants.iter().map( | v | &v.fields).collect()
+        }
+    }
+}
+
+// accepts #[attr("value")], #[attr(value)] and #[attr = "value"]
+fn attr_string(attr: &Attribute) -> Result<String, TokenStream> {
+    if let Some(args) = &attr.args {
+        let mut iter = args.clone().into_iter();
+        if let (Some(tt), None) = (iter.next(), iter.next()) {
+            match tt {
+                TokenTree::Literal(lit) => {
+                    let s = lit.to_string();
+                    if let Some(s) = s.strip_prefix('"').and_then( | s | s.strip_suffix('"')) {
+                        return Ok(s.to_string())
+                    }
+                }
+                TokenTree::Ident(ident) => return Ok(ident.to_string()),
+                _ => ()
+            }
+        }
+    }
+    Err(error(&format!("#[{}] expects a name, as in #[{}(\"name\")]", attr.name, attr.name)))
+}
+
+fn parse_field(sf: StructField) -> Result<Field, TokenStream> {
+    let mut field = Field {
+        name: sf.name,
+        ty: sf.ty,
+        rename: None,
+        default: None,
+        skip: false,
+        flatten: false,
+    };
+    for attr in &sf.attrs {
+        match attr.name.as_ref() {
+            "rename" => field.rename = Some(attr_string(attr) ?),
+            "default" => field.default = Some(match attr.args.clone().filter( | args | !args.is_empty()) {
+                Some(expr) => expr,
+                None => {
+                    let mut tb = TokenBuilder::new();
+                    tb.add("std :: default :: Default :: default ( )");
+                    tb.end()
+                }
+            }),
+            "skip" => field.skip = true,
+            "flatten" => field.flatten = true,
+            _ => ()
+        }
+    }
+    if field.skip && field.flatten {
+        return Err(error(&format!("field {} cannot be both #[skip] and #[flatten]", field.name)))
+    }
+    Ok(field)
+}
+
+fn parse_fields(parser: &mut TokenParser) -> Result<Option<Fields>, TokenStream> {
+    if let Some(fields) = parser.eat_all_struct_fields() {
+        let mut out = Vec::new();
+        for sf in fields {
+            out.push(parse_field(sf) ?);
+        }
+        return Ok(Some(Fields::Named(out)))
+    }
+    Ok(None)
+}
+
+fn parse_tuple(parser: &mut TokenParser) -> Result<Option<Fields>, TokenStream> {
+    if !parser.open_paren() {
+        return Ok(None)
+    }
+    let mut types = Vec::new();
+    while !parser.eat_eot() {
+        for attr in parser.eat_attributes() {
+            if let "rename" | "default" | "skip" | "flatten" = attr.name.as_ref() {
+                return Err(error(&format!("#[{}] is only supported on named fields", attr.name)))
+            }
+        }
+        parser.eat_ident("pub");
+        if let Some(ty) = parser.eat_type() {
+            types.push(ty);
+            parser.eat_punct_alone(',');
+        }
+        else {
+            return Err(parser.unexpected())
+        }
+    }
+    Ok(Some(Fields::Tuple(types)))
+}
+
+pub fn parse_item(input: TokenStream, trait_name: &str) -> Result<Item, TokenStream> {
+    let mut parser = TokenParser::new(input);
+
+    let mut tag = None;
+    let mut content = None;
+    let mut untagged = false;
+    for attr in parser.eat_attributes() {
+        match attr.name.as_ref() {
+            "tag" => tag = Some(attr_string(&attr) ?),
+            "content" => content = Some(attr_string(&attr) ?),
+            "untagged" => untagged = true,
+            _ => ()
+        }
+    }
+
+    parser.eat_ident("pub");
+    if parser.eat_ident("struct") {
+        let name = parser.eat_any_ident().ok_or_else( || parser.unexpected()) ?;
+        if tag.is_some() || content.is_some() || untagged {
+            return Err(error("#[tag], #[content] and #[untagged] are only supported on enums"))
+        }
+        let generic = parser.eat_generic();
+        let tuple = parse_tuple(&mut parser) ?;
+        let where_clause = parser.eat_where_clause(Some(trait_name));
+        let fields = if let Some(tuple) = tuple {
+            tuple
+        }
+        else if let Some(fields) = parse_fields(&mut parser) ? {
+            fields
+        }
+        else {
+            return Err(parser.unexpected())
+        };
+        return Ok(Item {name, generic, where_clause, data: Data::Struct(fields)})
+    }
+    else if parser.eat_ident("enum") {
+        let name = parser.eat_any_ident().ok_or_else( || parser.unexpected()) ?;
+        let generic = parser.eat_generic();
+        let where_clause = parser.eat_where_clause(Some(trait_name));
+
+        let tagging = match (tag, content, untagged) {
+            (None, None, false) => Tagging::External,
+            (Some(tag), None, false) => Tagging::Internal(tag),
+            (Some(tag), Some(content), false) => Tagging::Adjacent(tag, content),
+            (None, None, true) => Tagging::Untagged,
+            (None, Some(_), false) => return Err(error("#[content] needs a #[tag] as well")),
+            _ => return Err(error("#[untagged] cannot be combined with #[tag] or #[content]"))
+        };
+
+        if !parser.open_brace() {
+            return Err(parser.unexpected())
+        }
+        let mut variants = Vec::new();
+        while !parser.eat_eot() {
+            let mut rename = None;
+            for attr in parser.eat_attributes() {
+                if attr.name == "rename" {
+                    rename = Some(attr_string(&attr) ?);
+                }
+            }
+            let name = parser.eat_any_ident().ok_or_else( || parser.unexpected()) ?;
+            let fields = if let Some(tuple) = parse_tuple(&mut parser) ? {
+                tuple
+            }
+            else if let Some(fields) = parse_fields(&mut parser) ? {
+                fields
+            }
+            else if parser.is_punct_alone(',') || parser.is_eot() {
+                Fields::Unit
+            }
+            else {
+                return Err(parser.unexpected())
+            };
+            if let (Tagging::Internal(_), Fields::Tuple(types)) = (&tagging, &fields) {
+                if types.len() != 1 {
+                    return Err(error(&format!("internally tagged variant {} can only hold a single struct", name)))
+                }
+            }
+            variants.push(Variant {name, rename, fields});
+            parser.eat_punct_alone(',');
+        }
+        return Ok(Item {name, generic, where_clause, data: Data::Enum(tagging, variants)})
+    }
+    Err(parser.unexpected())
+}
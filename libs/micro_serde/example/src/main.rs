@@ -14,7 +14,13 @@ struct MyStruct<T> where T: Clone {
     h: MyEnum<T>,
     i: MyEnum<T>,
     j: String,
-    k: [u32;2]
+    k: [u32;2],
+    #[rename("shape")]
+    l: Shape,
+    #[default]
+    m: u32,
+    #[skip]
+    n: Vec<u32>,
 } 
 
 #[derive(SerBin, DeBin, SerJson, DeJson, SerRon, DeRon, PartialEq)]
@@ -25,6 +31,14 @@ enum MyEnum<T> where T: Clone {
     Four {z: Option<u32>, w: T},
 }
 
+#[derive(SerBin, DeBin, SerJson, DeJson, SerRon, DeRon, PartialEq)]
+#[tag("kind")]
+enum Shape {
+    Circle {radius: f64},
+    #[rename("rect")]
+    Rectangle {width: f64, height: f64},
+}
+
 fn main() {
     //let a = MyStruct{step1:1,step2:None};
     //let x = MyStruct2(1,2);
@@ -39,7 +53,10 @@ fn main() {
         h: MyEnum::Four {z: None, w: 8},
         i: MyEnum::Four {z: Some(9), w: 8},
         j: "Hello".to_string(),
-        k: [10,11]
+        k: [10,11],
+        l: Shape::Rectangle {width: 12.0, height: 13.0},
+        m: 14,
+        n: Vec::new(),
     };
     let bin = x.serialize_bin();
     println!("Bin len: {}", bin.len());
@@ -7,4 +7,155 @@ mod serde_json;
 pub use crate::serde_json::*;
 
 mod serde_ron;
-pub use crate::serde_ron::*;
\ No newline at end of file
+pub use crate::serde_ron::*;
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(SerBin, DeBin, SerJson, DeJson, SerRon, DeRon, PartialEq, Debug)]
+    struct Inner {
+        x: u32,
+        y: Option<String>,
+    }
+
+    #[derive(SerBin, DeBin, SerJson, DeJson, SerRon, DeRon, PartialEq, Debug)]
+    struct Outer {
+        #[rename("identifier")]
+        id: u32,
+        #[default]
+        count: u32,
+        #[default(vec![1, 2])]
+        list: Vec<u32>,
+        #[skip]
+        cache: Vec<u32>,
+        #[flatten]
+        inner: Inner,
+    }
+
+    #[derive(SerBin, DeBin, SerJson, DeJson, SerRon, DeRon, PartialEq, Debug)]
+    #[tag("kind")]
+    enum Internal {
+        Empty,
+        #[rename("circle")]
+        Circle {radius: f64},
+        Wrapped(Inner),
+    }
+
+    #[derive(SerBin, DeBin, SerJson, DeJson, SerRon, DeRon, PartialEq, Debug)]
+    #[tag("t")]
+    #[content("c")]
+    enum Adjacent {
+        Empty,
+        Pair(u32, String),
+        Single(u32),
+        Named {a: u32},
+    }
+
+    #[derive(SerBin, DeBin, SerJson, DeJson, SerRon, DeRon, PartialEq, Debug)]
+    #[untagged]
+    enum Untagged {
+        Empty,
+        Number(u32),
+        Text(String),
+        Named {a: u32, b: u32},
+    }
+
+    fn outer() -> Outer {
+        Outer {
+            id: 1,
+            count: 2,
+            list: vec![3],
+            cache: vec![4],
+            inner: Inner {x: 5, y: Some("five".to_string())},
+        }
+    }
+
+    fn roundtrip<T>(value: T) where T: SerBin + DeBin + SerJson + DeJson + SerRon + DeRon + PartialEq + std::fmt::Debug {
+        assert_eq!(T::deserialize_bin(&value.serialize_bin()).unwrap(), value);
+        assert_eq!(T::deserialize_json(&value.serialize_json()).unwrap(), value);
+        assert_eq!(T::deserialize_ron(&value.serialize_ron()).unwrap(), value);
+    }
+
+    #[test]
+    fn field_attributes_json() {
+        let json = outer().serialize_json();
+        assert_eq!(json, r#"{"identifier":1,"count":2,"list":[3],"x":5,"y":"five"}"#);
+        let back = Outer::deserialize_json(&json).unwrap();
+        assert_eq!(back, Outer {cache: vec![], ..outer()});
+
+        let back = Outer::deserialize_json(r#"{"x":5,"identifier":1,"cache":[9]}"#).unwrap();
+        assert_eq!(back, Outer {id: 1, count: 0, list: vec![1, 2], cache: vec![], inner: Inner {x: 5, y: None}});
+
+        assert!(Outer::deserialize_json(r#"{"x":5}"#).is_err());
+        assert!(Inner::deserialize_json(r#"{"x":5,"z":1}"#).is_err());
+    }
+
+    #[test]
+    fn field_attributes_ron() {
+        let ron = outer().serialize_ron();
+        assert_eq!(ron, "(\n    identifier:1,\n    count:2,\n    list:[\n        3,\n    ],\n    x:5,\n    y:\"five\",\n)");
+        let back = Outer::deserialize_ron(&ron).unwrap();
+        assert_eq!(back, Outer {cache: vec![], ..outer()});
+
+        let back = Outer::deserialize_ron("(x:5, identifier:1, cache:[9])").unwrap();
+        assert_eq!(back, Outer {id: 1, count: 0, list: vec![1, 2], cache: vec![], inner: Inner {x: 5, y: None}});
+    }
+
+    #[test]
+    fn field_attributes_bin() {
+        let back = Outer::deserialize_bin(&outer().serialize_bin()).unwrap();
+        assert_eq!(back, Outer {cache: vec![], ..outer()});
+    }
+
+    #[test]
+    fn internally_tagged() {
+        assert_eq!(Internal::Empty.serialize_json(), r#"{"kind":"Empty"}"#);
+        assert_eq!(Internal::Circle {radius: 0.5}.serialize_json(), r#"{"kind":"circle","radius":0.5}"#);
+        assert_eq!(
+            Internal::Wrapped(Inner {x: 1, y: None}).serialize_json(),
+            r#"{"kind":"Wrapped","x":1}"#
+        );
+        assert_eq!(
+            Internal::deserialize_json(r#"{"radius":2.0,"kind":"circle"}"#).unwrap(),
+            Internal::Circle {radius: 2.0}
+        );
+        assert_eq!(
+            Internal::deserialize_ron("(x:3, kind:Wrapped)").unwrap(),
+            Internal::Wrapped(Inner {x: 3, y: None})
+        );
+        assert!(Internal::deserialize_json(r#"{"radius":2.0}"#).is_err());
+        assert!(Internal::deserialize_json(r#"{"kind":"Square"}"#).is_err());
+        roundtrip(Internal::Empty);
+        roundtrip(Internal::Circle {radius: 1.5});
+        roundtrip(Internal::Wrapped(Inner {x: 1, y: Some("y".to_string())}));
+    }
+
+    #[test]
+    fn adjacently_tagged() {
+        assert_eq!(Adjacent::Empty.serialize_json(), r#"{"t":"Empty"}"#);
+        assert_eq!(Adjacent::Pair(1, "a".to_string()).serialize_json(), r#"{"t":"Pair","c":[1,"a"]}"#);
+        assert_eq!(Adjacent::Single(2).serialize_json(), r#"{"t":"Single","c":2}"#);
+        assert_eq!(Adjacent::Named {a: 3}.serialize_json(), r#"{"t":"Named","c":{"a":3}}"#);
+        assert_eq!(Adjacent::deserialize_json(r#"{"c":4,"t":"Single"}"#).unwrap(), Adjacent::Single(4));
+        assert!(Adjacent::deserialize_json(r#"{"t":"Single"}"#).is_err());
+        roundtrip(Adjacent::Empty);
+        roundtrip(Adjacent::Pair(1, "a".to_string()));
+        roundtrip(Adjacent::Single(2));
+        roundtrip(Adjacent::Named {a: 3});
+    }
+
+    #[test]
+    fn untagged() {
+        assert_eq!(Untagged::Empty.serialize_json(), "null");
+        assert_eq!(Untagged::Number(1).serialize_json(), "1");
+        assert_eq!(Untagged::Named {a: 1, b: 2}.serialize_json(), r#"{"a":1,"b":2}"#);
+        assert_eq!(Untagged::deserialize_json(r#""hi""#).unwrap(), Untagged::Text("hi".to_string()));
+        assert_eq!(Untagged::deserialize_ron("(a:1, b:2)").unwrap(), Untagged::Named {a: 1, b: 2});
+        assert!(Untagged::deserialize_json("[]").is_err());
+        roundtrip(Untagged::Empty);
+        roundtrip(Untagged::Number(7));
+        roundtrip(Untagged::Text("text".to_string()));
+        roundtrip(Untagged::Named {a: 1, b: 2});
+    }
+}
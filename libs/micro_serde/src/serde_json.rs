@@ -40,6 +40,24 @@ impl SerJsonState {
         self.out.push('}');
     }
     
+    // separates a field from the previous one, if any
+    pub fn sep(&mut self) {
+        if !self.out.ends_with('{') {
+            self.out.push(',');
+        }
+    }
+    
+    // writes the fields of an object valued field into the object being written
+    pub fn flatten<T: SerJson + ?Sized>(&mut self, d: usize, value: &T) {
+        let mut inner = SerJsonState {out: String::new()};
+        value.ser_json(d, &mut inner);
+        if let Some(fields) = inner.out.strip_prefix('{').and_then( | v | v.strip_suffix('}')) {
+            if !fields.is_empty() {
+                self.sep();
+                self.out.push_str(fields);
+            }
+        }
+    }
 }
 
 pub trait SerJson {
@@ -70,7 +88,7 @@ pub trait DeJson: Sized {
     DeJsonErr>;
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 #[derive(Default)]
 pub enum DeJsonTok {
     Str,
@@ -92,7 +110,7 @@ pub enum DeJsonTok {
     Eof
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct DeJsonState {
     pub cur: char,
     pub tok: DeJsonTok,
@@ -100,7 +118,10 @@ pub struct DeJsonState {
     pub numbuf:String,
     pub identbuf:String,
     pub line: usize,
-    pub col: usize
+    pub col: usize,
+    // set when the next derived struct should ignore keys it doesn't know,
+    // used to parse #[flatten] fields and internally tagged enums
+    pub skip_unknown: bool
 }
 
 pub struct DeJsonErr{
@@ -219,6 +240,36 @@ impl DeJsonState {
         }
     }
     
+    pub fn null(&mut self, i: &mut Chars) -> Result<(), DeJsonErr> {
+        if self.tok == DeJsonTok::Null {
+            self.next_tok(i) ?;
+            return Ok(())
+        }
+        Err(self.err_token("null"))
+    }
+    
+    pub fn skip_value(&mut self, i: &mut Chars) -> Result<(), DeJsonErr> {
+        JsonValue::de_json(self, i) ?;
+        Ok(())
+    }
+    
+    // looks ahead in the object we are at for the string value of key tag
+    pub fn peek_tag(&self, i: &Chars, tag: &str) -> Result<String, DeJsonErr> {
+        let mut s = self.clone();
+        let mut i = i.clone();
+        s.curly_open(&mut i) ?;
+        while s.next_str().is_some() {
+            let is_tag = s.strbuf == tag;
+            s.next_colon(&mut i) ?;
+            if is_tag {
+                return s.as_string()
+            }
+            s.skip_value(&mut i) ?;
+            s.eat_comma_curly(&mut i) ?;
+        }
+        Err(self.err_nf(tag))
+    }
+    
     pub fn next_colon(&mut self, i: &mut Chars) -> Result<(), DeJsonErr> {
         self.next_tok(i) ?;
         self.colon(i) ?;
@@ -686,6 +737,32 @@ impl JsonValue{
         }
         panic!()
     }
+    // the non panicking versions, for json of which the shape isn't known up front
+    pub fn get(&self, key:&str)->Option<&JsonValue>{
+        if let JsonValue::Object(obj) = self{
+            return obj.get(key)
+        }
+        None
+    }
+    pub fn as_str(&self)->Option<&str>{
+        if let JsonValue::String(v) = self{
+            return Some(v)
+        }
+        None
+    }
+    pub fn as_u64(&self)->Option<u64>{
+        match self{
+            JsonValue::U64(v) => Some(*v),
+            JsonValue::I64(v) if *v >= 0 => Some(*v as u64),
+            _ => None
+        }
+    }
+    pub fn as_array(&self)->Option<&[JsonValue]>{
+        if let JsonValue::Array(v) = self{
+            return Some(v)
+        }
+        None
+    }
 }
 
 impl DeJson for JsonValue{
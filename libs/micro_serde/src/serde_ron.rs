@@ -32,6 +32,14 @@ impl SerRonState {
         self.out.push(')');
     }
     
+    // writes the fields of a struct valued field into the struct being written
+    pub fn flatten<T: SerRon + ?Sized>(&mut self, d: usize, value: &T) {
+        let mut inner = SerRonState {out: String::new()};
+        value.ser_ron(d, &mut inner);
+        if let Some(fields) = inner.out.strip_prefix("(\n").and_then( | v | v.strip_suffix(')')) {
+            self.out.push_str(fields.trim_end_matches(' '));
+        }
+    }
 }
 
 pub trait SerRon {
@@ -62,7 +70,7 @@ pub trait DeRon: Sized {
     DeRonErr>;
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 #[derive(Default)]
 pub enum DeRonTok {
     Ident,
@@ -87,7 +95,7 @@ pub enum DeRonTok {
 
 
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct DeRonState {
     pub cur: char,
     pub tok: DeRonTok,
@@ -95,7 +103,10 @@ pub struct DeRonState {
     pub numbuf: String,
     pub identbuf: String,
     pub line: usize,
-    pub col: usize
+    pub col: usize,
+    // set when the next derived struct should ignore fields it doesn't know,
+    // used to parse #[flatten] fields and internally tagged enums
+    pub skip_unknown: bool
 }
 
 pub struct DeRonErr {
@@ -131,6 +142,10 @@ impl DeRonState {
         DeRonErr {msg: format!("Unexpected key {}", name), line: self.line, col: self.col}
     }
     
+    pub fn err_msg(&self, msg: &str) -> DeRonErr {
+        DeRonErr {msg: msg.to_string(), line: self.line, col: self.col}
+    }
+    
     pub fn err_nf(&self, name: &str) -> DeRonErr {
         DeRonErr {msg: format!("Key not found {}", name), line: self.line, col: self.col}
     }
@@ -239,6 +254,56 @@ impl DeRonState {
         }
     }
     
+    // skips over a value of any shape, including nested structs and enums
+    pub fn skip_value(&mut self, i: &mut Chars) -> Result<(), DeRonErr> {
+        let mut depth = 0;
+        loop {
+            match self.tok {
+                DeRonTok::Ident => {
+                    self.next_tok(i) ?;
+                    if depth == 0 && self.tok != DeRonTok::ParenOpen {
+                        return Ok(())
+                    }
+                    continue;
+                }
+                DeRonTok::ParenOpen | DeRonTok::BlockOpen | DeRonTok::CurlyOpen => depth += 1,
+                DeRonTok::ParenClose | DeRonTok::BlockClose | DeRonTok::CurlyClose => {
+                    if depth == 0 {
+                        return Err(self.err_token("value"))
+                    }
+                    depth -= 1;
+                }
+                DeRonTok::Bof | DeRonTok::Eof => return Err(self.err_token("value")),
+                DeRonTok::Colon | DeRonTok::Comma if depth == 0 => return Err(self.err_token("value")),
+                _ => ()
+            }
+            self.next_tok(i) ?;
+            if depth == 0 {
+                return Ok(())
+            }
+        }
+    }
+    
+    // looks ahead in the struct we are at for the identifier value of field tag
+    pub fn peek_tag(&self, i: &Chars, tag: &str) -> Result<String, DeRonErr> {
+        let mut s = self.clone();
+        let mut i = i.clone();
+        s.paren_open(&mut i) ?;
+        while s.next_ident().is_some() {
+            let is_tag = s.identbuf == tag;
+            s.next_colon(&mut i) ?;
+            if is_tag {
+                if s.tok == DeRonTok::Ident {
+                    return Ok(s.identbuf)
+                }
+                return Err(s.err_token("Identifier"))
+            }
+            s.skip_value(&mut i) ?;
+            s.eat_comma_paren(&mut i) ?;
+        }
+        Err(self.err_nf(tag))
+    }
+    
     pub fn paren_open(&mut self, i: &mut Chars) -> Result<(), DeRonErr> {
         if self.tok == DeRonTok::ParenOpen {
             self.next_tok(i) ?;
@@ -0,0 +1,745 @@
+use std::fmt;
+
+// A small backtracking regex engine for searching single lines of code. It supports literals, `.`,
+// classes like `[a-z]` and `\d \w \s`, anchors `^ $ \b \B`, groups `(...)` and `(?:...)`,
+// alternation and the greedy and lazy quantifiers `* + ? {n,m}`.
+
+const MAX_STEPS: usize = 1_000_000;
+// The matcher recurses for every repetition of a group and every node that isn't a single char,
+// which takes up to 1.5 KB of stack a level in debug builds. The limit keeps that well within the
+// 2 MB stack of a spawned thread.
+const MAX_DEPTH: usize = 600;
+
+#[derive(Clone, Debug)]
+pub struct Regex {
+    node: Node,
+    capture_count: usize,
+    ignore_case: bool,
+    whole_word: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegexError {
+    pub message: String,
+    pub offset: usize,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.offset)
+    }
+}
+
+// Returned when a search gave up because the pattern backtracked or nested too much on the text,
+// with the matches found before that point.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegexAborted {
+    pub matches: Vec<RegexMatch>,
+}
+
+impl fmt::Display for RegexAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the pattern is too complex to search this text")
+    }
+}
+
+// A match in byte offsets, group 0 is the whole match.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegexMatch {
+    groups: Vec<Option<(usize, usize)>>,
+}
+
+impl RegexMatch {
+    pub fn start(&self) -> usize {
+        self.groups[0].unwrap().0
+    }
+
+    pub fn end(&self) -> usize {
+        self.groups[0].unwrap().1
+    }
+
+    pub fn group<'a>(&self, text: &'a str, index: usize) -> Option<&'a str> {
+        let (start, end) = (*self.groups.get(index)?)?;
+        Some(&text[start..end])
+    }
+
+    // expands `$0`-`$9` and `${n}` in a replacement to the matched groups, `$$` is a dollar sign
+    pub fn expand(&self, text: &str, replacement: &str) -> String {
+        let mut result = String::new();
+        let mut chars = replacement.chars().peekable();
+        while let Some(char) = chars.next() {
+            if char != '$' {
+                result.push(char);
+                continue;
+            }
+            match chars.peek().copied() {
+                Some('$') => {
+                    chars.next();
+                    result.push('$');
+                }
+                Some(digit) if digit.is_ascii_digit() => {
+                    chars.next();
+                    let index = digit as usize - '0' as usize;
+                    result.push_str(self.group(text, index).unwrap_or(""));
+                }
+                Some('{') => {
+                    let rest: String = chars.clone().skip(1).take_while(|c| *c != '}').collect();
+                    match rest.parse::<usize>() {
+                        Ok(index) if chars.clone().nth(1 + rest.len()) == Some('}') => {
+                            for _ in 0..rest.len() + 2 {
+                                chars.next();
+                            }
+                            result.push_str(self.group(text, index).unwrap_or(""));
+                        }
+                        _ => result.push('$'),
+                    }
+                }
+                _ => result.push('$'),
+            }
+        }
+        result
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Class),
+    LineStart,
+    LineEnd,
+    WordBoundary(bool),
+    Group {
+        alternatives: Vec<Vec<Node>>,
+        capture: Option<usize>,
+    },
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(self, char: char) -> bool {
+        match self {
+            Self::Range(start, end) => start <= char && char <= end,
+            Self::Digit(is) => char.is_ascii_digit() == is,
+            Self::Word(is) => is_word_char(char) == is,
+            Self::Space(is) => char.is_whitespace() == is,
+        }
+    }
+}
+
+impl Class {
+    fn matches(&self, char: char, ignore_case: bool) -> bool {
+        let matches = |char| self.items.iter().any(|item| item.matches(char));
+        let mut is_match = matches(char);
+        if !is_match && ignore_case {
+            is_match = char.to_lowercase().any(matches) || char.to_uppercase().any(matches);
+        }
+        is_match != self.negated
+    }
+}
+
+fn is_word_char(char: char) -> bool {
+    char.is_alphanumeric() || char == '_'
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            capture_count: 0,
+            depth: 0,
+        };
+        let alternatives = parser.parse_alternatives()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unmatched )"));
+        }
+        Ok(Self {
+            node: Node::Group {
+                alternatives,
+                capture: Some(0),
+            },
+            capture_count: parser.capture_count + 1,
+            ignore_case: false,
+            whole_word: false,
+        })
+    }
+
+    // a regex that matches the text as is
+    pub fn literal(text: &str) -> Self {
+        Self {
+            node: Node::Group {
+                alternatives: vec![text.chars().map(Node::Char).collect()],
+                capture: Some(0),
+            },
+            capture_count: 1,
+            ignore_case: false,
+            whole_word: false,
+        }
+    }
+
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    // only keeps matches that don't continue a word on either side
+    pub fn whole_word(mut self, whole_word: bool) -> Self {
+        self.whole_word = whole_word;
+        self
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self.find_all(text) {
+            Ok(matches) => !matches.is_empty(),
+            Err(aborted) => !aborted.matches.is_empty(),
+        }
+    }
+
+    // all non-overlapping, non-empty matches from left to right
+    pub fn find_all(&self, text: &str) -> Result<Vec<RegexMatch>, RegexAborted> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut offsets: Vec<usize> = text.char_indices().map(|(offset, _)| offset).collect();
+        offsets.push(text.len());
+        let mut matcher = Matcher {
+            text: &chars,
+            ignore_case: self.ignore_case,
+            captures: vec![None; self.capture_count],
+            steps: 0,
+            depth: 0,
+            aborted: false,
+        };
+        let mut matches = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            matcher.captures.iter_mut().for_each(|capture| *capture = None);
+            let whole_word = self.whole_word;
+            let mut end = None;
+            matcher.match_node(&self.node, start, &mut |m, pos| {
+                if pos == start || whole_word && !m.is_whole_word(start, pos) {
+                    return false;
+                }
+                end = Some(pos);
+                true
+            });
+            // a match found while giving up can be cut short
+            if matcher.aborted {
+                break;
+            }
+            match end {
+                Some(end) => {
+                    matches.push(RegexMatch {
+                        groups: matcher
+                            .captures
+                            .iter()
+                            .map(|capture| capture.map(|(start, end)| (offsets[start], offsets[end])))
+                            .collect(),
+                    });
+                    start = end;
+                }
+                None => start += 1,
+            }
+        }
+        if matcher.aborted {
+            return Err(RegexAborted { matches });
+        }
+        Ok(matches)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    capture_count: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> RegexError {
+        RegexError {
+            message: message.to_string(),
+            offset: self.pos,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, char: char) -> bool {
+        if self.peek() == Some(char) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_alternatives(&mut self) -> Result<Vec<Vec<Node>>, RegexError> {
+        let mut alternatives = vec![self.parse_sequence()?];
+        while self.eat('|') {
+            alternatives.push(self.parse_sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn parse_sequence(&mut self) -> Result<Vec<Node>, RegexError> {
+        let mut nodes = Vec::new();
+        while let Some(char) = self.peek() {
+            if char == '|' || char == ')' && self.depth > 0 {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifiers(atom)?);
+        }
+        Ok(nodes)
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, RegexError> {
+        let char = self.peek().unwrap();
+        self.pos += 1;
+        Ok(match char {
+            '(' => {
+                let capture = if self.eat('?') {
+                    if !self.eat(':') {
+                        return Err(self.error("unsupported group"));
+                    }
+                    None
+                } else {
+                    self.capture_count += 1;
+                    Some(self.capture_count)
+                };
+                self.depth += 1;
+                let alternatives = self.parse_alternatives()?;
+                self.depth -= 1;
+                if !self.eat(')') {
+                    return Err(self.error("unclosed ("));
+                }
+                Node::Group {
+                    alternatives,
+                    capture,
+                }
+            }
+            ')' => return Err(self.error("unmatched )")),
+            '*' | '+' | '?' => return Err(self.error("nothing to repeat")),
+            '[' => Node::Class(self.parse_class()?),
+            '.' => Node::Any,
+            '^' => Node::LineStart,
+            '$' => Node::LineEnd,
+            '\\' => match self.parse_escape()? {
+                Escape::Char(char) => Node::Char(char),
+                Escape::Class(item) => Node::Class(Class {
+                    items: vec![item],
+                    negated: false,
+                }),
+                Escape::WordBoundary(is) => Node::WordBoundary(is),
+            },
+            char => Node::Char(char),
+        })
+    }
+
+    fn parse_escape(&mut self) -> Result<Escape, RegexError> {
+        let Some(char) = self.peek() else {
+            return Err(self.error("trailing \\"));
+        };
+        self.pos += 1;
+        Ok(match char {
+            'd' => Escape::Class(ClassItem::Digit(true)),
+            'D' => Escape::Class(ClassItem::Digit(false)),
+            'w' => Escape::Class(ClassItem::Word(true)),
+            'W' => Escape::Class(ClassItem::Word(false)),
+            's' => Escape::Class(ClassItem::Space(true)),
+            'S' => Escape::Class(ClassItem::Space(false)),
+            'b' => Escape::WordBoundary(true),
+            'B' => Escape::WordBoundary(false),
+            't' => Escape::Char('\t'),
+            'n' => Escape::Char('\n'),
+            'r' => Escape::Char('\r'),
+            char if char.is_alphanumeric() => {
+                self.pos -= 1;
+                return Err(self.error("unsupported escape"));
+            }
+            char => Escape::Char(char),
+        })
+    }
+
+    fn parse_class(&mut self) -> Result<Class, RegexError> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let Some(char) = self.peek() else {
+                return Err(self.error("unclosed ["));
+            };
+            self.pos += 1;
+            if char == ']' && !first {
+                break;
+            }
+            first = false;
+            let start = if char == '\\' {
+                match self.parse_escape()? {
+                    Escape::Char(char) => char,
+                    Escape::Class(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                    Escape::WordBoundary(_) => {
+                        return Err(self.error("word boundary in class"));
+                    }
+                }
+            } else {
+                char
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']') {
+                self.pos += 1;
+                let mut end = self.peek().unwrap();
+                self.pos += 1;
+                if end == '\\' {
+                    match self.parse_escape()? {
+                        Escape::Char(char) => end = char,
+                        _ => return Err(self.error("invalid range")),
+                    }
+                }
+                if end < start {
+                    return Err(self.error("invalid range"));
+                }
+                items.push(ClassItem::Range(start, end));
+            } else {
+                items.push(ClassItem::Range(start, start));
+            }
+        }
+        Ok(Class { items, negated })
+    }
+
+    fn parse_quantifiers(&mut self, node: Node) -> Result<Node, RegexError> {
+        let mut node = node;
+        loop {
+            let (min, max) = match self.peek() {
+                Some('{') => match self.parse_counted() {
+                    Some(range) => range,
+                    // a brace that isn't a valid count is a literal
+                    None => return Ok(node),
+                },
+                Some(char @ ('*' | '+' | '?')) => {
+                    self.pos += 1;
+                    match char {
+                        '*' => (0, None),
+                        '+' => (1, None),
+                        _ => (0, Some(1)),
+                    }
+                }
+                _ => return Ok(node),
+            };
+            let greedy = !self.eat('?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    // parses `{n}`, `{n,}` or `{n,m}`, consuming it only when it is valid
+    fn parse_counted(&mut self) -> Option<(usize, Option<usize>)> {
+        let rest: String = self.chars[self.pos + 1..].iter().take_while(|c| **c != '}').collect();
+        if self.pos + 1 + rest.len() >= self.chars.len() {
+            return None;
+        }
+        let range = match rest.split_once(',') {
+            None => {
+                let n = rest.parse().ok()?;
+                (n, Some(n))
+            }
+            Some((min, "")) => (min.parse().ok()?, None),
+            Some((min, max)) => {
+                let (min, max) = (min.parse().ok()?, max.parse().ok()?);
+                if max < min {
+                    return None;
+                }
+                (min, Some(max))
+            }
+        };
+        self.pos += rest.len() + 2;
+        Some(range)
+    }
+}
+
+enum Escape {
+    Char(char),
+    Class(ClassItem),
+    WordBoundary(bool),
+}
+
+struct Matcher<'a> {
+    text: &'a [char],
+    ignore_case: bool,
+    captures: Vec<Option<(usize, usize)>>,
+    steps: usize,
+    depth: usize,
+    // set when a pattern backtracks or nests too much, so a bad regex can't hang or crash the editor
+    aborted: bool,
+}
+
+type Continuation<'k, 'a> = dyn FnMut(&mut Matcher<'a>, usize) -> bool + 'k;
+
+impl<'a> Matcher<'a> {
+    fn char_eq(&self, a: char, b: char) -> bool {
+        a == b || self.ignore_case && a.to_lowercase().eq(b.to_lowercase())
+    }
+
+    fn is_word_boundary(&self, pos: usize) -> bool {
+        let before = pos > 0 && is_word_char(self.text[pos - 1]);
+        let after = pos < self.text.len() && is_word_char(self.text[pos]);
+        before != after
+    }
+
+    fn is_whole_word(&self, start: usize, end: usize) -> bool {
+        let text = self.text;
+        let inside_word = |pos: usize| is_word_char(text[pos - 1]) && is_word_char(text[pos]);
+        !(start > 0 && inside_word(start) || end < text.len() && inside_word(end))
+    }
+
+    fn matches_char(&self, node: &Node, pos: usize) -> Option<bool> {
+        let char = self.text.get(pos).copied();
+        match node {
+            Node::Char(expected) => Some(char.is_some_and(|char| self.char_eq(char, *expected))),
+            Node::Any => Some(char.is_some()),
+            Node::Class(class) => {
+                Some(char.is_some_and(|char| class.matches(char, self.ignore_case)))
+            }
+            _ => None,
+        }
+    }
+
+    fn match_seq(&mut self, seq: &[Node], pos: usize, k: &mut Continuation<'_, 'a>) -> bool {
+        // runs of single char nodes, like a literal, are matched in a loop instead of recursing
+        let (mut seq, mut pos) = (seq, pos);
+        while let Some((node, rest)) = seq.split_first() {
+            match self.matches_char(node, pos) {
+                Some(true) => {
+                    self.steps += 1;
+                    seq = rest;
+                    pos += 1;
+                }
+                Some(false) => return false,
+                None => break,
+            }
+        }
+        match seq.split_first() {
+            None => k(self, pos),
+            Some((node, rest)) => self.match_node(node, pos, &mut |m, pos| m.match_seq(rest, pos, k)),
+        }
+    }
+
+    fn match_node(&mut self, node: &Node, pos: usize, k: &mut Continuation<'_, 'a>) -> bool {
+        self.steps += 1;
+        self.depth += 1;
+        if self.steps > MAX_STEPS || self.depth > MAX_DEPTH {
+            self.aborted = true;
+        }
+        let is_match = !self.aborted && self.match_node_unchecked(node, pos, k);
+        self.depth -= 1;
+        is_match
+    }
+
+    fn match_node_unchecked(&mut self, node: &Node, pos: usize, k: &mut Continuation<'_, 'a>) -> bool {
+        if let Some(is_match) = self.matches_char(node, pos) {
+            return is_match && k(self, pos + 1);
+        }
+        match node {
+            Node::LineStart => pos == 0 && k(self, pos),
+            Node::LineEnd => pos == self.text.len() && k(self, pos),
+            Node::WordBoundary(is) => self.is_word_boundary(pos) == *is && k(self, pos),
+            Node::Group {
+                alternatives,
+                capture,
+            } => {
+                for alternative in alternatives {
+                    let is_match = self.match_seq(alternative, pos, &mut |m, end| match capture {
+                        Some(index) => {
+                            let old = m.captures[*index];
+                            m.captures[*index] = Some((pos, end));
+                            if k(m, end) {
+                                return true;
+                            }
+                            m.captures[*index] = old;
+                            false
+                        }
+                        None => k(m, end),
+                    });
+                    if is_match {
+                        return true;
+                    }
+                }
+                false
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                if self.matches_char(node, pos).is_some() {
+                    self.match_char_repeat(node, *min, *max, *greedy, pos, k)
+                } else {
+                    self.match_repeat(node, *min, *max, *greedy, 0, pos, k)
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // repeats of a single char node are matched in a loop instead of recursing for every char
+    fn match_char_repeat(
+        &mut self,
+        node: &Node,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+        pos: usize,
+        k: &mut Continuation<'_, 'a>,
+    ) -> bool {
+        let mut count = 0;
+        while max.is_none_or(|max| count < max) && self.matches_char(node, pos + count) == Some(true)
+        {
+            count += 1;
+        }
+        if count < min {
+            return false;
+        }
+        let mut try_count = |m: &mut Self, count: usize| {
+            m.steps += 1;
+            !m.aborted && k(m, pos + count)
+        };
+        if greedy {
+            (min..=count).rev().any(|count| try_count(self, count))
+        } else {
+            (min..=count).any(|count| try_count(self, count))
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn match_repeat(
+        &mut self,
+        node: &Node,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+        count: usize,
+        pos: usize,
+        k: &mut Continuation<'_, 'a>,
+    ) -> bool {
+        let can_stop = count >= min;
+        let can_continue = max.is_none_or(|max| count < max);
+        if !greedy && can_stop && k(self, pos) {
+            return true;
+        }
+        if can_continue
+            && self.match_node(node, pos, &mut |m, next| {
+                // an empty repetition makes no progress, so stop repeating
+                if next == pos && can_stop {
+                    return false;
+                }
+                m.match_repeat(node, min, max, greedy, count + 1, next, k)
+            })
+        {
+            return true;
+        }
+        greedy && can_stop && k(self, pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Vec<String> {
+        Regex::new(pattern)
+            .unwrap()
+            .find_all(text)
+            .unwrap()
+            .iter()
+            .map(|m| text[m.start()..m.end()].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn finds_matches() {
+        assert_eq!(find("a+", "caaat a"), ["aaa", "a"]);
+        assert_eq!(find("[a-c]\\d{2,}", "a1 b22 c333"), ["b22", "c333"]);
+        assert_eq!(find("fn (\\w+)|let", "fn main() { let x }"), ["fn main", "let"]);
+        assert_eq!(find("<.*?>", "<a><b>"), ["<a>", "<b>"]);
+        assert_eq!(find("^\\s*//", "  // comment //"), ["  //"]);
+        assert_eq!(find("\\bis\\b", "this is"), ["is"]);
+        assert_eq!(find("x{", "x{y}"), ["x{"]);
+        assert!(Regex::new("(a").is_err());
+        assert!(Regex::new("a)").is_err());
+        assert!(Regex::new("*a").is_err());
+    }
+
+    #[test]
+    fn case_whole_word_and_expand() {
+        let regex = Regex::literal("Foo").ignore_case(true).whole_word(true);
+        let matches = regex.find_all("foo food FOO").unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[1].start(), 9);
+
+        let text = "let abc = 1;";
+        let m = &Regex::new("let (\\w+) = (\\d)").unwrap().find_all(text).unwrap()[0];
+        assert_eq!(m.expand(text, "$2 => ${1}$$"), "1 => abc$");
+    }
+
+    #[test]
+    fn aborts_instead_of_overflowing_the_stack() {
+        let text = "ab".repeat(50_000);
+        // run on a stack the size of a default spawned thread, whatever the test harness uses
+        let result = std::thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(move || Regex::new("(ab)+").unwrap().find_all(&text))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(result.is_err());
+
+        let text = format!("{} end", "word ".repeat(5_000));
+        let result = std::thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(move || Regex::new("(\\w+ )+end").unwrap().find_all(&text))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(result.is_err());
+
+        // the matches before the point it gave up are kept
+        let text = format!("x {}", "ab".repeat(50_000));
+        let aborted = Regex::new("x|(ab)+").unwrap().find_all(&text).unwrap_err();
+        assert_eq!(aborted.matches.len(), 1);
+        assert_eq!(aborted.matches[0].start(), 0);
+
+        // shorter repetitions and long literals are fine
+        assert_eq!(find("(ab)+", &"ab".repeat(300)).len(), 1);
+        let text = "a".repeat(100_000);
+        assert_eq!(Regex::literal(&text).find_all(&text).unwrap().len(), 1);
+        assert!(Regex::new("(a|b)*c").unwrap().find_all(&"ab".repeat(20)).is_ok());
+    }
+}
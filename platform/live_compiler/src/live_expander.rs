@@ -309,7 +309,7 @@ impl<'a> LiveExpander<'a> {
                                             break
                                         }
                                     }
-                                    index = out_doc.nodes.skip_node(index);
+                                    index = doc.nodes.skip_node(index);
                                 }
                                 if let Some(index) = found {
                                     let old_len = out_doc.nodes.len();
@@ -38,6 +38,9 @@ use {
         window::CxWindowPool,
         draw_list::CxDrawListPool,
         web_socket::WebSocket,
+        thread::SignalToUI,
+        cx_async::CxAsyncState,
+        file_dialogs::FileDialogEvent,
         pass::CxPassPool,
         texture::{CxTexturePool,TextureFormat,Texture},
         geometry::{
@@ -111,9 +114,10 @@ pub struct Cx {
     
     pub debug: Debug,
 
-    #[allow(dead_code)]
     pub(crate) executor: Option<Executor>,
     pub(crate) spawner: Spawner,
+    pub(crate) async_state: Rc<RefCell<CxAsyncState>>,
+    pub(crate) file_dialog_events: Vec<FileDialogEvent>,
     
     pub(crate) studio_web_socket: Option<WebSocket>,
     
@@ -208,7 +212,8 @@ impl Cx {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
         });
         
-        let (executor, spawner) = executor::new_executor_and_spawner();
+        // tasks woken from other threads need the event loop to come around and run them
+        let (executor, spawner) = executor::new_executor_and_spawner_with_wake_hook(Some(SignalToUI::set_ui_signal));
         let (send, recv) = std::sync::mpsc::channel();
         Self {
             null_texture,
@@ -269,6 +274,8 @@ impl Cx {
 
             executor: Some(executor),
             spawner,
+            async_state: Default::default(),
+            file_dialog_events: Default::default(),
 
             self_ref: None,
             performance_stats: Default::default(),
@@ -82,10 +82,10 @@ pub enum CxOsOp {
     CleanupVideoPlaybackResources(LiveId),
     UpdateVideoSurfaceTexture(LiveId),
     
-    SaveFileDialog(FileDialog),
-    SelectFileDialog(FileDialog),
-    SaveFolderDialog(FileDialog),
-    SelectFolderDialog(FileDialog),    
+    SaveFileDialog(LiveId, FileDialog),
+    SelectFileDialog(LiveId, FileDialog),
+    SaveFolderDialog(LiveId, FileDialog),
+    SelectFolderDialog(LiveId, FileDialog),    
 }
 
 impl Cx {
@@ -407,6 +407,10 @@ impl Cx {
     pub fn has_key_focus(&self, focus_area: Area) -> bool {
         self.keyboard.has_key_focus(focus_area)
     }
+    
+    pub fn key_focus(&self) -> Area {
+        self.keyboard.key_focus()
+    }
 
     pub fn new_next_frame(&mut self) -> NextFrame {
         let res = NextFrame(self.next_frame_id);
@@ -527,22 +531,45 @@ impl Cx {
         println!("Num textures: {}", self.textures.0.pool.len());
     }
 
-    pub fn open_system_savefile_dialog(&mut self) {
-        self.platform_ops.push(CxOsOp::SaveFileDialog(FileDialog::new()));
+    /// The result arrives as an `Event::FileDialog` carrying `dialog_id`
+    pub fn open_save_file_dialog(&mut self, dialog_id: LiveId, dialog: FileDialog) {
+        self.platform_ops.push(CxOsOp::SaveFileDialog(dialog_id, dialog));
+    }
+
+    pub fn open_select_file_dialog(&mut self, dialog_id: LiveId, dialog: FileDialog) {
+        self.platform_ops.push(CxOsOp::SelectFileDialog(dialog_id, dialog));
     }
 
-    pub fn open_system_openfile_dialog(&mut self) {
-        self.platform_ops.push(CxOsOp::SelectFileDialog(FileDialog::new()));
+    pub fn open_save_folder_dialog(&mut self, dialog_id: LiveId, dialog: FileDialog) {
+        self.platform_ops.push(CxOsOp::SaveFolderDialog(dialog_id, dialog));
     }
 
-    pub fn open_system_savefolder_dialog(&mut self) {
-        self.platform_ops.push(CxOsOp::SaveFolderDialog(FileDialog::new()));
+    pub fn open_select_folder_dialog(&mut self, dialog_id: LiveId, dialog: FileDialog) {
+        self.platform_ops.push(CxOsOp::SelectFolderDialog(dialog_id, dialog));
+    }
+
+    pub fn open_system_savefile_dialog(&mut self) -> LiveId {
+        let dialog_id = LiveId::unique();
+        self.open_save_file_dialog(dialog_id, FileDialog::new());
+        dialog_id
+    }
 
+    pub fn open_system_openfile_dialog(&mut self) -> LiveId {
+        let dialog_id = LiveId::unique();
+        self.open_select_file_dialog(dialog_id, FileDialog::new());
+        dialog_id
     }
 
-    pub fn open_system_openfolder_dialog(&mut self) {
-        self.platform_ops.push(CxOsOp::SelectFolderDialog(FileDialog::new()));
+    pub fn open_system_savefolder_dialog(&mut self) -> LiveId {
+        let dialog_id = LiveId::unique();
+        self.open_save_folder_dialog(dialog_id, FileDialog::new());
+        dialog_id
+    }
 
+    pub fn open_system_openfolder_dialog(&mut self) -> LiveId {
+        let dialog_id = LiveId::unique();
+        self.open_select_folder_dialog(dialog_id, FileDialog::new());
+        dialog_id
     }
 }
 
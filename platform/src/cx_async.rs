@@ -0,0 +1,217 @@
+use {
+    std::{
+        cell::RefCell,
+        collections::HashMap,
+        future::Future,
+        rc::Rc,
+    },
+    crate::{
+        makepad_futures::channel::oneshot,
+        makepad_live_id::LiveId,
+        cx::Cx,
+        cx_api::CxOsOp,
+        action::{Action, ActionTrait},
+        file_dialogs::{FileDialog, FileDialogResult},
+        event::{
+            Event,
+            HttpRequest,
+            HttpResponse,
+            NetworkResponse,
+        },
+    }
+};
+
+// Futures spawned on cx.spawner() can't borrow the Cx, so they queue their work on an AsyncCx.
+// The queue is applied to the Cx right after the executor ran, and the events answering
+// that work resolve the matching futures before the app sees them.
+
+enum AsyncOp {
+    HttpRequest(HttpRequest, oneshot::Sender<Result<HttpResponse, String >>),
+    Timeout(f64, oneshot::Sender<()>),
+    FileDialog(fn(LiveId, FileDialog) -> CxOsOp, FileDialog, oneshot::Sender<FileDialogResult>),
+    Action(Action),
+}
+
+#[derive(Default)]
+pub (crate) struct CxAsyncState {
+    ops: Vec<AsyncOp>,
+    http_requests: HashMap<LiveId, oneshot::Sender<Result<HttpResponse, String >> >,
+    timers: HashMap<u64, oneshot::Sender<()>>,
+    file_dialogs: HashMap<LiveId, oneshot::Sender<FileDialogResult >>,
+}
+
+/// A handle to the `Cx` that can be moved into futures spawned on `cx.spawner()`.
+///
+/// ```ignore
+/// let acx = cx.async_cx();
+/// cx.spawner().spawn(async move {
+///     let response = acx.http_request(request).await;
+///     acx.timeout(1.0).await;
+///     acx.action(MyAction::Loaded(response.ok()));
+/// }).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct AsyncCx {
+    state: Rc<RefCell<CxAsyncState >>,
+}
+
+impl AsyncCx {
+    fn push_op(&self, op: AsyncOp) {
+        self.state.borrow_mut().ops.push(op);
+    }
+
+    /// Sends the request and resolves with its response, or the error message if it failed.
+    pub fn http_request(&self, request: HttpRequest) -> impl Future<Output = Result<HttpResponse, String >> {
+        let (sender, receiver) = oneshot::channel();
+        self.push_op(AsyncOp::HttpRequest(request, sender));
+        async move {
+            receiver.await.unwrap_or_else( | _ | Err("http request was dropped".to_string()))
+        }
+    }
+
+    /// Resolves after `interval` seconds.
+    pub fn timeout(&self, interval: f64) -> impl Future<Output = ()> {
+        let (sender, receiver) = oneshot::channel();
+        self.push_op(AsyncOp::Timeout(interval, sender));
+        async move {
+            let _ = receiver.await;
+        }
+    }
+
+    pub fn save_file_dialog(&self, dialog: FileDialog) -> impl Future<Output = FileDialogResult> {
+        self.file_dialog(CxOsOp::SaveFileDialog, dialog)
+    }
+
+    pub fn select_file_dialog(&self, dialog: FileDialog) -> impl Future<Output = FileDialogResult> {
+        self.file_dialog(CxOsOp::SelectFileDialog, dialog)
+    }
+
+    pub fn save_folder_dialog(&self, dialog: FileDialog) -> impl Future<Output = FileDialogResult> {
+        self.file_dialog(CxOsOp::SaveFolderDialog, dialog)
+    }
+
+    pub fn select_folder_dialog(&self, dialog: FileDialog) -> impl Future<Output = FileDialogResult> {
+        self.file_dialog(CxOsOp::SelectFolderDialog, dialog)
+    }
+
+    fn file_dialog(&self, op: fn(LiveId, FileDialog) -> CxOsOp, dialog: FileDialog) -> impl Future<Output = FileDialogResult> {
+        let (sender, receiver) = oneshot::channel();
+        self.push_op(AsyncOp::FileDialog(op, dialog, sender));
+        async move {
+            receiver.await.unwrap_or(FileDialogResult::Cancelled)
+        }
+    }
+
+    /// Queues an action, it is delivered to the app's event handler once the task yields.
+    pub fn action(&self, action: impl ActionTrait) {
+        self.push_op(AsyncOp::Action(Box::new(action)));
+    }
+}
+
+impl Cx {
+    pub fn async_cx(&self) -> AsyncCx {
+        AsyncCx {state: self.async_state.clone()}
+    }
+
+    // resolves the futures waiting on this event, the event is still passed on to the app
+    pub (crate) fn resolve_async_event(&mut self, event: &Event) {
+        let mut state = self.async_state.borrow_mut();
+        match event {
+            Event::NetworkResponses(responses) => for item in responses {
+                let result = match &item.response {
+                    NetworkResponse::HttpResponse(response) => Ok(response.clone()),
+                    NetworkResponse::HttpRequestError(err) => Err(err.clone()),
+                    NetworkResponse::HttpProgress {..} => continue
+                };
+                if let Some(sender) = state.http_requests.remove(&item.request_id) {
+                    let _ = sender.send(result);
+                }
+            }
+            Event::Timer(te) => if let Some(sender) = state.timers.remove(&te.timer_id) {
+                let _ = sender.send(());
+            }
+            Event::FileDialog(fe) => if let Some(sender) = state.file_dialogs.remove(&fe.dialog_id) {
+                let _ = sender.send(fe.result.clone());
+            }
+            _ => ()
+        }
+    }
+
+    // polls all woken tasks, then turns the work they queued into platform ops
+    pub (crate) fn run_async_tasks(&mut self) {
+        // nested event handling (ie from handle_actions) leaves the executor to the outer call
+        let executor = if let Some(executor) = self.executor.take() {executor} else {return};
+        executor.run_until_stalled();
+        self.executor = Some(executor);
+
+        let ops = std::mem::take(&mut self.async_state.borrow_mut().ops);
+        if ops.is_empty() {
+            return
+        }
+        for op in ops {
+            match op {
+                AsyncOp::HttpRequest(request, sender) => {
+                    let request_id = LiveId::unique();
+                    self.async_state.borrow_mut().http_requests.insert(request_id, sender);
+                    self.http_request(request_id, request);
+                }
+                AsyncOp::Timeout(interval, sender) => {
+                    let timer = self.start_timeout(interval);
+                    self.async_state.borrow_mut().timers.insert(timer.0, sender);
+                }
+                AsyncOp::FileDialog(op, dialog, sender) => {
+                    let dialog_id = LiveId::unique();
+                    self.async_state.borrow_mut().file_dialogs.insert(dialog_id, sender);
+                    self.platform_ops.push(op(dialog_id, dialog));
+                }
+                AsyncOp::Action(action) => self.new_actions.push(action),
+            }
+        }
+        self.handle_actions();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::event::TimerEvent,
+        std::cell::Cell,
+    };
+
+    #[derive(Debug)]
+    struct Loaded(u32);
+
+    #[test]
+    fn resolves_timeouts_and_delivers_actions() {
+        let loaded = Rc::new(Cell::new(None));
+        let mut cx = Cx::new(Box::new({
+            let loaded = loaded.clone();
+            move | _cx, event | if let Event::Actions(actions) = event {
+                for action in actions {
+                    if let Some(Loaded(value)) = action.as_ref().downcast_ref::<Loaded>() {
+                        loaded.set(Some(*value));
+                    }
+                }
+            }
+        }));
+        let acx = cx.async_cx();
+        cx.spawner().spawn(async move {
+            acx.timeout(0.5).await;
+            acx.action(Loaded(7));
+        }).unwrap();
+
+        cx.run_async_tasks();
+        let timer_id = match cx.platform_ops.last() {
+            Some(CxOsOp::StartTimer {timer_id, interval, repeats: false}) if *interval == 0.5 => *timer_id,
+            _ => panic!("the timeout did not start a timer")
+        };
+        assert_eq!(loaded.get(), None);
+
+        let event = Event::Timer(TimerEvent {time: None, timer_id});
+        cx.resolve_async_event(&event);
+        cx.run_async_tasks();
+        assert_eq!(loaded.get(), Some(7));
+        assert!(cx.async_state.borrow().timers.is_empty());
+    }
+}
@@ -23,6 +23,7 @@ use {
         midi::MidiPortsEvent,
         video::VideoInputsEvent,
         draw_list::DrawListId,
+        file_dialogs::{FileDialogEvent, FileDialogFallbackEvent},
     },
 };
 
@@ -154,6 +155,7 @@ pub enum Event {
     KeyDown(KeyEvent),
     KeyUp(KeyEvent),
     TextInput(TextInputEvent),
+    TextComposition(TextCompositionEvent),
     TextCopy(TextClipboardEvent),
     TextCut(TextClipboardEvent),
 
@@ -175,6 +177,10 @@ pub enum Event {
     TextureHandleReady(TextureHandleReadyEvent),
 
     BackPressed,
+    /// The result of a file dialog opened with one of the `Cx` file dialog functions.
+    FileDialog(FileDialogEvent),
+    /// No native file dialog is available, an in-app dialog should be shown instead.
+    FileDialogFallback(FileDialogFallbackEvent),
     #[cfg(target_arch = "wasm32")]
     ToWasmMsg(ToWasmMsgEvent),
 }
@@ -246,9 +252,12 @@ impl Event{
             48=>"MouseLeave",
             49=>"Actions",
             50=>"BackPressed",
+            51=>"TextComposition",
+            52=>"FileDialog",
+            53=>"FileDialogFallback",
 
             #[cfg(target_arch = "wasm32")]
-            51=>"ToWasmMsg",
+            54=>"ToWasmMsg",
             _=>panic!()
         }
     }
@@ -315,9 +324,12 @@ impl Event{
             Self::MouseLeave(_)=>48,
             Self::Actions(_)=>49,
             Self::BackPressed=>50,
+            Self::TextComposition(_)=>51,
+            Self::FileDialog(_)=>52,
+            Self::FileDialogFallback(_)=>53,
 
             #[cfg(target_arch = "wasm32")]
-            Self::ToWasmMsg(_)=>51,
+            Self::ToWasmMsg(_)=>54,
         }
     }
 }
@@ -330,6 +342,7 @@ pub enum Hit{
     KeyUp(KeyEvent),
     Trigger(TriggerHitEvent),
     TextInput(TextInputEvent),
+    TextComposition(TextCompositionEvent),
     TextCopy(TextClipboardEvent),
     TextCut(TextClipboardEvent),
 
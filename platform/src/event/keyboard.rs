@@ -47,6 +47,10 @@ impl CxKeyboard {
     pub fn has_key_focus(&self, focus_area: Area) -> bool {
         self.key_focus == focus_area
     }
+    
+    pub fn key_focus(&self) -> Area {
+        self.key_focus
+    }
 
     pub fn set_text_ime_dismissed(&mut self) {
         self.text_ime_dismissed = true;
@@ -120,6 +124,14 @@ pub struct TextInputEvent {
     pub was_paste: bool
 }
 
+/// The in-progress (preedit) text of an input method composition. `cursor` is a char offset
+/// into `text`. An empty `text` ends the composition, the committed text arrives as a `TextInputEvent`.
+#[derive(Clone, Debug, Default, SerBin, DeBin, SerJson, DeJson, PartialEq)]
+pub struct TextCompositionEvent {
+    pub text: String,
+    pub cursor: usize,
+}
+
 #[derive(Clone, Debug)]
 pub struct TextClipboardEvent {
     pub response: Rc<RefCell<Option<String>>>
@@ -1,9 +1,13 @@
 // mildly stripped down version of native_dialog_rs dialog interface.
 use std::path::{PathBuf};
+use crate::{
+    makepad_live_id::LiveId,
+    cx::Cx,
+};
 
 
 /// Represents a set of file extensions and their description.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Filter {
     pub description: String,
     pub extensions: Vec<String>,
@@ -11,7 +15,7 @@ pub struct Filter {
 
 /// Builds and shows file dialogs.
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct FileDialog {
     pub filename: Option<String>,
     pub location: Option<PathBuf>,
@@ -93,3 +97,55 @@ impl Default for FileDialog {
     }
 }
 
+/// Sent as `Event::FileDialog` once the dialog opened under `dialog_id` is closed.
+#[derive(Clone, Debug)]
+pub struct FileDialogEvent {
+    pub dialog_id: LiveId,
+    pub result: FileDialogResult,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileDialogResult {
+    /// The chosen paths. Save dialogs and folder dialogs return exactly one.
+    Selected(Vec<PathBuf>),
+    Cancelled,
+    Error(String),
+}
+
+impl Filter {
+    /// Whether a file name ends in one of the filter's extensions
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.extensions.iter().any( | ext | name.ends_with(&format!(".{}", ext.trim_start_matches('.').to_lowercase())))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileDialogKind {
+    SaveFile,
+    SelectFile,
+    SaveFolder,
+    SelectFolder,
+}
+
+impl FileDialogKind {
+    pub fn is_folder(&self) -> bool {
+        matches!(self, Self::SaveFolder | Self::SelectFolder)
+    }
+}
+
+/// Sent as `Event::FileDialogFallback` when the platform has no native dialog to show.
+/// An in-app dialog should present `dialog` and answer with `Cx::complete_file_dialog`.
+#[derive(Clone, Debug)]
+pub struct FileDialogFallbackEvent {
+    pub dialog_id: LiveId,
+    pub kind: FileDialogKind,
+    pub dialog: FileDialog,
+}
+
+impl Cx {
+    /// Finishes the dialog `dialog_id`, the result is sent as `Event::FileDialog` after the current event.
+    pub fn complete_file_dialog(&mut self, dialog_id: LiveId, result: FileDialogResult) {
+        self.file_dialog_events.push(FileDialogEvent {dialog_id, result});
+    }
+}
@@ -8,6 +8,7 @@ mod live_prims;
 #[macro_use]
 mod cx;
 mod cx_api;
+mod cx_async;
 
 #[macro_use]
 pub mod log;
@@ -50,6 +51,9 @@ pub mod audio_stream;
 
 pub mod file_dialogs;
 
+#[cfg(all(target_os = "linux", not(linux_direct)))]
+pub mod visual_test;
+
 mod media_api;
 
 #[macro_use]
@@ -74,7 +78,7 @@ pub use {
     makepad_shader_compiler::makepad_micro_serde,
     makepad_shader_compiler::makepad_live_compiler,
     makepad_shader_compiler::makepad_live_id,
-    //makepad_image_formats::image,
+    makepad_image_formats,
     makepad_derive_live::*,
     log::*,
     makepad_math::*,
@@ -125,6 +129,8 @@ pub use {
     crate::{
         os::*,
         cx_api::CxOsApi,
+        cx_async::AsyncCx,
+        file_dialogs::{FileDialog, FileDialogEvent, FileDialogResult, FileDialogKind, FileDialogFallbackEvent},
         media_api::CxMediaApi,
         scope::*,
         draw_list::{
@@ -185,6 +191,7 @@ pub use {
             KeyEvent,
             KeyFocusEvent,
             TextInputEvent,
+            TextCompositionEvent,
             TextClipboardEvent,
             WindowCloseRequestedEvent,
             WindowClosedEvent,
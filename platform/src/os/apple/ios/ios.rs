@@ -66,11 +66,6 @@ impl Cx {
                     let mut cx_ref = cx.borrow_mut();
                     let mut metal_cx = metal_cx.borrow_mut();
                     let event_flow = cx_ref.ios_event_callback(event, &mut metal_cx);
-                    let executor = cx_ref.executor.take().unwrap();
-                    drop(cx_ref);
-                    executor.run_until_stalled();
-                    let mut cx_ref = cx.borrow_mut();
-                    cx_ref.executor = Some(executor);
                     event_flow
                 }
             }),
@@ -146,11 +141,6 @@ impl Cx {
                 let mut cx_ref = cx.borrow_mut();
                 let mut metal_cx = metal_cx.borrow_mut();
                 let event_flow = cx_ref.ios_event_callback(event, &mut metal_cx);
-                let executor = cx_ref.executor.take().unwrap();
-                drop(cx_ref);
-                executor.run_until_stalled();
-                let mut cx_ref = cx.borrow_mut();
-                cx_ref.executor = Some(executor);
                 event_flow
             }
         }));
@@ -406,10 +396,10 @@ impl Cx {
                 CxOsOp::CleanupVideoPlaybackResources(_) => todo!(),
                 CxOsOp::UpdateVideoSurfaceTexture(_) => todo!(),
 
-                CxOsOp::SaveFileDialog(_) => todo!(),
-                CxOsOp::SelectFileDialog(_) => todo!(),
-                CxOsOp::SaveFolderDialog(_) => todo!(),
-                CxOsOp::SelectFolderDialog(_) => todo!(),
+                CxOsOp::SaveFileDialog(_, _) => todo!(),
+                CxOsOp::SelectFileDialog(_, _) => todo!(),
+                CxOsOp::SaveFolderDialog(_, _) => todo!(),
+                CxOsOp::SelectFolderDialog(_, _) => todo!(),
                 
             }
         }
@@ -161,11 +161,6 @@ impl Cx {
                 let mut metal_cx = metal_cx.borrow_mut();
                 let mut metal_windows = metal_windows.borrow_mut();
                 let event_flow = cx_ref.cocoa_event_callback(event, &mut metal_cx, &mut metal_windows);
-                let executor = cx_ref.executor.take().unwrap();
-                drop(cx_ref);
-                executor.run_until_stalled();
-                let mut cx_ref = cx.borrow_mut();
-                cx_ref.executor = Some(executor);
                 event_flow
             }
         }));
@@ -535,22 +530,22 @@ impl Cx {
                 CxOsOp::CleanupVideoPlaybackResources(_) => todo!(),
                 CxOsOp::UpdateVideoSurfaceTexture(_) => todo!(),
 
-                CxOsOp::SaveFileDialog(settings) => 
+                CxOsOp::SaveFileDialog(_, settings) => 
                 {
                     get_macos_app_global().open_save_file_dialog(settings);
                 }
                 
-                CxOsOp::SelectFileDialog(settings) => 
+                CxOsOp::SelectFileDialog(_, settings) => 
                 {
                     get_macos_app_global().open_select_file_dialog(settings);                   
                 }
                 
-                CxOsOp::SaveFolderDialog(settings) => 
+                CxOsOp::SaveFolderDialog(_, settings) => 
                 {
                     get_macos_app_global().open_save_folder_dialog(settings);
                 }
                 
-                CxOsOp::SelectFolderDialog(settings) => 
+                CxOsOp::SelectFolderDialog(_, settings) => 
                 {
                     get_macos_app_global().open_select_folder_dialog(settings);
                 }
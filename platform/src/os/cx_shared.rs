@@ -174,10 +174,16 @@ impl Cx {
     }
     
     pub (crate) fn call_event_handler(&mut self, event: &Event) {
+        self.resolve_async_event(event);
         self.inner_call_event_handler(event);
         self.inner_key_focus_change();
         self.handle_triggers();
         self.handle_actions();
+        // results of in-app file dialogs are sent as their own events
+        for event in std::mem::take(&mut self.file_dialog_events) {
+            self.call_event_handler(&Event::FileDialog(event));
+        }
+        self.run_async_tasks();
     }
 
     // helpers
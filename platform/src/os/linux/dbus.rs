@@ -0,0 +1,659 @@
+// A minimal D-Bus client, just enough of the wire protocol to make method calls
+// and wait for signals on the session bus without linking libdbus.
+
+use {
+    std::{
+        io::{Read, Write},
+        os::unix::net::UnixStream,
+    },
+    self::super::libc_sys,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DbusValue {
+    Byte(u8),
+    Bool(bool),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+    // the element signature is kept so empty arrays can be written
+    Array(String, Vec<DbusValue>),
+    Struct(Vec<DbusValue>),
+    DictEntry(Box<DbusValue>, Box<DbusValue>),
+    Variant(Box<DbusValue>),
+}
+
+impl DbusValue {
+    pub fn signature(&self) -> String {
+        match self {
+            Self::Byte(_) => "y".into(),
+            Self::Bool(_) => "b".into(),
+            Self::I16(_) => "n".into(),
+            Self::U16(_) => "q".into(),
+            Self::I32(_) => "i".into(),
+            Self::U32(_) => "u".into(),
+            Self::I64(_) => "x".into(),
+            Self::U64(_) => "t".into(),
+            Self::F64(_) => "d".into(),
+            Self::Str(_) => "s".into(),
+            Self::ObjectPath(_) => "o".into(),
+            Self::Signature(_) => "g".into(),
+            Self::Array(elem, _) => format!("a{}", elem),
+            Self::Struct(fields) => format!("({})", fields.iter().map( | f | f.signature()).collect::<String>()),
+            Self::DictEntry(k, v) => format!("{{{}{}}}", k.signature(), v.signature()),
+            Self::Variant(_) => "v".into(),
+        }
+    }
+
+    /// An `a{sv}` dictionary, the options argument of most portal calls
+    pub fn dict(entries: Vec<(&str, DbusValue)>) -> Self {
+        Self::Array("{sv}".into(), entries.into_iter().map( | (k, v) | {
+            Self::DictEntry(Box::new(Self::Str(k.into())), Box::new(Self::Variant(Box::new(v))))
+        }).collect())
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(s) | Self::ObjectPath(s) | Self::Signature(s) => Some(s),
+            Self::Variant(v) => v.as_str(),
+            _ => None
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            Self::U32(v) => Some(*v),
+            Self::Variant(v) => v.as_u32(),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[DbusValue]> {
+        match self {
+            Self::Array(_, items) => Some(items),
+            Self::Variant(v) => v.as_array(),
+            _ => None
+        }
+    }
+
+    /// Looks up a key in an `a{sv}` or `a{sy..}` dictionary
+    pub fn dict_get(&self, key: &str) -> Option<&DbusValue> {
+        self.as_array() ?.iter().find_map( | entry | match entry {
+            Self::DictEntry(k, v) if k.as_str() == Some(key) => Some(&**v),
+            _ => None
+        })
+    }
+}
+
+fn type_alignment(sig: u8) -> usize {
+    match sig {
+        b'y' | b'g' | b'v' => 1,
+        b'n' | b'q' => 2,
+        b'x' | b't' | b'd' | b'(' | b'{' => 8,
+        _ => 4
+    }
+}
+
+// splits the first complete type off a signature
+fn split_type(sig: &str) -> Result<(&str, &str), String> {
+    let bytes = sig.as_bytes();
+    let end = match bytes.first() {
+        None => return Err("empty signature".into()),
+        Some(b'a') => 1 + split_type(&sig[1..]) ?.0.len(),
+        Some(open @ (b'(' | b'{')) => {
+            let close = if *open == b'(' {b')'} else {b'}'};
+            let mut depth = 0;
+            let mut end = None;
+            for (i, c) in bytes.iter().enumerate() {
+                if *c == b'(' || *c == b'{' {
+                    depth += 1;
+                }
+                else if *c == b')' || *c == b'}' {
+                    depth -= 1;
+                    if depth == 0 {
+                        if *c != close {
+                            return Err(format!("unbalanced signature {}", sig))
+                        }
+                        end = Some(i + 1);
+                        break;
+                    }
+                }
+            }
+            end.ok_or_else( || format!("unbalanced signature {}", sig)) ?
+        }
+        Some(_) => 1
+    };
+    Ok((&sig[..end], &sig[end..]))
+}
+
+#[derive(Default)]
+struct DbusWriter {
+    buf: Vec<u8>,
+}
+
+impl DbusWriter {
+    fn pad(&mut self, align: usize) {
+        while self.buf.len() % align != 0 {
+            self.buf.push(0);
+        }
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.pad(4);
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.buf.extend_from_slice(s.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn sig(&mut self, s: &str) {
+        self.buf.push(s.len() as u8);
+        self.buf.extend_from_slice(s.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn value(&mut self, v: &DbusValue) {
+        match v {
+            DbusValue::Byte(v) => self.buf.push(*v),
+            DbusValue::Bool(v) => self.u32(*v as u32),
+            DbusValue::I16(v) => {self.pad(2); self.buf.extend_from_slice(&v.to_le_bytes())}
+            DbusValue::U16(v) => {self.pad(2); self.buf.extend_from_slice(&v.to_le_bytes())}
+            DbusValue::I32(v) => self.u32(*v as u32),
+            DbusValue::U32(v) => self.u32(*v),
+            DbusValue::I64(v) => {self.pad(8); self.buf.extend_from_slice(&v.to_le_bytes())}
+            DbusValue::U64(v) => {self.pad(8); self.buf.extend_from_slice(&v.to_le_bytes())}
+            DbusValue::F64(v) => {self.pad(8); self.buf.extend_from_slice(&v.to_le_bytes())}
+            DbusValue::Str(s) | DbusValue::ObjectPath(s) => self.str(s),
+            DbusValue::Signature(s) => self.sig(s),
+            DbusValue::Array(elem, items) => {
+                self.u32(0);
+                let len_pos = self.buf.len() - 4;
+                // the padding to the first element is not part of the array length
+                self.pad(type_alignment(elem.as_bytes()[0]));
+                let start = self.buf.len();
+                for item in items {
+                    self.value(item);
+                }
+                let len = (self.buf.len() - start) as u32;
+                self.buf[len_pos..len_pos + 4].copy_from_slice(&len.to_le_bytes());
+            }
+            DbusValue::Struct(fields) => {
+                self.pad(8);
+                for field in fields {
+                    self.value(field);
+                }
+            }
+            DbusValue::DictEntry(k, v) => {
+                self.pad(8);
+                self.value(k);
+                self.value(v);
+            }
+            DbusValue::Variant(v) => {
+                self.sig(&v.signature());
+                self.value(v);
+            }
+        }
+    }
+}
+
+struct DbusReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> DbusReader<'a> {
+    fn align(&mut self, align: usize) {
+        self.pos = (self.pos + align - 1) / align * align;
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.pos + len > self.data.len() {
+            return Err("message is truncated".into())
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn fixed<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        self.align(N);
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.bytes(N) ?);
+        if self.big_endian {
+            out.reverse();
+        }
+        Ok(out)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.fixed() ?))
+    }
+
+    fn string(&mut self, len: usize) -> Result<String, String> {
+        let s = String::from_utf8(self.bytes(len) ?.to_vec()).map_err( | _ | "invalid utf8 in string".to_string()) ?;
+        self.bytes(1) ?;
+        Ok(s)
+    }
+
+    fn value(&mut self, sig: &str) -> Result<DbusValue, String> {
+        Ok(match sig.as_bytes()[0] {
+            b'y' => DbusValue::Byte(self.bytes(1) ?[0]),
+            b'b' => DbusValue::Bool(self.u32() ? != 0),
+            b'n' => DbusValue::I16(i16::from_le_bytes(self.fixed() ?)),
+            b'q' => DbusValue::U16(u16::from_le_bytes(self.fixed() ?)),
+            b'i' => DbusValue::I32(self.u32() ? as i32),
+            b'u' | b'h' => DbusValue::U32(self.u32() ?),
+            b'x' => DbusValue::I64(i64::from_le_bytes(self.fixed() ?)),
+            b't' => DbusValue::U64(u64::from_le_bytes(self.fixed() ?)),
+            b'd' => DbusValue::F64(f64::from_le_bytes(self.fixed() ?)),
+            b's' => {
+                let len = self.u32() ? as usize;
+                DbusValue::Str(self.string(len) ?)
+            }
+            b'o' => {
+                let len = self.u32() ? as usize;
+                DbusValue::ObjectPath(self.string(len) ?)
+            }
+            b'g' => {
+                let len = self.bytes(1) ?[0] as usize;
+                DbusValue::Signature(self.string(len) ?)
+            }
+            b'v' => {
+                let len = self.bytes(1) ?[0] as usize;
+                let inner = self.string(len) ?;
+                let (ty, _) = split_type(&inner) ?;
+                DbusValue::Variant(Box::new(self.value(ty) ?))
+            }
+            b'a' => {
+                let len = self.u32() ? as usize;
+                let elem = &sig[1..];
+                self.align(type_alignment(elem.as_bytes()[0]));
+                let end = self.pos + len;
+                let mut items = Vec::new();
+                while self.pos < end {
+                    items.push(self.value(elem) ?);
+                }
+                DbusValue::Array(elem.to_string(), items)
+            }
+            b'(' => {
+                self.align(8);
+                let mut rest = &sig[1..sig.len() - 1];
+                let mut fields = Vec::new();
+                while !rest.is_empty() {
+                    let (ty, next) = split_type(rest) ?;
+                    fields.push(self.value(ty) ?);
+                    rest = next;
+                }
+                DbusValue::Struct(fields)
+            }
+            b'{' => {
+                self.align(8);
+                let (key, value) = split_type(&sig[1..sig.len() - 1]) ?;
+                DbusValue::DictEntry(Box::new(self.value(key) ?), Box::new(self.value(value) ?))
+            }
+            c => return Err(format!("unsupported signature type {}", c as char))
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DbusMessageType {
+    MethodCall = 1,
+    MethodReturn = 2,
+    Error = 3,
+    Signal = 4,
+}
+
+#[derive(Clone, Debug)]
+pub struct DbusMessage {
+    pub message_type: DbusMessageType,
+    pub serial: u32,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub reply_serial: Option<u32>,
+    pub destination: Option<String>,
+    pub sender: Option<String>,
+    pub body: Vec<DbusValue>,
+}
+
+impl DbusMessage {
+    fn new(message_type: DbusMessageType) -> Self {
+        Self {
+            message_type,
+            serial: 0,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            reply_serial: None,
+            destination: None,
+            sender: None,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn method_call(destination: &str, path: &str, interface: &str, member: &str, body: Vec<DbusValue>) -> Self {
+        Self {
+            destination: Some(destination.into()),
+            path: Some(path.into()),
+            interface: Some(interface.into()),
+            member: Some(member.into()),
+            body,
+            ..Self::new(DbusMessageType::MethodCall)
+        }
+    }
+
+    pub fn method_return(call: &DbusMessage, body: Vec<DbusValue>) -> Self {
+        Self {
+            reply_serial: Some(call.serial),
+            destination: call.sender.clone(),
+            body,
+            ..Self::new(DbusMessageType::MethodReturn)
+        }
+    }
+
+    pub fn error(call: &DbusMessage, error_name: &str, message: &str) -> Self {
+        Self {
+            reply_serial: Some(call.serial),
+            destination: call.sender.clone(),
+            error_name: Some(error_name.into()),
+            body: vec![DbusValue::Str(message.into())],
+            ..Self::new(DbusMessageType::Error)
+        }
+    }
+
+    pub fn signal(path: &str, interface: &str, member: &str, body: Vec<DbusValue>) -> Self {
+        Self {
+            path: Some(path.into()),
+            interface: Some(interface.into()),
+            member: Some(member.into()),
+            body,
+            ..Self::new(DbusMessageType::Signal)
+        }
+    }
+
+    pub fn is_signal(&self, interface: &str, member: &str) -> bool {
+        self.message_type == DbusMessageType::Signal
+            && self.interface.as_deref() == Some(interface)
+            && self.member.as_deref() == Some(member)
+    }
+
+    pub fn encode(&self, serial: u32) -> Vec<u8> {
+        let mut body = DbusWriter::default();
+        for value in &self.body {
+            body.value(value);
+        }
+        let signature: String = self.body.iter().map( | v | v.signature()).collect();
+
+        let mut fields = Vec::new();
+        let mut field = | code: u8, value: DbusValue | {
+            fields.push(DbusValue::Struct(vec![DbusValue::Byte(code), DbusValue::Variant(Box::new(value))]));
+        };
+        if let Some(v) = &self.path {field(1, DbusValue::ObjectPath(v.clone()))}
+        if let Some(v) = &self.interface {field(2, DbusValue::Str(v.clone()))}
+        if let Some(v) = &self.member {field(3, DbusValue::Str(v.clone()))}
+        if let Some(v) = &self.error_name {field(4, DbusValue::Str(v.clone()))}
+        if let Some(v) = self.reply_serial {field(5, DbusValue::U32(v))}
+        if let Some(v) = &self.destination {field(6, DbusValue::Str(v.clone()))}
+        if let Some(v) = &self.sender {field(7, DbusValue::Str(v.clone()))}
+        if !signature.is_empty() {field(8, DbusValue::Signature(signature))}
+
+        let mut out = DbusWriter::default();
+        out.buf.extend_from_slice(&[b'l', self.message_type as u8, 0, 1]);
+        out.u32(body.buf.len() as u32);
+        out.u32(serial);
+        out.value(&DbusValue::Array("(yv)".into(), fields));
+        out.pad(8);
+        out.buf.extend_from_slice(&body.buf);
+        out.buf
+    }
+
+    pub fn read_from(stream: &mut impl Read) -> Result<DbusMessage, DbusError> {
+        let mut fixed = [0u8; 16];
+        stream.read_exact(&mut fixed) ?;
+        let big_endian = match fixed[0] {
+            b'l' => false,
+            b'B' => true,
+            _ => return Err(DbusError::Protocol("invalid endianness flag".into()))
+        };
+        let word = | bytes: &[u8] | {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            if big_endian {u32::from_be_bytes(bytes)} else {u32::from_le_bytes(bytes)}
+        };
+        let body_len = word(&fixed[4..8]) as usize;
+        let fields_len = word(&fixed[12..16]) as usize;
+        let header_len = (16 + fields_len + 7) / 8 * 8;
+        let mut data = fixed.to_vec();
+        data.resize(header_len + body_len, 0);
+        stream.read_exact(&mut data[16..]) ?;
+        Self::decode(&data).map_err(DbusError::Protocol)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<DbusMessage, String> {
+        if data.len() < 16 {
+            return Err("message is truncated".into())
+        }
+        let mut reader = DbusReader {data, pos: 0, big_endian: data[0] == b'B'};
+        let message_type = match data[1] {
+            1 => DbusMessageType::MethodCall,
+            2 => DbusMessageType::MethodReturn,
+            3 => DbusMessageType::Error,
+            4 => DbusMessageType::Signal,
+            t => return Err(format!("unknown message type {}", t))
+        };
+        reader.pos = 4;
+        let body_len = reader.u32() ? as usize;
+        let mut message = Self::new(message_type);
+        message.serial = reader.u32() ?;
+        let mut signature = String::new();
+        if let DbusValue::Array(_, fields) = reader.value("a(yv)") ? {
+            for field in fields {
+                if let DbusValue::Struct(f) = field {
+                    let value = &f[1];
+                    match f[0] {
+                        DbusValue::Byte(1) => message.path = value.as_str().map(Into::into),
+                        DbusValue::Byte(2) => message.interface = value.as_str().map(Into::into),
+                        DbusValue::Byte(3) => message.member = value.as_str().map(Into::into),
+                        DbusValue::Byte(4) => message.error_name = value.as_str().map(Into::into),
+                        DbusValue::Byte(5) => message.reply_serial = value.as_u32(),
+                        DbusValue::Byte(6) => message.destination = value.as_str().map(Into::into),
+                        DbusValue::Byte(7) => message.sender = value.as_str().map(Into::into),
+                        DbusValue::Byte(8) => signature = value.as_str().unwrap_or("").into(),
+                        _ => ()
+                    }
+                }
+            }
+        }
+        reader.align(8);
+        // the body is aligned relative to its own start
+        let body = reader.bytes(body_len) ?;
+        let mut reader = DbusReader {data: body, pos: 0, big_endian: reader.big_endian};
+        let mut rest = signature.as_str();
+        while !rest.is_empty() {
+            let (ty, next) = split_type(rest) ?;
+            message.body.push(reader.value(ty) ?);
+            rest = next;
+        }
+        Ok(message)
+    }
+}
+
+#[derive(Debug)]
+pub enum DbusError {
+    Io(String),
+    Protocol(String),
+    /// An error reply, with its error name and message
+    Reply(String, String),
+}
+
+impl From<std::io::Error> for DbusError {
+    fn from(err: std::io::Error) -> Self {
+        DbusError::Io(err.to_string())
+    }
+}
+
+impl std::fmt::Display for DbusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "dbus io error: {}", e),
+            Self::Protocol(e) => write!(f, "dbus protocol error: {}", e),
+            Self::Reply(name, msg) => write!(f, "{}: {}", name, msg),
+        }
+    }
+}
+
+pub struct DbusConnection {
+    stream: UnixStream,
+    serial: u32,
+    pub unique_name: String,
+    // messages that arrived while waiting for something else
+    queue: Vec<DbusMessage>,
+}
+
+fn unescape_address(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(v) = hex.and_then( | hex | u8::from_str_radix(hex, 16).ok()) {
+                out.push(v);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into()
+}
+
+fn connect_address(address: &str) -> Result<UnixStream, DbusError> {
+    let mut last_err = DbusError::Io(format!("no usable address in {}", address));
+    for entry in address.split(';') {
+        let Some(params) = entry.strip_prefix("unix:") else {continue};
+        for param in params.split(',') {
+            let result = if let Some(path) = param.strip_prefix("path=") {
+                UnixStream::connect(unescape_address(path))
+            }
+            else if let Some(name) = param.strip_prefix("abstract=") {
+                use std::os::linux::net::SocketAddrExt;
+                std::os::unix::net::SocketAddr::from_abstract_name(unescape_address(name).as_bytes())
+                    .and_then( | addr | UnixStream::connect_addr(&addr))
+            }
+            else {
+                continue
+            };
+            match result {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = err.into(),
+            }
+        }
+    }
+    Err(last_err)
+}
+
+impl DbusConnection {
+    pub fn session() -> Result<Self, DbusError> {
+        let address = match std::env::var("DBUS_SESSION_BUS_ADDRESS") {
+            Ok(address) => address,
+            Err(_) => match std::env::var("XDG_RUNTIME_DIR") {
+                Ok(dir) => format!("unix:path={}/bus", dir),
+                Err(_) => return Err(DbusError::Io("no session bus address".into()))
+            }
+        };
+        Self::connect(&address)
+    }
+
+    pub fn connect(address: &str) -> Result<Self, DbusError> {
+        let mut stream = connect_address(address) ?;
+
+        let uid = unsafe {libc_sys::getuid()}.to_string();
+        let hex_uid: String = uid.bytes().map( | b | format!("{:02x}", b)).collect();
+        stream.write_all(b"\0") ?;
+        stream.write_all(format!("AUTH EXTERNAL {}\r\n", hex_uid).as_bytes()) ?;
+        let line = read_line(&mut stream) ?;
+        if !line.starts_with("OK") {
+            return Err(DbusError::Protocol(format!("authentication failed: {}", line)))
+        }
+        stream.write_all(b"BEGIN\r\n") ?;
+
+        let mut conn = Self {
+            stream,
+            serial: 0,
+            unique_name: String::new(),
+            queue: Vec::new(),
+        };
+        let reply = conn.call(DbusMessage::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+            vec![]
+        )) ?;
+        conn.unique_name = reply.body.first().and_then( | v | v.as_str()).unwrap_or("").into();
+        Ok(conn)
+    }
+
+    pub fn send(&mut self, message: &DbusMessage) -> Result<u32, DbusError> {
+        self.serial += 1;
+        self.stream.write_all(&message.encode(self.serial)) ?;
+        Ok(self.serial)
+    }
+
+    /// Sends a method call and blocks until its reply arrives
+    pub fn call(&mut self, message: DbusMessage) -> Result<DbusMessage, DbusError> {
+        let serial = self.send(&message) ?;
+        loop {
+            let reply = DbusMessage::read_from(&mut self.stream) ?;
+            if reply.reply_serial != Some(serial) {
+                self.queue.push(reply);
+                continue
+            }
+            if reply.message_type == DbusMessageType::Error {
+                let name = reply.error_name.clone().unwrap_or_default();
+                let msg = reply.body.first().and_then( | v | v.as_str()).unwrap_or("").to_string();
+                return Err(DbusError::Reply(name, msg))
+            }
+            return Ok(reply)
+        }
+    }
+
+    /// Blocks until a message matching `filter` arrives
+    pub fn wait_for(&mut self, filter: impl Fn(&DbusMessage) -> bool) -> Result<DbusMessage, DbusError> {
+        if let Some(index) = self.queue.iter().position( | m | filter(m)) {
+            return Ok(self.queue.remove(index))
+        }
+        loop {
+            let message = DbusMessage::read_from(&mut self.stream) ?;
+            if filter(&message) {
+                return Ok(message)
+            }
+        }
+    }
+}
+
+pub fn read_line(stream: &mut impl Read) -> Result<String, DbusError> {
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    while !line.ends_with(b"\r\n") {
+        stream.read_exact(&mut byte) ?;
+        line.push(byte[0]);
+    }
+    line.truncate(line.len() - 2);
+    Ok(String::from_utf8_lossy(&line).into())
+}
@@ -9,7 +9,8 @@ use {
     self::super::super::{
         gl_sys,
         select_timer::SelectTimers,
-        linux_media::CxLinuxMedia
+        linux_media::CxLinuxMedia,
+        http::make_http_request,
     },
     crate::{
         cx_api::{CxOsOp, CxOsApi},
@@ -20,6 +21,7 @@ use {
             TimerEvent,
             Event,
             WindowGeom,
+            NetworkResponseChannel,
         },
         window::CxWindowPool,
         pass::CxPassParent,
@@ -180,6 +182,7 @@ impl Cx {
                         self.handle_media_signals();
                         self.call_event_handler(&Event::Signal);
                     }
+                    self.handle_networking_events();
                 }
                 else {
                     self.call_event_handler(&Event::Timer(e))
@@ -193,6 +196,16 @@ impl Cx {
         }
     }
     
+    pub (crate) fn handle_networking_events(&mut self) {
+        let mut out = Vec::new();
+        while let Ok(event) = self.os.network_response.receiver.try_recv() {
+            out.push(event);
+        }
+        if out.len()>0 {
+            self.call_event_handler(&Event::NetworkResponses(out))
+        }
+    }
+    
     pub fn draw_pass_to_fullscreen(
         &mut self,
         pass_id: PassId,
@@ -297,6 +310,9 @@ impl Cx {
                 CxOsOp::StopTimer(timer_id) => {
                     direct_app.timers.stop_timer(timer_id);
                 },
+                CxOsOp::HttpRequest{request_id, request} => {
+                    make_http_request(request_id, request, self.os.network_response.sender.clone());
+                },
                 _ => ()
             }
         }
@@ -319,5 +335,6 @@ impl CxOsApi for Cx {
 #[derive(Default)]
 pub struct CxOs {
     pub (crate) media: CxLinuxMedia,
+    pub (crate) network_response: NetworkResponseChannel,
 }
 
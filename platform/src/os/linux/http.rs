@@ -0,0 +1,331 @@
+use {
+    std::{
+        ffi::CString,
+        io::{self, BufReader, Read, Write},
+        net::{Shutdown, TcpStream},
+        os::unix::io::AsRawFd,
+        sync::mpsc::Sender,
+        time::Duration,
+    },
+    makepad_http::client::*,
+    self::super::openssl_sys::*,
+    crate::{
+        makepad_live_id::LiveId,
+        event::{HttpRequest, HttpResponse, HttpMethod, NetworkResponse, NetworkResponseItem},
+    }
+};
+
+pub struct TlsStream {
+    tcp_stream: TcpStream,
+    ctx: *mut SSL_CTX,
+    ssl: *mut SSL,
+}
+
+// the SSL object is only ever used from one thread at a time
+unsafe impl Send for TlsStream {}
+
+impl TlsStream {
+    pub fn connect(tcp_stream: TcpStream, host: &str, ignore_ssl_cert: bool) -> Result<TlsStream, String> {
+        let lib = get_lib_ssl().ok_or_else( || "Cannot load libssl for https".to_string()) ?;
+        unsafe {
+            let ctx = (lib.SSL_CTX_new)((lib.TLS_client_method)());
+            if ctx.is_null() {
+                return Err("SSL_CTX_new failed".to_string())
+            }
+            if ignore_ssl_cert {
+                (lib.SSL_CTX_set_verify)(ctx, SSL_VERIFY_NONE, std::ptr::null());
+            }
+            else {
+                (lib.SSL_CTX_set_verify)(ctx, SSL_VERIFY_PEER, std::ptr::null());
+                (lib.SSL_CTX_set_default_verify_paths)(ctx);
+            }
+            let ssl = (lib.SSL_new)(ctx);
+            // from here on drop cleans up ctx and ssl
+            let stream = TlsStream {tcp_stream, ctx, ssl};
+            if ssl.is_null() {
+                return Err("SSL_new failed".to_string())
+            }
+            let c_host = CString::new(host).map_err( | _ | "Invalid host".to_string()) ?;
+            (lib.SSL_ctrl)(ssl, SSL_CTRL_SET_TLSEXT_HOSTNAME, TLSEXT_NAMETYPE_HOST_NAME, c_host.as_ptr() as *mut _);
+            if !ignore_ssl_cert {
+                (lib.SSL_set1_host)(ssl, c_host.as_ptr());
+            }
+            (lib.SSL_set_fd)(ssl, stream.tcp_stream.as_raw_fd());
+            let ret = (lib.SSL_connect)(ssl);
+            if ret != 1 {
+                let err = (lib.SSL_get_error)(ssl, ret);
+                if !ignore_ssl_cert && (lib.SSL_get_verify_result)(ssl) != X509_V_OK {
+                    return Err(format!("SSL certificate verification failed for {}", host))
+                }
+                return Err(format!("SSL handshake with {} failed ({})", host, err))
+            }
+            Ok(stream)
+        }
+    }
+
+    fn map_error(&self, ret: i32) -> io::Error {
+        let lib = get_lib_ssl().unwrap();
+        match unsafe {(lib.SSL_get_error)(self.ssl, ret)} {
+            SSL_ERROR_WANT_READ | SSL_ERROR_WANT_WRITE => io::ErrorKind::WouldBlock.into(),
+            SSL_ERROR_SYSCALL => {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(0) {io::ErrorKind::UnexpectedEof.into()} else {err}
+            }
+            err => io::Error::new(io::ErrorKind::Other, format!("SSL error {}", err))
+        }
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let lib = get_lib_ssl().unwrap();
+        let len = buf.len().min(i32::MAX as usize) as i32;
+        let ret = unsafe {(lib.SSL_read)(self.ssl, buf.as_mut_ptr() as *mut _, len)};
+        if ret > 0 {
+            return Ok(ret as usize)
+        }
+        if unsafe {(lib.SSL_get_error)(self.ssl, ret)} == SSL_ERROR_ZERO_RETURN {
+            return Ok(0)
+        }
+        Err(self.map_error(ret))
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0)
+        }
+        let lib = get_lib_ssl().unwrap();
+        let len = buf.len().min(i32::MAX as usize) as i32;
+        let ret = unsafe {(lib.SSL_write)(self.ssl, buf.as_ptr() as *const _, len)};
+        if ret > 0 {
+            return Ok(ret as usize)
+        }
+        Err(self.map_error(ret))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for TlsStream {
+    fn drop(&mut self) {
+        let lib = get_lib_ssl().unwrap();
+        unsafe {
+            if !self.ssl.is_null() {
+                (lib.SSL_shutdown)(self.ssl);
+                (lib.SSL_free)(self.ssl);
+            }
+            (lib.SSL_CTX_free)(self.ctx);
+        }
+    }
+}
+
+pub enum HttpStream {
+    Tcp(TcpStream),
+    Tls(TlsStream)
+}
+
+impl HttpStream {
+    pub fn connect(url: &HttpUrl, ignore_ssl_cert: bool) -> Result<HttpStream, String> {
+        let tcp_stream = TcpStream::connect((url.host.as_str(), url.port))
+            .map_err( | e | format!("Cannot connect to {}:{} - {}", url.host, url.port, e)) ?;
+        let _ = tcp_stream.set_nodelay(true);
+        if url.is_tls {
+            Ok(HttpStream::Tls(TlsStream::connect(tcp_stream, &url.host, ignore_ssl_cert) ?))
+        }
+        else {
+            Ok(HttpStream::Tcp(tcp_stream))
+        }
+    }
+
+    pub fn tcp_stream(&self) -> &TcpStream {
+        match self {
+            Self::Tcp(s) => s,
+            Self::Tls(s) => &s.tcp_stream
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.tcp_stream().set_read_timeout(timeout)
+    }
+
+    pub fn shutdown(&self) {
+        let _ = self.tcp_stream().shutdown(Shutdown::Both);
+    }
+}
+
+impl Read for HttpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(s) => s.read(buf),
+            Self::Tls(s) => s.read(buf)
+        }
+    }
+}
+
+impl Write for HttpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(s) => s.write(buf),
+            Self::Tls(s) => s.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Tcp(s) => s.flush(),
+            Self::Tls(s) => s.flush()
+        }
+    }
+}
+
+fn request_headers(request: &HttpRequest) -> Vec<(String, String)> {
+    request.headers.iter().map( | (k, v) | (k.clone(), v.join(","))).collect()
+}
+
+fn http_request_with_redirects(request_id: LiveId, request: &HttpRequest, networking_sender: &Sender<NetworkResponseItem>) -> Result<HttpResponse, String> {
+    let mut url = HttpUrl::parse(&request.url).ok_or_else( || format!("Invalid url {}", request.url)) ?;
+    let mut method = request.method.to_string().to_string();
+    let mut body = request.body.as_ref();
+    let mut headers = request_headers(request);
+
+    for _ in 0..MAX_REDIRECTS {
+        let mut stream = HttpStream::connect(&url, request.ignore_ssl_cert) ?;
+        let head = build_request_head(&method, &url, &headers, body.map( | b | b.len()));
+        stream.write_all(head.as_bytes()).map_err( | e | format!("Write error: {}", e)) ?;
+        if let Some(body) = body {
+            stream.write_all(body).map_err( | e | format!("Write error: {}", e)) ?;
+        }
+
+        let mut reader = BufReader::new(stream);
+        let response_head = read_response_head(&mut reader) ?;
+
+        if response_head.is_redirect() {
+            let location = response_head.header("Location").unwrap();
+            let next_url = url.join(location).ok_or_else( || format!("Invalid redirect location {}", location)) ?;
+            if !url.is_same_origin(&next_url) {
+                remove_credential_headers(&mut headers);
+            }
+            url = next_url;
+            // 303 always turns into a GET, 301/302 do so for anything but GET/HEAD by convention
+            if response_head.status_code == 303 || (matches!(response_head.status_code, 301 | 302) && method != "GET" && method != "HEAD") {
+                method = "GET".to_string();
+                body = None;
+            }
+            continue;
+        }
+
+        let mut last_progress = 0;
+        let response_body = read_response_body(&mut reader, &response_head, request.method == HttpMethod::HEAD, | loaded, total | {
+            if loaded - last_progress >= 65536 {
+                last_progress = loaded;
+                let _ = networking_sender.send(NetworkResponseItem {
+                    request_id,
+                    response: NetworkResponse::HttpProgress {loaded, total}
+                });
+            }
+        }) ?;
+        reader.get_ref().shutdown();
+
+        let mut response = HttpResponse::new(
+            request.metadata_id,
+            response_head.status_code,
+            "".to_string(),
+            Some(response_body)
+        );
+        for (key, value) in response_head.headers {
+            response.set_header(key, value);
+        }
+        return Ok(response)
+    }
+    Err(format!("Too many redirects for {}", request.url))
+}
+
+pub fn make_http_request(request_id: LiveId, request: HttpRequest, networking_sender: Sender<NetworkResponseItem>) {
+    std::thread::spawn(move || {
+        let response = match http_request_with_redirects(request_id, &request, &networking_sender) {
+            Ok(response) => NetworkResponse::HttpResponse(response),
+            Err(err) => NetworkResponse::HttpRequestError(err)
+        };
+        let _ = networking_sender.send(NetworkResponseItem {
+            request_id,
+            response
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{io::BufRead, net::TcpListener, sync::mpsc, thread},
+    };
+
+    // answers every connection with the response for its request head, returns the address
+    fn serve(respond: impl Fn(&str) -> String + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() <= 2 {
+                        break
+                    }
+                    head.push_str(&line);
+                }
+                stream.write_all(respond(&head).as_bytes()).unwrap();
+            }
+        });
+        address
+    }
+
+    fn echo_head(head: &str) -> String {
+        format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", head.len(), head)
+    }
+
+    fn redirect_to(location: &str) -> String {
+        format!("HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n", location)
+    }
+
+    // the head of the request the redirect ended up at
+    fn get_with_credentials(url: String) -> String {
+        let mut request = HttpRequest::new(url, HttpMethod::GET);
+        request.set_header("Authorization".to_string(), "Bearer secret".to_string());
+        request.set_header("Cookie".to_string(), "session=secret".to_string());
+        request.set_header("X-Other".to_string(), "kept".to_string());
+        let (sender, _receiver) = mpsc::channel();
+        let response = http_request_with_redirects(LiveId(0), &request, &sender).unwrap();
+        String::from_utf8(response.get_body().unwrap().clone()).unwrap().to_lowercase()
+    }
+
+    #[test]
+    fn keeps_credentials_on_same_origin_redirects() {
+        let address = serve( | head | if head.starts_with("GET /start ") {redirect_to("/end")} else {echo_head(head)});
+        let head = get_with_credentials(format!("http://{}/start", address));
+        assert!(head.starts_with("get /end "));
+        assert!(head.contains("authorization: bearer secret"));
+        assert!(head.contains("cookie: session=secret"));
+    }
+
+    #[test]
+    fn drops_credentials_on_cross_origin_redirects() {
+        let other_address = serve(echo_head);
+        let address = serve(move | head | if head.starts_with("GET /start ") {
+            redirect_to(&format!("http://{}/end", other_address))
+        } else {
+            echo_head(head)
+        });
+        let head = get_with_credentials(format!("http://{}/start", address));
+        assert!(head.starts_with("get /end "));
+        assert!(!head.contains("authorization"));
+        assert!(!head.contains("cookie"));
+        assert!(head.contains("x-other: kept"));
+    }
+}
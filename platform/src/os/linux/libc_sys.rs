@@ -36,6 +36,8 @@ pub struct fd_set {
     fds_bits: [c_ulong; FD_SETSIZE / ULONG_SIZE],
 }
 
+pub const LC_CTYPE: c_int = 0;
+
 pub const RTLD_LAZY: c_int = 1;
 pub const RTLD_LOCAL: c_int = 0;
     
@@ -55,6 +57,8 @@ extern "C"{
         timeout: *mut timeval,
     ) -> c_int;
     pub fn read(fd: c_int, buf: *mut c_void, count: size_t) -> c_int;
+    pub fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char;
+    pub fn getuid() -> u32;
 }
 
 pub unsafe fn FD_SET(fd: c_int, set: *mut fd_set) -> () {
@@ -32,6 +32,14 @@ pub mod pulse_sys;
 
 #[cfg(not(target_os="android"))]
 mod web_socket;
+#[cfg(not(target_os="android"))]
+pub mod openssl_sys;
+#[cfg(not(target_os="android"))]
+pub mod http;
+#[cfg(not(target_os="android"))]
+pub mod dbus;
+#[cfg(not(target_os="android"))]
+pub mod xdg_portal;
 
 #[cfg(target_os="android")]
 pub mod android;
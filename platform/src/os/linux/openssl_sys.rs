@@ -0,0 +1,116 @@
+#![allow(non_camel_case_types, non_snake_case, dead_code)]
+
+// libssl is loaded at runtime so apps that never touch https don't get a hard link dependency
+
+use std::os::raw::{c_char, c_int, c_long, c_void};
+use std::sync::OnceLock;
+
+pub enum SSL_METHOD {}
+pub enum SSL_CTX {}
+pub enum SSL {}
+
+pub const SSL_VERIFY_NONE: c_int = 0;
+pub const SSL_VERIFY_PEER: c_int = 1;
+
+pub const SSL_ERROR_NONE: c_int = 0;
+pub const SSL_ERROR_SSL: c_int = 1;
+pub const SSL_ERROR_WANT_READ: c_int = 2;
+pub const SSL_ERROR_WANT_WRITE: c_int = 3;
+pub const SSL_ERROR_SYSCALL: c_int = 5;
+pub const SSL_ERROR_ZERO_RETURN: c_int = 6;
+
+pub const SSL_CTRL_SET_TLSEXT_HOSTNAME: c_int = 55;
+pub const TLSEXT_NAMETYPE_HOST_NAME: c_long = 0;
+
+pub const X509_V_OK: c_long = 0;
+
+pub type PFN_OPENSSL_init_ssl = unsafe extern "C" fn(opts: u64, settings: *const c_void) -> c_int;
+pub type PFN_TLS_client_method = unsafe extern "C" fn() -> *const SSL_METHOD;
+pub type PFN_SSL_CTX_new = unsafe extern "C" fn(method: *const SSL_METHOD) -> *mut SSL_CTX;
+pub type PFN_SSL_CTX_free = unsafe extern "C" fn(ctx: *mut SSL_CTX);
+pub type PFN_SSL_CTX_set_verify = unsafe extern "C" fn(ctx: *mut SSL_CTX, mode: c_int, callback: *const c_void);
+pub type PFN_SSL_CTX_set_default_verify_paths = unsafe extern "C" fn(ctx: *mut SSL_CTX) -> c_int;
+pub type PFN_SSL_new = unsafe extern "C" fn(ctx: *mut SSL_CTX) -> *mut SSL;
+pub type PFN_SSL_free = unsafe extern "C" fn(ssl: *mut SSL);
+pub type PFN_SSL_set_fd = unsafe extern "C" fn(ssl: *mut SSL, fd: c_int) -> c_int;
+pub type PFN_SSL_ctrl = unsafe extern "C" fn(ssl: *mut SSL, cmd: c_int, larg: c_long, parg: *mut c_void) -> c_long;
+pub type PFN_SSL_set1_host = unsafe extern "C" fn(ssl: *mut SSL, hostname: *const c_char) -> c_int;
+pub type PFN_SSL_connect = unsafe extern "C" fn(ssl: *mut SSL) -> c_int;
+pub type PFN_SSL_read = unsafe extern "C" fn(ssl: *mut SSL, buf: *mut c_void, num: c_int) -> c_int;
+pub type PFN_SSL_write = unsafe extern "C" fn(ssl: *mut SSL, buf: *const c_void, num: c_int) -> c_int;
+pub type PFN_SSL_shutdown = unsafe extern "C" fn(ssl: *mut SSL) -> c_int;
+pub type PFN_SSL_get_error = unsafe extern "C" fn(ssl: *const SSL, ret: c_int) -> c_int;
+pub type PFN_SSL_get_verify_result = unsafe extern "C" fn(ssl: *const SSL) -> c_long;
+
+pub struct LibSsl {
+    pub OPENSSL_init_ssl: PFN_OPENSSL_init_ssl,
+    pub TLS_client_method: PFN_TLS_client_method,
+    pub SSL_CTX_new: PFN_SSL_CTX_new,
+    pub SSL_CTX_free: PFN_SSL_CTX_free,
+    pub SSL_CTX_set_verify: PFN_SSL_CTX_set_verify,
+    pub SSL_CTX_set_default_verify_paths: PFN_SSL_CTX_set_default_verify_paths,
+    pub SSL_new: PFN_SSL_new,
+    pub SSL_free: PFN_SSL_free,
+    pub SSL_set_fd: PFN_SSL_set_fd,
+    pub SSL_ctrl: PFN_SSL_ctrl,
+    pub SSL_set1_host: PFN_SSL_set1_host,
+    pub SSL_connect: PFN_SSL_connect,
+    pub SSL_read: PFN_SSL_read,
+    pub SSL_write: PFN_SSL_write,
+    pub SSL_shutdown: PFN_SSL_shutdown,
+    pub SSL_get_error: PFN_SSL_get_error,
+    pub SSL_get_verify_result: PFN_SSL_get_verify_result,
+}
+
+impl LibSsl {
+    fn try_load() -> Option<LibSsl> {
+        use self::super::libc_sys::{dlopen, dlsym, RTLD_LAZY, RTLD_LOCAL};
+        use std::ffi::CString;
+
+        let module = ["libssl.so.3", "libssl.so.1.1", "libssl.so"].iter().find_map( | path | {
+            let path = CString::new(*path).unwrap();
+            let module = unsafe {dlopen(path.as_ptr(), RTLD_LAZY | RTLD_LOCAL)};
+            if module.is_null() {None} else {Some(module)}
+        }) ?;
+
+        // the module is never closed, the function pointers live as long as the process
+        macro_rules! get_symbol {
+            ($name:literal) => {{
+                let name = CString::new($name).unwrap();
+                let symbol = unsafe {dlsym(module, name.as_ptr())};
+                if symbol.is_null() {
+                    return None
+                }
+                unsafe {std::mem::transmute_copy(&symbol)}
+            }}
+        }
+
+        let lib = LibSsl {
+            OPENSSL_init_ssl: get_symbol!("OPENSSL_init_ssl"),
+            TLS_client_method: get_symbol!("TLS_client_method"),
+            SSL_CTX_new: get_symbol!("SSL_CTX_new"),
+            SSL_CTX_free: get_symbol!("SSL_CTX_free"),
+            SSL_CTX_set_verify: get_symbol!("SSL_CTX_set_verify"),
+            SSL_CTX_set_default_verify_paths: get_symbol!("SSL_CTX_set_default_verify_paths"),
+            SSL_new: get_symbol!("SSL_new"),
+            SSL_free: get_symbol!("SSL_free"),
+            SSL_set_fd: get_symbol!("SSL_set_fd"),
+            SSL_ctrl: get_symbol!("SSL_ctrl"),
+            SSL_set1_host: get_symbol!("SSL_set1_host"),
+            SSL_connect: get_symbol!("SSL_connect"),
+            SSL_read: get_symbol!("SSL_read"),
+            SSL_write: get_symbol!("SSL_write"),
+            SSL_shutdown: get_symbol!("SSL_shutdown"),
+            SSL_get_error: get_symbol!("SSL_get_error"),
+            SSL_get_verify_result: get_symbol!("SSL_get_verify_result"),
+        };
+        unsafe {(lib.OPENSSL_init_ssl)(0, std::ptr::null())};
+        Some(lib)
+    }
+}
+
+static LIB_SSL: OnceLock<Option<LibSsl>> = OnceLock::new();
+
+pub fn get_lib_ssl() -> Option<&'static LibSsl> {
+    LIB_SSL.get_or_init(LibSsl::try_load).as_ref()
+}
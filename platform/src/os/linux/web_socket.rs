@@ -1,16 +1,161 @@
+use {
+    std::{
+        io::{self, BufReader, Read, Write},
+        sync::mpsc::{channel, Sender, Receiver, TryRecvError},
+        time::{Duration, Instant},
+    },
+    makepad_http::{
+        client::*,
+        websocket::{WebSocket, WebSocketMessage as WebSocketFrame, MessageHeader, MessageFormat},
+    },
+    self::super::http::HttpStream,
+    crate::{
+        event::HttpRequest,
+        web_socket::WebSocketMessage,
+    }
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+const PING_INTERVAL: Duration = Duration::from_secs(30);
 
-use crate::event::HttpRequest;
-use crate::web_socket::{WebSocketMessage};
-use std::sync::mpsc::{Sender};
 pub struct OsWebSocket{
+    sender: Sender<WebSocketMessage>,
 }
 
 impl OsWebSocket{
-    pub fn send_message(&mut self, _message:WebSocketMessage)->Result<(),()>{
-        todo!();
+    pub fn send_message(&mut self, message:WebSocketMessage)->Result<(),()>{
+        self.sender.send(message).map_err(|_|())
+    }
+
+    pub fn open(request: HttpRequest, rx_sender:Sender<WebSocketMessage>)->OsWebSocket{
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || {
+            let result = match connect(&request) {
+                Ok(reader) => run_socket(reader, &receiver, &rx_sender),
+                Err(err) => Err(err)
+            };
+            if let Err(err) = result {
+                let _ = rx_sender.send(WebSocketMessage::Error(err));
+            }
+            let _ = rx_sender.send(WebSocketMessage::Closed);
+        });
+        OsWebSocket{
+            sender
+        }
+    }
+}
+
+impl Drop for OsWebSocket {
+    fn drop(&mut self) {
+        let _ = self.sender.send(WebSocketMessage::Closed);
+    }
+}
+
+fn connect(request: &HttpRequest) -> Result<BufReader<HttpStream>, String> {
+    let url = HttpUrl::parse(&request.url).ok_or_else( || format!("Invalid websocket url {}", request.url)) ?;
+    let stream = HttpStream::connect(&url, request.ignore_ssl_cert) ?;
+
+    let key = WebSocket::create_client_key();
+    let mut headers: Vec<(String, String)> = request.headers.iter().map( | (k, v) | (k.clone(), v.join(","))).collect();
+    headers.push(("Connection".to_string(), "Upgrade".to_string()));
+    headers.push(("Upgrade".to_string(), "websocket".to_string()));
+    headers.push(("Sec-WebSocket-Version".to_string(), "13".to_string()));
+    headers.push(("Sec-WebSocket-Key".to_string(), key.clone()));
+
+    let mut reader = BufReader::new(stream);
+    let head = build_request_head("GET", &url, &headers, None);
+    reader.get_mut().write_all(head.as_bytes()).map_err( | e | format!("Write error: {}", e)) ?;
+
+    let response = read_response_head(&mut reader) ?;
+    if response.status_code != 101 {
+        return Err(format!("Websocket upgrade failed with status {}", response.status_code))
     }
-                    
-    pub fn open(_request: HttpRequest, _rx_sender:Sender<WebSocketMessage>)->OsWebSocket{
-        todo!();
+    if response.header("Sec-WebSocket-Accept") != Some(WebSocket::create_accept_key(&key).as_str()) {
+        return Err("Websocket upgrade returned an invalid Sec-WebSocket-Accept".to_string())
     }
-}
\ No newline at end of file
+    Ok(reader)
+}
+
+fn write_frame(stream: &mut HttpStream, format: MessageFormat, data: &[u8]) -> Result<(), String> {
+    // client to server frames always have to be masked
+    let header = MessageHeader::from_len(data.len(), format, true);
+    let frame = WebSocket::build_message(header, data);
+    stream.write_all(&frame).map_err( | e | format!("Write error: {}", e))
+}
+
+fn run_socket(mut reader: BufReader<HttpStream>, receiver: &Receiver<WebSocketMessage>, rx_sender: &Sender<WebSocketMessage>) -> Result<(), String> {
+    // one thread owns the stream, a short read timeout lets us interleave outgoing messages
+    reader.get_ref().set_read_timeout(Some(POLL_INTERVAL)).map_err( | e | e.to_string()) ?;
+    let mut web_socket = WebSocket::new();
+    let mut data = [0u8; 65535];
+    let mut last_traffic = Instant::now();
+    loop {
+        loop {
+            match receiver.try_recv() {
+                Ok(WebSocketMessage::String(text)) => {
+                    write_frame(reader.get_mut(), MessageFormat::Text, text.as_bytes()) ?;
+                }
+                Ok(WebSocketMessage::Binary(data)) => {
+                    write_frame(reader.get_mut(), MessageFormat::Binary, &data) ?;
+                }
+                Ok(WebSocketMessage::Closed) | Err(TryRecvError::Disconnected) => {
+                    let _ = write_frame(reader.get_mut(), MessageFormat::Close, &[]);
+                    reader.get_ref().shutdown();
+                    return Ok(())
+                }
+                Ok(WebSocketMessage::Error(_)) => (),
+                Err(TryRecvError::Empty) => break
+            }
+        }
+
+        if last_traffic.elapsed() > PING_INTERVAL {
+            write_frame(reader.get_mut(), MessageFormat::Ping, &[]) ?;
+            last_traffic = Instant::now();
+        }
+
+        let n = match reader.read(&mut data) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("Read error: {}", e))
+        };
+        last_traffic = Instant::now();
+
+        let mut pong = None;
+        let mut closed = false;
+        let mut error = None;
+        web_socket.parse(&data[0..n], | result | {
+            match result {
+                Ok(WebSocketFrame::Ping(payload)) => {
+                    pong = Some(payload.to_vec());
+                }
+                Ok(WebSocketFrame::Pong(_)) => {
+                }
+                Ok(WebSocketFrame::Text(text)) => {
+                    let _ = rx_sender.send(WebSocketMessage::String(text.to_string()));
+                }
+                Ok(WebSocketFrame::Binary(data)) => {
+                    let _ = rx_sender.send(WebSocketMessage::Binary(data.to_vec()));
+                }
+                Ok(WebSocketFrame::Close) => {
+                    closed = true;
+                }
+                Err(e) => {
+                    error = Some(format!("Websocket error {:?}", e));
+                }
+            }
+        });
+        if let Some(payload) = pong {
+            write_frame(reader.get_mut(), MessageFormat::Pong, &payload) ?;
+        }
+        if let Some(error) = error {
+            reader.get_ref().shutdown();
+            return Err(error)
+        }
+        if closed {
+            let _ = write_frame(reader.get_mut(), MessageFormat::Close, &[]);
+            reader.get_ref().shutdown();
+            return Ok(())
+        }
+    }
+}
@@ -9,14 +9,19 @@ use {
         egl_sys,
         x11::xlib_event::*,
         x11::xlib_app::*,
-        linux_media::CxLinuxMedia
+        linux_media::CxLinuxMedia,
+        http::make_http_request,
+        xdg_portal::{open_portal_file_dialog, PortalChannel, PortalResponse},
     },
+    crate::os::software::CxSoftware,
     crate::{
         cx_api::{CxOsOp, CxOsApi}, 
         makepad_math::dvec2,
         makepad_live_id::*,
         thread::SignalToUI,
-        event::Event,
+        event::{Event, MouseUpEvent, NetworkResponseChannel, TextCompositionEvent},
+        window::CxWindowPool,
+        file_dialogs::FileDialogKind,
         pass::CxPassParent,
         cx::{Cx, OsType,LinuxWindowParams}, 
         os::cx_stdin::{PollTimers},
@@ -33,8 +38,15 @@ impl Cx {
         });
         cx.borrow_mut().gpu_info.performance = GpuPerformance::Tier1;
 
-        let opengl_windows = Rc::new(RefCell::new(Vec::new()));
         let is_stdin_loop = std::env::args().find(|v| v=="--stdin-loop").is_some();
+        if std::env::args().any(|v| v=="--headless") {
+            if is_stdin_loop {
+                return cx.borrow_mut().headless_stdin_event_loop();
+            }
+            return cx.borrow_mut().headless_event_loop();
+        }
+
+        let opengl_windows = Rc::new(RefCell::new(Vec::new()));
         init_xlib_app_global(Box::new({
             let cx = cx.clone();
             move | xlib_app,
@@ -168,14 +180,31 @@ impl Cx {
             XlibEvent::TextInput(e) => {
                 self.call_event_handler(&Event::TextInput(e))
             }
+            XlibEvent::TextComposition(e) => {
+                self.call_event_handler(&Event::TextComposition(e))
+            }
             XlibEvent::Drag(e) => {
-                self.call_event_handler(&Event::Drag(e))
+                self.call_event_handler(&Event::Drag(e));
+                self.drag_drop.cycle_drag();
             }
             XlibEvent::Drop(e) => {
-                self.call_event_handler(&Event::Drop(e))
+                self.call_event_handler(&Event::Drop(e));
+                self.drag_drop.cycle_drag();
             }
             XlibEvent::DragEnd => {
-                self.call_event_handler(&Event::DragEnd)
+                // the pointer grab of the drag swallowed the mouse up
+                self.call_event_handler(&Event::MouseUp(MouseUpEvent {
+                    abs: dvec2(-100000.0, -100000.0),
+                    button: 0,
+                    window_id: CxWindowPool::id_zero(),
+                    modifiers: Default::default(),
+                    time: 0.0
+                }));
+                self.fingers.mouse_up(0);
+                self.fingers.cycle_hover_area(live_id!(mouse).into());
+                
+                self.call_event_handler(&Event::DragEnd);
+                self.drag_drop.cycle_drag();
             }
             XlibEvent::KeyDown(e) => {
                 self.keyboard.process_key_down(e.clone());
@@ -198,6 +227,8 @@ impl Cx {
                         self.handle_media_signals();
                         self.call_event_handler(&Event::Signal);
                     }
+                    self.handle_networking_events();
+                    self.handle_file_dialog_responses();
                 }
                 else{
                     self.call_event_handler(&Event::Timer(e))
@@ -214,6 +245,22 @@ impl Cx {
     }
 
     pub(crate) fn handle_networking_events(&mut self) {
+        let mut out = Vec::new();
+        while let Ok(event) = self.os.network_response.receiver.try_recv() {
+            out.push(event);
+        }
+        if out.len()>0 {
+            self.call_event_handler(&Event::NetworkResponses(out))
+        }
+    }
+    
+    fn handle_file_dialog_responses(&mut self) {
+        while let Ok(response) = self.os.file_dialogs.receiver.try_recv() {
+            match response {
+                PortalResponse::Done(e) => self.call_event_handler(&Event::FileDialog(e)),
+                PortalResponse::Unavailable(e) => self.call_event_handler(&Event::FileDialogFallback(e)),
+            }
+        }
     }
     
     pub (crate) fn handle_repaint(&mut self, opengl_windows: &mut Vec<OpenglWindow>) {
@@ -304,11 +351,20 @@ impl Cx {
                 CxOsOp::XrStopPresenting => {
                     //todo!()
                 },
-                CxOsOp::ShowTextIME(_area, _pos) => {
-                    //todo!()
+                CxOsOp::ShowTextIME(area, pos) => {
+                    let pos = area.clipped_rect(self).pos + pos;
+                    opengl_windows.iter_mut().for_each( | w | {
+                        w.xlib_window.set_ime_spot(pos);
+                    });
                 }
                 CxOsOp::HideTextIME => {
-                    //todo!()
+                    let mut had_composition = false;
+                    opengl_windows.iter_mut().for_each( | w | {
+                        had_composition |= w.xlib_window.reset_ime();
+                    });
+                    if had_composition {
+                        self.call_event_handler(&Event::TextComposition(TextCompositionEvent::default()));
+                    }
                 },
                 CxOsOp::SetCursor(cursor) => {
                     xlib_app.set_mouse_cursor(cursor);
@@ -319,12 +375,13 @@ impl Cx {
                 CxOsOp::StopTimer(timer_id) => {
                     xlib_app.stop_timer(timer_id);
                 },
-                CxOsOp::StartDragging(_dragged_item) => {
+                CxOsOp::StartDragging(items) => {
+                    xlib_app.start_dragging(items);
                 },
                 CxOsOp::UpdateMacosMenu(_menu) => {
                 },
-                CxOsOp::HttpRequest{request_id:_, request:_} => {
-                    todo!()
+                CxOsOp::HttpRequest{request_id, request} => {
+                    make_http_request(request_id, request, self.os.network_response.sender.clone());
                 },
                 CxOsOp::PrepareVideoPlayback(_, _, _, _, _) => todo!(),
                 CxOsOp::BeginVideoPlayback(_) => todo!(),
@@ -335,10 +392,18 @@ impl Cx {
                 CxOsOp::CleanupVideoPlaybackResources(_) => todo!(),
                 CxOsOp::UpdateVideoSurfaceTexture(_) => todo!(),
 
-                CxOsOp::SaveFileDialog(_) => todo!(),
-                CxOsOp::SelectFileDialog(_) => todo!(),
-                CxOsOp::SaveFolderDialog(_) => todo!(),
-                CxOsOp::SelectFolderDialog(_) => todo!(),
+                CxOsOp::SaveFileDialog(dialog_id, dialog) => {
+                    open_portal_file_dialog(dialog_id, FileDialogKind::SaveFile, dialog, self.os.file_dialogs.sender.clone());
+                }
+                CxOsOp::SelectFileDialog(dialog_id, dialog) => {
+                    open_portal_file_dialog(dialog_id, FileDialogKind::SelectFile, dialog, self.os.file_dialogs.sender.clone());
+                }
+                CxOsOp::SaveFolderDialog(dialog_id, dialog) => {
+                    open_portal_file_dialog(dialog_id, FileDialogKind::SaveFolder, dialog, self.os.file_dialogs.sender.clone());
+                }
+                CxOsOp::SelectFolderDialog(dialog_id, dialog) => {
+                    open_portal_file_dialog(dialog_id, FileDialogKind::SelectFolder, dialog, self.os.file_dialogs.sender.clone());
+                }
             }
         }
         ret
@@ -361,6 +426,9 @@ impl CxOsApi for Cx {
 pub struct CxOs {
     pub(crate) media: CxLinuxMedia,
     pub (crate) stdin_timers: PollTimers,
+    pub (crate) network_response: NetworkResponseChannel,
+    pub (crate) file_dialogs: PortalChannel,
+    pub (crate) software: CxSoftware,
 
     // HACK(eddyb) generalize this to EGL, properly.
     pub(super) opengl_cx: Option<OpenglCx>,
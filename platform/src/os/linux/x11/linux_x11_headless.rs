@@ -0,0 +1,216 @@
+use {
+    std::{
+        io::prelude::*,
+        path::PathBuf,
+        time::{Duration, Instant},
+    },
+    crate::{
+        makepad_math::*,
+        makepad_image_formats::png,
+        event::{Event, WindowGeom, WindowGeomChangeEvent},
+        thread::SignalToUI,
+        os::cx_stdin::{HostToStdin, PollTimer, PresentableDraw, StdinToHost, Swapchain},
+        pass::{CxPassParent, PassId},
+        window::CxWindowPool,
+        cx_api::CxOsOp,
+        cx::Cx,
+        os::linux::http::make_http_request,
+    }
+};
+
+impl Cx {
+
+    /// Runs the app without a display server, every pass is rendered by the software rasterizer
+    pub fn headless_event_loop(&mut self) {
+        self.headless_start();
+        let start = Instant::now();
+        while self.headless_step(start.elapsed().as_secs_f64()) {
+            std::thread::sleep(Duration::from_millis(8));
+        }
+    }
+
+    /// Sends the startup event, after this the loop can be driven with headless_step
+    pub fn headless_start(&mut self) {
+        self.call_event_handler(&Event::Startup);
+        self.redraw_all();
+    }
+
+    /// The stdin protocol without a gpu: instead of drawing into the host's swapchain every
+    /// presented frame is written as a png into the directory given with
+    /// `--stdin-loop-frame-dir=`, named after the presentable image id in its hex form.
+    pub fn headless_stdin_event_loop(&mut self) {
+        let frame_dir = std::env::args()
+            .find_map( | arg | arg.strip_prefix("--stdin-loop-frame-dir=").map(PathBuf::from))
+            .unwrap_or_else( || std::env::temp_dir().join(format!("makepad_stdin_frames_{}", std::process::id())));
+        if let Err(err) = std::fs::create_dir_all(&frame_dir) {
+            crate::error!("cannot create frame directory {}: {}", frame_dir.display(), err);
+            return
+        }
+        let json_msg_rx = Self::stdin_spawn_reader();
+        let _ = std::io::stdout().write_all(StdinToHost::ReadyToStart.to_json().as_bytes());
+
+        // only the image ids are used, they name the frame files
+        let swapchain = Swapchain::new(0, 0);
+        let mut present_index = 0;
+        let mut window_passes = Vec::new();
+
+        self.headless_start();
+
+        while let Ok(msg) = json_msg_rx.recv() {
+            match msg {
+                HostToStdin::Swapchain(_) => (),
+                HostToStdin::WindowGeomChange {dpi_factor, inner_width, inner_height} => {
+                    let window_id = CxWindowPool::id_zero();
+                    let old_geom = self.windows[window_id].window_geom.clone();
+                    let new_geom = WindowGeom {
+                        dpi_factor,
+                        inner_size: dvec2(inner_width, inner_height),
+                        outer_size: dvec2(inner_width, inner_height),
+                        ..old_geom.clone()
+                    };
+                    self.windows[window_id].window_geom = new_geom.clone();
+                    self.call_event_handler(&Event::WindowGeomChange(WindowGeomChangeEvent {window_id, old_geom, new_geom}));
+                    self.redraw_all();
+                }
+                HostToStdin::Tick {time, ..} => {
+                    window_passes.clear();
+                    let running = self.headless_step_passes(time, &mut window_passes);
+                    for &pass_id in &window_passes {
+                        let image = &swapchain.presentable_images[present_index];
+                        present_index = (present_index + 1) % swapchain.presentable_images.len();
+                        let pixels = self.software_pass_texture(pass_id).and_then( | texture | texture.read_pixels(self));
+                        let pixels = if let Some(pixels) = pixels {pixels} else {continue};
+                        let path = frame_dir.join(format!("{:016x}.png", image.id.as_u64()));
+                        let written = png::encode(&pixels).and_then( | data | std::fs::write(&path, data).map_err( | e | e.to_string()));
+                        if let Err(err) = written {
+                            crate::error!("cannot write frame {}: {}", path.display(), err);
+                            continue
+                        }
+                        let presentable_draw = PresentableDraw {
+                            target_id: image.id,
+                            width: pixels.width as u32,
+                            height: pixels.height as u32,
+                        };
+                        let _ = std::io::stdout().write_all(StdinToHost::DrawCompleteAndFlip(presentable_draw).to_json().as_bytes());
+                    }
+                    if !running {
+                        return
+                    }
+                }
+                msg => self.stdin_handle_input(msg)
+            }
+        }
+    }
+
+    /// Runs one iteration of the headless loop, returns false when the app wants to quit
+    pub fn headless_step(&mut self, time: f64) -> bool {
+        self.headless_step_passes(time, &mut Vec::new())
+    }
+
+    /// Like headless_step, also collects the window passes that were drawn
+    fn headless_step_passes(&mut self, time: f64, window_passes: &mut Vec<PassId>) -> bool {
+        if SignalToUI::check_and_clear_ui_signal() {
+            self.handle_media_signals();
+            self.call_event_handler(&Event::Signal);
+        }
+        for event in self.os.stdin_timers.get_dispatch() {
+            self.call_event_handler(&event);
+        }
+        if self.handle_live_edit() {
+            self.call_event_handler(&Event::LiveEdit);
+            self.redraw_all();
+        }
+        self.handle_networking_events();
+
+        if !self.headless_handle_platform_ops() {
+            return false
+        }
+
+        if self.new_next_frames.len() != 0 {
+            self.call_next_frame_event(time);
+        }
+        if self.need_redrawing() {
+            self.call_draw_event();
+            self.software_compile_shaders();
+        }
+        // platform ops issued during draw (like window creation) have to land before we paint
+        if !self.headless_handle_platform_ops() {
+            return false
+        }
+        self.headless_handle_repaint(window_passes);
+        true
+    }
+
+    fn headless_handle_repaint(&mut self, window_passes: &mut Vec<PassId>) {
+        let mut passes_todo = Vec::new();
+        self.compute_pass_repaint_order(&mut passes_todo);
+        self.repaint_id += 1;
+        for &pass_id in &passes_todo {
+            match self.passes[pass_id].parent.clone() {
+                CxPassParent::Window(window_id) => {
+                    if self.windows[window_id].is_created {
+                        self.software_draw_pass(pass_id);
+                        window_passes.push(pass_id);
+                    }
+                }
+                CxPassParent::Pass(_) | CxPassParent::None => {
+                    self.software_draw_pass(pass_id);
+                }
+            }
+        }
+    }
+
+    fn headless_handle_platform_ops(&mut self) -> bool {
+        let mut running = true;
+        while let Some(op) = self.platform_ops.pop() {
+            match op {
+                CxOsOp::CreateWindow(window_id) => {
+                    let window = &mut self.windows[window_id];
+                    let old_geom = window.window_geom.clone();
+                    // a stdin host may have sent the window size before the window got created
+                    let (inner_size, dpi_factor) = if old_geom.inner_size != dvec2(0., 0.) {
+                        (old_geom.inner_size, old_geom.dpi_factor)
+                    }
+                    else {
+                        (window.create_inner_size.unwrap_or(dvec2(800., 600.)), window.dpi_override.unwrap_or(1.0))
+                    };
+                    window.window_geom = WindowGeom {
+                        dpi_factor,
+                        position: window.create_position.unwrap_or(dvec2(0., 0.)),
+                        inner_size,
+                        outer_size: inner_size,
+                        ..Default::default()
+                    };
+                    window.is_created = true;
+                    let new_geom = window.window_geom.clone();
+                    self.call_event_handler(&Event::WindowGeomChange(WindowGeomChangeEvent {
+                        window_id,
+                        old_geom,
+                        new_geom,
+                    }));
+                    self.redraw_all();
+                },
+                CxOsOp::CloseWindow(window_id) => {
+                    self.windows[window_id].is_created = false;
+                    if self.windows.iter().all( | w | !w.is_created) {
+                        running = false
+                    }
+                },
+                CxOsOp::Quit => {
+                    running = false
+                },
+                CxOsOp::StartTimer {timer_id, interval, repeats} => {
+                    self.os.stdin_timers.timers.insert(timer_id, PollTimer::new(interval, repeats));
+                },
+                CxOsOp::StopTimer(timer_id) => {
+                    self.os.stdin_timers.timers.remove(&timer_id);
+                },
+                CxOsOp::HttpRequest {request_id, request} => {
+                    make_http_request(request_id, request, self.os.network_response.sender.clone());
+                },
+                _ => ()
+            }
+        }
+        running
+    }
+}
@@ -3,6 +3,7 @@ use {
         io,
         io::prelude::*,
         io::BufReader,
+        sync::mpsc::Receiver,
     },
     crate::{
         makepad_live_id::*,
@@ -19,11 +20,89 @@ use {
         cx_api::CxOsOp,
         cx::Cx,
         gl_sys,
+        os::linux::http::make_http_request,
     } 
 };
 
 impl Cx {
     
+    /// Parses the host messages on stdin on a separate thread, one json message per line
+    pub (crate) fn stdin_spawn_reader() -> Receiver<HostToStdin> {
+        let (json_msg_tx, json_msg_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(std::io::stdin().lock());
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if let Ok(0) | Err(_) = reader.read_line(&mut line) {
+                    break;
+                }
+
+                // alright lets put the line in a json parser
+                match HostToStdin::deserialize_json(&line) {
+                    Ok(msg) => {
+                        if json_msg_tx.send(msg).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        // we should output a log string
+                        crate::error!("Cant parse stdin-JSON {} {:?}", line, err)
+                    }
+                }
+            }
+        });
+        json_msg_rx
+    }
+    
+    /// Handles the input and file reload messages, these work the same with or without a gpu
+    pub (crate) fn stdin_handle_input(&mut self, msg: HostToStdin) {
+        match msg {
+            HostToStdin::ReloadFile{file, contents}=>{
+                // alright lets reload this file in our DSL system
+                let _ = self.live_file_change_sender.send(vec![LiveFileChange{
+                    file_name: file,
+                    content: contents
+                }]);
+            }
+            HostToStdin::KeyDown(e) => {
+                self.call_event_handler(&Event::KeyDown(e));
+            }
+            HostToStdin::KeyUp(e) => {
+                self.call_event_handler(&Event::KeyUp(e));
+            }
+            HostToStdin::TextInput(e) => {
+                self.call_event_handler(&Event::TextInput(e));
+            }
+            HostToStdin::MouseDown(e) => {
+                self.fingers.process_tap_count(
+                    dvec2(e.x,e.y),
+                    e.time
+                );
+                self.fingers.mouse_down(e.button);
+
+                self.call_event_handler(&Event::MouseDown(e.into()));
+            }
+            HostToStdin::MouseMove(e) => {
+                self.call_event_handler(&Event::MouseMove(e.into()));
+                self.fingers.cycle_hover_area(live_id!(mouse).into());
+                self.fingers.switch_captures();
+            }
+            HostToStdin::MouseUp(e) => {
+                let button = e.button;
+                self.call_event_handler(&Event::MouseUp(e.into()));
+                self.fingers.mouse_up(button);
+                self.fingers.cycle_hover_area(live_id!(mouse).into());
+            }
+            HostToStdin::Scroll(e) => {
+                self.call_event_handler(&Event::Scroll(e.into()))
+            }
+            HostToStdin::Swapchain(_) |
+            HostToStdin::WindowGeomChange {..} |
+            HostToStdin::Tick {..} => ()
+        }
+    }
+    
     pub (crate) fn stdin_handle_repaint(
         &mut self,
         swapchain: Option<&Swapchain<Texture>>,
@@ -77,32 +156,7 @@ impl Cx {
                 .expect("failed to acquire auxiliary channel");
 
 
-        let (json_msg_tx, json_msg_rx) = std::sync::mpsc::channel();
-        {
-            std::thread::spawn(move || {
-                let mut reader = BufReader::new(std::io::stdin().lock());
-                let mut line = String::new();
-                loop {
-                    line.clear();
-                    if let Ok(0) | Err(_) = reader.read_line(&mut line) {
-                        break;
-                    }
-
-                    // alright lets put the line in a json parser
-                    match HostToStdin::deserialize_json(&line) {
-                        Ok(msg) => {
-                            if json_msg_tx.send(msg).is_err() {
-                                break;
-                            }
-                        }
-                        Err(err) => {
-                            // we should output a log string
-                            crate::error!("Cant parse stdin-JSON {} {:?}", line, err)
-                        }
-                    }
-                }
-            });
-        }
+        let json_msg_rx = Self::stdin_spawn_reader();
 
         let _ = io::stdout().write_all(StdinToHost::ReadyToStart.to_json().as_bytes());
 
@@ -113,45 +167,6 @@ impl Cx {
 
         while let Ok(msg) = json_msg_rx.recv(){
             match msg {
-                HostToStdin::ReloadFile{file, contents}=>{
-                    // alright lets reload this file in our DSL system
-                    let _ = self.live_file_change_sender.send(vec![LiveFileChange{
-                        file_name: file,
-                        content: contents
-                    }]);
-                }
-                HostToStdin::KeyDown(e) => {
-                    self.call_event_handler(&Event::KeyDown(e));
-                }
-                HostToStdin::KeyUp(e) => {
-                    self.call_event_handler(&Event::KeyUp(e));
-                }
-                HostToStdin::TextInput(e) => {
-                    self.call_event_handler(&Event::TextInput(e));
-                }
-                HostToStdin::MouseDown(e) => {
-                    self.fingers.process_tap_count(
-                        dvec2(e.x,e.y),
-                        e.time
-                    );
-                    self.fingers.mouse_down(e.button);
-
-                    self.call_event_handler(&Event::MouseDown(e.into()));
-                }
-                HostToStdin::MouseMove(e) => {
-                    self.call_event_handler(&Event::MouseMove(e.into()));
-                    self.fingers.cycle_hover_area(live_id!(mouse).into());
-                    self.fingers.switch_captures();
-                }
-                HostToStdin::MouseUp(e) => {
-                    let button = e.button;
-                    self.call_event_handler(&Event::MouseUp(e.into()));
-                    self.fingers.mouse_up(button);
-                    self.fingers.cycle_hover_area(live_id!(mouse).into());
-                }
-                HostToStdin::Scroll(e) => {
-                    self.call_event_handler(&Event::Scroll(e.into()))
-                }
                 HostToStdin::WindowGeomChange { dpi_factor, inner_width, inner_height } => {
                     self.windows[CxWindowPool::id_zero()].window_geom = WindowGeom {
                         dpi_factor,
@@ -226,6 +241,7 @@ impl Cx {
 
                     self.stdin_handle_repaint(swapchain.as_ref(), &mut present_index);
                 }
+                msg => self.stdin_handle_input(msg)
             }
         }
     }
@@ -263,6 +279,9 @@ impl Cx {
                 CxOsOp::StopTimer(timer_id) => {
                     self.os.stdin_timers.timers.remove(&timer_id);
                 },
+                CxOsOp::HttpRequest{request_id, request} => {
+                    make_http_request(request_id, request, self.os.network_response.sender.clone());
+                },
                 _ => ()
                 /*
                 CxOsOp::CloseWindow(_window_id) => {},
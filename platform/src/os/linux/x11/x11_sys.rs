@@ -9,6 +9,7 @@ use std::os::raw::{
     c_void,
     c_char,
     c_uchar,
+    c_ushort,
 };
 
 
@@ -29,6 +30,10 @@ pub type XEvent = _XEvent;
 pub type XIC = *mut _XIC;
 pub type XExtData = _XExtData;
 pub type XPointer = *mut c_char;
+pub type XIMStyle = c_ulong;
+pub type XIMFeedback = c_ulong;
+pub type XIMProc = Option<unsafe extern "C" fn(XIC, XPointer, XPointer) -> c_int>;
+pub type XVaNestedList = *mut c_void;
 pub type VisualID = c_ulong;
 pub type XrmDatabase = *mut _XrmHashBucketRec;
 pub type XKeyPressedEvent = XKeyEvent;
@@ -81,13 +86,35 @@ pub const FocusChangeMask: u32 = 2097152;
 pub const EnterWindowMask: u32 = 16;
 pub const LeaveWindowMask: u32 = 32;
 pub const XBufferOverflow: i32 = -1;
-
+pub const GrabModeAsync: c_int = 1;
+pub const GrabSuccess: c_int = 0;
+
+pub const XLookupChars: i32 = 2;
+pub const XLookupBoth: i32 = 4;
+pub const FocusIn: u32 = 9;
+pub const FocusOut: u32 = 10;
+
+pub const XIMForwardChar: c_int = 0;
+pub const XIMBackwardChar: c_int = 1;
+pub const XIMLineStart: c_int = 8;
+pub const XIMLineEnd: c_int = 9;
+pub const XIMAbsolutePosition: c_int = 10;
+
+pub const XIMPreeditCallbacks: u32 = 2;
+pub const XIMPreeditPosition: u32 = 4;
 pub const XIMPreeditNothing: u32 = 8;
 pub const XIMStatusNothing: u32 = 1024;
 
 pub const XNInputStyle: &'static [u8; 11usize] = b"inputStyle\0";
 pub const XNClientWindow: &'static [u8; 13usize] = b"clientWindow\0";
 pub const XNFocusWindow: &'static [u8; 12usize] = b"focusWindow\0";
+pub const XNQueryInputStyle: &'static [u8; 16usize] = b"queryInputStyle\0";
+pub const XNPreeditAttributes: &'static [u8; 18usize] = b"preeditAttributes\0";
+pub const XNSpotLocation: &'static [u8; 13usize] = b"spotLocation\0";
+pub const XNPreeditStartCallback: &'static [u8; 21usize] = b"preeditStartCallback\0";
+pub const XNPreeditDoneCallback: &'static [u8; 20usize] = b"preeditDoneCallback\0";
+pub const XNPreeditDrawCallback: &'static [u8; 20usize] = b"preeditDrawCallback\0";
+pub const XNPreeditCaretCallback: &'static [u8; 21usize] = b"preeditCaretCallback\0";
 
 pub const Mod1Mask: u32 = 8;
 pub const ShiftMask: u32 = 1;
@@ -353,6 +380,26 @@ extern "C" {
     
     pub fn XCreateIC(arg1: XIM, ...) -> XIC;
     
+    pub fn XDestroyIC(arg1: XIC);
+    
+    pub fn XGetIMValues(arg1: XIM, ...) -> *mut c_char;
+    
+    pub fn XSetICValues(arg1: XIC, ...) -> *mut c_char;
+    
+    pub fn XVaCreateNestedList(arg1: c_int, ...) -> XVaNestedList;
+    
+    pub fn XSetICFocus(arg1: XIC);
+    
+    pub fn XUnsetICFocus(arg1: XIC);
+    
+    pub fn Xutf8ResetIC(arg1: XIC) -> *mut c_char;
+    
+    pub fn XFilterEvent(arg1: *mut XEvent, arg2: Window) -> c_int;
+    
+    pub fn XSetLocaleModifiers(arg1: *const c_char) -> *mut c_char;
+    
+    pub fn XSupportsLocale() -> c_int;
+    
     pub fn XDestroyWindow(arg1: *mut Display, arg2: Window) -> c_int;
     
     pub fn XIconifyWindow(
@@ -397,6 +444,43 @@ extern "C" {
     
     pub fn XUngrabPointer(arg1: *mut Display, arg2: Time) -> c_int;
     
+    pub fn XGrabPointer(
+        arg1: *mut Display,
+        arg2: Window,
+        arg3: c_int,
+        arg4: c_uint,
+        arg5: c_int,
+        arg6: c_int,
+        arg7: Window,
+        arg8: Cursor,
+        arg9: Time,
+    ) -> c_int;
+    
+    pub fn XTranslateCoordinates(
+        arg1: *mut Display,
+        arg2: Window,
+        arg3: Window,
+        arg4: c_int,
+        arg5: c_int,
+        arg6: *mut c_int,
+        arg7: *mut c_int,
+        arg8: *mut Window,
+    ) -> c_int;
+    
+    pub fn XQueryPointer(
+        arg1: *mut Display,
+        arg2: Window,
+        arg3: *mut Window,
+        arg4: *mut Window,
+        arg5: *mut c_int,
+        arg6: *mut c_int,
+        arg7: *mut c_int,
+        arg8: *mut c_int,
+        arg9: *mut c_uint,
+    ) -> c_int;
+    
+    pub fn XDeleteProperty(arg1: *mut Display, arg2: Window, arg3: Atom) -> c_int;
+    
     pub fn XSetSelectionOwner(
         arg1: *mut Display,
         arg2: Atom,
@@ -448,6 +532,60 @@ pub struct _XIC {
     _unused: [u8; 0],
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XIMStyles {
+    pub count_styles: c_ushort,
+    pub supported_styles: *mut XIMStyle,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XIMCallback {
+    pub client_data: XPointer,
+    pub callback: XIMProc,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union XIMTextString {
+    pub multi_byte: *mut c_char,
+    pub wide_char: *mut c_int,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct XIMText {
+    pub length: c_ushort,
+    pub feedback: *mut XIMFeedback,
+    pub encoding_is_wchar: c_int,
+    pub string: XIMTextString,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XIMPreeditDrawCallbackStruct {
+    pub caret: c_int,
+    pub chg_first: c_int,
+    pub chg_length: c_int,
+    pub text: *mut XIMText,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XIMPreeditCaretCallbackStruct {
+    pub position: c_int,
+    pub direction: c_int,
+    pub style: c_int,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XPoint {
+    pub x: c_short,
+    pub y: c_short,
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct _XComposeStatus {
@@ -11,7 +11,9 @@ use {
         x11_sys,
         xlib_event::XlibEvent,
         xlib_window::*,
+        xlib_dnd::Dnd,
         super::select_timer::SelectTimers,
+        super::libc_sys,
     },
     crate::{
         makepad_math::DVec2,
@@ -50,6 +52,7 @@ pub struct XlibApp {
     pub last_scroll_time: f64,
     pub last_click_time: f64,
     pub last_click_pos: (i32, i32),
+    pub last_click_window: c_ulong,
     pub event_callback: Option<Box<dyn FnMut(&mut XlibApp, XlibEvent) -> EventFlow >>,
     //pub free_timers: Vec<usize>,
     pub event_flow: EventFlow,
@@ -64,7 +67,7 @@ impl XlibApp {
         unsafe {
             let display = x11_sys::XOpenDisplay(ptr::null());
             let display_fd = x11_sys::XConnectionNumber(display);
-            let xim = x11_sys::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+            let xim = Self::open_im(display);
             //let mut signal_fds = [0, 0];
             //libc_sys::pipe(signal_fds.as_mut_ptr());
             x11_sys::XrmInitialize();
@@ -80,6 +83,7 @@ impl XlibApp {
                 last_scroll_time: 0.0,
                 last_click_time: 0.0,
                 last_click_pos: (0, 0),
+                last_click_window: 0,
                 window_map: HashMap::new(),
                 timers: SelectTimers::new(),
                 event_flow: EventFlow::Poll,
@@ -91,6 +95,19 @@ impl XlibApp {
         }
     }
     
+    unsafe fn open_im(display: *mut x11_sys::Display) -> x11_sys::XIM {
+        // XIM picks the input method and text encoding from the locale, which Rust leaves at "C"
+        libc_sys::setlocale(libc_sys::LC_CTYPE, "\0".as_ptr() as *const c_char);
+        x11_sys::XSetLocaleModifiers("\0".as_ptr() as *const c_char);
+        let xim = x11_sys::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+        if !xim.is_null() {
+            return xim
+        }
+        // no (reachable) input method server, fall back to the builtin compose handling
+        x11_sys::XSetLocaleModifiers("@im=none\0".as_ptr() as *const c_char);
+        x11_sys::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut())
+    }
+    
     pub unsafe fn event_loop_poll(&mut self) {
         // Update the current time, and compute the amount of time that elapsed since we
         // last recorded the current time.
@@ -98,11 +115,15 @@ impl XlibApp {
             let mut event = mem::MaybeUninit::uninit();
             x11_sys::XNextEvent(self.display, event.as_mut_ptr());
             let mut event = event.assume_init();
+            // let the input method consume the keys that are part of a composition
+            if x11_sys::XFilterEvent(&mut event, 0) != 0 {
+                continue;
+            }
             match event.type_ as u32 {
                 x11_sys::SelectionNotify => {
                     let selection = event.xselection;
-                    if selection.property == self.dnd.atoms.selection {
-                        self.dnd.handle_selection_event(&selection);
+                    if selection.selection == self.dnd.atoms.selection {
+                        self.handle_dnd_selection_notify(&selection);
                     } else {
                         // first get the size of the thing
                         let mut actual_type = mem::MaybeUninit::uninit();
@@ -161,6 +182,9 @@ impl XlibApp {
                 },
                 x11_sys::SelectionRequest => {
                     let request = event.xselectionrequest;
+                    if self.handle_dnd_selection_request(&request) {
+                        continue;
+                    }
                     let mut response = x11_sys::XSelectionEvent {
                         type_: x11_sys::SelectionNotify as i32,
                         serial: 0,
@@ -246,6 +270,9 @@ impl XlibApp {
                 },
                 x11_sys::MotionNotify => { // mousemove
                     let motion = event.xmotion;
+                    if self.handle_dnd_motion(&motion) {
+                        continue;
+                    }
                     if let Some(window_ptr) = self.window_map.get(&motion.window) {
                         let window = &mut (**window_ptr);
                         let x = motion.x;
@@ -408,15 +435,23 @@ impl XlibApp {
                     }
                     self.last_click_time = time_now;
                     self.last_click_pos = (button.x_root, button.y_root);
+                    self.last_click_window = button.window;
                 },
                 x11_sys::ButtonRelease => { // mouse up
                     let button = event.xbutton;
+                    if self.handle_dnd_button_release(&button) {
+                        continue;
+                    }
                     if let Some(window_ptr) = self.window_map.get(&button.window) {
                         let window = &mut (**window_ptr);
                         window.send_mouse_up(button.button as usize, self.xkeystate_to_modifiers(button.state))
                     }
                 },
                 x11_sys::KeyPress => {
+                    if self.dnd.is_dragging() && self.xkeyevent_to_keycode(&mut event.xkey) == KeyCode::Escape {
+                        self.cancel_dragging();
+                        continue;
+                    }
                     if let Some(window_ptr) = self.window_map.get(&event.xkey.window) {
                         let window = &mut (**window_ptr);
                         let block_text = if event.xkey.keycode != 0 {
@@ -480,34 +515,75 @@ impl XlibApp {
                         }else {false};
                         
                         if !block_text {
-                            // decode the character
-                            let mut buffer = [0u8; 32];
-                            let mut keysym = mem::MaybeUninit::uninit();
-                            let mut status = mem::MaybeUninit::uninit();
-                            let count = x11_sys::Xutf8LookupString(
-                                window.xic.unwrap(),
-                                &mut event.xkey,
-                                buffer.as_mut_ptr() as *mut c_char,
-                                buffer.len() as c_int,
-                                keysym.as_mut_ptr(),
-                                status.as_mut_ptr(),
-                            );
-                            //let keysym = keysym.assume_init();
-                            let status = status.assume_init();
-                            if status != x11_sys::XBufferOverflow {
-                                let utf8 = std::str::from_utf8(&buffer[..count as usize]).unwrap_or("").to_string();
-                                let char_code = utf8.chars().next().unwrap_or('\0');
-                                if char_code >= ' ' && char_code != 127 as char {
-                                    self.do_callback(XlibEvent::TextInput(TextInputEvent {
-                                        input: utf8,
-                                        was_paste: false,
-                                        replace_last: false
-                                    }));
+                            // decode the character, or the text committed by the input method
+                            let utf8 = if let Some(xic) = window.xic {
+                                let mut buffer = vec![0u8; 32];
+                                let mut keysym = mem::MaybeUninit::uninit();
+                                let mut status = mem::MaybeUninit::uninit();
+                                let mut count = x11_sys::Xutf8LookupString(
+                                    xic,
+                                    &mut event.xkey,
+                                    buffer.as_mut_ptr() as *mut c_char,
+                                    buffer.len() as c_int,
+                                    keysym.as_mut_ptr(),
+                                    status.as_mut_ptr(),
+                                );
+                                if status.assume_init() == x11_sys::XBufferOverflow {
+                                    // count is the size the commit needs
+                                    buffer.resize(count as usize, 0);
+                                    count = x11_sys::Xutf8LookupString(
+                                        xic,
+                                        &mut event.xkey,
+                                        buffer.as_mut_ptr() as *mut c_char,
+                                        buffer.len() as c_int,
+                                        keysym.as_mut_ptr(),
+                                        status.as_mut_ptr(),
+                                    );
+                                }
+                                let status = status.assume_init();
+                                if status == x11_sys::XLookupChars || status == x11_sys::XLookupBoth {
+                                    // the commit replaces the composition, some input methods only send done after it
+                                    if window.preedit.commit() {
+                                        window.send_text_composition();
+                                    }
+                                    std::str::from_utf8(&buffer[..count as usize]).unwrap_or("").to_string()
                                 }
+                                else {
+                                    String::new()
+                                }
+                            }
+                            else {
+                                let mut buffer = [0u8; 32];
+                                let count = x11_sys::XLookupString(
+                                    &mut event.xkey,
+                                    buffer.as_mut_ptr() as *mut c_char,
+                                    buffer.len() as c_int,
+                                    ptr::null_mut(),
+                                    ptr::null_mut(),
+                                );
+                                std::str::from_utf8(&buffer[..count.max(0) as usize]).unwrap_or("").to_string()
+                            };
+                            let char_code = utf8.chars().next().unwrap_or('\0');
+                            if char_code >= ' ' && char_code != 127 as char {
+                                self.do_callback(XlibEvent::TextInput(TextInputEvent {
+                                    input: utf8,
+                                    was_paste: false,
+                                    replace_last: false
+                                }));
                             }
                         }
                     }
                 },
+                x11_sys::FocusIn => {
+                    if let Some(window_ptr) = self.window_map.get(&event.xfocus.window) {
+                        (**window_ptr).set_ime_focus(true);
+                    }
+                },
+                x11_sys::FocusOut => {
+                    if let Some(window_ptr) = self.window_map.get(&event.xfocus.window) {
+                        (**window_ptr).set_ime_focus(false);
+                    }
+                },
                 x11_sys::KeyRelease => {
                     self.do_callback(XlibEvent::KeyUp(KeyEvent {
                         key_code: self.xkeyevent_to_keycode(&mut event.xkey),
@@ -524,14 +600,8 @@ impl XlibApp {
                             window.close_window();
                         }
                     }
-                    if event.message_type == self.dnd.atoms.enter {
-                        self.dnd.handle_enter_event(&event);
-                    } else if event.message_type == self.dnd.atoms.drop {
-                        self.dnd.handle_drop_event(&event);
-                    } else if event.message_type == self.dnd.atoms.leave {
-                        self.dnd.handle_leave_event(&event);
-                    } else if event.message_type == self.dnd.atoms.position {
-                        self.dnd.handle_position_event(&event);
+                    else {
+                        self.handle_dnd_client_message(&event);
                     }
                 },
                 x11_sys::Expose => {
@@ -700,7 +770,7 @@ impl XlibApp {
         }
     }
 
-    fn xkeystate_to_modifiers(&self, state: c_uint) -> KeyModifiers {
+    pub fn xkeystate_to_modifiers(&self, state: c_uint) -> KeyModifiers {
         KeyModifiers {
             alt: state & x11_sys::Mod1Mask != 0,
             shift: state & x11_sys::ShiftMask != 0,
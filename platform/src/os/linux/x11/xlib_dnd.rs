@@ -0,0 +1,829 @@
+use {
+    std::{
+        mem,
+        cell::Cell,
+        rc::Rc,
+        os::raw::{c_int, c_long, c_uchar, c_uint, c_ulong, c_void},
+        ptr,
+    },
+    self::super::{
+        x11_sys,
+        xlib_app::XlibApp,
+        xlib_event::XlibEvent,
+        super::xdg_portal::file_uri_to_path,
+    },
+    crate::{
+        makepad_math::{dvec2, DVec2},
+        event::{DragEvent, DragItem, DragResponse, DropEvent, KeyModifiers},
+    },
+};
+
+// XDND drag and drop (https://freedesktop.org/wiki/Specifications/XDND/).
+// Incoming drags fetch the data as soon as the source enters, so Drag events can carry the
+// items like on the other platforms. Outgoing drags over our own windows skip the protocol
+// and hand the items over directly, which keeps their internal_id intact.
+
+const XDND_VERSION: c_long = 5;
+const XA_ATOM: x11_sys::Atom = 4;
+
+// where a drag that left the window is reported, so the last drag area sees DragState::Out
+const DRAG_OUT_POS: DVec2 = DVec2 {x: -100000.0, y: -100000.0};
+
+pub struct Dnd {
+    pub atoms: DndAtoms,
+    pub display: *mut x11_sys::Display,
+    incoming: Option<DndIncoming>,
+    outgoing: Option<DndOutgoing>,
+}
+
+struct DndIncoming {
+    source: x11_sys::Window,
+    target: x11_sys::Window,
+    data_type: x11_sys::Atom,
+    requested: bool,
+    items: Option<Rc<Vec<DragItem >>>,
+    pos: DVec2,
+    // the action the source asked for, and what the app answered
+    proposed: DragResponse,
+    response: DragResponse,
+    // the drop arrived before the data did
+    dropped: bool,
+}
+
+struct DndOutgoing {
+    source: x11_sys::Window,
+    items: Rc<Vec<DragItem >>,
+    types: Vec<x11_sys::Atom>,
+    target: Option<DndTarget>,
+    // XdndDrop was sent, we keep serving the selection until XdndFinished
+    dropped: bool,
+}
+
+struct DndTarget {
+    window: x11_sys::Window,
+    internal: bool,
+    accepted: bool,
+    waiting_for_status: bool,
+    pending_position: Option<[c_long; 5]>,
+}
+
+impl Dnd {
+    pub unsafe fn new(display: *mut x11_sys::Display) -> Dnd {
+        Dnd {
+            atoms: DndAtoms::new(display),
+            display,
+            incoming: None,
+            outgoing: None,
+        }
+    }
+
+    /// Enables drag-and-drop for the given window.
+    pub unsafe fn enable_for_window(&mut self, window: x11_sys::Window) {
+        // To enable drag-and-drop for a window, we need to set the XDndAware property of the window
+        // to the version of XDnd we support.
+        let version = XDND_VERSION as c_ulong;
+        x11_sys::XChangeProperty(
+            self.display,
+            window,
+            self.atoms.aware,
+            XA_ATOM,
+            32,
+            x11_sys::PropModeReplace as c_int,
+            &version as *const c_ulong as *const c_uchar,
+            1
+        );
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.outgoing.as_ref().map_or(false, | outgoing | !outgoing.dropped)
+    }
+
+    /// Reads a whole property, 32 bit items are returned as c_ulongs like Xlib does.
+    unsafe fn read_property(&self, window: x11_sys::Window, property: x11_sys::Atom, req_type: x11_sys::Atom, delete: bool) -> Option<Vec<u8 >> {
+        let mut data = Vec::new();
+        let mut offset = 0;
+        loop {
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut nitems = 0;
+            let mut bytes_after = 0;
+            let mut prop = ptr::null_mut();
+            x11_sys::XGetWindowProperty(
+                self.display,
+                window,
+                property,
+                offset,
+                1024,
+                delete as c_int,
+                req_type,
+                &mut actual_type,
+                &mut actual_format,
+                &mut nitems,
+                &mut bytes_after,
+                &mut prop,
+            );
+            if prop.is_null() || actual_type == 0 {
+                return None
+            }
+            let item_size = if actual_format == 32 {mem::size_of::<c_ulong>()} else {actual_format as usize / 8};
+            data.extend_from_slice(std::slice::from_raw_parts(prop, nitems as usize * item_size));
+            x11_sys::XFree(prop as *mut c_void);
+            offset += (nitems as usize * actual_format as usize / 32) as c_long;
+            if bytes_after == 0 {
+                break;
+            }
+        }
+        Some(data)
+    }
+
+    unsafe fn read_atoms(&self, window: x11_sys::Window, property: x11_sys::Atom) -> Vec<x11_sys::Atom> {
+        self.read_property(window, property, XA_ATOM, false).map_or(Vec::new(), | data | {
+            data.chunks_exact(mem::size_of::<c_ulong>())
+                .map( | chunk | c_ulong::from_ne_bytes(chunk.try_into().unwrap()))
+                .collect()
+        })
+    }
+
+    fn response_to_action(&self, response: DragResponse) -> x11_sys::Atom {
+        match response {
+            DragResponse::None => self.atoms.none,
+            DragResponse::Copy => self.atoms.action_copy,
+            DragResponse::Link => self.atoms.action_link,
+            DragResponse::Move => self.atoms.action_move,
+        }
+    }
+
+    fn action_to_response(&self, action: x11_sys::Atom) -> DragResponse {
+        if action == self.atoms.action_move {DragResponse::Move}
+        else if action == self.atoms.action_link {DragResponse::Link}
+        else {DragResponse::Copy}
+    }
+
+    fn pick_data_type(&self, types: &[x11_sys::Atom]) -> x11_sys::Atom {
+        [self.atoms.uri_list, self.atoms.utf8_string, self.atoms.text_plain_utf8, self.atoms.text_plain]
+            .into_iter()
+            .find( | ty | types.contains(ty))
+            .unwrap_or(self.atoms.none)
+    }
+
+    fn data_types(&self, items: &[DragItem]) -> Vec<x11_sys::Atom> {
+        let mut types = Vec::new();
+        if items.iter().any( | item | matches!(item, DragItem::FilePath {..})) {
+            types.push(self.atoms.uri_list);
+        }
+        types.extend([self.atoms.utf8_string, self.atoms.text_plain_utf8, self.atoms.text_plain]);
+        types
+    }
+
+    fn encode_data(&self, items: &[DragItem], target: x11_sys::Atom) -> Option<String> {
+        if target == self.atoms.uri_list {
+            Some(uri_list_from_items(items))
+        }
+        else if target == self.atoms.utf8_string || target == self.atoms.text_plain_utf8 || target == self.atoms.text_plain {
+            Some(text_from_items(items))
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl XlibApp {
+    unsafe fn root_window(&self) -> x11_sys::Window {
+        x11_sys::XRootWindow(self.display, x11_sys::XDefaultScreen(self.display))
+    }
+
+    // root coordinates to the logical coordinates of one of our windows
+    unsafe fn root_to_window_pos(&self, window: x11_sys::Window, x_root: c_int, y_root: c_int) -> DVec2 {
+        let Some(window_ptr) = self.window_map.get(&window) else {
+            return DRAG_OUT_POS
+        };
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        x11_sys::XTranslateCoordinates(self.display, self.root_window(), window, x_root, y_root, &mut x, &mut y, &mut child);
+        let dpi_factor = (**window_ptr).last_window_geom.dpi_factor;
+        dvec2(x as f64 / dpi_factor, y as f64 / dpi_factor)
+    }
+
+    unsafe fn pointer_modifiers(&self) -> KeyModifiers {
+        let (mut root, mut child) = (0, 0);
+        let (mut x_root, mut y_root, mut x, mut y) = (0, 0, 0, 0);
+        let mut mask = 0;
+        x11_sys::XQueryPointer(self.display, self.root_window(), &mut root, &mut child, &mut x_root, &mut y_root, &mut x, &mut y, &mut mask);
+        self.xkeystate_to_modifiers(mask)
+    }
+
+    // the innermost XdndAware window under the pointer
+    unsafe fn find_dnd_target(&self, x_root: c_int, y_root: c_int) -> Option<x11_sys::Window> {
+        let root = self.root_window();
+        let mut window = root;
+        loop {
+            let (mut x, mut y, mut child) = (0, 0, 0);
+            if x11_sys::XTranslateCoordinates(self.display, root, window, x_root, y_root, &mut x, &mut y, &mut child) == 0 || child == 0 {
+                return None
+            }
+            window = child;
+            let version = self.dnd.read_atoms(window, self.dnd.atoms.aware);
+            if version.first().map_or(false, | version | *version >= 3) {
+                return Some(window)
+            }
+        }
+    }
+
+    pub unsafe fn handle_dnd_client_message(&mut self, event: &x11_sys::XClientMessageEvent) {
+        let atoms = &self.dnd.atoms;
+        let l = event.data.l;
+        if event.message_type == atoms.enter {
+            self.handle_dnd_enter(event);
+        }
+        else if event.message_type == atoms.position {
+            self.handle_dnd_position(event);
+        }
+        else if event.message_type == atoms.leave {
+            if let Some(incoming) = self.dnd.incoming.take() {
+                if incoming.source == l[0] as x11_sys::Window && incoming.items.is_some() {
+                    self.send_incoming_drag(&incoming, DRAG_OUT_POS);
+                }
+            }
+        }
+        else if event.message_type == atoms.drop {
+            self.handle_dnd_drop(event);
+        }
+        else if event.message_type == atoms.status {
+            self.handle_dnd_status(event);
+        }
+        else if event.message_type == atoms.finished {
+            if let Some(outgoing) = &self.dnd.outgoing {
+                if outgoing.dropped {
+                    self.dnd.outgoing = None;
+                }
+            }
+        }
+    }
+
+    // Incoming drags
+
+    unsafe fn handle_dnd_enter(&mut self, event: &x11_sys::XClientMessageEvent) {
+        // XdndEnter is sent when a drag enters one of our windows. If the source supports more
+        // than three types they are listed in the XdndTypeList property of the source window.
+        let l = event.data.l;
+        let source = l[0] as x11_sys::Window;
+        let types = if l[1] & 1 != 0 {
+            self.dnd.read_atoms(source, self.dnd.atoms.type_list)
+        }
+        else {
+            l[2..5].iter().map( | &l | l as x11_sys::Atom).filter( | &atom | atom != 0).collect()
+        };
+        let data_type = self.dnd.pick_data_type(&types);
+        self.dnd.incoming = Some(DndIncoming {
+            source,
+            target: event.window,
+            data_type,
+            requested: false,
+            // nothing we can read, the drag is refused
+            items: if data_type == self.dnd.atoms.none {Some(Rc::new(Vec::new()))} else {None},
+            pos: DRAG_OUT_POS,
+            proposed: DragResponse::Copy,
+            response: DragResponse::None,
+            dropped: false,
+        });
+    }
+
+    unsafe fn handle_dnd_position(&mut self, event: &x11_sys::XClientMessageEvent) {
+        let l = event.data.l;
+        let x_root = ((l[2] >> 16) & 0xffff) as c_int;
+        let y_root = (l[2] & 0xffff) as c_int;
+        let pos = {
+            let Some(incoming) = &self.dnd.incoming else {return};
+            if incoming.source != l[0] as x11_sys::Window {
+                return
+            }
+            self.root_to_window_pos(incoming.target, x_root, y_root)
+        };
+        let proposed = self.dnd.action_to_response(l[4] as x11_sys::Atom);
+        let Some(incoming) = &mut self.dnd.incoming else {return};
+        incoming.pos = pos;
+        incoming.proposed = proposed;
+        if !incoming.requested {
+            // the answer arrives as a SelectionNotify on the target window
+            x11_sys::XConvertSelection(
+                self.display,
+                self.dnd.atoms.selection,
+                incoming.data_type,
+                self.dnd.atoms.selection,
+                incoming.target,
+                l[3] as x11_sys::Time,
+            );
+            incoming.requested = true;
+        }
+        self.update_incoming_drag();
+    }
+
+    unsafe fn handle_dnd_drop(&mut self, event: &x11_sys::XClientMessageEvent) {
+        let Some(incoming) = &mut self.dnd.incoming else {return};
+        if incoming.source != event.data.l[0] as x11_sys::Window {
+            return
+        }
+        incoming.dropped = true;
+        if incoming.items.is_some() {
+            self.finish_incoming_drop();
+        }
+        else if !incoming.requested {
+            x11_sys::XConvertSelection(
+                self.display,
+                self.dnd.atoms.selection,
+                incoming.data_type,
+                self.dnd.atoms.selection,
+                incoming.target,
+                event.data.l[2] as x11_sys::Time,
+            );
+            incoming.requested = true;
+        }
+    }
+
+    /// Handles the SelectionNotify that answers our request for the dragged data.
+    pub unsafe fn handle_dnd_selection_notify(&mut self, event: &x11_sys::XSelectionEvent) {
+        let Some(incoming) = &self.dnd.incoming else {return};
+        if incoming.target != event.requestor || incoming.items.is_some() {
+            return
+        }
+        let data = if event.property != 0 {
+            self.dnd.read_property(event.requestor, event.property, x11_sys::AnyPropertyType as x11_sys::Atom, true)
+        } else {None};
+        let items = data.map_or(Vec::new(), | data | {
+            let text = String::from_utf8_lossy(&data);
+            if incoming.data_type == self.dnd.atoms.uri_list {
+                items_from_uri_list(&text)
+            }
+            else {
+                vec![DragItem::String {value: text.to_string(), internal_id: None}]
+            }
+        });
+        let Some(incoming) = &mut self.dnd.incoming else {return};
+        incoming.items = Some(Rc::new(items));
+        if incoming.dropped {
+            self.finish_incoming_drop();
+        }
+        else {
+            self.update_incoming_drag();
+        }
+    }
+
+    unsafe fn send_incoming_drag(&mut self, incoming: &DndIncoming, pos: DVec2) -> DragResponse {
+        let response = Rc::new(Cell::new(incoming.proposed));
+        self.do_callback(XlibEvent::Drag(DragEvent {
+            modifiers: self.pointer_modifiers(),
+            handled: Cell::new(false),
+            abs: pos,
+            items: incoming.items.clone().unwrap_or_default(),
+            response: response.clone(),
+        }));
+        response.get()
+    }
+
+    // reports the drag to the app once the data is there, and tells the source what it said
+    unsafe fn update_incoming_drag(&mut self) {
+        let Some(incoming) = self.dnd.incoming.take() else {return};
+        let response = match &incoming.items {
+            Some(items) if !items.is_empty() => self.send_incoming_drag(&incoming, incoming.pos),
+            _ => DragResponse::None
+        };
+        let accepted = response != DragResponse::None;
+        send_client_message(self.display, incoming.source, self.dnd.atoms.status, [
+            incoming.target as c_long,
+            // bit 1 asks for position messages everywhere, our drop areas are smaller than the window
+            (accepted as c_long) | 2,
+            0,
+            0,
+            self.dnd.response_to_action(response) as c_long,
+        ]);
+        self.dnd.incoming = Some(DndIncoming {response, ..incoming});
+    }
+
+    unsafe fn finish_incoming_drop(&mut self) {
+        let Some(incoming) = self.dnd.incoming.take() else {return};
+        let mut accepted = false;
+        if let Some(items) = incoming.items.clone().filter( | items | !items.is_empty()) {
+            if incoming.response != DragResponse::None {
+                let event = DropEvent {
+                    modifiers: self.pointer_modifiers(),
+                    handled: Cell::new(false),
+                    abs: incoming.pos,
+                    items,
+                };
+                self.do_callback(XlibEvent::Drop(event));
+                accepted = true;
+            }
+            else {
+                self.send_incoming_drag(&incoming, DRAG_OUT_POS);
+            }
+        }
+        send_client_message(self.display, incoming.source, self.dnd.atoms.finished, [
+            incoming.target as c_long,
+            accepted as c_long,
+            if accepted {self.dnd.response_to_action(incoming.response)} else {self.dnd.atoms.none} as c_long,
+            0,
+            0,
+        ]);
+    }
+
+    // Outgoing drags
+
+    pub fn start_dragging(&mut self, items: Vec<DragItem>) {
+        unsafe {
+            let source = self.last_click_window;
+            if !self.window_map.contains_key(&source) {
+                crate::error!("start_dragging: no window to drag from");
+                return
+            }
+            self.cancel_dragging();
+            let types = self.dnd.data_types(&items);
+            x11_sys::XSetSelectionOwner(self.display, self.dnd.atoms.selection, source, x11_sys::CurrentTime as x11_sys::Time);
+            x11_sys::XChangeProperty(
+                self.display,
+                source,
+                self.dnd.atoms.type_list,
+                XA_ATOM,
+                32,
+                x11_sys::PropModeReplace as c_int,
+                types.as_ptr() as *const c_uchar,
+                types.len() as c_int
+            );
+            // the button is still down, so this turns the implicit grab into one that lasts until the drop
+            x11_sys::XGrabPointer(
+                self.display,
+                source,
+                x11_sys::False as c_int,
+                (x11_sys::ButtonReleaseMask | x11_sys::PointerMotionMask) as c_uint,
+                x11_sys::GrabModeAsync,
+                x11_sys::GrabModeAsync,
+                0,
+                0,
+                x11_sys::CurrentTime as x11_sys::Time
+            );
+            self.dnd.outgoing = Some(DndOutgoing {
+                source,
+                items: Rc::new(items),
+                types,
+                target: None,
+                dropped: false,
+            });
+        }
+    }
+
+    pub unsafe fn cancel_dragging(&mut self) {
+        if self.dnd.outgoing.is_none() {
+            return
+        }
+        self.leave_dnd_target();
+        self.dnd.outgoing = None;
+        x11_sys::XUngrabPointer(self.display, x11_sys::CurrentTime as x11_sys::Time);
+        self.do_callback(XlibEvent::DragEnd);
+    }
+
+    unsafe fn leave_dnd_target(&mut self) {
+        let Some(outgoing) = &mut self.dnd.outgoing else {return};
+        let items = outgoing.items.clone();
+        let source = outgoing.source;
+        match outgoing.target.take() {
+            Some(target) if target.internal => {
+                self.do_callback(XlibEvent::Drag(DragEvent {
+                    modifiers: self.pointer_modifiers(),
+                    handled: Cell::new(false),
+                    abs: DRAG_OUT_POS,
+                    items,
+                    response: Rc::new(Cell::new(DragResponse::None)),
+                }));
+            }
+            Some(target) => {
+                send_client_message(self.display, target.window, self.dnd.atoms.leave, [source as c_long, 0, 0, 0, 0]);
+            }
+            None => ()
+        }
+    }
+
+    /// Moves an outgoing drag along, returns false if there is none.
+    pub unsafe fn handle_dnd_motion(&mut self, motion: &x11_sys::XMotionEvent) -> bool {
+        if !self.dnd.is_dragging() {
+            return false
+        }
+        let target_window = self.find_dnd_target(motion.x_root, motion.y_root);
+        let Some(outgoing) = &self.dnd.outgoing else {return false};
+        if outgoing.target.as_ref().map(| target | target.window) != target_window {
+            self.leave_dnd_target();
+            let Some(outgoing) = &mut self.dnd.outgoing else {return false};
+            if let Some(window) = target_window {
+                let internal = self.window_map.contains_key(&window);
+                if !internal {
+                    let types = &outgoing.types;
+                    let atom = | i: usize | types.get(i).copied().unwrap_or(0) as c_long;
+                    let enter = [
+                        outgoing.source as c_long,
+                        (XDND_VERSION << 24) | (types.len() > 3) as c_long,
+                        atom(0),
+                        atom(1),
+                        atom(2),
+                    ];
+                    send_client_message(self.display, window, self.dnd.atoms.enter, enter);
+                }
+                outgoing.target = Some(DndTarget {
+                    window,
+                    internal,
+                    accepted: false,
+                    waiting_for_status: false,
+                    pending_position: None,
+                });
+            }
+        }
+        let modifiers = self.xkeystate_to_modifiers(motion.state);
+        let Some(outgoing) = &mut self.dnd.outgoing else {return false};
+        let Some(target) = &mut outgoing.target else {return true};
+        if target.internal {
+            let window = target.window;
+            let items = outgoing.items.clone();
+            let response = Rc::new(Cell::new(DragResponse::None));
+            let abs = self.root_to_window_pos(window, motion.x_root, motion.y_root);
+            self.do_callback(XlibEvent::Drag(DragEvent {
+                modifiers,
+                handled: Cell::new(false),
+                abs,
+                items,
+                response: response.clone(),
+            }));
+            if let Some(DndOutgoing {target: Some(target), ..}) = &mut self.dnd.outgoing {
+                target.accepted = response.get() != DragResponse::None;
+            }
+        }
+        else {
+            let action = if modifiers.shift {self.dnd.atoms.action_move} else {self.dnd.atoms.action_copy};
+            let position = [
+                outgoing.source as c_long,
+                0,
+                ((motion.x_root as c_long) << 16) | (motion.y_root as c_long & 0xffff),
+                motion.time as c_long,
+                action as c_long,
+            ];
+            // only one position can be in flight, the newest one waits for the status
+            if target.waiting_for_status {
+                target.pending_position = Some(position);
+            }
+            else {
+                target.waiting_for_status = true;
+                send_client_message(self.display, target.window, self.dnd.atoms.position, position);
+            }
+        }
+        true
+    }
+
+    unsafe fn handle_dnd_status(&mut self, event: &x11_sys::XClientMessageEvent) {
+        let l = event.data.l;
+        let Some(DndOutgoing {target: Some(target), ..}) = &mut self.dnd.outgoing else {return};
+        if target.window != l[0] as x11_sys::Window {
+            return
+        }
+        target.accepted = l[1] & 1 != 0;
+        target.waiting_for_status = false;
+        if let Some(position) = target.pending_position.take() {
+            target.waiting_for_status = true;
+            send_client_message(self.display, target.window, self.dnd.atoms.position, position);
+        }
+    }
+
+    /// Drops an outgoing drag, returns false if there is none.
+    pub unsafe fn handle_dnd_button_release(&mut self, button: &x11_sys::XButtonEvent) -> bool {
+        if !self.dnd.is_dragging() {
+            return false
+        }
+        x11_sys::XUngrabPointer(self.display, x11_sys::CurrentTime as x11_sys::Time);
+        let Some(outgoing) = &mut self.dnd.outgoing else {return false};
+        match outgoing.target.take() {
+            Some(target) if target.internal && target.accepted => {
+                let items = outgoing.items.clone();
+                self.dnd.outgoing = None;
+                let abs = self.root_to_window_pos(target.window, button.x_root, button.y_root);
+                self.do_callback(XlibEvent::Drop(DropEvent {
+                    modifiers: self.xkeystate_to_modifiers(button.state),
+                    handled: Cell::new(false),
+                    abs,
+                    items,
+                }));
+            }
+            Some(target) if !target.internal && target.accepted => {
+                outgoing.dropped = true;
+                send_client_message(self.display, target.window, self.dnd.atoms.drop, [
+                    outgoing.source as c_long,
+                    0,
+                    button.time as c_long,
+                    0,
+                    0
+                ]);
+            }
+            target => {
+                outgoing.target = target;
+                self.leave_dnd_target();
+                self.dnd.outgoing = None;
+            }
+        }
+        self.do_callback(XlibEvent::DragEnd);
+        true
+    }
+
+    /// Answers a request for the data of our outgoing drag, returns false if it is for another selection.
+    pub unsafe fn handle_dnd_selection_request(&mut self, request: &x11_sys::XSelectionRequestEvent) -> bool {
+        if request.selection != self.dnd.atoms.selection {
+            return false
+        }
+        let mut response = x11_sys::XSelectionEvent {
+            type_: x11_sys::SelectionNotify as c_int,
+            serial: 0,
+            send_event: 0,
+            display: self.display,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            time: request.time,
+            property: request.property,
+        };
+        let outgoing = self.dnd.outgoing.as_ref().filter( | outgoing | outgoing.source == request.owner);
+        match outgoing {
+            Some(outgoing) if request.target == self.atoms.targets => {
+                let mut targets = outgoing.types.clone();
+                targets.push(self.atoms.targets);
+                x11_sys::XChangeProperty(
+                    self.display,
+                    request.requestor,
+                    request.property,
+                    XA_ATOM,
+                    32,
+                    x11_sys::PropModeReplace as c_int,
+                    targets.as_ptr() as *const c_uchar,
+                    targets.len() as c_int
+                );
+            }
+            Some(outgoing) => match self.dnd.encode_data(&outgoing.items, request.target) {
+                Some(data) => {
+                    x11_sys::XChangeProperty(
+                        self.display,
+                        request.requestor,
+                        request.property,
+                        request.target,
+                        8,
+                        x11_sys::PropModeReplace as c_int,
+                        data.as_ptr(),
+                        data.len() as c_int
+                    );
+                }
+                None => response.property = 0
+            }
+            None => response.property = 0
+        }
+        x11_sys::XSendEvent(self.display, request.requestor, 1, 0, &mut response as *mut _ as *mut x11_sys::XEvent);
+        x11_sys::XFlush(self.display);
+        true
+    }
+}
+
+unsafe fn send_client_message(display: *mut x11_sys::Display, window: x11_sys::Window, message_type: x11_sys::Atom, l: [c_long; 5]) {
+    let mut event = x11_sys::XClientMessageEvent {
+        type_: x11_sys::ClientMessage as c_int,
+        serial: 0,
+        send_event: 0,
+        display,
+        window,
+        message_type,
+        format: 32,
+        data: {
+            let mut data = mem::zeroed::<x11_sys::XClientMessageEvent__bindgen_ty_1>();
+            data.l = l;
+            data
+        }
+    };
+    x11_sys::XSendEvent(
+        display,
+        window,
+        x11_sys::False as c_int,
+        x11_sys::NoEventMask as c_long,
+        &mut event as *mut x11_sys::XClientMessageEvent as *mut x11_sys::XEvent
+    );
+    x11_sys::XFlush(display);
+}
+
+fn items_from_uri_list(list: &str) -> Vec<DragItem> {
+    list.lines()
+        .map( | line | line.trim())
+        .filter( | line | !line.is_empty() && !line.starts_with('#'))
+        .map( | uri | match file_uri_to_path(uri) {
+            Some(path) => DragItem::FilePath {path: path.to_string_lossy().to_string(), internal_id: None},
+            None => DragItem::String {value: uri.to_string(), internal_id: None}
+        })
+        .collect()
+}
+
+fn uri_list_from_items(items: &[DragItem]) -> String {
+    let mut list = String::new();
+    for item in items {
+        if let DragItem::FilePath {path, ..} = item {
+            list.push_str("file://");
+            for byte in path.bytes() {
+                if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+                    list.push(byte as char);
+                }
+                else {
+                    list.push_str(&format!("%{:02X}", byte));
+                }
+            }
+            list.push_str("\r\n");
+        }
+    }
+    list
+}
+
+fn text_from_items(items: &[DragItem]) -> String {
+    let strings: Vec<&str> = items.iter().filter_map( | item | match item {
+        DragItem::String {value, ..} => Some(value.as_str()),
+        _ => None
+    }).collect();
+    if !strings.is_empty() {
+        return strings.join("\n")
+    }
+    items.iter().filter_map( | item | match item {
+        DragItem::FilePath {path, ..} => Some(path.as_str()),
+        _ => None
+    }).collect::<Vec<_ >>().join("\n")
+}
+
+pub struct DndAtoms {
+    pub action_copy: x11_sys::Atom,
+    pub action_link: x11_sys::Atom,
+    pub action_move: x11_sys::Atom,
+    pub aware: x11_sys::Atom,
+    pub drop: x11_sys::Atom,
+    pub enter: x11_sys::Atom,
+    pub finished: x11_sys::Atom,
+    pub leave: x11_sys::Atom,
+    pub none: x11_sys::Atom,
+    pub position: x11_sys::Atom,
+    pub selection: x11_sys::Atom,
+    pub status: x11_sys::Atom,
+    pub type_list: x11_sys::Atom,
+    pub uri_list: x11_sys::Atom,
+    pub utf8_string: x11_sys::Atom,
+    pub text_plain: x11_sys::Atom,
+    pub text_plain_utf8: x11_sys::Atom,
+}
+
+impl DndAtoms {
+    pub unsafe fn new(display: *mut x11_sys::Display) -> DndAtoms {
+        DndAtoms {
+            action_copy: x11_sys::XInternAtom(display, "XdndActionCopy\0".as_ptr() as *const _, 0),
+            action_link: x11_sys::XInternAtom(display, "XdndActionLink\0".as_ptr() as *const _, 0),
+            action_move: x11_sys::XInternAtom(display, "XdndActionMove\0".as_ptr() as *const _, 0),
+            aware: x11_sys::XInternAtom(display, "XdndAware\0".as_ptr() as *const _, 0),
+            drop: x11_sys::XInternAtom(display, "XdndDrop\0".as_ptr() as *const _, 0),
+            enter: x11_sys::XInternAtom(display, "XdndEnter\0".as_ptr() as *const _, 0),
+            finished: x11_sys::XInternAtom(display, "XdndFinished\0".as_ptr() as *const _, 0),
+            leave: x11_sys::XInternAtom(display, "XdndLeave\0".as_ptr() as *const _, 0),
+            none: x11_sys::None as x11_sys::Atom,
+            position: x11_sys::XInternAtom(display, "XdndPosition\0".as_ptr() as *const _, 0),
+            selection: x11_sys::XInternAtom(display, "XdndSelection\0".as_ptr() as *const _, 0),
+            status: x11_sys::XInternAtom(display, "XdndStatus\0".as_ptr() as *const _, 0),
+            type_list: x11_sys::XInternAtom(display, "XdndTypeList\0".as_ptr() as *const _, 0),
+            uri_list: x11_sys::XInternAtom(display, "text/uri-list\0".as_ptr() as *const _, 0),
+            utf8_string: x11_sys::XInternAtom(display, "UTF8_STRING\0".as_ptr() as *const _, 0),
+            text_plain: x11_sys::XInternAtom(display, "text/plain\0".as_ptr() as *const _, 0),
+            text_plain_utf8: x11_sys::XInternAtom(display, "text/plain;charset=utf-8\0".as_ptr() as *const _, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_list_round_trip() {
+        let items = vec![
+            DragItem::FilePath {path: "/home/me/my file.rs".to_string(), internal_id: None},
+            DragItem::FilePath {path: "/tmp/ü#1".to_string(), internal_id: None},
+        ];
+        let list = uri_list_from_items(&items);
+        assert_eq!(list, "file:///home/me/my%20file.rs\r\nfile:///tmp/%C3%BC%231\r\n");
+        assert_eq!(items_from_uri_list(&list), items);
+    }
+
+    #[test]
+    fn uri_list_comments_and_urls() {
+        let items = items_from_uri_list("# a comment\r\nfile://localhost/etc/hosts\r\nhttps://makepad.dev\n\n");
+        assert_eq!(items, vec![
+            DragItem::FilePath {path: "/etc/hosts".to_string(), internal_id: None},
+            DragItem::String {value: "https://makepad.dev".to_string(), internal_id: None},
+        ]);
+    }
+
+    #[test]
+    fn text_prefers_strings() {
+        let file = DragItem::FilePath {path: "/a".to_string(), internal_id: None};
+        let text = DragItem::String {value: "hello".to_string(), internal_id: None};
+        assert_eq!(text_from_items(&[file.clone(), text]), "hello");
+        assert_eq!(text_from_items(&[file.clone(), file]), "/a\n/a");
+    }
+}
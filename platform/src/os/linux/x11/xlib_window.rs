@@ -3,9 +3,9 @@ use {
         mem,
         cell::Cell,
         rc::Rc,
-        os::raw::{c_ulong, c_long, c_void, c_char},
+        os::raw::{c_ulong, c_long, c_void, c_char, c_int, c_short},
         ptr,
-        ffi::{CStr,CString}, 
+        ffi::CStr, 
     },
     self::super::{
         x11_sys,
@@ -34,9 +34,65 @@ pub struct XlibWindow {
     pub last_window_geom: WindowGeom,
     
     pub ime_spot: DVec2,
+    pub ime_style: x11_sys::XIMStyle,
+    pub ime_callbacks: Option<Box<[x11_sys::XIMCallback; 4]>>,
+    pub preedit: XimPreedit,
     pub current_cursor: MouseCursor,
     pub last_mouse_pos: DVec2,
 }
+
+/// The text an input method is composing, kept up to date by the XIM preedit callbacks
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct XimPreedit {
+    pub text: Vec<char>,
+    pub cursor: usize,
+}
+
+impl XimPreedit {
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+    
+    /// Replaces `length` chars at `first` with `text`, a `None` text only changes the caret
+    pub fn draw(&mut self, first: c_int, length: c_int, text: Option<Vec<char>>, caret: c_int) {
+        let first = (first.max(0) as usize).min(self.text.len());
+        let end = (first + length.max(0) as usize).min(self.text.len());
+        if let Some(text) = text {
+            self.text.splice(first..end, text);
+        }
+        self.cursor = (caret.max(0) as usize).min(self.text.len());
+    }
+    
+    /// Moves the caret like XIMPreeditCaretCallbackStruct asks and returns the new position
+    pub fn move_caret(&mut self, direction: c_int, position: c_int) -> usize {
+        let len = self.text.len();
+        self.cursor = match direction {
+            x11_sys::XIMForwardChar => (self.cursor + 1).min(len),
+            x11_sys::XIMBackwardChar => self.cursor.saturating_sub(1),
+            x11_sys::XIMLineStart => 0,
+            x11_sys::XIMLineEnd => len,
+            x11_sys::XIMAbsolutePosition => (position.max(0) as usize).min(len),
+            _ => self.cursor
+        };
+        self.cursor
+    }
+    
+    /// The input method committed its text, returns true if a composition was still showing
+    pub fn commit(&mut self) -> bool {
+        let was_composing = !self.text.is_empty();
+        self.clear();
+        was_composing
+    }
+    
+    pub fn to_event(&self) -> TextCompositionEvent {
+        TextCompositionEvent {
+            text: self.text.iter().collect(),
+            cursor: self.cursor,
+        }
+    }
+}
+
 /*
 #[derive(Clone)]
 pub struct XlibChildWindow {
@@ -62,6 +118,9 @@ impl XlibWindow {
             last_window_geom: WindowGeom::default(),
             last_nc_mode: None,
             ime_spot: DVec2::default(),
+            ime_style: 0,
+            ime_callbacks: None,
+            preedit: XimPreedit::default(),
             current_cursor: MouseCursor::Default,
             last_mouse_pos: DVec2::default(),
         }
@@ -150,24 +209,13 @@ impl XlibWindow {
             let title_bytes = format!("{}\0", title);
             x11_sys::XStoreName(display, window, title_bytes.as_bytes().as_ptr() as *const c_char);
             
-            let xic = x11_sys::XCreateIC(
-                get_xlib_app_global().xim,
-                x11_sys::XNInputStyle.as_ptr(),
-                (x11_sys::XIMPreeditNothing | x11_sys::XIMStatusNothing) as i32,
-                x11_sys::XNClientWindow.as_ptr(),
-                window,
-                x11_sys::XNFocusWindow.as_ptr(),
-                window,
-                ptr::null_mut() as *mut c_void
-            );
-            
             // Create a window
             get_xlib_app_global().window_map.insert(window, self);
             
             self.attributes = Some(attributes);
             self.visual_info = Some(visual_info);
             self.window = Some(window);
-            self.xic = Some(xic);
+            self.xic = self.create_ic(window);
             self.last_window_geom = self.get_window_geom();
             
             let new_geom = self.get_window_geom();
@@ -218,8 +266,147 @@ impl XlibWindow {
         self.restore_or_maximize(_NET_WM_STATE_ADD);
     }
     
+    unsafe fn create_ic(&mut self, window: c_ulong) -> Option<x11_sys::XIC> {
+        let xim = get_xlib_app_global().xim;
+        if xim.is_null() {
+            return None
+        }
+        // pick the richest preedit style the input method supports:
+        // callbacks let us draw the composition inline, position at least puts the IME window at the cursor
+        let mut styles: *mut x11_sys::XIMStyles = ptr::null_mut();
+        let mut supported = Vec::new();
+        if x11_sys::XGetIMValues(xim, x11_sys::XNQueryInputStyle.as_ptr(), &mut styles, ptr::null_mut::<c_void>()).is_null() && !styles.is_null() {
+            let styles_ref = &*styles;
+            for i in 0..styles_ref.count_styles as usize {
+                supported.push(*styles_ref.supported_styles.add(i));
+            }
+            x11_sys::XFree(styles as *mut c_void);
+        }
+        let callbacks_style = (x11_sys::XIMPreeditCallbacks | x11_sys::XIMStatusNothing) as x11_sys::XIMStyle;
+        let position_style = (x11_sys::XIMPreeditPosition | x11_sys::XIMStatusNothing) as x11_sys::XIMStyle;
+        let nothing_style = (x11_sys::XIMPreeditNothing | x11_sys::XIMStatusNothing) as x11_sys::XIMStyle;
+        
+        let spot = x11_sys::XPoint {x: 0, y: 0};
+        if supported.contains(&callbacks_style) {
+            let client_data = self as *mut XlibWindow as x11_sys::XPointer;
+            let callbacks = Box::new([
+                x11_sys::XIMCallback {client_data, callback: Some(xim_preedit_start)},
+                x11_sys::XIMCallback {client_data, callback: Some(xim_preedit_done)},
+                x11_sys::XIMCallback {client_data, callback: Some(xim_preedit_draw)},
+                x11_sys::XIMCallback {client_data, callback: Some(xim_preedit_caret)},
+            ]);
+            let preedit_attributes = x11_sys::XVaCreateNestedList(
+                0,
+                x11_sys::XNSpotLocation.as_ptr(),
+                &spot,
+                x11_sys::XNPreeditStartCallback.as_ptr(),
+                &callbacks[0],
+                x11_sys::XNPreeditDoneCallback.as_ptr(),
+                &callbacks[1],
+                x11_sys::XNPreeditDrawCallback.as_ptr(),
+                &callbacks[2],
+                x11_sys::XNPreeditCaretCallback.as_ptr(),
+                &callbacks[3],
+                ptr::null_mut::<c_void>()
+            );
+            let xic = self.create_ic_with_style(xim, window, callbacks_style, preedit_attributes);
+            x11_sys::XFree(preedit_attributes);
+            if xic.is_some() {
+                self.ime_callbacks = Some(callbacks);
+                return xic
+            }
+        }
+        if supported.contains(&position_style) {
+            let preedit_attributes = x11_sys::XVaCreateNestedList(
+                0,
+                x11_sys::XNSpotLocation.as_ptr(),
+                &spot,
+                ptr::null_mut::<c_void>()
+            );
+            let xic = self.create_ic_with_style(xim, window, position_style, preedit_attributes);
+            x11_sys::XFree(preedit_attributes);
+            if xic.is_some() {
+                return xic
+            }
+        }
+        self.create_ic_with_style(xim, window, nothing_style, ptr::null_mut())
+    }
+    
+    unsafe fn create_ic_with_style(&mut self, xim: x11_sys::XIM, window: c_ulong, style: x11_sys::XIMStyle, preedit_attributes: x11_sys::XVaNestedList) -> Option<x11_sys::XIC> {
+        let xic = if preedit_attributes.is_null() {
+            x11_sys::XCreateIC(
+                xim,
+                x11_sys::XNInputStyle.as_ptr(),
+                style,
+                x11_sys::XNClientWindow.as_ptr(),
+                window,
+                x11_sys::XNFocusWindow.as_ptr(),
+                window,
+                ptr::null_mut::<c_void>()
+            )
+        }
+        else {
+            x11_sys::XCreateIC(
+                xim,
+                x11_sys::XNInputStyle.as_ptr(),
+                style,
+                x11_sys::XNClientWindow.as_ptr(),
+                window,
+                x11_sys::XNFocusWindow.as_ptr(),
+                window,
+                x11_sys::XNPreeditAttributes.as_ptr(),
+                preedit_attributes,
+                ptr::null_mut::<c_void>()
+            )
+        };
+        if xic.is_null() {
+            return None
+        }
+        self.ime_style = style;
+        Some(xic)
+    }
+    
+    pub fn set_ime_focus(&mut self, focus: bool) {
+        if let Some(xic) = self.xic {
+            unsafe {
+                if focus {
+                    x11_sys::XSetICFocus(xic);
+                }
+                else {
+                    x11_sys::XUnsetICFocus(xic);
+                }
+            }
+        }
+    }
+    
+    /// Drops any composition in progress, for instance when the text input loses focus.
+    /// Returns true if there was one, so the caller can tell the app it ended.
+    pub fn reset_ime(&mut self) -> bool {
+        if self.preedit.text.is_empty() {
+            return false
+        }
+        if let Some(xic) = self.xic {
+            unsafe {
+                let pending = x11_sys::Xutf8ResetIC(xic);
+                if !pending.is_null() {
+                    x11_sys::XFree(pending as *mut c_void);
+                }
+            }
+        }
+        self.preedit.clear();
+        true
+    }
+    
+    pub fn send_text_composition(&mut self) {
+        let event = self.preedit.to_event();
+        self.do_callback(XlibEvent::TextComposition(event))
+    }
+    
     pub fn close_window(&mut self) {
         unsafe {
+            if let Some(xic) = self.xic.take() {
+                x11_sys::XDestroyIC(xic);
+            }
             x11_sys::XDestroyWindow(get_xlib_app_global().display, self.window.unwrap());
             self.window = None;
             // lets remove us from the mapping
@@ -298,7 +485,34 @@ impl XlibWindow {
     }
     
     pub fn set_ime_spot(&mut self, spot: DVec2) {
+        if self.ime_spot == spot {
+            return
+        }
         self.ime_spot = spot;
+        let has_spot = self.ime_style & (x11_sys::XIMPreeditCallbacks | x11_sys::XIMPreeditPosition) as x11_sys::XIMStyle != 0;
+        if let (Some(xic), true) = (self.xic, has_spot) {
+            // the spot location is in window pixels
+            let dpi_factor = self.last_window_geom.dpi_factor;
+            let point = x11_sys::XPoint {
+                x: (spot.x * dpi_factor) as c_short,
+                y: (spot.y * dpi_factor) as c_short,
+            };
+            unsafe {
+                let preedit_attributes = x11_sys::XVaCreateNestedList(
+                    0,
+                    x11_sys::XNSpotLocation.as_ptr(),
+                    &point,
+                    ptr::null_mut::<c_void>()
+                );
+                x11_sys::XSetICValues(
+                    xic,
+                    x11_sys::XNPreeditAttributes.as_ptr(),
+                    preedit_attributes,
+                    ptr::null_mut::<c_void>()
+                );
+                x11_sys::XFree(preedit_attributes);
+            }
+        }
     }
     
     pub fn get_position(&self) -> DVec2 {
@@ -473,6 +687,62 @@ impl XlibWindow {
 }
 
 
+unsafe extern "C" fn xim_preedit_start(_xic: x11_sys::XIC, client_data: x11_sys::XPointer, _call_data: x11_sys::XPointer) -> c_int {
+    let window = &mut *(client_data as *mut XlibWindow);
+    window.preedit.clear();
+    // no limit on the preedit length
+    -1
+}
+
+unsafe extern "C" fn xim_preedit_done(_xic: x11_sys::XIC, client_data: x11_sys::XPointer, _call_data: x11_sys::XPointer) -> c_int {
+    let window = &mut *(client_data as *mut XlibWindow);
+    window.preedit.clear();
+    window.send_text_composition();
+    0
+}
+
+/// The chars of an XIMText, `None` when only its feedback (highlighting) changed
+unsafe fn xim_text_chars(text: *const x11_sys::XIMText) -> Option<Vec<char>> {
+    if text.is_null() {
+        return Some(Vec::new())
+    }
+    let text = &*text;
+    if text.encoding_is_wchar != 0 {
+        if text.string.wide_char.is_null() {
+            None
+        }
+        else {
+            Some((0..text.length as usize)
+                .filter_map( | i | char::from_u32(*text.string.wide_char.add(i) as u32))
+                .collect())
+        }
+    }
+    else if text.string.multi_byte.is_null() {
+        None
+    }
+    else {
+        Some(CStr::from_ptr(text.string.multi_byte).to_string_lossy().chars().collect())
+    }
+}
+
+unsafe extern "C" fn xim_preedit_draw(_xic: x11_sys::XIC, client_data: x11_sys::XPointer, call_data: x11_sys::XPointer) -> c_int {
+    let window = &mut *(client_data as *mut XlibWindow);
+    let draw = &*(call_data as *mut x11_sys::XIMPreeditDrawCallbackStruct);
+    
+    let replacement = xim_text_chars(draw.text);
+    window.preedit.draw(draw.chg_first, draw.chg_length, replacement, draw.caret);
+    window.send_text_composition();
+    0
+}
+
+unsafe extern "C" fn xim_preedit_caret(_xic: x11_sys::XIC, client_data: x11_sys::XPointer, call_data: x11_sys::XPointer) -> c_int {
+    let window = &mut *(client_data as *mut XlibWindow);
+    let caret = &mut *(call_data as *mut x11_sys::XIMPreeditCaretCallbackStruct);
+    caret.position = window.preedit.move_caret(caret.direction, caret.position) as c_int;
+    window.send_text_composition();
+    0
+}
+
 #[derive(Clone, Copy, PartialEq)]
 #[repr(C)]
 struct MwmHints {
@@ -510,273 +780,88 @@ pub const _NET_WM_STATE_TOGGLE: c_long = 2;/* toggle property  */
 
 /* move via keyboard */
 
-pub struct Dnd {
-    pub atoms: DndAtoms,
-    pub display: *mut x11_sys::Display,
-    pub type_list: Option<Vec<x11_sys::Atom >>,
-    pub selection: Option<CString>,
-}
-
-impl Dnd {
-    pub unsafe fn new(display: *mut x11_sys::Display) -> Dnd {
-        Dnd {
-            atoms: DndAtoms::new(display),
-            display,
-            type_list: None,
-            selection: None,
-        }
-    }
-    
-    /// Enables drag-and-drop for the given window.
-    pub unsafe fn enable_for_window(&mut self, window: x11_sys::Window) {
-        // To enable drag-and-drop for a window, we need to set the XDndAware property of the window
-        // to the version of XDnd we support.
-        
-        // I took this value from the Winit source code. Apparently, this is the latest version, and
-        // hasn't changed since 2002.
-        let version = 5 as c_ulong;
-        
-        x11_sys::XChangeProperty(
-            self.display,
-            window,
-            self.atoms.aware,
-            4, // XA_ATOM
-            32,
-            x11_sys::PropModeReplace as std::os::raw::c_int,
-            &version as *const c_ulong as *const std::os::raw::c_uchar,
-            1
-        );
-    }
-    
-    /// Handles a XDndEnter event.
-    pub unsafe fn handle_enter_event(&mut self, event: &x11_sys::XClientMessageEvent) {
-        // The XDndEnter event is sent by the source window when a drag begins. That is, the mouse
-        // enters the client rectangle of the target window. The target window is supposed to
-        // respond to this by requesting the list of types supported by the source.
-        
-        let source_window = event.data.l[0] as x11_sys::Window;
-        let has_more_types = event.data.l[1] & (1 << 0) != 0;
-        
-        // If the has_more_types flags is set, we have to obtain the list of supported types from
-        // the XDndTypeList property. Otherwise, we can obtain the list of supported types from the
-        // event itself.
-        self.type_list = Some(if has_more_types {
-            self.get_type_list_property(source_window)
-        } else {
-            event.data.l[2..4]
-                .iter()
-                .map( | &l | l as x11_sys::Atom)
-                .filter( | &atom | atom != x11_sys::None as x11_sys::Atom)
-                .collect()
-        });
-    }
-    
-    /// Handles a XDndDrop event.
-    pub unsafe fn handle_drop_event(&mut self, event: &x11_sys::XClientMessageEvent) {
-        // The XDndLeave event is sent by the source window when a drag is confirmed. That is, the
-        // mouse button is released while the mouse is inside the client rectangle of the target
-        // window. The target window is supposed to respond to this by requesting that the selection
-        // representing the thing being dragged is converted to the appropriate data type (in our
-        // case, a URI list). The source window, in turn, is supposed to respond this by sending a
-        // selection event containing the data to the source window.
-        
-        let target_window = event.window as x11_sys::Window;
-        self.convert_selection(target_window);
-        self.type_list = None;
-    }
-    
-    /// Handles a XDndLeave event.
-    pub unsafe fn handle_leave_event(&mut self, _event: &x11_sys::XClientMessageEvent) {
-        // The XDndLeave event is sent by the source window when a drag is canceled. That is, the
-        // mouse leaves the client rectangle of the target window. The target window is supposed to
-        // repsond this this by pretending the drag never happened.
-        
-        self.type_list = None;
-    }
-    
-    /// Handles a XDndPosition event.
-    pub unsafe fn handle_position_event(&mut self, event: &x11_sys::XClientMessageEvent) {
-        // The XDndPosition event is sent by the source window after the XDndEnter event, every time
-        // the mouse is moved. The target window is supposed to respond to this by sending a status
-        // event to the source window notifying whether it can accept the drag at this position.
-        
-        let target_window = event.window as x11_sys::Window;
-        let source_window = event.data.l[0] as x11_sys::Window;
-        
-        // For now we accept te drag if and only if the list of types supported by the source
-        // includes a uri list.
-        //
-        // TODO: Extend this test by taking into account the position of the mouse as well.
-        let accepted = self.type_list.as_ref().map_or(false, | type_list | type_list.contains(&self.atoms.uri_list));
-        
-        // Notify the source window whether we can accept the drag at this position.
-        self.send_status_event(source_window, target_window, accepted);
-        
-        // If this is the first time we've accepted the drag, request that the drag-and-drop
-        // selection be converted to a URI list. The target window is supposed to respond to this by
-        // sending a XSelectionEvent containing the URI list.
-        
-        // Since this is an asynchronous operation, its possible for another XDndPosition event to
-        // come in before the response to the first conversion request has been received. In this
-        // case, a second conversion request will be sent, the response to which will be ignored.
-        if accepted && self.selection.is_none() {
-        }
-    }
-    
-    /// Handles a XSelectionEvent.
-    pub unsafe fn handle_selection_event(&mut self, _event: &x11_sys::XSelectionEvent) {
-        // The XSelectionEvent is sent by the source window in response to a request by the source
-        // window to convert the selection representing the thing being dragged to the appropriate
-        // data type. This request is always sent in response to a XDndDrop event, so this event
-        // should only be received after a drop operation has completed.
-        
-        //let source_window = event.requestor;
-        //let selection = CString::new(self.get_selection_property(source_window)).unwrap();
-        
-        // TODO: Actually use the selection
-    }
-    
-    /// Gets the XDndSelection property from the source window.
-    pub unsafe fn get_selection_property(&mut self, source_window: x11_sys::Window) -> Vec< std::os::raw::c_uchar> {
-        let mut selection = Vec::new();
-        let mut offset = 0;
-        let length = 1024;
-        let mut actual_type = 0;
-        let mut actual_format = 0;
-        let mut nitems = 0;
-        let mut bytes_after = 0;
-        let mut prop = ptr::null_mut();
-        loop {
-            x11_sys::XGetWindowProperty(
-                self.display,
-                source_window,
-                self.atoms.selection,
-                offset,
-                length,
-                x11_sys::False as std::os::raw::c_int,
-                self.atoms.uri_list,
-                &mut actual_type,
-                &mut actual_format,
-                &mut nitems,
-                &mut bytes_after,
-                &mut prop,
-            );
-            selection.extend_from_slice(std::slice::from_raw_parts(prop as *mut  std::os::raw::c_uchar, nitems as usize));
-            x11_sys::XFree(prop as *mut c_void);
-            if bytes_after == 0 {
-                break;
-            }
-            offset += length;
-        };
-        selection
-    }
-    
-    /// Gets the XDndTypeList property from the source window.
-    pub unsafe fn get_type_list_property(&mut self, source_window: x11_sys::Window) -> Vec<x11_sys::Atom> {
-        let mut type_list = Vec::new();
-        let mut offset = 0;
-        let length = 1024;
-        let mut actual_type = 0;
-        let mut actual_format = 0;
-        let mut nitems = 0;
-        let mut bytes_after = 0;
-        let mut prop = ptr::null_mut();
-        loop {
-            x11_sys::XGetWindowProperty(
-                self.display,
-                source_window,
-                self.atoms.type_list,
-                offset,
-                length,
-                x11_sys::False as  std::os::raw::c_int,
-                4, // XA_ATOM,
-                &mut actual_type,
-                &mut actual_format,
-                &mut nitems,
-                &mut bytes_after,
-                &mut prop,
-            );
-            type_list.extend_from_slice(std::slice::from_raw_parts(prop as *mut x11_sys::Atom, nitems as usize));
-            x11_sys::XFree(prop as *mut c_void);
-            if bytes_after == 0 {
-                break;
-            }
-            offset += length;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn chars(text: &str) -> Option<Vec<char>> {
+        Some(text.chars().collect())
+    }
+    
+    fn composing(text: &str, cursor: usize) -> XimPreedit {
+        XimPreedit {text: text.chars().collect(), cursor}
+    }
+    
+    #[test]
+    fn draws_the_composition() {
+        let mut preedit = XimPreedit::default();
+        // start callback
+        preedit.clear();
+        preedit.draw(0, 0, chars("ni"), 2);
+        assert_eq!(preedit, composing("ni", 2));
+        preedit.draw(2, 0, chars("hao"), 5);
+        assert_eq!(preedit, composing("nihao", 5));
+        // the input method converts the whole string
+        preedit.draw(0, 5, chars("你好"), 2);
+        assert_eq!(preedit.to_event().text, "你好");
+        assert_eq!(preedit.to_event().cursor, 2);
+        // a highlight change keeps the text but can move the caret
+        preedit.draw(0, 2, None, 1);
+        assert_eq!(preedit, composing("你好", 1));
+        // deleting and out of range values are clamped to the text
+        preedit.draw(1, 9, Some(Vec::new()), 7);
+        assert_eq!(preedit, composing("你", 1));
+        preedit.draw(-3, 0, chars("a"), -1);
+        assert_eq!(preedit, composing("a你", 0));
+        // done callback
+        preedit.clear();
+        assert_eq!(preedit.to_event().text, "");
+        assert_eq!(preedit.cursor, 0);
+    }
+    
+    #[test]
+    fn moves_the_caret() {
+        let mut preedit = composing("abc", 1);
+        assert_eq!(preedit.move_caret(x11_sys::XIMForwardChar, 0), 2);
+        assert_eq!(preedit.move_caret(x11_sys::XIMForwardChar, 0), 3);
+        assert_eq!(preedit.move_caret(x11_sys::XIMForwardChar, 0), 3);
+        assert_eq!(preedit.move_caret(x11_sys::XIMLineStart, 0), 0);
+        assert_eq!(preedit.move_caret(x11_sys::XIMBackwardChar, 0), 0);
+        assert_eq!(preedit.move_caret(x11_sys::XIMLineEnd, 0), 3);
+        assert_eq!(preedit.move_caret(x11_sys::XIMAbsolutePosition, 1), 1);
+        assert_eq!(preedit.move_caret(x11_sys::XIMAbsolutePosition, 10), 3);
+        // directions we don't know, like moving by words, leave the caret alone
+        assert_eq!(preedit.move_caret(4, 0), 3);
+    }
+    
+    #[test]
+    fn commit_clears_the_composition() {
+        let mut preedit = composing("nihao", 5);
+        assert!(preedit.commit());
+        assert_eq!(preedit, XimPreedit::default());
+        // a plain key press commits without any composition
+        assert!(!preedit.commit());
+    }
+    
+    #[test]
+    fn decodes_xim_text() {
+        let mut multi_byte = b"ka\xe3\x81\x8b\0".to_vec();
+        let mut wide_char: Vec<c_int> = "かな".chars().map( | c | c as c_int).collect();
+        let text = | encoding_is_wchar, string | x11_sys::XIMText {
+            length: 2,
+            feedback: ptr::null_mut(),
+            encoding_is_wchar,
+            string,
         };
-        type_list
-    }
-    
-    /// Sends a XDndStatus event to the target window.
-    pub unsafe fn send_status_event(&mut self, source_window: x11_sys::Window, target_window: x11_sys::Window, accepted: bool) {
-        x11_sys::XSendEvent(
-            self.display,
-            source_window,
-            x11_sys::False as  std::os::raw::c_int,
-            x11_sys::NoEventMask as  std::os::raw::c_long,
-            &mut x11_sys::XClientMessageEvent {
-                type_: x11_sys::ClientMessage as  std::os::raw::c_int,
-                serial: 0,
-                send_event: 0,
-                display: self.display,
-                window: source_window,
-                message_type: self.atoms.status,
-                format: 32,
-                data: {
-                    let mut data = mem::zeroed::<x11_sys::XClientMessageEvent__bindgen_ty_1>();
-                    data.l[0] = target_window as c_long;
-                    data.l[1] = if accepted {1 << 0} else {0};
-                    data.l[2] = 0;
-                    data.l[3] = 0;
-                    data.l[4] = if accepted {self.atoms.action_private} else {self.atoms.none} as c_long;
-                    data
-                }
-            } as *mut x11_sys::XClientMessageEvent as *mut x11_sys::XEvent
-        );
-        x11_sys::XFlush(self.display);
-    }
-    
-    // Requests that the selection representing the thing being dragged is converted to the
-    // appropriate data type (in our case, a URI list).
-    pub unsafe fn convert_selection(&self, target_window: x11_sys::Window) {
-        x11_sys::XConvertSelection(
-            self.display,
-            self.atoms.selection,
-            self.atoms.uri_list,
-            self.atoms.selection,
-            target_window,
-            x11_sys::CurrentTime as x11_sys::Time,
-        );
-    }
-}
-
-pub struct DndAtoms {
-    pub action_private: x11_sys::Atom,
-    pub aware: x11_sys::Atom,
-    pub drop: x11_sys::Atom,
-    pub enter: x11_sys::Atom,
-    pub leave: x11_sys::Atom,
-    pub none: x11_sys::Atom,
-    pub position: x11_sys::Atom,
-    pub selection: x11_sys::Atom,
-    pub status: x11_sys::Atom,
-    pub type_list: x11_sys::Atom,
-    pub uri_list: x11_sys::Atom,
-}
-
-impl DndAtoms {
-    pub unsafe fn new(display: *mut x11_sys::Display) -> DndAtoms {
-        DndAtoms {
-            action_private: x11_sys::XInternAtom(display, "XdndActionPrivate\0".as_ptr() as *const _, 0),
-            aware: x11_sys::XInternAtom(display, "XdndAware\0".as_ptr() as *const _, 0),
-            drop: x11_sys::XInternAtom(display, "XdndDrop\0".as_ptr() as *const _, 0),
-            enter: x11_sys::XInternAtom(display, "XdndEnter\0".as_ptr() as *const _, 0),
-            leave: x11_sys::XInternAtom(display, "XdndLeave\0".as_ptr() as *const _, 0),
-            none: x11_sys::XInternAtom(display, "None\0".as_ptr() as *const _, 0),
-            position: x11_sys::XInternAtom(display, "XdndPosition\0".as_ptr() as *const _, 0),
-            selection: x11_sys::XInternAtom(display, "XdndSelection\0".as_ptr() as *const _, 0),
-            status: x11_sys::XInternAtom(display, "XdndStatus\0".as_ptr() as *const _, 0),
-            type_list: x11_sys::XInternAtom(display, "XdndTypeList\0".as_ptr() as *const _, 0),
-            uri_list: x11_sys::XInternAtom(display, "text/uri-list\0".as_ptr() as *const _, 0),
+        unsafe {
+            let mb = text(0, x11_sys::XIMTextString {multi_byte: multi_byte.as_mut_ptr() as *mut c_char});
+            assert_eq!(xim_text_chars(&mb), chars("kaか"));
+            let wc = text(1, x11_sys::XIMTextString {wide_char: wide_char.as_mut_ptr()});
+            assert_eq!(xim_text_chars(&wc), chars("かな"));
+            // no string is a feedback only change, no text at all deletes
+            let feedback = text(0, x11_sys::XIMTextString {multi_byte: ptr::null_mut()});
+            assert_eq!(xim_text_chars(&feedback), None);
+            assert_eq!(xim_text_chars(ptr::null()), Some(Vec::new()));
         }
     }
 }
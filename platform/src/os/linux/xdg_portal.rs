@@ -0,0 +1,315 @@
+// File dialogs through the org.freedesktop.portal.FileChooser interface of the XDG desktop portal.
+// Each dialog runs on its own thread, talking to the session bus with the minimal client in dbus.rs.
+
+use {
+    std::{
+        ffi::OsString,
+        os::unix::ffi::OsStringExt,
+        path::PathBuf,
+        sync::mpsc::{channel, Receiver, Sender},
+    },
+    self::super::dbus::{DbusConnection, DbusError, DbusMessage, DbusValue},
+    crate::{
+        makepad_live_id::LiveId,
+        file_dialogs::{
+            FileDialog,
+            FileDialogEvent,
+            FileDialogFallbackEvent,
+            FileDialogKind,
+            FileDialogResult,
+        },
+    }
+};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const FILE_CHOOSER: &str = "org.freedesktop.portal.FileChooser";
+const REQUEST: &str = "org.freedesktop.portal.Request";
+
+pub enum PortalResponse {
+    Done(FileDialogEvent),
+    /// There is no portal to talk to, the app should show its in-app dialog
+    Unavailable(FileDialogFallbackEvent),
+}
+
+pub struct PortalChannel {
+    pub sender: Sender<PortalResponse>,
+    pub receiver: Receiver<PortalResponse>,
+}
+
+impl Default for PortalChannel {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PortalError {
+    Unavailable,
+    Failed(String),
+}
+
+impl From<DbusError> for PortalError {
+    fn from(err: DbusError) -> Self {
+        match err {
+            DbusError::Reply(name, msg) => match name.as_str() {
+                "org.freedesktop.DBus.Error.ServiceUnknown" |
+                "org.freedesktop.DBus.Error.NameHasNoOwner" |
+                "org.freedesktop.DBus.Error.UnknownMethod" |
+                "org.freedesktop.DBus.Error.UnknownInterface" |
+                "org.freedesktop.DBus.Error.UnknownObject" => PortalError::Unavailable,
+                _ => PortalError::Failed(format!("{}: {}", name, msg))
+            },
+            // without a bus there is no portal either
+            DbusError::Io(_) => PortalError::Unavailable,
+            DbusError::Protocol(msg) => PortalError::Failed(msg),
+        }
+    }
+}
+
+pub fn open_portal_file_dialog(dialog_id: LiveId, kind: FileDialogKind, dialog: FileDialog, sender: Sender<PortalResponse>) {
+    std::thread::spawn(move || {
+        let result = DbusConnection::session()
+            .map_err(PortalError::from)
+            .and_then( | mut conn | portal_file_dialog(&mut conn, dialog_id, kind, &dialog));
+        let _ = sender.send(match result {
+            Ok(result) => PortalResponse::Done(FileDialogEvent {dialog_id, result}),
+            Err(PortalError::Unavailable) => PortalResponse::Unavailable(FileDialogFallbackEvent {dialog_id, kind, dialog}),
+            Err(PortalError::Failed(err)) => PortalResponse::Done(FileDialogEvent {dialog_id, result: FileDialogResult::Error(err)}),
+        });
+    });
+}
+
+fn portal_options(dialog_id: LiveId, kind: FileDialogKind, dialog: &FileDialog) -> DbusValue {
+    let mut options = vec![
+        ("handle_token", DbusValue::Str(request_token(dialog_id))),
+        ("modal", DbusValue::Bool(true)),
+    ];
+    if kind.is_folder() {
+        options.push(("directory", DbusValue::Bool(true)));
+    }
+    else if !dialog.filters.is_empty() {
+        // a(sa(us)), where 0 marks a glob pattern
+        options.push(("filters", DbusValue::Array("(sa(us))".into(), dialog.filters.iter().map( | filter | {
+            DbusValue::Struct(vec![
+                DbusValue::Str(filter.description.clone()),
+                DbusValue::Array("(us)".into(), filter.extensions.iter().map( | ext | {
+                    DbusValue::Struct(vec![DbusValue::U32(0), DbusValue::Str(format!("*.{}", ext.trim_start_matches('.')))])
+                }).collect())
+            ])
+        }).collect())));
+    }
+    if kind == FileDialogKind::SaveFile {
+        if let Some(filename) = &dialog.filename {
+            options.push(("current_name", DbusValue::Str(filename.clone())));
+        }
+    }
+    if let Some(location) = &dialog.location {
+        // a nul terminated byte string
+        let mut bytes = location.clone().into_os_string().into_vec();
+        bytes.push(0);
+        options.push(("current_folder", DbusValue::Array("y".into(), bytes.into_iter().map(DbusValue::Byte).collect())));
+    }
+    DbusValue::dict(options)
+}
+
+fn request_token(dialog_id: LiveId) -> String {
+    format!("makepad_{:x}", dialog_id.0)
+}
+
+fn portal_file_dialog(conn: &mut DbusConnection, dialog_id: LiveId, kind: FileDialogKind, dialog: &FileDialog) -> Result<FileDialogResult, PortalError> {
+    // the portal answers with a Response signal on a request object, we subscribe before
+    // making the call so we can't miss it
+    conn.call(DbusMessage::method_call(
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "AddMatch",
+        vec![DbusValue::Str(format!("type='signal',interface='{}',member='Response'", REQUEST))]
+    )) ?;
+
+    let (method, default_title) = match kind {
+        FileDialogKind::SaveFile => ("SaveFile", "Save File"),
+        FileDialogKind::SelectFile => ("OpenFile", "Open File"),
+        // the portal has no separate save folder dialog
+        FileDialogKind::SaveFolder | FileDialogKind::SelectFolder => ("OpenFile", "Select Folder"),
+    };
+    let title = dialog.title.clone().unwrap_or_else( || default_title.to_string());
+    let reply = conn.call(DbusMessage::method_call(
+        PORTAL_DESTINATION,
+        PORTAL_PATH,
+        FILE_CHOOSER,
+        method,
+        vec![
+            DbusValue::Str(String::new()),
+            DbusValue::Str(title),
+            portal_options(dialog_id, kind, dialog),
+        ]
+    )) ?;
+
+    // older portals ignore handle_token, so use the handle they give back
+    let handle = match reply.body.first().and_then( | v | v.as_str()) {
+        Some(handle) => handle.to_string(),
+        None => format!(
+            "{}/request/{}/{}",
+            PORTAL_PATH,
+            conn.unique_name.trim_start_matches(':').replace('.', "_"),
+            request_token(dialog_id)
+        )
+    };
+    let response = conn.wait_for( | m | m.is_signal(REQUEST, "Response") && m.path.as_deref() == Some(&handle)) ?;
+
+    match response.body.first().and_then( | v | v.as_u32()) {
+        Some(0) => {
+            let uris = response.body.get(1).and_then( | r | r.dict_get("uris")).and_then( | u | u.as_array()).unwrap_or(&[]);
+            let paths: Vec<PathBuf> = uris.iter().filter_map( | uri | uri.as_str().and_then(file_uri_to_path)).collect();
+            if paths.is_empty() {
+                return Ok(FileDialogResult::Cancelled)
+            }
+            Ok(FileDialogResult::Selected(paths))
+        }
+        Some(1) => Ok(FileDialogResult::Cancelled),
+        _ => Err(PortalError::Failed("the file dialog was closed by the portal".into()))
+    }
+}
+
+pub (crate) fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://") ?;
+    // skip the host part, which is empty or localhost
+    let path = &rest[rest.find('/') ?..];
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(v) = hex.and_then( | hex | u8::from_str_radix(hex, 16).ok()) {
+                out.push(v);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    Some(PathBuf::from(OsString::from_vec(out)))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{
+            io::Write,
+            os::unix::net::{UnixListener, UnixStream},
+            path::PathBuf,
+        },
+        super::*,
+        super::super::dbus::{read_line, DbusMessageType},
+    };
+
+    // a session bus and portal in one, answering a single connection
+    fn mock_portal(name: &str, respond: impl Fn(&mut UnixStream, &DbusMessage) + Send + 'static) -> String {
+        let path = std::env::temp_dir().join(format!("makepad_portal_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut nul = [0u8];
+            std::io::Read::read_exact(&mut stream, &mut nul).unwrap();
+            assert!(read_line(&mut stream).unwrap().starts_with("AUTH EXTERNAL "));
+            stream.write_all(b"OK 0123456789abcdef0123456789abcdef\r\n").unwrap();
+            assert_eq!(read_line(&mut stream).unwrap(), "BEGIN");
+            let mut serial = 100;
+            while let Ok(call) = DbusMessage::read_from(&mut stream) {
+                assert_eq!(call.message_type, DbusMessageType::MethodCall);
+                let reply = match call.member.as_deref() {
+                    Some("Hello") => DbusMessage::method_return(&call, vec![DbusValue::Str(":1.42".into())]),
+                    Some("AddMatch") => DbusMessage::method_return(&call, vec![]),
+                    _ => {
+                        respond(&mut stream, &call);
+                        continue
+                    }
+                };
+                serial += 1;
+                stream.write_all(&reply.encode(serial)).unwrap();
+            }
+        });
+        format!("unix:path={}", path.display())
+    }
+
+    fn run(address: &str, kind: FileDialogKind, dialog: FileDialog) -> Result<FileDialogResult, PortalError> {
+        let mut conn = DbusConnection::connect(address).map_err(PortalError::from) ?;
+        assert_eq!(conn.unique_name, ":1.42");
+        portal_file_dialog(&mut conn, LiveId(0x1234), kind, &dialog)
+    }
+
+    #[test]
+    fn select_file() {
+        let address = mock_portal("select", | stream, call | {
+            assert_eq!(call.interface.as_deref(), Some(FILE_CHOOSER));
+            assert_eq!(call.member.as_deref(), Some("OpenFile"));
+            assert_eq!(call.body[1].as_str(), Some("Pick one"));
+            let options = &call.body[2];
+            assert_eq!(options.dict_get("handle_token").and_then( | v | v.as_str()), Some("makepad_1234"));
+            let filters = options.dict_get("filters").and_then( | v | v.as_array()).unwrap();
+            assert_eq!(filters[0], DbusValue::Struct(vec![
+                DbusValue::Str("Rust".into()),
+                DbusValue::Array("(us)".into(), vec![DbusValue::Struct(vec![DbusValue::U32(0), DbusValue::Str("*.rs".into())])])
+            ]));
+
+            let handle = "/org/freedesktop/portal/desktop/request/1_42/makepad_1234";
+            let reply = DbusMessage::method_return(call, vec![DbusValue::ObjectPath(handle.into())]);
+            stream.write_all(&reply.encode(1)).unwrap();
+            // a response for someone else's request first
+            let other = DbusMessage::signal("/other", REQUEST, "Response", vec![DbusValue::U32(1), DbusValue::dict(vec![])]);
+            stream.write_all(&other.encode(2)).unwrap();
+            let uris = DbusValue::Array("s".into(), vec![DbusValue::Str("file:///tmp/some%20file.rs".into())]);
+            let response = DbusMessage::signal(handle, REQUEST, "Response", vec![DbusValue::U32(0), DbusValue::dict(vec![("uris", uris)])]);
+            stream.write_all(&response.encode(3)).unwrap();
+        });
+        let dialog = FileDialog::new().set_title("Pick one".into()).add_filter("Rust".into(), vec!["rs".into()]);
+        match run(&address, FileDialogKind::SelectFile, dialog) {
+            Ok(FileDialogResult::Selected(paths)) => assert_eq!(paths, vec![PathBuf::from("/tmp/some file.rs")]),
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn save_file_cancelled() {
+        let address = mock_portal("save", | stream, call | {
+            assert_eq!(call.member.as_deref(), Some("SaveFile"));
+            let options = &call.body[2];
+            assert_eq!(options.dict_get("current_name").and_then( | v | v.as_str()), Some("notes.txt"));
+            let folder = options.dict_get("current_folder").and_then( | v | v.as_array()).unwrap();
+            assert_eq!(folder.last(), Some(&DbusValue::Byte(0)));
+
+            let handle = "/org/freedesktop/portal/desktop/request/1_42/makepad_1234";
+            let reply = DbusMessage::method_return(call, vec![DbusValue::ObjectPath(handle.into())]);
+            stream.write_all(&reply.encode(1)).unwrap();
+            let response = DbusMessage::signal(handle, REQUEST, "Response", vec![DbusValue::U32(1), DbusValue::dict(vec![])]);
+            stream.write_all(&response.encode(2)).unwrap();
+        });
+        let dialog = FileDialog::new().set_filename("notes.txt".into()).set_location("/tmp".into());
+        assert!(matches!(run(&address, FileDialogKind::SaveFile, dialog), Ok(FileDialogResult::Cancelled)));
+    }
+
+    #[test]
+    fn no_portal_service() {
+        let address = mock_portal("missing", | stream, call | {
+            let reply = DbusMessage::error(call, "org.freedesktop.DBus.Error.ServiceUnknown", "no portal");
+            stream.write_all(&reply.encode(1)).unwrap();
+        });
+        assert!(matches!(run(&address, FileDialogKind::SelectFolder, FileDialog::new()), Err(PortalError::Unavailable)));
+    }
+
+    #[test]
+    fn file_uris() {
+        assert_eq!(file_uri_to_path("file:///home/me/a%C3%A9.txt"), Some(PathBuf::from("/home/me/aé.txt")));
+        assert_eq!(file_uri_to_path("file://localhost/tmp/x"), Some(PathBuf::from("/tmp/x")));
+        assert_eq!(file_uri_to_path("https://example.com/x"), None);
+    }
+}
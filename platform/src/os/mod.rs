@@ -37,6 +37,9 @@ pub use crate::os::linux::android::android_media::*;
 #[cfg(target_os = "linux")]
 pub use crate::os::linux::linux_media::*;
 
+#[cfg(all(target_os = "linux", not(linux_direct)))]
+pub mod software;
+
 //#[cfg(target_os = "linux")]
 //pub use crate::os::linux::*;
 
@@ -0,0 +1,6 @@
+pub mod value;
+pub mod shader_vm;
+pub mod rasterizer;
+pub mod software;
+
+pub use self::software::CxSoftware;
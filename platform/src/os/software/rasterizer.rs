@@ -0,0 +1,359 @@
+use super::value::Dual;
+
+/// A CPU render target, colors are premultiplied RGBA in 0..1
+#[derive(Clone, Default)]
+pub struct SoftwareTarget {
+    pub width: usize,
+    pub height: usize,
+    pub color: Vec<[f32; 4]>,
+    pub depth: Vec<f32>,
+}
+
+impl SoftwareTarget {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            color: vec![[0.0; 4]; width * height],
+            depth: vec![1.0; width * height],
+        }
+    }
+
+    /// Returns true when the target had to be (re)allocated
+    pub fn resize(&mut self, width: usize, height: usize) -> bool {
+        if self.width == width && self.height == height {
+            return false
+        }
+        *self = Self::new(width, height);
+        true
+    }
+
+    pub fn clear_color(&mut self, color: [f32; 4]) {
+        self.color.fill(color);
+    }
+
+    pub fn clear_depth(&mut self, depth: f32) {
+        self.depth.fill(depth);
+    }
+
+    /// Packs the target into the same u32 BGRA layout as TextureFormat::VecBGRAu8_32
+    pub fn to_bgra_u32(&self) -> Vec<u32> {
+        self.color.iter().map( | c | {
+            let b = | v: f32 | (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u32;
+            (b(c[3]) << 24) | (b(c[0]) << 16) | (b(c[1]) << 8) | b(c[2])
+        }).collect()
+    }
+}
+
+pub enum TexelData<'a> {
+    Bgra8(&'a [u32]),
+    R8(&'a [u8]),
+    Rg8(&'a [u8]),
+    Rf32(&'a [f32]),
+    Rgbaf32(&'a [f32]),
+    Target(&'a [[f32; 4]]),
+}
+
+/// A read only view on texture pixels the shader interpreter can sample from
+pub struct TextureView<'a> {
+    pub width: usize,
+    pub height: usize,
+    // in pixels
+    pub stride: usize,
+    pub data: TexelData<'a>,
+}
+
+impl<'a> TextureView<'a> {
+    fn texel(&self, x: usize, y: usize) -> [f32; 4] {
+        let i = y * self.stride + x;
+        match &self.data {
+            TexelData::Bgra8(d) => d.get(i).map( | c | [
+                ((c >> 16) & 0xff) as f32 / 255.0,
+                ((c >> 8) & 0xff) as f32 / 255.0,
+                (c & 0xff) as f32 / 255.0,
+                ((c >> 24) & 0xff) as f32 / 255.0,
+            ]),
+            TexelData::R8(d) => d.get(i).map( | r | [*r as f32 / 255.0, 0.0, 0.0, 1.0]),
+            TexelData::Rg8(d) => d.get(i * 2..i * 2 + 2).map( | c | [c[0] as f32 / 255.0, c[1] as f32 / 255.0, 0.0, 1.0]),
+            TexelData::Rf32(d) => d.get(i).map( | r | [*r, 0.0, 0.0, 1.0]),
+            TexelData::Rgbaf32(d) => d.get(i * 4..i * 4 + 4).map( | c | [c[0], c[1], c[2], c[3]]),
+            TexelData::Target(d) => d.get(i).cloned(),
+        }.unwrap_or([0.0; 4])
+    }
+
+    /// Bilinear sample with clamp to edge addressing
+    pub fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+        if self.width == 0 || self.height == 0 || !u.is_finite() || !v.is_finite() {
+            return [0.0; 4]
+        }
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+        let cx = | x: f32 | (x.max(0.0) as usize).min(self.width - 1);
+        let cy = | y: f32 | (y.max(0.0) as usize).min(self.height - 1);
+        let (xa, xb, ya, yb) = (cx(x0), cx(x0 + 1.0), cy(y0), cy(y0 + 1.0));
+        let t00 = self.texel(xa, ya);
+        let t10 = self.texel(xb, ya);
+        let t01 = self.texel(xa, yb);
+        let t11 = self.texel(xb, yb);
+        let mut out = [0.0; 4];
+        for i in 0..4 {
+            let top = t00[i] + (t10[i] - t00[i]) * fx;
+            let bottom = t01[i] + (t11[i] - t01[i]) * fx;
+            out[i] = top + (bottom - top) * fy;
+        }
+        out
+    }
+}
+
+/// The output of the vertex stage, clip space position plus the flattened pixel shader inputs
+pub struct RasterVertex {
+    pub pos: [f32; 4],
+    pub inputs: Vec<f32>,
+}
+
+struct ScreenVertex {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+}
+
+fn edge(ax: f32, ay: f32, bx: f32, by: f32, px: f32, py: f32) -> f32 {
+    (bx - ax) * (py - ay) - (by - ay) * (px - ax)
+}
+
+// top-left fill rule for the winding we normalize to, so the shared edge of the
+// two triangles of a quad is only filled once and blending doesn't double up
+fn is_top_left(ax: f32, ay: f32, bx: f32, by: f32) -> bool {
+    let dx = bx - ax;
+    let dy = by - ay;
+    (dy == 0.0 && dx > 0.0) || dy < 0.0
+}
+
+/// Rasterizes one triangle with a LEQUAL depth test and premultiplied alpha blending
+/// (ONE, ONE_MINUS_SRC_ALPHA), the same state the GPU backends set up.
+/// The pixel closure receives the perspective correct interpolated inputs with their screen space derivatives.
+pub fn rasterize_triangle(
+    target: &mut SoftwareTarget,
+    verts: [&RasterVertex; 3],
+    inputs: &mut Vec<Dual>,
+    mut pixel: impl FnMut(&[Dual]) -> [f32; 4]
+) {
+    // we don't clip against the near plane, anything behind the camera is dropped
+    if verts.iter().any( | v | !(v.pos[3] > 0.0)) {
+        return
+    }
+    let (w, h) = (target.width as f32, target.height as f32);
+    let mut sv: Vec<ScreenVertex> = verts.iter().map( | v | {
+        let inv_w = 1.0 / v.pos[3];
+        ScreenVertex {
+            x: (v.pos[0] * inv_w + 1.0) * 0.5 * w,
+            y: (1.0 - v.pos[1] * inv_w) * 0.5 * h,
+            z: v.pos[2] * inv_w,
+            inv_w,
+        }
+    }).collect();
+    let mut order = [0, 1, 2];
+    let mut area = edge(sv[0].x, sv[0].y, sv[1].x, sv[1].y, sv[2].x, sv[2].y);
+    if !(area.abs() > 0.0) || !area.is_finite() {
+        return
+    }
+    if area < 0.0 {
+        sv.swap(1, 2);
+        order.swap(1, 2);
+        area = -area;
+    }
+    let (v0, v1, v2) = (&sv[0], &sv[1], &sv[2]);
+    let min_x = v0.x.min(v1.x).min(v2.x).floor().max(0.0) as usize;
+    let max_x = (v0.x.max(v1.x).max(v2.x).ceil().min(w)).max(0.0) as usize;
+    let min_y = v0.y.min(v1.y).min(v2.y).floor().max(0.0) as usize;
+    let max_y = (v0.y.max(v1.y).max(v2.y).ceil().min(h)).max(0.0) as usize;
+
+    let tl = [
+        is_top_left(v1.x, v1.y, v2.x, v2.y),
+        is_top_left(v2.x, v2.y, v0.x, v0.y),
+        is_top_left(v0.x, v0.y, v1.x, v1.y),
+    ];
+    // screen space derivatives of the barycentrics are constant over the triangle
+    let db = [
+        (-(v2.y - v1.y) / area, (v2.x - v1.x) / area),
+        (-(v0.y - v2.y) / area, (v0.x - v2.x) / area),
+        (-(v1.y - v0.y) / area, (v1.x - v0.x) / area),
+    ];
+    let sources = [&verts[order[0]].inputs, &verts[order[1]].inputs, &verts[order[2]].inputs];
+    let input_count = sources.iter().map( | s | s.len()).min().unwrap_or(0);
+
+    for py in min_y..max_y {
+        let fy = py as f32 + 0.5;
+        for px in min_x..max_x {
+            let fx = px as f32 + 0.5;
+            let e = [
+                edge(v1.x, v1.y, v2.x, v2.y, fx, fy),
+                edge(v2.x, v2.y, v0.x, v0.y, fx, fy),
+                edge(v0.x, v0.y, v1.x, v1.y, fx, fy),
+            ];
+            if (0..3).any( | i | e[i] < 0.0 || (e[i] == 0.0 && !tl[i])) {
+                continue
+            }
+            let b = [e[0] / area, e[1] / area, e[2] / area];
+            let z = b[0] * v0.z + b[1] * v1.z + b[2] * v2.z;
+            let index = py * target.width + px;
+            if !(z <= target.depth[index]) {
+                continue
+            }
+            // perspective correct weights, as duals so dFdx/dFdy fall out of the interpolation
+            let q = [
+                Dual {v: b[0], dx: db[0].0, dy: db[0].1} * Dual::new(v0.inv_w),
+                Dual {v: b[1], dx: db[1].0, dy: db[1].1} * Dual::new(v1.inv_w),
+                Dual {v: b[2], dx: db[2].0, dy: db[2].1} * Dual::new(v2.inv_w),
+            ];
+            let sum = q[0] + q[1] + q[2];
+            let p = [q[0] / sum, q[1] / sum, q[2] / sum];
+            inputs.clear();
+            for k in 0..input_count {
+                inputs.push(
+                    p[0] * Dual::new(sources[0][k]) + p[1] * Dual::new(sources[1][k]) + p[2] * Dual::new(sources[2][k])
+                );
+            }
+            let src = pixel(inputs);
+            let src = src.map( | c | if c.is_finite() {c.clamp(0.0, 1.0)} else {0.0});
+            let dst = &mut target.color[index];
+            let inv_a = 1.0 - src[3];
+            for i in 0..4 {
+                dst[i] = src[i] + dst[i] * inv_a;
+            }
+            target.depth[index] = z;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD_INDICES: [usize; 6] = [0, 1, 2, 2, 3, 0];
+
+    /// Draws a rect given in pixels the way DrawQuad does, a unit quad geometry scaled by the
+    /// instance rect, and passes the geometry position on to the pixel closure
+    fn draw_rect(
+        target: &mut SoftwareTarget,
+        rect: [f32; 4],
+        z: f32,
+        mut pixel: impl FnMut(&[Dual]) -> [f32; 4]
+    ) {
+        let (w, h) = (target.width as f32, target.height as f32);
+        let vertices: Vec<RasterVertex> = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].iter().map( | geom: &[f32; 2] | {
+            let x = rect[0] + geom[0] * rect[2];
+            let y = rect[1] + geom[1] * rect[3];
+            RasterVertex {
+                pos: [x / w * 2.0 - 1.0, 1.0 - y / h * 2.0, z, 1.0],
+                inputs: geom.to_vec(),
+            }
+        }).collect();
+        let mut inputs = Vec::new();
+        for triangle in QUAD_INDICES.chunks_exact(3) {
+            let verts = [&vertices[triangle[0]], &vertices[triangle[1]], &vertices[triangle[2]]];
+            rasterize_triangle(target, verts, &mut inputs, &mut pixel);
+        }
+    }
+
+    fn covered(target: &SoftwareTarget) -> Vec<(usize, usize)> {
+        (0..target.height).flat_map( | y | (0..target.width).map(move | x | (x, y)))
+            .filter( | (x, y) | target.color[y * target.width + x][3] > 0.0)
+            .collect()
+    }
+
+    #[test]
+    fn fills_a_quad_once_per_pixel() {
+        let mut target = SoftwareTarget::new(8, 8);
+        draw_rect(&mut target, [2.0, 2.0, 4.0, 3.0], 0.0, | _ | [0.5, 0.0, 0.0, 0.5]);
+        let expected: Vec<_> = (2..5).flat_map( | y | (2..6).map(move | x | (x, y))).collect();
+        assert_eq!(covered(&target), expected);
+        // the diagonal shared by both triangles must not get blended twice
+        for (x, y) in expected {
+            assert_eq!(target.color[y * 8 + x], [0.5, 0.0, 0.0, 0.5]);
+        }
+    }
+
+    #[test]
+    fn clips_to_the_target() {
+        let mut target = SoftwareTarget::new(8, 8);
+        draw_rect(&mut target, [-4.0, -4.0, 7.0, 20.0], 0.0, | _ | [1.0, 1.0, 1.0, 1.0]);
+        let expected: Vec<_> = (0..8).flat_map( | y | (0..3).map(move | x | (x, y))).collect();
+        assert_eq!(covered(&target), expected);
+
+        // entirely outside, and behind the camera
+        let mut target = SoftwareTarget::new(8, 8);
+        draw_rect(&mut target, [10.0, 2.0, 4.0, 4.0], 0.0, | _ | [1.0; 4]);
+        let behind = RasterVertex {pos: [0.0, 0.0, 0.0, -1.0], inputs: Vec::new()};
+        let front = RasterVertex {pos: [1.0, 1.0, 0.0, 1.0], inputs: Vec::new()};
+        rasterize_triangle(&mut target, [&behind, &front, &front], &mut Vec::new(), | _ | [1.0; 4]);
+        assert!(covered(&target).is_empty());
+    }
+
+    #[test]
+    fn blends_premultiplied_alpha() {
+        let mut target = SoftwareTarget::new(2, 2);
+        target.clear_color([0.0, 0.0, 1.0, 1.0]);
+        draw_rect(&mut target, [0.0, 0.0, 2.0, 2.0], 0.0, | _ | [0.5, 0.0, 0.0, 0.5]);
+        assert_eq!(target.color[0], [0.5, 0.0, 0.5, 1.0]);
+        // out of range shader output is clamped before blending
+        draw_rect(&mut target, [0.0, 0.0, 2.0, 2.0], 0.0, | _ | [2.0, -1.0, f32::NAN, 1.0]);
+        assert_eq!(target.color[3], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(target.to_bgra_u32()[3], 0xffff0000);
+    }
+
+    #[test]
+    fn draws_instances_with_a_depth_test() {
+        let mut target = SoftwareTarget::new(8, 4);
+        let instances = [
+            ([0.0, 0.0, 4.0, 4.0], 0.5, [1.0, 0.0, 0.0, 1.0]),
+            ([2.0, 0.0, 4.0, 4.0], 0.5, [0.0, 1.0, 0.0, 1.0]),
+            ([4.0, 0.0, 4.0, 4.0], 0.8, [0.0, 0.0, 1.0, 1.0]),
+        ];
+        for (rect, z, color) in instances {
+            draw_rect(&mut target, rect, z, | _ | color);
+        }
+        let row: Vec<_> = target.color[0..8].iter().map( | c | [c[0], c[1], c[2]]).collect();
+        // an equal depth passes, so the second instance draws over the first,
+        // the third is further away than the second where they overlap
+        assert_eq!(row, vec![
+            [1.0, 0.0, 0.0], [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0], [0.0, 1.0, 0.0], [0.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0], [0.0, 0.0, 1.0],
+        ]);
+        assert!((target.depth[7] - 0.8).abs() < 1e-5);
+    }
+
+    #[test]
+    fn interpolates_inputs_with_derivatives() {
+        let mut target = SoftwareTarget::new(4, 4);
+        let mut samples = Vec::new();
+        draw_rect(&mut target, [0.0, 0.0, 4.0, 4.0], 0.0, | inputs | {
+            samples.push((inputs[0], inputs[1]));
+            [0.0; 4]
+        });
+        assert_eq!(samples.len(), 16);
+        for (x, y) in samples {
+            assert!((x.dx - 0.25).abs() < 1e-5 && x.dy.abs() < 1e-5);
+            assert!((y.dy - 0.25).abs() < 1e-5 && y.dx.abs() < 1e-5);
+            // pixel centers, so a quarter pixel step starts at 1/8
+            assert!(((x.v - 0.125) * 4.0).fract().abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn samples_textures_bilinearly_with_clamping() {
+        let data = [0xff000000u32, 0xffffffff];
+        let view = TextureView {width: 2, height: 1, stride: 2, data: TexelData::Bgra8(&data)};
+        assert_eq!(view.sample(0.25, 0.5), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(view.sample(0.5, 0.5), [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(view.sample(-3.0, 0.5), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(view.sample(3.0, 2.0), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(view.sample(f32::NAN, 0.5), [0.0; 4]);
+    }
+}
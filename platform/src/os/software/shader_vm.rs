@@ -0,0 +1,830 @@
+use {
+    std::{
+        collections::HashMap,
+        rc::Rc,
+    },
+    crate::{
+        makepad_live_id::*,
+        makepad_math::Vec4,
+        makepad_shader_compiler::{
+            shader_ast::*,
+            shader_registry::ShaderRegistry,
+            swizzle::Swizzle,
+        },
+    },
+    super::{
+        value::*,
+        rasterizer::TextureView,
+    },
+};
+
+/// Interprets the analysed shader AST directly, so we don't need to generate
+/// and compile any code to run a draw shader on the CPU.
+pub struct ShaderVm<'a> {
+    pub registry: &'a ShaderRegistry,
+    pub def: &'a DrawShaderDef,
+    pub const_table: &'a DrawShaderConstTable,
+    pub textures: Vec<Option<TextureView<'a>>>,
+    /// the values of the draw shader fields (self.x), indexed like def.fields
+    pub fields: Vec<Value>,
+    live_values: Vec<(ValuePtr, Value)>,
+    swizzles: HashMap<LiveId, Vec<usize>>,
+    frames: Vec<Frame>,
+}
+
+struct Frame {
+    fn_ptr: FnPtr,
+    const_offset: Option<usize>,
+    vars: Vec<(Ident, ScopeSymShadow, Value)>,
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+// the for loop variable isn't given a shadow by the analyser, var lookups fall back to it by ident
+const FOR_LOOP_SHADOW: ScopeSymShadow = ScopeSymShadow(usize::MAX);
+
+impl<'a> ShaderVm<'a> {
+    pub fn new(registry: &'a ShaderRegistry, def: &'a DrawShaderDef, const_table: &'a DrawShaderConstTable, live_uniforms: &[f32]) -> Self {
+        // live values are packed in all_live_refs order, like the glsl live_table
+        let mut live_values = Vec::new();
+        let mut slot = 0;
+        for (value_ptr, ty) in def.all_live_refs.borrow().iter() {
+            live_values.push((*value_ptr, Value::from_slots(ty, live_uniforms.get(slot..).unwrap_or(&[]))));
+            slot += ty.slots();
+        }
+        let mut fields = Vec::with_capacity(def.fields.len());
+        let mut texture_slot = 0;
+        for field in &def.fields {
+            if let DrawShaderFieldKind::Texture {..} = field.kind {
+                fields.push(Value::Texture(texture_slot));
+                texture_slot += 1;
+            }
+            else {
+                fields.push(Value::zero(field.ty_expr.ty.borrow().as_ref().unwrap()));
+            }
+        }
+        Self {
+            registry,
+            def,
+            const_table,
+            textures: Vec::new(),
+            fields,
+            live_values,
+            swizzles: HashMap::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Unpacks all the fields of one kind from a packed float buffer, fields are packed
+    /// in declaration order just like the glsl unpackers read them
+    pub fn unpack_fields(&mut self, slots: &[f32], filter: impl Fn(&DrawShaderFieldKind) -> bool) {
+        let mut offset = 0;
+        for (index, field) in self.def.fields.iter().enumerate() {
+            if filter(&field.kind) {
+                let ty = field.ty_expr.ty.borrow();
+                let ty = ty.as_ref().unwrap();
+                self.fields[index] = Value::from_slots(ty, slots.get(offset..).unwrap_or(&[]));
+                offset += ty.slots();
+            }
+        }
+    }
+
+    /// Unpacks the uniform blocks (pass, view, draw, user) with the same layout the generators use
+    pub fn unpack_uniform_blocks(&mut self, blocks: &[(LiveId, &[f32])]) {
+        for (ident, fields) in self.def.fields_as_uniform_blocks() {
+            let slots = blocks.iter().find( | (id, _) | *id == ident.0).map( | (_, slots) | *slots).unwrap_or(&[]);
+            let mut offset = 0;
+            for (index, _) in fields {
+                let ty = self.def.fields[index].ty_expr.ty.borrow();
+                let ty = ty.as_ref().unwrap();
+                self.fields[index] = Value::from_slots(ty, slots.get(offset..).unwrap_or(&[]));
+                offset += ty.slots();
+            }
+        }
+    }
+
+    pub fn field_index(&self, ident: Ident) -> Option<usize> {
+        self.def.fields.iter().position( | field | field.ident == ident)
+    }
+
+    /// Runs a draw shader method like vertex or pixel
+    pub fn call_method(&mut self, ident: LiveId) -> Value {
+        let registry = self.registry;
+        if let Some(fn_def) = registry.draw_shader_method_decl_from_ident(self.def, Ident(ident)) {
+            let args = fn_def.params.iter().map( | _ | Value::DrawShader).collect();
+            self.call_fn(fn_def, args).0
+        }
+        else {
+            Value::Void
+        }
+    }
+
+    fn call_fn(&mut self, fn_def: &FnDef, args: Vec<Value>) -> (Value, Vec<Value>) {
+        let mut frame = Frame {
+            fn_ptr: fn_def.fn_ptr,
+            const_offset: self.const_table.offsets.get(&fn_def.fn_ptr).cloned(),
+            vars: Vec::with_capacity(16),
+        };
+        for (param, arg) in fn_def.params.iter().zip(args) {
+            if let Some(shadow) = param.shadow.get() {
+                frame.vars.push((param.ident, shadow, arg));
+            }
+        }
+        self.frames.push(frame);
+        let ret = match self.exec_block(&fn_def.block) {
+            Flow::Return(value) => value,
+            _ => Value::Void
+        };
+        let frame = self.frames.pop().unwrap();
+        let mut params = Vec::new();
+        for param in &fn_def.params {
+            params.push(param.shadow.get().and_then( | shadow | {
+                frame.vars.iter().find( | (i, s, _) | *i == param.ident && *s == shadow).map( | (_, _, v) | v.clone())
+            }).unwrap_or_default());
+        }
+        (ret, params)
+    }
+
+    fn call_with_args(&mut self, fn_def: &FnDef, arg_exprs: &[Expr]) -> Value {
+        let mut args = Vec::with_capacity(arg_exprs.len());
+        for arg_expr in arg_exprs {
+            if let ExprKind::ClosureDef(index) = arg_expr.kind {
+                args.push(self.make_closure(index));
+            }
+            else {
+                args.push(self.eval_expr(arg_expr));
+            }
+        }
+        let (ret, params) = self.call_fn(fn_def, args);
+        // write inout params back into the expressions they came from
+        for ((param, value), arg_expr) in fn_def.params.iter().zip(params).zip(arg_exprs) {
+            if param.is_inout {
+                self.assign(arg_expr, value);
+            }
+        }
+        ret
+    }
+
+    fn make_closure(&self, index: ClosureDefIndex) -> Value {
+        let frame = self.frames.last().unwrap();
+        Value::Closure(Rc::new(ClosureValue {
+            fn_ptr: frame.fn_ptr,
+            closure_def_index: index,
+            captured: frame.vars.clone(),
+            const_offset: frame.const_offset,
+        }))
+    }
+
+    fn call_closure(&mut self, param_index: usize, arg_exprs: &[Expr]) -> Value {
+        let registry = self.registry;
+        let frame = self.frames.last().unwrap();
+        let fn_def = registry.all_fns.get(&frame.fn_ptr).unwrap();
+        let param = &fn_def.params[param_index];
+        let closure = match param.shadow.get().and_then( | shadow | self.get_var(param.ident, shadow)) {
+            Some(Value::Closure(closure)) => closure,
+            _ => return Value::Void
+        };
+        let args: Vec<Value> = arg_exprs.iter().map( | arg_expr | self.eval_expr(arg_expr)).collect();
+        let closure_def = &registry.all_fns.get(&closure.fn_ptr).unwrap().closure_defs[closure.closure_def_index.0];
+        let mut frame = Frame {
+            fn_ptr: closure.fn_ptr,
+            const_offset: closure.const_offset,
+            vars: closure.captured.clone(),
+        };
+        for (param, arg) in closure_def.params.iter().zip(args) {
+            if let Some(shadow) = param.shadow.get() {
+                frame.vars.push((param.ident, shadow, arg));
+            }
+        }
+        self.frames.push(frame);
+        let ret = match &closure_def.kind {
+            ClosureDefKind::Expr(expr) => self.eval_expr(expr),
+            ClosureDefKind::Block(block) => match self.exec_block(block) {
+                Flow::Return(value) => value,
+                _ => Value::Void
+            }
+        };
+        self.frames.pop();
+        ret
+    }
+
+    fn get_var(&self, ident: Ident, shadow: ScopeSymShadow) -> Option<Value> {
+        let vars = &self.frames.last().unwrap().vars;
+        vars.iter().rev().find( | (i, s, _) | *i == ident && *s == shadow)
+            .or_else( || vars.iter().rev().find( | (i, s, _) | *i == ident && *s == FOR_LOOP_SHADOW))
+            .map( | (_, _, v) | v.clone())
+    }
+
+    fn set_var(&mut self, ident: Ident, shadow: ScopeSymShadow, value: Value) {
+        let vars = &mut self.frames.last_mut().unwrap().vars;
+        if let Some(var) = vars.iter_mut().rev().find( | (i, s, _) | *i == ident && *s == shadow) {
+            var.2 = value;
+        }
+        else {
+            vars.push((ident, shadow, value));
+        }
+    }
+
+    fn exec_block(&mut self, block: &Block) -> Flow {
+        for stmt in &block.stmts {
+            match self.exec_stmt(stmt) {
+                Flow::Normal => (),
+                flow => return flow
+            }
+        }
+        Flow::Normal
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt) -> Flow {
+        match stmt {
+            Stmt::Break {..} => Flow::Break,
+            Stmt::Continue {..} => Flow::Continue,
+            Stmt::For {ident, from_expr, to_expr, step_expr, block, ..} => {
+                let const_int = | expr: &Expr | expr.const_val.borrow().as_ref().and_then( | v | v.as_ref()).and_then( | v | v.to_int()).unwrap_or(0);
+                let from = const_int(from_expr);
+                let to = const_int(to_expr);
+                let step = if let Some(step_expr) = step_expr {const_int(step_expr)} else if from < to {1} else {-1};
+                if step == 0 {
+                    return Flow::Normal
+                }
+                // mirrors the generated glsl: for(i = from; i < to; i += step) or counting down to 'to' inclusive
+                let mut i = if from <= to {from} else {from - 1};
+                while if from <= to {i < to} else {i >= to} {
+                    self.set_var(*ident, FOR_LOOP_SHADOW, Value::Int(i));
+                    match self.exec_block(block) {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Flow::Return(value),
+                        _ => ()
+                    }
+                    i += step;
+                }
+                Flow::Normal
+            }
+            Stmt::If {expr, block_if_true, block_if_false, ..} => {
+                if self.eval_expr(expr).as_bool() {
+                    self.exec_block(block_if_true)
+                }
+                else if let Some(block_if_false) = block_if_false {
+                    self.exec_block(block_if_false)
+                }
+                else {
+                    Flow::Normal
+                }
+            }
+            Stmt::Match {expr, matches, ..} => {
+                let value = self.eval_expr(expr).as_f32();
+                for match_item in matches {
+                    if let Some(enum_value) = match_item.enum_value.get() {
+                        if (value - enum_value as f32).abs() < 0.5 {
+                            return self.exec_block(&match_item.block)
+                        }
+                    }
+                }
+                Flow::Normal
+            }
+            Stmt::Let {ty, shadow, ident, expr, ..} => {
+                let value = if let Some(expr) = expr {
+                    self.eval_expr(expr)
+                }
+                else {
+                    Value::zero(ty.borrow().as_ref().unwrap())
+                };
+                self.set_var(*ident, shadow.get().unwrap(), value);
+                Flow::Normal
+            }
+            Stmt::Return {expr, ..} => {
+                Flow::Return(expr.as_ref().map( | expr | self.eval_expr(expr)).unwrap_or_default())
+            }
+            Stmt::Block {block, ..} => self.exec_block(block),
+            Stmt::Expr {expr, ..} => {
+                self.eval_expr(expr);
+                Flow::Normal
+            }
+        }
+    }
+
+    fn const_value(&self, expr: &Expr) -> Option<Value> {
+        let const_val = expr.const_val.borrow();
+        let val = const_val.as_ref()?.as_ref()?;
+        // floats come out of the const table so live edited values show up without a recompile
+        if let (Some(index), Some(offset)) = (expr.const_index.get(), self.frames.last().and_then( | f | f.const_offset)) {
+            let table = &self.const_table.table;
+            match val {
+                Val::Float(_) => if let Some(v) = table.get(offset + index) {
+                    return Some(Value::float(*v))
+                }
+                Val::Vec4(_) => if let Some(v) = table.get(offset + index..offset + index + 4) {
+                    return Some(Value::vec(v))
+                }
+                _ => ()
+            }
+        }
+        Some(match val {
+            Val::Bool(v) => Value::Bool(*v),
+            Val::Int(v) => Value::Int(*v),
+            Val::Float(v) => Value::float(*v),
+            Val::Vec4(v) => Value::vec(&[v.x, v.y, v.z, v.w]),
+        })
+    }
+
+    fn swizzle_indices(&mut self, ident: Ident) -> Vec<usize> {
+        self.swizzles.entry(ident.0).or_insert_with( || {
+            Swizzle::parse(ident).map( | s | s.iter().cloned().collect()).unwrap_or_default()
+        }).clone()
+    }
+
+    fn struct_field_index(&self, struct_ptr: StructPtr, ident: Ident) -> Option<usize> {
+        self.registry.structs.get(&struct_ptr)?.fields.iter().position( | field | field.ident == ident)
+    }
+
+    pub fn eval_expr(&mut self, expr: &Expr) -> Value {
+        if let Some(value) = self.const_value(expr) {
+            return value
+        }
+        match &expr.kind {
+            ExprKind::Cond {expr, expr_if_true, expr_if_false, ..} => {
+                if self.eval_expr(expr).as_bool() {
+                    self.eval_expr(expr_if_true)
+                }
+                else {
+                    self.eval_expr(expr_if_false)
+                }
+            }
+            ExprKind::Bin {op, left_expr, right_expr, ..} => self.eval_bin_expr(*op, left_expr, right_expr),
+            ExprKind::Un {op, expr, ..} => {
+                let value = self.eval_expr(expr);
+                match op {
+                    UnOp::Not => match value {
+                        Value::Bvec(n, mut c) => {
+                            for c in c.iter_mut() {*c = !*c}
+                            Value::Bvec(n, c)
+                        }
+                        value => Value::Bool(!value.as_bool())
+                    }
+                    UnOp::Neg => match value {
+                        Value::Int(v) => Value::Int(v.wrapping_neg()),
+                        Value::Ivec(n, c) => Value::Ivec(n, c.map( | v | v.wrapping_neg())),
+                        value => value.map( | v | -v)
+                    }
+                }
+            }
+            ExprKind::Field {expr, field_ident, ..} => {
+                let ty = expr.ty.borrow().clone();
+                match ty {
+                    Some(Ty::DrawShader(_)) => {
+                        self.field_index(*field_ident).map( | i | self.fields[i].clone()).unwrap_or_default()
+                    }
+                    Some(Ty::Struct(struct_ptr)) => {
+                        let index = self.struct_field_index(struct_ptr, *field_ident);
+                        match (self.eval_expr(expr), index) {
+                            (Value::Struct(mut fields), Some(index)) if index < fields.len() => fields.swap_remove(index),
+                            _ => Value::Void
+                        }
+                    }
+                    _ => {
+                        let indices = self.swizzle_indices(*field_ident);
+                        self.eval_expr(expr).swizzle(&indices)
+                    }
+                }
+            }
+            ExprKind::Index {expr, index_expr, ..} => {
+                let index = self.eval_expr(index_expr).as_int().max(0) as usize;
+                self.eval_expr(expr).index(index)
+            }
+            ExprKind::MethodCall {ident, arg_exprs, ..} => {
+                let registry = self.registry;
+                let ty = arg_exprs[0].ty.borrow().clone();
+                let fn_def = match ty {
+                    Some(Ty::Struct(struct_ptr)) => registry.struct_method_decl_from_ident(
+                        registry.structs.get(&struct_ptr).unwrap(),
+                        *ident
+                    ),
+                    Some(Ty::DrawShader(shader_ptr)) => registry.draw_shader_method_decl_from_ident(
+                        registry.draw_shader_defs.get(&shader_ptr).unwrap_or(self.def),
+                        *ident
+                    ),
+                    _ => None
+                };
+                match fn_def {
+                    Some(fn_def) => self.call_with_args(fn_def, arg_exprs),
+                    None => Value::Void
+                }
+            }
+            ExprKind::PlainCall {fn_ptr, param_index, arg_exprs, ..} => {
+                if let Some(param_index) = param_index.get() {
+                    self.call_closure(param_index, arg_exprs)
+                }
+                else {
+                    let registry = self.registry;
+                    match fn_ptr.and_then( | fn_ptr | registry.all_fns.get(&fn_ptr)) {
+                        Some(fn_def) => self.call_with_args(fn_def, arg_exprs),
+                        None => Value::Void
+                    }
+                }
+            }
+            ExprKind::BuiltinCall {ident, arg_exprs, ..} => {
+                let args: Vec<Value> = arg_exprs.iter().map( | arg_expr | self.eval_expr(arg_expr)).collect();
+                self.eval_builtin(*ident, args)
+            }
+            ExprKind::ClosureDef(index) => self.make_closure(*index),
+            ExprKind::ConsCall {ty_lit, arg_exprs, ..} => {
+                let args: Vec<Value> = arg_exprs.iter().map( | arg_expr | self.eval_expr(arg_expr)).collect();
+                construct(*ty_lit, &args)
+            }
+            ExprKind::StructCons {struct_ptr, args, ..} => {
+                let registry = self.registry;
+                let struct_def = registry.structs.get(struct_ptr).unwrap();
+                let mut fields: Vec<Value> = struct_def.fields.iter().map( | field | {
+                    Value::zero(field.ty_expr.ty.borrow().as_ref().unwrap())
+                }).collect();
+                for (ident, expr) in args {
+                    if let Some(index) = struct_def.fields.iter().position( | field | field.ident == *ident) {
+                        fields[index] = self.eval_expr(expr);
+                    }
+                }
+                Value::Struct(fields)
+            }
+            ExprKind::Var {kind, ..} => match kind.get() {
+                Some(VarKind::Local {ident, shadow}) | Some(VarKind::MutLocal {ident, shadow}) => {
+                    self.get_var(ident, shadow).unwrap_or_default()
+                }
+                Some(VarKind::LiveValue(value_ptr)) => {
+                    self.live_values.iter().find( | (ptr, _) | *ptr == value_ptr).map( | (_, v) | v.clone()).unwrap_or_default()
+                }
+                None => Value::Void
+            }
+            ExprKind::Lit {lit, ..} => match lit {
+                Lit::Bool(v) => Value::Bool(*v),
+                Lit::Int(v) => Value::Int(*v),
+                Lit::Float(v) => Value::float(*v),
+                Lit::Color(v) => {
+                    let c = Vec4::from_u32(*v);
+                    Value::vec(&[c.x, c.y, c.z, c.w])
+                }
+            }
+        }
+    }
+
+    fn eval_bin_expr(&mut self, op: BinOp, left_expr: &Expr, right_expr: &Expr) -> Value {
+        match op {
+            BinOp::Assign => {
+                let value = self.eval_expr(right_expr);
+                self.assign(left_expr, value);
+                Value::Void
+            }
+            BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign => {
+                let left = self.eval_expr(left_expr);
+                let right = self.eval_expr(right_expr);
+                let value = arith(match op {
+                    BinOp::AddAssign => BinOp::Add,
+                    BinOp::SubAssign => BinOp::Sub,
+                    BinOp::MulAssign => BinOp::Mul,
+                    _ => BinOp::Div
+                }, &left, &right);
+                self.assign(left_expr, value);
+                Value::Void
+            }
+            BinOp::Or => Value::Bool(self.eval_expr(left_expr).as_bool() || self.eval_expr(right_expr).as_bool()),
+            BinOp::And => Value::Bool(self.eval_expr(left_expr).as_bool() && self.eval_expr(right_expr).as_bool()),
+            _ => {
+                let left = self.eval_expr(left_expr);
+                let right = self.eval_expr(right_expr);
+                match op {
+                    BinOp::Eq => Value::Bool(left.values_equal(&right)),
+                    BinOp::Ne => Value::Bool(!left.values_equal(&right)),
+                    BinOp::Lt => Value::Bool(left.as_f32() < right.as_f32()),
+                    BinOp::Le => Value::Bool(left.as_f32() <= right.as_f32()),
+                    BinOp::Gt => Value::Bool(left.as_f32() > right.as_f32()),
+                    BinOp::Ge => Value::Bool(left.as_f32() >= right.as_f32()),
+                    op => arith(op, &left, &right)
+                }
+            }
+        }
+    }
+
+    fn assign(&mut self, expr: &Expr, value: Value) {
+        match &expr.kind {
+            ExprKind::Var {kind, ..} => match kind.get() {
+                Some(VarKind::Local {ident, shadow}) | Some(VarKind::MutLocal {ident, shadow}) => {
+                    self.set_var(ident, shadow, value);
+                }
+                _ => ()
+            }
+            ExprKind::Field {expr: inner, field_ident, ..} => {
+                let ty = inner.ty.borrow().clone();
+                match ty {
+                    Some(Ty::DrawShader(_)) => if let Some(index) = self.field_index(*field_ident) {
+                        self.fields[index] = value;
+                    }
+                    Some(Ty::Struct(struct_ptr)) => if let Some(index) = self.struct_field_index(struct_ptr, *field_ident) {
+                        let mut target = self.eval_expr(inner);
+                        if let Value::Struct(fields) = &mut target {
+                            if index < fields.len() {
+                                fields[index] = value;
+                            }
+                        }
+                        self.assign(inner, target);
+                    }
+                    _ => {
+                        let indices = self.swizzle_indices(*field_ident);
+                        let mut target = self.eval_expr(inner);
+                        target.set_swizzle(&indices, &value);
+                        self.assign(inner, target);
+                    }
+                }
+            }
+            ExprKind::Index {expr: inner, index_expr, ..} => {
+                let index = self.eval_expr(index_expr).as_int().max(0) as usize;
+                let mut target = self.eval_expr(inner);
+                target.set_index(index, value);
+                self.assign(inner, target);
+            }
+            _ => ()
+        }
+    }
+
+    fn eval_builtin(&mut self, ident: Ident, args: Vec<Value>) -> Value {
+        let a = | i: usize | &args[i];
+        match ident.0 {
+            live_id!(abs) => match a(0) {
+                Value::Int(v) => Value::Int(v.wrapping_abs()),
+                Value::Ivec(n, c) => Value::Ivec(*n, c.map( | v | v.wrapping_abs())),
+                v => v.map(Dual::abs)
+            },
+            live_id!(sin) => a(0).map( | d | d.map(f32::sin, f32::cos)),
+            live_id!(cos) => a(0).map( | d | d.map(f32::cos, | v | -v.sin())),
+            live_id!(tan) => a(0).map( | d | d.map(f32::tan, | v | 1.0 / (v.cos() * v.cos()))),
+            live_id!(asin) => a(0).map( | d | d.map(f32::asin, | v | 1.0 / (1.0 - v * v).max(1e-12).sqrt())),
+            live_id!(acos) => a(0).map( | d | d.map(f32::acos, | v | -1.0 / (1.0 - v * v).max(1e-12).sqrt())),
+            live_id!(atan) => if args.len() == 2 {
+                a(0).zip(a(1), Dual::atan2)
+            }
+            else {
+                a(0).map( | d | d.map(f32::atan, | v | 1.0 / (1.0 + v * v)))
+            },
+            live_id!(exp) => a(0).map( | d | d.map(f32::exp, f32::exp)),
+            live_id!(exp2) => a(0).map( | d | d.map(f32::exp2, | v | v.exp2() * std::f32::consts::LN_2)),
+            live_id!(log) => a(0).map( | d | d.map(f32::ln, | v | 1.0 / v)),
+            live_id!(log2) => a(0).map( | d | d.map(f32::log2, | v | 1.0 / (v * std::f32::consts::LN_2))),
+            live_id!(sqrt) => a(0).map(Dual::sqrt),
+            live_id!(inversesqrt) => a(0).map( | d | Dual::new(1.0) / d.sqrt()),
+            live_id!(pow) => a(0).zip(a(1), Dual::pow),
+            live_id!(degrees) => a(0).map( | d | d * Dual::new(180.0 / std::f32::consts::PI)),
+            live_id!(radians) => a(0).map( | d | d * Dual::new(std::f32::consts::PI / 180.0)),
+            live_id!(floor) => a(0).map(Dual::floor),
+            live_id!(ceil) => a(0).map(Dual::ceil),
+            live_id!(fract) => a(0).map(Dual::fract),
+            live_id!(sign) => a(0).map(Dual::sign),
+            live_id!(min) => if a(0).is_int() {a(0).zip_int(a(1), i32::min)} else {a(0).zip(a(1), Dual::min)},
+            live_id!(max) => if a(0).is_int() {a(0).zip_int(a(1), i32::max)} else {a(0).zip(a(1), Dual::max)},
+            live_id!(clamp) => if a(0).is_int() {
+                a(0).zip_int(a(1), i32::max).zip_int(a(2), i32::min)
+            }
+            else {
+                a(0).zip(a(1), Dual::max).zip(a(2), Dual::min)
+            },
+            live_id!(mod) => a(0).zip(a(1), | x, y | x - y * (x / y).floor()),
+            live_id!(mix) => {
+                let d = arith(BinOp::Sub, a(1), a(0));
+                arith(BinOp::Add, a(0), &d.zip(a(2), | d, t | d * t))
+            }
+            live_id!(step) => a(0).zip(a(1), | edge, x | Dual::new(if x.v < edge.v {0.0} else {1.0})),
+            live_id!(smoothstep) => {
+                let range = arith(BinOp::Sub, a(1), a(0));
+                let t = arith(BinOp::Sub, a(2), a(0)).zip(&range, | x, r | x / r);
+                t.map( | t | {
+                    let t = t.max(Dual::new(0.0)).min(Dual::new(1.0));
+                    t * t * (Dual::new(3.0) - Dual::new(2.0) * t)
+                })
+            }
+            live_id!(length) => Value::Float(length(a(0))),
+            live_id!(distance) => Value::Float(length(&arith(BinOp::Sub, a(0), a(1)))),
+            live_id!(dot) => Value::Float(dot(a(0), a(1))),
+            live_id!(normalize) => {
+                let len = length(a(0));
+                a(0).map( | d | d / len)
+            }
+            live_id!(cross) => {
+                let (x, y) = (a(0), a(1));
+                let c = | i | x.component(i);
+                let d = | i | y.component(i);
+                Value::Vec(3, [
+                    c(1) * d(2) - c(2) * d(1),
+                    c(2) * d(0) - c(0) * d(2),
+                    c(0) * d(1) - c(1) * d(0),
+                    Dual::default()
+                ])
+            }
+            live_id!(reflect) => {
+                let d = dot(a(1), a(0));
+                let n = a(1).map( | n | n * d * Dual::new(2.0));
+                arith(BinOp::Sub, a(0), &n)
+            }
+            live_id!(refract) => {
+                let (i, n, eta) = (a(0), a(1), a(2).as_dual());
+                let d = dot(n, i);
+                let k = Dual::new(1.0) - eta * eta * (Dual::new(1.0) - d * d);
+                if k.v < 0.0 {
+                    i.map( | _ | Dual::default())
+                }
+                else {
+                    let f = eta * d + k.sqrt();
+                    arith(BinOp::Sub, &i.map( | v | v * eta), &n.map( | v | v * f))
+                }
+            }
+            live_id!(faceforward) => {
+                if dot(a(2), a(1)).v < 0.0 {a(0).clone()} else {a(0).map( | d | -d)}
+            }
+            live_id!(dFdx) => a(0).map( | d | Dual::new(d.dx)),
+            live_id!(dFdy) => a(0).map( | d | Dual::new(d.dy)),
+            live_id!(lessThan) => compare(a(0), a(1), | a, b | a < b),
+            live_id!(lessThanEqual) => compare(a(0), a(1), | a, b | a <= b),
+            live_id!(greaterThan) => compare(a(0), a(1), | a, b | a > b),
+            live_id!(greaterThanEqual) => compare(a(0), a(1), | a, b | a >= b),
+            live_id!(equal) => compare(a(0), a(1), | a, b | a == b),
+            live_id!(notEqual) => compare(a(0), a(1), | a, b | a != b),
+            live_id!(any) => match a(0) {
+                Value::Bvec(n, c) => Value::Bool(c[0..*n].iter().any( | v | *v)),
+                v => Value::Bool(v.as_bool())
+            },
+            live_id!(all) => match a(0) {
+                Value::Bvec(n, c) => Value::Bool(c[0..*n].iter().all( | v | *v)),
+                v => Value::Bool(v.as_bool())
+            },
+            live_id!(not) => match a(0) {
+                Value::Bvec(n, c) => Value::Bvec(*n, c.map( | v | !v)),
+                v => Value::Bool(!v.as_bool())
+            },
+            live_id!(matrixCompMult) => a(0).zip(a(1), | x, y | x * y),
+            live_id!(transpose) => match a(0) {
+                Value::Mat(n, m) => {
+                    let mut r = Box::<[Dual; 16]>::default();
+                    for col in 0..*n {
+                        for row in 0..*n {
+                            r[row * 4 + col] = m[col * 4 + row];
+                        }
+                    }
+                    Value::Mat(*n, r)
+                }
+                v => v.clone()
+            },
+            live_id!(inverse) => match a(0) {
+                Value::Mat(n, m) => Value::Mat(*n, invert(*n, m)),
+                v => v.clone()
+            },
+            live_id!(sample2d) | live_id!(sample2d_rt) | live_id!(sample2dOES) => {
+                let slot = if let Value::Texture(slot) = a(0) {*slot} else {usize::MAX};
+                let uv = a(1);
+                let rgba = match self.textures.get(slot) {
+                    Some(Some(texture)) => texture.sample(uv.component(0).v, uv.component(1).v),
+                    _ => [0.0; 4]
+                };
+                Value::vec(&rgba)
+            }
+            _ => Value::Void
+        }
+    }
+}
+
+fn length(v: &Value) -> Dual {
+    dot(v, v).sqrt()
+}
+
+fn dot(a: &Value, b: &Value) -> Dual {
+    let mut sum = Dual::default();
+    for i in 0..a.components() {
+        sum = sum + a.component(i) * b.component(i);
+    }
+    sum
+}
+
+fn compare(a: &Value, b: &Value, f: fn(f32, f32) -> bool) -> Value {
+    let n = a.components();
+    let mut c = [false; 4];
+    for (i, c) in c.iter_mut().enumerate().take(n) {
+        *c = f(a.component(i).v, b.component(i).v);
+    }
+    Value::Bvec(n, c)
+}
+
+fn invert(n: usize, m: &[Dual; 16]) -> Box<[Dual; 16]> {
+    // gauss-jordan on the plain values, derivatives of an inverse are not worth the trouble
+    let mut a = [[0f32; 8]; 4];
+    for (row, a) in a.iter_mut().enumerate().take(n) {
+        for col in 0..n {
+            a[col] = m[col * 4 + row].v;
+        }
+        a[n + row] = 1.0;
+    }
+    for col in 0..n {
+        let pivot = (col..n).max_by( | x, y | a[*x][col].abs().total_cmp(&a[*y][col].abs())).unwrap();
+        a.swap(col, pivot);
+        let p = a[col][col];
+        if p == 0.0 {
+            return Box::default()
+        }
+        for k in 0..2 * n {
+            a[col][k] /= p;
+        }
+        for row in 0..n {
+            if row != col {
+                let f = a[row][col];
+                for k in 0..2 * n {
+                    a[row][k] -= f * a[col][k];
+                }
+            }
+        }
+    }
+    let mut r = Box::<[Dual; 16]>::default();
+    for (row, a) in a.iter().enumerate().take(n) {
+        for col in 0..n {
+            r[col * 4 + row] = Dual::new(a[n + col]);
+        }
+    }
+    r
+}
+
+pub fn arith(op: BinOp, a: &Value, b: &Value) -> Value {
+    if a.is_int() && b.is_int() {
+        return a.zip_int(b, match op {
+            BinOp::Add => i32::wrapping_add,
+            BinOp::Sub => i32::wrapping_sub,
+            BinOp::Mul => i32::wrapping_mul,
+            _ => | a: i32, b: i32 | if b == 0 {0} else {a.wrapping_div(b)}
+        })
+    }
+    match (op, a, b) {
+        (BinOp::Mul, Value::Mat(n, m), Value::Vec(_, v)) => mat_mul_vec(*n, m, v),
+        (BinOp::Mul, Value::Vec(n, v), Value::Mat(_, m)) => vec_mul_mat(*n, v, m),
+        (BinOp::Mul, Value::Mat(n, x), Value::Mat(_, y)) => mat_mul_mat(*n, x, y),
+        (BinOp::Add, a, b) => a.zip(b, | a, b | a + b),
+        (BinOp::Sub, a, b) => a.zip(b, | a, b | a - b),
+        (BinOp::Mul, a, b) => a.zip(b, | a, b | a * b),
+        (BinOp::Div, a, b) => a.zip(b, | a, b | a / b),
+        _ => Value::Void
+    }
+}
+
+/// The glsl constructor rules: splat a single scalar, otherwise consume components in order
+pub fn construct(ty_lit: TyLit, args: &[Value]) -> Value {
+    let mut components = Vec::with_capacity(16);
+    for arg in args {
+        for i in 0..arg.components() {
+            components.push(arg.component(i));
+        }
+    }
+    let get = | i: usize | -> Dual {
+        if components.len() == 1 {components[0]} else {components.get(i).cloned().unwrap_or_default()}
+    };
+    let vec_n = | n: usize | -> [Dual; 4] {
+        let mut c = [Dual::default(); 4];
+        for (i, c) in c.iter_mut().enumerate().take(n) {*c = get(i)}
+        c
+    };
+    match ty_lit {
+        TyLit::Bool => Value::Bool(get(0).v != 0.0),
+        TyLit::Int => Value::Int(get(0).v as i32),
+        TyLit::Float => Value::Float(get(0)),
+        TyLit::Vec2 => Value::Vec(2, vec_n(2)),
+        TyLit::Vec3 => Value::Vec(3, vec_n(3)),
+        TyLit::Vec4 => Value::Vec(4, vec_n(4)),
+        TyLit::Ivec2 => Value::Ivec(2, vec_n(2).map( | d | d.v as i32)),
+        TyLit::Ivec3 => Value::Ivec(3, vec_n(3).map( | d | d.v as i32)),
+        TyLit::Ivec4 => Value::Ivec(4, vec_n(4).map( | d | d.v as i32)),
+        TyLit::Bvec2 => Value::Bvec(2, vec_n(2).map( | d | d.v != 0.0)),
+        TyLit::Bvec3 => Value::Bvec(3, vec_n(3).map( | d | d.v != 0.0)),
+        TyLit::Bvec4 => Value::Bvec(4, vec_n(4).map( | d | d.v != 0.0)),
+        TyLit::Mat2 | TyLit::Mat3 | TyLit::Mat4 => {
+            let n = match ty_lit {TyLit::Mat2 => 2, TyLit::Mat3 => 3, _ => 4};
+            let mut m = Box::<[Dual; 16]>::default();
+            match args {
+                // a single scalar makes a diagonal matrix
+                [arg] if arg.components() == 1 => for i in 0..n {
+                    m[i * 4 + i] = arg.as_dual();
+                }
+                // a matrix from a matrix copies the overlap and fills in identity
+                [Value::Mat(src_n, src)] => for col in 0..n {
+                    for row in 0..n {
+                        m[col * 4 + row] = if col < *src_n && row < *src_n {
+                            src[col * 4 + row]
+                        } else {
+                            Dual::new(if col == row {1.0} else {0.0})
+                        };
+                    }
+                }
+                _ => for col in 0..n {
+                    for row in 0..n {
+                        m[col * 4 + row] = components.get(col * n + row).cloned().unwrap_or_default();
+                    }
+                }
+            }
+            Value::Mat(n, m)
+        }
+        TyLit::Texture2D | TyLit::TextureOES => args.first().cloned().unwrap_or_default(),
+    }
+}
@@ -0,0 +1,257 @@
+use {
+    std::collections::HashMap,
+    crate::{
+        makepad_live_id::*,
+        makepad_math::*,
+        makepad_shader_compiler::shader_ast::{DrawShaderFieldKind, DrawShaderPtr},
+        cx::Cx,
+        draw_list::DrawListId,
+        pass::{CxPassParent, PassClearColor, PassClearDepth, PassId},
+        texture::{Texture, TextureFormat, TextureId, TextureSize},
+    },
+    super::{
+        value::{Dual, Value},
+        shader_vm::ShaderVm,
+        rasterizer::{rasterize_triangle, RasterVertex, SoftwareTarget, TexelData, TextureView},
+    },
+};
+
+/// The CPU side storage of the software renderer, render target textures live here
+/// instead of in GPU memory.
+#[derive(Default)]
+pub struct CxSoftware {
+    pub targets: HashMap<TextureId, SoftwareTarget>,
+    // window passes draw straight to the screen on the GPU backends, here they get a texture of their own
+    pub window_textures: HashMap<PassId, Texture>,
+    // draw shaders with the same source share one id, the analysed AST only exists for the one that got compiled
+    pub shader_ptrs: HashMap<usize, DrawShaderPtr>,
+}
+
+impl Cx {
+    pub fn software_compile_shaders(&mut self) {
+        // the interpreter runs straight off the analysed shader AST, there is nothing to generate
+        for draw_shader_ptr in std::mem::take(&mut self.draw_shaders.compile_set) {
+            if let Some(item) = self.draw_shaders.ptr_to_item.get(&draw_shader_ptr) {
+                self.os.software.shader_ptrs.insert(item.draw_shader_id, draw_shader_ptr);
+            }
+        }
+    }
+
+    /// Renders a pass into the CPU target of its first color texture
+    pub fn software_draw_pass(&mut self, pass_id: PassId) {
+        let draw_list_id = if let Some(draw_list_id) = self.passes[pass_id].main_draw_list_id {
+            draw_list_id
+        }
+        else {
+            return
+        };
+        // a pass that wasn't begun this frame has no dpi of its own, use the one of the window it ends up in
+        if self.passes[pass_id].dpi_factor.is_none() {
+            let dpi_factor = self.get_delegated_dpi_factor(pass_id);
+            self.passes[pass_id].dpi_factor = Some(dpi_factor);
+        }
+        let pass_size = if let Some(pass_size) = self.setup_render_pass(pass_id) {
+            pass_size
+        }
+        else {
+            return
+        };
+        let (texture, clear_color) = if let Some(color_texture) = self.passes[pass_id].color_textures.first() {
+            (color_texture.texture.clone(), color_texture.clear_color.clone())
+        }
+        else if let CxPassParent::Window(_) = self.passes[pass_id].parent {
+            let texture = if let Some(texture) = self.os.software.window_textures.get(&pass_id) {
+                texture.clone()
+            }
+            else {
+                let texture = Texture::new_with_format(self, TextureFormat::RenderBGRAu8 {size: TextureSize::Auto});
+                self.os.software.window_textures.insert(pass_id, texture.clone());
+                texture
+            };
+            (texture, PassClearColor::ClearWith(self.passes[pass_id].clear_color))
+        }
+        else {
+            return
+        };
+        let size = pass_size * self.passes[pass_id].dpi_factor.unwrap();
+        let texture_id = texture.texture_id();
+        let mut target = self.os.software.targets.remove(&texture_id).unwrap_or_default();
+        let is_new = target.resize(size.x as usize, size.y as usize);
+        match clear_color {
+            PassClearColor::InitWith(color) => if is_new {
+                target.clear_color([color.x, color.y, color.z, color.w]);
+            }
+            PassClearColor::ClearWith(color) => {
+                target.clear_color([color.x, color.y, color.z, color.w]);
+            }
+        }
+        match self.passes[pass_id].clear_depth {
+            PassClearDepth::InitWith(depth) => if is_new {
+                target.clear_depth(depth);
+            }
+            PassClearDepth::ClearWith(depth) => {
+                target.clear_depth(depth);
+            }
+        }
+        let zbias_step = self.passes[pass_id].zbias_step;
+        let mut zbias = 0.0;
+        self.software_render_view(pass_id, draw_list_id, &mut zbias, zbias_step, &mut target);
+        self.os.software.targets.insert(texture_id, target);
+    }
+
+    fn software_render_view(
+        &mut self,
+        pass_id: PassId,
+        draw_list_id: DrawListId,
+        zbias: &mut f32,
+        zbias_step: f32,
+        target: &mut SoftwareTarget,
+    ) {
+        let draw_items_len = self.draw_lists[draw_list_id].draw_items.len();
+        self.draw_lists[draw_list_id].uniform_view_transform(&Mat4::identity());
+
+        for draw_item_id in 0..draw_items_len {
+            if let Some(sub_list_id) = self.draw_lists[draw_list_id].draw_items[draw_item_id].kind.sub_list() {
+                self.software_render_view(pass_id, sub_list_id, zbias, zbias_step, target);
+                continue;
+            }
+            if let Some(draw_call) = self.draw_lists[draw_list_id].draw_items[draw_item_id].kind.draw_call_mut() {
+                draw_call.instance_dirty = false;
+                draw_call.uniforms_dirty = false;
+                draw_call.draw_uniforms.set_zbias(*zbias);
+                *zbias += zbias_step;
+            }
+            else {
+                continue;
+            }
+            self.software_draw_call(pass_id, draw_list_id, draw_item_id, target);
+        }
+    }
+
+    fn software_texture_view(&self, texture: &Texture) -> Option<TextureView<'_>> {
+        let texture_id = texture.texture_id();
+        if let Some(target) = self.os.software.targets.get(&texture_id) {
+            return Some(TextureView {
+                width: target.width,
+                height: target.height,
+                stride: target.width,
+                data: TexelData::Target(&target.color),
+            })
+        }
+        let (width, height, stride, data) = match &self.textures[texture_id].format {
+            TextureFormat::VecBGRAu8_32 {width, height, data} |
+            TextureFormat::VecMipBGRAu8_32 {width, height, data, ..} => (*width, *height, *width, TexelData::Bgra8(data)),
+            TextureFormat::VecRGBAf32 {width, height, data} => (*width, *height, *width, TexelData::Rgbaf32(data)),
+            TextureFormat::VecRu8 {width, height, data, unpack_row_length} => {
+                (*width, *height, unpack_row_length.unwrap_or(*width), TexelData::R8(data))
+            }
+            TextureFormat::VecRGu8 {width, height, data, unpack_row_length} => {
+                (*width, *height, unpack_row_length.unwrap_or(*width), TexelData::Rg8(data))
+            }
+            TextureFormat::VecRf32 {width, height, data} => (*width, *height, *width, TexelData::Rf32(data)),
+            _ => return None
+        };
+        Some(TextureView {width, height, stride, data})
+    }
+
+    fn software_draw_call(&self, pass_id: PassId, draw_list_id: DrawListId, draw_item_id: usize, target: &mut SoftwareTarget) {
+        let draw_list = &self.draw_lists[draw_list_id];
+        let draw_item = &draw_list.draw_items[draw_item_id];
+        let draw_call = draw_item.kind.draw_call().unwrap();
+        let sh = &self.draw_shaders.shaders[draw_call.draw_shader.draw_shader_id];
+        let def = self.os.software.shader_ptrs.get(&draw_call.draw_shader.draw_shader_id)
+            .and_then( | draw_shader_ptr | self.shader_registry.draw_shader_defs.get(draw_shader_ptr));
+        let def = if let Some(def) = def {
+            def
+        }
+        else {
+            return
+        };
+        let instances = if let Some(instances) = &draw_item.instances {instances} else {return};
+        let geometry = if let Some(geometry_id) = draw_call.geometry_id {&self.geometries[geometry_id]} else {return};
+        let instance_slots = sh.mapping.instances.total_slots;
+        let geometry_slots = sh.mapping.geometries.total_slots;
+        if instance_slots == 0 || geometry_slots == 0 || instances.len() < instance_slots {
+            return
+        }
+
+        let mut vm = ShaderVm::new(&self.shader_registry, def, &sh.mapping.const_table, &sh.mapping.live_uniforms_buf);
+        vm.textures = (0..sh.mapping.textures.len()).map( | i | {
+            draw_call.texture_slots[i].as_ref().and_then( | texture | self.software_texture_view(texture))
+        }).collect();
+        vm.unpack_uniform_blocks(&[
+            (live_id!(pass), &self.passes[pass_id].pass_uniforms.as_slice()[..]),
+            (live_id!(view), &draw_list.draw_list_uniforms.as_slice()[..]),
+            (live_id!(draw), &draw_call.draw_uniforms.as_slice()[..]),
+            (live_id!(user), &draw_call.user_uniforms[..]),
+        ]);
+
+        // the fields the pixel shader gets interpolated, the same set the glsl generator packs into varyings
+        let pixel_inputs: Vec<usize> = def.fields.iter().enumerate().filter_map( | (index, field) | match &field.kind {
+            DrawShaderFieldKind::Geometry {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => Some(index),
+            DrawShaderFieldKind::Varying {..} => Some(index),
+            _ => None
+        }).collect();
+        let varyings: Vec<usize> = def.fields.iter().enumerate().filter_map( | (index, field) | match &field.kind {
+            DrawShaderFieldKind::Varying {..} => Some(index),
+            _ => None
+        }).collect();
+
+        let mut duals = Vec::new();
+        let mut inputs = Vec::new();
+        for instance in instances.chunks_exact(instance_slots) {
+            vm.unpack_fields(instance, | kind | matches!(kind, DrawShaderFieldKind::Instance {..}));
+
+            let vertices: Vec<RasterVertex> = geometry.vertices.chunks_exact(geometry_slots).map( | vertex | {
+                vm.unpack_fields(vertex, | kind | matches!(kind, DrawShaderFieldKind::Geometry {..}));
+                for index in &varyings {
+                    vm.fields[*index] = Value::zero(def.fields[*index].ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                let pos = vm.call_method(live_id!(vertex));
+                duals.clear();
+                for index in &pixel_inputs {
+                    vm.fields[*index].to_duals(&mut duals);
+                }
+                RasterVertex {
+                    pos: [pos.component(0).v, pos.component(1).v, pos.component(2).v, pos.component(3).v],
+                    inputs: duals.iter().map( | d | d.v).collect(),
+                }
+            }).collect();
+
+            for triangle in geometry.indices.chunks_exact(3) {
+                let (a, b, c) = match (vertices.get(triangle[0] as usize), vertices.get(triangle[1] as usize), vertices.get(triangle[2] as usize)) {
+                    (Some(a), Some(b), Some(c)) => (a, b, c),
+                    _ => continue
+                };
+                rasterize_triangle(target, [a, b, c], &mut inputs, | interpolated: &[Dual] | {
+                    let mut offset = 0;
+                    for index in &pixel_inputs {
+                        let ty = def.fields[*index].ty_expr.ty.borrow();
+                        let ty = ty.as_ref().unwrap();
+                        let slots = ty.slots();
+                        if let Some(duals) = interpolated.get(offset..offset + slots) {
+                            vm.fields[*index] = Value::from_duals(ty, duals);
+                        }
+                        offset += slots;
+                    }
+                    let color = vm.call_method(live_id!(pixel));
+                    [color.component(0).v, color.component(1).v, color.component(2).v, color.component(3).v]
+                });
+            }
+        }
+    }
+
+    /// The texture a pass renders into, for window passes this is the texture standing in for the screen
+    pub fn software_pass_texture(&self, pass_id: PassId) -> Option<Texture> {
+        if let Some(color_texture) = self.passes[pass_id].color_textures.first() {
+            return Some(color_texture.texture.clone())
+        }
+        self.os.software.window_textures.get(&pass_id).cloned()
+    }
+
+    /// Reads back the pixels of a texture the software renderer drew into, as u32 BGRA
+    pub fn software_texture_pixels(&self, texture: &Texture) -> Option<(usize, usize, Vec<u32>)> {
+        let target = self.os.software.targets.get(&texture.texture_id())?;
+        Some((target.width, target.height, target.to_bgra_u32()))
+    }
+}
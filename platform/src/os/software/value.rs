@@ -0,0 +1,513 @@
+use {
+    std::{
+        ops::{Add, Sub, Mul, Div, Neg},
+        rc::Rc,
+    },
+    crate::makepad_shader_compiler::shader_ast::*,
+};
+
+/// A float that carries its screen space derivatives along, this is how the
+/// interpreter answers dFdx/dFdy without running pixels in 2x2 quads.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Dual {
+    pub v: f32,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+impl Dual {
+    pub const fn new(v: f32) -> Self {
+        Self {v, dx: 0.0, dy: 0.0}
+    }
+
+    // applies the chain rule for a function with value v and derivative d at self.v
+    pub fn chain(self, v: f32, d: f32) -> Self {
+        Self {v, dx: self.dx * d, dy: self.dy * d}
+    }
+
+    pub fn map(self, f: fn(f32) -> f32, df: fn(f32) -> f32) -> Self {
+        self.chain(f(self.v), df(self.v))
+    }
+
+    pub fn sqrt(self) -> Self {
+        let v = self.v.max(0.0).sqrt();
+        self.chain(v, if v > 0.0 {0.5 / v} else {0.0})
+    }
+
+    pub fn pow(self, e: Dual) -> Self {
+        let v = self.v.powf(e.v);
+        let dv = if self.v != 0.0 {e.v * self.v.powf(e.v - 1.0)} else {0.0};
+        let de = if self.v > 0.0 {v * self.v.ln()} else {0.0};
+        Self {
+            v,
+            dx: self.dx * dv + e.dx * de,
+            dy: self.dy * dv + e.dy * de,
+        }
+    }
+
+    pub fn atan2(self, x: Dual) -> Self {
+        let d = self.v * self.v + x.v * x.v;
+        let (fy, fx) = if d > 0.0 {(x.v / d, -self.v / d)} else {(0.0, 0.0)};
+        Self {
+            v: self.v.atan2(x.v),
+            dx: self.dx * fy + x.dx * fx,
+            dy: self.dy * fy + x.dy * fx,
+        }
+    }
+
+    pub fn min(self, o: Dual) -> Self {if o.v < self.v {o} else {self}}
+    pub fn max(self, o: Dual) -> Self {if o.v > self.v {o} else {self}}
+    pub fn abs(self) -> Self {if self.v < 0.0 {-self} else {self}}
+    pub fn floor(self) -> Self {Self::new(self.v.floor())}
+    pub fn ceil(self) -> Self {Self::new(self.v.ceil())}
+    pub fn fract(self) -> Self {Self {v: self.v - self.v.floor(), ..self}}
+    pub fn sign(self) -> Self {Self::new(if self.v > 0.0 {1.0} else if self.v < 0.0 {-1.0} else {0.0})}
+}
+
+impl Add for Dual {
+    type Output = Dual;
+    fn add(self, o: Dual) -> Dual {Dual {v: self.v + o.v, dx: self.dx + o.dx, dy: self.dy + o.dy}}
+}
+
+impl Sub for Dual {
+    type Output = Dual;
+    fn sub(self, o: Dual) -> Dual {Dual {v: self.v - o.v, dx: self.dx - o.dx, dy: self.dy - o.dy}}
+}
+
+impl Mul for Dual {
+    type Output = Dual;
+    fn mul(self, o: Dual) -> Dual {
+        Dual {v: self.v * o.v, dx: self.dx * o.v + self.v * o.dx, dy: self.dy * o.v + self.v * o.dy}
+    }
+}
+
+impl Div for Dual {
+    type Output = Dual;
+    fn div(self, o: Dual) -> Dual {
+        let v = self.v / o.v;
+        Dual {v, dx: (self.dx - v * o.dx) / o.v, dy: (self.dy - v * o.dy) / o.v}
+    }
+}
+
+impl Neg for Dual {
+    type Output = Dual;
+    fn neg(self) -> Dual {Dual {v: -self.v, dx: -self.dx, dy: -self.dy}}
+}
+
+pub struct ClosureValue {
+    pub fn_ptr: FnPtr,
+    pub closure_def_index: ClosureDefIndex,
+    pub captured: Vec<(Ident, ScopeSymShadow, Value)>,
+    pub const_offset: Option<usize>,
+}
+
+/// A shader value. Vectors and matrices are stored in fixed size arrays
+/// with their dimension alongside, matrices are column major (c * 4 + r).
+#[derive(Clone)]
+pub enum Value {
+    Void,
+    Bool(bool),
+    Int(i32),
+    Float(Dual),
+    Bvec(usize, [bool; 4]),
+    Ivec(usize, [i32; 4]),
+    Vec(usize, [Dual; 4]),
+    Mat(usize, Box<[Dual; 16]>),
+    Array(Vec<Value>),
+    Struct(Vec<Value>),
+    Texture(usize),
+    Closure(Rc<ClosureValue>),
+    DrawShader,
+}
+
+impl Default for Value {
+    fn default() -> Self {Value::Void}
+}
+
+impl Value {
+    pub fn float(v: f32) -> Self {
+        Value::Float(Dual::new(v))
+    }
+
+    pub fn vec(values: &[f32]) -> Self {
+        let mut c = [Dual::default(); 4];
+        for (i, v) in values.iter().enumerate() {
+            c[i] = Dual::new(*v);
+        }
+        Value::Vec(values.len(), c)
+    }
+
+    pub fn zero(ty: &Ty) -> Self {
+        match ty {
+            Ty::Void => Value::Void,
+            Ty::Bool => Value::Bool(false),
+            Ty::Int => Value::Int(0),
+            Ty::Float | Ty::Enum(_) => Value::float(0.0),
+            Ty::Bvec2 => Value::Bvec(2, [false; 4]),
+            Ty::Bvec3 => Value::Bvec(3, [false; 4]),
+            Ty::Bvec4 => Value::Bvec(4, [false; 4]),
+            Ty::Ivec2 => Value::Ivec(2, [0; 4]),
+            Ty::Ivec3 => Value::Ivec(3, [0; 4]),
+            Ty::Ivec4 => Value::Ivec(4, [0; 4]),
+            Ty::Vec2 => Value::Vec(2, Default::default()),
+            Ty::Vec3 => Value::Vec(3, Default::default()),
+            Ty::Vec4 => Value::Vec(4, Default::default()),
+            Ty::Mat2 => Value::Mat(2, Default::default()),
+            Ty::Mat3 => Value::Mat(3, Default::default()),
+            Ty::Mat4 => Value::Mat(4, Default::default()),
+            Ty::Array {elem_ty, len} => Value::Array(vec![Value::zero(elem_ty); *len]),
+            _ => Value::Void
+        }
+    }
+
+    /// Unpacks a value from float slots the same way the glsl generator unpacks its tables
+    pub fn from_slots(ty: &Ty, slots: &[f32]) -> Self {
+        fn get(slots: &[f32], i: usize) -> f32 {slots.get(i).cloned().unwrap_or(0.0)}
+        match ty {
+            Ty::Bool => Value::Bool(get(slots, 0) > 0.5),
+            Ty::Int => Value::Int(get(slots, 0) as i32),
+            Ty::Float | Ty::Enum(_) => Value::float(get(slots, 0)),
+            Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 => {
+                let n = ty.slots();
+                let mut c = [false; 4];
+                for (i, c) in c.iter_mut().enumerate().take(n) {*c = get(slots, i) > 0.5}
+                Value::Bvec(n, c)
+            }
+            Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4 => {
+                let n = ty.slots();
+                let mut c = [0; 4];
+                for (i, c) in c.iter_mut().enumerate().take(n) {*c = get(slots, i) as i32}
+                Value::Ivec(n, c)
+            }
+            Ty::Vec2 | Ty::Vec3 | Ty::Vec4 => {
+                let n = ty.slots();
+                let mut c = [Dual::default(); 4];
+                for (i, c) in c.iter_mut().enumerate().take(n) {*c = Dual::new(get(slots, i))}
+                Value::Vec(n, c)
+            }
+            Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+                let n = match ty {Ty::Mat2 => 2, Ty::Mat3 => 3, _ => 4};
+                let mut m = Box::<[Dual; 16]>::default();
+                for col in 0..n {
+                    for row in 0..n {
+                        m[col * 4 + row] = Dual::new(get(slots, col * n + row));
+                    }
+                }
+                Value::Mat(n, m)
+            }
+            Ty::Array {elem_ty, len} => {
+                let step = elem_ty.slots();
+                Value::Array((0..*len).map( | i | Value::from_slots(elem_ty, slots.get(i * step..).unwrap_or(&[]))).collect())
+            }
+            _ => Value::Void
+        }
+    }
+
+    /// Flattens a value into duals, used to interpolate varyings across a triangle
+    pub fn to_duals(&self, out: &mut Vec<Dual>) {
+        match self {
+            Value::Bool(v) => out.push(Dual::new(if *v {1.0} else {0.0})),
+            Value::Int(v) => out.push(Dual::new(*v as f32)),
+            Value::Float(v) => out.push(*v),
+            Value::Bvec(n, c) => out.extend(c[0..*n].iter().map( | v | Dual::new(if *v {1.0} else {0.0}))),
+            Value::Ivec(n, c) => out.extend(c[0..*n].iter().map( | v | Dual::new(*v as f32))),
+            Value::Vec(n, c) => out.extend_from_slice(&c[0..*n]),
+            Value::Mat(n, m) => for col in 0..*n {
+                out.extend_from_slice(&m[col * 4..col * 4 + n]);
+            }
+            Value::Array(a) => for v in a {v.to_duals(out)},
+            _ => ()
+        }
+    }
+
+    /// The inverse of to_duals, keeps the derivatives of the interpolated values
+    pub fn from_duals(ty: &Ty, duals: &[Dual]) -> Self {
+        match ty {
+            Ty::Float | Ty::Enum(_) => Value::Float(duals[0]),
+            Ty::Vec2 | Ty::Vec3 | Ty::Vec4 => {
+                let n = ty.slots();
+                let mut c = [Dual::default(); 4];
+                c[0..n].copy_from_slice(&duals[0..n]);
+                Value::Vec(n, c)
+            }
+            Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+                let n = match ty {Ty::Mat2 => 2, Ty::Mat3 => 3, _ => 4};
+                let mut m = Box::<[Dual; 16]>::default();
+                for col in 0..n {
+                    m[col * 4..col * 4 + n].copy_from_slice(&duals[col * n..col * n + n]);
+                }
+                Value::Mat(n, m)
+            }
+            _ => {
+                let slots: Vec<f32> = duals.iter().map( | d | d.v).collect();
+                Value::from_slots(ty, &slots)
+            }
+        }
+    }
+
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Value::Bool(v) => *v,
+            Value::Int(v) => *v != 0,
+            Value::Float(v) => v.v != 0.0,
+            _ => false
+        }
+    }
+
+    pub fn as_int(&self) -> i32 {
+        match self {
+            Value::Bool(v) => *v as i32,
+            Value::Int(v) => *v,
+            Value::Float(v) => v.v as i32,
+            _ => 0
+        }
+    }
+
+    pub fn as_dual(&self) -> Dual {
+        match self {
+            Value::Bool(v) => Dual::new(if *v {1.0} else {0.0}),
+            Value::Int(v) => Dual::new(*v as f32),
+            Value::Float(v) => *v,
+            Value::Vec(_, c) => c[0],
+            _ => Dual::default()
+        }
+    }
+
+    pub fn as_f32(&self) -> f32 {
+        self.as_dual().v
+    }
+
+    /// Number of scalar components, 1 for scalars and n*n for matrices
+    pub fn components(&self) -> usize {
+        match self {
+            Value::Bool(_) | Value::Int(_) | Value::Float(_) => 1,
+            Value::Bvec(n, _) | Value::Ivec(n, _) | Value::Vec(n, _) => *n,
+            Value::Mat(n, _) => n * n,
+            _ => 0
+        }
+    }
+
+    /// Scalar component i as a dual, matrices are walked column major
+    pub fn component(&self, i: usize) -> Dual {
+        match self {
+            Value::Bvec(_, c) => Dual::new(if c[i] {1.0} else {0.0}),
+            Value::Ivec(_, c) => Dual::new(c[i] as f32),
+            Value::Vec(_, c) => c[i],
+            Value::Mat(n, m) => m[(i / n) * 4 + i % n],
+            v => v.as_dual()
+        }
+    }
+
+    pub fn map(&self, f: impl Fn(Dual) -> Dual) -> Value {
+        match self {
+            Value::Float(v) => Value::Float(f(*v)),
+            Value::Vec(n, c) => {
+                let mut r = *c;
+                for r in r.iter_mut().take(*n) {*r = f(*r)}
+                Value::Vec(*n, r)
+            }
+            Value::Mat(n, m) => {
+                let mut r = m.clone();
+                for r in r.iter_mut() {*r = f(*r)}
+                Value::Mat(*n, r)
+            }
+            Value::Int(v) => Value::Float(f(Dual::new(*v as f32))),
+            v => v.clone()
+        }
+    }
+
+    /// Componentwise float op where a scalar on either side is broadcast
+    pub fn zip(&self, o: &Value, f: impl Fn(Dual, Dual) -> Dual) -> Value {
+        match (self, o) {
+            (Value::Vec(n, a), Value::Vec(_, b)) => {
+                let mut r = [Dual::default(); 4];
+                for i in 0..*n {r[i] = f(a[i], b[i])}
+                Value::Vec(*n, r)
+            }
+            (Value::Vec(n, a), b) => {
+                let b = b.as_dual();
+                let mut r = [Dual::default(); 4];
+                for i in 0..*n {r[i] = f(a[i], b)}
+                Value::Vec(*n, r)
+            }
+            (a, Value::Vec(n, b)) => {
+                let a = a.as_dual();
+                let mut r = [Dual::default(); 4];
+                for i in 0..*n {r[i] = f(a, b[i])}
+                Value::Vec(*n, r)
+            }
+            (Value::Mat(n, a), Value::Mat(_, b)) => {
+                let mut r = Box::<[Dual; 16]>::default();
+                for i in 0..16 {r[i] = f(a[i], b[i])}
+                Value::Mat(*n, r)
+            }
+            (Value::Mat(n, a), b) => {
+                let b = b.as_dual();
+                let mut r = Box::<[Dual; 16]>::default();
+                for i in 0..16 {r[i] = f(a[i], b)}
+                Value::Mat(*n, r)
+            }
+            (a, Value::Mat(n, b)) => {
+                let a = a.as_dual();
+                let mut r = Box::<[Dual; 16]>::default();
+                for i in 0..16 {r[i] = f(a, b[i])}
+                Value::Mat(*n, r)
+            }
+            (a, b) => Value::Float(f(a.as_dual(), b.as_dual()))
+        }
+    }
+
+    pub fn zip_int(&self, o: &Value, f: impl Fn(i32, i32) -> i32) -> Value {
+        match (self, o) {
+            (Value::Ivec(n, a), Value::Ivec(_, b)) => {
+                let mut r = [0; 4];
+                for i in 0..*n {r[i] = f(a[i], b[i])}
+                Value::Ivec(*n, r)
+            }
+            (Value::Ivec(n, a), b) => {
+                let b = b.as_int();
+                let mut r = [0; 4];
+                for i in 0..*n {r[i] = f(a[i], b)}
+                Value::Ivec(*n, r)
+            }
+            (a, Value::Ivec(n, b)) => {
+                let a = a.as_int();
+                let mut r = [0; 4];
+                for i in 0..*n {r[i] = f(a, b[i])}
+                Value::Ivec(*n, r)
+            }
+            (a, b) => Value::Int(f(a.as_int(), b.as_int()))
+        }
+    }
+
+    pub fn is_int(&self) -> bool {
+        matches!(self, Value::Int(_) | Value::Ivec(..))
+    }
+
+    pub fn swizzle(&self, indices: &[usize]) -> Value {
+        match (self, indices.len()) {
+            (Value::Vec(_, c), 1) => Value::Float(c[indices[0]]),
+            (Value::Ivec(_, c), 1) => Value::Int(c[indices[0]]),
+            (Value::Bvec(_, c), 1) => Value::Bool(c[indices[0]]),
+            (Value::Vec(_, c), n) => {
+                let mut r = [Dual::default(); 4];
+                for (i, index) in indices.iter().enumerate() {r[i] = c[*index]}
+                Value::Vec(n, r)
+            }
+            (Value::Ivec(_, c), n) => {
+                let mut r = [0; 4];
+                for (i, index) in indices.iter().enumerate() {r[i] = c[*index]}
+                Value::Ivec(n, r)
+            }
+            (Value::Bvec(_, c), n) => {
+                let mut r = [false; 4];
+                for (i, index) in indices.iter().enumerate() {r[i] = c[*index]}
+                Value::Bvec(n, r)
+            }
+            // a scalar can be swizzled in glsl as well (f.xxx)
+            (v, 1) => v.clone(),
+            (v, n) => {
+                let d = v.as_dual();
+                Value::Vec(n, [d; 4])
+            }
+        }
+    }
+
+    pub fn set_swizzle(&mut self, indices: &[usize], value: &Value) {
+        match self {
+            Value::Vec(_, c) => for (i, index) in indices.iter().enumerate() {
+                c[*index] = value.component(i.min(value.components().max(1) - 1));
+            }
+            Value::Ivec(_, c) => for (i, index) in indices.iter().enumerate() {
+                c[*index] = value.component(i.min(value.components().max(1) - 1)).v as i32;
+            }
+            Value::Bvec(_, c) => for (i, index) in indices.iter().enumerate() {
+                c[*index] = value.component(i.min(value.components().max(1) - 1)).v != 0.0;
+            }
+            v => *v = value.clone()
+        }
+    }
+
+    pub fn index(&self, i: usize) -> Value {
+        match self {
+            Value::Array(a) => a.get(i).cloned().unwrap_or_default(),
+            Value::Vec(n, c) => Value::Float(c[i.min(n - 1)]),
+            Value::Ivec(n, c) => Value::Int(c[i.min(n - 1)]),
+            Value::Bvec(n, c) => Value::Bool(c[i.min(n - 1)]),
+            Value::Mat(n, m) => {
+                let col = i.min(n - 1);
+                let mut r = [Dual::default(); 4];
+                r[0..*n].copy_from_slice(&m[col * 4..col * 4 + n]);
+                Value::Vec(*n, r)
+            }
+            v => v.clone()
+        }
+    }
+
+    pub fn set_index(&mut self, i: usize, value: Value) {
+        match self {
+            Value::Array(a) => if let Some(a) = a.get_mut(i) {*a = value},
+            Value::Vec(n, c) => c[i.min(*n - 1)] = value.as_dual(),
+            Value::Ivec(n, c) => c[i.min(*n - 1)] = value.as_int(),
+            Value::Bvec(n, c) => c[i.min(*n - 1)] = value.as_bool(),
+            Value::Mat(n, m) => {
+                let col = i.min(*n - 1);
+                for row in 0..*n {
+                    m[col * 4 + row] = value.component(row);
+                }
+            }
+            _ => ()
+        }
+    }
+
+    pub fn values_equal(&self, o: &Value) -> bool {
+        match (self, o) {
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) | (Value::Struct(a), Value::Struct(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all( | (a, b) | a.values_equal(b))
+            }
+            (a, b) => {
+                let n = a.components();
+                n == b.components() && (0..n).all( | i | a.component(i).v == b.component(i).v)
+            }
+        }
+    }
+}
+
+pub fn mat_mul_vec(n: usize, m: &[Dual; 16], v: &[Dual; 4]) -> Value {
+    let mut r = [Dual::default(); 4];
+    for (row, r) in r.iter_mut().enumerate().take(n) {
+        let mut sum = Dual::default();
+        for col in 0..n {
+            sum = sum + m[col * 4 + row] * v[col];
+        }
+        *r = sum;
+    }
+    Value::Vec(n, r)
+}
+
+pub fn vec_mul_mat(n: usize, v: &[Dual; 4], m: &[Dual; 16]) -> Value {
+    let mut r = [Dual::default(); 4];
+    for (col, r) in r.iter_mut().enumerate().take(n) {
+        let mut sum = Dual::default();
+        for row in 0..n {
+            sum = sum + v[row] * m[col * 4 + row];
+        }
+        *r = sum;
+    }
+    Value::Vec(n, r)
+}
+
+pub fn mat_mul_mat(n: usize, a: &[Dual; 16], b: &[Dual; 16]) -> Value {
+    let mut r = Box::<[Dual; 16]>::default();
+    for col in 0..n {
+        for row in 0..n {
+            let mut sum = Dual::default();
+            for k in 0..n {
+                sum = sum + a[k * 4 + row] * b[col * 4 + k];
+            }
+            r[col * 4 + row] = sum;
+        }
+    }
+    Value::Mat(n, r)
+}
@@ -502,10 +502,10 @@ impl Cx {
                 CxOsOp::UnmuteVideoPlayback(_) => todo!(),
                 CxOsOp::CleanupVideoPlaybackResources(_) => todo!(),
                 CxOsOp::UpdateVideoSurfaceTexture(_) => todo!(),
-                CxOsOp::SaveFileDialog(_) => todo!(),
-                CxOsOp::SelectFileDialog(_) => todo!(),
-                CxOsOp::SaveFolderDialog(_) => todo!(),
-                CxOsOp::SelectFolderDialog(_) => todo!(),    
+                CxOsOp::SaveFileDialog(_, _) => todo!(),
+                CxOsOp::SelectFileDialog(_, _) => todo!(),
+                CxOsOp::SaveFolderDialog(_, _) => todo!(),
+                CxOsOp::SelectFolderDialog(_, _) => todo!(),    
             }
         }
     }
@@ -24,7 +24,7 @@ use crate::{
 #[derive(Debug)]
 pub struct Pass(PoolId);
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PassId(pub (crate) usize);
 
 impl Pass {
@@ -3,6 +3,7 @@ use {
         id_pool::*,
         cx::Cx,
         os::CxOsTexture,
+        makepad_image_formats::ImageBuffer,
     },
     std::rc::Rc,
 };
@@ -11,7 +12,7 @@ use {
 #[derive(Clone)]
 pub struct Texture(Rc<PoolId>);
 
-#[derive(Clone, Debug, PartialEq, Copy)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
 pub struct TextureId(pub (crate) usize, u64);
 
 impl Texture {
@@ -461,6 +462,36 @@ impl Texture {
         &mut cx.textures[self.texture_id()].format
     }
     
+    /// Reads the texture contents back as u32 BGRA pixels. Vec formats return a copy of their data,
+    /// render targets can only be read back when they were drawn by the software renderer.
+    pub fn read_pixels(&self, cx: &Cx) -> Option<ImageBuffer> {
+        #[cfg(all(target_os = "linux", not(linux_direct)))]
+        if let Some((width, height, data)) = cx.software_texture_pixels(self) {
+            return Some(ImageBuffer {width, height, data})
+        }
+        let to_u8 = | v: f32 | (v.max(0.0).min(1.0) * 255.0 + 0.5) as u32;
+        let (width, height, data) = match &cx.textures[self.texture_id()].format {
+            TextureFormat::VecBGRAu8_32 {width, height, data} |
+            TextureFormat::VecMipBGRAu8_32 {width, height, data, ..} => (*width, *height, data.clone()),
+            TextureFormat::VecRGBAf32 {width, height, data} => (*width, *height, data.chunks_exact(4).map( | c | {
+                (to_u8(c[3]) << 24) | (to_u8(c[0]) << 16) | (to_u8(c[1]) << 8) | to_u8(c[2])
+            }).collect()),
+            TextureFormat::VecRu8 {width, height, data, unpack_row_length} => {
+                let stride = unpack_row_length.unwrap_or(*width);
+                (*width, *height, (0..width * height).map( | i | {
+                    let r = data.get(i / width * stride + i % width).cloned().unwrap_or(0) as u32;
+                    0xff000000 | (r << 16) | (r << 8) | r
+                }).collect())
+            }
+            TextureFormat::VecRf32 {width, height, data} => (*width, *height, data.iter().map( | r | {
+                let r = to_u8(*r);
+                0xff000000 | (r << 16) | (r << 8) | r
+            }).collect()),
+            _ => return None
+        };
+        Some(ImageBuffer {width, height, data})
+    }
+    
     pub fn swap_vec_u32(&self, cx: &mut Cx, image: &mut Vec<u32>) {
         let cxtexture = &mut cx.textures[self.texture_id()];
         match &mut cxtexture.format{
@@ -1,4 +1,9 @@
 use {
+    std::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll, Waker},
+    },
     std::sync::{
         atomic::{AtomicBool, Ordering},
         mpsc::{
@@ -67,15 +72,17 @@ impl SignalFromUI {
 pub struct ToUIReceiver<T> {
     sender: Sender<T>,
     pub receiver: Receiver<T>,
+    waker: Arc<Mutex<Option<Waker >> >,
 }
 
 pub struct ToUISender<T> {
     sender: Sender<T>,
+    waker: Arc<Mutex<Option<Waker >> >,
 }
 
 impl<T> Clone for ToUISender<T> {
     fn clone(&self) -> Self {
-        Self {sender: self.sender.clone()}
+        Self {sender: self.sender.clone(), waker: self.waker.clone()}
     }
 }
 
@@ -87,6 +94,7 @@ impl<T> Default for ToUIReceiver<T> {
         Self {
             sender,
             receiver,
+            waker: Default::default(),
         }
     }
 }
@@ -95,9 +103,15 @@ impl<T> ToUIReceiver<T> {
     pub fn sender(&self) -> ToUISender<T> {
         ToUISender {
             sender: self.sender.clone(),
+            waker: self.waker.clone(),
         }
     }
     
+    /// Returns a future that resolves with the next message, for use in tasks spawned on `cx.spawner()`
+    pub fn recv_async(&self) -> ToUIRecv<'_, T> {
+        ToUIRecv {receiver: self}
+    }
+    
     pub fn try_recv(&self) -> Result<T, TryRecvError> {
         self.receiver.try_recv()
     }
@@ -128,11 +142,35 @@ impl<T> ToUIReceiver<T> {
 impl<T> ToUISender<T> {
     pub fn send(&self, t: T) -> Result<(), SendError<T >> {
         let res = self.sender.send(t);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
         SignalToUI::set_ui_signal();
         res
     }
 }
 
+pub struct ToUIRecv<'a, T> {
+    receiver: &'a ToUIReceiver<T>,
+}
+
+impl<T> Future for ToUIRecv<'_, T> {
+    type Output = T;
+    
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        // the receiver holds a sender itself so the channel never disconnects
+        if let Ok(t) = self.receiver.receiver.try_recv() {
+            return Poll::Ready(t)
+        }
+        *self.receiver.waker.lock().unwrap() = Some(cx.waker().clone());
+        // a message may have arrived between the first check and storing the waker
+        if let Ok(t) = self.receiver.receiver.try_recv() {
+            return Poll::Ready(t)
+        }
+        Poll::Pending
+    }
+}
+
 pub struct FromUIReceiver<T> {
     receiver: Receiver<T>,
 }
@@ -327,3 +365,39 @@ impl<T> MessageThreadPool<T> where T: Clone + Send + 'static {
         self.sender.send(Box::new(task)).unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::makepad_futures::executor,
+        std::{cell::Cell, rc::Rc},
+    };
+
+    #[test]
+    fn recv_async_completes_when_sent_from_another_thread() {
+        let (executor, spawner) = executor::new_executor_and_spawner_with_wake_hook(Some(SignalToUI::set_ui_signal));
+        let receiver = ToUIReceiver::<u32>::default();
+        let sender = receiver.sender();
+        let result = Rc::new(Cell::new(None));
+        spawner.spawn({
+            let result = result.clone();
+            async move {
+                let first = receiver.recv_async().await;
+                let second = receiver.recv_async().await;
+                result.set(Some(first + second));
+            }
+        }).unwrap();
+        executor.run_until_stalled();
+        assert_eq!(result.get(), None);
+
+        std::thread::spawn(move || {
+            sender.send(1).unwrap();
+            sender.send(2).unwrap();
+        }).join().unwrap();
+        // the send woke the task, which scheduled the event loop to run the executor again
+        assert!(UI_SIGNAL.load(Ordering::SeqCst));
+        executor.run_until_stalled();
+        assert_eq!(result.get(), Some(3));
+    }
+}
@@ -0,0 +1,419 @@
+//! Visual regression testing on top of the headless software renderer.
+//!
+//! A `VisualTest` builds an app the same way `app_main!` does, sends it synthetic events,
+//! draws frames offscreen and compares the window pass against golden PNG files.
+//! Run the tests with `MAKEPAD_UPDATE_GOLDEN=1` to (re)write the golden images.
+
+use {
+    std::{
+        any::Any,
+        cell::{Cell, RefCell},
+        path::{Path, PathBuf},
+        rc::Rc,
+    },
+    crate::{
+        makepad_image_formats::{png, ImageBuffer},
+        makepad_live_id::*,
+        makepad_math::*,
+        app_main::AppMain,
+        area::Area,
+        cx::Cx,
+        cx_api::CxOsApi,
+        event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseDownEvent, MouseMoveEvent, MouseUpEvent, TextInputEvent, WindowGeom, WindowGeomChangeEvent},
+        live_traits::LiveNew,
+        pass::Pass,
+        window::{CxWindowPool, WindowId},
+    }
+};
+
+const FRAME_TIME: f64 = 1.0 / 60.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct GoldenTolerance {
+    /// The largest per channel difference that still counts as the same pixel
+    pub channel: u8,
+    /// How many pixels are allowed to differ before the comparison fails
+    pub max_mismatched_pixels: usize,
+}
+
+impl Default for GoldenTolerance {
+    fn default() -> Self {
+        Self {channel: 2, max_mismatched_pixels: 0}
+    }
+}
+
+pub struct ImageDiff {
+    pub mismatched_pixels: usize,
+    pub max_channel_delta: u8,
+    /// Matching pixels are shown dimmed in grayscale, mismatches in red
+    pub diff: ImageBuffer,
+}
+
+impl ImageDiff {
+    pub fn passes(&self, tolerance: GoldenTolerance) -> bool {
+        self.mismatched_pixels <= tolerance.max_mismatched_pixels
+    }
+}
+
+/// Compares two images pixel by pixel, returns an error when their sizes differ
+pub fn diff_images(actual: &ImageBuffer, expected: &ImageBuffer, tolerance: GoldenTolerance) -> Result<ImageDiff, String> {
+    if actual.width != expected.width || actual.height != expected.height {
+        return Err(format!(
+            "image size {}x{} does not match the expected {}x{}",
+            actual.width, actual.height, expected.width, expected.height
+        ))
+    }
+    let mut diff = ImageBuffer::new(actual.width, actual.height);
+    let mut mismatched_pixels = 0;
+    let mut max_channel_delta = 0;
+    for (i, (a, e)) in actual.data.iter().zip(expected.data.iter()).enumerate() {
+        let delta = (0..4).map( | c | {
+            let shift = c * 8;
+            (((a >> shift) & 0xff) as i32 - ((e >> shift) & 0xff) as i32).unsigned_abs() as u8
+        }).max().unwrap();
+        max_channel_delta = max_channel_delta.max(delta);
+        diff.data[i] = if delta > tolerance.channel {
+            mismatched_pixels += 1;
+            0xffff0000
+        }
+        else {
+            let luma = (((a >> 16) & 0xff) * 77 + ((a >> 8) & 0xff) * 150 + (a & 0xff) * 29) >> 8;
+            let dim = luma / 4 + 0x40;
+            0xff000000 | (dim << 16) | (dim << 8) | dim
+        };
+    }
+    Ok(ImageDiff {mismatched_pixels, max_channel_delta, diff})
+}
+
+pub fn save_png(image: &ImageBuffer, path: &Path) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err( | e | format!("cannot create {}: {}", dir.display(), e)) ?;
+    }
+    let data = png::encode(image) ?;
+    std::fs::write(path, data).map_err( | e | format!("cannot write {}: {}", path.display(), e))
+}
+
+/// True when the run was started with MAKEPAD_UPDATE_GOLDEN set
+pub fn update_golden_requested() -> bool {
+    std::env::var_os("MAKEPAD_UPDATE_GOLDEN").is_some()
+}
+
+/// Compares an image against `golden_dir/<name>.png`. Golden images are only written when
+/// `update` is set, a missing one is an error otherwise.
+/// On a mismatch the actual and diff images are written to `output_dir`.
+pub fn compare_golden(actual: &ImageBuffer, golden_dir: &Path, output_dir: &Path, name: &str, tolerance: GoldenTolerance, update: bool) -> Result<(), String> {
+    let golden_path = golden_dir.join(format!("{}.png", name));
+    if update {
+        return save_png(actual, &golden_path)
+    }
+    if !golden_path.exists() {
+        let actual_path = output_dir.join(format!("{}.actual.png", name));
+        save_png(actual, &actual_path) ?;
+        return Err(format!(
+            "{}: no golden image at {}, actual image written to {}, run with MAKEPAD_UPDATE_GOLDEN=1 to accept it",
+            name,
+            golden_path.display(),
+            actual_path.display()
+        ))
+    }
+    let data = std::fs::read(&golden_path).map_err( | e | format!("cannot read {}: {}", golden_path.display(), e)) ?;
+    let expected = png::decode(&data) ?;
+
+    let actual_path = output_dir.join(format!("{}.actual.png", name));
+    let diff = match diff_images(actual, &expected, tolerance) {
+        Ok(diff) => diff,
+        Err(err) => {
+            save_png(actual, &actual_path) ?;
+            return Err(format!("{}: {}, actual image written to {}", name, err, actual_path.display()))
+        }
+    };
+    if diff.passes(tolerance) {
+        return Ok(())
+    }
+    let diff_path = output_dir.join(format!("{}.diff.png", name));
+    save_png(actual, &actual_path) ?;
+    save_png(&diff.diff, &diff_path) ?;
+    Err(format!(
+        "{}: {} pixels differ (max channel delta {}), see {} and {}",
+        name,
+        diff.mismatched_pixels,
+        diff.max_channel_delta,
+        actual_path.display(),
+        diff_path.display()
+    ))
+}
+
+pub struct VisualTest {
+    pub cx: Rc<RefCell<Cx>>,
+    pub golden_dir: PathBuf,
+    pub output_dir: PathBuf,
+    /// Write the captures as the new golden images instead of comparing, set from MAKEPAD_UPDATE_GOLDEN
+    pub update_golden: bool,
+    app: Rc<dyn Any>,
+    time: f64,
+}
+
+impl VisualTest {
+    /// Builds the app and runs the startup, `live_design` is the function that registers the app's live code
+    pub fn new<A: AppMain + LiveNew + 'static>(live_design: fn(&mut Cx)) -> Self {
+        let app = Rc::new(RefCell::new(None::<A>));
+        let app_ref = app.clone();
+        let cx = Rc::new(RefCell::new(Cx::new(Box::new(move | cx, event | {
+            if let Event::Startup = event {
+                *app.borrow_mut() = Some(A::new_main(cx));
+            }
+            if let Event::LiveEdit = event {
+                if let Some(app) = app.borrow_mut().as_mut() {
+                    app.update_main(cx);
+                }
+            }
+            if let Some(app) = app.borrow_mut().as_mut() {
+                app.handle_event(cx, event);
+            }
+        }))));
+        cx.borrow_mut().self_ref = Some(cx.clone());
+        live_design(&mut cx.borrow_mut());
+        cx.borrow_mut().init_cx_os();
+        cx.borrow_mut().headless_start();
+
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default();
+        let mut test = Self {
+            cx,
+            golden_dir: manifest_dir.join("tests").join("golden"),
+            output_dir: std::env::temp_dir().join("makepad_visual_test"),
+            update_golden: update_golden_requested(),
+            app: app_ref,
+            time: 0.0,
+        };
+        // creates the window and runs the first draw
+        test.run_frames(1);
+        test
+    }
+
+    /// Calls `f` with the app to look at its widgets, `A` has to be the type the test was built with
+    pub fn with_app<A: 'static, R>(&self, f: impl FnOnce(&mut A) -> R) -> R {
+        let app = self.app.downcast_ref::<RefCell<Option<A>>>().expect("not the app type of this test");
+        let mut app = app.borrow_mut();
+        f(app.as_mut().expect("the app was not started"))
+    }
+
+    /// Runs one iteration of the event loop, which draws when something asked for a redraw
+    pub fn step(&mut self) {
+        self.time += FRAME_TIME;
+        self.cx.borrow_mut().headless_step(self.time);
+    }
+
+    pub fn run_frames(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    pub fn send_event(&mut self, event: &Event) {
+        self.cx.borrow_mut().call_event_handler(event);
+    }
+
+    pub fn resize(&mut self, inner_size: DVec2, dpi_factor: f64) {
+        let window_id = self.window_id();
+        let mut cx = self.cx.borrow_mut();
+        let old_geom = cx.windows[window_id].window_geom.clone();
+        let new_geom = WindowGeom {
+            dpi_factor,
+            inner_size,
+            outer_size: inner_size,
+            ..old_geom.clone()
+        };
+        cx.windows[window_id].window_geom = new_geom.clone();
+        cx.call_event_handler(&Event::WindowGeomChange(WindowGeomChangeEvent {window_id, old_geom, new_geom}));
+        cx.redraw_all();
+    }
+
+    pub fn mouse_move(&mut self, abs: DVec2) {
+        let mut cx = self.cx.borrow_mut();
+        cx.call_event_handler(&Event::MouseMove(MouseMoveEvent {
+            abs,
+            window_id: CxWindowPool::id_zero(),
+            modifiers: Default::default(),
+            time: self.time,
+            handled: Cell::new(Area::Empty),
+        }));
+        cx.fingers.cycle_hover_area(live_id!(mouse).into());
+        cx.fingers.switch_captures();
+    }
+
+    pub fn mouse_down(&mut self, abs: DVec2, button: usize) {
+        let mut cx = self.cx.borrow_mut();
+        cx.fingers.process_tap_count(abs, self.time);
+        cx.fingers.mouse_down(button);
+        cx.call_event_handler(&Event::MouseDown(MouseDownEvent {
+            abs,
+            button,
+            window_id: CxWindowPool::id_zero(),
+            modifiers: Default::default(),
+            time: self.time,
+            handled: Cell::new(Area::Empty),
+        }));
+    }
+
+    pub fn mouse_up(&mut self, abs: DVec2, button: usize) {
+        let mut cx = self.cx.borrow_mut();
+        cx.call_event_handler(&Event::MouseUp(MouseUpEvent {
+            abs,
+            button,
+            window_id: CxWindowPool::id_zero(),
+            modifiers: Default::default(),
+            time: self.time,
+        }));
+        cx.fingers.mouse_up(button);
+        cx.fingers.cycle_hover_area(live_id!(mouse).into());
+    }
+
+    /// Moves to, presses and releases the left mouse button, with a frame in between each step
+    pub fn click(&mut self, abs: DVec2) {
+        self.mouse_move(abs);
+        self.step();
+        self.mouse_down(abs, 0);
+        self.step();
+        self.mouse_up(abs, 0);
+        self.step();
+    }
+
+    pub fn key_down(&mut self, key_code: KeyCode, modifiers: KeyModifiers) {
+        self.send_event(&Event::KeyDown(KeyEvent {key_code, is_repeat: false, modifiers, time: self.time}));
+    }
+
+    pub fn key_up(&mut self, key_code: KeyCode, modifiers: KeyModifiers) {
+        self.send_event(&Event::KeyUp(KeyEvent {key_code, is_repeat: false, modifiers, time: self.time}));
+    }
+
+    pub fn text_input(&mut self, input: &str) {
+        self.send_event(&Event::TextInput(TextInputEvent {
+            input: input.to_string(),
+            replace_last: false,
+            was_paste: false,
+        }));
+    }
+
+    fn window_id(&self) -> WindowId {
+        CxWindowPool::id_zero()
+    }
+
+    /// Reads back what the window drew in its last frame
+    pub fn capture(&self) -> ImageBuffer {
+        let cx = self.cx.borrow();
+        let pass_id = cx.windows[self.window_id()].main_pass_id.expect("window has no pass");
+        cx.software_pass_texture(pass_id)
+            .and_then( | texture | texture.read_pixels(&cx))
+            .expect("window pass was not drawn")
+    }
+
+    pub fn capture_pass(&self, pass: &Pass) -> Option<ImageBuffer> {
+        let cx = self.cx.borrow();
+        cx.software_pass_texture(pass.pass_id()).and_then( | texture | texture.read_pixels(&cx))
+    }
+
+    pub fn save_png(image: &ImageBuffer, path: &Path) -> Result<(), String> {
+        save_png(image, path)
+    }
+
+    /// Compares the current window contents against `golden_dir/<name>.png`, see `compare_golden`
+    pub fn compare_golden(&self, name: &str, tolerance: GoldenTolerance) -> Result<(), String> {
+        compare_golden(&self.capture(), &self.golden_dir, &self.output_dir, name, tolerance, self.update_golden)
+    }
+
+    pub fn assert_golden(&self, name: &str, tolerance: GoldenTolerance) {
+        if let Err(err) = self.compare_golden(name, tolerance) {
+            panic!("visual test failed: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: usize, height: usize, data: &[u32]) -> ImageBuffer {
+        ImageBuffer {width, height, data: data.to_vec()}
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("makepad_visual_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn diffs_images_within_a_channel_tolerance() {
+        let expected = image(2, 2, &[0xff102030, 0xff000000, 0xffffffff, 0x80406080]);
+        let actual = image(2, 2, &[0xff112030, 0xff000000, 0xfffffffb, 0x80406080]);
+
+        let diff = diff_images(&actual, &expected, GoldenTolerance::default()).unwrap();
+        assert_eq!(diff.mismatched_pixels, 1);
+        assert_eq!(diff.max_channel_delta, 4);
+        assert_eq!(diff.diff.data[2], 0xffff0000);
+        assert_ne!(diff.diff.data[0], 0xffff0000);
+        assert!(!diff.passes(GoldenTolerance::default()));
+        assert!(diff.passes(GoldenTolerance {channel: 2, max_mismatched_pixels: 1}));
+
+        let diff = diff_images(&actual, &expected, GoldenTolerance {channel: 4, max_mismatched_pixels: 0}).unwrap();
+        assert_eq!(diff.mismatched_pixels, 0);
+
+        // alpha counts as a channel too
+        let diff = diff_images(&image(1, 1, &[0x00ffffff]), &image(1, 1, &[0xffffffff]), GoldenTolerance::default()).unwrap();
+        assert_eq!((diff.mismatched_pixels, diff.max_channel_delta), (1, 255));
+
+        assert!(diff_images(&image(2, 1, &[0, 0]), &image(1, 2, &[0, 0]), GoldenTolerance::default()).is_err());
+    }
+
+    #[test]
+    fn does_not_write_missing_goldens() {
+        let dir = temp_dir("missing");
+        let actual = image(1, 1, &[0xff00ff00]);
+        let err = compare_golden(&actual, &dir.join("golden"), &dir.join("output"), "missing", GoldenTolerance::default(), false).unwrap_err();
+        assert!(err.contains("MAKEPAD_UPDATE_GOLDEN"));
+        assert!(!dir.join("golden").join("missing.png").exists());
+        assert!(dir.join("output").join("missing.actual.png").exists());
+
+        save_png(&image(1, 1, &[0xff00ff01]), &dir.join("golden").join("missing.png")).unwrap();
+        assert!(compare_golden(&actual, &dir.join("golden"), &dir.join("output"), "missing", GoldenTolerance::default(), false).is_ok());
+        save_png(&image(1, 1, &[0xffff0000]), &dir.join("golden").join("missing.png")).unwrap();
+        assert!(compare_golden(&actual, &dir.join("golden"), &dir.join("output"), "missing", GoldenTolerance::default(), false).is_err());
+        assert!(dir.join("output").join("missing.diff.png").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Draws overlapping translucent quads and a gradient with the software rasterizer and
+    /// checks them against `tests/golden/software_quads.png`
+    #[cfg(all(target_os = "linux", not(linux_direct)))]
+    #[test]
+    fn software_quads_match_golden() {
+        use crate::os::software::rasterizer::{rasterize_triangle, RasterVertex, SoftwareTarget};
+        let mut target = SoftwareTarget::new(32, 24);
+        target.clear_color([0.1, 0.1, 0.1, 1.0]);
+        let quads = [
+            ([2.0, 2.0, 28.0, 8.0], None),
+            ([4.0, 6.0, 12.0, 14.0], Some([0.8, 0.2, 0.1, 1.0])),
+            ([10.0, 9.0, 14.0, 12.0], Some([0.0, 0.3, 0.6, 0.6])),
+            ([20.0, 4.0, 16.0, 16.0], Some([0.4, 0.4, 0.0, 0.4])),
+        ];
+        let mut inputs = Vec::new();
+        for (rect, color) in quads {
+            let vertices: Vec<RasterVertex> = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].iter().map( | geom: &[f32; 2] | {
+                let x = rect[0] + geom[0] * rect[2];
+                let y = rect[1] + geom[1] * rect[3];
+                RasterVertex {pos: [x / 16.0 - 1.0, 1.0 - y / 12.0, 0.0, 1.0], inputs: geom.to_vec()}
+            }).collect();
+            for triangle in [[0, 1, 2], [2, 3, 0]] {
+                let verts = [&vertices[triangle[0]], &vertices[triangle[1]], &vertices[triangle[2]]];
+                rasterize_triangle(&mut target, verts, &mut inputs, | inputs | {
+                    color.unwrap_or([inputs[0].v, 0.5 * inputs[1].v, 1.0 - inputs[0].v, 1.0])
+                });
+            }
+        }
+        let actual = ImageBuffer {width: target.width, height: target.height, data: target.to_bgra_u32()};
+        let golden_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+        if let Err(err) = compare_golden(&actual, &golden_dir, &temp_dir("software"), "software_quads", GoldenTolerance::default(), update_golden_requested()) {
+            panic!("{}", err);
+        }
+    }
+}
@@ -89,7 +89,7 @@ impl Cx{
                         }
                         WebSocketThreadMsg::SendMessage{socket_id, message}=>{
                             if let Some(socket) = sockets.get_mut(&socket_id){
-                                socket.send_message(message).unwrap();
+                                let _ = socket.send_message(message);
                             }
                         }
                         WebSocketThreadMsg::AppToStudio{message}=>{
@@ -113,7 +113,7 @@ impl Cx{
                     if Instant::now().duration_since(first_time) >= collect_time{
                         // lets send it
                         if let Some(socket) = sockets.get_mut(&0){
-                            socket.send_message(WebSocketMessage::Binary(app_to_studio.serialize_bin())).unwrap();
+                            let _ = socket.send_message(WebSocketMessage::Binary(app_to_studio.serialize_bin()));
                         }
                         app_to_studio.0.clear();
                         first_message = None;
@@ -33,6 +33,10 @@ impl CxWindowPool {
     pub fn id_zero()->WindowId{
         WindowId(0, 0)
     }
+    
+    pub fn iter(&self) -> impl Iterator<Item = &CxWindow> {
+        self.0.pool.iter().map(|d| &d.item)
+    }
 }
 
 impl std::ops::Index<WindowId> for CxWindowPool {
@@ -39,7 +39,10 @@ pub enum FileRequest {
     /// Requests the collab server to apply the given delta to the given revision of the file with
     /// the given id.
     SaveFile(String, String, u64),
-
+    /// Requests the collab server to search the text of its files. The matches are sent as
+    /// `SearchResults` notifications while the search runs, followed by a `SearchDone`
+    /// notification. Starting a search stops the previous one.
+    Search(SearchRequest),
 }
 
 /// A type for representing either a response or a notification from the collab server.
@@ -62,6 +65,40 @@ pub enum FileResponse {
     /// The result of requesting the collab server to apply a delta to a revision of the file with
     /// the given id.
     SaveFile(Result<(String,String,String, u64), FileError>),
+    /// The result of requesting the collab server to start a search with the given id.
+    Search(Result<u64, FileError>),
+}
+
+/// The maximum number of matches a search reports, after which it stops.
+pub const MAX_SEARCH_RESULTS: usize = 10_000;
+
+/// A type for representing what to search for, and in which files.
+#[derive(Clone, Debug, Default, SerBin, DeBin)]
+pub struct SearchRequest {
+    /// Identifies the search. Its notifications carry this id, so that the results of an older
+    /// search can be told apart.
+    pub id: u64,
+    pub pattern: String,
+    /// Whether the pattern is a regular expression, rather than literal text.
+    pub is_regex: bool,
+    pub match_case: bool,
+    /// Globs such as `*.rs` or `src/**/*.toml`, matched against paths relative to the root. Globs
+    /// without a `/` are matched against file names. A file is searched if it matches any of the
+    /// globs, or if there are none, and it matches none of the globs that start with `!`.
+    pub globs: Vec<String>,
+}
+
+/// A type for representing a match of a search.
+#[derive(Clone, Debug, SerBin, DeBin)]
+pub struct SearchResult {
+    /// The path of the file, relative to the root.
+    pub path: String,
+    pub line_index: usize,
+    /// The byte range of the match within its line.
+    pub start: usize,
+    pub end: usize,
+    /// The text of the line.
+    pub line: String,
 }
 
 /// A type for representing data about a file tree.
@@ -97,6 +134,11 @@ pub struct DirectoryEntry {
 #[derive(Clone, Debug, SerBin, DeBin)]
 pub enum FileNotification {
     FileChangedOnDisk,
+    /// Notifies the client of matches found by the search with the given id.
+    SearchResults{ id: u64, results: Vec<SearchResult> },
+    /// Notifies the client that the search with the given id is done, how many matches it found,
+    /// and how many lines it skipped because the pattern was too complex to search them.
+    SearchDone{ id: u64, match_count: usize, skipped_line_count: usize },
     // Notifies the client that another client applied the given delta to the file with the given
     // id. This is only sent for files for which the client is a participant.
    // DeltaWasApplied(TextFileId),
@@ -8,17 +8,23 @@ use {
             FileNotification,
             FileRequest,
             FileResponse,
+            SearchRequest,
         },
+        search::Searcher,
     },
     std::{
         cmp::Ordering,
         fmt,
         fs,
         path::{Path, PathBuf},
-        sync::{Arc, RwLock},
+        sync::{Arc, RwLock, atomic::{self, AtomicU64}},
+        thread,
     },
 };
 
+// The stack size of the thread a search runs on.
+const SEARCH_STACK_SIZE: usize = 16 * 1024 * 1024;
+
 pub struct FileServer {
     // The id for the next connection
     next_connection_id: usize,
@@ -47,7 +53,8 @@ impl FileServer {
         FileServerConnection {
             _connection_id:connection_id,
             shared: self.shared.clone(),
-            _notification_sender: notification_sender
+            notification_sender,
+            search_id: Arc::new(AtomicU64::new(0)),
         }
     }
 }
@@ -59,7 +66,9 @@ pub struct FileServerConnection {
     // State is shared between every connection.
     shared: Arc<RwLock<Shared >>,
     // Used to send notifications for this connection.
-    _notification_sender: Box<dyn NotificationSender>,
+    notification_sender: Box<dyn NotificationSender>,
+    // The id of the last search, a running search stops when it changes.
+    search_id: Arc<AtomicU64>,
 }
 
 impl FileServerConnection {
@@ -74,6 +83,7 @@ impl FileServerConnection {
             FileRequest::LoadFileTree {with_data} => FileResponse::LoadFileTree(self.load_file_tree(with_data)),
             FileRequest::OpenFile(path,id) => FileResponse::OpenFile(self.open_file(path, id)),
             FileRequest::SaveFile(path, delta, id) => FileResponse::SaveFile(self.save_file(path, delta, id)),
+            FileRequest::Search(request) => FileResponse::Search(self.search(request)),
         }
     }
     
@@ -202,6 +212,31 @@ impl FileServerConnection {
         
         Ok((child_path, old_content, new_content, id))
     }
+    
+    // Handles a `Search` request. The search runs on its own thread, so that other requests can
+    // be handled in the meantime, and sends what it finds as notifications.
+    fn search(&self, request: SearchRequest) -> Result<u64, FileError> {
+        let searcher = Searcher::new(&request).map_err(
+            | error | FileError::Unknown(error.to_string())
+        ) ?;
+        let id = request.id;
+        self.search_id.store(id, atomic::Ordering::SeqCst);
+        let search_id = self.search_id.clone();
+        let notification_sender = self.notification_sender.clone();
+        let root_path = self.shared.read().unwrap().root_path.clone();
+        // the pattern matcher recurses, so give it more room than the default stack of a thread
+        thread::Builder::new().name("search".to_string()).stack_size(SEARCH_STACK_SIZE).spawn(move || {
+            let (match_count, skipped_line_count) = searcher.search_tree(
+                &root_path,
+                &|| search_id.load(atomic::Ordering::SeqCst) != id,
+                &mut | results | notification_sender.send_notification(FileNotification::SearchResults {id, results}),
+            );
+            notification_sender.send_notification(FileNotification::SearchDone {id, match_count, skipped_line_count});
+        }).map_err(
+            | error | FileError::Unknown(error.to_string())
+        ) ?;
+        Ok(id)
+    }
 }
 
 /// A trait for sending notifications over a connection.
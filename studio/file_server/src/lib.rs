@@ -3,8 +3,11 @@
 pub mod file_server;
 #[cfg(not(target_arch = "wasm32"))]
 pub use file_server::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod search;
 
 pub use makepad_micro_serde;
 pub use makepad_live_id;
 pub use makepad_file_protocol;
+pub use makepad_regex;
 pub use makepad_file_protocol::*;
@@ -0,0 +1,252 @@
+use {
+    crate::{
+        makepad_file_protocol::{SearchRequest, SearchResult, MAX_SEARCH_RESULTS},
+        makepad_regex::{Regex, RegexError},
+    },
+    std::{
+        fs,
+        path::Path,
+    },
+};
+
+/// A search request, with its pattern compiled, so it can be run over every file of a tree.
+pub struct Searcher {
+    regex: Regex,
+    include_globs: Vec<String>,
+    exclude_globs: Vec<String>,
+}
+
+impl Searcher {
+    pub fn new(request: &SearchRequest) -> Result<Searcher, RegexError> {
+        let regex = if request.is_regex {
+            Regex::new(&request.pattern) ?
+        }
+        else {
+            Regex::literal(&request.pattern)
+        };
+        let mut include_globs = Vec::new();
+        let mut exclude_globs = Vec::new();
+        for glob in &request.globs {
+            let glob = glob.trim();
+            if let Some(glob) = glob.strip_prefix('!') {
+                exclude_globs.push(glob.to_string());
+            }
+            else if !glob.is_empty() {
+                include_globs.push(glob.to_string());
+            }
+        }
+        Ok(Searcher {
+            regex: regex.ignore_case(!request.match_case),
+            include_globs,
+            exclude_globs,
+        })
+    }
+
+    /// Whether the file at `path`, relative to the root, passes the globs of the request.
+    pub fn includes_path(&self, path: &str) -> bool {
+        (self.include_globs.is_empty() || self.include_globs.iter().any( | glob | glob_matches_path(glob, path)))
+            && !self.exclude_globs.iter().any( | glob | glob_matches_path(glob, path))
+    }
+
+    /// Adds the matches in `text`, the contents of the file at `path`, to `results`. Returns the
+    /// number of lines the pattern was too complex to search completely.
+    pub fn search_text(&self, path: &str, text: &str, results: &mut Vec<SearchResult>) -> usize {
+        let mut skipped_line_count = 0;
+        for (line_index, line) in text.lines().enumerate() {
+            let regex_matches = match self.regex.find_all(line) {
+                Ok(regex_matches) => regex_matches,
+                Err(aborted) => {
+                    skipped_line_count += 1;
+                    aborted.matches
+                }
+            };
+            for regex_match in regex_matches {
+                if regex_match.start() == regex_match.end() {
+                    continue;
+                }
+                results.push(SearchResult {
+                    path: path.to_string(),
+                    line_index,
+                    start: regex_match.start(),
+                    end: regex_match.end(),
+                    line: line.to_string(),
+                });
+            }
+        }
+        skipped_line_count
+    }
+
+    /// Searches every file below `root_path`, skipping the same entries the file tree does, and
+    /// calls `send_results` with the matches of each file that has any. It stops early when
+    /// `is_cancelled` returns true, or after `MAX_SEARCH_RESULTS` matches. Returns the number of
+    /// matches found and the number of lines that were too complex to search completely.
+    pub fn search_tree(
+        &self,
+        root_path: &Path,
+        is_cancelled: &dyn Fn() -> bool,
+        send_results: &mut dyn FnMut(Vec<SearchResult>),
+    ) -> (usize, usize) {
+        let mut match_count = 0;
+        let mut skipped_line_count = 0;
+        self.search_directory(root_path, "", is_cancelled, send_results, &mut match_count, &mut skipped_line_count);
+        (match_count, skipped_line_count)
+    }
+
+    fn search_directory(
+        &self,
+        path: &Path,
+        relative_path: &str,
+        is_cancelled: &dyn Fn() -> bool,
+        send_results: &mut dyn FnMut(Vec<SearchResult>),
+        match_count: &mut usize,
+        skipped_line_count: &mut usize,
+    ) {
+        let Ok(entries) = fs::read_dir(path) else {return};
+        let mut entries: Vec<_> = entries.filter_map( | entry | entry.ok()).collect();
+        entries.sort_by_key( | entry | entry.file_name());
+        for entry in entries {
+            if is_cancelled() || *match_count >= MAX_SEARCH_RESULTS {
+                return
+            }
+            let entry_path = entry.path();
+            // Skip entries with a non UTF-8 file name.
+            let Ok(name) = entry.file_name().into_string() else {continue};
+            if entry_path.is_dir() && name == "target" || name.starts_with('.') {
+                continue;
+            }
+            let entry_relative_path = if relative_path.is_empty() {
+                name
+            }
+            else {
+                format!("{}/{}", relative_path, name)
+            };
+            if entry_path.is_dir() {
+                self.search_directory(&entry_path, &entry_relative_path, is_cancelled, send_results, match_count, skipped_line_count);
+            }
+            else if entry_path.is_file() && self.includes_path(&entry_relative_path) {
+                let Ok(bytes) = fs::read(&entry_path) else {continue};
+                // Skip files that look binary.
+                if bytes.iter().take(1024).any( | byte | *byte == 0) {
+                    continue;
+                }
+                let mut results = Vec::new();
+                *skipped_line_count += self.search_text(&entry_relative_path, &String::from_utf8_lossy(&bytes), &mut results);
+                results.truncate(MAX_SEARCH_RESULTS - *match_count);
+                if !results.is_empty() {
+                    *match_count += results.len();
+                    send_results(results);
+                }
+            }
+        }
+    }
+}
+
+/// Matches a glob against a path with `/` separators. `*` and `?` don't match a `/`, `**` does.
+/// Globs without a `/` are matched against the file name only.
+pub fn glob_matches_path(glob: &str, path: &str) -> bool {
+    let path = if glob.contains('/') {
+        path
+    }
+    else {
+        path.rsplit('/').next().unwrap()
+    };
+    let glob: Vec<char> = glob.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    glob_matches(&glob, &path)
+}
+
+fn glob_matches(glob: &[char], path: &[char]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directories at all
+            if let ['/', after_slash @ ..] = rest {
+                if glob_matches(after_slash, path) {
+                    return true
+                }
+            }
+            (0..=path.len()).any( | index | glob_matches(rest, &path[index..]))
+        }
+        ['*', rest @ ..] => {
+            let len = path.iter().position( | char | *char == '/').unwrap_or(path.len());
+            (0..=len).any( | index | glob_matches(rest, &path[index..]))
+        }
+        ['?', rest @ ..] => match path {
+            [char, path_rest @ ..] if *char != '/' => glob_matches(rest, path_rest),
+            _ => false
+        },
+        [glob_char, rest @ ..] => match path {
+            [char, path_rest @ ..] if char == glob_char => glob_matches(rest, path_rest),
+            _ => false
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_matches_path("*.rs", "studio/src/app.rs"));
+        assert!(!glob_matches_path("*.rs", "studio/Cargo.toml"));
+        assert!(glob_matches_path("studio/**/*.rs", "studio/src/app.rs"));
+        assert!(glob_matches_path("studio/**/*.rs", "studio/app.rs"));
+        assert!(!glob_matches_path("studio/*.rs", "studio/src/app.rs"));
+        assert!(glob_matches_path("src/ap?.rs", "src/app.rs"));
+        assert!(!glob_matches_path("src/ap?.rs", "src/appp.rs"));
+    }
+
+    #[test]
+    fn searches_text_with_filters() {
+        let searcher = Searcher::new(&SearchRequest {
+            id: 1,
+            pattern: "fn \\w+".to_string(),
+            is_regex: true,
+            match_case: true,
+            globs: vec!["*.rs".to_string(), "!tests/**".to_string()],
+        }).unwrap();
+        assert!(searcher.includes_path("src/lib.rs"));
+        assert!(!searcher.includes_path("tests/lib.rs"));
+        assert!(!searcher.includes_path("README.md"));
+
+        let mut results = Vec::new();
+        searcher.search_text("src/lib.rs", "fn main() {}\r\n// FN no\n  fn run() {}", &mut results);
+        let found: Vec<_> = results.iter().map( | result | (result.line_index, result.start, result.end)).collect();
+        assert_eq!(found, vec![(0, 0, 7), (2, 2, 8)]);
+
+        let searcher = Searcher::new(&SearchRequest {
+            pattern: "FN".to_string(),
+            ..Default::default()
+        }).unwrap();
+        let mut results = Vec::new();
+        searcher.search_text("a.rs", "fn a() {}", &mut results);
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn searches_a_tree_with_a_long_line() {
+        let root_path = std::env::temp_dir().join(format!("makepad_search_tree_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root_path);
+        fs::create_dir_all(root_path.join("src")).unwrap();
+        fs::create_dir_all(root_path.join("target")).unwrap();
+        fs::write(root_path.join("src/short.rs"), "ab\nx ab ab").unwrap();
+        fs::write(root_path.join("src/long.rs"), format!("ab\n{}", "ab".repeat(50_000))).unwrap();
+        fs::write(root_path.join("target/skipped.rs"), "ab").unwrap();
+
+        let searcher = Searcher::new(&SearchRequest {
+            pattern: "(ab)+".to_string(),
+            is_regex: true,
+            ..Default::default()
+        }).unwrap();
+        let mut results = Vec::new();
+        // the stack of a test thread is as small as the default one of a spawned thread
+        let (match_count, skipped_line_count) = searcher.search_tree(&root_path, &|| false, &mut | file_results | results.extend(file_results));
+        fs::remove_dir_all(&root_path).unwrap();
+
+        let found: Vec<_> = results.iter().map( | result | (result.path.as_str(), result.line_index, result.start)).collect();
+        assert_eq!(found, vec![("src/long.rs", 0, 0), ("src/short.rs", 0, 0), ("src/short.rs", 1, 2), ("src/short.rs", 1, 5)]);
+        assert_eq!(match_count, 4);
+        assert_eq!(skipped_line_count, 1);
+    }
+}
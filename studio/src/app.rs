@@ -1,5 +1,5 @@
 use crate::{
-    makepad_code_editor::code_editor::*,
+    makepad_code_editor::{code_editor::*, session::RangeReplacement},
     makepad_widgets::*,
     makepad_micro_serde::*,
     makepad_widgets::file_tree::*,
@@ -13,7 +13,12 @@ use crate::{
             BuildManager,
             BuildManagerAction
         },
-    }
+        build_protocol::LogItemFix,
+    },
+    lsp::lsp_manager::{
+        LspManager,
+        LspAction
+    },
 }; 
 use std::fs::File;
 use std::io::Write;
@@ -39,6 +44,7 @@ impl LiveRegister for App{
         crate::makepad_code_editor::live_design(cx);
         crate::run_list::live_design(cx);
         crate::log_list::live_design(cx);
+        crate::search_list::live_design(cx);
         crate::profiler::live_design(cx);
         crate::run_view::live_design(cx);
         crate::studio_editor::live_design(cx);
@@ -61,12 +67,52 @@ impl App {
             self.data.file_system.ensure_unique_tab_names(cx, &dock)
         }
     }
+    
+    /// Applies the fixes to the files they are for, opening those that aren't open yet.
+    /// Each file gets a single undoable edit. The fixes are dropped from the log, so they can't
+    /// be applied twice.
+    fn apply_fixes(&mut self, cx: &mut Cx, fixes: Vec<LogItemFix>) {
+        let dock = self.ui.dock(id!(dock));
+        self.data.build_manager.remove_fixes(&fixes);
+        cx.action(AppAction::RedrawLog);
+        let mut file_ranges: Vec<(String, Vec<_>)> = Vec::new();
+        for fix in fixes {
+            let range = RangeReplacement {
+                start: fix.start,
+                end: fix.end,
+                original: fix.original,
+                text: fix.replacement,
+            };
+            match file_ranges.iter_mut().find( | (file_name, _) | *file_name == fix.file_name) {
+                Some((_, ranges)) => ranges.push(range),
+                None => file_ranges.push((fix.file_name, vec![range]))
+            }
+        }
+        for (file_name, ranges) in file_ranges {
+            let Some(file_id) = self.data.file_system.path_to_file_node_id(&file_name) else {continue};
+            let tab_id = match self.data.file_system.file_node_id_to_tab_id(file_id) {
+                Some(tab_id) => tab_id,
+                None => {
+                    let tab_id = dock.unique_tab_id(file_id.0.0);
+                    self.data.file_system.request_open_file(tab_id, file_id);
+                    dock.create_and_select_tab(cx, live_id!(edit_tabs), tab_id, live_id!(StudioEditor), "".to_string(), TabClosable::Yes);
+                    self.data.file_system.ensure_unique_tab_names(cx, &dock);
+                    tab_id
+                }
+            };
+            if self.data.file_system.replace_ranges(tab_id, ranges) {
+                self.data.lsp.did_change(tab_id, &mut self.data.file_system);
+                self.data.file_system.redraw_view_by_file_id(cx, file_id, &dock);
+            }
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct AppData{
     pub build_manager: BuildManager,
     pub file_system: FileSystem,
+    pub lsp: LspManager,
 }
 
 // all global app commands coming in from keybindings, and UI components
@@ -74,6 +120,7 @@ pub struct AppData{
 #[derive(DefaultNone, Debug, Clone)]
 pub enum AppAction{
     JumpTo(JumpTo),
+    ApplyFixes(Vec<LogItemFix>),
     RedrawLog,
     RedrawProfiler,
     RedrawFile(FileNodeId),
@@ -99,12 +146,30 @@ impl MatchEvent for App{
         self.data.build_manager.init(cx, &root_path);
         self.data.build_manager.discover_external_ip(cx);
         self.data.build_manager.start_http_server();
+        self.data.lsp.init(&root_path);
+        
+        // restore the dock layout of the previous session
+        if let Ok(contents) = std::fs::read_to_string("makepad_state.ron") {
+            if let Ok(mut state) = PersistentState::deserialize_ron(&contents) {
+                // editor tabs would come back without their files, so only restore the layout around them
+                state.dock_items.retain(|di| {
+                    if let DockItemStore::Tab{kind,..} = di{
+                        if kind.0 == live_id!(StudioEditor){
+                            return false
+                        }
+                    }
+                    true
+                });
+                self.ui.dock(id!(dock)).from_store_item(cx, &state.dock_items);
+            }
+        }
     }
     
     fn handle_action(&mut self, cx:&mut Cx, action:&Action){
         let dock = self.ui.dock(id!(dock));
         let file_tree = self.ui.view(id!(file_tree));
         let log_list = self.ui.view(id!(log_list));
+        let search = self.ui.view(id!(search));
         let run_list = self.ui.view(id!(run_list));
         let profiler = self.ui.view(id!(profiler));
         match action.cast(){
@@ -115,7 +180,7 @@ impl MatchEvent for App{
                         // ok lets scroll into view
                         if let Some(mut editor) = dock.item(tab_id).as_studio_editor().borrow_mut() {
                             if let Some(session) = self.data.file_system.get_session_mut(tab_id) {
-                                editor.editor.set_cursor_and_scroll(cx, jt.start, session);
+                                editor.editor.set_selection_and_scroll(cx, jt.start, jt.end, session);
                                 editor.editor.set_key_focus(cx);
                             }
                         }
@@ -124,6 +189,8 @@ impl MatchEvent for App{
                         // lets open the editor
                         let tab_id = dock.unique_tab_id(file_id.0.0);
                         self.data.file_system.request_open_file(tab_id, file_id);
+                        // and jump once the file has loaded
+                        self.data.file_system.pending_jumps.insert(tab_id, jt);
                         // lets add a file tab 'somewhere'
                         dock.create_and_select_tab(cx, live_id!(edit_tabs), tab_id, live_id!(StudioEditor), "".to_string(), TabClosable::Yes);
                         // lets scan the entire doc for duplicates
@@ -131,6 +198,9 @@ impl MatchEvent for App{
                     }
                 }
             }
+            AppAction::ApplyFixes(fixes)=>{
+                self.apply_fixes(cx, fixes);
+            }
             AppAction::RedrawFile(file_id)=>{
                 self.data.file_system.redraw_view_by_file_id(cx, file_id, &dock);
             }
@@ -171,12 +241,26 @@ impl MatchEvent for App{
             }
             BuildManagerAction::None=>()
         }
+        
+        match action.cast(){
+            LspAction::ShowCompletions {tab_id, items} => {
+                if let Some(mut editor) = dock.item(tab_id).as_studio_editor().borrow_mut() {
+                    if let Some(session) = self.data.file_system.get_session_mut(tab_id) {
+                        editor.editor.show_completions(cx, session, items);
+                    }
+                }
+            }
+            LspAction::None=>()
+        }
                 
         match action.cast(){
             FileSystemAction::TreeLoaded => {
                 file_tree.redraw(cx);
                 //self.open_code_file_by_path(cx, "examples/slides/src/app.rs");
             }
+            FileSystemAction::SearchChanged => {
+                search.redraw(cx);
+            }
             FileSystemAction::RecompileNeeded => {
                 self.data.build_manager.start_recompile_timer(cx, &self.ui);
             }
@@ -207,7 +291,29 @@ impl MatchEvent for App{
             match action.cast(){
                 CodeEditorAction::TextDidChange => {
                     // lets write the file
-                    self.data.file_system.request_save_file(action.path.get(0))
+                    self.data.file_system.request_save_file(action.path.get(0));
+                    self.data.lsp.did_change(action.path.get(0), &mut self.data.file_system);
+                }
+                CodeEditorAction::RequestCompletion => {
+                    self.data.lsp.request_completion(action.path.get(0), &mut self.data.file_system);
+                }
+                CodeEditorAction::RequestHover => {
+                    self.data.lsp.request_hover(action.path.get(0), &mut self.data.file_system);
+                }
+                CodeEditorAction::RequestDefinition => {
+                    self.data.lsp.request_definition(action.path.get(0), &mut self.data.file_system);
+                }
+                CodeEditorAction::QuickFix => {
+                    let tab_id = action.path.get(0);
+                    if let Some(file_id) = self.data.file_system.tab_id_to_file_node_id.get(&tab_id).cloned() {
+                        let file_name = self.data.file_system.file_node_path(file_id);
+                        let position = self.data.file_system.get_session_mut(tab_id).and_then( | session | {
+                            Some(session.selections()[session.last_added_selection_index() ?].cursor.position)
+                        });
+                        if let Some(fixes) = position.and_then( | position | self.data.build_manager.fixes_at(&file_name, position)) {
+                            self.apply_fixes(cx, fixes);
+                        }
+                    }
                 }
                 CodeEditorAction::None=>{}
             }
@@ -324,6 +430,7 @@ impl AppMain for App {
         
         self.data.file_system.handle_event(cx, event, &self.ui);
         self.data.build_manager.handle_event(cx, event, &mut self.data.file_system); 
+        self.data.lsp.handle_event(cx, event, &mut self.data.file_system);
 
         // process events on all run_views
         let dock = self.ui.dock(id!(dock));
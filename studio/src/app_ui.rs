@@ -8,6 +8,7 @@ live_design!{
     import makepad_studio::studio_file_tree::StudioFileTree;
     import makepad_studio::run_view::RunView;
     import makepad_studio::log_list::LogList;
+    import makepad_studio::search_list::SearchList;
     import makepad_studio::run_list::RunList;
     import makepad_studio::profiler::Profiler;
     
@@ -222,48 +223,7 @@ live_design!{
             }
             RunList = <RunList> {
             }
-            Search = <RectView> {
-                draw_bg: {color: #x28}
-                //  margin:{left: 0, top: 0}
-                <View> {
-                    margin:10
-                    flow: Down
-                    <View> 
-                    {
-                        flow: Right
-                        height: Fit
-                        <TextInput>{
-                            draw_bg: {
-                                fn pixel(self) -> vec4 {
-                                    return #x00000044
-                                }
-                            }
-                            width: Fill,
-                            empty_message:"Search here"                           
-                        }
-                        //panic = <IconButton> {draw_icon: {svg_file: (ICO_PANIC)} icon_walk: {width: Fit, height: 17.0}, margin: {left: 5.0, right: -10.0}}
-                    // <Button> {
-                    //     text:"Search"
-                            
-                    //     draw_icon: {
-                    //         svg_file: (ICO_SEARCH)
-                    //         fn get_color(self) -> vec4 {
-                    //             return #8;
-                    //         }
-                    //     } 
-                    //     icon_walk:  {
-                    //         margin: {left: 10}
-                    //         width: Fit,
-                    //         // height: 12.0
-                    //     } 
-                    // }
-                }
-                    <Label>
-                    {
-                        text: "this does not work yet."
-                    }
-                }
-            }
+            Search = <SearchList> {}
             RunView = <RunView> {}
             StudioFileTree = <StudioFileTree> {}
             LogList = <LogList> {}
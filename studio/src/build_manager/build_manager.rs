@@ -192,6 +192,34 @@ impl BuildManager {
         self.profile.clear();
     }
     
+    /// All fixes the build logged that are safe to apply, for applying them in one go.
+    pub fn build_fixes(&self, build_id: LiveId) -> Vec<LogItemFix> {
+        self.log.iter().filter_map( | (id, item) | match item {
+            LogItem::Location(loc) if *id == build_id => Some(loc.fixes.iter().cloned()),
+            _ => None
+        }).flatten().collect()
+    }
+
+    /// The fixes of the first logged message at `position` in `file_name` that has any.
+    pub fn fixes_at(&self, file_name: &str, position: text::Position) -> Option<Vec<LogItemFix>> {
+        self.log.iter().find_map( | (_, item) | match item {
+            LogItem::Location(loc) if loc.file_name == file_name
+                && loc.start <= position && position <= loc.end
+                && !loc.fixes.is_empty() => Some(loc.fixes.clone()),
+            _ => None
+        })
+    }
+
+    /// Drops fixes from the log once they are applied, so they aren't applied to the changed
+    /// text again.
+    pub fn remove_fixes(&mut self, fixes: &[LogItemFix]) {
+        for (_, item) in &mut self.log {
+            if let LogItem::Location(loc) = item {
+                loc.fixes.retain( | fix | !fixes.contains(fix));
+            }
+        }
+    }
+
     pub fn start_recompile_timer(&mut self, cx: &mut Cx, ui: &WidgetRef) {
         cx.stop_timer(self.recompile_timer);
         self.recompile_timer = cx.start_timeout(self.recompile_timeout);
@@ -264,7 +292,8 @@ impl BuildManager {
                                 file_name: item.file_name,
                                 start,
                                 end,
-                                message: item.message
+                                message: item.message,
+                                fixes: Vec::new()
                             })));
                             cx.action(AppAction::RedrawLog)
                         }
@@ -147,7 +147,20 @@ pub struct LogItemLocation{
     pub file_name: String,
     pub start: Position,
     pub end: Position,
-    pub message: String
+    pub message: String,
+    /// The fixes the compiler suggested for this message that are safe to apply.
+    pub fixes: Vec<LogItemFix>
+}
+
+/// A replacement of the text between `start` and `end`, where the columns are byte indices.
+/// `original` is the text rustc found there, the fix only applies while it is still there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogItemFix{
+    pub file_name: String,
+    pub start: Position,
+    pub end: Position,
+    pub original: String,
+    pub replacement: String
 }
 
 #[derive(Clone, Debug)]
@@ -303,10 +303,12 @@ impl BuildConnection {
         // initial swapchain to the client at all, unless we have this first
         // (thankfully sending this before we ever read from the client means
         // it will definitely arrive before C->H ReadyToStart triggers anything).
-        msg_sender.send_message(BuildClientMessageWrap{
-            cmd_id,
-            message: BuildClientMessage::AuxChanHostEndpointCreated(process.aux_chan_host_endpoint.clone()),
-        });
+        if let Some(aux_chan_host_endpoint) = &process.aux_chan_host_endpoint {
+            msg_sender.send_message(BuildClientMessageWrap{
+                cmd_id,
+                message: BuildClientMessage::AuxChanHostEndpointCreated(aux_chan_host_endpoint.clone()),
+            });
+        }
 
        // let mut stderr_state = StdErrState::First;
         //let stdin_sender = process.stdin_sender.clone();
@@ -361,6 +363,8 @@ impl BuildConnection {
                             msg_sender.send_bare_message(cmd_id, LogLevel::Error, line);
                         }
                     }
+                    // builds are started line based
+                    ChildStdIO::StdOutBytes(_) => {}
                     ChildStdIO::Term => {
                         msg_sender.send_bare_message(cmd_id, LogLevel::Log, "process terminated".into());
                         break;
@@ -426,7 +430,7 @@ pub trait MsgSender: Send {
     }
     
 
-    fn send_location_msg(&self, cmd_id: LiveId, level: LogLevel, file_name: String, start: Position, end: Position, message: String) {
+    fn send_location_msg(&self, cmd_id: LiveId, level: LogLevel, file_name: String, start: Position, end: Position, message: String, fixes: Vec<LogItemFix>) {
         self.send_message(
             BuildClientMessageWrap{
                 cmd_id,
@@ -435,7 +439,8 @@ pub trait MsgSender: Send {
                 file_name,
                 start,
                 end,
-                message
+                message,
+                fixes
             }))
         });
     }
@@ -455,7 +460,15 @@ pub trait MsgSender: Send {
                 }
             };
             if let Some(span) = msg.spans.iter().find( | span | span.is_primary) {
-                self.send_location_msg(cmd_id, level, span.file_name.clone(),span.start(), span.end(), msg.message.clone());
+                // without the text rustc saw there, a fix can't be checked against the file
+                let fixes = msg.machine_applicable_spans().filter_map( | span | Some(LogItemFix {
+                    file_name: span.file_name.clone(),
+                    start: span.start_in_bytes(),
+                    end: span.end_in_bytes(),
+                    original: span.highlighted_text() ?,
+                    replacement: span.suggested_replacement.clone().unwrap_or_default(),
+                })).collect();
+                self.send_location_msg(cmd_id, level, span.file_name.clone(),span.start(), span.end(), msg.message.clone(), fixes);
                 /*
                 if let Some(label) = &span.label {
                     self.send_location_msg(cmd_id, level, span.file_name.clone(), range, label.clone());
@@ -14,11 +14,12 @@ pub struct ChildProcess {
     pub stdin_sender: Sender<ChildStdIn>,
     pub line_sender: Sender<ChildStdIO>,
     pub line_receiver: Receiver<ChildStdIO>,
-    pub aux_chan_host_endpoint: aux_chan::HostEndpoint,
+    pub aux_chan_host_endpoint: Option<aux_chan::HostEndpoint>,
 }
 
 pub enum ChildStdIO {
     StdOut(String),
+    StdOutBytes(Vec<u8>),
     StdErr(String),
     Term,
     Kill
@@ -41,8 +42,29 @@ impl ChildProcess {
         let mut cmd_build = Command::new(cmd);
         
         cmd_build.args(args)
-            .args(aux_chan_client_endpoint_inheritable.extra_args_for_client_spawning())
-            .stdin(Stdio::piped())
+            .args(aux_chan_client_endpoint_inheritable.extra_args_for_client_spawning());
+        
+        let child = Self::spawn(cmd_build, current_dir, env)?;
+
+        // In the parent process, an inherited fd doesn't need to exist past
+        // the spawning of the child process (which clones non-`CLOEXEC` fds).
+        drop(aux_chan_client_endpoint_inheritable);
+        
+        Ok(Self::connect(child, Some(aux_chan_host_endpoint), false))
+    }
+    
+    /// Starts a process that isn't a makepad application, without the aux channel. Its stdout
+    /// arrives as `StdOutBytes` chunks instead of lines, for protocols that aren't line based
+    /// such as the language server protocol.
+    pub fn start_raw(cmd: &str, args: &[String], current_dir: PathBuf, env: &[(&str, &str)]) -> Result<ChildProcess, std::io::Error> {
+        let mut cmd_build = Command::new(cmd);
+        cmd_build.args(args);
+        let child = Self::spawn(cmd_build, current_dir, env)?;
+        Ok(Self::connect(child, None, true))
+    }
+    
+    fn spawn(mut cmd_build: Command, current_dir: PathBuf, env: &[(&str, &str)]) -> Result<Child, std::io::Error> {
+        cmd_build.stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .current_dir(current_dir);
@@ -51,12 +73,10 @@ impl ChildProcess {
             cmd_build.env(key, value);
         }
         
-        let mut child = cmd_build.spawn()?;
-
-        // In the parent process, an inherited fd doesn't need to exist past
-        // the spawning of the child process (which clones non-`CLOEXEC` fds).
-        drop(aux_chan_client_endpoint_inheritable);
-        
+        cmd_build.spawn()
+    }
+    
+    fn connect(mut child: Child, aux_chan_host_endpoint: Option<aux_chan::HostEndpoint>, stdout_bytes: bool) -> ChildProcess {
         let (line_sender, line_receiver) = mpsc::channel();
         let (stdin_sender, stdin_receiver) = mpsc::channel();
 
@@ -69,6 +89,23 @@ impl ChildProcess {
             let stdin_sender = stdin_sender.clone();
             thread::spawn(move || {
                 let mut reader = BufReader::new(stdout);
+                if stdout_bytes{
+                    let mut buf = [0u8; 4096];
+                    loop{
+                        match reader.read(&mut buf){
+                            Ok(len) if len > 0 => if line_sender.send(ChildStdIO::StdOutBytes(buf[..len].to_vec())).is_err(){
+                                break
+                            }
+                            // report the end of the output as well, so the reader knows the process is gone
+                            _ => {
+                                let _ = line_sender.send(ChildStdIO::Term);
+                                let _ = stdin_sender.send(ChildStdIn::Term);
+                                break;
+                            }
+                        }
+                    }
+                    return
+                }
                 loop{
                     let mut line = String::new();
                     if let Ok(len) = reader.read_line(&mut line){
@@ -126,13 +163,13 @@ impl ChildProcess {
                 }
             });
         };
-        Ok(ChildProcess {
+        ChildProcess {
             stdin_sender,
             line_sender,
             child,
             line_receiver,
             aux_chan_host_endpoint,
-        })
+        }
     }
     
     pub fn wait(mut self) {
@@ -60,6 +60,45 @@ impl RustcSpan {
     pub fn length(&self) -> Length {
         self.end() - self.start()
     }
+    
+    /// `start` with the column, which rustc counts in chars, turned into a byte index into
+    /// its line, so it can be used to edit the text.
+    pub fn start_in_bytes(&self) -> Position {
+        let start = self.start();
+        Position {
+            line_index: start.line_index,
+            byte_index: self.text.first().map_or(start.byte_index, | text | char_to_byte_index(&text.text, start.byte_index))
+        }
+    }
+    
+    pub fn end_in_bytes(&self) -> Position {
+        let end = self.end();
+        Position {
+            line_index: end.line_index,
+            byte_index: self.text.last().map_or(end.byte_index, | text | char_to_byte_index(&text.text, end.byte_index))
+        }
+    }
+    
+    /// The text the span covers, if rustc included it. Its columns are 1 based char indices.
+    pub fn highlighted_text(&self) -> Option<String> {
+        if self.text.is_empty() {
+            return None
+        }
+        let lines: Vec<String> = self.text.iter().map( | text | {
+            let start = text.highlight_start.saturating_sub(1);
+            let end = text.highlight_end.saturating_sub(1);
+            text.text.chars().skip(start).take(end.saturating_sub(start)).collect()
+        }).collect();
+        Some(lines.join("\n"))
+    }
+    
+    pub fn is_machine_applicable(&self) -> bool {
+        self.suggested_replacement.is_some() && self.suggestion_applicability.as_deref() == Some("MachineApplicable")
+    }
+}
+
+fn char_to_byte_index(line: &str, char_index: usize) -> usize {
+    line.char_indices().nth(char_index).map_or(line.len(), | (byte_index, _) | byte_index)
 }
 
 #[derive(Clone, DeJson, Debug, Default)]
@@ -87,6 +126,16 @@ pub struct RustcMessage {
     pub rendered: Option<String>
 }
 
+impl RustcMessage {
+    /// The suggestions of this message and its children that rustc considers safe to apply
+    /// without looking at them.
+    pub fn machine_applicable_spans(&self) -> impl Iterator<Item = &RustcSpan> {
+        self.spans.iter()
+            .chain(self.children.iter().flat_map( | child | child.spans.iter()))
+            .filter( | span | span.is_machine_applicable())
+    }
+}
+
 #[derive(Clone, DeJson, Debug, Default)]
 pub struct RustcProfile {
     pub opt_level: String,
@@ -116,3 +165,22 @@ pub struct RustcCompilerMessage {
     pub executable: Option<String>,
     pub fresh: Option<bool>
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn finds_machine_applicable_suggestions_in_bytes() {
+        let json = r#"{"message":"unused variable: `é`","code":null,"level":"warning","spans":[{"file_name":"src/main.rs","byte_start":23,"byte_end":25,"line_start":2,"line_end":2,"column_start":9,"column_end":10,"is_primary":true,"text":[{"text":"    let é = 1;","highlight_start":9,"highlight_end":10}],"label":null,"suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[{"message":"if this is intentional, prefix it with an underscore","code":null,"level":"help","spans":[{"file_name":"src/main.rs","byte_start":23,"byte_end":25,"line_start":2,"line_end":2,"column_start":9,"column_end":10,"is_primary":true,"text":[{"text":"    let é = 1;","highlight_start":9,"highlight_end":10}],"label":null,"suggested_replacement":"_é","suggestion_applicability":"MachineApplicable","expansion":null}],"children":[],"rendered":null},{"message":"or maybe","code":null,"level":"help","spans":[{"file_name":"src/main.rs","byte_start":23,"byte_end":25,"line_start":2,"line_end":2,"column_start":9,"column_end":10,"is_primary":true,"text":[],"label":null,"suggested_replacement":"x","suggestion_applicability":"MaybeIncorrect","expansion":null}],"children":[],"rendered":null}],"rendered":null}"#;
+        let msg: RustcMessage = DeJson::deserialize_json(json).unwrap();
+        let spans: Vec<&RustcSpan> = msg.machine_applicable_spans().collect();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].suggested_replacement.as_deref(), Some("_é"));
+        // 'é' is the ninth char of its line but takes two bytes
+        assert_eq!(spans[0].start_in_bytes(), Position {line_index: 1, byte_index: 8});
+        assert_eq!(spans[0].end_in_bytes(), Position {line_index: 1, byte_index: 10});
+        assert_eq!(spans[0].highlighted_text().as_deref(), Some("é"));
+        assert_eq!(msg.children[1].spans[0].highlighted_text(), None);
+    }
+}
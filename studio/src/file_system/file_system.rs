@@ -2,7 +2,7 @@ use {
     std::collections::{HashMap, hash_map},
     std::path::Path,
     crate::{
-        makepad_code_editor::{Document, decoration::{Decoration, DecorationSet}, Session},
+        makepad_code_editor::{Document, decoration::{Decoration, DecorationSet}, Session, session::RangeReplacement},
         makepad_platform::makepad_live_compiler::LiveFileChange,
         makepad_widgets::*,
         makepad_widgets::file_tree::*,
@@ -12,9 +12,14 @@ use {
             FileError,
             FileResponse,
             FileClientMessage,
+            FileNotification,
             FileNodeData,
             FileTreeData,
+            SearchRequest,
+            SearchResult,
         },
+        app::AppAction,
+        log_list::JumpTo,
     },
 };
 
@@ -26,7 +31,24 @@ pub struct FileSystem {
     pub path_to_file_node_id: HashMap<String, FileNodeId>,
     pub tab_id_to_file_node_id: HashMap<LiveId, FileNodeId>,
     pub tab_id_to_session: HashMap<LiveId, Session>,
-    pub open_documents: HashMap<FileNodeId, OpenDoc>
+    pub open_documents: HashMap<FileNodeId, OpenDoc>,
+    /// Edits for tabs whose file is still loading, made once it has loaded.
+    pub pending_replacements: HashMap<LiveId, Vec<RangeReplacement>>,
+    /// Jumps into tabs whose file is still loading.
+    pub pending_jumps: HashMap<LiveId, JumpTo>,
+    pub search: WorkspaceSearch,
+}
+
+/// The last search through the files of the workspace.
+#[derive(Default)]
+pub struct WorkspaceSearch {
+    pub id: u64,
+    pub results: Vec<SearchResult>,
+    /// How many matches were found, once the search is done.
+    pub match_count: Option<usize>,
+    /// How many lines were too complex for the pattern to search completely.
+    pub skipped_line_count: usize,
+    pub error: Option<String>,
 }
 
 pub enum OpenDoc {
@@ -57,6 +79,7 @@ pub struct FileEdge {
 #[derive(DefaultNone, Debug, Clone)]
 pub enum FileSystemAction {
     TreeLoaded,
+    SearchChanged,
     RecompileNeeded,
     LiveReloadNeeded(LiveFileChange),
     None
@@ -75,6 +98,8 @@ impl FileSystem {
     pub fn remove_tab(&mut self, tab_id: LiveId) {
         self.tab_id_to_file_node_id.remove(&tab_id);
         self.tab_id_to_session.remove(&tab_id);
+        self.pending_replacements.remove(&tab_id);
+        self.pending_jumps.remove(&tab_id);
     }
     
     pub fn path_to_file_node_id(&self, path: &str) -> Option<FileNodeId> {
@@ -116,7 +141,7 @@ impl FileSystem {
                         }
                         FileResponse::OpenFile(result) => {
                             match result {
-                                Ok((_unix_path, data, id)) => {
+                                Ok((unix_path, data, id)) => {
                                     let file_id = FileNodeId(LiveId(id));
                                     let dock = ui.dock(id!(dock));
                                     for (tab_id, file_id) in &self.tab_id_to_file_node_id {
@@ -126,8 +151,22 @@ impl FileSystem {
                                     }
                                     if let Some(OpenDoc::Decorations(dec)) = self.open_documents.get(&file_id) {
                                         let dec = dec.clone();
-                                        self.open_documents.insert(file_id, OpenDoc::Document(Document::new(data.into(), dec)));
+                                        let extension = unix_path.rsplit_once('.').map_or("", |(_, extension)| extension);
+                                        self.open_documents.insert(file_id, OpenDoc::Document(Document::new_for_extension(data.into(), dec, extension)));
                                     }else {panic!()}
+                                    let tab_ids: Vec<LiveId> = self.pending_replacements.keys().cloned().collect();
+                                    for tab_id in tab_ids {
+                                        if self.tab_id_to_file_node_id.get(&tab_id) == Some(&file_id) {
+                                            let ranges = self.pending_replacements.remove(&tab_id).unwrap();
+                                            self.replace_ranges(tab_id, ranges);
+                                        }
+                                    }
+                                    let tab_ids: Vec<LiveId> = self.pending_jumps.keys().cloned().collect();
+                                    for tab_id in tab_ids {
+                                        if self.tab_id_to_file_node_id.get(&tab_id) == Some(&file_id) {
+                                            cx.action(AppAction::JumpTo(self.pending_jumps.remove(&tab_id).unwrap()));
+                                        }
+                                    }
                                     ui.redraw(cx);
                                 }
                                 Err(FileError::CannotOpen(_unix_path)) => {
@@ -175,9 +214,26 @@ impl FileSystem {
                             // to see if we need a recompile
                             
                         }
+                        FileResponse::Search(result) => {
+                            if let Err(FileError::Unknown(err) | FileError::CannotOpen(err)) = result {
+                                self.search.error = Some(err);
+                                cx.action(FileSystemAction::SearchChanged)
+                            }
+                        }
                     },
-                    FileClientMessage::Notification(_notification) => {
-                        //self.editors.handle_collab_notification(cx, &mut state.editor_state, notification)
+                    FileClientMessage::Notification(notification) => match notification {
+                        FileNotification::SearchResults {id, results} => if id == self.search.id {
+                            self.search.results.extend(results);
+                            cx.action(FileSystemAction::SearchChanged)
+                        }
+                        FileNotification::SearchDone {id, match_count, skipped_line_count} => if id == self.search.id {
+                            self.search.match_count = Some(match_count);
+                            self.search.skipped_line_count = skipped_line_count;
+                            cx.action(FileSystemAction::SearchChanged)
+                        }
+                        FileNotification::FileChangedOnDisk => {
+                            //self.editors.handle_collab_notification(cx, &mut state.editor_state, notification)
+                        }
                     }
                 }
             }
@@ -225,6 +281,33 @@ impl FileSystem {
         };
     }
     
+    /// Replaces ranges of the file open in the tab as one undoable edit, and saves it. If the
+    /// file is still loading this happens once it has loaded. Returns whether it happened now.
+    pub fn replace_ranges(&mut self, tab_id: LiveId, ranges: Vec<RangeReplacement>) -> bool {
+        if let Some(session) = self.get_session_mut(tab_id) {
+            if session.replace_ranges(&ranges) > 0 {
+                self.request_save_file(tab_id);
+            }
+            true
+        }
+        else {
+            self.pending_replacements.entry(tab_id).or_default().extend(ranges);
+            false
+        }
+    }
+    
+    /// Starts searching the files of the workspace, which stops the previous search.
+    pub fn search(&mut self, request: SearchRequest) {
+        self.search = WorkspaceSearch {
+            id: self.search.id + 1,
+            ..Default::default()
+        };
+        self.file_client.send_request(FileRequest::Search(SearchRequest {
+            id: self.search.id,
+            ..request
+        }));
+    }
+    
     pub fn clear_decorations(&mut self, file_node_id: &FileNodeId) {
         // ok lets see if we have a document
         // ifnot, we create a new one
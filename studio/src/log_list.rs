@@ -193,6 +193,13 @@ live_design!{
                 binary = <Label> {draw_text: {color: #5}, width: Fit, margin: {right: 4}, padding: 0, draw_text: {wrap: Word}}
                 location = <LinkLabel> {margin: 0, text: ""}
                 body = <Label> {width: Fill, margin: {left: 5}, padding: 0, draw_text: {wrap: Word}}
+                fixes = <View> {
+                    visible: false,
+                    width: Fit,
+                    height: Fit,
+                    fix = <LinkLabel> {margin: {right: 5}, text: "fix"}
+                    fix_all = <LinkLabel> {margin: {right: 5}, text: "fix all"}
+                }
             }
             Bare = <LogItem> {
                 icon = <LogIcon> {},
@@ -214,10 +221,13 @@ pub enum LogListAction {
     None
 }
 
+/// Opens a file and selects the text between `start` and `end`, or puts the cursor at `start`
+/// when they are the same.
 #[derive(Clone, Debug)]
 pub struct JumpTo{
     pub file_name:String, 
-    pub start:Position
+    pub start:Position,
+    pub end:Position
 }
 
 #[derive(Live, LiveHook, Widget)]
@@ -266,6 +276,7 @@ impl LogList{
                             icon = {active_page: (map_level_to_icon(msg.level))},
                             body = {text: (&msg.message)}
                             location = {text: (format!("{}: {}:{}", msg.file_name, msg.start.line_index + 1, msg.start.byte_index + 1))}
+                            fixes = {visible: (!msg.fixes.is_empty())}
                             draw_bg: {is_even: (if is_even {1.0} else {0.0})}
                         });
                         item.draw_all(cx, &mut Scope::empty());
@@ -301,18 +312,30 @@ impl Widget for LogList {
                     if let Some((_build_id, log_item)) = data.build_manager.log.get(item_id as usize) {
                         match log_item {
                             LogItem::Location(msg) => {
+                                let start = Position{
+                                    line_index: msg.start.line_index,
+                                    byte_index: msg.start.byte_index,
+                                };
                                 cx.action(AppAction::JumpTo(JumpTo{
                                     file_name:msg.file_name.clone(), 
-                                    start:Position{
-                                        line_index: msg.start.line_index,
-                                        byte_index: msg.start.byte_index,
-                                    },
+                                    start,
+                                    end:start,
                                 }));
                             }
                             _ => ()
                         }
                     }
                 }
+                if item.link_label(id!(fix)).pressed(&actions) {
+                    if let Some((_build_id, LogItem::Location(msg))) = data.build_manager.log.get(item_id as usize) {
+                        cx.action(AppAction::ApplyFixes(msg.fixes.clone()));
+                    }
+                }
+                if item.link_label(id!(fix_all)).pressed(&actions) {
+                    if let Some((build_id, _)) = data.build_manager.log.get(item_id as usize) {
+                        cx.action(AppAction::ApplyFixes(data.build_manager.build_fixes(*build_id)));
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,138 @@
+use {
+    crate::{
+        makepad_micro_serde::*,
+        makepad_widgets::*,
+        build_manager::child_process::{ChildProcess, ChildStdIO, ChildStdIn},
+        lsp::lsp_protocol::*,
+    },
+    std::{
+        path::PathBuf,
+        sync::mpsc::Sender,
+        thread,
+    },
+};
+
+pub enum LspEvent {
+    Message(LspMessage),
+    Exited,
+}
+
+/// A language server running as a child process, that speaks json-rpc over its stdin and
+/// stdout. Its messages are decoded on a thread and sent to the UI as `LspEvent`s.
+pub struct LspClient {
+    stdin_sender: Sender<ChildStdIn>,
+    line_sender: Sender<ChildStdIO>,
+    next_id: u64,
+}
+
+impl LspClient {
+    pub fn start(cmd: &str, args: &[String], current_dir: PathBuf, event_sender: ToUISender<LspEvent>) -> Result<LspClient, std::io::Error> {
+        let process = ChildProcess::start_raw(cmd, args, current_dir, &[]) ?;
+        let stdin_sender = process.stdin_sender.clone();
+        let line_sender = process.line_sender.clone();
+        thread::spawn(move || {
+            let mut decoder = MessageDecoder::default();
+            while let Ok(line) = process.line_receiver.recv() {
+                match line {
+                    ChildStdIO::StdOutBytes(bytes) => {
+                        decoder.push(&bytes);
+                        while let Some(body) = decoder.next_message() {
+                            match LspMessage::parse(&body) {
+                                Ok(message) => {
+                                    let _ = event_sender.send(LspEvent::Message(message));
+                                }
+                                Err(err) => {
+                                    log!("Cannot parse language server message {:?}", err);
+                                }
+                            }
+                        }
+                    }
+                    // servers write their logging to stderr
+                    ChildStdIO::StdOut(_) | ChildStdIO::StdErr(_) => {}
+                    ChildStdIO::Term => {
+                        let _ = event_sender.send(LspEvent::Exited);
+                        break;
+                    }
+                    ChildStdIO::Kill => {
+                        return process.kill();
+                    }
+                }
+            }
+        });
+        Ok(LspClient {
+            stdin_sender,
+            line_sender,
+            next_id: 0,
+        })
+    }
+
+    /// Sends a request and returns its id, which the response will carry.
+    pub fn request(&mut self, method: &str, params: &impl SerJson) -> u64 {
+        self.next_id += 1;
+        self.send(request_json(self.next_id, method, params));
+        self.next_id
+    }
+
+    pub fn notify(&self, method: &str, params: &impl SerJson) {
+        self.send(notification_json(method, params));
+    }
+
+    pub fn respond(&self, id: &JsonValue, result: &str) {
+        self.send(response_json(id, result));
+    }
+
+    fn send(&self, body: String) {
+        let _ = self.stdin_sender.send(ChildStdIn::Send(encode_message(&body)));
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        let _ = self.line_sender.send(ChildStdIO::Kill);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use {
+        super::*,
+        std::time::Duration,
+    };
+
+    // The fake server writes one canned notification and then echoes whatever it reads,
+    // so every request comes back as a request from the server with the same id.
+    #[test]
+    fn talks_to_a_fake_stdio_server() {
+        let notification = encode_message("{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{\"uri\":\"file:///a.rs\",\"diagnostics\":[]}}");
+        let args = [
+            "-c".to_string(),
+            "printf '%s' \"$1\"; exec cat".to_string(),
+            "fake_server".to_string(),
+            notification,
+        ];
+        let receiver = ToUIReceiver::<LspEvent>::default();
+        let mut client = LspClient::start("sh", &args, std::env::temp_dir(), receiver.sender()).unwrap();
+
+        match receiver.receiver.recv_timeout(Duration::from_secs(10)).unwrap() {
+            LspEvent::Message(LspMessage::Notification {method, params}) => {
+                assert_eq!(method, "textDocument/publishDiagnostics");
+                assert_eq!(params.get("uri").and_then(|uri| uri.as_str()), Some("file:///a.rs"));
+            }
+            _ => panic!("expected the canned notification"),
+        }
+
+        let id = client.request("textDocument/hover", &TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {uri: "file:///a.rs".to_string()},
+            position: LspPosition {line: 1, character: 4},
+        });
+        match receiver.receiver.recv_timeout(Duration::from_secs(10)).unwrap() {
+            LspEvent::Message(LspMessage::Request {id: echoed_id, method, params}) => {
+                assert_eq!(echoed_id.as_u64(), Some(id));
+                assert_eq!(method, "textDocument/hover");
+                let position = params.get("position").and_then(lsp_position_from_json);
+                assert_eq!(position, Some(LspPosition {line: 1, character: 4}));
+            }
+            _ => panic!("expected the echoed request"),
+        }
+    }
+}
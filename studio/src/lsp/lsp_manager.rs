@@ -0,0 +1,523 @@
+use {
+    crate::{
+        app::AppAction,
+        file_system::file_system::{FileSystem, OpenDoc},
+        log_list::JumpTo,
+        lsp::{
+            lsp_client::{LspClient, LspEvent},
+            lsp_protocol::*,
+        },
+        makepad_code_editor::{
+            completion::CompletionItem,
+            decoration::{Decoration, DecorationType},
+            inlays::BlockInlay,
+            text::Position,
+        },
+        makepad_micro_serde::*,
+        makepad_widgets::*,
+        makepad_widgets::file_tree::FileNodeId,
+    },
+    std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+    },
+};
+
+const MAX_HOVER_LINES: usize = 12;
+
+#[derive(Clone, Debug, DefaultNone)]
+pub enum LspAction {
+    ShowCompletions {tab_id: LiveId, items: Vec<CompletionItem>},
+    None
+}
+
+// what each request we are waiting for was made for
+enum PendingRequest {
+    Initialize,
+    Hover {file_id: FileNodeId, line_index: usize},
+    Completion {tab_id: LiveId},
+    Definition,
+}
+
+/// Runs a language server for the project (rust-analyzer unless studio is started with
+/// `--lsp=<command>`, `--lsp=none` turns it off) and keeps it in sync with the open documents.
+/// Its diagnostics become decorations, hovers a block inlay below the cursor line and
+/// completions a popup in the editor.
+#[derive(Default)]
+pub struct LspManager {
+    root_path: PathBuf,
+    client: Option<LspClient>,
+    recv_event: ToUIReceiver<LspEvent>,
+    initialized: bool,
+    pending: HashMap<u64, PendingRequest>,
+    // the version of each document the server was told about
+    versions: HashMap<FileNodeId, i32>,
+    hover_file_id: Option<FileNodeId>,
+}
+
+impl LspManager {
+    pub fn init(&mut self, path: &Path) {
+        self.root_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mut server = "rust-analyzer".to_string();
+        for arg in std::env::args() {
+            if let Some(prefix) = arg.strip_prefix("--lsp=") {
+                server = prefix.to_string();
+                break;
+            }
+        }
+        let mut parts = server.split_whitespace();
+        let Some(cmd) = parts.next() else {return};
+        if cmd == "none" {
+            return
+        }
+        let args: Vec<String> = parts.map(|part| part.to_string()).collect();
+        match LspClient::start(cmd, &args, self.root_path.clone(), self.recv_event.sender()) {
+            Ok(mut client) => {
+                let id = client.request("initialize", &InitializeParams {
+                    process_id: std::process::id(),
+                    root_uri: path_to_uri(&self.root_path),
+                    capabilities: ClientCapabilities {
+                        text_document: TextDocumentClientCapabilities {
+                            hover: HoverClientCapabilities {
+                                content_format: vec!["plaintext".to_string(), "markdown".to_string()],
+                            },
+                            publish_diagnostics: PublishDiagnosticsClientCapabilities {
+                                related_information: false,
+                            },
+                        },
+                    },
+                });
+                self.pending.insert(id, PendingRequest::Initialize);
+                self.client = Some(client);
+            }
+            Err(err) => {
+                log!("Cannot start language server {}: {}", server, err);
+            }
+        }
+    }
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &Event, file_system: &mut FileSystem) {
+        if let Event::Signal = event {
+            while let Ok(event) = self.recv_event.receiver.try_recv() {
+                match event {
+                    LspEvent::Message(message) => self.handle_message(cx, message, file_system),
+                    LspEvent::Exited => {
+                        log!("Language server exited");
+                        self.client = None;
+                        self.initialized = false;
+                        self.pending.clear();
+                        self.versions.clear();
+                    }
+                }
+            }
+            // documents open once the file server sent them, which also comes in as a signal
+            self.sync_documents(file_system);
+        }
+    }
+
+    /// Sends the new text of the document in the tab, the server gets all of it on every change.
+    pub fn did_change(&mut self, tab_id: LiveId, file_system: &mut FileSystem) {
+        let Some(file_id) = file_system.tab_id_to_file_node_id.get(&tab_id).cloned() else {return};
+        // the hover describes the text as it was
+        if self.hover_file_id == Some(file_id) {
+            self.clear_hover(file_system);
+        }
+        let (Some(client), Some(version)) = (&self.client, self.versions.get_mut(&file_id)) else {return};
+        let Some(OpenDoc::Document(document)) = file_system.open_documents.get(&file_id) else {return};
+        *version += 1;
+        client.notify("textDocument/didChange", &DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: path_to_uri(&self.root_path.join(file_system.file_node_path(file_id))),
+                version: *version,
+            },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                text: document.as_text().to_string(),
+            }],
+        });
+    }
+
+    /// Shows what the server knows about the code at the cursor below its line, or hides it
+    /// again when it is already shown for this file.
+    pub fn request_hover(&mut self, tab_id: LiveId, file_system: &mut FileSystem) {
+        let hover_file_id = self.hover_file_id;
+        self.clear_hover(file_system);
+        let Some((file_id, line_index, params)) = self.position_params(tab_id, file_system) else {return};
+        if hover_file_id == Some(file_id) {
+            return
+        }
+        if let Some(client) = &mut self.client {
+            let id = client.request("textDocument/hover", &params);
+            self.pending.insert(id, PendingRequest::Hover {file_id, line_index});
+        }
+    }
+
+    pub fn request_completion(&mut self, tab_id: LiveId, file_system: &mut FileSystem) {
+        let Some((_, _, params)) = self.position_params(tab_id, file_system) else {return};
+        if let Some(client) = &mut self.client {
+            let id = client.request("textDocument/completion", &params);
+            self.pending.insert(id, PendingRequest::Completion {tab_id});
+        }
+    }
+
+    pub fn request_definition(&mut self, tab_id: LiveId, file_system: &mut FileSystem) {
+        let Some((_, _, params)) = self.position_params(tab_id, file_system) else {return};
+        if let Some(client) = &mut self.client {
+            let id = client.request("textDocument/definition", &params);
+            self.pending.insert(id, PendingRequest::Definition);
+        }
+    }
+
+    fn sync_documents(&mut self, file_system: &FileSystem) {
+        let Some(client) = &self.client else {return};
+        if !self.initialized {
+            return
+        }
+        for (file_id, open_doc) in &file_system.open_documents {
+            let OpenDoc::Document(document) = open_doc else {continue};
+            if self.versions.contains_key(file_id) {
+                continue
+            }
+            let path = file_system.file_node_path(*file_id);
+            client.notify("textDocument/didOpen", &DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: path_to_uri(&self.root_path.join(&path)),
+                    language_id: language_id(&path).to_string(),
+                    version: 1,
+                    text: document.as_text().to_string(),
+                },
+            });
+            self.versions.insert(*file_id, 1);
+        }
+    }
+
+    // the document and cursor position of the tab, for requests about the code at the cursor
+    fn position_params(&self, tab_id: LiveId, file_system: &mut FileSystem) -> Option<(FileNodeId, usize, TextDocumentPositionParams)> {
+        let file_id = *file_system.tab_id_to_file_node_id.get(&tab_id) ?;
+        if !self.versions.contains_key(&file_id) {
+            return None
+        }
+        let uri = path_to_uri(&self.root_path.join(file_system.file_node_path(file_id)));
+        let session = file_system.get_session_mut(tab_id) ?;
+        let position = session.selections()[session.last_added_selection_index() ?].cursor.position;
+        let text = session.document().as_text();
+        Some((file_id, position.line_index, TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {uri},
+            position: to_lsp_position(text.as_lines(), position),
+        }))
+    }
+
+    fn clear_hover(&mut self, file_system: &mut FileSystem) {
+        if let Some(file_id) = self.hover_file_id.take() {
+            if let Some(OpenDoc::Document(document)) = file_system.open_documents.get_mut(&file_id) {
+                document.set_block_inlays(Vec::new());
+            }
+        }
+    }
+
+    fn handle_message(&mut self, cx: &mut Cx, message: LspMessage, file_system: &mut FileSystem) {
+        match message {
+            LspMessage::Response {id, result, error} => {
+                let Some(pending) = self.pending.remove(&id) else {return};
+                if let Some(error) = error {
+                    log!("Language server error: {}", error);
+                    return
+                }
+                match pending {
+                    PendingRequest::Initialize => {
+                        self.initialized = true;
+                        if let Some(client) = &self.client {
+                            client.notify("initialized", &HashMap::<String, String>::new());
+                        }
+                        self.sync_documents(file_system);
+                    }
+                    PendingRequest::Hover {file_id, line_index} => {
+                        let text = hover_text(&result);
+                        if text.is_empty() {
+                            return
+                        }
+                        self.clear_hover(file_system);
+                        if let Some(OpenDoc::Document(document)) = file_system.open_documents.get_mut(&file_id) {
+                            document.set_block_inlays(vec![(line_index + 1, BlockInlay::Text(text))]);
+                            self.hover_file_id = Some(file_id);
+                            cx.action(AppAction::RedrawFile(file_id));
+                        }
+                    }
+                    PendingRequest::Completion {tab_id} => {
+                        let items = completion_items(&result);
+                        if !items.is_empty() {
+                            cx.action(LspAction::ShowCompletions {tab_id, items});
+                        }
+                    }
+                    PendingRequest::Definition => {
+                        if let Some(jump_to) = self.definition_jump_to(&result, file_system) {
+                            cx.action(AppAction::JumpTo(jump_to));
+                        }
+                    }
+                }
+            }
+            LspMessage::Notification {method, params} => {
+                if method == "textDocument/publishDiagnostics" {
+                    self.apply_diagnostics(cx, &params, file_system);
+                }
+            }
+            LspMessage::Request {id, method, params} => {
+                let Some(client) = &self.client else {return};
+                // we have no settings to give, and accept whatever else the server asks for
+                if method == "workspace/configuration" {
+                    let count = params.get("items").and_then(|items| items.as_array()).map_or(0, |items| items.len());
+                    client.respond(&id, &format!("[{}]", vec!["null"; count].join(",")));
+                }
+                else {
+                    client.respond(&id, "null");
+                }
+            }
+        }
+    }
+
+    fn apply_diagnostics(&mut self, cx: &mut Cx, params: &JsonValue, file_system: &mut FileSystem) {
+        let Some(path) = params.get("uri").and_then(|uri| uri.as_str()).and_then(|uri| self.uri_to_path(uri)) else {return};
+        let Some(file_id) = file_system.path_to_file_node_id(&path) else {return};
+        let diagnostics = params.get("diagnostics").and_then(|diagnostics| diagnostics.as_array()).unwrap_or(&[]);
+        // each notification has all diagnostics of the file, so they replace what was there
+        file_system.clear_decorations(&file_id);
+        for (index, diagnostic) in diagnostics.iter().enumerate() {
+            let ty = match diagnostic.get("severity").and_then(|severity| severity.as_u64()) {
+                Some(1) | None => DecorationType::Error,
+                Some(2) => DecorationType::Warning,
+                // information and hints
+                _ => continue
+            };
+            let Some((start, end)) = diagnostic.get("range").and_then(lsp_range_from_json) else {continue};
+            let start = from_lsp_position_in_file(file_system, file_id, start);
+            let end = from_lsp_position_in_file(file_system, file_id, end);
+            file_system.add_decoration(file_id, Decoration::new(index, start, end, ty));
+        }
+        cx.action(AppAction::RedrawFile(file_id));
+    }
+
+    // a definition comes as a Location, a list of them or a list of LocationLinks
+    fn definition_jump_to(&self, result: &JsonValue, file_system: &FileSystem) -> Option<JumpTo> {
+        let location = match result.as_array() {
+            Some(locations) => locations.first() ?,
+            None => result,
+        };
+        let uri = location.get("uri").or(location.get("targetUri")) ?.as_str() ?;
+        let range = location.get("range").or(location.get("targetSelectionRange")) ?;
+        let (start, _) = lsp_range_from_json(range) ?;
+        let file_name = self.uri_to_path(uri) ?;
+        let file_id = file_system.path_to_file_node_id(&file_name) ?;
+        let start = from_lsp_position_in_file(file_system, file_id, start);
+        Some(JumpTo {
+            start,
+            end: start,
+            file_name,
+        })
+    }
+
+    // the path relative to the root that the file system knows the file by
+    fn uri_to_path(&self, uri: &str) -> Option<String> {
+        let path = uri_to_path(uri) ?;
+        let root = self.root_path.to_string_lossy().replace('\\', "/");
+        let relative = path.strip_prefix(root.trim_end_matches('/')) ?;
+        Some(relative.trim_start_matches('/').to_string())
+    }
+}
+
+// Without the text of the file the utf-16 offset can't be mapped, so it is taken as a byte
+// index, which is only off on lines with non ascii chars.
+fn from_lsp_position_in_file(file_system: &FileSystem, file_id: FileNodeId, position: LspPosition) -> Position {
+    match file_system.open_documents.get(&file_id) {
+        Some(OpenDoc::Document(document)) => from_lsp_position(document.as_text().as_lines(), position),
+        _ => Position {
+            line_index: position.line as usize,
+            byte_index: position.character as usize,
+        }
+    }
+}
+
+fn language_id(path: &str) -> &str {
+    match path.rsplit_once('.').map_or("", |(_, extension)| extension) {
+        "rs" => "rust",
+        "md" => "markdown",
+        extension => extension,
+    }
+}
+
+fn hover_text(result: &JsonValue) -> String {
+    fn markup_text(value: &JsonValue) -> String {
+        match value {
+            JsonValue::String(text) => text.clone(),
+            JsonValue::Array(values) => values.iter().map(markup_text).collect::<Vec<_>>().join("\n"),
+            // MarkupContent and MarkedString both keep their text in value
+            _ => value.get("value").and_then(|value| value.as_str()).unwrap_or("").to_string(),
+        }
+    }
+    let Some(contents) = result.get("contents") else {return String::new()};
+    // markdown code fences only get in the way as plain text
+    let text = markup_text(contents);
+    let lines: Vec<_> = text.lines().filter(|line| !line.starts_with("```")).take(MAX_HOVER_LINES).collect();
+    lines.join("\n").trim().to_string()
+}
+
+fn completion_items(result: &JsonValue) -> Vec<CompletionItem> {
+    let items = result.as_array().or_else(|| result.get("items").and_then(|items| items.as_array())).unwrap_or(&[]);
+    items.iter().filter_map(|item| {
+        let label = item.get("label") ?.as_str() ?.to_string();
+        let insert_text = item.get("textEdit").and_then(|edit| edit.get("newText"))
+            .or(item.get("insertText"))
+            .and_then(|text| text.as_str())
+            .unwrap_or(&label)
+            .to_string();
+        Some(CompletionItem {
+            detail: item.get("detail").and_then(|detail| detail.as_str()).unwrap_or("").to_string(),
+            label,
+            insert_text,
+        })
+    }).collect()
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+fn uri_to_path(uri: &str) -> Option<String> {
+    let encoded = uri.strip_prefix("file://") ?;
+    let mut bytes = Vec::new();
+    let mut iter = encoded.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex: String = iter.by_ref().take(2).map(|byte| byte as char).collect();
+            bytes.push(u8::from_str_radix(&hex, 16).ok() ?);
+        }
+        else {
+            bytes.push(byte);
+        }
+    }
+    let path = String::from_utf8_lossy(&bytes).to_string();
+    // windows paths come as /C:/..
+    if path.len() > 2 && path.as_bytes()[2] == b':' {
+        return Some(path[1..].to_string())
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(text: &str) -> JsonValue {
+        JsonValue::deserialize_json(text).unwrap()
+    }
+
+    fn position(line_index: usize, byte_index: usize) -> Position {
+        Position {line_index, byte_index}
+    }
+
+    fn manager_with_file() -> (LspManager, FileSystem, FileNodeId) {
+        let manager = LspManager {root_path: PathBuf::from("/proj"), ..LspManager::default()};
+        let mut file_system = FileSystem::default();
+        let file_id = FileNodeId(live_id!(main));
+        file_system.path_to_file_node_id.insert("src/main.rs".to_string(), file_id);
+        (manager, file_system, file_id)
+    }
+
+    #[test]
+    fn hover_text_of_each_contents_shape() {
+        assert_eq!(hover_text(&json(r#"{"contents":{"kind":"markdown","value":"```rust\nfn main()\n```\nStarts here"}}"#)), "fn main()\nStarts here");
+        assert_eq!(hover_text(&json(r#"{"contents":{"language":"rust","value":"let x: u32"}}"#)), "let x: u32");
+        assert_eq!(hover_text(&json(r#"{"contents":"plain"}"#)), "plain");
+        assert_eq!(hover_text(&json(r#"{"contents":["a",{"language":"rust","value":"b"}]}"#)), "a\nb");
+        assert_eq!(hover_text(&json("null")), "");
+        let long = (0..20).map(|i| i.to_string()).collect::<Vec<_>>().join("\\n");
+        assert_eq!(hover_text(&json(&format!(r#"{{"contents":"{}"}}"#, long))).lines().count(), MAX_HOVER_LINES);
+    }
+
+    #[test]
+    fn completion_items_prefer_the_text_edit() {
+        let items = completion_items(&json(r#"{"isIncomplete":false,"items":[
+            {"label":"push","detail":"fn(&mut self)","insertText":"push_ins","textEdit":{"newText":"push()"}},
+            {"label":"pop","insertText":"pop()"},
+            {"label":"len"},
+            {"detail":"no label"}
+        ]}"#));
+        let items: Vec<_> = items.iter().map(|item| (item.label.as_str(), item.insert_text.as_str(), item.detail.as_str())).collect();
+        assert_eq!(items, vec![("push", "push()", "fn(&mut self)"), ("pop", "pop()", ""), ("len", "len", "")]);
+        assert_eq!(completion_items(&json(r#"[{"label":"a"}]"#)).len(), 1);
+        assert!(completion_items(&json("null")).is_empty());
+    }
+
+    #[test]
+    fn definition_from_location_list_or_link() {
+        let (manager, file_system, _) = manager_with_file();
+        let range = r#"{"start":{"line":3,"character":4},"end":{"line":3,"character":8}}"#;
+        let location = format!(r#"{{"uri":"file:///proj/src/main.rs","range":{}}}"#, range);
+        let link = format!(r#"{{"targetUri":"file:///proj/src/main.rs","targetRange":{{"start":{{"line":0,"character":0}},"end":{{"line":9,"character":0}}}},"targetSelectionRange":{}}}"#, range);
+        for result in [location.clone(), format!("[{}]", location), format!("[{}]", link)] {
+            let jump_to = manager.definition_jump_to(&json(&result), &file_system).unwrap();
+            assert_eq!(jump_to.file_name, "src/main.rs");
+            assert_eq!(jump_to.start, position(3, 4));
+            assert_eq!(jump_to.end, position(3, 4));
+        }
+        assert!(manager.definition_jump_to(&json("[]"), &file_system).is_none());
+        assert!(manager.definition_jump_to(&json(r#"{"uri":"file:///elsewhere/a.rs","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":0}}}"#), &file_system).is_none());
+    }
+
+    #[test]
+    fn uris_round_trip_paths() {
+        let uri = path_to_uri(Path::new("/home/a b/ü%.rs"));
+        assert_eq!(uri, "file:///home/a%20b/%C3%BC%25.rs");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some("/home/a b/ü%.rs"));
+        let uri = path_to_uri(Path::new("C:\\My Code\\main.rs"));
+        assert_eq!(uri, "file:///C:/My%20Code/main.rs");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some("C:/My Code/main.rs"));
+        assert_eq!(uri_to_path("file:///c%3A/x.rs").as_deref(), Some("c:/x.rs"));
+        assert_eq!(uri_to_path("http://x/y"), None);
+        assert_eq!(uri_to_path("file:///bad%zz"), None);
+        let (manager, _, _) = manager_with_file();
+        assert_eq!(manager.uri_to_path("file:///proj/src/main.rs").as_deref(), Some("src/main.rs"));
+        assert_eq!(manager.uri_to_path("file:///other/main.rs"), None);
+    }
+
+    #[test]
+    fn diagnostics_become_error_and_warning_decorations() {
+        let mut cx = Cx::new(Box::new(|_, _| {}));
+        let (mut manager, mut file_system, file_id) = manager_with_file();
+        let diagnostic = |severity: &str, line: usize| format!(
+            r#"{{{}"range":{{"start":{{"line":{},"character":1}},"end":{{"line":{},"character":5}}}},"message":"m"}}"#,
+            severity, line, line
+        );
+        let params = json(&format!(r#"{{"uri":"file:///proj/src/main.rs","diagnostics":[{},{},{},{},{}]}}"#,
+            diagnostic(r#""severity":1,"#, 0),
+            diagnostic(r#""severity":2,"#, 1),
+            diagnostic(r#""severity":3,"#, 2),
+            diagnostic(r#""severity":4,"#, 3),
+            diagnostic("", 4),
+        ));
+        manager.apply_diagnostics(&mut cx, &params, &mut file_system);
+        let decorations = |file_system: &FileSystem| match file_system.open_documents.get(&file_id) {
+            Some(OpenDoc::Decorations(decorations)) => decorations.as_decorations().iter().map(|decoration| {
+                (decoration.ty, decoration.start(), decoration.end())
+            }).collect::<Vec<_>>(),
+            _ => panic!("no decorations"),
+        };
+        assert_eq!(decorations(&file_system), vec![
+            (DecorationType::Error, position(0, 1), position(0, 5)),
+            (DecorationType::Warning, position(1, 1), position(1, 5)),
+            (DecorationType::Error, position(4, 1), position(4, 5)),
+        ]);
+        // the next notification replaces them
+        manager.apply_diagnostics(&mut cx, &json(r#"{"uri":"file:///proj/src/main.rs","diagnostics":[]}"#), &mut file_system);
+        assert!(decorations(&file_system).is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{
+    makepad_micro_serde::*,
+    makepad_code_editor::text::Position,
+};
+
+// The parts of the language server protocol studio speaks. Outgoing params are SerJson
+// structs, incoming messages are read as JsonValue because their shape depends on the method.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, SerJson)]
+pub struct LspPosition {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct TextDocumentIdentifier {
+    pub uri: String,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct VersionedTextDocumentIdentifier {
+    pub uri: String,
+    pub version: i32,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct TextDocumentItem {
+    pub uri: String,
+    #[rename("languageId")]
+    pub language_id: String,
+    pub version: i32,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct TextDocumentContentChangeEvent {
+    pub text: String,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct DidOpenTextDocumentParams {
+    #[rename("textDocument")]
+    pub text_document: TextDocumentItem,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct DidChangeTextDocumentParams {
+    #[rename("textDocument")]
+    pub text_document: VersionedTextDocumentIdentifier,
+    #[rename("contentChanges")]
+    pub content_changes: Vec<TextDocumentContentChangeEvent>,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct TextDocumentPositionParams {
+    #[rename("textDocument")]
+    pub text_document: TextDocumentIdentifier,
+    pub position: LspPosition,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct InitializeParams {
+    #[rename("processId")]
+    pub process_id: u32,
+    #[rename("rootUri")]
+    pub root_uri: String,
+    pub capabilities: ClientCapabilities,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct ClientCapabilities {
+    #[rename("textDocument")]
+    pub text_document: TextDocumentClientCapabilities,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct TextDocumentClientCapabilities {
+    pub hover: HoverClientCapabilities,
+    #[rename("publishDiagnostics")]
+    pub publish_diagnostics: PublishDiagnosticsClientCapabilities,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct HoverClientCapabilities {
+    #[rename("contentFormat")]
+    pub content_format: Vec<String>,
+}
+
+#[derive(Clone, Debug, SerJson)]
+pub struct PublishDiagnosticsClientCapabilities {
+    #[rename("relatedInformation")]
+    pub related_information: bool,
+}
+
+/// A message from the server: the response to one of our requests, a notification, or a
+/// request of its own, which has to be responded to.
+#[derive(Clone, Debug)]
+pub enum LspMessage {
+    Response {id: u64, result: JsonValue, error: Option<String>},
+    Notification {method: String, params: JsonValue},
+    Request {id: JsonValue, method: String, params: JsonValue},
+}
+
+impl LspMessage {
+    pub fn parse(body: &str) -> Result<LspMessage, DeJsonErr> {
+        let value: JsonValue = DeJson::deserialize_json(body) ?;
+        let params = value.get("params").cloned().unwrap_or(JsonValue::Null);
+        match (value.get("id"), value.get("method").and_then(|method| method.as_str())) {
+            (Some(id), Some(method)) => Ok(LspMessage::Request {
+                id: id.clone(),
+                method: method.to_string(),
+                params,
+            }),
+            (None, Some(method)) => Ok(LspMessage::Notification {
+                method: method.to_string(),
+                params,
+            }),
+            (Some(id), None) => Ok(LspMessage::Response {
+                // we only send numeric ids
+                id: id.as_u64().unwrap_or(0),
+                result: value.get("result").cloned().unwrap_or(JsonValue::Null),
+                error: value.get("error").map(|error| {
+                    error.get("message").and_then(|message| message.as_str()).unwrap_or("unknown error").to_string()
+                }),
+            }),
+            (None, None) => Err(DeJsonErr {
+                msg: "message without id or method".to_string(),
+                line: 0,
+                col: 0,
+            }),
+        }
+    }
+}
+
+pub fn request_json(id: u64, method: &str, params: &impl SerJson) -> String {
+    format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"method\":{},\"params\":{}}}", id, method.to_string().serialize_json(), params.serialize_json())
+}
+
+pub fn notification_json(method: &str, params: &impl SerJson) -> String {
+    format!("{{\"jsonrpc\":\"2.0\",\"method\":{},\"params\":{}}}", method.to_string().serialize_json(), params.serialize_json())
+}
+
+/// Responds to a request of the server with `result`, which has to be json already.
+pub fn response_json(id: &JsonValue, result: &str) -> String {
+    let id = match id {
+        JsonValue::String(id) => id.serialize_json(),
+        JsonValue::U64(id) => id.to_string(),
+        JsonValue::I64(id) => id.to_string(),
+        _ => "null".to_string(),
+    };
+    format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"result\":{}}}", id, result)
+}
+
+/// Frames a message body with the header that tells the other side how long it is.
+pub fn encode_message(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+/// Collects the bytes the server writes and splits them into message bodies.
+#[derive(Default)]
+pub struct MessageDecoder {
+    buffer: Vec<u8>,
+}
+
+impl MessageDecoder {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Returns the next complete message body, if all of it has arrived.
+    pub fn next_message(&mut self) -> Option<String> {
+        let header_end = self.buffer.windows(4).position(|window| window == b"\r\n\r\n") ?;
+        let header = String::from_utf8_lossy(&self.buffer[..header_end]);
+        let content_length = header.split("\r\n").find_map(|line| {
+            let (name, value) = line.split_once(':') ?;
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                value.trim().parse::<usize>().ok()
+            }
+            else {
+                None
+            }
+        });
+        let body_start = header_end + 4;
+        let Some(content_length) = content_length else {
+            // a header we can't read, skip it so we don't get stuck on it
+            self.buffer.drain(..body_start);
+            return self.next_message()
+        };
+        if self.buffer.len() < body_start + content_length {
+            return None
+        }
+        let body = String::from_utf8_lossy(&self.buffer[body_start..body_start + content_length]).to_string();
+        self.buffer.drain(..body_start + content_length);
+        Some(body)
+    }
+}
+
+/// The protocol counts columns in utf-16 code units, the code editor in bytes.
+pub fn byte_to_utf16(line: &str, byte_index: usize) -> u32 {
+    line[..byte_index.min(line.len())].chars().map(|char| char.len_utf16() as u32).sum()
+}
+
+/// Maps a utf-16 offset back to a byte index. Offsets past the end of the line end up at its
+/// end, offsets in the middle of a surrogate pair at the start of that char.
+pub fn utf16_to_byte(line: &str, utf16: u32) -> usize {
+    let mut count = 0;
+    for (byte_index, char) in line.char_indices() {
+        count += char.len_utf16() as u32;
+        if count > utf16 {
+            return byte_index
+        }
+    }
+    line.len()
+}
+
+pub fn to_lsp_position(lines: &[String], position: Position) -> LspPosition {
+    LspPosition {
+        line: position.line_index as u32,
+        character: lines.get(position.line_index).map_or(0, |line| byte_to_utf16(line, position.byte_index)),
+    }
+}
+
+pub fn from_lsp_position(lines: &[String], position: LspPosition) -> Position {
+    let line_index = (position.line as usize).min(lines.len().saturating_sub(1));
+    Position {
+        line_index,
+        byte_index: lines.get(line_index).map_or(0, |line| utf16_to_byte(line, position.character)),
+    }
+}
+
+pub fn lsp_position_from_json(value: &JsonValue) -> Option<LspPosition> {
+    Some(LspPosition {
+        line: value.get("line") ?.as_u64() ? as u32,
+        character: value.get("character") ?.as_u64() ? as u32,
+    })
+}
+
+/// The start and end of a json `Range`.
+pub fn lsp_range_from_json(value: &JsonValue) -> Option<(LspPosition, LspPosition)> {
+    Some((lsp_position_from_json(value.get("start") ?) ?, lsp_position_from_json(value.get("end") ?) ?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_messages_split_over_chunks() {
+        let mut decoder = MessageDecoder::default();
+        let framed = format!("{}{}", encode_message("{\"id\":1}"), encode_message("{\"id\":\"é\"}"));
+        let (first, rest) = framed.as_bytes().split_at(7);
+        decoder.push(first);
+        assert_eq!(decoder.next_message(), None);
+        decoder.push(rest);
+        assert_eq!(decoder.next_message().as_deref(), Some("{\"id\":1}"));
+        assert_eq!(decoder.next_message().as_deref(), Some("{\"id\":\"é\"}"));
+        assert_eq!(decoder.next_message(), None);
+    }
+
+    #[test]
+    fn maps_utf16_offsets_to_bytes() {
+        // 'é' is two bytes and one utf-16 unit, '😀' is four bytes and two units
+        let line = "aé😀b";
+        assert_eq!(byte_to_utf16(line, 0), 0);
+        assert_eq!(byte_to_utf16(line, 3), 2);
+        assert_eq!(byte_to_utf16(line, 7), 4);
+        assert_eq!(byte_to_utf16(line, line.len()), 5);
+        assert_eq!(utf16_to_byte(line, 2), 3);
+        assert_eq!(utf16_to_byte(line, 3), 3);
+        assert_eq!(utf16_to_byte(line, 4), 7);
+        assert_eq!(utf16_to_byte(line, 99), line.len());
+
+        let lines = vec!["fn main() {".to_string(), "    let 😀 = 1;".to_string()];
+        let position = Position {line_index: 1, byte_index: 13};
+        assert_eq!(to_lsp_position(&lines, position), LspPosition {line: 1, character: 11});
+        assert_eq!(from_lsp_position(&lines, LspPosition {line: 1, character: 11}), position);
+    }
+
+    #[test]
+    fn parses_responses_notifications_and_requests() {
+        match LspMessage::parse("{\"jsonrpc\":\"2.0\",\"id\":3,\"result\":{\"contents\":\"x\"}}").unwrap() {
+            LspMessage::Response {id, result, error} => {
+                assert_eq!(id, 3);
+                assert_eq!(result.get("contents").and_then(|contents| contents.as_str()), Some("x"));
+                assert!(error.is_none());
+            }
+            message => panic!("{:?}", message),
+        }
+        match LspMessage::parse("{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{\"diagnostics\":[]}}").unwrap() {
+            LspMessage::Notification {method, params} => {
+                assert_eq!(method, "textDocument/publishDiagnostics");
+                assert_eq!(params.get("diagnostics").and_then(|diagnostics| diagnostics.as_array()).map(|diagnostics| diagnostics.len()), Some(0));
+            }
+            message => panic!("{:?}", message),
+        }
+        match LspMessage::parse("{\"jsonrpc\":\"2.0\",\"id\":\"a\",\"method\":\"window/workDoneProgress/create\"}").unwrap() {
+            LspMessage::Request {id, ..} => assert_eq!(response_json(&id, "null"), "{\"jsonrpc\":\"2.0\",\"id\":\"a\",\"result\":null}"),
+            message => panic!("{:?}", message),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod lsp_client;
+pub mod lsp_manager;
+pub mod lsp_protocol;
@@ -0,0 +1,183 @@
+use {
+    crate::{
+        app::{AppAction, AppData},
+        file_system::file_system::WorkspaceSearch,
+        log_list::JumpTo,
+        makepad_widgets::*,
+        makepad_code_editor::text::{Position},
+        makepad_file_protocol::{SearchRequest, MAX_SEARCH_RESULTS},
+        makepad_widgets::portal_list::PortalList,
+    },
+    std::collections::HashSet,
+};
+
+live_design!{
+    import makepad_draw::shader::std::*;
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    SearchItem = <RectView> {
+        height: Fit,
+        width: Fill
+        padding: {top: 6, bottom: 6}
+
+        draw_bg: {
+            instance is_even: 0.0
+            fn pixel(self) -> vec4 {
+                return mix(
+                    THEME_COLOR_BG_EDITOR,
+                    THEME_COLOR_BG_ODD,
+                    self.is_even
+                );
+            }
+        }
+    }
+
+    SearchList = {{SearchList}}{
+        height: Fill,
+        width: Fill
+        flow: Down
+        <View> {
+            height: Fit,
+            width: Fill
+            flow: Down
+            padding: 10
+            spacing: 5
+            query = <TextInput> {
+                width: Fill,
+                empty_message: "Search"
+            }
+            globs = <TextInput> {
+                width: Fill,
+                empty_message: "Files, like *.rs, !examples/**"
+            }
+            <View> {
+                height: Fit,
+                width: Fill
+                flow: Right
+                spacing: 10
+                regex = <CheckBox> {text: "Regex"}
+                match_case = <CheckBox> {text: "Match case"}
+            }
+            status = <Label> {draw_text: {color: #8}, text: ""}
+        }
+        list = <PortalList> {
+            drag_scrolling: false
+            height: Fill,
+            width: Fill
+            flow: Down
+            Result = <SearchItem> {
+                location = <LinkLabel> {margin: 0, text: ""}
+                body = <Label> {width: Fill, margin: {left: 5}, padding: 0, draw_text: {wrap: Ellipsis}}
+            }
+            Empty = <SearchItem> {
+                cursor: Default
+                height: 24,
+                width: Fill
+            }
+        }
+    }
+}
+
+/// Searches the files of the workspace and lists the matches, clicking one opens the file
+/// with the match selected.
+#[derive(Live, LiveHook, Widget)]
+pub struct SearchList{
+    #[deref] view:View
+}
+
+impl SearchList{
+    fn draw_results(&mut self, cx: &mut Cx2d, list:&mut PortalList, search:&WorkspaceSearch){
+        list.set_item_range(cx, 0, search.results.len());
+
+        while let Some(item_id) = list.next_visible_item(cx) {
+            let is_even = item_id & 1 == 0;
+            if let Some(result) = search.results.get(item_id as usize) {
+                let item = list.item(cx, item_id, live_id!(Result)).unwrap().as_view();
+                item.apply_over(cx, live!{
+                    location = {text: (format!("{}:{}", result.path, result.line_index + 1))}
+                    body = {text: (result.line.trim())}
+                    draw_bg: {is_even: (if is_even {1.0} else {0.0})}
+                });
+                item.draw_all(cx, &mut Scope::empty());
+                continue
+            }
+            let item = list.item(cx, item_id, live_id!(Empty)).unwrap().as_view();
+            item.apply_over(cx, live!{draw_bg: {is_even: (if is_even {1.0} else {0.0})}});
+            item.draw_all(cx, &mut Scope::empty());
+        }
+    }
+
+    fn status(search:&WorkspaceSearch) -> String {
+        if let Some(error) = &search.error {
+            return error.clone()
+        }
+        let file_count = search.results.iter().map(|result| &result.path).collect::<HashSet<_>>().len();
+        let mut status = match search.match_count {
+            None if search.id == 0 => String::new(),
+            None => format!("Searching, {} matches in {} files", search.results.len(), file_count),
+            Some(match_count) if match_count >= MAX_SEARCH_RESULTS => format!("The first {} matches, in {} files", match_count, file_count),
+            Some(match_count) => format!("{} matches in {} files", match_count, file_count),
+        };
+        if search.skipped_line_count > 0 {
+            status.push_str(&format!(", {} lines too complex to search", search.skipped_line_count));
+        }
+        status
+    }
+}
+
+impl WidgetMatchEvent for SearchList{
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope){
+        let file_system = &mut scope.data.get_mut::<AppData>().file_system;
+        let query = self.view.text_input(id!(query));
+        let globs = self.view.text_input(id!(globs));
+        let regex = self.view.check_box(id!(regex));
+        let match_case = self.view.check_box(id!(match_case));
+
+        if query.returned(actions).is_some() || globs.returned(actions).is_some()
+            || regex.changed(actions).is_some() || match_case.changed(actions).is_some() {
+            let pattern = query.text();
+            if !pattern.is_empty() {
+                file_system.search(SearchRequest {
+                    id: 0,
+                    pattern,
+                    is_regex: regex.selected(cx),
+                    match_case: match_case.selected(cx),
+                    globs: globs.text().split(',').map(|glob| glob.trim().to_string()).filter(|glob| !glob.is_empty()).collect(),
+                });
+                self.view.redraw(cx);
+            }
+        }
+
+        let list = self.view.portal_list(id!(list));
+        for (item_id, item) in list.items_with_actions(actions) {
+            if item.link_label(id!(location)).pressed(actions) {
+                if let Some(result) = file_system.search.results.get(item_id as usize) {
+                    cx.action(AppAction::JumpTo(JumpTo{
+                        file_name: result.path.clone(),
+                        start: Position{line_index: result.line_index, byte_index: result.start},
+                        end: Position{line_index: result.line_index, byte_index: result.end},
+                    }));
+                }
+            }
+        }
+    }
+}
+
+impl Widget for SearchList {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope:&mut Scope, walk:Walk)->DrawStep{
+        let search = &scope.data.get::<AppData>().file_system.search;
+        self.view.label(id!(status)).set_text(&Self::status(search));
+        while let Some(step) = self.view.draw_walk(cx, scope, walk).step(){
+            if let Some(mut list) = step.as_portal_list().borrow_mut(){
+                self.draw_results(cx, &mut *list, &scope.data.get::<AppData>().file_system.search)
+            }
+        }
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope){
+        self.widget_match_event(cx, event, scope);
+        self.view.handle_event(cx, event, scope);
+    }
+}
@@ -0,0 +1,467 @@
+//! Drives a Makepad app over the stdin protocol that Studio uses for its run view,
+//! without needing Studio or a GPU.
+//!
+//! The app is spawned with `--stdin-loop --headless`. In that mode it renders with the
+//! software renderer and writes every presented frame into a directory as a png,
+//! announcing it with the usual `StdinToHost::DrawCompleteAndFlip`. The driver sends
+//! `HostToStdin` messages, ticks the app until a frame arrives and compares frames
+//! against golden images.
+//!
+//! Scripts are json lines. Every line is either a `HostToStdin` message, so a recording
+//! of what Studio sends can be replayed as is, or a `ScriptStep`. Empty lines and lines
+//! starting with `#` are skipped.
+
+use {
+    std::{
+        ffi::OsStr,
+        io::{BufRead, BufReader, Write},
+        path::{Path, PathBuf},
+        process::{Child, ChildStdin, Command, Stdio},
+        sync::mpsc::{self, Receiver, RecvTimeoutError},
+        time::{Duration, Instant},
+    },
+    makepad_platform::{
+        makepad_image_formats::{png, ImageBuffer},
+        makepad_micro_serde::*,
+        KeyCode, KeyEvent, KeyModifiers, MouseCursor, TextInputEvent,
+        os::cx_stdin::{HostToStdin, PresentableDraw, StdinMouseDown, StdinMouseMove, StdinMouseUp, StdinScroll, StdinToHost},
+        visual_test::{self, GoldenTolerance},
+    },
+};
+
+pub use makepad_platform;
+
+const FRAME_TIME: f64 = 1.0 / 60.0;
+
+#[derive(Clone, Debug)]
+pub struct StdinDriverOptions {
+    /// Extra arguments for the app, the stdin loop arguments are added by the driver
+    pub args: Vec<String>,
+    pub width: f64,
+    pub height: f64,
+    pub dpi_factor: f64,
+    /// How long to wait for the app to start and for each frame
+    pub timeout: Duration,
+    pub golden_dir: PathBuf,
+    /// Where captures and failed comparisons are written
+    pub output_dir: PathBuf,
+}
+
+impl Default for StdinDriverOptions {
+    fn default() -> Self {
+        Self {
+            args: Vec::new(),
+            width: 800.0,
+            height: 600.0,
+            dpi_factor: 1.0,
+            timeout: Duration::from_secs(30),
+            golden_dir: PathBuf::from("tests").join("golden"),
+            output_dir: std::env::temp_dir().join("makepad_stdin_driver"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, SerJson, DeJson)]
+pub enum ScriptStep {
+    /// Sends a message to the app as is
+    Send(HostToStdin),
+    /// Ticks the app until it presents a new frame
+    WaitFrame,
+    /// Ticks the app a number of times without waiting for frames, to let timers and animations run
+    Ticks(u64),
+    MouseMove {x: f64, y: f64},
+    MouseDown {x: f64, y: f64, button: usize},
+    MouseUp {x: f64, y: f64, button: usize},
+    /// Moves to, presses and releases the left mouse button, waiting for a frame after each
+    Click {x: f64, y: f64},
+    Scroll {x: f64, y: f64, sx: f64, sy: f64},
+    KeyDown(KeyCode),
+    KeyUp(KeyCode),
+    TextInput(String),
+    /// Writes the last frame to `output_dir/<name>.png`
+    Capture(String),
+    /// Compares the last frame against `golden_dir/<name>.png` with the default tolerance
+    AssertGolden(String),
+    AssertGoldenWithin {name: String, channel: u8, max_mismatched_pixels: usize},
+}
+
+impl ScriptStep {
+    /// Parses a script, returns the steps with their line numbers
+    pub fn parse_script(script: &str) -> Result<Vec<(usize, ScriptStep)>, String> {
+        let mut steps = Vec::new();
+        for (index, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let step = match ScriptStep::deserialize_json(line) {
+                Ok(step) => step,
+                Err(step_err) => match HostToStdin::deserialize_json(line) {
+                    Ok(msg) => ScriptStep::Send(msg),
+                    Err(_) => return Err(format!("line {}: {:?}", index + 1, step_err))
+                }
+            };
+            steps.push((index + 1, step));
+        }
+        Ok(steps)
+    }
+}
+
+pub struct Frame {
+    pub draw: PresentableDraw,
+    pub image: ImageBuffer,
+}
+
+pub struct StdinDriver {
+    pub options: StdinDriverOptions,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    messages: Receiver<StdinToHost>,
+    frame_dir: PathBuf,
+    time: f64,
+    frame: u64,
+    modifiers: KeyModifiers,
+    pub last_frame: Option<Frame>,
+    pub cursor: Option<MouseCursor>,
+}
+
+impl StdinDriver {
+    /// Spawns an app binary and waits until it is ready
+    pub fn spawn(program: impl AsRef<OsStr>, options: StdinDriverOptions) -> Result<Self, String> {
+        Self::spawn_command(Command::new(program), options)
+    }
+
+    /// Builds and runs a package of the current cargo workspace with `cargo run`
+    pub fn spawn_cargo(package: &str, options: StdinDriverOptions) -> Result<Self, String> {
+        // build first so compiling doesn't count towards the startup timeout
+        let status = Command::new("cargo").args(["build", "-p", package]).status()
+            .map_err( | e | format!("cannot run cargo: {}", e)) ?;
+        if !status.success() {
+            return Err(format!("building {} failed", package))
+        }
+        let mut command = Command::new("cargo");
+        command.args(["run", "-p", package, "--"]);
+        Self::spawn_command(command, options)
+    }
+
+    fn spawn_command(mut command: Command, options: StdinDriverOptions) -> Result<Self, String> {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+        let frame_dir = std::env::temp_dir().join(format!(
+            "makepad_stdin_driver_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&frame_dir).map_err( | e | format!("cannot create {}: {}", frame_dir.display(), e)) ?;
+
+        let mut child = command
+            .args(&options.args)
+            .arg("--stdin-loop")
+            .arg("--headless")
+            .arg(format!("--stdin-loop-frame-dir={}", frame_dir.display()))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err( | e | format!("cannot spawn app: {}", e)) ?;
+
+        let stdout = child.stdout.take().unwrap();
+        let (msg_tx, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if let Ok(0) | Err(_) = reader.read_line(&mut line) {
+                    break;
+                }
+                // everything that isn't a protocol message is the app's own output
+                match StdinToHost::deserialize_json(&line) {
+                    Ok(msg) => if msg_tx.send(msg).is_err() {
+                        break
+                    }
+                    Err(_) => print!("{}", line)
+                }
+            }
+        });
+
+        let mut driver = Self {
+            options,
+            stdin: child.stdin.take(),
+            child,
+            messages,
+            frame_dir,
+            time: 0.0,
+            frame: 0,
+            modifiers: Default::default(),
+            last_frame: None,
+            cursor: None,
+        };
+
+        let deadline = Instant::now() + driver.options.timeout;
+        loop {
+            match driver.recv_until(deadline) ? {
+                Some(StdinToHost::ReadyToStart) => break,
+                Some(msg) => driver.handle_message(msg) ?,
+                None => return Err("app did not start in time".to_string())
+            }
+        }
+        driver.send(&HostToStdin::WindowGeomChange {
+            dpi_factor: driver.options.dpi_factor,
+            inner_width: driver.options.width,
+            inner_height: driver.options.height,
+        }) ?;
+        Ok(driver)
+    }
+
+    pub fn send(&mut self, msg: &HostToStdin) -> Result<(), String> {
+        let stdin = self.stdin.as_mut().ok_or_else( || "app stdin is closed".to_string()) ?;
+        stdin.write_all(msg.to_json().as_bytes())
+            .and_then( | _ | stdin.flush())
+            .map_err( | e | format!("cannot write to app: {}", e))
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Advances the clock by one frame and sends a tick
+    pub fn tick(&mut self) -> Result<(), String> {
+        self.time += FRAME_TIME;
+        self.frame += 1;
+        self.send(&HostToStdin::Tick {buffer_id: 0, frame: self.frame, time: self.time})
+    }
+
+    /// Sends a number of ticks a frame time apart, handling whatever the app sends meanwhile
+    pub fn ticks(&mut self, count: u64) -> Result<(), String> {
+        for _ in 0..count {
+            self.tick() ?;
+            let deadline = Instant::now() + Duration::from_secs_f64(FRAME_TIME);
+            while let Some(msg) = self.recv_until(deadline) ? {
+                self.handle_message(msg) ?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ticks the app until it presents a frame, frames presented before this call are skipped
+    pub fn wait_frame(&mut self) -> Result<&Frame, String> {
+        while let Ok(msg) = self.messages.try_recv() {
+            self.handle_message(msg) ?;
+        }
+        let deadline = Instant::now() + self.options.timeout;
+        loop {
+            self.tick() ?;
+            let tick_deadline = (Instant::now() + Duration::from_secs_f64(FRAME_TIME)).min(deadline);
+            while let Some(msg) = self.recv_until(tick_deadline) ? {
+                if let StdinToHost::DrawCompleteAndFlip(_) = msg {
+                    self.handle_message(msg) ?;
+                    return Ok(self.last_frame.as_ref().unwrap())
+                }
+                self.handle_message(msg) ?;
+            }
+            if Instant::now() >= deadline {
+                return Err("timed out waiting for a frame".to_string())
+            }
+        }
+    }
+
+    /// Returns None when the deadline passes, and an error when the app went away
+    fn recv_until(&mut self, deadline: Instant) -> Result<Option<StdinToHost>, String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.messages.recv_timeout(timeout) {
+            Ok(msg) => Ok(Some(msg)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                let status = self.child.wait().map( | s | s.to_string()).unwrap_or_default();
+                Err(format!("app exited ({})", status))
+            }
+        }
+    }
+
+    fn handle_message(&mut self, msg: StdinToHost) -> Result<(), String> {
+        match msg {
+            StdinToHost::ReadyToStart => (),
+            StdinToHost::SetCursor(cursor) => self.cursor = Some(cursor),
+            StdinToHost::DrawCompleteAndFlip(draw) => {
+                self.last_frame = Some(Frame {image: self.read_frame(&draw) ?, draw});
+            }
+        }
+        Ok(())
+    }
+
+    fn read_frame(&self, draw: &PresentableDraw) -> Result<ImageBuffer, String> {
+        let path = self.frame_dir.join(format!("{:016x}.png", draw.target_id.as_u64()));
+        let data = std::fs::read(&path).map_err( | e | format!("cannot read frame {}: {}", path.display(), e)) ?;
+        let image = png::decode(&data) ?;
+        let (width, height) = (draw.width as usize, draw.height as usize);
+        if image.width == width && image.height == height {
+            return Ok(image)
+        }
+        // only the drawn part of the presentable image counts
+        let mut cropped = ImageBuffer::new(width.min(image.width), height.min(image.height));
+        for y in 0..cropped.height {
+            let src = &image.data[y * image.width..y * image.width + cropped.width];
+            cropped.data[y * cropped.width..(y + 1) * cropped.width].copy_from_slice(src);
+        }
+        Ok(cropped)
+    }
+
+    /// The last presented frame, waiting for one if the app didn't present anything yet
+    pub fn capture(&mut self) -> Result<&ImageBuffer, String> {
+        if self.last_frame.is_none() {
+            self.wait_frame() ?;
+        }
+        Ok(&self.last_frame.as_ref().unwrap().image)
+    }
+
+    pub fn save_capture(&mut self, name: &str) -> Result<PathBuf, String> {
+        let path = self.options.output_dir.join(format!("{}.png", name));
+        visual_test::save_png(self.capture() ?, &path) ?;
+        Ok(path)
+    }
+
+    /// Compares the last frame against `golden_dir/<name>.png`, see `visual_test::compare_golden`
+    pub fn compare_golden(&mut self, name: &str, tolerance: GoldenTolerance) -> Result<(), String> {
+        let golden_dir = self.options.golden_dir.clone();
+        let output_dir = self.options.output_dir.clone();
+        visual_test::compare_golden(self.capture() ?, &golden_dir, &output_dir, name, tolerance, visual_test::update_golden_requested())
+    }
+
+    pub fn mouse_move(&mut self, x: f64, y: f64) -> Result<(), String> {
+        self.send(&HostToStdin::MouseMove(StdinMouseMove {time: self.time, x, y}))
+    }
+
+    pub fn mouse_down(&mut self, x: f64, y: f64, button: usize) -> Result<(), String> {
+        self.send(&HostToStdin::MouseDown(StdinMouseDown {button, x, y, time: self.time}))
+    }
+
+    pub fn mouse_up(&mut self, x: f64, y: f64, button: usize) -> Result<(), String> {
+        self.send(&HostToStdin::MouseUp(StdinMouseUp {time: self.time, button, x, y}))
+    }
+
+    /// Moves to, presses and releases the left mouse button, with a frame in between each step
+    pub fn click(&mut self, x: f64, y: f64) -> Result<(), String> {
+        self.mouse_move(x, y) ?;
+        self.ticks(1) ?;
+        self.mouse_down(x, y, 0) ?;
+        self.ticks(1) ?;
+        self.mouse_up(x, y, 0) ?;
+        self.ticks(1)
+    }
+
+    pub fn scroll(&mut self, x: f64, y: f64, sx: f64, sy: f64) -> Result<(), String> {
+        self.send(&HostToStdin::Scroll(StdinScroll {time: self.time, sx, sy, x, y, is_mouse: true}))
+    }
+
+    /// Modifier keys are tracked, so a `KeyDown(Shift)` applies to the keys that follow it
+    pub fn key_down(&mut self, key_code: KeyCode) -> Result<(), String> {
+        self.set_modifier(key_code, true);
+        self.send(&HostToStdin::KeyDown(KeyEvent {key_code, is_repeat: false, modifiers: self.modifiers, time: self.time}))
+    }
+
+    pub fn key_up(&mut self, key_code: KeyCode) -> Result<(), String> {
+        self.set_modifier(key_code, false);
+        self.send(&HostToStdin::KeyUp(KeyEvent {key_code, is_repeat: false, modifiers: self.modifiers, time: self.time}))
+    }
+
+    fn set_modifier(&mut self, key_code: KeyCode, down: bool) {
+        match key_code {
+            KeyCode::Shift => self.modifiers.shift = down,
+            KeyCode::Control => self.modifiers.control = down,
+            KeyCode::Alt => self.modifiers.alt = down,
+            KeyCode::Logo => self.modifiers.logo = down,
+            _ => ()
+        }
+    }
+
+    pub fn text_input(&mut self, input: &str) -> Result<(), String> {
+        self.send(&HostToStdin::TextInput(TextInputEvent {
+            input: input.to_string(),
+            replace_last: false,
+            was_paste: false,
+        }))
+    }
+
+    pub fn run_step(&mut self, step: &ScriptStep) -> Result<(), String> {
+        match step {
+            ScriptStep::Send(msg) => self.send(msg),
+            ScriptStep::WaitFrame => self.wait_frame().map( | _ | ()),
+            ScriptStep::Ticks(count) => self.ticks(*count),
+            ScriptStep::MouseMove {x, y} => self.mouse_move(*x, *y),
+            ScriptStep::MouseDown {x, y, button} => self.mouse_down(*x, *y, *button),
+            ScriptStep::MouseUp {x, y, button} => self.mouse_up(*x, *y, *button),
+            ScriptStep::Click {x, y} => self.click(*x, *y),
+            ScriptStep::Scroll {x, y, sx, sy} => self.scroll(*x, *y, *sx, *sy),
+            ScriptStep::KeyDown(key_code) => self.key_down(*key_code),
+            ScriptStep::KeyUp(key_code) => self.key_up(*key_code),
+            ScriptStep::TextInput(input) => self.text_input(input),
+            ScriptStep::Capture(name) => self.save_capture(name).map( | _ | ()),
+            ScriptStep::AssertGolden(name) => self.compare_golden(name, GoldenTolerance::default()),
+            ScriptStep::AssertGoldenWithin {name, channel, max_mismatched_pixels} => {
+                self.compare_golden(name, GoldenTolerance {channel: *channel, max_mismatched_pixels: *max_mismatched_pixels})
+            }
+        }
+    }
+
+    /// Runs all steps of a script, stops at the first failing one
+    pub fn run_script(&mut self, script: &str) -> Result<(), String> {
+        for (line, step) in ScriptStep::parse_script(script) ? {
+            self.run_step(&step).map_err( | e | format!("line {}: {}", line, e)) ?;
+        }
+        Ok(())
+    }
+
+    pub fn run_script_file(&mut self, path: &Path) -> Result<(), String> {
+        let script = std::fs::read_to_string(path).map_err( | e | format!("cannot read {}: {}", path.display(), e)) ?;
+        self.run_script(&script).map_err( | e | format!("{}: {}", path.display(), e))
+    }
+
+    /// Closes stdin which ends the app's stdin loop, the app is killed if it doesn't exit in time
+    pub fn quit(mut self) -> Result<(), String> {
+        self.stdin = None;
+        let deadline = Instant::now() + self.options.timeout;
+        while Instant::now() < deadline {
+            match self.child.try_wait() {
+                Ok(Some(_)) => return Ok(()),
+                Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+                Err(e) => return Err(e.to_string())
+            }
+        }
+        Err("app did not exit in time".to_string())
+    }
+}
+
+impl Drop for StdinDriver {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+        let _ = std::fs::remove_dir_all(&self.frame_dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_script() {
+        let script = r#"
+            # a comment
+            {"WaitFrame":[]}
+            {"MouseMove":[{"time":0.5,"x":10.0,"y":20.0}]}
+            {"Click":{"x":1.0,"y":2.0}}
+            {"AssertGoldenWithin":{"name":"start","channel":4,"max_mismatched_pixels":10}}
+        "#;
+        let steps = ScriptStep::parse_script(script).unwrap();
+        assert_eq!(steps.len(), 4);
+        assert!(matches!(steps[0], (3, ScriptStep::WaitFrame)));
+        assert!(matches!(&steps[1].1, ScriptStep::Send(HostToStdin::MouseMove(m)) if m.x == 10.0 && m.y == 20.0));
+        assert!(matches!(steps[2].1, ScriptStep::Click {x, y} if x == 1.0 && y == 2.0));
+        assert!(matches!(&steps[3].1, ScriptStep::AssertGoldenWithin {name, channel: 4, max_mismatched_pixels: 10} if name == "start"));
+        assert!(ScriptStep::parse_script("{\"Nope\":[]}").is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use {
+    std::{path::PathBuf, time::Duration},
+    makepad_stdin_driver::{StdinDriver, StdinDriverOptions},
+};
+
+fn show_help(err: &str) {
+    if !err.is_empty() {
+        println!("{}", err);
+    }
+    println!("Makepad stdin driver");
+    println!("    Runs a makepad app headless over the stdin protocol and replays a script against it");
+    println!();
+    println!("Usage makepad-stdin-driver [options] <script> -- <app binary> [app args]");
+    println!("      makepad-stdin-driver [options] <script> --package=<name> [app args]");
+    println!();
+    println!("    [options] with its default value:");
+    println!();
+    println!("       --size=800x600                            The window size in logical pixels");
+    println!("       --dpi=1.0                                 The dpi factor");
+    println!("       --timeout=30                              Seconds to wait for startup and for each frame");
+    println!("       --golden-dir=tests/golden                 Where AssertGolden looks for golden images");
+    println!("       --output-dir=<tmp>/makepad_stdin_driver   Where captures and failed comparisons are written");
+    println!();
+    println!("    Set MAKEPAD_UPDATE_GOLDEN=1 to (re)write the golden images");
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut options = StdinDriverOptions::default();
+    let mut script = None;
+    let mut package = None;
+    let mut program = None;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--" {
+            program = args.get(i + 1).cloned();
+            options.args.extend(args.iter().skip(i + 2).cloned());
+            break;
+        }
+        if let Some(v) = arg.strip_prefix("--package=") {
+            package = Some(v.to_string());
+        }
+        else if let Some(v) = arg.strip_prefix("--size=") {
+            match v.split_once('x').map( | (w, h) | (w.parse(), h.parse())) {
+                Some((Ok(w), Ok(h))) => {
+                    options.width = w;
+                    options.height = h;
+                }
+                _ => return show_help(&format!("invalid size {}", v))
+            }
+        }
+        else if let Some(v) = arg.strip_prefix("--dpi=") {
+            match v.parse() {
+                Ok(dpi) => options.dpi_factor = dpi,
+                Err(_) => return show_help(&format!("invalid dpi {}", v))
+            }
+        }
+        else if let Some(v) = arg.strip_prefix("--timeout=") {
+            match v.parse() {
+                Ok(secs) => options.timeout = Duration::from_secs_f64(secs),
+                Err(_) => return show_help(&format!("invalid timeout {}", v))
+            }
+        }
+        else if let Some(v) = arg.strip_prefix("--golden-dir=") {
+            options.golden_dir = PathBuf::from(v);
+        }
+        else if let Some(v) = arg.strip_prefix("--output-dir=") {
+            options.output_dir = PathBuf::from(v);
+        }
+        else if arg == "--help" || arg == "-h" {
+            return show_help("");
+        }
+        else if script.is_none() {
+            script = Some(PathBuf::from(arg));
+        }
+        else if package.is_some() {
+            options.args.push(arg.clone());
+        }
+        else {
+            return show_help(&format!("unexpected argument {}", arg));
+        }
+        i += 1;
+    }
+    let script = if let Some(script) = script {script} else {
+        return show_help("no script given");
+    };
+    let driver = match (package, program) {
+        (Some(package), None) => StdinDriver::spawn_cargo(&package, options),
+        (None, Some(program)) => StdinDriver::spawn(program, options),
+        _ => return show_help("give either --package=<name> or -- <app binary>")
+    };
+    let result = driver.and_then( | mut driver | {
+        driver.run_script_file(&script) ?;
+        driver.quit()
+    });
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
@@ -38,6 +38,7 @@ pub fn derive_widget_node_impl(input: TokenStream) ->  TokenStream {
         let mut wrap_field = None;
         let mut find_fields = Vec::new();
         let mut redraw_fields = Vec::new();
+        let mut area_field = None;
         for field in &mut fields {
             if field.attrs.iter().find(|v| v.name == "walk").is_some(){
                 walk_field = Some(field.name.clone());
@@ -47,6 +48,9 @@ pub fn derive_widget_node_impl(input: TokenStream) ->  TokenStream {
             }
             if field.attrs.iter().find(|v| v.name == "redraw").is_some(){
                 redraw_fields.push(field.name.clone());
+                if area_field.is_none(){
+                    area_field = Some((field.name.clone(), field.ty.to_string().trim() == "Area"));
+                }
             }
             if field.attrs.iter().find(|v| v.name == "find").is_some(){
                 find_fields.push(field.name.clone());
@@ -61,6 +65,8 @@ pub fn derive_widget_node_impl(input: TokenStream) ->  TokenStream {
             tb.add("    fn walk(&mut self, cx:&mut Cx) -> Walk { self.").ident(&wrap_field).add(".walk(cx)}");            
             tb.add("    fn redraw(&mut self, cx:&mut Cx) { self.").ident(&wrap_field).add(".redraw(cx)}");
             tb.add("    fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results: &mut WidgetSet){self.").ident(&wrap_field).add(".find_widgets(path, cached, results)}");
+            tb.add("    fn area(&self) -> Area { self.").ident(&wrap_field).add(".area()}");
+            tb.add("    fn widget_tree_children(&self, cx: &Cx, children: &mut Vec<WidgetTreeNode>) { self.").ident(&wrap_field).add(".widget_tree_children(cx, children)}");
         }
         else{
             if let Some(walk_field) = &walk_field{
@@ -85,15 +91,32 @@ pub fn derive_widget_node_impl(input: TokenStream) ->  TokenStream {
             else{
                 return error("Need either a field marked redraw or deref or wrap to find redraw method")
             }
+            match &area_field{
+                Some((area_field, true))=>{
+                    tb.add("    fn area(&self) -> Area { self.").ident(area_field).add("}");
+                }
+                Some((area_field, false))=>{
+                    tb.add("    fn area(&self) -> Area { self.").ident(area_field).add(".area()}");
+                }
+                None=> if let Some(deref_field) = &deref_field{
+                    tb.add("    fn area(&self) -> Area { self.").ident(&deref_field).add(".area()}");
+                }
+            }
             if find_fields.len()>0{
                 tb.add("    fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results: &mut WidgetSet){");
-                for find_field in find_fields{
-                    tb.add("    self.").ident(&find_field).add(".find_widgets(path, cached, results);");
+                for find_field in &find_fields{
+                    tb.add("    self.").ident(find_field).add(".find_widgets(path, cached, results);");
+                }
+                tb.add("    }");
+                tb.add("    fn widget_tree_children(&self, cx: &Cx, children: &mut Vec<WidgetTreeNode>){");
+                for find_field in &find_fields{
+                    tb.add("    if let Some(node) = self.").ident(find_field).add(".widget_tree_node(cx, LiveId::from_str(").string(find_field).add(")){children.push(node)}");
                 }
                 tb.add("    }");
             }
             else if let Some(deref_field) = &deref_field{
                 tb.add("    fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results: &mut WidgetSet){self.").ident(&deref_field).add(".find_widgets(path, cached, results)}");
+                tb.add("    fn widget_tree_children(&self, cx: &Cx, children: &mut Vec<WidgetTreeNode>){self.").ident(&deref_field).add(".widget_tree_children(cx, children)}");
             }
             else{
                 tb.add("    fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results: &mut WidgetSet){}");
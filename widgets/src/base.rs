@@ -11,10 +11,12 @@ live_design!{
     import crate::drop_down::DropDownBase;
     import crate::file_tree::FileTreeBase;
     import crate::file_tree::FileTreeNodeBase;
+    import crate::fallback_file_dialog::FallbackFileDialogBase;
     import crate::fold_button::FoldButtonBase;
     import crate::fold_header::FoldHeaderBase;
     import crate::image::ImageBase;
     import crate::rotated_image::RotatedImageBase;
+    import crate::svg::SvgBase;
     import crate::video::VideoBase;
     import crate::popup_menu::PopupMenuBase;
     import crate::label::LabelBase;
@@ -45,6 +47,8 @@ live_design!{
     import crate::keyboard_view::KeyboardViewBase;
     import crate::window_menu::WindowMenuBase;
     import crate::html::HtmlBase;
+    import crate::markdown::MarkdownBase;
+    import crate::code_block::CodeBlockBase;
     
     import makepad_draw::shader::std::*;
     import makepad_draw::shader::draw_color::DrawColor;
@@ -81,10 +85,13 @@ live_design!{
     Image = <ImageBase> {
         width: 100
         height: 100
+        fade_in: 0.25
         
         draw_bg: {
             texture image: texture2d
             instance opacity: 1.0
+            instance fade: 1.0
+            instance placeholder_color: #0000
             instance image_scale: vec2(1.0, 1.0)
             instance image_pan: vec2(0.0, 0.0)
             
@@ -98,7 +105,9 @@ live_design!{
             
             fn pixel(self) -> vec4 {
                 let color = self.get_color();
-                return Pal::premul(vec4(color.xyz, color.w * self.opacity))
+                // async loaded images fade in over the placeholder
+                let image = mix(Pal::premul(self.placeholder_color), Pal::premul(color), self.fade);
+                return image * self.opacity
             }
         }
     }
@@ -202,6 +211,11 @@ live_design!{
             fill: Image
         }
     }
+    
+    Svg = <SvgBase> {
+        width: Fit
+        height: Fit
+    }
 
     Video = <VideoBase> {
         width: 100, height: 100
@@ -667,6 +681,8 @@ live_design!{
     // todo fix this by allowing reexporting imports
     // for now this works too
     HtmlBase = <HtmlBase>{}
+    MarkdownBase = <MarkdownBase>{}
+    CodeBlockBase = <CodeBlockBase>{}
     KeyboardViewBase = <KeyboardViewBase>{}
     PageFlipBase = <PageFlipBase>{}
     ViewBase = <ViewBase>{}
@@ -679,10 +695,12 @@ live_design!{
     DropDownBase = <DropDownBase> {}
     FileTreeBase = <FileTreeBase> {}
     FileTreeNodeBase = <FileTreeNodeBase> {}
+    FallbackFileDialogBase = <FallbackFileDialogBase> {}
     FoldButtonBase = <FoldButtonBase> {}
     FoldHeaderBase = <FoldHeaderBase> {}
     ImageBase = <ImageBase> {}
     RotatedImageBase = <RotatedImageBase> {}
+    SvgBase = <SvgBase> {}
     VideoBase = <VideoBase> {}
     LabelBase = <LabelBase> {}
     LinkLabelBase = <LinkLabelBase> {}
@@ -35,9 +35,17 @@ pub struct Button {
 
     #[live(true)]
     grab_key_focus: bool,
+    
+    // 0 follows the widget tree order, positive values come first, negative skips the button when tabbing
+    #[live]
+    tab_index: i64,
 
     #[live]
     pub text: RcStringMut,
+    
+    // the key that pressed the button, only its key up clicks
+    #[rust]
+    key_down: Option<KeyCode>,
 }
 
 impl Widget for Button {
@@ -61,6 +69,21 @@ impl Widget for Button {
             Hit::FingerHoverOut(_) => {
                 self.animator_play(cx, id!(hover.off));
             }
+            Hit::KeyDown(ke) if !ke.is_repeat && self.key_down.is_none() && matches!(ke.key_code, KeyCode::Space | KeyCode::ReturnKey) => {
+                self.key_down = Some(ke.key_code);
+                cx.widget_action(uid, &scope.path, ButtonAction::Pressed);
+                self.animator_play(cx, id!(hover.pressed));
+            }
+            Hit::KeyUp(ke) if self.key_down == Some(ke.key_code) => {
+                self.key_down = None;
+                cx.widget_action(uid, &scope.path, ButtonAction::Clicked);
+                cx.widget_action(uid, &scope.path, ButtonAction::Released);
+                self.animator_play(cx, id!(hover.off));
+            }
+            Hit::KeyFocusLost(_) => if self.key_down.take().is_some() {
+                cx.widget_action(uid, &scope.path, ButtonAction::Released);
+                self.animator_play(cx, id!(hover.off));
+            }
             Hit::FingerUp(fe) => {
                 if fe.is_over {
                     cx.widget_action(uid, &scope.path, ButtonAction::Clicked);
@@ -85,6 +108,9 @@ impl Widget for Button {
             .draw_walk(cx, self.label_walk, Align::default(), self.text.as_ref());
         self.draw_icon.draw_walk(cx, self.icon_walk);
         self.draw_bg.end(cx);
+        if self.grab_key_focus {
+            cx.add_nav_stop_with_tab_index(self.draw_bg.area(), NavRole::Button, Margin::default(), self.tab_index);
+        }
         DrawStep::done()
     }
 
@@ -95,6 +121,11 @@ impl Widget for Button {
     fn set_text(&mut self, v: &str) {
         self.text.as_mut_empty().push_str(v);
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Button;
+        node.label = self.text();
+    }
 }
 
 impl ButtonRef {
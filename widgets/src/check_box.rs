@@ -49,6 +49,7 @@ pub struct CheckBox {
     #[live] text: RcStringMut,
     
     #[live] bind: String,
+    #[live] tab_index: i64,
 }
 
 #[derive(Clone, Debug, DefaultNone)]
@@ -71,6 +72,19 @@ impl CheckBox {
         self.draw_text.draw_walk(cx, self.label_walk, self.label_align, self.text.as_ref());
         self.draw_icon.draw_walk(cx, self.icon_walk);
         self.draw_check.end(cx);
+        cx.add_nav_stop_with_tab_index(self.draw_check.area(), NavRole::CheckBox, Margin::default(), self.tab_index);
+    }
+    
+    fn toggle(&mut self, cx: &mut Cx, scope: &mut Scope) {
+        let uid = self.widget_uid();
+        if self.animator_in_state(cx, id!(selected.on)) {
+            self.animator_play(cx, id!(selected.off));
+            cx.widget_action(uid, &scope.path, CheckBoxAction::Change(false));
+        }
+        else {
+            self.animator_play(cx, id!(selected.on));
+            cx.widget_action(uid, &scope.path, CheckBoxAction::Change(true));
+        }
     }
 }
 
@@ -95,7 +109,6 @@ impl Widget for CheckBox {
     }
     
     fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
-        let uid = self.widget_uid();
         self.animator_handle_event(cx, event);
                 
         match event.hits(cx, self.draw_check.area()) {
@@ -107,15 +120,12 @@ impl Widget for CheckBox {
                 self.animator_play(cx, id!(hover.off));
             },
             Hit::FingerDown(_fe) => {
-                if self.animator_in_state(cx, id!(selected.on)) {
-                    self.animator_play(cx, id!(selected.off));
-                    cx.widget_action(uid, &scope.path, CheckBoxAction::Change(false));
-                }
-                else {
-                    self.animator_play(cx, id!(selected.on));
-                    cx.widget_action(uid, &scope.path, CheckBoxAction::Change(true));
-                }
+                cx.set_key_focus(self.draw_check.area());
+                self.toggle(cx, scope);
             },
+            Hit::KeyDown(ke) if !ke.is_repeat && matches!(ke.key_code, KeyCode::Space | KeyCode::ReturnKey) => {
+                self.toggle(cx, scope);
+            }
             Hit::FingerUp(_fe) => {
                                 
             }
@@ -138,6 +148,12 @@ impl Widget for CheckBox {
     fn set_text(&mut self, v: &str) {
         self.text.as_mut_empty().push_str(v);
     }
+
+    fn describe_widget(&self, cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::CheckBox;
+        node.label = self.text();
+        node.checked = Some(self.animator_in_state(cx, id!(selected.on)));
+    }
 }
 
 impl CheckBoxRef {
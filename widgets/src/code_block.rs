@@ -0,0 +1,61 @@
+use {
+    crate::{
+        makepad_derive_widget::*,
+        makepad_draw::*,
+        widget::*,
+    }
+};
+
+live_design!{
+    CodeBlockBase = {{CodeBlock}} {}
+}
+
+// A read only block of code in the fixed width font, the default code_block template of
+// Markdown. makepad-code-editor has a CodeView with the same fields that also colors the code.
+#[derive(Live, LiveHook, Widget)]
+pub struct CodeBlock {
+    #[walk] walk: Walk,
+    #[layout] layout: Layout,
+    #[redraw] #[live] draw_bg: DrawColor,
+    #[live] draw_text: DrawText,
+    #[live] text: String,
+    // the language of a fenced code block, like "rust"
+    #[live] language: String,
+}
+
+impl Widget for CodeBlock {
+    fn draw_walk(&mut self, cx: &mut Cx2d, _scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.draw_bg.begin(cx, walk, self.layout);
+        for line in self.text.split('\n') {
+            // an empty line still has to take up a line
+            self.draw_text.draw_walk_word(cx, if line.is_empty() {" "} else {line});
+            cx.turtle_new_line();
+        }
+        self.draw_bg.end(cx);
+        DrawStep::done()
+    }
+
+    fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    fn set_text(&mut self, v: &str) {
+        self.text = v.to_string();
+    }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Label;
+        node.label = self.text.clone();
+    }
+}
+
+impl CodeBlockRef {
+    pub fn language(&self) -> String {
+        if let Some(inner) = self.borrow() {
+            inner.language.clone()
+        }
+        else {
+            String::new()
+        }
+    }
+}
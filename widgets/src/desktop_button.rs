@@ -59,6 +59,10 @@ impl Widget for DesktopButton{
         let _ = self.draw_walk_desktop_button(cx, walk);
         DrawStep::done()
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Button;
+    }
 }
 
 #[derive(Live, LiveHook)]
@@ -75,6 +75,18 @@ impl WidgetNode for Dock{
         self.area.redraw(cx)
     }
     
+    fn area(&self) -> Area{
+        self.area
+    }
+    
+    fn widget_tree_children(&self, cx: &Cx, children: &mut Vec<WidgetTreeNode>) {
+        for (id, (_, widget)) in self.items.iter() {
+            if let Some(node) = widget.widget_tree_node(cx, *id) {
+                children.push(node);
+            }
+        }
+    }
+    
     fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results: &mut WidgetSet) {
         if let Some((_, widget)) = self.items.get_mut(&path[0]) {
             if path.len()>1 {
@@ -285,6 +297,126 @@ pub enum DockItemStore{
     }
 }
 
+fn dock_items_to_store(dock_items: &HashMap<LiveId, DockItem>)->Vec<DockItemStore>{
+    let mut out = Vec::new();
+    for (id, dock_item) in dock_items{
+        match dock_item{
+            DockItem::Splitter {
+                axis,
+                align,
+                a,
+                b
+            }=>{
+                out.push(DockItemStore::Splitter{
+                    id:LiveIdStore(*id),
+                    axis:*axis,
+                    align:*align, 
+                    a:LiveIdStore(*a),
+                    b:LiveIdStore(*b)
+                });
+            }
+            DockItem::Tabs {
+                tabs,
+                selected,
+                closable
+            }=>{
+                out.push(DockItemStore::Tabs{
+                    id:LiveIdStore(*id),
+                    tabs: tabs.iter().map(|v| LiveIdStore(*v)).collect(),
+                    selected: *selected,
+                    closable: *closable
+                });
+            }
+            DockItem:: Tab {
+                name,
+                closable,
+                kind
+            }=>{
+                out.push(DockItemStore::Tab{
+                    id:LiveIdStore(*id),
+                    name: name.clone(),
+                    closable: *closable,
+                    kind: LiveIdStore(*kind)
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Rebuilds the dock item tree from its stored form, starting at `root`.
+/// Tabs without a template are dropped and the tree is repaired around them like `close_tab` would:
+/// closable tab bars that end up empty are removed, and so are the splitters that lose a side.
+fn dock_items_from_store<F>(store: &[DockItemStore], has_template: F)->Option<HashMap<LiveId, DockItem>>
+where F: Fn(LiveId)->bool
+{
+    let mut stored = HashMap::new();
+    for item in store{
+        match item{
+            DockItemStore::Splitter{id, axis, align, a, b}=>{
+                stored.insert(id.0, DockItem::Splitter{axis: *axis, align: *align, a: a.0, b: b.0});
+            }
+            DockItemStore::Tabs{id, tabs, selected, closable}=>{
+                stored.insert(id.0, DockItem::Tabs{
+                    tabs: tabs.iter().map(|v| v.0).collect(),
+                    selected: *selected,
+                    closable: *closable
+                });
+            }
+            DockItemStore::Tab{id, name, closable, kind}=>{
+                if has_template(kind.0){
+                    stored.insert(id.0, DockItem::Tab{name: name.clone(), closable: *closable, kind: kind.0});
+                }
+                else{
+                    log!("Dock template not found for stored tab {} {}", name, kind.0);
+                }
+            }
+        }
+    }
+    
+    // items are moved from stored to out as they are reached, which also breaks cycles
+    fn restore(stored: &mut HashMap<LiveId, DockItem>, out: &mut HashMap<LiveId, DockItem>, id: LiveId)->Option<LiveId>{
+        match stored.remove(&id)?{
+            DockItem::Splitter{axis, align, a, b}=>{
+                match (restore(stored, out, a), restore(stored, out, b)){
+                    (Some(a), Some(b))=>{
+                        out.insert(id, DockItem::Splitter{axis, align, a, b});
+                        Some(id)
+                    }
+                    (Some(rest), None) | (None, Some(rest))=>Some(rest),
+                    (None, None)=>None
+                }
+            }
+            DockItem::Tabs{tabs, selected, closable}=>{
+                let mut restored = Vec::new();
+                for tab in tabs{
+                    if let Some(item @ DockItem::Tab{..}) = stored.remove(&tab){
+                        out.insert(tab, item);
+                        restored.push(tab);
+                    }
+                }
+                if restored.len() == 0 && closable{
+                    return None
+                }
+                let selected = selected.min(restored.len().max(1) - 1);
+                out.insert(id, DockItem::Tabs{tabs: restored, selected, closable});
+                Some(id)
+            }
+            DockItem::Tab{..}=>None
+        }
+    }
+    
+    let root = live_id!(root);
+    let mut out = HashMap::new();
+    let top = restore(&mut stored, &mut out, root)?;
+    if top != root{
+        // the root splitter lost a side, the survivor takes its place
+        let item = out.remove(&top).unwrap();
+        out.insert(root, item);
+    }
+    Some(out)
+}
+
 impl LiveHook for Dock {
     fn apply_value_instance(&mut self, cx: &mut Cx, apply: &mut Apply, index: usize, nodes: &[LiveNode]) -> usize {
         let id = nodes[index].id;
@@ -462,54 +594,39 @@ impl Dock {
     }
     
     pub fn to_store_items(&self)->Vec<DockItemStore>{
-        let mut out = Vec::new();
-        for (id, dock_item) in &self.dock_items{
-            match dock_item{
-                DockItem::Splitter {
-                    axis,
-                    align,
-                    a,
-                    b
-                }=>{
-                    out.push(DockItemStore::Splitter{
-                        id:LiveIdStore(*id),
-                        axis:*axis,
-                        align:*align, 
-                        a:LiveIdStore(*a),
-                        b:LiveIdStore(*b)
-                    });
-                }
-                DockItem::Tabs {
-                    tabs,
-                    selected,
-                    closable
-                }=>{
-                    out.push(DockItemStore::Tabs{
-                        id:LiveIdStore(*id),
-                        tabs: tabs.iter().map(|v| LiveIdStore(*v)).collect(),
-                        selected: *selected,
-                        closable: *closable
-                    });
-                }
-                DockItem:: Tab {
-                    name,
-                    closable,
-                    kind
-                }=>{
-                    out.push(DockItemStore::Tab{
-                        id:LiveIdStore(*id),
-                        name: name.clone(),
-                        closable: *closable,
-                        kind: LiveIdStore(*kind)
-                    });
-                }
-            }
-        }
-        out
+        dock_items_to_store(&self.dock_items)
     }
     
-    pub fn from_store_item(&mut self, _store:&[DockItemStore]){
-        
+    /// Replaces the layout with one saved by `to_store_items`. Tabs whose template no longer
+    /// exists are dropped, the same way closing them would. Returns false, leaving the dock
+    /// untouched, if nothing of the stored layout survives.
+    pub fn from_store_item(&mut self, cx: &mut Cx, store:&[DockItemStore])->bool{
+        let templates = &self.templates;
+        let dock_items = match dock_items_from_store(store, | kind | templates.contains_key(&kind)){
+            Some(dock_items)=>dock_items,
+            None=>return false
+        };
+        self.dock_items = dock_items;
+        // the tab bars and splitters are recreated on the next draw
+        self.tab_bars.clear();
+        self.splitters.clear();
+        self.drop_state = None;
+        let dock_items = &self.dock_items;
+        self.items.retain(| id, (kind, _) | {
+            matches!(dock_items.get(id), Some(DockItem::Tab{kind: k, ..}) if *k == *kind)
+        });
+        let mut items = Vec::new();
+        for (item_id, item) in self.dock_items.iter() {
+            if let DockItem::Tab {kind, ..} = item {
+                items.push((*item_id, *kind));
+            }
+        }
+        for (item_id, kind) in items {
+            self.item_or_create(cx, item_id, kind);
+        }
+        self.needs_save = false;
+        self.area.redraw(cx);
+        true
     }
     
     pub fn item(&mut self, entry_id: LiveId) -> Option<WidgetRef> {
@@ -1198,7 +1315,149 @@ impl DockRef {
         None
     }
     
+    pub fn from_store_item(&self, cx: &mut Cx, store: &[DockItemStore])->bool{
+        if let Some(mut dock) = self.borrow_mut() {
+            return dock.from_store_item(cx, store)
+        }
+        false
+    }
+    
     pub fn tab_start_drag(&self, cx: &mut Cx, _tab_id: LiveId, item: DragItem) {
         cx.start_dragging(vec![item]);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn store_tab(id: LiveId, kind: LiveId) -> DockItemStore {
+        DockItemStore::Tab {id: LiveIdStore(id), name: format!("{}", id), closable: true, kind: LiveIdStore(kind)}
+    }
+    
+    fn store_tabs(id: LiveId, tabs: &[LiveId], selected: usize, closable: bool) -> DockItemStore {
+        DockItemStore::Tabs {id: LiveIdStore(id), tabs: tabs.iter().map( | v | LiveIdStore(*v)).collect(), selected, closable}
+    }
+    
+    fn store_splitter(id: LiveId, axis: SplitterAxis, align: SplitterAlign, a: LiveId, b: LiveId) -> DockItemStore {
+        DockItemStore::Splitter {id: LiveIdStore(id), axis, align, a: LiveIdStore(a), b: LiveIdStore(b)}
+    }
+    
+    fn sorted(store: &[DockItemStore]) -> Vec<String> {
+        let mut out: Vec<String> = store.iter().map( | v | format!("{:?}", v)).collect();
+        out.sort();
+        out
+    }
+    
+    fn layout() -> Vec<DockItemStore> {
+        vec![
+            store_splitter(live_id!(root), SplitterAxis::Horizontal, SplitterAlign::FromA(250.0), live_id!(file_tree_tabs), live_id!(split1)),
+            store_splitter(live_id!(split1), SplitterAxis::Vertical, SplitterAlign::Weighted(0.75), live_id!(edit_tabs), live_id!(log_tabs)),
+            store_tabs(live_id!(file_tree_tabs), &[live_id!(file_tree)], 0, false),
+            store_tabs(live_id!(edit_tabs), &[live_id!(file1), live_id!(file2), live_id!(file3)], 2, true),
+            store_tabs(live_id!(log_tabs), &[live_id!(log_list)], 0, true),
+            store_tab(live_id!(file_tree), live_id!(FileTree)),
+            store_tab(live_id!(file1), live_id!(CodeEditor)),
+            store_tab(live_id!(file2), live_id!(CodeEditor)),
+            store_tab(live_id!(file3), live_id!(CodeEditor)),
+            store_tab(live_id!(log_list), live_id!(LogList)),
+        ]
+    }
+    
+    #[test]
+    fn round_trip_through_ron() {
+        let store = layout();
+        let ron = store.serialize_ron();
+        let loaded: Vec<DockItemStore> = DeRon::deserialize_ron(&ron).unwrap();
+        let dock_items = dock_items_from_store(&loaded, | _ | true).unwrap();
+        assert_eq!(dock_items.len(), store.len());
+        assert_eq!(sorted(&dock_items_to_store(&dock_items)), sorted(&store));
+    }
+    
+    #[test]
+    fn keeps_tab_order_and_selection() {
+        let dock_items = dock_items_from_store(&layout(), | _ | true).unwrap();
+        match dock_items.get(&live_id!(edit_tabs)) {
+            Some(DockItem::Tabs {tabs, selected, ..}) => {
+                assert_eq!(tabs, &vec![live_id!(file1), live_id!(file2), live_id!(file3)]);
+                assert_eq!(*selected, 2);
+            }
+            _ => panic!("edit_tabs not restored")
+        }
+    }
+    
+    #[test]
+    fn drops_tabs_without_template() {
+        let mut store = layout();
+        store.push(store_tabs(live_id!(run_tabs), &[], 0, false));
+        let dock_items = dock_items_from_store(&store, | kind | kind != live_id!(CodeEditor)).unwrap();
+        for file in [live_id!(file1), live_id!(file2), live_id!(file3)] {
+            assert!(dock_items.get(&file).is_none());
+        }
+        // the closable edit_tabs is now empty so it goes away, and its splitter with it
+        assert!(dock_items.get(&live_id!(edit_tabs)).is_none());
+        assert!(dock_items.get(&live_id!(split1)).is_none());
+        match dock_items.get(&live_id!(root)) {
+            Some(DockItem::Splitter {a, b, ..}) => {
+                assert_eq!(*a, live_id!(file_tree_tabs));
+                assert_eq!(*b, live_id!(log_tabs));
+            }
+            _ => panic!("root splitter not restored")
+        }
+        // unreachable items are not restored
+        assert!(dock_items.get(&live_id!(run_tabs)).is_none());
+    }
+    
+    #[test]
+    fn clamps_selection_and_keeps_fixed_tab_bars() {
+        let store = vec![
+            store_splitter(live_id!(root), SplitterAxis::Horizontal, SplitterAlign::Weighted(0.5), live_id!(left), live_id!(right)),
+            store_tabs(live_id!(left), &[live_id!(a), live_id!(b), live_id!(gone)], 2, true),
+            store_tabs(live_id!(right), &[live_id!(gone2)], 0, false),
+            store_tab(live_id!(a), live_id!(Kind)),
+            store_tab(live_id!(b), live_id!(Kind)),
+        ];
+        let dock_items = dock_items_from_store(&store, | _ | true).unwrap();
+        match dock_items.get(&live_id!(left)) {
+            Some(DockItem::Tabs {tabs, selected, ..}) => {
+                assert_eq!(tabs.len(), 2);
+                assert_eq!(*selected, 1);
+            }
+            _ => panic!("left not restored")
+        }
+        match dock_items.get(&live_id!(right)) {
+            Some(DockItem::Tabs {tabs, selected, ..}) => {
+                assert!(tabs.is_empty());
+                assert_eq!(*selected, 0);
+            }
+            _ => panic!("right not restored")
+        }
+    }
+    
+    #[test]
+    fn survivor_replaces_collapsed_root() {
+        let store = vec![
+            store_splitter(live_id!(root), SplitterAxis::Horizontal, SplitterAlign::Weighted(0.5), live_id!(left), live_id!(right)),
+            store_tabs(live_id!(left), &[live_id!(a)], 0, true),
+            store_tabs(live_id!(right), &[live_id!(b)], 0, true),
+            store_tab(live_id!(a), live_id!(Kept)),
+            store_tab(live_id!(b), live_id!(Removed)),
+        ];
+        let dock_items = dock_items_from_store(&store, | kind | kind == live_id!(Kept)).unwrap();
+        assert_eq!(dock_items.len(), 2);
+        match dock_items.get(&live_id!(root)) {
+            Some(DockItem::Tabs {tabs, ..}) => assert_eq!(tabs, &vec![live_id!(a)]),
+            _ => panic!("root not replaced")
+        }
+    }
+    
+    #[test]
+    fn rejects_store_without_root() {
+        let store = vec![
+            store_tabs(live_id!(left), &[live_id!(a)], 0, true),
+            store_tab(live_id!(a), live_id!(Kind)),
+        ];
+        assert!(dock_items_from_store(&store, | _ | true).is_none());
+        assert!(dock_items_from_store(&[], | _ | true).is_none());
+    }
+}
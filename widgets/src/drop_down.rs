@@ -36,6 +36,7 @@ pub struct DropDown {
     #[rust] is_open: bool,
     
     #[live] selected_item: usize,
+    #[live] tab_index: i64,
     
     #[layout] layout: Layout,
 }
@@ -117,7 +118,7 @@ impl DropDown {
         }
         self.draw_bg.end(cx);
         
-        cx.add_nav_stop(self.draw_bg.area(), NavRole::DropDown, Margin::default());
+        cx.add_nav_stop_with_tab_index(self.draw_bg.area(), NavRole::DropDown, Margin::default(), self.tab_index);
         
         if self.is_open && self.popup_menu.is_some() {
             //cx.set_sweep_lock(self.draw_bg.area());
@@ -237,6 +238,17 @@ impl Widget for DropDown {
                         self.draw_bg.redraw(cx);
                     }
                 },
+                KeyCode::Space | KeyCode::ReturnKey if !ke.is_repeat => {
+                    if self.is_open {
+                        self.set_closed(cx);
+                    }
+                    else {
+                        self.set_open(cx);
+                    }
+                }
+                KeyCode::Escape => if self.is_open {
+                    self.set_closed(cx);
+                }
                 _ => ()
             }
             Hit::FingerDown(_fe) => {
@@ -269,6 +281,12 @@ impl Widget for DropDown {
         self.draw_walk(cx, walk);
         DrawStep::done()
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::ComboBox;
+        node.label = self.labels.get(self.selected_item).cloned().unwrap_or_default();
+        node.value = Some(node.label.clone());
+    }
 }
 
 impl DropDownRef {
@@ -0,0 +1,230 @@
+use {
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+    crate::{
+        makepad_derive_widget::*,
+        makepad_draw::*,
+        widget::*,
+        view::*,
+        button::*,
+        label::*,
+        file_tree::*,
+    }
+};
+
+live_design! {
+    FallbackFileDialogBase = {{FallbackFileDialog}} {}
+}
+
+// An in-app file dialog for platforms without a native one. Place it over the app's UI,
+// it opens itself on Event::FileDialogFallback and answers with cx.complete_file_dialog.
+
+struct DialogNode {
+    path: PathBuf,
+    name: String,
+    // None for files, folders are read when they are first opened
+    children: Option<Vec<FileNodeId >>,
+    loaded: bool,
+}
+
+#[derive(Live, LiveHook, Widget)]
+pub struct FallbackFileDialog {
+    #[deref] view: View,
+    #[rust] request: Option<FileDialogFallbackEvent>,
+    #[rust] root: Option<FileNodeId>,
+    #[rust] nodes: LiveIdMap<FileNodeId, DialogNode>,
+    #[rust] selected: Option<FileNodeId>,
+}
+
+impl FallbackFileDialog {
+    fn node_id(path: &Path) -> FileNodeId {
+        FileNodeId(LiveId::from_str(&path.to_string_lossy()))
+    }
+
+    fn add_node(&mut self, path: PathBuf, name: String, is_dir: bool) -> FileNodeId {
+        let node_id = Self::node_id(&path);
+        self.nodes.insert(node_id, DialogNode {
+            path,
+            name,
+            children: if is_dir {Some(Vec::new())} else {None},
+            loaded: false,
+        });
+        node_id
+    }
+
+    fn load_folder(&mut self, node_id: FileNodeId) {
+        let Some(request) = &self.request else {return};
+        let kind = request.kind;
+        let filters = request.dialog.filters.clone();
+        let path = match self.nodes.get(&node_id) {
+            Some(node) if node.children.is_some() && !node.loaded => node.path.clone(),
+            _ => return
+        };
+        let mut entries = Vec::new();
+        if let Ok(read_dir) = fs::read_dir(&path) {
+            for entry in read_dir.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') {
+                    continue
+                }
+                let is_dir = entry.path().is_dir();
+                if !is_dir && (kind.is_folder() || !filters.is_empty() && !filters.iter().any( | f | f.matches(&name))) {
+                    continue
+                }
+                entries.push((name, is_dir));
+            }
+        }
+        // folders first, then files, both by name
+        entries.sort_by( | a, b | b.1.cmp(&a.1).then_with( | | a.0.to_lowercase().cmp(&b.0.to_lowercase())));
+        let children = entries.into_iter().map( | (name, is_dir) | {
+            self.add_node(path.join(&name), name, is_dir)
+        }).collect();
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.children = Some(children);
+            node.loaded = true;
+        }
+    }
+
+    fn set_root(&mut self, cx: &mut Cx, path: PathBuf) {
+        self.nodes.clear();
+        self.selected = None;
+        let name = path.to_string_lossy().to_string();
+        let root = self.add_node(path, name, true);
+        self.root = Some(root);
+        self.load_folder(root);
+        if let Some(mut file_tree) = self.view.file_tree(id!(file_tree)).borrow_mut() {
+            file_tree.forget();
+        }
+        self.view.redraw(cx);
+    }
+
+    fn open(&mut self, cx: &mut Cx, request: FileDialogFallbackEvent) {
+        if let Some(old) = self.request.take() {
+            cx.complete_file_dialog(old.dialog_id, FileDialogResult::Cancelled);
+        }
+        let (title, confirm) = match request.kind {
+            FileDialogKind::SaveFile => ("Save File", "Save"),
+            FileDialogKind::SelectFile => ("Open File", "Open"),
+            FileDialogKind::SaveFolder | FileDialogKind::SelectFolder => ("Select Folder", "Select"),
+        };
+        let title = request.dialog.title.clone().unwrap_or_else( || title.to_string());
+        self.view.label(id!(title)).set_text(&title);
+        self.view.button(id!(confirm)).set_text(confirm);
+        self.view.view(id!(filename_row)).set_visible(request.kind == FileDialogKind::SaveFile);
+        self.view.widget(id!(filename)).set_text(request.dialog.filename.as_deref().unwrap_or(""));
+
+        let root = request.dialog.location.clone()
+            .or_else( || std::env::current_dir().ok())
+            .unwrap_or_else( || PathBuf::from("/"));
+        self.request = Some(request);
+        self.set_root(cx, root);
+    }
+
+    fn finish(&mut self, cx: &mut Cx, result: FileDialogResult) {
+        if let Some(request) = self.request.take() {
+            cx.complete_file_dialog(request.dialog_id, result);
+        }
+        self.nodes.clear();
+        self.root = None;
+        self.selected = None;
+        self.view.redraw(cx);
+    }
+
+    // the path the confirm button would return, if the current selection allows one
+    fn confirmed_path(&self, filename: String) -> Option<PathBuf> {
+        let kind = self.request.as_ref() ?.kind;
+        let selected = self.selected.and_then( | id | self.nodes.get(&id));
+        let root = self.nodes.get(&self.root ?) ?;
+        match kind {
+            FileDialogKind::SelectFile => selected.filter( | n | n.children.is_none()).map( | n | n.path.clone()),
+            FileDialogKind::SaveFolder | FileDialogKind::SelectFolder => {
+                Some(selected.unwrap_or(root).path.clone())
+            }
+            FileDialogKind::SaveFile => {
+                if filename.is_empty() {
+                    return None
+                }
+                let folder = match selected {
+                    Some(node) if node.children.is_some() => node.path.clone(),
+                    Some(node) => node.path.parent().map(Into::into).unwrap_or_else( || root.path.clone()),
+                    None => root.path.clone()
+                };
+                Some(folder.join(filename))
+            }
+        }
+    }
+
+    fn draw_node(cx: &mut Cx2d, node_id: FileNodeId, file_tree: &mut FileTree, nodes: &LiveIdMap<FileNodeId, DialogNode>) {
+        if let Some(node) = nodes.get(&node_id) {
+            match &node.children {
+                Some(children) => {
+                    if file_tree.begin_folder(cx, node_id, &node.name).is_ok() {
+                        for child in children {
+                            Self::draw_node(cx, *child, file_tree, nodes);
+                        }
+                        file_tree.end_folder();
+                    }
+                }
+                None => file_tree.file(cx, node_id, &node.name)
+            }
+        }
+    }
+}
+
+impl Widget for FallbackFileDialog {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Event::FileDialogFallback(e) = event {
+            self.open(cx, e.clone());
+        }
+        if self.request.is_none() {
+            return
+        }
+        let actions = cx.capture_actions( | cx | self.view.handle_event(cx, event, scope));
+
+        let file_tree = self.view.file_tree(id!(file_tree));
+        if let Some(node_id) = file_tree.folder_clicked(&actions) {
+            self.selected = Some(node_id);
+            self.load_folder(node_id);
+            self.view.redraw(cx);
+        }
+        if let Some(node_id) = file_tree.file_clicked(&actions) {
+            self.selected = Some(node_id);
+            if self.request.as_ref().map( | r | r.kind) == Some(FileDialogKind::SaveFile) {
+                if let Some(node) = self.nodes.get(&node_id) {
+                    self.view.widget(id!(filename)).set_text_and_redraw(cx, &node.name);
+                }
+            }
+        }
+        if self.view.button(id!(up)).clicked(&actions) {
+            let parent = self.root.and_then( | root | self.nodes.get(&root)).and_then( | root | root.path.parent().map(Into::into));
+            if let Some(parent) = parent {
+                self.set_root(cx, parent);
+            }
+        }
+        if self.view.button(id!(cancel)).clicked(&actions) {
+            self.finish(cx, FileDialogResult::Cancelled);
+        }
+        else if self.view.button(id!(confirm)).clicked(&actions) {
+            let filename = self.view.widget(id!(filename)).text();
+            if let Some(path) = self.confirmed_path(filename) {
+                self.finish(cx, FileDialogResult::Selected(vec![path]));
+            }
+        }
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let Some(root) = self.root else {
+            return DrawStep::done()
+        };
+        let file_tree = self.view.file_tree(id!(file_tree));
+        while let Some(next) = self.view.draw_walk(cx, scope, walk).step() {
+            if let Some(mut file_tree) = file_tree.has_widget(&next).borrow_mut() {
+                file_tree.set_folder_is_open(cx, root, true, Animate::No);
+                Self::draw_node(cx, root, &mut *file_tree, &self.nodes);
+            }
+        }
+        DrawStep::done()
+    }
+}
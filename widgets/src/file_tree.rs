@@ -464,6 +464,10 @@ impl Widget for FileTree {
         }
         DrawStep::done()
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Tree;
+    }
 }
 
 impl FileTreeRef{
@@ -485,6 +489,15 @@ impl FileTreeRef{
         None
     }
     
+    pub fn folder_clicked(&self, actions: &Actions) -> Option<FileNodeId> {
+        if let Some(item) = actions.find_widget_action(self.widget_uid()) {
+            if let FileTreeAction::FolderClicked(file_id) = item.cast() {
+                return Some(file_id)
+            }
+        }
+        None
+    }
+    
     
     pub fn file_start_drag(&self, cx: &mut Cx, _file_id: FileNodeId, item: DragItem) {
         cx.start_dragging(vec![item]);
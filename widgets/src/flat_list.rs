@@ -291,6 +291,20 @@ impl Widget for FlatList {
         self.draw_state.end();
         DrawStep::done()
     }
+
+    fn describe_widget(&self, cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::List;
+        let start = node.children.len();
+        for (id, (_, item)) in self.items.iter() {
+            if let Some(mut child) = item.widget_tree_node(cx, *id) {
+                if child.role == WidgetRole::Generic {
+                    child.role = WidgetRole::ListItem;
+                }
+                node.children.push(child);
+            }
+        }
+        node.children[start..].sort_by( | a, b | (a.rect.y, a.rect.x).partial_cmp(&(b.rect.y, b.rect.x)).unwrap_or(std::cmp::Ordering::Equal));
+    }
 }
 
 impl FlatListRef {
@@ -99,6 +99,10 @@ impl Widget for FoldButton {
         self.draw_walk_fold_button(cx, walk);
         DrawStep::done()
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Button;
+    }
 }
 
 
@@ -4,7 +4,7 @@ use {
         makepad_derive_widget::*,
         makepad_draw::*,
         widget::*,
-        text_flow::TextFlow,
+        text_flow::{TextFlow, TextFlowAction},
     },
     std::rc::Rc,
 };
@@ -13,49 +13,264 @@ live_design!{
     HtmlBase = {{Html}} {
         // ok so we can use one drawtext
         // change to italic, change bold (SDF), strikethrough
+        flow: RightWrap,
+        paragraph_spacing: 8,
+        list_item_width: 16,
+        quote_layout: {flow: RightWrap, padding: {left: 12, top: 6, right: 6, bottom: 6}}
+        quote_walk: {width: Fill, height: Fit}
+        code_layout: {flow: RightWrap, padding: 8}
+        code_walk: {width: Fill, height: Fit}
+        sep_walk: {width: Fill, height: 9}
+        list_item_layout: {flow: Right}
+        list_item_walk: {width: Fill, height: Fit}
+        table_cell_layout: {flow: RightWrap, padding: 4}
     }
 }
 
+// Renders a subset of html on top of TextFlow, other tags are drawn with the template of the same name.
+// An image template is used for <img>, without one the alt text is drawn
 #[derive(Live, Widget)]
 pub struct Html{
     #[deref] text_flow: TextFlow,
     #[live] html: Rc<String>,
+    #[live] list_item_width: f64,
     #[rust] doc: HtmlDoc
 }
 
 // alright lets parse the HTML
 impl LiveHook for Html{
     fn after_apply_from(&mut self, _cx: &mut Cx, _apply:&mut Apply) {
+        self.parse();
+    }
+}
+
+// the blocks that are open while drawing, unclosed ones are closed by their parent or at the end
+enum Block{
+    Quote,
+    Pre,
+    List(Option<u64>),
+    Item,
+    Table,
+    Row,
+    Cell{header: bool},
+}
+
+impl Block{
+    fn end(&self, tf: &mut TextFlow, cx: &mut Cx2d, nested: bool){
+        match self{
+            Block::Quote=>{
+                tf.end_quote(cx);
+                tf.new_paragraph(cx);
+            }
+            Block::Pre=>{
+                tf.end_code(cx);
+                tf.new_paragraph(cx);
+            }
+            Block::List(_)=>if !nested{
+                tf.new_paragraph(cx);
+            }
+            Block::Item=>tf.end_list_item(cx),
+            Block::Table=>{
+                tf.end_table(cx);
+                tf.new_paragraph(cx);
+            }
+            Block::Row=>tf.end_table_row(cx),
+            Block::Cell{header}=>{
+                tf.end_table_cell(cx);
+                if *header{
+                    tf.pop_bold();
+                }
+            }
+        }
+    }
+}
+
+impl Html{
+    fn parse(&mut self){
         let mut errors = Some(Vec::new());
-        self.doc = parse_html(&*self.html, &mut errors);
-        if errors.as_ref().unwrap().len()>0{
+        self.doc = parse_html(&self.html, &mut errors);
+        if !errors.as_ref().unwrap().is_empty(){
             log!("HTML parser returned errors {:?}", errors)
         }
     }
+
+    // closes the innermost block that matches and everything opened inside of it
+    fn end_block<F>(tf: &mut TextFlow, cx: &mut Cx2d, blocks: &mut Vec<Block>, until: Option<usize>, is_match: F) where F: Fn(&Block)->bool{
+        let stop = until.unwrap_or(0);
+        if let Some(index) = blocks.iter().rposition(is_match).filter(|index| *index >= stop){
+            while blocks.len() > index{
+                let block = blocks.pop().unwrap();
+                let nested = blocks.iter().any(|b| matches!(b, Block::List(_)));
+                block.end(tf, cx, nested);
+            }
+        }
+    }
+
+    // the number of cells in the first row of the table
+    fn table_columns(node: &HtmlWalker)->usize{
+        let mut columns = 0;
+        let mut node = node.walk();
+        while !node.empty(){
+            match node.open_tag_lc(){
+                some_id!(td) | some_id!(th)=>{
+                    columns += 1;
+                    node = node.jump_to_close();
+                }
+                some_id!(table)=>node = node.jump_to_close(),
+                _=>()
+            }
+            match node.close_tag_lc(){
+                some_id!(tr) if columns > 0=>break,
+                some_id!(table)=>break,
+                _=>()
+            }
+            node = node.walk();
+        }
+        columns
+    }
 }
- 
+
 impl Widget for Html {
     fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
         self.text_flow.handle_event(cx, event, scope);
     }
-    
+
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk:Walk)->DrawStep{
         let tf = &mut self.text_flow;
         tf.begin(cx, walk);
         let mut auto_id = 0;
+        let mut blocks = Vec::new();
+        let mut links = Vec::new();
+        // leading whitespace of the first text in a block is dropped
+        let mut block_start = true;
         // alright lets iterate the html doc and draw it
         let mut node = self.doc.walk();
         while !node.empty(){
-            
+            let in_pre = blocks.iter().any(|b| matches!(b, Block::Pre));
             match node.open_tag_lc(){
-                some_id!(a)=>{
-                    node = node.jump_to_close();
+                some_id!(h1) | some_id!(h2) | some_id!(h3) | some_id!(h4) | some_id!(h5) | some_id!(h6)=>{
+                    tf.new_line(cx);
+                    tf.push_bold();
+                    tf.push_scale(match node.open_tag_lc(){
+                        some_id!(h1)=>2.0,
+                        some_id!(h2)=>1.6,
+                        some_id!(h3)=>1.3,
+                        some_id!(h4)=>1.15,
+                        _=>1.0
+                    });
+                    block_start = true;
+                }
+                some_id!(p) | some_id!(div)=>{
+                    tf.new_line(cx);
+                    block_start = true;
+                }
+                some_id!(br)=>{
+                    // an empty line still has to take up a line
+                    tf.draw_text(cx, " ");
+                    tf.new_line(cx);
+                    block_start = true;
                 }
-                some_id!(h1)=>{
-                    tf.push_scale(1.5)
+                some_id!(hr)=>{
+                    tf.sep(cx);
+                    tf.new_paragraph(cx);
+                    block_start = true;
+                }
+                some_id!(b) | some_id!(strong)=>tf.push_bold(),
+                some_id!(i) | some_id!(em)=>tf.push_italic(),
+                some_id!(u)=>tf.push_underline(),
+                some_id!(s) | some_id!(strike) | some_id!(del)=>tf.push_strikethrough(),
+                some_id!(code)=>if !in_pre{
+                    tf.push_inline_code()
                 },
-                some_id!(b)=>tf.push_bold(),
-                some_id!(i)=>tf.push_italic(),
+                some_id!(a)=>{
+                    let href = node.find_attr_lc(live_id!(href));
+                    if let Some(href) = href{
+                        tf.push_link(href);
+                    }
+                    links.push(href.is_some());
+                }
+                some_id!(img)=>{
+                    auto_id += 1;
+                    let src = node.find_attr_lc(live_id!(src)).unwrap_or("");
+                    let width = node.find_attr_lc(live_id!(width)).and_then(|v| v.parse::<f64>().ok());
+                    let height = node.find_attr_lc(live_id!(height)).and_then(|v| v.parse::<f64>().ok());
+                    if let Some(item) = tf.image_item(cx, auto_id, src, |cx, item|{
+                        if let Some(width) = width{
+                            item.apply_over(cx, live!{width: (width)});
+                        }
+                        if let Some(height) = height{
+                            item.apply_over(cx, live!{height: (height)});
+                        }
+                    }){
+                        item.draw_all(cx, scope);
+                    }
+                    else{
+                        tf.draw_text(cx, node.find_attr_lc(live_id!(alt)).unwrap_or(""));
+                    }
+                    block_start = false;
+                }
+                some_id!(blockquote)=>{
+                    tf.begin_quote(cx);
+                    blocks.push(Block::Quote);
+                    block_start = true;
+                }
+                some_id!(pre)=>{
+                    tf.begin_code(cx);
+                    blocks.push(Block::Pre);
+                    block_start = true;
+                }
+                some_id!(ul) | some_id!(ol)=>{
+                    tf.new_line(cx);
+                    let number = if let some_id!(ol) = node.open_tag_lc(){
+                        Some(node.find_attr_lc(live_id!(start)).and_then(|v| v.parse().ok()).unwrap_or(1))
+                    }
+                    else{
+                        None
+                    };
+                    blocks.push(Block::List(number));
+                    block_start = true;
+                }
+                some_id!(li)=>{
+                    // <li> doesn't have to be closed before the next one
+                    let list = blocks.iter().rposition(|b| matches!(b, Block::List(_)));
+                    Self::end_block(tf, cx, &mut blocks, list, |b| matches!(b, Block::Item));
+                    if let Some(Block::List(number)) = list.map(|list| &mut blocks[list]){
+                        let marker = if let Some(number) = number{
+                            *number += 1;
+                            format!("{}.", *number - 1)
+                        }
+                        else{
+                            "•".to_string()
+                        };
+                        tf.begin_list_item(cx, &marker, self.list_item_width);
+                        blocks.push(Block::Item);
+                    }
+                    block_start = true;
+                }
+                some_id!(table)=>{
+                    tf.begin_table(cx, Self::table_columns(&node));
+                    blocks.push(Block::Table);
+                    block_start = true;
+                }
+                some_id!(tr)=>{
+                    let table = blocks.iter().rposition(|b| matches!(b, Block::Table));
+                    Self::end_block(tf, cx, &mut blocks, table, |b| matches!(b, Block::Row));
+                    tf.begin_table_row(cx);
+                    blocks.push(Block::Row);
+                    block_start = true;
+                }
+                some_id!(td) | some_id!(th)=>{
+                    let row = blocks.iter().rposition(|b| matches!(b, Block::Row));
+                    Self::end_block(tf, cx, &mut blocks, row, |b| matches!(b, Block::Cell{..}));
+                    let header = node.open_tag_lc() == Some(live_id!(th));
+                    if header{
+                        tf.push_bold();
+                    }
+                    tf.begin_table_cell(cx);
+                    blocks.push(Block::Cell{header});
+                    block_start = true;
+                }
+                some_id!(span) | some_id!(thead) | some_id!(tbody) | some_id!(html) | some_id!(body)=>(),
                 Some(_)=>{ // custom widget
                     let id = if let Some(id) = node.find_attr_lc(live_id!(id)){
                         LiveId::from_str(id).0
@@ -65,35 +280,132 @@ impl Widget for Html {
                         auto_id
                     };
                     let template = node.open_tag_nc().unwrap();
+                    // tags we don't have a template for just draw what is inside of them
                     if let Some(item) = tf.item(cx, id, template){
                         item.set_text(node.find_text().unwrap_or(""));
                         item.draw_all(cx, scope);
+                        node = node.jump_to_close();
+                        block_start = false;
                     }
-                    node = node.jump_to_close();
                 }
                 _=>()
-            } 
+            }
             match node.close_tag_lc(){
-                some_id!(h1)=>tf.pop_size(),
-                some_id!(b)=>tf.pop_bold(),
-                some_id!(i)=>tf.pop_italic(),
+                some_id!(h1) | some_id!(h2) | some_id!(h3) | some_id!(h4) | some_id!(h5) | some_id!(h6)=>{
+                    tf.pop_size();
+                    tf.pop_bold();
+                    tf.new_paragraph(cx);
+                    block_start = true;
+                }
+                some_id!(p)=>{
+                    tf.new_paragraph(cx);
+                    block_start = true;
+                }
+                some_id!(div)=>{
+                    tf.new_line(cx);
+                    block_start = true;
+                }
+                some_id!(b) | some_id!(strong)=>tf.pop_bold(),
+                some_id!(i) | some_id!(em)=>tf.pop_italic(),
+                some_id!(u)=>tf.pop_underline(),
+                some_id!(s) | some_id!(strike) | some_id!(del)=>tf.pop_strikethrough(),
+                some_id!(code)=>if !in_pre{
+                    tf.pop_inline_code()
+                },
+                some_id!(a)=>{
+                    if let Some(true) = links.pop(){
+                        tf.pop_link();
+                    }
+                }
+                some_id!(blockquote)=>{
+                    Self::end_block(tf, cx, &mut blocks, None, |b| matches!(b, Block::Quote));
+                    block_start = true;
+                }
+                some_id!(pre)=>{
+                    Self::end_block(tf, cx, &mut blocks, None, |b| matches!(b, Block::Pre));
+                    block_start = true;
+                }
+                some_id!(ul) | some_id!(ol)=>{
+                    Self::end_block(tf, cx, &mut blocks, None, |b| matches!(b, Block::List(_)));
+                    block_start = true;
+                }
+                some_id!(li)=>{
+                    Self::end_block(tf, cx, &mut blocks, None, |b| matches!(b, Block::Item));
+                    block_start = true;
+                }
+                some_id!(table)=>{
+                    Self::end_block(tf, cx, &mut blocks, None, |b| matches!(b, Block::Table));
+                    block_start = true;
+                }
+                some_id!(tr)=>{
+                    Self::end_block(tf, cx, &mut blocks, None, |b| matches!(b, Block::Row));
+                    block_start = true;
+                }
+                some_id!(td) | some_id!(th)=>{
+                    Self::end_block(tf, cx, &mut blocks, None, |b| matches!(b, Block::Cell{..}));
+                    block_start = true;
+                }
                 _=>()
             }
             if let Some(text) = node.text(){
-                tf.draw_text(cx, text);
+                if in_pre{
+                    // whitespace is kept in <pre>, a newline right after the tag is skipped
+                    let text = if block_start{text.strip_prefix('\n').unwrap_or(text)} else {text};
+                    let mut lines = text.split('\n').peekable();
+                    let mut first = true;
+                    while let Some(line) = lines.next(){
+                        if !first{
+                            tf.new_line(cx);
+                        }
+                        first = false;
+                        if !line.is_empty(){
+                            tf.draw_text(cx, line);
+                        }
+                        else if lines.peek().is_some(){
+                            tf.draw_text(cx, " ");
+                        }
+                    }
+                    block_start = false;
+                }
+                else{
+                    let text = if block_start{text.trim_start()} else {text};
+                    if !text.is_empty(){
+                        tf.draw_text(cx, text);
+                        block_start = false;
+                    }
+                }
             }
             node = node.walk();
         }
+        Self::end_block(tf, cx, &mut blocks, None, |_| true);
         tf.end(cx);
         DrawStep::done()
-    }  
-     
+    }
+
     fn text(&self)->String{
         self.html.as_ref().to_string()
-    } 
-    
+    }
+
     fn set_text(&mut self, v:&str){
-        self.html = Rc::new(v.to_string())
+        self.html = Rc::new(v.to_string());
+        self.parse();
+    }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Document;
+        node.label = self.text();
     }
-} 
- 
\ No newline at end of file
+}
+
+impl HtmlRef {
+    pub fn link_clicked(&self, actions:&Actions) -> Option<String> {
+        if let Some(inner) = self.borrow(){
+            if let Some(item) = actions.find_widget_action(inner.text_flow.widget_uid()) {
+                if let TextFlowAction::LinkClicked(href) = item.cast() {
+                    return Some(href)
+                }
+            }
+        }
+        None
+    }
+}
@@ -19,7 +19,14 @@ pub struct Image {
     #[live(1.0)] width_scale: f64,
     #[live] fit: ImageFit,
     #[live] source: LiveDependency,
+    // seconds it takes an async loaded image to fade in over the placeholder
+    #[live] fade_in: f64,
     #[rust] texture: Option<Texture>,
+    // the image cache key of an async loaded image, used to pick mips
+    #[rust] image_key: Option<String>,
+    #[rust] loading: bool,
+    #[rust] fade_start: Option<f64>,
+    #[rust] next_frame: NextFrame,
 }
 
 impl ImageCacheImpl for Image {
@@ -29,6 +36,8 @@ impl ImageCacheImpl for Image {
     
     fn set_texture(&mut self, texture: Option<Texture>) {
         self.texture = texture;
+        self.image_key = None;
+        self.loading = false;
     }
 }
 
@@ -43,18 +52,101 @@ impl LiveHook for Image{
 }
 
 impl Widget for Image {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, _scope: &mut Scope) {
+        // the Window feeds the cache, we only look if our image arrived
+        if self.loading {
+            self.check_loaded(cx);
+        }
+        if let Some(ne) = self.next_frame.is_event(event) {
+            let fade_start = *self.fade_start.get_or_insert(ne.time);
+            let fade = ((ne.time - fade_start) / self.fade_in).min(1.0);
+            self.draw_bg.apply_over(cx, live!{fade: (fade)});
+            if fade < 1.0 {
+                self.next_frame = cx.new_next_frame();
+            }
+            self.redraw(cx);
+        }
+    }
+    
     fn draw_walk(&mut self, cx: &mut Cx2d, _scope: &mut Scope, walk: Walk) -> DrawStep {
         self.draw_walk(cx, walk)
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Image;
+        node.label = self.source.as_str().to_string();
+    }
 }
 
 impl Image {
+    // starts showing the cached image under key, the cache has to be loading it already
+    fn begin_async_load(&mut self, cx: &mut Cx, key: &str) {
+        self.image_key = Some(key.to_string());
+        if let Some(texture) = cx.get_global::<ImageCache>().texture(key) {
+            self.texture = Some(texture);
+            self.loading = false;
+            return
+        }
+        self.texture = None;
+        self.loading = true;
+        if self.fade_in > 0.0 {
+            self.draw_bg.apply_over(cx, live!{fade: 0.0});
+        }
+        self.redraw(cx);
+    }
+    
+    fn check_loaded(&mut self, cx: &mut Cx) {
+        let Some(key) = &self.image_key else {return};
+        let cache = cx.get_global::<ImageCache>();
+        match cache.entry(key) {
+            Some(ImageCacheEntry::Loading) => return,
+            Some(ImageCacheEntry::Loaded {..}) => {
+                self.texture = cache.texture(key);
+                if self.fade_in > 0.0 {
+                    self.fade_start = None;
+                    self.next_frame = cx.new_next_frame();
+                }
+                self.redraw(cx);
+            }
+            // the image failed to load, or got evicted before we saw it
+            _ => ()
+        }
+        self.loading = false;
+    }
+    
+    /// Loads a dependency like `crate://self/resources/image.png`, decoding happens on a background thread
+    pub fn load_image_dep_by_path_async(&mut self, cx: &mut Cx, image_path: &str) {
+        self.lazy_create_image_cache(cx);
+        ImageCache::load_dep_async(cx, image_path);
+        self.begin_async_load(cx, image_path);
+    }
+    
+    /// Loads an image file, reading and decoding happens on a background thread
+    pub fn load_image_file_by_path_async(&mut self, cx: &mut Cx, image_path: &str) {
+        self.lazy_create_image_cache(cx);
+        ImageCache::load_file_async(cx, image_path);
+        self.begin_async_load(cx, image_path);
+    }
+    
+    /// Fetches a PNG or JPEG over http, decoding happens on a background thread
+    pub fn load_image_url(&mut self, cx: &mut Cx, url: &str) {
+        self.lazy_create_image_cache(cx);
+        ImageCache::load_url_async(cx, url);
+        self.begin_async_load(cx, url);
+    }
+    
     
     pub fn draw_walk(&mut self, cx: &mut Cx2d, mut walk: Walk) -> DrawStep {
         // alright we get a walk. depending on our aspect ratio
         // we change either nothing, or width or height
         let rect = cx.peek_walk_turtle(walk);
         let dpi = cx.current_dpi_factor();
+        // async loaded images use the smallest mip that still covers the rect
+        if let (Some(key), false) = (&self.image_key, self.loading) {
+            if let Some(texture) = cx.get_global::<ImageCache>().texture_for_size(key, rect.size * dpi) {
+                self.texture = Some(texture);
+            }
+        }
         let (width, height) = if let Some(image_texture) = &self.texture {
             self.draw_bg.draw_vars.set_texture(0, image_texture);
             let (width,height) = image_texture.get_format(cx).vec_width_height().unwrap_or((self.min_width as usize, self.min_height as usize));
@@ -111,6 +203,15 @@ impl ImageRef {
         }
     }
     
+    /// Loads the image file at the given filesystem `image_path` into this `ImageRef`.
+    pub fn load_image_file_by_path(&self, cx: &mut Cx, image_path: &str) -> Result<(), ImageError> {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.load_image_file_by_path(cx, image_path)
+        } else {
+            Ok(()) // preserving existing behavior of silent failures.
+        }
+    }
+    
     /// Loads a JPEG into this `ImageRef` by decoding the given encoded JPEG `data`.
     pub fn load_jpg_from_data(&self, cx: &mut Cx, data: &[u8]) -> Result<(), ImageError> {
         if let Some(mut inner) = self.borrow_mut() {
@@ -129,9 +230,30 @@ impl ImageRef {
         }
     }
     
+    /// Loads a dependency into this `ImageRef` without blocking, see [`Image::load_image_dep_by_path_async`].
+    pub fn load_image_dep_by_path_async(&self, cx: &mut Cx, image_path: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.load_image_dep_by_path_async(cx, image_path)
+        }
+    }
+    
+    /// Loads an image file into this `ImageRef` without blocking, see [`Image::load_image_file_by_path_async`].
+    pub fn load_image_file_by_path_async(&self, cx: &mut Cx, image_path: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.load_image_file_by_path_async(cx, image_path)
+        }
+    }
+    
+    /// Loads an image from a url into this `ImageRef` without blocking, see [`Image::load_image_url`].
+    pub fn load_image_url(&self, cx: &mut Cx, url: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.load_image_url(cx, url)
+        }
+    }
+    
     pub fn set_texture(&self, texture: Option<Texture>) {
         if let Some(mut inner) = self.borrow_mut() {
-            inner.texture = texture
+            inner.set_texture(texture)
         }
     }
     
@@ -74,6 +74,62 @@ impl ImageBuffer {
         texture
     }
     
+    // the texture is stored in the image cache under key
+    fn into_new_cached_texture(self, cx:&mut Cx, key:&str)->Texture{
+        let (width, height) = (self.width, self.height);
+        let texture = self.into_new_texture(cx);
+        cx.get_global::<ImageCache>().insert_loaded(key.to_string(), vec![(texture, width, height)])
+    }
+    
+    /// An image with half the width and height, every pixel is the average of 2x2 pixels
+    pub fn downscale_half(&self) -> ImageBuffer {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0u32; 4];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let sx = (x * 2 + dx).min(self.width - 1);
+                    let sy = (y * 2 + dy).min(self.height - 1);
+                    let pixel = self.data[sy * self.width + sx];
+                    for (c, sum) in sum.iter_mut().enumerate() {
+                        *sum += (pixel >> (c * 8)) & 0xff;
+                    }
+                }
+                data.push(sum.iter().enumerate().fold(0u32, | pixel, (c, sum) | pixel | ((sum / 4) << (c * 8))));
+            }
+        }
+        ImageBuffer {width, height, data}
+    }
+    
+    /// This image followed by downscaled versions of it, down to min_size
+    pub fn into_mips(self, min_size: usize) -> Vec<ImageBuffer> {
+        let mut mips = vec![self];
+        loop {
+            let last = mips.last().unwrap();
+            if last.width.max(last.height) / 2 < min_size || last.width < 2 || last.height < 2 {
+                break
+            }
+            let mip = last.downscale_half();
+            mips.push(mip);
+        }
+        mips
+    }
+    
+    /// Decodes PNG or JPEG data, the format is detected from the data
+    pub fn from_data(data: &[u8]) -> Result<Self, ImageError> {
+        if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+            Self::from_png(data)
+        }
+        else if data.starts_with(&[0xff, 0xd8]) {
+            Self::from_jpg(data)
+        }
+        else {
+            Err(ImageError::UnsupportedFormat)
+        }
+    }
+    
     pub fn from_png(
         data: &[u8]
     ) -> Result<Self, ImageError> {
@@ -114,14 +170,217 @@ impl ImageBuffer {
     }
 }
 
+// the decoder threads send the image with its mips back, or the error message
+struct ImageDecoded {
+    key: String,
+    result: Result<Vec<ImageBuffer>, String>,
+}
+
+pub enum ImageCacheEntry {
+    /// Being fetched or decoded on the thread pool
+    Loading,
+    /// The full size texture first, followed by the mips that halve in size each time
+    Loaded {
+        textures: Vec<(Texture, usize, usize)>,
+        bytes: usize,
+        last_used: u64,
+    },
+    Error,
+}
+
+/// Caches textures by path or url, least recently used images are evicted when the
+/// textures take up more than the byte budget. Async loads are decoded on a thread pool
 pub struct ImageCache {
-    map: HashMap<String, Texture>,
+    map: HashMap<String, ImageCacheEntry>,
+    byte_budget: usize,
+    bytes_used: usize,
+    use_counter: u64,
+    thread_pool: Option<TagThreadPool<String>>,
+    decoded: ToUIReceiver<ImageDecoded>,
+    http_requests: HashMap<LiveId, String>,
 }
 
+// mips are generated until they are smaller than this
+const IMAGE_CACHE_MIN_MIP_SIZE: usize = 64;
+
 impl ImageCache {
     pub fn new() -> Self {
         Self {
             map: HashMap::new(),
+            byte_budget: 256 * 1024 * 1024,
+            bytes_used: 0,
+            use_counter: 0,
+            thread_pool: None,
+            decoded: ToUIReceiver::default(),
+            http_requests: HashMap::new(),
+        }
+    }
+    
+    /// Sets how many bytes of textures the cache keeps around, textures still in use by widgets aren't freed
+    pub fn set_byte_budget(&mut self, byte_budget: usize) {
+        self.byte_budget = byte_budget;
+        self.evict(None);
+    }
+    
+    pub fn byte_budget(&self) -> usize {
+        self.byte_budget
+    }
+    
+    pub fn bytes_used(&self) -> usize {
+        self.bytes_used
+    }
+    
+    pub fn entry(&self, key: &str) -> Option<&ImageCacheEntry> {
+        self.map.get(key)
+    }
+    
+    /// The full size texture, marks the image as recently used
+    pub fn texture(&mut self, key: &str) -> Option<Texture> {
+        self.texture_for_size(key, DVec2 {x: f64::INFINITY, y: f64::INFINITY})
+    }
+    
+    /// The smallest texture that is at least `size` pixels, marks the image as recently used
+    pub fn texture_for_size(&mut self, key: &str, size: DVec2) -> Option<Texture> {
+        self.use_counter += 1;
+        if let Some(ImageCacheEntry::Loaded {textures, last_used, ..}) = self.map.get_mut(key) {
+            *last_used = self.use_counter;
+            let texture = textures.iter().rev().find( | (_, width, height) | {
+                *width as f64 >= size.x && *height as f64 >= size.y
+            }).unwrap_or(&textures[0]);
+            return Some(texture.0.clone())
+        }
+        None
+    }
+    
+    fn insert_loaded(&mut self, key: String, textures: Vec<(Texture, usize, usize)>) -> Texture {
+        let texture = textures[0].0.clone();
+        let bytes = textures.iter().map( | (_, width, height) | width * height * 4).sum();
+        self.use_counter += 1;
+        self.remove(&key);
+        self.bytes_used += bytes;
+        self.map.insert(key.clone(), ImageCacheEntry::Loaded {textures, bytes, last_used: self.use_counter});
+        self.evict(Some(&key));
+        texture
+    }
+    
+    fn remove(&mut self, key: &str) {
+        if let Some(ImageCacheEntry::Loaded {bytes, ..}) = self.map.remove(key) {
+            self.bytes_used -= bytes;
+        }
+    }
+    
+    fn evict(&mut self, keep: Option<&str>) {
+        while self.bytes_used > self.byte_budget {
+            let oldest = self.map.iter().filter_map( | (key, entry) | match entry {
+                ImageCacheEntry::Loaded {last_used, ..} if Some(key.as_str()) != keep => Some((key, *last_used)),
+                _ => None
+            }).min_by_key( | (_, last_used) | *last_used).map( | (key, _) | key.clone());
+            if let Some(oldest) = oldest {
+                self.remove(&oldest);
+            }
+            else {
+                break
+            }
+        }
+    }
+    
+    fn decode_async(cx: &mut Cx, key: String, read: Box<dyn FnOnce() -> Result<Vec<u8>, String> + Send>) {
+        if cx.get_global::<ImageCache>().thread_pool.is_none() {
+            let threads = (cx.cpu_cores() / 2).max(1);
+            let thread_pool = TagThreadPool::new(cx, threads);
+            cx.get_global::<ImageCache>().thread_pool = Some(thread_pool);
+        }
+        let cache = cx.get_global::<ImageCache>();
+        let sender = cache.decoded.sender();
+        cache.thread_pool.as_ref().unwrap().execute_rev(key, move | key | {
+            let result = read().and_then( | data | {
+                ImageBuffer::from_data(&data).map_err( | err | err.to_string())
+            }).map( | image | image.into_mips(IMAGE_CACHE_MIN_MIP_SIZE));
+            let _ = sender.send(ImageDecoded {key, result});
+        });
+    }
+    
+    // returns true if the image is already loaded or loading
+    fn begin_load(cx: &mut Cx, key: &str) -> bool {
+        let cache = cx.get_global::<ImageCache>();
+        match cache.map.get(key) {
+            Some(ImageCacheEntry::Loaded {..}) | Some(ImageCacheEntry::Loading) => true,
+            _ => {
+                cache.map.insert(key.to_string(), ImageCacheEntry::Loading);
+                false
+            }
+        }
+    }
+    
+    /// Loads and decodes a dependency like `crate://self/resources/image.png` on the thread pool
+    pub fn load_dep_async(cx: &mut Cx, image_path: &str) {
+        if Self::begin_load(cx, image_path) {
+            return
+        }
+        let data = cx.get_dependency(image_path).map( | data | data.to_vec());
+        Self::decode_async(cx, image_path.to_string(), Box::new(move || data));
+    }
+    
+    /// Reads and decodes an image file on the thread pool
+    pub fn load_file_async(cx: &mut Cx, image_path: &str) {
+        if Self::begin_load(cx, image_path) {
+            return
+        }
+        let path = image_path.to_string();
+        Self::decode_async(cx, image_path.to_string(), Box::new(move || {
+            std::fs::read(&path).map_err( | err | format!("Cannot read file {} {}", path, err))
+        }));
+    }
+    
+    /// Fetches an image with a GET request, the response is decoded on the thread pool
+    pub fn load_url_async(cx: &mut Cx, url: &str) {
+        if Self::begin_load(cx, url) {
+            return
+        }
+        let request_id = LiveId::from_str(url);
+        cx.get_global::<ImageCache>().http_requests.insert(request_id, url.to_string());
+        cx.http_request(request_id, HttpRequest::new(url.to_string(), HttpMethod::GET));
+    }
+    
+    /// Picks up network responses and decoded images, the Window calls this from its handle_event
+    pub fn handle_event(cx: &mut Cx, event: &Event) {
+        match event {
+            Event::NetworkResponses(responses) => for item in responses {
+                let url = match cx.get_global::<ImageCache>().http_requests.get(&item.request_id) {
+                    Some(url) => url.clone(),
+                    None => continue
+                };
+                let data = match &item.response {
+                    NetworkResponse::HttpResponse(response) if response.status_code == 200 => {
+                        response.get_body().cloned().ok_or_else( || "Empty response".to_string())
+                    }
+                    NetworkResponse::HttpResponse(response) => Err(format!("Http status {}", response.status_code)),
+                    NetworkResponse::HttpRequestError(err) => Err(err.clone()),
+                    NetworkResponse::HttpProgress {..} => continue
+                };
+                cx.get_global::<ImageCache>().http_requests.remove(&item.request_id);
+                Self::decode_async(cx, url, Box::new(move || data));
+            }
+            Event::Signal => {
+                while let Ok(decoded) = cx.get_global::<ImageCache>().decoded.try_recv() {
+                    match decoded.result {
+                        Ok(images) => {
+                            let textures = images.into_iter().map( | image | {
+                                let (width, height) = (image.width, image.height);
+                                (image.into_new_texture(cx), width, height)
+                            }).collect();
+                            cx.get_global::<ImageCache>().insert_loaded(decoded.key, textures);
+                        }
+                        Err(err) => {
+                            error!("ImageCache: Cannot load image {} {}", decoded.key, err);
+                            cx.get_global::<ImageCache>().map.insert(decoded.key, ImageCacheEntry::Error);
+                        }
+                    }
+                    // widgets that handled this event before us check again on the next frame
+                    cx.new_next_frame();
+                }
+            }
+            _ => ()
         }
     }
 }
@@ -167,8 +426,8 @@ pub trait ImageCacheImpl {
         cx: &mut Cx,
         image_path: &str,
     ) -> Result<(), ImageError> {
-        if let Some(texture) = cx.get_global::<ImageCache>().map.get(image_path){
-            self.set_texture(Some(texture.clone()));
+        if let Some(texture) = cx.get_global::<ImageCache>().texture(image_path){
+            self.set_texture(Some(texture));
             Ok(())
         }
         else{
@@ -177,9 +436,7 @@ pub trait ImageCacheImpl {
                     if image_path.ends_with(".jpg") {
                         match ImageBuffer::from_jpg(&*data){
                             Ok(data)=>{
-                                let texture = data.into_new_texture(cx);
-                                cx.get_global::<ImageCache>().map.insert(image_path.to_string(), texture.clone());
-                                self.set_texture(Some(texture));
+                                self.set_texture(Some(data.into_new_cached_texture(cx, image_path)));
                                 Ok(())
                             }
                             Err(err)=>{
@@ -190,9 +447,7 @@ pub trait ImageCacheImpl {
                     } else if image_path.ends_with(".png") {
                         match ImageBuffer::from_png(&*data){
                             Ok(data)=>{
-                                let texture = data.into_new_texture(cx);
-                                cx.get_global::<ImageCache>().map.insert(image_path.to_string(), texture.clone());
-                                self.set_texture(Some(texture));
+                                self.set_texture(Some(data.into_new_cached_texture(cx, image_path)));
                                 Ok(())
                             }
                             Err(err)=>{
@@ -212,4 +467,147 @@ pub trait ImageCacheImpl {
             }
         }
     }
+
+    // loads an image from the filesystem, images are cached by path
+    fn load_image_file_by_path(
+        &mut self,
+        cx: &mut Cx,
+        image_path: &str,
+    ) -> Result<(), ImageError> {
+        if let Some(texture) = cx.get_global::<ImageCache>().texture(image_path){
+            self.set_texture(Some(texture));
+            return Ok(())
+        }
+        let data = match std::fs::read(image_path) {
+            Ok(data) => data,
+            Err(err) => {
+                error!("load_image_file_by_path: Cannot read file {} {}", image_path, err);
+                return Err(ImageError::PathNotFound(image_path.to_string()))
+            }
+        };
+        let image = if image_path.ends_with(".jpg") || image_path.ends_with(".jpeg") {
+            ImageBuffer::from_jpg(&data)
+        } else if image_path.ends_with(".png") {
+            ImageBuffer::from_png(&data)
+        } else {
+            error!("load_image_file_by_path: Image format not supported {}", image_path);
+            return Err(ImageError::UnsupportedFormat)
+        };
+        match image {
+            Ok(image) => {
+                self.set_texture(Some(image.into_new_cached_texture(cx, image_path)));
+                Ok(())
+            }
+            Err(err) => {
+                error!("load_image_file_by_path: Cannot load image from path: {} {}", image_path, err);
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    
+    #[test]
+    fn mips_halve_and_average() {
+        let image = ImageBuffer {
+            width: 4,
+            height: 2,
+            data: vec![
+                0xff000000, 0xff0000ff, 0xff00ff00, 0xff00ff00,
+                0xff0000ff, 0xff000000, 0xff00ff00, 0xff00ff00,
+            ]
+        };
+        let mips = image.into_mips(1);
+        assert_eq!(mips.iter().map( | m | (m.width, m.height)).collect::<Vec<_>>(), vec![(4, 2), (2, 1)]);
+        assert_eq!(mips[1].data, vec![0xff00007f, 0xff00ff00]);
+    }
+    
+    fn new_texture(cx: &mut Cx, width: usize, height: usize) -> Texture {
+        ImageBuffer {width, height, data: vec![0; width * height]}.into_new_texture(cx)
+    }
+    
+    // a 4x4 image takes up 64 bytes
+    fn insert_image(cx: &mut Cx, cache: &mut ImageCache, key: &str) -> Texture {
+        let texture = new_texture(cx, 4, 4);
+        cache.insert_loaded(key.to_string(), vec![(texture, 4, 4)])
+    }
+    
+    fn loaded(cache: &ImageCache, key: &str) -> bool {
+        matches!(cache.entry(key), Some(ImageCacheEntry::Loaded {..}))
+    }
+    
+    #[test]
+    fn evicts_least_recently_used_first() {
+        let mut cx = Cx::new(Box::new( | _, _ | {}));
+        let mut cache = ImageCache::new();
+        cache.set_byte_budget(3 * 64);
+        let _a = insert_image(&mut cx, &mut cache, "a");
+        let b = insert_image(&mut cx, &mut cache, "b");
+        let _c = insert_image(&mut cx, &mut cache, "c");
+        assert_eq!(cache.bytes_used(), 3 * 64);
+        cache.texture("a");
+        let _d = insert_image(&mut cx, &mut cache, "d");
+        assert!(!loaded(&cache, "b"));
+        assert!(loaded(&cache, "a") && loaded(&cache, "c") && loaded(&cache, "d"));
+        assert_eq!(cache.bytes_used(), 3 * 64);
+        cache.set_byte_budget(64);
+        assert!(!loaded(&cache, "a") && !loaded(&cache, "c") && loaded(&cache, "d"));
+        assert_eq!(cache.bytes_used(), 64);
+        // a widget still holding an evicted texture keeps it, new textures don't reuse it
+        let new = new_texture(&mut cx, 4, 4);
+        assert_ne!(new.texture_id(), b.texture_id());
+    }
+    
+    #[test]
+    fn keeps_the_image_being_inserted_over_budget() {
+        let mut cx = Cx::new(Box::new( | _, _ | {}));
+        let mut cache = ImageCache::new();
+        cache.set_byte_budget(10);
+        let _a = insert_image(&mut cx, &mut cache, "a");
+        assert!(loaded(&cache, "a"));
+        let _b = insert_image(&mut cx, &mut cache, "b");
+        assert!(!loaded(&cache, "a") && loaded(&cache, "b"));
+        assert_eq!(cache.bytes_used(), 64);
+        // inserting under the same key replaces the entry and its bytes
+        let _b = insert_image(&mut cx, &mut cache, "b");
+        assert_eq!(cache.bytes_used(), 64);
+    }
+    
+    #[test]
+    fn picks_the_smallest_mip_that_covers_the_size() {
+        let mut cx = Cx::new(Box::new( | _, _ | {}));
+        let mut cache = ImageCache::new();
+        let mips: Vec<(Texture, usize, usize)> = [(256, 128), (128, 64), (64, 32)].into_iter().map( | (width, height) | {
+            (new_texture(&mut cx, width, height), width, height)
+        }).collect();
+        let ids: Vec<TextureId> = mips.iter().map( | (texture, _, _) | texture.texture_id()).collect();
+        cache.insert_loaded("a".to_string(), mips);
+        let id_for = | cache: &mut ImageCache, x: f64, y: f64 | cache.texture_for_size("a", DVec2 {x, y}).unwrap().texture_id();
+        assert_eq!(id_for(&mut cache, 10.0, 10.0), ids[2]);
+        assert_eq!(id_for(&mut cache, 64.0, 32.0), ids[2]);
+        assert_eq!(id_for(&mut cache, 65.0, 32.0), ids[1]);
+        assert_eq!(id_for(&mut cache, 100.0, 100.0), ids[0]);
+        // nothing is big enough, the full size is the best we have
+        assert_eq!(id_for(&mut cache, 1000.0, 10.0), ids[0]);
+        assert_eq!(cache.texture("a").unwrap().texture_id(), ids[0]);
+        assert!(cache.texture_for_size("b", DVec2 {x: 1.0, y: 1.0}).is_none());
+    }
+    
+    #[test]
+    fn loads_an_image_once() {
+        let mut cx = Cx::new(Box::new( | _, _ | {}));
+        cx.set_global(ImageCache::new());
+        assert!(!ImageCache::begin_load(&mut cx, "a"));
+        assert!(matches!(cx.get_global::<ImageCache>().entry("a"), Some(ImageCacheEntry::Loading)));
+        assert!(ImageCache::begin_load(&mut cx, "a"));
+        let texture = new_texture(&mut cx, 4, 4);
+        cx.get_global::<ImageCache>().insert_loaded("a".to_string(), vec![(texture, 4, 4)]);
+        assert!(ImageCache::begin_load(&mut cx, "a"));
+        // failed loads are tried again
+        cx.get_global::<ImageCache>().map.insert("b".to_string(), ImageCacheEntry::Error);
+        assert!(!ImageCache::begin_load(&mut cx, "b"));
+    }
 }
@@ -34,4 +34,9 @@ impl Widget for Label {
     fn set_text(&mut self, v:&str){
         self.text.as_mut_empty().push_str(v);
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Label;
+        node.label = self.text();
+    }
 }
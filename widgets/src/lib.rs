@@ -1,6 +1,7 @@
 pub use makepad_draw::makepad_platform;
 pub use makepad_draw;
 pub use makepad_html;
+pub use makepad_markdown;
 pub use makepad_derive_widget;
 pub use makepad_draw::*;
 pub use makepad_derive_widget::*;
@@ -35,17 +36,21 @@ pub mod window;
 pub mod scroll_shadow;
 pub mod window_menu;
 pub mod html;
+pub mod markdown;
 pub mod text_flow;
+pub mod code_block;
 // Only available on Android at the moment
 // #[cfg(target_os="android")]
 pub mod video;
 pub mod rotated_image;
+pub mod svg;
 pub mod slide_panel;
 pub mod page_flip;
 pub mod keyboard_view;
 pub mod flat_list;
 
 pub mod file_tree;
+pub mod fallback_file_dialog;
 pub mod slides_view;
 pub mod color_picker;
 
@@ -56,6 +61,7 @@ pub mod nav_control;
 pub mod view;
 pub mod widget;
 pub mod widget_match_event;
+pub mod widget_tree;
 
 pub mod touch_gesture;
 
@@ -71,6 +77,7 @@ pub use crate::{
     button::*,
     view::*,
     image::*,
+    svg::*,
     label::*,
     slider::*,
     check_box::*,
@@ -87,6 +94,7 @@ pub use crate::{
     dock::*,
     stack_navigation::*,
     expandable_panel::*,
+    fallback_file_dialog::*,
     window::*,
     tab::TabClosable,
     scroll_bars::{ScrollBars},
@@ -94,6 +102,7 @@ pub use crate::{
     scroll_bar::{ScrollBar},
     slides_view::{SlidesView},
     widget_match_event::WidgetMatchEvent,
+    widget_tree::{WidgetRole, WidgetTreeNode, WidgetTreeRect},
     widget::{
         WidgetSet,
         WidgetSetIterator,
@@ -132,6 +141,7 @@ pub fn live_design(cx: &mut Cx) {
     crate::nav_control::live_design(cx);
     crate::image::live_design(cx);
     crate::rotated_image::live_design(cx);
+    crate::svg::live_design(cx);
     crate::video::live_design(cx);
     crate::view::live_design(cx);
     crate::fold_button::live_design(cx);
@@ -158,6 +168,7 @@ pub fn live_design(cx: &mut Cx) {
     crate::dock::live_design(cx);
     crate::color_picker::live_design(cx);
     crate::file_tree::live_design(cx);
+    crate::fallback_file_dialog::live_design(cx);
     crate::slides_view::live_design(cx);
     crate::tab_close_button::live_design(cx);
     crate::keyboard_view::live_design(cx);
@@ -165,5 +176,7 @@ pub fn live_design(cx: &mut Cx) {
     crate::stack_navigation::live_design(cx);
     crate::expandable_panel::live_design(cx);
     crate::html::live_design(cx);
+    crate::markdown::live_design(cx);
     crate::text_flow::live_design(cx);
+    crate::code_block::live_design(cx);
 }
@@ -37,6 +37,11 @@ impl Widget for LinkLabel {
     fn set_text(&mut self, v:&str){
         self.button.set_text(v);
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Link;
+        node.label = self.text();
+    }
 }
 
 impl LinkLabelRef {
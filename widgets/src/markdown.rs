@@ -0,0 +1,219 @@
+use {
+    crate::{
+        makepad_markdown::*,
+        makepad_derive_widget::*,
+        makepad_draw::*,
+        widget::*,
+        text_flow::{TextFlow, TextFlowAction},
+    },
+};
+
+live_design!{
+    MarkdownBase = {{Markdown}} {
+        flow: RightWrap,
+        paragraph_spacing: 8,
+        list_item_width: 16,
+        quote_layout: {flow: RightWrap, padding: {left: 12, top: 6, right: 6, bottom: 6}}
+        quote_walk: {width: Fill, height: Fit}
+        code_layout: {flow: RightWrap, padding: 8}
+        code_walk: {width: Fill, height: Fit}
+        sep_walk: {width: Fill, height: 9}
+        list_item_layout: {flow: Right}
+        list_item_walk: {width: Fill, height: Fit}
+        table_cell_layout: {flow: RightWrap, padding: 4}
+    }
+}
+
+// Renders markdown on top of TextFlow. Optional templates:
+// code_block: a widget with a text and a language field, like CodeBlock or CodeView, that gets
+//   the fenced code and its language. Without one code is drawn in draw_fixed
+// image: an Image widget used for ![alt](src), otherwise the alt text is drawn
+#[derive(Live, Widget)]
+pub struct Markdown{
+    #[deref] text_flow: TextFlow,
+    #[live] body: String,
+    #[live] list_item_width: f64,
+    #[rust] doc: MarkdownDoc
+}
+
+impl LiveHook for Markdown{
+    fn after_apply_from(&mut self, _cx: &mut Cx, _apply:&mut Apply) {
+        self.doc.update(&self.body);
+    }
+}
+
+struct ListState{
+    number: Option<u64>,
+    tight: bool,
+}
+
+impl Widget for Markdown {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.text_flow.handle_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk:Walk)->DrawStep{
+        let tf = &mut self.text_flow;
+        let doc = &self.doc;
+        let mut lists: Vec<ListState> = Vec::new();
+        let mut header_row = false;
+        tf.begin(cx, walk);
+        for (index, node) in doc.nodes.iter().enumerate(){
+            match node{
+                MarkdownNode::BeginHead{level}=>{
+                    tf.new_line(cx);
+                    tf.push_bold();
+                    tf.push_scale(match level{1=>2.0, 2=>1.6, 3=>1.3, 4=>1.15, _=>1.0});
+                }
+                MarkdownNode::EndHead=>{
+                    tf.pop_size();
+                    tf.pop_bold();
+                    tf.new_paragraph(cx);
+                }
+                MarkdownNode::BeginParagraph=>tf.new_line(cx),
+                MarkdownNode::EndParagraph=>{
+                    // paragraphs in tight lists are not spaced apart
+                    if lists.last().map(|l| l.tight).unwrap_or(false){
+                        tf.new_line(cx);
+                    }
+                    else{
+                        tf.new_paragraph(cx);
+                    }
+                }
+                MarkdownNode::BeginQuote=>tf.begin_quote(cx),
+                MarkdownNode::EndQuote=>{
+                    tf.end_quote(cx);
+                    tf.new_paragraph(cx);
+                }
+                MarkdownNode::BeginList{kind, tight}=>{
+                    tf.new_line(cx);
+                    lists.push(ListState{
+                        number: if let ListKind::Ordered(start) = kind{Some(*start)} else {None},
+                        tight: *tight
+                    });
+                }
+                MarkdownNode::EndList=>{
+                    lists.pop();
+                    if lists.is_empty(){
+                        tf.new_paragraph(cx);
+                    }
+                }
+                MarkdownNode::BeginListItem=>{
+                    let list = lists.last_mut().unwrap();
+                    let marker = if let Some(number) = &mut list.number{
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    else{
+                        "•".to_string()
+                    };
+                    tf.begin_list_item(cx, &marker, self.list_item_width);
+                }
+                MarkdownNode::EndListItem=>tf.end_list_item(cx),
+                MarkdownNode::BeginTable{columns}=>tf.begin_table(cx, *columns),
+                MarkdownNode::EndTable=>{
+                    tf.end_table(cx);
+                    tf.new_paragraph(cx);
+                }
+                MarkdownNode::BeginTableRow{header}=>{
+                    header_row = *header;
+                    if header_row{
+                        tf.push_bold();
+                    }
+                    tf.begin_table_row(cx);
+                }
+                MarkdownNode::EndTableRow=>{
+                    tf.end_table_row(cx);
+                    if header_row{
+                        tf.pop_bold();
+                    }
+                }
+                MarkdownNode::BeginTableCell{..}=>tf.begin_table_cell(cx),
+                MarkdownNode::EndTableCell=>tf.end_table_cell(cx),
+                MarkdownNode::CodeBlock{lang_start, lang_end, start, end}=>{
+                    let code = doc.str(*start, *end);
+                    let language = doc.str(*lang_start, *lang_end);
+                    // the language is applied once, a block that changes it gets a new item
+                    let entry_id = LiveId::from_str_num(language, index as u64).0;
+                    let item = tf.item_with(cx, entry_id, live_id!(code_block), |cx, item|{
+                        item.apply_over(cx, live!{language: (language)});
+                    });
+                    if let Some(item) = item{
+                        item.set_text(code);
+                        tf.begin_block(cx);
+                        item.draw_all(cx, scope);
+                        tf.end_block(cx);
+                    }
+                    else{
+                        tf.begin_code(cx);
+                        for line in code.split('\n'){
+                            // an empty line still has to take up a line
+                            tf.draw_text(cx, if line.is_empty(){" "} else {line});
+                            tf.new_line(cx);
+                        }
+                        tf.end_code(cx);
+                    }
+                    tf.new_paragraph(cx);
+                }
+                MarkdownNode::Separator=>{
+                    tf.sep(cx);
+                    tf.new_paragraph(cx);
+                }
+                MarkdownNode::BeginBold=>tf.push_bold(),
+                MarkdownNode::EndBold=>tf.pop_bold(),
+                MarkdownNode::BeginItalic=>tf.push_italic(),
+                MarkdownNode::EndItalic=>tf.pop_italic(),
+                MarkdownNode::BeginStrikethrough=>tf.push_strikethrough(),
+                MarkdownNode::EndStrikethrough=>tf.pop_strikethrough(),
+                MarkdownNode::BeginLink{href_start, href_end}=>tf.push_link(doc.str(*href_start, *href_end)),
+                MarkdownNode::EndLink=>tf.pop_link(),
+                MarkdownNode::Image{src_start, src_end, alt_start, alt_end}=>{
+                    if let Some(item) = tf.image_item(cx, index as u64, doc.str(*src_start, *src_end), |_,_|{}){
+                        item.draw_all(cx, scope);
+                    }
+                    else{
+                        tf.draw_text(cx, doc.str(*alt_start, *alt_end));
+                    }
+                }
+                MarkdownNode::InlineCode{start, end}=>{
+                    tf.push_inline_code();
+                    tf.draw_text(cx, doc.str(*start, *end));
+                    tf.pop_inline_code();
+                }
+                MarkdownNode::Text{start, end}=>tf.draw_text(cx, doc.str(*start, *end)),
+                MarkdownNode::SoftBreak=>tf.draw_text(cx, " "),
+                MarkdownNode::HardBreak=>tf.new_line(cx),
+            }
+        }
+        tf.end(cx);
+        DrawStep::done()
+    }
+
+    fn text(&self)->String{
+        self.body.clone()
+    }
+
+    // appending to the previous text, like a streamed response, only parses the last block again
+    fn set_text(&mut self, v:&str){
+        self.body = v.to_string();
+        self.doc.update(&self.body);
+    }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Document;
+        node.label = self.text();
+    }
+}
+
+impl MarkdownRef {
+    pub fn link_clicked(&self, actions:&Actions) -> Option<String> {
+        if let Some(inner) = self.borrow(){
+            if let Some(item) = actions.find_widget_action(inner.text_flow.widget_uid()) {
+                if let TextFlowAction::LinkClicked(href) = item.cast() {
+                    return Some(href)
+                }
+            }
+        }
+        None
+    }
+}
@@ -52,6 +52,12 @@ impl WidgetNode for MultiWindow{
         }
     }
     fn walk(&mut self, _cx:&mut Cx) -> Walk {Walk::default()}
+    
+    fn widget_tree_children(&self, cx: &Cx, children: &mut Vec<WidgetTreeNode>) {
+        for (id, window) in self.windows.iter() {
+            children.push(WidgetTreeNode::from_widget(cx, *id, window));
+        }
+    }
         
     fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results:&mut WidgetSet){
         for window in self.windows.values_mut() {
@@ -12,7 +12,45 @@ pub struct NavControl {
     #[live] draw_list: DrawList2d,
     #[live] draw_focus: DrawQuad,
     #[live] draw_text: DrawText,
-    #[rust] _recent_focus: Area,
+    #[live] focus_margin: f64,
+    #[rust] recent_focus: Area,
+    // the focus ring is only shown when focus was moved with the keyboard
+    #[rust] show_focus: bool,
+}
+
+// the index of the stop tab moves to from the focused one, None leaves the focus alone
+fn next_tab_stop(len: usize, focused: Option<usize>, has_key_focus: bool, in_scope: bool, backwards: bool) -> Option<usize> {
+    if len == 0 {
+        return None
+    }
+    match focused {
+        Some(index) if backwards => Some((index + len - 1) % len),
+        Some(index) => Some((index + 1) % len),
+        // don't take focus away from something that isn't a nav stop, like a code editor,
+        // unless a focus scope like a modal is open
+        None if has_key_focus && !in_scope => None,
+        None if backwards => Some(len - 1),
+        None => Some(0)
+    }
+}
+
+// the next button or checkbox in the same scroll area as the focused stop, like items in a list
+fn next_arrow_stop(stops: &[(NavStop, Vec<Area>)], index: usize, backwards: bool) -> Option<usize> {
+    let (stop, stack) = stops.get(index) ?;
+    let is_item = | role: NavRole | matches!(role, NavRole::Button | NavRole::CheckBox);
+    if !is_item(stop.role) || stack.len() < 2 {
+        return None
+    }
+    let same_list = | i: &usize | {
+        let (other, other_stack) = &stops[*i];
+        is_item(other.role) && other_stack.len() == stack.len() && other_stack[..stack.len() - 1] == stack[..stack.len() - 1]
+    };
+    if backwards {
+        (0..index).rev().find(same_list)
+    }
+    else {
+        (index + 1..stops.len()).find(same_list)
+    }
 }
 
 impl NavControl {
@@ -30,54 +68,116 @@ impl NavControl {
         }
     }
     
+    fn focus_stop(&mut self, cx: &mut Cx, area: Area, scroll_stack: Vec<Area>) {
+        Self::send_trigger_to_scroll_stack(cx, scroll_stack);
+        cx.set_key_focus(area);
+        self.recent_focus = area;
+        self.show_focus = true;
+        self.draw_list.redraw(cx);
+    }
+    
+    fn tab(&mut self, cx: &mut Cx, root: DrawListId, backwards: bool) {
+        let (mut stops, in_scope) = Cx2d::nav_stops(cx, root);
+        let focused = stops.iter().position( | (stop, _) | cx.has_key_focus(stop.area));
+        let Some(next) = next_tab_stop(stops.len(), focused, !cx.key_focus().is_empty(), in_scope, backwards) else {
+            return
+        };
+        let (stop, scroll_stack) = stops.swap_remove(next);
+        self.focus_stop(cx, stop.area, scroll_stack);
+    }
+    
+    // arrow keys move between buttons and checkboxes that share the same scroll area, like items in a list
+    fn arrow(&mut self, cx: &mut Cx, root: DrawListId, backwards: bool) {
+        let (stops, _) = Cx2d::nav_stops(cx, root);
+        let Some(index) = stops.iter().position( | (stop, _) | cx.has_key_focus(stop.area)) else {
+            return
+        };
+        if let Some(next) = next_arrow_stop(&stops, index, backwards) {
+            let (stop, scroll_stack) = &stops[next];
+            self.focus_stop(cx, stop.area, scroll_stack.clone());
+        }
+    }
+    
     pub fn handle_event(&mut self, cx: &mut Cx, event: &Event, root: DrawListId) {
         match event {
-            Event::KeyDown(ke) => match ke.key_code {
-                KeyCode::Tab => {
-                    if ke.modifiers.shift {
-                        let mut prev_area = Area::Empty;
-                        if let Some((prev_area, scroll_stack)) = Cx2d::iterate_nav_stops(cx, root, | cx, stop | {
-                            if cx.has_key_focus(stop.area) {
-                                return Some(prev_area);
-                            }
-                            prev_area = stop.area;
-                            None
-                        }) {
-                            if !prev_area.is_empty() {
-                                Self::send_trigger_to_scroll_stack(cx, scroll_stack);
-                                cx.set_key_focus(prev_area);
-                            }
-                        }
-                    }
-                    else {
-                        let mut next_stop = false;
-                        if let Some((next_area, scroll_stack)) = Cx2d::iterate_nav_stops(cx, root, | cx, stop | {
-                            if next_stop {
-                                return Some(stop.area)
-                            }
-                            if cx.has_key_focus(stop.area) {
-                                next_stop = true;
-                            }
-                            None
-                        }) {
-                            Self::send_trigger_to_scroll_stack(cx, scroll_stack);
-                            cx.set_key_focus(next_area);
-                        }
-                    }
+            Event::KeyDown(ke) => {
+                if ke.modifiers.control || ke.modifiers.logo || ke.modifiers.alt {
+                    return
+                }
+                match ke.key_code {
+                    KeyCode::Tab => self.tab(cx, root, ke.modifiers.shift),
+                    KeyCode::ArrowUp | KeyCode::ArrowLeft => self.arrow(cx, root, true),
+                    KeyCode::ArrowDown | KeyCode::ArrowRight => self.arrow(cx, root, false),
+                    _ => ()
                 }
-                _ => ()
-            },
+            }
+            Event::MouseDown(_) | Event::TouchUpdate(_) => if self.show_focus {
+                self.show_focus = false;
+                self.draw_list.redraw(cx);
+            }
             _ => ()
         }
     }
     
     pub fn draw(&mut self, cx: &mut Cx2d) {
-        if !self.draw_list.begin(cx, Walk::default()).is_redrawing() {
-            return
+        self.draw_list.begin_overlay_last(cx);
+        if self.show_focus && cx.has_key_focus(self.recent_focus) && self.recent_focus.is_valid(cx) {
+            let rect = self.recent_focus.rect(cx);
+            let m = self.focus_margin;
+            self.draw_focus.draw_abs(cx, Rect {
+                pos: rect.pos - dvec2(m, m),
+                size: rect.size + dvec2(2.0 * m, 2.0 * m)
+            });
         }
-        
         self.draw_list.end(cx);
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabs_around_the_stops() {
+        assert_eq!(next_tab_stop(0, None, false, false, false), None);
+        assert_eq!(next_tab_stop(3, Some(0), true, false, false), Some(1));
+        assert_eq!(next_tab_stop(3, Some(2), true, false, false), Some(0));
+        assert_eq!(next_tab_stop(3, Some(0), true, false, true), Some(2));
+        assert_eq!(next_tab_stop(3, None, false, false, false), Some(0));
+        assert_eq!(next_tab_stop(3, None, false, false, true), Some(2));
+        // something else has the focus, only a scope takes it away
+        assert_eq!(next_tab_stop(3, None, true, false, false), None);
+        assert_eq!(next_tab_stop(3, None, true, true, false), Some(0));
+    }
 
+    #[test]
+    fn arrows_between_items_of_the_same_list() {
+        let mut cx = Cx::new(Box::new( | _, _ | {}));
+        let draw_list = cx.draw_lists.alloc();
+        let area = | id: usize | Area::Rect(RectArea {draw_list_id: draw_list.id(), rect_id: id, redraw_id: 0});
+        let stop = | id: usize, role: NavRole, stack: &[usize] | {
+            let stop = NavStop {role, order: NavOrder::Default, margin: Margin::default(), area: area(id)};
+            (stop, stack.iter().map( | id | area(*id)).collect::<Vec<_>>())
+        };
+        let stops = vec![
+            stop(0, NavRole::Button, &[0]),
+            stop(1, NavRole::Button, &[100, 1]),
+            stop(2, NavRole::TextInput, &[100, 2]),
+            stop(3, NavRole::CheckBox, &[100, 3]),
+            stop(4, NavRole::Button, &[100, 101, 4]),
+            stop(5, NavRole::Button, &[200, 5]),
+            stop(6, NavRole::Button, &[100, 6]),
+        ];
+        assert_eq!(next_arrow_stop(&stops, 1, false), Some(3));
+        assert_eq!(next_arrow_stop(&stops, 3, false), Some(6));
+        assert_eq!(next_arrow_stop(&stops, 6, true), Some(3));
+        assert_eq!(next_arrow_stop(&stops, 1, true), None);
+        assert_eq!(next_arrow_stop(&stops, 6, false), None);
+        // text inputs keep their arrow keys, and stops outside a scroll area have no list
+        assert_eq!(next_arrow_stop(&stops, 2, false), None);
+        assert_eq!(next_arrow_stop(&stops, 0, false), None);
+        assert_eq!(next_arrow_stop(&stops, 5, false), None);
+        assert_eq!(next_arrow_stop(&stops, 4, true), None);
+        assert_eq!(next_arrow_stop(&stops, 9, false), None);
+    }
+}
@@ -97,6 +97,16 @@ impl WidgetNode for PageFlip{
     fn redraw(&mut self, cx: &mut Cx){
         self.area.redraw(cx)
     }
+    
+    fn area(&self) -> Area{
+        self.area
+    }
+    
+    fn widget_tree_children(&self, cx: &Cx, children: &mut Vec<WidgetTreeNode>) {
+        if let Some(node) = self.pages.get(&self.active_page).and_then(|page| page.widget_tree_node(cx, self.active_page)) {
+            children.push(node);
+        }
+    }
         
     fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results: &mut WidgetSet) {
         if let Some(page) = self.pages.get_mut(&path[0]) {
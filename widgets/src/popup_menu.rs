@@ -140,6 +140,8 @@ impl PopupMenu {
     
     pub fn begin(&mut self, cx: &mut Cx2d) {
         self.draw_list.begin_overlay_reuse(cx);
+        // keep tab navigation inside the menu while it is open
+        cx.add_nav_scope();
         
         cx.begin_pass_sized_turtle(Layout::flow_down());
         
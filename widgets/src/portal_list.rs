@@ -73,6 +73,15 @@ pub struct PortalList {
     #[rust] detect_tail_in_draw: bool,
     #[live(false)] auto_tail: bool,
     #[rust(false)] tail_range: bool,
+    // a fixed number of columns turns the list into a grid, min_cell_size fits as many as there is room for
+    #[live(1usize)] columns: usize,
+    #[live] min_cell_size: f64,
+    #[rust(1usize)] grid_columns: usize,
+    #[rust] cell_size: f64,
+    #[rust] grid_cell: Option<(usize, usize)>,
+    // scroll_to_item aligns the first item once its size is known
+    #[rust] align_first: Option<f64>,
+    #[rust] nav_scroll_index: Option<NavScrollIndex>,
     
     #[rust] templates: ComponentMap<LiveId, LivePtr>,
     #[rust] items: ComponentMap<(usize, LiveId), WidgetRef>,
@@ -131,11 +140,45 @@ impl LiveHook for PortalList {
     }
 }
 
+// the number of rows `columns` wide it takes to show the items from range_start up to range_end
+fn row_count(range_start: usize, range_end: usize, columns: usize) -> usize {
+    range_end.saturating_sub(range_start).div_ceil(columns.max(1))
+}
+
+// the first item of the row `id` is on, rows start at range_start
+fn row_start(id: usize, range_start: usize, columns: usize) -> usize {
+    let columns = columns.max(1);
+    range_start + id.saturating_sub(range_start) / columns * columns
+}
+
+// an item id moved into range_start..range_end, or range_start when the range is empty
+fn clamp_to_range(id: usize, range_start: usize, range_end: usize) -> usize {
+    id.min(range_end.max(1) - 1).max(range_start)
+}
+
 impl PortalList {
     
     fn begin(&mut self, cx: &mut Cx2d, walk: Walk) {
         cx.begin_turtle(walk, self.layout);
+        self.nav_scroll_index = Some(cx.add_begin_scroll());
         self.draw_align_list.clear();
+        self.grid_cell = None;
+        let viewport = cx.turtle().padded_rect();
+        let cross = match self.vec_index {
+            Vec2Index::Y => viewport.size.x,
+            Vec2Index::X => viewport.size.y,
+        };
+        self.grid_columns = if self.min_cell_size > 0.0 && cross > 0.0 {
+            (cross / self.min_cell_size).floor().max(1.0) as usize
+        }
+        else {
+            self.columns.max(1)
+        };
+        self.cell_size = cross / self.grid_columns as f64;
+        // the first item has to start a row
+        if self.grid_columns > 1 {
+            self.first_id = row_start(self.first_id, self.range_start, self.grid_columns);
+        }
     }
     
     fn end(&mut self, cx: &mut Cx2d) {
@@ -152,6 +195,13 @@ impl PortalList {
                 list.sort_by( | a, b | a.index.cmp(&b.index));
                 let first_index = list.iter().position( | v | v.index == self.first_id).unwrap();
                 
+                if let Some(align) = self.align_first.take() {
+                    let size = list[first_index].size.index(vi);
+                    self.first_scroll = (viewport.size.index(vi) - size) * align;
+                    // the items in front of it get drawn next frame
+                    self.area.redraw(cx);
+                }
+                
                 // find the position of the first item in our set
                 
                 let mut first_pos = self.first_scroll;
@@ -287,16 +337,78 @@ impl PortalList {
                 self.tail_range = true;
             }
         }
-        let total_views = (self.range_end - self.range_start) as f64 / self.view_window as f64;
-        self.scroll_bar.draw_scroll_bar(cx, ScrollAxis::Vertical, rect, dvec2(100.0, rect.size.y * total_views));
+        let total_views = self.row_count() as f64 / self.view_window as f64;
+        match vi {
+            Vec2Index::Y => self.scroll_bar.draw_scroll_bar(cx, ScrollAxis::Vertical, rect, dvec2(100.0, rect.size.y * total_views)),
+            Vec2Index::X => self.scroll_bar.draw_scroll_bar(cx, ScrollAxis::Horizontal, rect, dvec2(rect.size.x * total_views, 100.0)),
+        };
         if !self.keep_invisible{
             self.items.retain_visible();
         }
         cx.end_turtle_with_area(&mut self.area);
+        cx.add_end_scroll(self.nav_scroll_index.take().unwrap(), self.area);
+    }
+    
+    // the walk of a row of items, which is a column when scrolling horizontally
+    fn row_walk(&self, viewport: Rect, pos: f64) -> Walk {
+        let abs_pos = viewport.pos + DVec2::from_index_pair(self.vec_index, pos, 0.0);
+        let (width, height) = match self.vec_index {
+            Vec2Index::Y => (Size::Fill, Size::Fit),
+            Vec2Index::X => (Size::Fit, Size::Fill),
+        };
+        Walk {
+            abs_pos: Some(abs_pos),
+            margin: Default::default(),
+            width,
+            height
+        }
+    }
+    
+    fn row_layout(&self) -> Layout {
+        match (self.vec_index, self.grid_columns > 1) {
+            (Vec2Index::Y, false) | (Vec2Index::X, true) => Layout::flow_down(),
+            (Vec2Index::X, false) | (Vec2Index::Y, true) => Layout::flow_right(),
+        }
+    }
+    
+    fn begin_grid_cell(&self, cx: &mut Cx2d) {
+        let walk = match self.vec_index {
+            Vec2Index::Y => Walk::size(Size::Fixed(self.cell_size), Size::Fit),
+            Vec2Index::X => Walk::size(Size::Fit, Size::Fixed(self.cell_size)),
+        };
+        cx.begin_turtle(walk, Layout::flow_down());
+    }
+    
+    // the number of rows in the item range
+    fn row_count(&self) -> usize {
+        row_count(self.range_start, self.range_end, self.grid_columns)
     }
     
     pub fn next_visible_item(&mut self, cx: &mut Cx2d) -> Option<usize> {
+        if self.grid_columns <= 1 {
+            return self.next_visible_row(cx)
+        }
+        // grids walk the rows, and the items in a row in turn
+        if let Some((row, column)) = self.grid_cell {
+            cx.end_turtle();
+            if column + 1 < self.grid_columns {
+                self.grid_cell = Some((row, column + 1));
+                self.begin_grid_cell(cx);
+                return Some(row + column + 1)
+            }
+        }
+        if let Some(row) = self.next_visible_row(cx) {
+            self.grid_cell = Some((row, 0));
+            self.begin_grid_cell(cx);
+            return Some(row)
+        }
+        self.grid_cell = None;
+        None
+    }
+    
+    fn next_visible_row(&mut self, cx: &mut Cx2d) -> Option<usize> {
         let vi = self.vec_index;
+        let step = self.grid_columns;
         if let Some(draw_state) = self.draw_state.get() {
             match draw_state {
                 ListDrawState::Begin => {
@@ -307,12 +419,7 @@ impl PortalList {
                         viewport,
                     });
                     
-                    cx.begin_turtle(Walk {
-                        abs_pos: Some(dvec2(viewport.pos.x, viewport.pos.y + self.first_scroll)),
-                        margin: Default::default(),
-                        width: Size::Fill,
-                        height: Size::Fit
-                    }, Layout::flow_down());
+                    cx.begin_turtle(self.row_walk(viewport, self.first_scroll), self.row_layout());
                     return Some(self.first_id)
                 }
                 ListDrawState::Down {index, pos, viewport} | ListDrawState::DownAgain {index, pos, viewport} => {
@@ -329,20 +436,15 @@ impl PortalList {
                     
                     if !did_draw || pos + rect.size.index(vi) > viewport.size.index(vi) {
                         // lets scan upwards
-                        if self.first_id>0 && !is_down_again {
+                        if self.first_id >= step && !is_down_again {
                             self.draw_state.set(ListDrawState::Up {
-                                index: self.first_id - 1,
+                                index: self.first_id - step,
                                 pos: self.first_scroll,
                                 hit_bottom: index >= self.range_end,
                                 viewport
                             });
-                            cx.begin_turtle(Walk {
-                                abs_pos: Some(dvec2(viewport.pos.x, viewport.pos.y)),
-                                margin: Default::default(),
-                                width: Size::Fill,
-                                height: Size::Fit
-                            }, Layout::flow_down());
-                            return Some(self.first_id - 1);
+                            cx.begin_turtle(self.row_walk(viewport, 0.0), self.row_layout());
+                            return Some(self.first_id - step);
                         }
                         else {
                             self.draw_state.set(ListDrawState::End {viewport});
@@ -351,25 +453,20 @@ impl PortalList {
                     }
                     if is_down_again {
                         self.draw_state.set(ListDrawState::DownAgain {
-                            index: index + 1,
+                            index: index + step,
                             pos: pos + rect.size.index(vi),
                             viewport
                         });
                     }
                     else {
                         self.draw_state.set(ListDrawState::Down {
-                            index: index + 1,
+                            index: index + step,
                             pos: pos + rect.size.index(vi),
                             viewport
                         });
                     }
-                    cx.begin_turtle(Walk {
-                        abs_pos: Some(dvec2(viewport.pos.x, viewport.pos.y + pos + rect.size.index(vi))),
-                        margin: Default::default(),
-                        width: Size::Fill,
-                        height: Size::Fit
-                    }, Layout::flow_down());
-                    return Some(index + 1)
+                    cx.begin_turtle(self.row_walk(viewport, pos + rect.size.index(vi)), self.row_layout());
+                    return Some(index + step)
                 }
                 ListDrawState::Up {index, pos, hit_bottom, viewport} => {
                     let did_draw = cx.turtle_has_align_items();
@@ -391,43 +488,33 @@ impl PortalList {
                                 // lets sum up all the items
                                 let total_height: f64 = self.draw_align_list.iter().map( | v | v.size.index(vi)).sum();
                                 self.draw_state.set(ListDrawState::DownAgain {
-                                    index: last_index + 1,
+                                    index: last_index + step,
                                     pos: total_height,
                                     viewport
                                 });
-                                cx.begin_turtle(Walk {
-                                    abs_pos: Some(dvec2(viewport.pos.x, viewport.pos.y + total_height)),
-                                    margin: Default::default(),
-                                    width: Size::Fill,
-                                    height: Size::Fit
-                                }, Layout::flow_down());
-                                return Some(last_index + 1);
+                                cx.begin_turtle(self.row_walk(viewport, total_height), self.row_layout());
+                                return Some(last_index + step);
                             }
                         }
                         self.draw_state.set(ListDrawState::End {viewport});
                         return None
                     }
                     
-                    if !did_draw || pos < if hit_bottom {-viewport.size.index(vi)} else {0.0} {
+                    if !did_draw || index < step || pos < if hit_bottom {-viewport.size.index(vi)} else {0.0} {
                         self.draw_state.set(ListDrawState::End {viewport});
                         return None
                     }
                     
                     self.draw_state.set(ListDrawState::Up {
-                        index: index - 1,
+                        index: index - step,
                         hit_bottom,
                         pos: pos - rect.size.index(vi),
                         viewport
                     });
                     
-                    cx.begin_turtle(Walk {
-                        abs_pos: Some(dvec2(viewport.pos.x, viewport.pos.y)),
-                        margin: Default::default(),
-                        width: Size::Fill,
-                        height: Size::Fit
-                    }, Layout::flow_down());
+                    cx.begin_turtle(self.row_walk(viewport, 0.0), self.row_layout());
                     
-                    return Some(index - 1);
+                    return Some(index - step);
                 }
                 _ => ()
             }
@@ -476,6 +563,17 @@ impl PortalList {
         self.items.contains_key(&(entry_id, template))
     }
     
+    /// Scrolls the item into view at `align` of the viewport, 0.0 is the top (or left), 0.5 the center and 1.0 the bottom.
+    pub fn scroll_to_item(&mut self, cx: &mut Cx, entry_id: usize, align: f64) {
+        self.first_id = clamp_to_range(entry_id, self.range_start, self.range_end);
+        self.first_scroll = 0.0;
+        self.align_first = if align > 0.0 {Some(align.min(1.0))} else {None};
+        self.tail_range = false;
+        self.scroll_state = ScrollState::Stopped;
+        self.update_scroll_bar(cx);
+        self.area.redraw(cx);
+    }
+    
     pub fn set_item_range(&mut self, cx: &mut Cx, range_start: usize, range_end: usize) {
         self.range_start = range_start;
         if self.range_end != range_end {
@@ -489,7 +587,8 @@ impl PortalList {
     }
     
     pub fn update_scroll_bar(&mut self, cx: &mut Cx) {
-        let scroll_pos = ((self.first_id - self.range_start) as f64 / ((self.range_end - self.range_start).max(self.view_window + 1) - self.view_window) as f64) * self.scroll_bar.get_scroll_view_total();
+        let first_row = self.first_id.saturating_sub(self.range_start) / self.grid_columns;
+        let scroll_pos = (first_row as f64 / (self.row_count().max(self.view_window + 1) - self.view_window) as f64) * self.scroll_bar.get_scroll_view_total();
         // move the scrollbar to the right 'top' position
         self.scroll_bar.set_scroll_pos_no_action(cx, scroll_pos);
     }
@@ -504,6 +603,26 @@ impl PortalList {
         }
         self.update_scroll_bar(cx);
     }
+    
+    // scrolls a focussed item that was reached with the keyboard into view
+    fn scroll_focus_into_view(&mut self, cx: &mut Cx, item: Area) {
+        let vi = self.vec_index;
+        let view = self.area.rect(cx);
+        let item = item.rect(cx);
+        let (view_pos, view_size) = (view.pos.index(vi), view.size.index(vi));
+        let (item_pos, item_size) = (item.pos.index(vi), item.size.index(vi));
+        let delta = if item_pos < view_pos {
+            view_pos - item_pos
+        }
+        else if item_pos + item_size > view_pos + view_size {
+            (view_pos + view_size - item_pos - item_size).max(view_pos - item_pos)
+        }
+        else {
+            return
+        };
+        self.delta_top_scroll(cx, delta, true);
+        self.area.redraw(cx);
+    }
 }
 
 
@@ -512,6 +631,12 @@ impl Widget for PortalList {
     fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
         let uid = self.widget_uid();
         
+        if let Event::Trigger(te) = event {
+            if let Some(trigger) = te.triggers.get(&self.area).and_then( | t | t.iter().find( | t | t.id == live_id!(scroll_focus_nav))) {
+                self.scroll_focus_into_view(cx, trigger.from);
+            }
+        }
+        
         let mut scroll_to = None;
         self.scroll_bar.handle_event_with(cx, event, &mut | _cx, action | {
             // snap the scrollbar to a top-index with scroll_pos 0
@@ -529,7 +654,7 @@ impl Widget for PortalList {
                 self.tail_range = false;
             }
 
-            self.first_id = ((scroll_to / self.scroll_bar.get_scroll_view_visible()) * self.view_window as f64) as usize;
+            self.first_id = ((scroll_to / self.scroll_bar.get_scroll_view_visible()) * self.view_window as f64) as usize * self.grid_columns;
             self.first_scroll = 0.0;
             cx.widget_action(uid, &scope.path, PortalListAction::Scroll);
             self.area.redraw(cx);
@@ -579,6 +704,11 @@ impl Widget for PortalList {
             _=>()
         }
         let vi = self.vec_index;
+        let step = self.grid_columns;
+        let (back_key, forward_key) = match vi {
+            Vec2Index::Y => (KeyCode::ArrowUp, KeyCode::ArrowDown),
+            Vec2Index::X => (KeyCode::ArrowLeft, KeyCode::ArrowRight),
+        };
         let is_scroll = if let Event::Scroll(_) = event {true} else {false};
         if self.scroll_bar.is_area_captured(cx){
             self.scroll_state = ScrollState::Stopped;
@@ -591,7 +721,9 @@ impl Widget for PortalList {
                     }
                     self.detect_tail_in_draw = true;
                     self.scroll_state = ScrollState::Stopped;
-                    self.delta_top_scroll(cx, -e.scroll.index(vi), true);
+                    // horizontal lists scroll with the mouse wheel as well
+                    let scroll = if vi == Vec2Index::X && e.scroll.x == 0.0 {e.scroll.y} else {e.scroll.index(vi)};
+                    self.delta_top_scroll(cx, -scroll, true);
                     cx.widget_action(uid, &scope.path, PortalListAction::Scroll);
                     self.area.redraw(cx);
                 },
@@ -614,14 +746,14 @@ impl Widget for PortalList {
                         self.area.redraw(cx);
                     },
                     KeyCode::PageUp => {
-                        self.first_id = self.first_id.max(self.view_window) - self.view_window;
+                        self.first_id = self.first_id.max(self.view_window * step) - self.view_window * step;
                         self.first_scroll = 0.0;
                         self.tail_range = false;
                         self.update_scroll_bar(cx);
                         self.area.redraw(cx);
                     },
                     KeyCode::PageDown => {
-                        self.first_id += self.view_window;
+                        self.first_id += self.view_window * step;
                         self.first_scroll = 0.0;
                         if self.first_id >= self.range_end.max(1) {
                             self.first_id = self.range_end.max(1) - 1;
@@ -630,8 +762,8 @@ impl Widget for PortalList {
                         self.update_scroll_bar(cx);
                         self.area.redraw(cx);
                     },
-                    KeyCode::ArrowDown => {
-                        self.first_id += 1;
+                    key if key == forward_key => {
+                        self.first_id += step;
                         if self.first_id >= self.range_end.max(1) {
                             self.first_id = self.range_end.max(1) - 1;
                         }
@@ -640,9 +772,9 @@ impl Widget for PortalList {
                         self.update_scroll_bar(cx);
                         self.area.redraw(cx);
                     },
-                    KeyCode::ArrowUp => {
-                        if self.first_id > 0 {
-                            self.first_id -= 1;
+                    key if key == back_key => {
+                        if self.first_id >= step {
+                            self.first_id -= step;
                             if self.first_id < self.range_start {
                                 self.first_id = self.range_start;
                             }
@@ -745,6 +877,22 @@ impl Widget for PortalList {
         }
         DrawStep::done()
     }
+
+    fn describe_widget(&self, cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::List;
+        // the visible items, the children come from the list and not from the widget fields
+        let mut items: Vec<_> = self.items.iter().collect();
+        items.sort_by_key( | ((item_id, _), _) | *item_id);
+        for ((item_id, template), item) in items {
+            if let Some(mut child) = item.widget_tree_node(cx, *template) {
+                child.id = item_id.to_string();
+                if child.role == WidgetRole::Generic {
+                    child.role = WidgetRole::ListItem;
+                }
+                node.children.push(child);
+            }
+        }
+    }
 }
 
 impl PortalListRef {
@@ -770,6 +918,13 @@ impl PortalListRef {
         }
     }
     
+    /// A convenience wrapper around [`PortalList::scroll_to_item()`].
+    pub fn scroll_to_item(&self, cx: &mut Cx, entry_id: usize, align: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.scroll_to_item(cx, entry_id, align)
+        }
+    }
+    
     pub fn set_tail_range(&self, tail_range: bool) {
         if let Some(mut inner) = self.borrow_mut() {
             inner.tail_range = tail_range
@@ -838,3 +993,42 @@ impl PortalListSet {
         set
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_rows_of_the_item_range() {
+        assert_eq!(row_count(0, 10, 1), 10);
+        assert_eq!(row_count(0, 10, 3), 4);
+        assert_eq!(row_count(0, 9, 3), 3);
+        assert_eq!(row_count(5, 12, 3), 3);
+        assert_eq!(row_count(4, 4, 3), 0);
+        assert_eq!(row_count(8, 4, 3), 0);
+        assert_eq!(row_count(0, 7, 0), 7);
+    }
+
+    #[test]
+    fn aligns_the_first_item_to_a_row_of_the_range() {
+        // rows start at range_start, not at multiples of the column count
+        assert_eq!(row_start(7, 5, 3), 5);
+        assert_eq!(row_start(8, 5, 3), 8);
+        assert_eq!(row_start(10, 5, 3), 8);
+        assert_eq!(row_start(11, 5, 3), 11);
+        assert_eq!(row_start(7, 0, 3), 6);
+        assert_eq!(row_start(2, 5, 3), 5);
+        assert_eq!(row_start(7, 5, 1), 7);
+    }
+
+    #[test]
+    fn clamps_scroll_targets_to_the_range() {
+        assert_eq!(clamp_to_range(3, 0, 10), 3);
+        assert_eq!(clamp_to_range(10, 0, 10), 9);
+        assert_eq!(clamp_to_range(usize::MAX, 2, 10), 9);
+        assert_eq!(clamp_to_range(0, 2, 10), 2);
+        assert_eq!(clamp_to_range(4, 0, 0), 0);
+        assert_eq!(clamp_to_range(9, 5, 5), 5);
+        assert_eq!(clamp_to_range(9, 0, usize::MAX), 9);
+    }
+}
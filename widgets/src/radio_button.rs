@@ -90,6 +90,10 @@ impl Widget for RadioButtonGroup {
         self.draw_walk(cx, walk);
         DrawStep::done()
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Group;
+    }
 }
 
 impl Widget for RadioButton {
@@ -127,6 +131,12 @@ impl Widget for RadioButton {
         self.draw_walk(cx, walk);
         DrawStep::done()
     }
+
+    fn describe_widget(&self, cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::RadioButton;
+        node.label = self.label.clone();
+        node.checked = Some(self.animator_in_state(cx, id!(selected.on)));
+    }
 }
 
 impl RadioButtonRef{
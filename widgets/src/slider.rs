@@ -52,6 +52,7 @@ pub struct Slider {
     #[live] step: f64,
     
     #[live] bind: String,
+    #[live] tab_index: i64,
     
     #[rust] pub value: f64,
     #[rust] pub dragging: Option<f64>,
@@ -111,6 +112,7 @@ impl Slider {
         
         if let Some(mut dw) = cx.defer_walk(self.label_walk) {
             //, (self.value*100.0) as usize);
+            self.text_input.tab_index = self.tab_index;
             let walk = self.text_input.walk(cx);
             self.text_input.draw_walk_text_input(cx, walk);
             self.draw_text.draw_walk(cx, dw.resolve(cx), self.label_align, &self.text);
@@ -118,6 +120,22 @@ impl Slider {
         
         self.draw_slider.end(cx);
     }
+    
+    // arrow keys on the focussed value step the slider, shift steps 10 times as far
+    fn handle_key_step(&mut self, cx: &mut Cx, ke: &KeyEvent, scope: &mut Scope) {
+        let dir = match ke.key_code {
+            KeyCode::ArrowUp => 1.0,
+            KeyCode::ArrowDown => -1.0,
+            _ => return
+        };
+        let step = if self.step != 0.0 {self.step} else {(self.max - self.min) / 100.0};
+        let step = if ke.modifiers.shift {step * 10.0} else {step};
+        if self.set_internal((self.to_external() + dir * step).max(self.min).min(self.max)) {
+            self.draw_slider.redraw(cx);
+            self.update_text_input_and_redraw(cx);
+            cx.widget_action(self.widget_uid(), &scope.path, SliderAction::Slide(self.to_external()));
+        }
+    }
 }
 
 
@@ -148,6 +166,11 @@ impl Widget for Slider {
                 _ => ()
             }
         };
+        if let Event::KeyDown(ke) = event {
+            if cx.has_key_focus(self.text_input.area()) {
+                self.handle_key_step(cx, ke, scope);
+            }
+        }
         match event.hits(cx, self.draw_slider.area()) {
             Hit::FingerHoverIn(_) => {
                 cx.set_cursor(MouseCursor::Arrow);
@@ -231,7 +254,12 @@ impl Widget for Slider {
             self.update_text_input()
         }
     }
-        
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Slider;
+        node.label = self.text.clone();
+        node.value = Some(self.text());
+    }
 }
 
 impl SliderRef{
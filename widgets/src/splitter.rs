@@ -185,6 +185,10 @@ impl Widget for Splitter {
         }
         DrawStep::done()
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Splitter;
+    }
 }
 
 impl Splitter {
@@ -251,6 +251,14 @@ impl WidgetNode for StackNavigation {
     fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results: &mut WidgetSet) {
         self.view.find_widgets(path, cached, results);
     }
+
+    fn area(&self) -> Area {
+        self.view.area()
+    }
+
+    fn widget_tree_children(&self, cx: &Cx, children: &mut Vec<WidgetTreeNode>) {
+        self.view.widget_tree_children(cx, children);
+    }
 }
 
 impl WidgetMatchEvent for StackNavigation {
@@ -0,0 +1,34 @@
+use crate::{makepad_draw::*, widget::*};
+use crate::makepad_derive_widget::*;
+
+live_design! {
+    SvgBase = {{Svg}} {}
+}
+
+/// Shows an svg file, with Fit sizing it takes the size the document asks for
+#[derive(Live, Widget)]
+pub struct Svg {
+    #[walk] walk: Walk,
+    #[redraw] #[live] draw_svg: DrawSvg,
+    #[live] source: LiveDependency,
+}
+
+impl LiveHook for Svg {
+    fn after_apply(&mut self, _cx: &mut Cx, _apply: &mut Apply, _index: usize, _nodes: &[LiveNode]) {
+        if self.source.as_str().len() > 0 {
+            self.draw_svg.svg_file = self.source.clone();
+        }
+    }
+}
+
+impl Widget for Svg {
+    fn draw_walk(&mut self, cx: &mut Cx2d, _scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.draw_svg.draw_walk(cx, walk);
+        DrawStep::done()
+    }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Image;
+        node.label = self.source.as_str().to_string();
+    }
+}
@@ -1,6 +1,7 @@
 use {
     crate::{
         tab_close_button::{TabCloseButtonAction, TabCloseButton},
+        widget::{WidgetRole, WidgetTreeNode},
         makepad_draw::*,
     }
 };
@@ -13,6 +14,7 @@ live_design!{
 pub struct Tab {
     #[rust] is_selected: bool,
     #[rust] is_dragging: bool,
+    #[rust] name: String,
     
     #[live] draw_bg: DrawQuad,
     #[live] draw_name: DrawText,
@@ -67,7 +69,25 @@ impl Tab {
         self.animator_toggle(cx, is_selected, animate, id!(selected.on), id!(selected.off));
     }
     
+    pub fn widget_tree_node(&self, cx: &Cx, id: LiveId) -> WidgetTreeNode {
+        let area = self.draw_bg.area();
+        let has_area = area.is_valid(cx);
+        WidgetTreeNode {
+            id: id.to_string(),
+            type_name: "Tab".to_string(),
+            role: WidgetRole::Tab,
+            label: self.name.clone(),
+            checked: Some(self.is_selected),
+            visible: has_area,
+            rect: if has_area {area.rect(cx).into()} else {Default::default()},
+            ..Default::default()
+        }
+    }
+    
     pub fn draw(&mut self, cx: &mut Cx2d, name: &str, closable:TabClosable) {
+        if self.name != name {
+            self.name = name.to_string();
+        }
         //self.bg_quad.color = self.color(self.is_selected);
         self.draw_bg.begin(cx, self.walk, self.layout);
         //self.name_text.color = self.name_color(self.is_selected);
@@ -124,6 +124,15 @@ impl Widget for TabBar{
         }
         DrawStep::done()
     }
+
+    fn describe_widget(&self, cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::TabList;
+        for id in &self.tab_order {
+            if let Some(tab) = self.tabs.get(id) {
+                node.children.push(tab.widget_tree_node(cx, *id));
+            }
+        }
+    }
 }
 
 
@@ -2,11 +2,60 @@ use {
     crate::{
         makepad_derive_widget::*,
         makepad_draw::*,
-        widget::*
+        widget::*,
+        image::ImageWidgetRefExt,
     },
 }; 
    
 live_design!{
+    import makepad_draw::shader::std::*;
+    
+    DrawFlowBlock = {{DrawFlowBlock}} {
+        fn pixel(self) -> vec4 {
+            let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+            match self.block_type {
+                FlowBlockType::Quote => {
+                    sdf.box(0., 0., self.rect_size.x, self.rect_size.y, 2.);
+                    sdf.fill(self.quote_bg_color);
+                    sdf.rect(0., 0., 4., self.rect_size.y);
+                    sdf.fill(self.quote_fg_color);
+                    return sdf.result;
+                }
+                FlowBlockType::Sep => {
+                    sdf.rect(0., self.rect_size.y * 0.5 - 0.5, self.rect_size.x, 1.);
+                    sdf.fill(self.sep_color);
+                    return sdf.result;
+                }
+                FlowBlockType::Code => {
+                    sdf.box(0., 0., self.rect_size.x, self.rect_size.y, 2.);
+                    sdf.fill(self.code_color);
+                    return sdf.result;
+                }
+                FlowBlockType::InlineCode => {
+                    sdf.box(0., 1., self.rect_size.x, self.rect_size.y - 2., 1.);
+                    sdf.fill(self.code_color);
+                    return sdf.result;
+                }
+                FlowBlockType::Underline => {
+                    sdf.rect(0., self.rect_size.y - 2., self.rect_size.x, 1.);
+                    sdf.fill(self.line_color);
+                    return sdf.result;
+                }
+                FlowBlockType::Strikethrough => {
+                    sdf.rect(0., self.rect_size.y * 0.5, self.rect_size.x, 1.);
+                    sdf.fill(self.line_color);
+                    return sdf.result;
+                }
+                FlowBlockType::TableRow => {
+                    sdf.rect(0., self.rect_size.y - 1., self.rect_size.x, 1.);
+                    sdf.fill(self.sep_color);
+                    return sdf.result;
+                }
+            }
+            return #f00
+        }
+    }
+    
     TextFlowBase = {{TextFlow}} {
         // ok so we can use one drawtext
         // change to italic, change bold (SDF), strikethrough
@@ -15,6 +64,41 @@ live_design!{
     }
 }
    
+#[derive(Live, LiveHook)]
+#[live_ignore]
+#[repr(u32)]
+pub enum FlowBlockType {
+    #[pick] Quote = shader_enum(1),
+    Sep = shader_enum(2),
+    Code = shader_enum(3),
+    InlineCode = shader_enum(4),
+    Underline = shader_enum(5),
+    Strikethrough = shader_enum(6),
+    TableRow = shader_enum(7),
+}
+
+#[derive(Live, LiveHook, LiveRegister)]
+#[repr(C)]
+pub struct DrawFlowBlock {
+    #[deref] draw_super: DrawQuad,
+    #[live] line_color: Vec4,
+    #[live] sep_color: Vec4,
+    #[live] code_color: Vec4,
+    #[live] quote_bg_color: Vec4,
+    #[live] quote_fg_color: Vec4,
+    #[live] block_type: FlowBlockType
+}
+
+#[derive(Clone, Debug, DefaultNone)]
+pub enum TextFlowAction {
+    LinkClicked(String),
+    None
+}
+
+struct TableState {
+    cell_width: f64,
+}
+
 // this widget has a retained and an immediate mode api
 #[derive(Live, Widget)]
 pub struct TextFlow {
@@ -22,16 +106,42 @@ pub struct TextFlow {
     #[live] draw_italic: DrawText,
     #[live] draw_bold: DrawText,
     #[live] draw_bold_italic: DrawText,
+    #[live] draw_fixed: DrawText,
+    #[live] draw_block: DrawFlowBlock,
+    #[live] link_color: Vec4,
     #[live] font_size: f64,
     #[walk] walk: Walk,
     #[rust] bold_counter: usize,
     #[rust] italic_counter: usize,
+    #[rust] fixed_counter: usize,
+    #[rust] underline_counter: usize,
+    #[rust] strikethrough_counter: usize,
+    #[rust] inline_code_counter: usize,
     #[rust] font_size_stack: FontSizeStack,
     #[layout] layout: Layout,
+    
+    #[live] paragraph_spacing: f64,
+    #[live] quote_layout: Layout,
+    #[live] quote_walk: Walk,
+    #[live] code_layout: Layout,
+    #[live] code_walk: Walk,
+    #[live] sep_walk: Walk,
+    #[live] list_item_layout: Layout,
+    #[live] list_item_walk: Walk,
+    #[live] table_cell_layout: Layout,
+    
     #[redraw] #[rust] area:Area,
     #[rust] draw_state: DrawStateWrap<DrawState>,
     #[rust] items: ComponentMap<(u64,LiveId), WidgetRef>,
     #[rust] templates: ComponentMap<LiveId, LivePtr>,
+    #[rust] block_areas: Vec<Area>,
+    #[rust] tables: Vec<TableState>,
+    #[rust] origin: DVec2,
+    // links are hit tested against the word rects they were drawn in, relative to our origin
+    #[rust] link_stack: Vec<usize>,
+    #[rust] link_hrefs: Vec<String>,
+    #[rust] link_rects: Vec<(Rect, usize)>,
+    #[rust] pressed_link: Option<usize>,
 }
 
 impl LiveHook for TextFlow{
@@ -135,6 +245,36 @@ impl Widget for TextFlow {
             });
             entry.handle_event(cx, event, scope);
         }
+        if self.link_rects.is_empty(){
+            return
+        }
+        // only fingers over a link are ours, everything else goes to whatever is under the text
+        let origin = self.area.rect(cx).pos;
+        let link_rects = &self.link_rects;
+        let link_at = |abs: DVec2| link_rects.iter().find(|(rect, _)| rect.contains(abs - origin)).map(|(_, link)| *link);
+        match event.hits_with_test(cx, self.area, |abs, rect, _| rect.contains(abs) && link_at(abs).is_some()) {
+            Hit::FingerHoverIn(_) | Hit::FingerHoverOver(_) => {
+                cx.set_cursor(MouseCursor::Hand);
+            }
+            Hit::FingerDown(fe) => {
+                self.pressed_link = link_at(fe.abs);
+            }
+            Hit::FingerUp(fe) => {
+                if let Some(link) = self.pressed_link.take() {
+                    if fe.is_over && link_at(fe.abs) == Some(link) {
+                        if let Some(href) = self.link_hrefs.get(link) {
+                            cx.widget_action(self.widget_uid(), &scope.path, TextFlowAction::LinkClicked(href.clone()));
+                        }
+                    }
+                }
+            }
+            _ => ()
+        }
+    }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Document;
+        node.label = self.text();
     }
 }
 
@@ -144,6 +284,10 @@ impl TextFlow{
         // well know if we have a known width to wrap
         // if we dont we just dont wrap
         cx.begin_turtle(walk, self.layout);
+        self.origin = cx.turtle().origin();
+        self.link_stack.clear();
+        self.link_hrefs.clear();
+        self.link_rects.clear();
         self.draw_state.set(DrawState::Drawing);
     }
     
@@ -153,6 +297,133 @@ impl TextFlow{
         self.items.retain_visible();
     }
     
+    // a walk that fills what is left of the current line, RightWrap turtles can't fill by themselves
+    fn fill_walk(cx: &Cx2d, walk: Walk) -> Walk {
+        if !walk.width.is_fill() {
+            return walk
+        }
+        let turtle = cx.turtle();
+        let width = turtle.rect().size.x - turtle.layout().padding.right - turtle.rel_pos().x - walk.margin.width();
+        Walk {
+            width: if width.is_nan() {Size::Fit} else {Size::Fixed(width.max(0.0))},
+            ..walk
+        }
+    }
+    
+    pub fn new_line(&mut self, cx: &mut Cx2d){
+        cx.turtle_new_line();
+    }
+    
+    // starts a new line with at least paragraph_spacing between it and the previous one
+    pub fn new_paragraph(&mut self, cx: &mut Cx2d){
+        cx.turtle_new_line_with_spacing(self.paragraph_spacing);
+    }
+    
+    // a full width block for other widgets, like a code_block template
+    pub fn begin_block(&mut self, cx: &mut Cx2d){
+        cx.turtle_new_line();
+        cx.begin_turtle(Self::fill_walk(cx, Walk::fill_fit()), Layout::flow_down());
+    }
+    
+    pub fn end_block(&mut self, cx: &mut Cx2d){
+        cx.end_turtle();
+        cx.turtle_new_line();
+    }
+    
+    fn begin_styled_block(&mut self, cx: &mut Cx2d, block_type: FlowBlockType, walk: Walk, layout: Layout){
+        cx.turtle_new_line();
+        self.draw_block.block_type = block_type;
+        self.draw_block.begin(cx, Self::fill_walk(cx, walk), layout);
+        self.block_areas.push(self.draw_block.draw_vars.area);
+    }
+    
+    fn end_styled_block(&mut self, cx: &mut Cx2d){
+        if let Some(area) = self.block_areas.pop() {
+            self.draw_block.draw_vars.area = area;
+            self.draw_block.end(cx);
+        }
+        cx.turtle_new_line();
+    }
+    
+    pub fn begin_quote(&mut self, cx: &mut Cx2d){
+        self.begin_styled_block(cx, FlowBlockType::Quote, self.quote_walk, self.quote_layout);
+    }
+    
+    pub fn end_quote(&mut self, cx: &mut Cx2d){
+        self.end_styled_block(cx);
+    }
+    
+    pub fn begin_code(&mut self, cx: &mut Cx2d){
+        self.begin_styled_block(cx, FlowBlockType::Code, self.code_walk, self.code_layout);
+        self.push_fixed();
+    }
+    
+    pub fn end_code(&mut self, cx: &mut Cx2d){
+        self.pop_fixed();
+        self.end_styled_block(cx);
+    }
+    
+    pub fn sep(&mut self, cx: &mut Cx2d){
+        cx.turtle_new_line();
+        self.draw_block.block_type = FlowBlockType::Sep;
+        self.draw_block.draw_walk(cx, Self::fill_walk(cx, self.sep_walk));
+        cx.turtle_new_line();
+    }
+    
+    // a list item draws its marker in a column of marker_width, the content wraps next to it
+    pub fn begin_list_item(&mut self, cx: &mut Cx2d, marker: &str, marker_width: f64){
+        cx.turtle_new_line();
+        cx.begin_turtle(Self::fill_walk(cx, self.list_item_walk), self.list_item_layout);
+        cx.begin_turtle(Walk::size(Size::Fixed(marker_width), Size::Fit), Layout::default());
+        self.draw_text(cx, marker);
+        cx.end_turtle();
+        cx.begin_turtle(Self::fill_walk(cx, Walk::fill_fit()), self.layout.with_padding(Padding::default()));
+    }
+    
+    pub fn end_list_item(&mut self, cx: &mut Cx2d){
+        cx.end_turtle();
+        cx.end_turtle();
+        cx.turtle_new_line();
+    }
+    
+    // tables get equal width columns, rows are separated by a line
+    pub fn begin_table(&mut self, cx: &mut Cx2d, columns: usize){
+        cx.turtle_new_line();
+        let walk = Self::fill_walk(cx, Walk::fill_fit());
+        let cell_width = walk.width.fixed_or_nan() / columns.max(1) as f64;
+        cx.begin_turtle(walk, Layout::flow_down());
+        self.tables.push(TableState {cell_width});
+    }
+    
+    pub fn end_table(&mut self, cx: &mut Cx2d){
+        self.tables.pop();
+        cx.end_turtle();
+        cx.turtle_new_line();
+    }
+    
+    pub fn begin_table_row(&mut self, cx: &mut Cx2d){
+        self.draw_block.block_type = FlowBlockType::TableRow;
+        self.draw_block.begin(cx, Walk::fill_fit(), Layout::flow_right());
+        self.block_areas.push(self.draw_block.draw_vars.area);
+    }
+    
+    pub fn end_table_row(&mut self, cx: &mut Cx2d){
+        if let Some(area) = self.block_areas.pop() {
+            self.draw_block.draw_vars.area = area;
+            self.draw_block.end(cx);
+        }
+    }
+    
+    pub fn begin_table_cell(&mut self, cx: &mut Cx2d){
+        let width = self.tables.last().map(|t| t.cell_width).unwrap_or(f64::NAN);
+        let width = if width.is_nan() {Size::Fit} else {Size::Fixed(width)};
+        cx.begin_turtle(Walk::size(width, Size::Fit), self.table_cell_layout);
+    }
+    
+    pub fn end_table_cell(&mut self, cx: &mut Cx2d){
+        cx.end_turtle();
+    }
+    
     pub fn push_bold(&mut self){
         self.bold_counter += 1;
     }
@@ -173,6 +444,58 @@ impl TextFlow{
         }
     }
     
+    pub fn push_fixed(&mut self){
+        self.fixed_counter += 1;
+    }
+    
+    pub fn pop_fixed(&mut self){
+        if self.fixed_counter>0{
+            self.fixed_counter -= 1;
+        }
+    }
+    
+    pub fn push_underline(&mut self){
+        self.underline_counter += 1;
+    }
+    
+    pub fn pop_underline(&mut self){
+        if self.underline_counter>0{
+            self.underline_counter -= 1;
+        }
+    }
+    
+    pub fn push_strikethrough(&mut self){
+        self.strikethrough_counter += 1;
+    }
+    
+    pub fn pop_strikethrough(&mut self){
+        if self.strikethrough_counter>0{
+            self.strikethrough_counter -= 1;
+        }
+    }
+    
+    pub fn push_inline_code(&mut self){
+        self.inline_code_counter += 1;
+        self.fixed_counter += 1;
+    }
+    
+    pub fn pop_inline_code(&mut self){
+        if self.inline_code_counter>0{
+            self.inline_code_counter -= 1;
+            self.pop_fixed();
+        }
+    }
+    
+    // text drawn until pop_link is underlined and clicking it emits TextFlowAction::LinkClicked
+    pub fn push_link(&mut self, href: &str){
+        self.link_stack.push(self.link_hrefs.len());
+        self.link_hrefs.push(href.to_string());
+    }
+    
+    pub fn pop_link(&mut self){
+        self.link_stack.pop();
+    }
+    
     pub fn push_size(&mut self, size: f64){
         self.font_size_stack.push(size);
     }
@@ -189,18 +512,53 @@ impl TextFlow{
     
     
     pub fn item(&mut self, cx: &mut Cx, entry_id: u64, template: LiveId) -> Option<WidgetRef> {
+        self.item_with(cx, entry_id, template, |_,_|{})
+    }
+    
+    // same as item, calls init once when the item is created
+    pub fn item_with<F>(&mut self, cx: &mut Cx, entry_id: u64, template: LiveId, init: F) -> Option<WidgetRef> where F: FnOnce(&mut Cx, &WidgetRef) {
         if let Some(ptr) = self.templates.get(&template) {
             let entry = self.items.get_or_insert(cx, (entry_id, template), | cx | {
-                WidgetRef::new_from_ptr(cx, Some(*ptr))
+                let entry = WidgetRef::new_from_ptr(cx, Some(*ptr));
+                init(cx, &entry);
+                entry
             });
             return Some(entry.clone())
         }
         None
     }
     
+    // the items that were created from a template
+    pub fn items_with_template(&self, template: LiveId) -> impl Iterator<Item = &WidgetRef> {
+        self.items.iter().filter(move |((_, id), _)| *id == template).map(|(_, item)| item)
+    }
+    
+    // an item from the image template with src loading into it, crate://, http(s):// and filesystem paths are supported.
+    // returns None when there is no image template, so the caller can draw alt text
+    pub fn image_item<F>(&mut self, cx: &mut Cx, entry_id: u64, src: &str, init: F) -> Option<WidgetRef> where F: FnOnce(&mut Cx, &WidgetRef) {
+        let id = LiveId::from_str_num(src, entry_id).0;
+        self.item_with(cx, id, live_id!(image), |cx, item|{
+            let image = item.image(id!(image));
+            let image = if image.is_empty() {item.as_image()} else {image};
+            if src.starts_with("crate://"){
+                image.load_image_dep_by_path_async(cx, src)
+            }
+            else if src.starts_with("http://") || src.starts_with("https://"){
+                image.load_image_url(cx, src)
+            }
+            else{
+                image.load_image_file_by_path_async(cx, src.strip_prefix("file://").unwrap_or(src))
+            }
+            init(cx, item);
+        })
+    }
+    
     pub fn draw_text(&mut self, cx:&mut Cx2d, text:&str){
         if let Some(DrawState::Drawing) = self.draw_state.get(){
-            let dt = if self.bold_counter > 0{
+            let dt = if self.fixed_counter > 0{
+                &mut self.draw_fixed
+            }
+            else if self.bold_counter > 0{
                 if self.italic_counter > 0{
                     &mut self.draw_bold_italic
                 }
@@ -219,7 +577,39 @@ impl TextFlow{
             let fs = self.font_size_stack.value(self.font_size);
             dt.text_style.font_size = fs;
             // the turtle is at pos X so we walk it.
-            dt.draw_walk_word(cx, text);
+            let link = self.link_stack.last().cloned();
+            if link.is_none() && self.underline_counter == 0 && self.strikethrough_counter == 0 && self.inline_code_counter == 0{
+                dt.draw_walk_word(cx, text);
+                return
+            }
+            let color = dt.color;
+            if link.is_some(){
+                dt.color = self.link_color;
+            }
+            let underline = link.is_some() || self.underline_counter > 0;
+            let strikethrough = self.strikethrough_counter > 0;
+            let inline_code = self.inline_code_counter > 0;
+            let draw_block = &mut self.draw_block;
+            let link_rects = &mut self.link_rects;
+            let origin = self.origin;
+            dt.draw_walk_word_with(cx, text, |cx, rect|{
+                if inline_code{
+                    draw_block.block_type = FlowBlockType::InlineCode;
+                    draw_block.draw_abs(cx, rect);
+                }
+                if underline{
+                    draw_block.block_type = FlowBlockType::Underline;
+                    draw_block.draw_abs(cx, rect);
+                }
+                if strikethrough{
+                    draw_block.block_type = FlowBlockType::Strikethrough;
+                    draw_block.draw_abs(cx, rect);
+                }
+                if let Some(link) = link{
+                    link_rects.push((Rect{pos: rect.pos - origin, size: rect.size}, link));
+                }
+            });
+            dt.color = color;
         }
     }
 }
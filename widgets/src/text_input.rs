@@ -44,6 +44,7 @@ pub struct TextInput {
     #[redraw] #[live] draw_bg: DrawColor,
     #[live] draw_select: DrawQuad,
     #[live] draw_cursor: DrawQuad,
+    #[live] draw_composition: DrawQuad,
     #[live] draw_text: DrawLabel,
     
     #[walk] walk: Walk,
@@ -60,6 +61,7 @@ pub struct TextInput {
     #[live] secret: bool,
     #[live] on_focus_select_all: bool,
     #[live] pub read_only: bool,
+    #[live] pub tab_index: i64,
     
     //#[live] label_walk: Walk,
     
@@ -72,7 +74,10 @@ pub struct TextInput {
     #[rust] undo_stack: Vec<UndoItem>,
     #[rust] redo_stack: Vec<UndoItem>,
     #[rust] cursor_tail: usize,
-    #[rust] cursor_head: usize
+    #[rust] cursor_head: usize,
+    // input method text that is being composed at the cursor, not yet part of the text
+    #[rust] composition: String,
+    #[rust] composition_cursor: usize,
 }
 
 impl Widget for TextInput {
@@ -84,6 +89,7 @@ impl Widget for TextInput {
         match event.hits(cx, self.draw_bg.area()) {
             Hit::KeyFocusLost(_) => {
                 self.animator_play(cx, id!(focus.off));
+                self.composition.clear();
                 cx.hide_text_ime();
                 cx.widget_action(uid, &scope.path, TextInputAction::Return(self.text.clone()));
                 cx.widget_action(uid, &scope.path, TextInputAction::KeyFocusLost);
@@ -121,6 +127,14 @@ impl Widget for TextInput {
                 }
                 if self.change(cx, &input){self.push_change_action(uid, scope, cx)}
             }
+            Hit::TextComposition(tc) => {
+                if self.read_only || self.secret {
+                    return
+                }
+                self.composition = tc.text.clone();
+                self.composition_cursor = tc.cursor;
+                self.draw_bg.redraw(cx);
+            }
             Hit::TextCopy(ce) => {
                 self.undo_id += 1;
                 *ce.response.borrow_mut() = Some(self.selected_text());
@@ -350,6 +364,12 @@ impl Widget for TextInput {
     fn set_text(&mut self, v: &str) {
         self.filter_input(&v, None);
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::TextInput;
+        node.label = self.empty_message.clone();
+        node.value = Some(self.text());
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, DefaultNone)]
@@ -560,7 +580,7 @@ impl TextInput {
         // this makes sure selection goes behind the text
         self.draw_select.append_to_draw_call(cx);
         
-        if self.text.len() == 0 {
+        if self.text.len() == 0 && self.composition.is_empty() {
             self.draw_text.is_empty = 1.0;
             self.draw_text.draw_walk(cx, Walk::size(self.walk.width, self.walk.height), self.label_align, &self.empty_message);
         }
@@ -572,6 +592,15 @@ impl TextInput {
                     self.walk.height
                 ), self.label_align, &"*".repeat(self.text.len()));
             }
+            else if !self.composition.is_empty() {
+                let at = self.text.char_indices().nth(self.cursor_head).map(|(i, _)| i).unwrap_or(self.text.len());
+                let mut text = self.text.clone();
+                text.insert_str(at, &self.composition);
+                self.draw_text.draw_walk(cx, Walk::size(
+                    self.walk.width,
+                    self.walk.height
+                ), self.label_align, &text);
+            }
             else {
                 self.draw_text.draw_walk(cx, Walk::size(
                     self.walk.width,
@@ -586,10 +615,22 @@ impl TextInput {
         // move the IME
         let line_spacing = self.draw_text.get_line_spacing();
         let top_drop = self.draw_text.get_font_size() * 0.2;
-        let head = self.draw_text.get_cursor_pos(cx, 0.0, self.cursor_head)
+        let composition_len = self.composition.chars().count();
+        let head = self.draw_text.get_cursor_pos(cx, 0.0, self.cursor_head + self.composition_cursor.min(composition_len))
             .unwrap_or(dvec2(turtle.pos.x, 0.0));
         
-        if !self.read_only && self.cursor_head == self.cursor_tail {
+        // underline the text the input method is still composing
+        if composition_len > 0 {
+            let rects = self.draw_text.get_selection_rects(cx, self.cursor_head, self.cursor_head + composition_len, dvec2(0.0, -top_drop), dvec2(0.0, 0.0));
+            for rect in rects {
+                self.draw_composition.draw_abs(cx, Rect {
+                    pos: dvec2(rect.pos.x, rect.pos.y + line_spacing - 1.0),
+                    size: dvec2(rect.size.x, 1.0)
+                });
+            }
+        }
+        
+        if !self.read_only && (self.cursor_head == self.cursor_tail || composition_len > 0) {
             self.draw_cursor.draw_abs(cx, Rect {
                 pos: dvec2(head.x - 0.5 * self.cursor_size, head.y - top_drop),
                 size: dvec2(self.cursor_size, line_spacing)
@@ -598,7 +639,7 @@ impl TextInput {
         
         // draw selection rects
         
-        if self.cursor_head != self.cursor_tail {
+        if self.cursor_head != self.cursor_tail && composition_len == 0 {
             let top_drop = self.draw_text.get_font_size() * 0.3;
             let bottom_drop = self.draw_text.get_font_size() * 0.1;
             
@@ -624,7 +665,7 @@ impl TextInput {
             }
         }
         
-        cx.add_nav_stop(self.draw_bg.area(), NavRole::TextInput, Margin::default())
+        cx.add_nav_stop_with_tab_index(self.draw_bg.area(), NavRole::TextInput, Margin::default(), self.tab_index)
     }
 }
 
@@ -636,4 +677,26 @@ impl TextInputRef {
         None
     }
     
+    pub fn returned(&self, actions: &Actions) -> Option<String> {
+        if let TextInputAction::Return(val) = actions.find_widget_action_cast(self.widget_uid()) {
+            return Some(val);
+        }
+        None
+    }
+    
+    pub fn escaped(&self, actions: &Actions) -> bool {
+        matches!(actions.find_widget_action_cast(self.widget_uid()), TextInputAction::Escape)
+    }
+    
+    pub fn set_key_focus(&self, cx: &mut Cx) {
+        if let Some(inner) = self.borrow() {
+            inner.set_key_focus(cx);
+        }
+    }
+    
+    pub fn select_all(&self) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.select_all();
+        }
+    }
 }
@@ -167,6 +167,42 @@ live_design! {
         draw_italic: {text_style:<THEME_FONT_ITALIC>{}}
         draw_bold: {text_style:<THEME_FONT_BOLD>{}}
         draw_bold_italic: {text_style:<THEME_FONT_BOLD_ITALIC>{}}
+        draw_fixed: {text_style:<THEME_FONT_CODE>{}}
+        link_color: #x6CB4EE
+        draw_block: {
+            line_color: (THEME_COLOR_TEXT_DEFAULT)
+            sep_color: #fff3
+            code_color: #fff1
+            quote_bg_color: #fff1
+            quote_fg_color: #fff4
+        }
+    }
+
+    CodeBlock = <CodeBlockBase> {
+        width: Fill, height: Fit
+        flow: RightWrap
+        padding: 8
+        draw_bg: {color: #fff1}
+        draw_text: {text_style: <THEME_FONT_CODE> {}, color: (THEME_COLOR_TEXT_DEFAULT)}
+    }
+
+    Markdown = <MarkdownBase>{
+        width: Fill, height: Fit
+        font_size: (THEME_FONT_SIZE_P)
+        draw_normal: {text_style:<THEME_FONT_LABEL>{}, color: (THEME_COLOR_TEXT_DEFAULT)}
+        draw_italic: {text_style:<THEME_FONT_ITALIC>{}, color: (THEME_COLOR_TEXT_DEFAULT)}
+        draw_bold: {text_style:<THEME_FONT_BOLD>{}, color: (THEME_COLOR_TEXT_DEFAULT)}
+        draw_bold_italic: {text_style:<THEME_FONT_BOLD_ITALIC>{}, color: (THEME_COLOR_TEXT_DEFAULT)}
+        draw_fixed: {text_style:<THEME_FONT_CODE>{}, color: (THEME_COLOR_TEXT_DEFAULT)}
+        link_color: #x6CB4EE
+        draw_block: {
+            line_color: (THEME_COLOR_TEXT_DEFAULT)
+            sep_color: #fff3
+            code_color: #fff1
+            quote_bg_color: #fff1
+            quote_fg_color: #fff4
+        }
+        code_block = <CodeBlock> {}
     }
 
     ScrollBar = <ScrollBarBase> {
@@ -687,9 +723,20 @@ live_design! {
     }
 
     NavControl = <NavControlBase> {
+        focus_margin: 2.0
         draw_focus: {
+            uniform border_radius: 3.0
+            uniform border_width: 1.5
             fn pixel(self) -> vec4 {
-                return #000f
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(
+                    self.border_width,
+                    self.border_width,
+                    self.rect_size.x - self.border_width * 2.0,
+                    self.rect_size.y - self.border_width * 2.0,
+                    self.border_radius
+                );
+                return sdf.stroke(THEME_COLOR_UP_50, self.border_width);
             }
         }
         draw_text: {
@@ -2019,6 +2066,12 @@ live_design! {
             }
         }
 
+        draw_composition: {
+            fn pixel(self) -> vec4 {
+                return #xFFFFFFCC
+            }
+        }
+
         cursor_margin_bottom: 3.0,
         cursor_margin_top: 4.0,
         select_pad_edges: 3.0
@@ -2110,6 +2163,40 @@ live_design! {
         }
     }
 
+    FallbackFileDialog = <FallbackFileDialogBase> {
+        width: Fill, height: Fill
+        align: {x: 0.5, y: 0.5}
+        show_bg: true
+        draw_bg: {color: (THEME_COLOR_DOWN_50)}
+
+        panel = <RoundedView> {
+            width: 520, height: 420
+            flow: Down
+            padding: 10., spacing: 6.
+            draw_bg: {color: (THEME_COLOR_BG_APP), radius: 4.0}
+
+            title = <Label> {text: "Open File", draw_text: {text_style: <THEME_FONT_BOLD> {}}}
+            header = <View> {
+                width: Fill, height: Fit
+                spacing: 6., align: {y: 0.5}
+                up = <Button> {text: "Up"}
+            }
+            file_tree = <FileTree> {width: Fill, height: Fill}
+            filename_row = <View> {
+                width: Fill, height: Fit
+                spacing: 6., align: {y: 0.5}
+                <Label> {text: "Name"}
+                filename = <TextInput> {width: Fill, text: ""}
+            }
+            buttons = <View> {
+                width: Fill, height: Fit
+                spacing: 6., align: {x: 1.0}
+                cancel = <Button> {text: "Cancel"}
+                confirm = <Button> {text: "Open"}
+            }
+        }
+    }
+
     Slider = <SliderBase> {
         min: 0.0,
         max: 1.0,
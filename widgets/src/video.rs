@@ -364,6 +364,10 @@ impl Widget for Video {
         self.handle_activity_events(cx, event);
         self.handle_errors(event);
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Video;
+    }
 }
 
 impl ImageCacheImpl for Video {
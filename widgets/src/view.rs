@@ -514,6 +514,18 @@ impl WidgetNode for View {
         }
     }
 
+    fn area(&self) -> Area {
+        self.area
+    }
+
+    fn widget_tree_children(&self, cx: &Cx, children: &mut Vec<WidgetTreeNode>) {
+        for id in &self.draw_order {
+            if let Some(node) = self.children.get(id).and_then( | child | child.widget_tree_node(cx, *id)) {
+                children.push(node);
+            }
+        }
+    }
+
     fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results: &mut WidgetSet) {
         match cached {
             WidgetCache::Yes | WidgetCache::Clear => {
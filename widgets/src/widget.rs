@@ -8,6 +8,7 @@ use {
     std::fmt
 };
 pub use crate::register_widget;
+pub use crate::widget_tree::{WidgetRole, WidgetTreeNode};
 
 #[derive(Clone, Copy)]
 pub enum WidgetCache {
@@ -26,6 +27,10 @@ pub trait WidgetNode: LiveApply{
     fn find_widgets(&mut self, _path: &[LiveId], _cached: WidgetCache, _results: &mut WidgetSet);
     fn walk(&mut self, _cx:&mut Cx) -> Walk;
     fn redraw(&mut self, _cx: &mut Cx);
+    /// The area the widget drew last, its rect is what the widget tree reports
+    fn area(&self) -> Area {Area::Empty}
+    /// Adds a widget tree node for every child widget, in draw order
+    fn widget_tree_children(&self, _cx: &Cx, _children: &mut Vec<WidgetTreeNode>) {}
 }
 
 pub trait Widget: WidgetNode {
@@ -78,6 +83,13 @@ pub trait Widget: WidgetNode {
     fn set_text(&mut self, _v: &str) {
     }
     
+    /// Fills in the role, label and state of this widget for the widget tree.
+    /// The id, type, rect and focus are already set, widgets that manage their own
+    /// items (like lists) can push those as children here
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.label = self.text();
+    }
+    
     fn set_text_and_redraw(&mut self, cx: &mut Cx, v: &str) {
         self.set_text(v);
         self.redraw(cx);
@@ -470,6 +482,24 @@ impl WidgetRef {
         true
     }
     
+    pub fn area(&self) -> Area {
+        if let Some(inner) = self.0.borrow().as_ref() {
+            return inner.widget.area()
+        }
+        Area::Empty
+    }
+    
+    /// Snapshots the widget tree starting at this widget. Widgets that are borrowed at the
+    /// time (for instance because the call comes from their own event handler) are left out.
+    pub fn widget_tree(&self, cx: &Cx) -> WidgetTreeNode {
+        self.widget_tree_node(cx, LiveId(0)).unwrap_or_default()
+    }
+    
+    pub fn widget_tree_node(&self, cx: &Cx, id: LiveId) -> Option<WidgetTreeNode> {
+        let inner = self.0.try_borrow().ok()?;
+        Some(WidgetTreeNode::from_widget(cx, id, &*inner.as_ref()?.widget))
+    }
+    
     pub fn draw_all(&self, cx: &mut Cx2d, scope:&mut Scope) {
         if let Some(inner) = self.0.borrow_mut().as_mut() {
             return inner.widget.draw_all(cx, scope)
@@ -0,0 +1,272 @@
+use {
+    crate::{
+        makepad_draw::*,
+        makepad_platform::makepad_micro_serde::*,
+        widget::*,
+    }
+};
+
+/// What a widget is to a user, in the spirit of the platform accessibility roles
+#[derive(Clone, Copy, Debug, PartialEq, SerJson, DeJson)]
+pub enum WidgetRole {
+    Generic,
+    Window,
+    Group,
+    Button,
+    CheckBox,
+    RadioButton,
+    Label,
+    Link,
+    TextInput,
+    Slider,
+    Image,
+    List,
+    ListItem,
+    Tab,
+    TabList,
+    ComboBox,
+    Menu,
+    MenuItem,
+    Tree,
+    TreeItem,
+    Splitter,
+    ScrollBar,
+    Document,
+    Video,
+}
+
+impl Default for WidgetRole {
+    fn default() -> Self {Self::Generic}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, SerJson, DeJson)]
+pub struct WidgetTreeRect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl From<Rect> for WidgetTreeRect {
+    fn from(r: Rect) -> Self {
+        Self {x: r.pos.x, y: r.pos.y, w: r.size.x, h: r.size.y}
+    }
+}
+
+impl WidgetTreeRect {
+    pub fn center(&self) -> DVec2 {
+        dvec2(self.x + self.w * 0.5, self.y + self.h * 0.5)
+    }
+
+    pub fn contains(&self, p: DVec2) -> bool {
+        p.x >= self.x && p.y >= self.y && p.x < self.x + self.w && p.y < self.y + self.h
+    }
+}
+
+/// A snapshot of one widget and its children, made with `WidgetRef::widget_tree`.
+/// The rect is in window coordinates of the last draw.
+#[derive(Clone, Debug, Default, SerJson, DeJson)]
+pub struct WidgetTreeNode {
+    /// The id of the widget in its parent, empty for the root
+    pub id: String,
+    pub uid: u64,
+    /// The name the widget type is registered with in the DSL, like `Button`
+    pub type_name: String,
+    pub role: WidgetRole,
+    pub label: String,
+    pub value: Option<String>,
+    pub checked: Option<bool>,
+    pub focused: bool,
+    pub visible: bool,
+    pub rect: WidgetTreeRect,
+    pub children: Vec<WidgetTreeNode>,
+}
+
+impl WidgetTreeNode {
+    /// Describes a widget and, recursively, its children. Most code wants
+    /// `WidgetRef::widget_tree` instead, this is for widgets that own their children directly.
+    pub fn from_widget(cx: &Cx, id: LiveId, widget: &(dyn Widget + 'static)) -> Self {
+        let area = widget.area();
+        let type_name = cx.live_registry.borrow().components.get::<WidgetRegistry>()
+            .map.get(&widget.ref_cast_type_id())
+            .map( | (info, _) | info.name.to_string())
+            .unwrap_or_default();
+        let has_area = area.is_valid(cx);
+        let mut node = WidgetTreeNode {
+            id: if id == LiveId(0) {String::new()} else {id.to_string()},
+            uid: widget.widget_uid().0,
+            type_name,
+            focused: has_area && cx.has_key_focus(area),
+            visible: widget.is_visible() && has_area,
+            rect: if has_area {area.rect(cx).into()} else {Default::default()},
+            ..Default::default()
+        };
+        widget.describe_widget(cx, &mut node);
+        widget.widget_tree_children(cx, &mut node.children);
+        node
+    }
+
+    pub fn to_json(&self) -> String {
+        self.serialize_json()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, DeJsonErr> {
+        Self::deserialize_json(json)
+    }
+
+    /// Depth first iteration over this node and all of its descendants
+    pub fn iter(&self) -> WidgetTreeIter<'_> {
+        WidgetTreeIter {stack: vec![self]}
+    }
+
+    pub fn find(&self, f: impl Fn(&WidgetTreeNode) -> bool) -> Option<&WidgetTreeNode> {
+        self.iter().find( | node | f(node))
+    }
+
+    pub fn find_all(&self, f: impl Fn(&WidgetTreeNode) -> bool) -> Vec<&WidgetTreeNode> {
+        self.iter().filter( | node | f(node)).collect()
+    }
+
+    /// Finds a visible widget by role and label, like the button labeled Save
+    pub fn find_labeled(&self, role: WidgetRole, label: &str) -> Option<&WidgetTreeNode> {
+        self.find( | node | node.visible && node.role == role && node.label == label)
+    }
+
+    /// Follows a path of ids, the same path `WidgetRef::widget` would search for.
+    /// When several widgets share an id the first one the rest of the path resolves in wins.
+    pub fn find_path(&self, path: &[LiveId]) -> Option<&WidgetTreeNode> {
+        let (first, rest) = path.split_first()?;
+        self.iter().skip(1)
+            .filter( | node | LiveId::from_str(&node.id) == *first)
+            .find_map( | node | if rest.is_empty() {Some(node)} else {node.find_path(rest)})
+    }
+
+    /// The deepest visible widget under a point, what a click there would most likely hit
+    pub fn hit_test(&self, p: DVec2) -> Option<&WidgetTreeNode> {
+        if !self.visible || !self.rect.contains(p) {
+            return None
+        }
+        for child in self.children.iter().rev() {
+            if let Some(node) = child.hit_test(p) {
+                return Some(node)
+            }
+        }
+        Some(self)
+    }
+}
+
+pub struct WidgetTreeIter<'a> {
+    stack: Vec<&'a WidgetTreeNode>,
+}
+
+impl<'a> Iterator for WidgetTreeIter<'a> {
+    type Item = &'a WidgetTreeNode;
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev());
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, role: WidgetRole, label: &str, rect: (f64, f64, f64, f64), children: Vec<WidgetTreeNode>) -> WidgetTreeNode {
+        WidgetTreeNode {
+            id: id.to_string(),
+            role,
+            label: label.to_string(),
+            visible: true,
+            rect: WidgetTreeRect {x: rect.0, y: rect.1, w: rect.2, h: rect.3},
+            children,
+            ..Default::default()
+        }
+    }
+
+    fn tree() -> WidgetTreeNode {
+        let mut hidden = node("save", WidgetRole::Button, "Save", (0.0, 0.0, 10.0, 10.0), vec![]);
+        hidden.visible = false;
+        node("", WidgetRole::Window, "", (0.0, 0.0, 200.0, 100.0), vec![
+            node("toolbar", WidgetRole::Group, "", (0.0, 0.0, 200.0, 20.0), vec![
+                hidden,
+                node("save", WidgetRole::Button, "Save", (10.0, 2.0, 40.0, 16.0), vec![
+                    node("icon", WidgetRole::Image, "", (12.0, 4.0, 12.0, 12.0), vec![]),
+                ]),
+                node("open", WidgetRole::Button, "Open", (60.0, 2.0, 40.0, 16.0), vec![]),
+            ]),
+            node("body", WidgetRole::Group, "", (0.0, 20.0, 200.0, 80.0), vec![
+                node("name", WidgetRole::TextInput, "", (10.0, 30.0, 100.0, 20.0), vec![]),
+                // drawn after the input, so it sits on top where they overlap
+                node("popup", WidgetRole::Menu, "", (50.0, 40.0, 60.0, 40.0), vec![]),
+            ]),
+        ])
+    }
+
+    #[test]
+    fn iterates_depth_first() {
+        let tree = tree();
+        let ids: Vec<_> = tree.iter().map( | node | node.id.as_str()).collect();
+        assert_eq!(ids, vec!["", "toolbar", "save", "save", "icon", "open", "body", "name", "popup"]);
+        assert_eq!(tree.find_all( | node | node.role == WidgetRole::Button).len(), 3);
+    }
+
+    #[test]
+    fn finds_paths() {
+        let tree = tree();
+        let icon = tree.find_path(&[live_id!(toolbar), live_id!(save), live_id!(icon)]).unwrap();
+        assert_eq!(icon.role, WidgetRole::Image);
+        // ids don't have to be direct children, like `WidgetRef::widget`
+        assert_eq!(tree.find_path(&[live_id!(popup)]).unwrap().role, WidgetRole::Menu);
+        assert_eq!(tree.find_path(&[live_id!(body), live_id!(name)]).unwrap().role, WidgetRole::TextInput);
+        assert!(tree.find_path(&[live_id!(body), live_id!(save)]).is_none());
+        assert!(tree.find_path(&[live_id!(missing)]).is_none());
+        assert!(tree.find_path(&[]).is_none());
+    }
+
+    #[test]
+    fn finds_visible_widgets_by_label() {
+        let tree = tree();
+        let save = tree.find_labeled(WidgetRole::Button, "Save").unwrap();
+        assert!(save.visible);
+        assert_eq!(save.rect.center(), dvec2(30.0, 10.0));
+        assert!(tree.find_labeled(WidgetRole::Link, "Save").is_none());
+        assert!(tree.find_labeled(WidgetRole::Button, "Close").is_none());
+    }
+
+    #[test]
+    fn hit_tests_the_topmost_deepest_widget() {
+        let tree = tree();
+        let hit = | x, y | tree.hit_test(dvec2(x, y)).map( | node | node.id.as_str());
+        assert_eq!(hit(14.0, 6.0), Some("icon"));
+        assert_eq!(hit(45.0, 6.0), Some("save"));
+        assert_eq!(hit(55.0, 6.0), Some("toolbar"));
+        assert_eq!(hit(60.0, 45.0), Some("popup"));
+        assert_eq!(hit(20.0, 45.0), Some("name"));
+        assert_eq!(hit(150.0, 90.0), Some("body"));
+        // the right and bottom edges are exclusive
+        assert_eq!(hit(200.0, 50.0), None);
+        assert_eq!(hit(-1.0, 50.0), None);
+    }
+
+    #[test]
+    fn round_trips_json() {
+        let mut tree = tree();
+        tree.uid = 42;
+        tree.type_name = "Window".to_string();
+        tree.children[1].children[0].value = Some("a \"quoted\" value".to_string());
+        tree.children[1].children[0].focused = true;
+        tree.children[0].children[1].checked = Some(false);
+
+        let json = tree.to_json();
+        let back = WidgetTreeNode::from_json(&json).unwrap();
+        assert_eq!(back.to_json(), json);
+        assert_eq!(back.uid, 42);
+        assert_eq!(back.children[1].children[0].value.as_deref(), Some("a \"quoted\" value"));
+        assert!(back.children[1].children[0].focused);
+        assert_eq!(back.children[0].children[1].checked, Some(false));
+        assert_eq!(back.children[0].children[1].rect, WidgetTreeRect {x: 10.0, y: 2.0, w: 40.0, h: 16.0});
+        assert_eq!(back.iter().count(), tree.iter().count());
+        assert!(WidgetTreeNode::from_json("{\"id\":").is_err());
+    }
+}
@@ -5,6 +5,7 @@ use crate::{
     makepad_draw::*,
     nav_control::NavControl,
     button::*,
+    image_cache::ImageCache,
     view::*,
     widget::*,
 };
@@ -135,6 +136,7 @@ impl Window {
     pub fn end(&mut self, cx: &mut Cx2d) {
         //while self.frame.draw_widget_continue(cx).is_not_done() {}
         self.debug_view.draw(cx);
+        self.nav_control.draw(cx);
         
         // lets draw our cursor
         if let OsType::LinuxDirect = cx.os_type() {
@@ -181,6 +183,10 @@ impl Widget for Window {
         let uid = self.widget_uid();
         
         self.debug_view.handle_event(cx, event);
+        // images load for widgets that are gone or not drawn too, so the cache doesn't wait on them
+        if cx.has_global::<ImageCache>() {
+            ImageCache::handle_event(cx, event);
+        }
         if self.show_performance_view {
             self.performance_view.handle_widget(cx, event);
         }
@@ -312,4 +318,8 @@ impl Widget for Window {
         
         DrawStep::done()
     }
+
+    fn describe_widget(&self, _cx: &Cx, node: &mut WidgetTreeNode) {
+        node.role = WidgetRole::Window;
+    }
 }
@@ -0,0 +1,67 @@
+//! Presses a focused Button with the keyboard, only the key up of the key that pressed it clicks
+
+use makepad_widgets::*;
+use makepad_widgets::makepad_platform::visual_test::VisualTest;
+
+live_design!{
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    App = {{App}} {
+        ui: <Window> {
+            window: {inner_size: vec2(200, 100)}
+            body = <View> {
+                button = <Button> {text: "Ok"}
+            }
+        }
+    }
+}
+
+#[derive(Live, LiveHook)]
+pub struct App {
+    #[live] ui: WidgetRef,
+    #[rust] clicks: usize,
+}
+
+impl LiveRegister for App {
+    fn live_register(cx: &mut Cx) {
+        makepad_widgets::live_design(cx);
+    }
+}
+
+impl AppMain for App {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event) {
+        let actions = cx.capture_actions(|cx| self.ui.handle_event(cx, event, &mut Scope::empty()));
+        if self.ui.button(id!(button)).clicked(&actions) {
+            self.clicks += 1;
+        }
+    }
+}
+
+fn clicks(test: &VisualTest) -> usize {
+    test.with_app(|app: &mut App| app.clicks)
+}
+
+#[test]
+fn clicks_on_the_key_up_of_the_key_that_pressed_it() {
+    let mut test = VisualTest::new::<App>(live_design);
+    let area = test.with_app(|app: &mut App| app.ui.button(id!(button)).area());
+    test.cx.borrow_mut().set_key_focus(area);
+    test.step();
+
+    // a key up without a key down, like the one of the key that moved the focus here
+    test.key_up(KeyCode::ReturnKey, KeyModifiers::default());
+    assert_eq!(clicks(&test), 0);
+
+    test.key_down(KeyCode::Space, KeyModifiers::default());
+    test.key_up(KeyCode::ReturnKey, KeyModifiers::default());
+    assert_eq!(clicks(&test), 0);
+    test.key_up(KeyCode::Space, KeyModifiers::default());
+    assert_eq!(clicks(&test), 1);
+    test.key_up(KeyCode::Space, KeyModifiers::default());
+    assert_eq!(clicks(&test), 1);
+
+    test.key_down(KeyCode::ReturnKey, KeyModifiers::default());
+    test.key_up(KeyCode::ReturnKey, KeyModifiers::default());
+    assert_eq!(clicks(&test), 2);
+}
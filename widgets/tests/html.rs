@@ -0,0 +1,121 @@
+//! Draws Html documents headless and checks where the blocks put the widgets inside of them
+
+use makepad_widgets::*;
+use makepad_widgets::html::*;
+use makepad_widgets::makepad_platform::visual_test::VisualTest;
+
+live_design!{
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    Marked = <Html> {
+        width: Fill, height: Fit
+        m0 = <SolidView> {width: 4, height: 4}
+        m1 = <SolidView> {width: 4, height: 4}
+        m2 = <SolidView> {width: 4, height: 4}
+        m3 = <SolidView> {width: 4, height: 4}
+        m4 = <SolidView> {width: 4, height: 4}
+        m5 = <SolidView> {width: 4, height: 4}
+        m6 = <SolidView> {width: 4, height: 4}
+        m7 = <SolidView> {width: 4, height: 4}
+    }
+
+    App = {{App}} {
+        ui: <Window> {
+            window: {inner_size: vec2(400, 600)}
+            body = <View> {
+                flow: Down
+                lists = <Marked> {
+                    html: "<m0/><ul><li><m1/><ul><li><m2/></li></ul></li><li><m3/><ol><li>a<li><m4/></ol></ul><m5/>"
+                }
+                quote = <Marked> {
+                    html: "<m0/><ul><li><blockquote><m1/></blockquote><m2/></li></ul><m3/>"
+                }
+                table = <Marked> {
+                    html: "<m0/><table><tr><td><m1/></td><td><m2/></td></tr><tr><td><m3/></td></tr><tr><td><m4/><td><m5/><td><m6/></table><m7/>"
+                }
+            }
+        }
+    }
+}
+
+#[derive(Live, LiveHook)]
+pub struct App {
+    #[live] ui: WidgetRef,
+}
+
+impl LiveRegister for App {
+    fn live_register(cx: &mut Cx) {
+        makepad_widgets::live_design(cx);
+    }
+}
+
+impl AppMain for App {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event) {
+        self.ui.handle_event(cx, event, &mut Scope::empty());
+    }
+}
+
+// the rects of the marker widgets m0..m7 an Html drew, relative to m0
+fn marks(test: &VisualTest, html: &[LiveId]) -> Vec<Option<Rect>> {
+    let cx = test.cx.borrow();
+    test.with_app(|app: &mut App| {
+        let html = app.ui.html(html);
+        let html = html.borrow().unwrap();
+        let rects: Vec<Option<Rect>> = (0..8).map(|i| {
+            let template = LiveId::from_str(&format!("m{}", i));
+            html.items_with_template(template).next().map(|item| item.area().rect(&cx))
+        }).collect();
+        let origin = rects[0].unwrap().pos;
+        rects.into_iter().map(|rect| rect.map(|rect| Rect {pos: rect.pos - origin, size: rect.size})).collect()
+    })
+}
+
+const LIST_ITEM_WIDTH: f64 = 16.0;
+const QUOTE_PADDING: f64 = 12.0;
+const CELL_PADDING: f64 = 4.0;
+
+#[test]
+fn nested_lists_indent_and_close() {
+    let test = VisualTest::new::<App>(live_design);
+    let m = marks(&test, id!(lists));
+    assert_eq!(m[1].unwrap().pos.x, LIST_ITEM_WIDTH);
+    assert_eq!(m[2].unwrap().pos.x, 2.0 * LIST_ITEM_WIDTH);
+    // the inner list closed, the next item is back at the first level
+    assert_eq!(m[3].unwrap().pos.x, LIST_ITEM_WIDTH);
+    // the <li> before it is closed by the next one instead of nesting
+    assert_eq!(m[4].unwrap().pos.x, 2.0 * LIST_ITEM_WIDTH);
+    assert_eq!(m[5].unwrap().pos.x, 0.0);
+    assert!(m[5].unwrap().pos.y > m[4].unwrap().pos.y);
+}
+
+#[test]
+fn quote_inside_a_list_item() {
+    let test = VisualTest::new::<App>(live_design);
+    let m = marks(&test, id!(quote));
+    assert_eq!(m[1].unwrap().pos.x, LIST_ITEM_WIDTH + QUOTE_PADDING);
+    // after the quote we are still in the list item
+    assert_eq!(m[2].unwrap().pos.x, LIST_ITEM_WIDTH);
+    assert!(m[2].unwrap().pos.y > m[1].unwrap().pos.y);
+    assert_eq!(m[3].unwrap().pos.x, 0.0);
+    assert!(m[3].unwrap().pos.y > m[2].unwrap().pos.y);
+}
+
+#[test]
+fn table_columns_come_from_the_first_row() {
+    let test = VisualTest::new::<App>(live_design);
+    let m = marks(&test, id!(table));
+    let column = m[2].unwrap().pos.x - m[1].unwrap().pos.x;
+    assert_eq!(m[1].unwrap().pos.x, CELL_PADDING);
+    assert_eq!(column, 200.0);
+    // a shorter row starts at the first column
+    assert_eq!(m[3].unwrap().pos.x, m[1].unwrap().pos.x);
+    assert!(m[3].unwrap().pos.y > m[1].unwrap().pos.y);
+    // a longer row with unclosed cells keeps the column width and runs past the table
+    assert_eq!(m[4].unwrap().pos.x, m[1].unwrap().pos.x);
+    assert_eq!(m[5].unwrap().pos.x - m[4].unwrap().pos.x, column);
+    assert_eq!(m[6].unwrap().pos.x - m[5].unwrap().pos.x, column);
+    assert!(m[4].unwrap().pos.y > m[3].unwrap().pos.y);
+    assert_eq!(m[7].unwrap().pos.x, 0.0);
+    assert!(m[7].unwrap().pos.y > m[6].unwrap().pos.y);
+}
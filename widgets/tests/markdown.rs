@@ -0,0 +1,53 @@
+//! Draws a Markdown body headless and checks the items it creates from its templates
+
+use makepad_widgets::*;
+use makepad_widgets::code_block::*;
+use makepad_widgets::markdown::*;
+use makepad_widgets::makepad_platform::visual_test::VisualTest;
+
+live_design!{
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    App = {{App}} {
+        ui: <Window> {
+            window: {inner_size: vec2(400, 300)}
+            body = <View> {
+                md = <Markdown> {
+                    body: "Some code:\n\n```rust\nfn main() {\n}\n```\n"
+                }
+            }
+        }
+    }
+}
+
+#[derive(Live, LiveHook)]
+pub struct App {
+    #[live] ui: WidgetRef,
+}
+
+impl LiveRegister for App {
+    fn live_register(cx: &mut Cx) {
+        makepad_widgets::live_design(cx);
+    }
+}
+
+impl AppMain for App {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event) {
+        self.ui.handle_event(cx, event, &mut Scope::empty());
+    }
+}
+
+#[test]
+fn fenced_code_becomes_a_code_block_with_its_language() {
+    let test = VisualTest::new::<App>(live_design);
+    test.with_app(|app: &mut App| {
+        let md = app.ui.markdown(id!(md));
+        let md = md.borrow().unwrap();
+        let blocks: Vec<WidgetRef> = md.items_with_template(live_id!(code_block)).cloned().collect();
+        assert_eq!(blocks.len(), 1);
+        let block = blocks[0].as_code_block();
+        assert_eq!(block.language(), "rust");
+        assert_eq!(block.text(), "fn main() {\n}");
+    });
+}
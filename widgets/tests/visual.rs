@@ -0,0 +1,86 @@
+//! Renders a small live_design app headless through VisualTest, run with MAKEPAD_UPDATE_GOLDEN=1 to rewrite the golden
+
+use makepad_widgets::*;
+use makepad_widgets::makepad_platform::visual_test::{GoldenTolerance, VisualTest};
+
+live_design!{
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    App = {{App}} {
+        ui: <Window> {
+            window: {inner_size: vec2(48, 32)}
+            pass: {clear_color: #203040}
+            body = <View> {
+                flow: Right
+                padding: 4
+                spacing: 4
+                <SolidView> {width: 8, height: 24, draw_bg: {color: #c04020}}
+                <RoundedView> {width: Fill, height: 16, draw_bg: {color: #40a060, radius: 3.0}}
+                <SolidView> {width: 4, height: 12, draw_bg: {color: #f0f000}}
+            }
+        }
+    }
+}
+
+#[derive(Live, LiveHook)]
+pub struct App {
+    #[live] ui: WidgetRef,
+}
+
+impl LiveRegister for App {
+    fn live_register(cx: &mut Cx) {
+        makepad_widgets::live_design(cx);
+    }
+}
+
+impl AppMain for App {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event) {
+        self.ui.handle_event(cx, event, &mut Scope::empty());
+    }
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("makepad_widgets_visual_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn draws_views_like_the_golden() {
+    let test = VisualTest::new::<App>(live_design);
+    test.assert_golden("window_views", GoldenTolerance::default());
+}
+
+#[test]
+fn updates_and_diffs_goldens() {
+    let mut test = VisualTest::new::<App>(live_design);
+    let dir = temp_dir("update");
+    test.golden_dir = dir.join("golden");
+    test.output_dir = dir.join("output");
+
+    test.update_golden = true;
+    test.compare_golden("views", GoldenTolerance::default()).unwrap();
+    assert!(test.golden_dir.join("views.png").exists());
+    assert!(!test.output_dir.join("views.actual.png").exists());
+
+    test.update_golden = false;
+    test.compare_golden("views", GoldenTolerance::default()).unwrap();
+
+    // a golden that differs in a 4x4 block only passes when the tolerance allows 16 pixels
+    let mut golden = test.capture();
+    for y in 0..4 {
+        for x in 0..4 {
+            golden.data[y * golden.width + x] ^= 0x00ffffff;
+        }
+    }
+    VisualTest::save_png(&golden, &test.golden_dir.join("views.png")).unwrap();
+    let err = test.compare_golden("views", GoldenTolerance::default()).unwrap_err();
+    assert!(err.contains("16 pixels differ"), "{}", err);
+    assert!(test.output_dir.join("views.actual.png").exists());
+    assert!(test.output_dir.join("views.diff.png").exists());
+    test.compare_golden("views", GoldenTolerance {channel: 2, max_mismatched_pixels: 16}).unwrap();
+    assert!(test.compare_golden("views", GoldenTolerance {channel: 2, max_mismatched_pixels: 15}).is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}